@@ -0,0 +1,57 @@
+use diesel::deserialize::{FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::ToSql;
+use diesel::sql_types;
+use serde::{Deserialize, Serialize};
+
+/// The kind of `graph-node` debugging data that was collected as evidence
+/// during a bisection run.
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    AsExpression,
+    FromSqlRow,
+    async_graphql::Enum,
+)]
+#[diesel(sql_type = sql_types::Integer)]
+pub enum BisectionEvidenceKind {
+    /// Entity changes produced by the subgraph deployment in the block.
+    EntityChanges,
+    /// Contents of `graph-node`'s ETH call cache for the block.
+    EthCallCache,
+    /// Contents of `graph-node`'s block cache for the block.
+    BlockCache,
+}
+
+impl ToSql<sql_types::Integer, Pg> for BisectionEvidenceKind {
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut diesel::serialize::Output<'b, '_, Pg>,
+    ) -> diesel::serialize::Result {
+        let value = match self {
+            BisectionEvidenceKind::EntityChanges => 1,
+            BisectionEvidenceKind::EthCallCache => 2,
+            BisectionEvidenceKind::BlockCache => 3,
+        };
+        <i32 as ToSql<sql_types::Integer, Pg>>::to_sql(&value, &mut out.reborrow())
+    }
+}
+
+impl FromSql<sql_types::Integer, Pg> for BisectionEvidenceKind {
+    fn from_sql(bytes: PgValue<'_>) -> diesel::deserialize::Result<Self> {
+        match i32::from_sql(bytes)? {
+            1 => Ok(BisectionEvidenceKind::EntityChanges),
+            2 => Ok(BisectionEvidenceKind::EthCallCache),
+            3 => Ok(BisectionEvidenceKind::BlockCache),
+            _ => Err(anyhow::anyhow!("invalid bisection evidence kind").into()),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use diesel::deserialize::{FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::ToSql;
+use diesel::sql_types;
+use serde::{Deserialize, Serialize};
+
+/// The status of a dispute in the `DisputeManager` contract, as reported by
+/// the network subgraph.
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    AsExpression,
+    FromSqlRow,
+    async_graphql::Enum,
+)]
+#[diesel(sql_type = sql_types::Integer)]
+pub enum DisputeStatus {
+    /// The arbitrator hasn't resolved the dispute yet.
+    Undecided,
+    /// The indexer was slashed.
+    Accepted,
+    /// The fisherman lost their deposit.
+    Rejected,
+    /// Neither side was penalized.
+    Draw,
+    /// The fisherman withdrew the dispute.
+    Cancelled,
+}
+
+impl ToSql<sql_types::Integer, Pg> for DisputeStatus {
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut diesel::serialize::Output<'b, '_, Pg>,
+    ) -> diesel::serialize::Result {
+        let value = match self {
+            DisputeStatus::Undecided => 1,
+            DisputeStatus::Accepted => 2,
+            DisputeStatus::Rejected => 3,
+            DisputeStatus::Draw => 4,
+            DisputeStatus::Cancelled => 5,
+        };
+        <i32 as ToSql<sql_types::Integer, Pg>>::to_sql(&value, &mut out.reborrow())
+    }
+}
+
+impl FromSql<sql_types::Integer, Pg> for DisputeStatus {
+    fn from_sql(bytes: PgValue<'_>) -> diesel::deserialize::Result<Self> {
+        match i32::from_sql(bytes)? {
+            1 => Ok(DisputeStatus::Undecided),
+            2 => Ok(DisputeStatus::Accepted),
+            3 => Ok(DisputeStatus::Rejected),
+            4 => Ok(DisputeStatus::Draw),
+            5 => Ok(DisputeStatus::Cancelled),
+            _ => Err(anyhow::anyhow!("invalid dispute status").into()),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use diesel::deserialize::{FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::ToSql;
+use diesel::sql_types;
+use serde::{Deserialize, Serialize};
+
+/// Where a divergence investigation request is in the job queue. Requests
+/// leave the queue once their investigation is acknowledged as over, unless
+/// they're dead-lettered.
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    AsExpression,
+    FromSqlRow,
+    async_graphql::Enum,
+)]
+#[diesel(sql_type = sql_types::Integer)]
+pub enum DivergenceInvestigationJobStatus {
+    /// Waiting for a worker, possibly to be retried after a failed attempt.
+    Pending,
+    /// A worker is investigating the divergence.
+    Running,
+    /// All attempts failed. The job is kept in the dead-letter queue for
+    /// inspection, until it's requeued.
+    DeadLettered,
+}
+
+impl ToSql<sql_types::Integer, Pg> for DivergenceInvestigationJobStatus {
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut diesel::serialize::Output<'b, '_, Pg>,
+    ) -> diesel::serialize::Result {
+        let value = match self {
+            DivergenceInvestigationJobStatus::Pending => 1,
+            DivergenceInvestigationJobStatus::Running => 2,
+            DivergenceInvestigationJobStatus::DeadLettered => 3,
+        };
+        <i32 as ToSql<sql_types::Integer, Pg>>::to_sql(&value, &mut out.reborrow())
+    }
+}
+
+impl FromSql<sql_types::Integer, Pg> for DivergenceInvestigationJobStatus {
+    fn from_sql(bytes: PgValue<'_>) -> diesel::deserialize::Result<Self> {
+        match i32::from_sql(bytes)? {
+            1 => Ok(DivergenceInvestigationJobStatus::Pending),
+            2 => Ok(DivergenceInvestigationJobStatus::Running),
+            3 => Ok(DivergenceInvestigationJobStatus::DeadLettered),
+            _ => Err(anyhow::anyhow!("invalid divergence investigation job status").into()),
+        }
+    }
+}
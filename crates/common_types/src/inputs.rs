@@ -3,9 +3,10 @@
 
 use std::ops::{Bound, RangeBounds};
 
-use async_graphql::InputObject;
+use async_graphql::{Enum, InputObject};
+use chrono::NaiveDateTime;
 
-use crate::{IndexerAddress, IpfsCid};
+use crate::{DivergenceInvestigationStatus, IndexerAddress, IpfsCid};
 
 /// A filter for subgraph deployments.
 #[derive(Default)]
@@ -16,6 +17,9 @@ pub struct SgDeploymentsQuery {
     pub name: Option<String>,
     /// The IPFS hash of the subgraph deployment(s).
     pub ipfs_cid: Option<IpfsCid>,
+    /// How to sort the results.
+    pub order_by: SgDeploymentsOrderBy,
+    pub order_direction: OrderDirection,
     /// Upper limit on the number of shown results.
     pub limit: Option<u16>,
 }
@@ -31,6 +35,9 @@ pub struct PoisQuery {
     /// Restricts the query to PoIs that were collected in the given block
     /// range.
     pub block_range: Option<BlockRange>,
+    /// Restricts the query to PoIs of indexers with any of these tags.
+    #[graphql(default)]
+    pub indexer_tags: Vec<String>,
     /// Upper limit on the number of shown results.
     pub limit: Option<u16>,
 }
@@ -41,17 +48,194 @@ pub struct IndexersQuery {
     /// The address of the indexer, encoded as a hex string with a '0x'
     /// prefix.
     pub address: Option<IndexerAddress>,
+    /// How to sort the results.
+    #[graphql(default)]
+    pub order_by: IndexersOrderBy,
+    #[graphql(default)]
+    pub order_direction: OrderDirection,
     /// Upper limit on the number of shown results.
     pub limit: Option<u16>,
+    /// Whether to include archived indexers. Indexers that are looked up by
+    /// address are always included.
+    #[graphql(default)]
+    pub include_archived: bool,
+    /// Restricts the query to indexers with any of these tags.
+    #[graphql(default)]
+    pub tags: Vec<String>,
 }
 
-/// A block range, specified by optional start and end block numbers.
+/// A filter for bisection runs of divergence investigations.
+#[derive(Default)]
+pub struct BisectionRunsQuery {
+    /// The subgraph deployment that was bisected.
+    pub deployment: Option<IpfsCid>,
+    /// One of the two indexers whose PoIs were compared.
+    pub indexer: Option<IndexerAddress>,
+    /// Restricts the query to bisection runs whose divergence block bounds
+    /// contain this block number.
+    pub block_number: Option<u64>,
+    /// Upper limit on the number of shown results.
+    pub limit: Option<u16>,
+}
+
+/// A filter for divergence investigation reports.
+#[derive(Default)]
+pub struct DivergenceInvestigationsQuery {
+    /// The subgraph deployment that was bisected.
+    pub deployment: Option<IpfsCid>,
+    /// One of the indexers whose PoIs were compared.
+    pub indexer: Option<IndexerAddress>,
+    pub status: Option<DivergenceInvestigationStatus>,
+    /// Restricts the query to investigations that started at or after this
+    /// time, in UTC.
+    pub created_after: Option<NaiveDateTime>,
+    /// Restricts the query to investigations that started at or before this
+    /// time, in UTC.
+    pub created_before: Option<NaiveDateTime>,
+    /// Upper limit on the number of shown results.
+    pub limit: Option<u16>,
+}
+
+/// A filter for PoIs submitted on chain when closing allocations.
+#[derive(Default)]
+pub struct AllocationPoisQuery {
+    pub deployment: Option<IpfsCid>,
+    pub indexer: Option<IndexerAddress>,
+    /// Only return PoIs that differ from the ones Graphix collected.
+    pub only_mismatches: bool,
+    /// Upper limit on the number of shown results.
+    pub limit: Option<u16>,
+}
+
+/// A filter for on-chain disputes about PoIs.
+#[derive(Default)]
+pub struct PoiDisputesQuery {
+    pub deployment: Option<IpfsCid>,
+    /// The address of the disputed indexer.
+    pub indexer: Option<IndexerAddress>,
+    /// Only return disputes that weren't resolved yet.
+    pub only_undecided: bool,
+    /// Upper limit on the number of shown results.
+    pub limit: Option<u16>,
+}
+
+/// A filter for allocations that indexers report, but don't seem to serve.
+#[derive(Default)]
+pub struct AllocationDiscrepanciesQuery {
+    /// The address of the allocating indexer.
+    pub indexer: Option<IndexerAddress>,
+    /// Upper limit on the number of shown results.
+    pub limit: Option<u16>,
+}
+
+/// A filter for chain disagreements.
+#[derive(Default)]
+pub struct ChainDisagreementsQuery {
+    pub deployment: Option<IpfsCid>,
+    /// Upper limit on the number of shown results.
+    pub limit: Option<u16>,
+}
+
+/// A filter for the subgraph deployments on which indexers currently
+/// disagree.
+#[derive(Default, InputObject)]
+pub struct DivergencesQuery {
+    /// Restricts the query to these subgraph deployments. All deployments are
+    /// included if empty.
+    #[graphql(default)]
+    pub deployments: Vec<IpfsCid>,
+    /// Only includes disagreements that involve this indexer.
+    pub indexer: Option<IndexerAddress>,
+}
+
+/// The direction in which results are sorted.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Enum)]
+pub enum OrderDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// The fields by which subgraph deployments can be sorted.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Enum)]
+pub enum SgDeploymentsOrderBy {
+    #[default]
+    IpfsCid,
+    /// The human-readable name of the subgraph deployment. Deployments without
+    /// a name come last.
+    Name,
+    /// When Graphix first saw the subgraph deployment.
+    CreatedAt,
+}
+
+/// The fields by which indexers can be sorted.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Enum)]
+pub enum IndexersOrderBy {
+    #[default]
+    Address,
+    /// Indexers without a name come last.
+    Name,
+    /// When Graphix first saw the indexer.
+    CreatedAt,
+    /// How many of the other indexers agree with the indexer's live PoIs on
+    /// average. Indexers without live PoIs come last.
+    AgreementRatio,
+}
+
+/// The fields by which PoIs can be sorted.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Enum)]
+pub enum PoisOrderBy {
+    #[default]
+    BlockNumber,
+    /// When Graphix collected the PoI.
+    CreatedAt,
+    /// The fraction of indexers that reported the same PoI for the same
+    /// subgraph deployment and block.
+    AgreementRatio,
+}
+
+/// How to sort PoIs. Defaults to the most recent block first.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PoisOrder {
+    pub by: PoisOrderBy,
+    pub direction: OrderDirection,
+}
+
+impl Default for PoisOrder {
+    fn default() -> Self {
+        Self {
+            by: PoisOrderBy::BlockNumber,
+            direction: OrderDirection::Desc,
+        }
+    }
+}
+
+/// A block range, specified by optional start and end block numbers and
+/// times. Time bounds only match blocks whose timestamp is known.
 #[derive(InputObject)]
 pub struct BlockRange {
     /// The start block number (inclusive).
     pub start: Option<u64>,
     /// The end block number (inclusive).
     pub end: Option<u64>,
+    /// The earliest block timestamp (inclusive).
+    pub start_time: Option<NaiveDateTime>,
+    /// The latest block timestamp (inclusive).
+    pub end_time: Option<NaiveDateTime>,
+}
+
+impl BlockRange {
+    /// Whether a block with the given timestamp is within the time bounds of
+    /// the range.
+    pub fn contains_timestamp(&self, timestamp: Option<NaiveDateTime>) -> bool {
+        if self.start_time.is_none() && self.end_time.is_none() {
+            return true;
+        }
+        timestamp.is_some_and(|timestamp| {
+            self.start_time.map_or(true, |start| timestamp >= start)
+                && self.end_time.map_or(true, |end| timestamp <= end)
+        })
+    }
 }
 
 impl RangeBounds<u64> for BlockRange {
@@ -4,17 +4,25 @@
 //! separate? It would be cleaner, but at the cost of some code duplication.
 
 mod api_key_permission_level;
+mod bisection_evidence_kind;
+mod dispute_status;
+mod divergence_investigation_job_status;
 mod hex_string;
 pub mod inputs;
 mod ipfs_cid;
+mod subgraph_health;
 
 pub use api_key_permission_level::ApiKeyPermissionLevel;
 use async_graphql::*;
+pub use bisection_evidence_kind::BisectionEvidenceKind;
 use chrono::NaiveDateTime;
+pub use dispute_status::DisputeStatus;
 pub use divergence_investigation::*;
+pub use divergence_investigation_job_status::DivergenceInvestigationJobStatus;
 pub use hex_string::HexString;
 pub use ipfs_cid::IpfsCid;
 use serde::{Deserialize, Serialize};
+pub use subgraph_health::SubgraphHealth;
 use uuid::Uuid;
 
 /// A PoI (proof of indexing) is always 32 bytes.
@@ -44,6 +52,10 @@ mod divergence_investigation {
         /// The investigation has been concluded and the end results are
         /// available.
         Complete,
+        /// The investigation was aborted, either on request or because it
+        /// exceeded its time limit. Results gathered until then are
+        /// available.
+        Cancelled,
     }
 
     /// A divergence investigation report contains all information that pertains to a divergence
@@ -60,11 +72,66 @@ mod divergence_investigation {
         /// divergence investigation. If the investigation is still in progress,
         /// this list may be incomplete.
         pub bisection_runs: Vec<BisectionRunReport>,
+        /// The cohort bisection run that was performed instead of pairwise
+        /// bisection runs, if requested.
+        #[serde(default)]
+        pub cohort_bisection: Option<CohortBisectionReport>,
         /// If the divergence investigation failed altogether, this field
         /// contains the error message. Please note that specific bisection runs
         /// may also fail, in which case the error message will be in the
         /// `error` field of the corresponding `BisectionRunReport`.
         pub error: Option<String>,
+        /// How far along the divergence investigation is. It's updated after
+        /// every probed block, so it tells a slow investigation from a stuck
+        /// one.
+        #[serde(default)]
+        pub progress: Option<DivergenceInvestigationProgress>,
+        /// The signature of the Graphix operator, if the investigation is
+        /// complete and a signing key is configured.
+        #[serde(default)]
+        pub signature: Option<ReportSignature>,
+    }
+
+    /// An Ed25519 signature of a divergence investigation report, which lets
+    /// third parties verify that the report originated from the operator
+    /// that holds the signing key.
+    #[derive(Debug, Clone, Serialize, SimpleObject, Deserialize)]
+    pub struct ReportSignature {
+        /// The signed report as JSON, without its signature. The signature
+        /// covers exactly the UTF-8 bytes of this string.
+        pub payload: String,
+        /// The hex-encoded signature of the payload.
+        pub signature: String,
+        /// The hex-encoded public key that the signature verifies against.
+        pub public_key: String,
+    }
+
+    /// The progress of a divergence investigation.
+    #[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+    pub struct DivergenceInvestigationProgress {
+        /// The number of bisection runs that are over.
+        pub completed_bisection_runs: u32,
+        /// The number of bisection runs that the investigation consists of.
+        pub total_bisection_runs: u32,
+        /// The current block bounds of the ongoing bisection run, if any.
+        pub current_block_bounds: Option<DivergenceBlockBounds>,
+        /// The number of blocks probed so far during the ongoing bisection
+        /// run.
+        pub blocks_probed: u32,
+        /// The most recent errors that indexers returned when probed, oldest
+        /// first.
+        pub indexer_errors: Vec<IndexerProbeError>,
+        /// When the investigation last made progress.
+        pub updated_at: NaiveDateTime,
+    }
+
+    /// An error that an indexer returned when probed for its PoI during a
+    /// divergence investigation.
+    #[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+    pub struct IndexerProbeError {
+        pub indexer: IndexerAddress,
+        pub block_number: i64,
+        pub error: String,
     }
 
     #[derive(Debug, Clone, Serialize, SimpleObject, Deserialize)]
@@ -93,6 +160,32 @@ mod divergence_investigation {
         pub entity_changes: Option<serde_json::Value>,
     }
 
+    /// Features of a subgraph manifest that have historically been sources of
+    /// non-determinism across `graph-node` versions and setups. They're
+    /// surfaced on bisection run reports as hints for investigators.
+    #[derive(Debug, Copy, Clone, Enum, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum ManifestFlag {
+        /// The deployment reads files from IPFS in its mappings (`ipfs.cat` and
+        /// `ipfs.map`), which depends on the availability of the files.
+        IpfsOnEthereumContracts,
+        /// The deployment keeps indexing past deterministic errors.
+        NonFatalErrors,
+        /// The deployment was grafted onto another deployment, whose PoIs it
+        /// inherits.
+        Grafted,
+        /// The deployment uses call handlers, which rely on Parity-style
+        /// traces.
+        CallHandlers,
+        /// The deployment uses block handlers with a `call` filter, which rely
+        /// on Parity-style traces.
+        BlockHandlerCallFilter,
+        /// Some mappings use an `apiVersion` older than 0.0.5, whose
+        /// `ethereum.call` handling differed between `graph-node` versions.
+        LegacyApiVersion,
+        /// The deployment uses file data sources.
+        FileDataSources,
+    }
+
     /// A bisection run report contains information about a specific bisection
     /// run that is part of a larger divergence investigation.
     #[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
@@ -112,6 +205,10 @@ mod divergence_investigation {
         /// available which includes the block number and hash, as well as the
         /// metadata that was collected from `graph-node` for that block.
         pub bisects: Vec<BisectionReport>,
+        /// Features of the subgraph deployment's manifest that are known to
+        /// be likely root causes of divergences, if the manifest is available.
+        #[serde(default)]
+        pub manifest_flags: Vec<ManifestFlag>,
         /// If the bisection run failed before reaching a conclusion at a single
         /// block, this field contains the error message.
         pub error: Option<String>,
@@ -129,6 +226,53 @@ mod divergence_investigation {
         /// `graph-node` instance.
         pub indexer2_response: String,
     }
+
+    /// A cohort bisection run bisects a whole group of disagreeing indexers at
+    /// once, instead of one pair of PoIs at a time. At each probed block, the
+    /// indexers are partitioned into cohorts that agree on the PoI.
+    #[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+    pub struct CohortBisectionReport {
+        /// The UUID of the bisection run that this report pertains to.
+        pub uuid: Uuid,
+        /// The indexers that were bisected, i.e. all indexers that produced
+        /// one of the investigated PoIs.
+        pub indexers: Vec<IndexerAddress>,
+        /// The lower and upper block bounds inside which the bisection run
+        /// occurred. All indexers agree at the lower bound, and the upper bound
+        /// is the first block at which they disagree.
+        pub divergence_block_bounds: DivergenceBlockBounds,
+        /// The cohorts at the upper bound of the divergence block bounds, i.e.
+        /// which indexers diverged from which, largest cohort first.
+        pub diverging_cohorts: Vec<PoiCohort>,
+        /// The cohorts at each probed block, in bisection order.
+        pub bisects: Vec<CohortBisectionStep>,
+        /// Features of the subgraph deployment's manifest that are known to
+        /// be likely root causes of divergences, if the manifest is available.
+        pub manifest_flags: Vec<ManifestFlag>,
+        /// If the bisection run failed before reaching a conclusion at a single
+        /// block, this field contains the error message.
+        pub error: Option<String>,
+    }
+
+    /// The cohorts of indexers that agreed with each other at a block probed
+    /// during a cohort bisection run.
+    #[derive(Debug, Clone, SimpleObject, Serialize, Deserialize)]
+    pub struct CohortBisectionStep {
+        /// The block number and hash that the cohorts pertain to.
+        pub block: PartialBlock,
+        /// The cohorts at this block, largest first.
+        pub cohorts: Vec<PoiCohort>,
+    }
+
+    /// A group of indexers that reported the same PoI for a block.
+    #[derive(Debug, Clone, PartialEq, Eq, SimpleObject, Serialize, Deserialize)]
+    pub struct PoiCohort {
+        /// The PoI that the indexers reported, or null for the indexers that
+        /// failed to report one.
+        pub poi: Option<PoiBytes>,
+        /// The addresses of the indexers in this cohort.
+        pub indexers: Vec<IndexerAddress>,
+    }
 }
 
 /// A block number that may or may not also have an associated hash.
@@ -172,3 +316,64 @@ pub struct PoiCrossCheckReport {
     proof_of_indexing2: PoiBytes,
     diverging_block: Option<DivergingBlock>,
 }
+
+/// Number of rows that belong to a single indexer and were deleted (or would
+/// be deleted, in a dry run) from the database.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, SimpleObject)]
+pub struct IndexerDataDeletion {
+    /// If true, nothing was actually deleted.
+    pub dry_run: bool,
+    pub pois: u64,
+    pub live_pois: u64,
+    pub failed_queries: u64,
+    /// Divergence investigation reports that compared PoIs of the indexer.
+    pub divergence_investigation_reports: u64,
+    pub bisection_runs: u64,
+    pub bisection_evidence: u64,
+    /// PoIs the indexer submitted on chain when closing allocations.
+    pub allocation_pois: u64,
+    /// Entity counts collected from the indexer's indexing statuses.
+    pub entity_counts: u64,
+    /// Indexing statuses collected from the indexer over time.
+    pub indexing_status_history: u64,
+}
+
+/// Corrections to live PoIs, made by recomputing them from the stored PoIs
+/// unless in a dry run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, SimpleObject)]
+pub struct LivePoiReport {
+    /// If true, nothing was actually corrected.
+    pub dry_run: bool,
+    /// Live PoIs that were missing for indexers with a PoI at the live block
+    /// of a deployment.
+    pub inserted: u64,
+    /// Live PoIs that referred to an older PoI of the same indexer at the
+    /// same block.
+    pub updated: u64,
+    /// Live PoIs of indexers without a PoI at the live block of a deployment.
+    pub deleted: u64,
+}
+
+/// Number of inconsistent rows found in the database, e.g. after a crash or
+/// manual database surgery, and deleted unless in a dry run. Repairs can
+/// cause further inconsistencies that are only counted once they're repaired,
+/// e.g. PoI values of deleted PoIs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, SimpleObject)]
+pub struct IntegrityReport {
+    /// If true, nothing was actually repaired.
+    pub dry_run: bool,
+    /// PoIs whose block doesn't exist.
+    pub pois_with_missing_blocks: u64,
+    /// Live PoIs that refer to a missing PoI, or to one of another indexer or
+    /// deployment.
+    pub mismatched_live_pois: u64,
+    /// Live PoIs at an older block than the latest live PoIs of the same
+    /// deployment, which they should have been replaced by.
+    pub stale_live_pois: u64,
+    /// PoI values that no PoI refers to.
+    pub orphaned_poi_values: u64,
+    /// Collected Graph Node versions that no indexer refers to anymore.
+    pub orphaned_graph_node_versions: u64,
+    /// Network subgraph metadata that no indexer refers to anymore.
+    pub orphaned_indexer_network_subgraph_metadata: u64,
+}
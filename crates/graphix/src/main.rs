@@ -2,71 +2,408 @@
 
 use std::collections::HashSet;
 use std::env;
-use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 
+use anyhow::Context as _;
 use clap::Parser;
-use graphix_indexer_client::{IndexerClient, IndexerId};
-use graphix_lib::bisect::handle_divergence_investigation_requests;
+use graphix_indexer_client::{limit_indexer_labels, IndexerClient, IndexerId};
+use graphix_lib::allocation_pois::AllocationPoiChecker;
+use graphix_lib::bisect::{
+    divergence_investigation_requests, enqueue_divergence_investigations,
+    handle_divergence_investigation_requests,
+};
+use graphix_lib::block_verification::{
+    block_timestamps, canonical_pois, chain_disagreements, deployment_networks,
+    verify_block_hashes, without_chain_disagreements,
+};
+use graphix_lib::check_tiers::{assign_check_tiers, retain_due_deployments};
 use graphix_lib::config::Config;
-use graphix_lib::graphql_api::{axum_router, GraphixState};
-use graphix_lib::indexing_loop::{query_indexing_statuses, query_proofs_of_indexing};
-use graphix_lib::{config, metrics, CliOptions, PrometheusExporter};
-use graphix_store::{models, PoiLiveness, Store};
+use graphix_lib::daily_agreement::run_daily_agreement_refresher;
+use graphix_lib::deployment_metadata::DeploymentMetadataSync;
+use graphix_lib::dispute_evidence::evidence_bundle;
+use graphix_lib::disputes::DisputeMonitor;
+use graphix_lib::graphql_api::{
+    api_schema_builder, axum_router, serve_api, ApiServerOptions, GraphixState,
+};
+use graphix_lib::indexing_loop::{
+    choose_poi_requests, poi_request_outcomes, query_active_allocations,
+    query_and_write_proofs_of_indexing, query_indexing_statuses, query_subgraph_api_versions,
+    retain_tracked_deployments, IndexerRequestLimits,
+};
+use graphix_lib::ipfs::{fetch_subgraph_manifests, IpfsClient};
+use graphix_lib::live_pois::run_live_poi_recomputation;
+use graphix_lib::networks::NetworkAliases;
+use graphix_lib::notifications::{send_notifications, DivergenceTracker};
+use graphix_lib::sharding::{retain_shard_deployments, Shard};
+use graphix_lib::uptime::run_uptime_prober;
+use graphix_lib::{
+    config, metrics, run_pool_metrics, CliOptions, Command, ConfigCommand, DbCommand, LogFormat,
+    MigrationsCommand, PrometheusExporter,
+};
+use graphix_network_sg_client::NetworkSubgraphClient;
+use graphix_store::{models, LeaderLock, Store, HOT_TABLES};
 use prometheus_exporter::prometheus;
-use tokio::net::TcpListener;
 use tokio::sync::watch;
 use tracing::*;
+use uuid::Uuid;
+
+/// How often replicas that aren't the leader check whether it stepped down.
+const LEADER_ELECTION_INTERVAL: Duration = Duration::from_secs(10);
 
 async fn load_config(store: &Store) -> anyhow::Result<Config> {
     info!("Loading configuration from database...");
-    let config_json_opt = store.current_config().await?;
+    let config_json_opt = config::effective_config_json(store.current_config().await?)?;
 
-    Ok(if let Some(json) = config_json_opt {
+    let mut config = if let Some(json) = config_json_opt {
         serde_json::from_value(json)?
     } else {
         warn!("Missing configuration; using empty configuration");
         Config::default()
+    };
+    // Secret files are re-read on every reload, so that rotated secrets are
+    // picked up without a restart.
+    config.load_secrets()?;
+
+    Ok(config)
+}
+
+/// Stores the configuration file at `path` in the database, unless the
+/// database already has a configuration. The database copy is authoritative
+/// from then on.
+async fn bootstrap_config(store: &Store, path: &std::path::Path) -> anyhow::Result<()> {
+    if store.current_config().await?.is_some() {
+        info!(
+            path = %path.display(),
+            "Database already has a configuration; ignoring base configuration file"
+        );
+        return Ok(());
+    }
+
+    info!(path = %path.display(), "Storing base configuration file in database");
+    let config = Config::read(path)?;
+    store.overwrite_config(serde_json::to_value(config)?).await
+}
+
+/// Reports all problems in the configuration file at `path`, and fails if
+/// there are any.
+fn validate_config_file(path: &std::path::Path) -> anyhow::Result<()> {
+    let diagnostics = config::validate_config(&std::fs::read_to_string(path)?);
+    for diagnostic in &diagnostics {
+        eprintln!("{}: {}", path.display(), diagnostic);
+    }
+
+    if diagnostics.is_empty() {
+        println!("{}: configuration is valid", path.display());
+        Ok(())
+    } else {
+        anyhow::bail!(
+            "found {} problem(s) in {}",
+            diagnostics.len(),
+            path.display()
+        )
+    }
+}
+
+async fn run_db_command(cli_options: &CliOptions, command: &DbCommand) -> anyhow::Result<()> {
+    if let DbCommand::Migrations(command) = command {
+        let store = Store::new_without_migrations(&cli_options.database_url()?).await?;
+        return run_migrations_command(&store, command).await;
+    }
+    let store = Store::new(&cli_options.database_url()?).await?;
+
+    match command {
+        DbCommand::Vacuum { analyze_only } => {
+            store.vacuum_hot_tables(*analyze_only).await?;
+            println!("Finished maintaining {}", HOT_TABLES.join(", "));
+        }
+        DbCommand::Sizes => {
+            println!(
+                "{:<40} {:>10} {:>10} {:>10} {:>12}",
+                "TABLE", "TOTAL", "DATA", "INDEXES", "ROWS (EST.)"
+            );
+            for size in store.table_sizes().await? {
+                println!(
+                    "{:<40} {:>10} {:>10} {:>10} {:>12}",
+                    size.table_name,
+                    format_bytes(size.total_bytes),
+                    format_bytes(size.table_bytes),
+                    format_bytes(size.index_bytes),
+                    size.estimated_rows.max(0),
+                );
+            }
+        }
+        DbCommand::IndexBloat => {
+            println!(
+                "{:<60} {:>10} {:>12} {:>10}",
+                "INDEX", "SIZE", "BLOAT (EST.)", "SCANS"
+            );
+            for bloat in store.index_bloat().await? {
+                println!(
+                    "{:<60} {:>10} {:>12} {:>10}",
+                    format!("{} ({})", bloat.index_name, bloat.table_name),
+                    format_bytes(bloat.index_bytes),
+                    format_bytes(bloat.estimated_bloat_bytes),
+                    bloat.scans.unwrap_or_default(),
+                );
+            }
+        }
+        DbCommand::CheckIntegrity { repair } => {
+            let report = store.check_integrity(!repair).await?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        DbCommand::RecomputeLivePois { repair } => {
+            let report = store.recompute_live_pois(!repair).await?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        DbCommand::Migrations(_) => unreachable!(),
+    }
+
+    Ok(())
+}
+
+async fn run_migrations_command(store: &Store, command: &MigrationsCommand) -> anyhow::Result<()> {
+    match command {
+        MigrationsCommand::Status => {
+            for migration in store.migration_status().await? {
+                let status = match migration.run_on {
+                    Some(run_on) => format!("applied at {run_on}"),
+                    None => "pending".to_string(),
+                };
+                println!("{:<60} {}", migration.name, status);
+            }
+        }
+        MigrationsCommand::Revert { confirm } => {
+            store.revert_migration(confirm).await?;
+            println!("Reverted {confirm}");
+        }
+    }
+
+    Ok(())
+}
+
+async fn export_dispute_evidence(
+    cli_options: &CliOptions,
+    bisection_run: &Uuid,
+    output: &Path,
+) -> anyhow::Result<()> {
+    let signer = cli_options
+        .evidence_signer()?
+        .context("--dispute-evidence-signing-key is required to sign evidence bundles")?;
+    let store = Store::new(&cli_options.database_url()?).await?;
+
+    let bundle = evidence_bundle(&store, &store, bisection_run, &signer).await?;
+    std::fs::write(output, bundle)
+        .with_context(|| format!("failed to write {}", output.display()))?;
+    println!(
+        "Wrote the evidence bundle of bisection run {bisection_run} to {}, signed by {}",
+        output.display(),
+        signer.public_key()
+    );
+
+    Ok(())
+}
+
+/// Formats a size for humans, e.g. `1.5 GiB`.
+fn format_bytes(bytes: i64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+fn api_server_options(
+    cli_options: &CliOptions,
+    read_only: bool,
+) -> anyhow::Result<ApiServerOptions> {
+    Ok(ApiServerOptions {
+        read_only,
+        cors_allowed_origins: cli_options.cors_allowed_origins.clone(),
+        max_request_body_size: cli_options.max_request_body_size,
+        serve_metrics: cli_options.tls_options().is_some(),
+        metrics_bearer_token: cli_options.metrics_bearer_token()?,
+        response_cache_ttl: (cli_options.response_cache_ttl_in_seconds > 0)
+            .then(|| Duration::from_secs(cli_options.response_cache_ttl_in_seconds)),
+        persisted_queries_allowlist: cli_options.persisted_queries_allowlist.clone(),
+        rate_limit_per_ip: cli_options.rate_limit_per_ip()?,
+        rate_limit_per_api_key: cli_options.rate_limit_per_api_key()?,
+        admin_allowed_networks: cli_options.admin_allowed_cidrs.clone(),
+        oidc: cli_options.oidc_options()?,
+        dispute_evidence_signing_key: cli_options.dispute_evidence_signing_key.clone(),
+        statement_timeout: cli_options.api_statement_timeout(),
     })
 }
 
+/// Only serves the GraphQL API, without collecting any data or writing to
+/// the database. The configuration is reloaded from the database
+/// periodically.
+async fn serve_read_only(cli_options: CliOptions) -> anyhow::Result<()> {
+    info!("Running in read-only mode");
+    let database_url = cli_options.database_url()?;
+    let store = Store::new_read_only(&database_url, cli_options.api_statement_timeout()).await?;
+    let (config_sender, config_receiver) = watch::channel(load_config(&store).await?);
+
+    tokio::spawn(async move {
+        loop {
+            let polling_period = config_sender.borrow().polling_period_in_seconds;
+            tokio::time::sleep(Duration::from_secs(polling_period)).await;
+
+            match load_config(&store).await {
+                Ok(config) => {
+                    config_sender.send(config).ok();
+                }
+                Err(err) => error!(error = %err, "Failed to reload configuration"),
+            }
+        }
+    });
+
+    let router = axum_router(
+        &database_url,
+        config_receiver,
+        api_server_options(&cli_options, true)?,
+    )
+    .await?;
+    serve_api(
+        SocketAddr::new(cli_options.listen_address, cli_options.port),
+        router,
+        cli_options.tls_options().as_ref(),
+    )
+    .await
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    init_tracing();
-
     let cli_options = CliOptions::parse();
+    init_tracing(cli_options.log_format);
 
-    info!("Initialize store and running migrations");
-    let store = Store::new(&cli_options.database_url).await?;
+    match &cli_options.command {
+        Some(Command::Config(ConfigCommand::Validate { path })) => {
+            return validate_config_file(path);
+        }
+        Some(Command::Db(command)) => {
+            return run_db_command(&cli_options, command).await;
+        }
+        Some(Command::Schema) => {
+            print!("{}", api_schema_builder().finish().sdl());
+            return Ok(());
+        }
+        Some(Command::DisputeEvidence {
+            bisection_run,
+            output,
+        }) => {
+            return export_dispute_evidence(&cli_options, bisection_run, output).await;
+        }
+        #[cfg(feature = "loadtest")]
+        Some(Command::Loadtest(options)) => {
+            let store = Store::new(&cli_options.database_url()?).await?;
+            let report = graphix_lib::loadtest::run_loadtest(&store, options).await?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            return Ok(());
+        }
+        None => {}
+    }
+    if cli_options.read_only {
+        return serve_read_only(cli_options).await;
+    }
+
+    let database_url = cli_options.database_url()?;
+    let shard = cli_options.shard()?;
+    if shard != Shard::ALL {
+        info!(%shard, "Cross-checking a shard of all deployments");
+    }
+
+    info!("Initialize store");
+    let store = Store::new_without_migrations_with_statement_timeout(
+        &database_url,
+        cli_options.collector_statement_timeout(),
+    )
+    .await?;
     info!("Store initialization successful");
+    tokio::spawn(run_pool_metrics("main", store.clone()));
 
-    let (config_sender, config_receiver) = watch::channel(load_config(&store).await?);
+    // The database may not be migrated yet, until a leader is elected.
+    let initial_config = match load_config(&store).await {
+        Ok(config) => config,
+        Err(err) => {
+            warn!(error = %err, "Failed to load configuration; using empty configuration");
+            Config::default()
+        }
+    };
+    let (config_sender, config_receiver) = watch::channel(initial_config);
 
     {
         let config_receiver = config_receiver.clone();
+        let address = SocketAddr::new(cli_options.listen_address, cli_options.port);
+        let database_url = database_url.clone();
+        let options = api_server_options(&cli_options, false)?;
+        let tls = cli_options.tls_options();
         tokio::spawn(async move {
-            axum::serve(
-                TcpListener::bind((Ipv4Addr::UNSPECIFIED, cli_options.port)).await?,
-                axum_router(&cli_options.database_url, config_receiver).await?,
-            )
-            .await?;
+            let router = axum_router(&database_url, config_receiver, options).await?;
+            let result = serve_api(address, router, tls.as_ref()).await;
+            if let Err(err) = &result {
+                error!(error = %err, "GraphQL API server failed");
+            }
 
-            Result::<(), anyhow::Error>::Ok(())
+            result
         });
     }
 
-    let mut config = load_config(&store).await?;
-
     // Prometheus metrics.
     let _exporter = PrometheusExporter::start(
-        cli_options.prometheus_port,
+        SocketAddr::new(
+            cli_options.prometheus_listen_address,
+            cli_options.prometheus_port,
+        ),
         prometheus::default_registry().clone(),
+        cli_options.metrics_bearer_token()?,
     )?;
 
-    info!("Initializing bisect request handler");
+    // All replicas serve the API, but only the leader collects data.
+    let mut leader_lock = wait_for_leadership(&store, shard, &config_sender).await?;
+
+    // Only the leader migrates, so that replicas don't race each other. A
+    // separate store is used because migrations aren't subject to the
+    // statement timeout.
+    info!("Running database migrations");
+    Store::new(&database_url).await?;
+
+    if let Some(path) = &cli_options.base_config {
+        bootstrap_config(&store, path).await?;
+    }
+
+    let mut config = load_config(&store).await?;
+    config_sender.send(config.clone()).ok();
+
     let (tx_indexers, rx_indexers) = watch::channel(vec![]);
+
+    // Work that isn't split across shards only runs on the primary one.
+    if shard.is_primary() {
+        info!("Initializing indexer uptime prober");
+        tokio::spawn(run_uptime_prober(
+            store.clone(),
+            rx_indexers.clone(),
+            config_receiver.clone(),
+        ));
+
+        info!("Initializing daily agreement rollup refresher");
+        tokio::spawn(run_daily_agreement_refresher(store.clone()));
+
+        info!("Initializing live PoI recomputation");
+        tokio::spawn(run_live_poi_recomputation(store.clone()));
+    }
+
     {
         let store_clone = store.clone();
 
@@ -82,61 +419,369 @@ async fn main() -> anyhow::Result<()> {
             .collect();
         store_clone.create_networks_if_missing(&networks).await?;
 
-        tokio::spawn(async move {
-            handle_divergence_investigation_requests(&store_clone, rx_indexers, &ctx)
-                .await
-                .unwrap()
-        });
+        if shard.is_primary() {
+            info!("Initializing bisect request handler");
+            tokio::spawn(async move {
+                handle_divergence_investigation_requests(&store_clone, rx_indexers, &ctx)
+                    .await
+                    .unwrap()
+            });
+        }
     }
 
+    let mut divergence_tracker = DivergenceTracker::default();
+    let mut allocation_poi_checker = AllocationPoiChecker::default();
+    let mut dispute_monitor = DisputeMonitor::default();
+    let mut deployment_metadata_sync = DeploymentMetadataSync::default();
+    let mut round_id: u64 = 0;
     loop {
-        config = load_config(&store).await?;
-        config_sender.send(config.clone()).ok();
+        round_id += 1;
+        // Everything logged during a round carries its ID, so that its logs
+        // can be told apart from those of other rounds.
+        async {
+            if !leader_lock.is_held().await {
+                // Another replica takes over in the meantime.
+                anyhow::bail!("Lost the leader lock, e.g. because the database connection dropped");
+            }
+
+            config = load_config(&store).await?;
+            config_sender.send(config.clone()).ok();
 
-        let sleep_duration = Duration::from_secs(config.polling_period_in_seconds);
+            let sleep_duration = Duration::from_secs(config.polling_period_in_seconds);
+            let request_limits = IndexerRequestLimits::new(&config);
 
-        info!("New main loop iteration");
-        info!("Initialize inputs (indexers, indexing statuses etc.)");
+            info!("New main loop iteration");
+            info!("Initialize inputs (indexers, indexing statuses etc.)");
 
-        let mut indexers = config::config_to_indexers(config.clone(), metrics()).await?;
-        // Different data sources, especially network subgraphs, result in
-        // duplicate indexers.
-        indexers = deduplicate_indexers(&indexers);
+            let mut indexers_config = config.clone();
+            match store.registered_indexers().await {
+                Ok(registered_indexers) => {
+                    indexers_config.add_registered_indexers(registered_indexers)
+                }
+                Err(err) => error!(
+                    error = %err,
+                    "Failed to load registered indexers, only using configured ones"
+                ),
+            }
 
-        store.write_indexers(&indexers).await?;
+            let mut indexers = config::config_to_indexers(indexers_config, metrics()).await?;
+            // Different data sources, especially network subgraphs, result in
+            // duplicate indexers.
+            indexers = deduplicate_indexers(&indexers);
+            // Archived indexers are only kept around for their history.
+            match store.archived_indexer_addresses().await {
+                Ok(archived_indexers) => {
+                    indexers.retain(|indexer| !archived_indexers.contains(&indexer.address()))
+                }
+                Err(err) => error!(
+                    error = %err,
+                    "Failed to load archived indexers, not filtering them out this round"
+                ),
+            }
+            limit_indexer_labels(
+                indexers.iter().map(|indexer| indexer.address()),
+                config.metrics.max_indexer_labels,
+            );
 
-        tx_indexers.send(indexers.clone())?;
+            store.write_indexers(&indexers).await?;
+            if shard.is_primary() {
+                if let Err(err) = store
+                    .write_configured_indexer_tags(&config.indexer_tags())
+                    .await
+                {
+                    error!(error = %err, "Failed to write configured indexer tags to database");
+                }
+            }
 
-        let graph_node_versions =
-            graphix_lib::indexing_loop::query_graph_node_versions(&indexers, metrics()).await;
-        store.write_graph_node_versions(graph_node_versions).await?;
+            // Nobody may be listening on non-primary shards.
+            tx_indexers.send_replace(indexers.clone());
 
-        let indexing_statuses = query_indexing_statuses(&indexers, metrics()).await;
+            if shard.is_primary() {
+                let graph_node_versions = graphix_lib::indexing_loop::query_graph_node_versions(
+                    &indexers,
+                    metrics(),
+                    &request_limits,
+                )
+                .await;
+                store.write_graph_node_versions(graph_node_versions).await?;
+            }
 
-        info!("Monitor proofs of indexing");
-        let pois = query_proofs_of_indexing(indexing_statuses, config.block_choice_policy).await;
+            let mut indexing_statuses =
+                query_indexing_statuses(&indexers, metrics(), &request_limits).await;
+            let network_aliases = NetworkAliases::new(&config.chains);
+            network_aliases.normalize(&mut indexing_statuses);
+            if shard.is_primary() {
+                let allocations = query_active_allocations(&indexers, &request_limits).await;
+                for (indexer, allocations) in allocations {
+                    if let Err(err) = store
+                        .write_indexer_allocations(
+                            indexer.as_ref(),
+                            &allocations,
+                            &indexing_statuses,
+                        )
+                        .await
+                    {
+                        error!(error = %err, "Failed to store active allocations");
+                    }
+                }
+            }
+            match store.tracked_deployments().await {
+                Ok(tracked_deployments) => {
+                    let tracked_deployments: Vec<_> = tracked_deployments
+                        .into_iter()
+                        .map(|deployment| {
+                            let network =
+                                network_aliases.canonical(&deployment.network).to_string();
+                            (deployment.cid, network)
+                        })
+                        .collect();
+                    retain_tracked_deployments(&mut indexing_statuses, &tracked_deployments);
+                }
+                Err(err) => error!(
+                    error = %err,
+                    "Failed to load tracked deployments, cross-checking all deployments this round"
+                ),
+            }
+            retain_shard_deployments(&mut indexing_statuses, shard);
 
-        info!(pois = pois.len(), "Finished tracking Pois");
+            match store.sg_deployment_signal_amounts().await {
+                Ok(signal_amounts) => {
+                    if shard.is_primary() {
+                        // Tiers only depend on the config and signal, so
+                        // recording those of all deployments at once agrees
+                        // with all shards.
+                        let result = async {
+                            let deployments = store.sg_deployments(Default::default()).await?;
+                            let check_tiers = assign_check_tiers(
+                                &config.check_tiers,
+                                deployments.iter().map(|deployment| &deployment.cid),
+                                &signal_amounts,
+                            );
+                            store.write_sg_deployment_check_tiers(&check_tiers).await
+                        }
+                        .await;
+                        if let Err(err) = result {
+                            error!(error = %err, "Failed to write check tiers to database");
+                        }
+                    }
+                    let check_tiers = assign_check_tiers(
+                        &config.check_tiers,
+                        indexing_statuses.iter().map(|status| &status.deployment),
+                        &signal_amounts,
+                    );
+                    retain_due_deployments(
+                        &mut indexing_statuses,
+                        &config.check_tiers,
+                        &check_tiers,
+                        round_id,
+                    );
+                }
+                Err(err) => error!(
+                    error = %err,
+                    "Failed to load curation signal, cross-checking all deployments this round"
+                ),
+            }
 
-        let write_err = store.write_pois(pois, PoiLiveness::Live).await.err();
-        if let Some(err) = write_err {
-            error!(error = %err, "Failed to write POIs to database");
+            let api_versions = match store.sg_deployments_with_api_versions().await {
+                Ok(known_deployments) => {
+                    query_subgraph_api_versions(
+                        &indexing_statuses,
+                        &known_deployments,
+                        &request_limits,
+                    )
+                    .await
+                }
+                Err(err) => {
+                    error!(
+                        error = %err,
+                        "Failed to load known subgraph API versions, not collecting any this round"
+                    );
+                    Default::default()
+                }
+            };
+
+            let deployment_networks = deployment_networks(&indexing_statuses);
+            // PoIs are stored on the network of their deployment.
+            if let Err(err) = store
+                .write_sg_deployment_networks(&deployment_networks)
+                .await
+            {
+                error!(error = %err, "Failed to write deployment networks to database");
+            }
+
+            info!("Monitor proofs of indexing");
+            let poi_requests = choose_poi_requests(&indexing_statuses, config.block_choice_policy);
+            let (pois, write_result) =
+                query_and_write_proofs_of_indexing(&store, &poi_requests, &request_limits).await;
+            let poi_request_outcomes = poi_request_outcomes(&poi_requests, &pois);
+
+            info!(pois = pois.len(), "Finished tracking Pois");
+
+            // PoIs on non-canonical blocks would be reported as bogus divergences.
+            let verification = verify_block_hashes(&config, &pois, &deployment_networks).await;
+            let comparable_pois = canonical_pois(&pois, &verification.canonical_by_hash);
+            // Neither are the PoIs of indexers that don't agree on the chain itself.
+            let chain_disagreements = chain_disagreements(&comparable_pois);
+            let comparable_pois =
+                without_chain_disagreements(&comparable_pois, &chain_disagreements);
+
+            // Without tags, notifiers that are restricted to some tags don't
+            // get this round's divergences, but all others still do.
+            let indexer_tags = match store.indexer_tags_by_address().await {
+                Ok(indexer_tags) => indexer_tags,
+                Err(err) => {
+                    error!(
+                        error = %err,
+                        "Failed to load indexer tags, routing notifications without them"
+                    );
+                    Default::default()
+                }
+            };
+            let divergence_events =
+                divergence_tracker.observe(&comparable_pois, &config, &indexer_tags);
+            send_notifications(&config, &divergence_events).await;
+
+            let investigation_requests = divergence_investigation_requests(
+                &config.auto_investigations,
+                &divergence_events,
+                &comparable_pois,
+            );
+
+            if let Err(err) = write_result {
+                error!(error = %err, "Failed to write POIs to database");
+            } else if let Err(err) = enqueue_divergence_investigations(
+                &store,
+                &config.auto_investigations,
+                investigation_requests,
+            )
+            .await
+            {
+                error!(error = %err, "Failed to launch divergence investigations");
+            }
+
+            if let Err(err) = store
+                .write_poi_request_outcomes(&poi_request_outcomes)
+                .await
+            {
+                error!(error = %err, "Failed to write PoI request outcomes to database");
+            }
+
+            if let Err(err) = store
+                .set_blocks_canonical(&verification.canonical_by_hash)
+                .await
+            {
+                error!(error = %err, "Failed to store block hash verification results");
+            }
+            let timestamps = block_timestamps(&config, &pois, &deployment_networks, &verification);
+            if let Err(err) = store.set_block_timestamps(&timestamps).await {
+                error!(error = %err, "Failed to store block timestamps");
+            }
+            if let Err(err) = store.write_chain_disagreements(&chain_disagreements).await {
+                error!(error = %err, "Failed to write chain disagreements to database");
+            }
+
+            // New deployments are only stored above, so API versions can only
+            // be written afterwards.
+            if let Err(err) = store.write_subgraph_api_versions(api_versions).await {
+                error!(error = %err, "Failed to write subgraph API versions to database");
+            }
+            if let Err(err) = store.write_entity_counts(&indexing_statuses).await {
+                error!(error = %err, "Failed to write entity counts to database");
+            }
+            if let Err(err) = store
+                .write_indexing_status_history(&indexing_statuses)
+                .await
+            {
+                error!(error = %err, "Failed to write indexing status history to database");
+            }
+
+            if shard.is_primary() {
+                let ipfs = IpfsClient::new(config.ipfs_url.clone());
+                if let Err(err) = fetch_subgraph_manifests(&store, &ipfs).await {
+                    error!(error = %err, "Failed to fetch subgraph manifests");
+                }
+            }
+
+            // Like indexers by address, closed allocations, disputes and deployment
+            // metadata are only looked up in the first network subgraph, and only
+            // by the primary shard.
+            let network_subgraphs = config.network_subgraphs();
+            let network_subgraph_config = network_subgraphs.first().filter(|_| shard.is_primary());
+            if let Some(network_subgraph_config) = network_subgraph_config {
+                let network_subgraph = NetworkSubgraphClient::new(
+                    network_subgraph_config.endpoint.parse()?,
+                    metrics().public_proofs_of_indexing_requests.clone(),
+                );
+                if let Err(err) = allocation_poi_checker
+                    .check(&store, &network_subgraph)
+                    .await
+                {
+                    error!(error = %err, "Failed to compare allocation PoIs");
+                }
+                if let Err(err) = dispute_monitor.check(&store, &network_subgraph).await {
+                    error!(error = %err, "Failed to check disputes");
+                }
+                if let Err(err) = deployment_metadata_sync
+                    .refresh(&store, &network_subgraph)
+                    .await
+                {
+                    error!(error = %err, "Failed to refresh deployment metadata");
+                }
+            }
+
+            info!(
+                sleep_seconds = sleep_duration.as_secs(),
+                "Sleeping for a while before next main loop iteration"
+            );
+            tokio::time::sleep(sleep_duration).await;
+            Ok::<_, anyhow::Error>(())
         }
+        .instrument(info_span!("round", round_id))
+        .await?;
+    }
+}
 
-        info!(
-            sleep_seconds = sleep_duration.as_secs(),
-            "Sleeping for a while before next main loop iteration"
-        );
-        tokio::time::sleep(sleep_duration).await;
+/// Waits until this replica becomes the leader of its shard, i.e. until no
+/// other replica runs its collection rounds and other singleton jobs. The
+/// configuration served by the API is kept up to date in the meantime.
+async fn wait_for_leadership(
+    store: &Store,
+    shard: Shard,
+    config_sender: &watch::Sender<Config>,
+) -> anyhow::Result<LeaderLock> {
+    let mut logged = false;
+    loop {
+        if let Some(lock) = store.try_acquire_leader_lock(shard.index()).await? {
+            return Ok(lock);
+        }
+        if !logged {
+            info!("Another replica is the leader, only serving the API until it steps down");
+            logged = true;
+        }
+
+        tokio::time::sleep(LEADER_ELECTION_INTERVAL).await;
+        match load_config(store).await {
+            Ok(config) => {
+                config_sender.send(config).ok();
+            }
+            Err(err) => error!(error = %err, "Failed to reload configuration"),
+        }
     }
 }
 
-fn init_tracing() {
+fn init_tracing(log_format: LogFormat) {
     use tracing_subscriber::prelude::*;
     use tracing_subscriber::{fmt, EnvFilter};
 
+    let fmt_layer = match log_format {
+        LogFormat::Pretty => fmt::layer().boxed(),
+        // Event fields such as `indexer` and `deployment` are top-level keys,
+        // and those of the enclosing spans, e.g. the round ID, are listed
+        // under `spans`.
+        LogFormat::Json => fmt::layer().json().flatten_event(true).boxed(),
+    };
     tracing_subscriber::registry()
-        .with(fmt::layer())
+        .with(fmt_layer)
         .with(
             EnvFilter::from_str(
                 &env::var("RUST_LOG").unwrap_or_else(|_| "graphix=debug".to_string()),
@@ -0,0 +1,87 @@
+//! Cross-checks the PoIs that indexers submit on chain when closing
+//! allocations against the PoIs Graphix collected from their status APIs,
+//! which surfaces indexers that submit PoIs their own `graph-node` disagrees
+//! with.
+
+use chrono::{DateTime, Utc};
+use graphix_network_sg_client::NetworkSubgraphClient;
+use graphix_store::models::{AllocationPoi, SubmittedAllocationPoi};
+use graphix_store::Store;
+use tracing::*;
+
+/// How far back to look for closed allocations on the first check, if none
+/// were stored before.
+const INITIAL_LOOKBACK_IN_SECONDS: i64 = 24 * 60 * 60;
+
+/// Upper limit on the number of allocations fetched per check.
+const MAX_ALLOCATIONS_PER_CHECK: u32 = 1000;
+
+/// Periodically fetches newly closed allocations from a network subgraph.
+#[derive(Debug, Default)]
+pub struct AllocationPoiChecker {
+    /// UNIX timestamp of the most recently closed allocation seen so far.
+    closed_after: Option<i64>,
+}
+
+impl AllocationPoiChecker {
+    /// Compares the PoIs of all allocations closed since the last check and
+    /// stores the results, returning the newly stored ones.
+    #[instrument(skip_all)]
+    pub async fn check(
+        &mut self,
+        store: &Store,
+        network_subgraph: &NetworkSubgraphClient,
+    ) -> anyhow::Result<Vec<AllocationPoi>> {
+        let closed_after = match self.closed_after {
+            Some(closed_after) => closed_after,
+            None => match store.latest_allocation_poi_closed_at().await? {
+                Some(closed_at) => closed_at.and_utc().timestamp(),
+                None => Utc::now().timestamp() - INITIAL_LOOKBACK_IN_SECONDS,
+            },
+        };
+
+        let allocations = network_subgraph
+            .closed_allocations(closed_after, Some(MAX_ALLOCATIONS_PER_CHECK))
+            .await?;
+        self.closed_after = Some(
+            allocations
+                .iter()
+                .map(|allocation| allocation.closed_at)
+                .fold(closed_after, i64::max),
+        );
+
+        let submitted_pois: Vec<_> = allocations
+            .into_iter()
+            .filter_map(|allocation| {
+                Some(SubmittedAllocationPoi {
+                    deployment: allocation.deployment.parse().ok()?,
+                    closed_at_epoch: allocation.closed_at_epoch.try_into().ok()?,
+                    block_number: allocation.epoch_start_block.try_into().ok()?,
+                    closed_at: DateTime::from_timestamp(allocation.closed_at, 0)?.naive_utc(),
+                    allocation_id: allocation.id,
+                    indexer_address: allocation.indexer,
+                    poi: allocation.poi,
+                })
+            })
+            .collect();
+        let allocation_pois = store.write_allocation_pois(&submitted_pois).await?;
+
+        for allocation_poi in &allocation_pois {
+            if allocation_poi.matches == Some(false) {
+                warn!(
+                    allocation_id = %allocation_poi.allocation_id,
+                    indexer_id = allocation_poi.indexer_id,
+                    block_number = allocation_poi.block_number,
+                    "Indexer submitted a PoI that differs from its status API"
+                );
+            }
+        }
+        info!(
+            allocations = submitted_pois.len(),
+            compared = allocation_pois.len(),
+            "Finished checking allocation PoIs"
+        );
+
+        Ok(allocation_pois)
+    }
+}
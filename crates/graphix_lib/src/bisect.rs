@@ -1,24 +1,51 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::hash::Hash;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Context as _};
 use graphix_common_types::{
-    BisectionReport, BisectionRunReport, DivergenceBlockBounds, DivergenceInvestigationReport,
-    DivergenceInvestigationStatus, DivergingBlock as DivergentBlock, HexString, PartialBlock,
-    PoiBytes,
+    BisectionEvidenceKind, BisectionReport, BisectionRunReport, BlockHash, CohortBisectionReport,
+    CohortBisectionStep, DivergenceBlockBounds, DivergenceInvestigationProgress,
+    DivergenceInvestigationReport, DivergenceInvestigationStatus, DivergingBlock as DivergentBlock,
+    HexString, IndexerAddress, IndexerProbeError, IpfsCid, ManifestFlag, PartialBlock, PoiBytes,
+    PoiCohort, ReportSignature,
 };
-use graphix_indexer_client::{IndexerClient, IndexerId, PoiRequest, ProofOfIndexing};
-use graphix_store::models::DivergenceInvestigationRequest;
-use graphix_store::Store;
+use graphix_indexer_client::{
+    BlockPointer, IndexerClient, IndexerId, PoiRequest, ProofOfIndexing, RawResponseCapture,
+};
+use graphix_store::models::{
+    DivergenceInvestigationJob, DivergenceInvestigationRequest, IntId, NewBisectionAttempt,
+    NewBisectionEvidence, NewBisectionRun, Poi,
+};
+use graphix_store::{PoiLiveness, PoiStore, ReportStore, Store};
 use thiserror::Error;
 use tokio::sync::watch;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+use crate::bisection_engine::{self, BisectionBounds, BisectionError, PoiOracle, Probe};
+use crate::config::{is_evm_chain, AutoInvestigationConfig};
+use crate::fork_tree::poi_clusters;
 use crate::graphql_api::api_types::{self, Indexer};
 use crate::graphql_api::GraphixState;
+use crate::ipfs::manifest_flags;
+use crate::notifications::{DivergenceEvent, DivergenceEventKind};
+use crate::signing::Signer;
+use crate::{metrics, IndexerQueryKind};
+
+// The number of bisections is quadratic to the number of Pois, so it's
+// important not to allow too many in a single request.
+const MAX_NUMBER_OF_POIS_PER_REQUEST: u32 = 4;
+// A cohort bisection is a single bisection run, no matter the number of Pois.
+const MAX_NUMBER_OF_POIS_PER_COHORT_REQUEST: u32 = 16;
+
+/// How many times a divergence investigation job is attempted before it's
+/// given up on.
+pub const MAX_INVESTIGATION_ATTEMPTS: i32 = 3;
+/// The delay before the first retry of a failed divergence investigation
+/// job, which doubles with every further attempt.
+const INVESTIGATION_RETRY_DELAY: Duration = Duration::from_secs(60);
 
 pub struct DivergingBlock {
     pub poi1: ProofOfIndexing,
@@ -39,6 +66,7 @@ impl From<DivergingBlock> for DivergentBlock {
 }
 
 pub struct PoiBisectingContext {
+    store: Store,
     report: BisectionRunReport,
     bisection_id: Uuid,
     poi1_data: PoiWithRelatedData,
@@ -47,6 +75,7 @@ pub struct PoiBisectingContext {
 
 impl PoiBisectingContext {
     fn new(
+        store: Store,
         report: BisectionRunReport,
         bisection_id: Uuid,
         poi1_data: PoiWithRelatedData,
@@ -66,6 +95,7 @@ impl PoiBisectingContext {
         //anyhow::ensure!(poi1.indexer.address() != poi2.indexer.address());
 
         Ok(Self {
+            store,
             report,
             bisection_id,
             poi1_data,
@@ -77,7 +107,12 @@ impl PoiBisectingContext {
         &self.poi1_data.deployment
     }
 
-    pub async fn start(mut self) -> (BisectionRunReport, u64) {
+    async fn start(
+        mut self,
+        req: &DivergenceInvestigationRequest,
+        progress: &mut ProgressTracker<'_>,
+        ctx: &GraphixState,
+    ) -> BisectionRunRecord {
         let deployment: api_types::SubgraphDeployment = self.deployment().clone();
 
         let indexer1 = self.poi1_data.indexer_client.clone();
@@ -89,64 +124,362 @@ impl PoiBisectingContext {
             "Starting Poi bisecting"
         );
 
-        // The range of block numbers that we're investigating is bounded
-        // inclusively both below and above. The bisection algorithm will
-        // continue searching until only a single block number is left in the
-        // range.
-        let mut bounds = 0..=self.poi1_data.block.number();
+        let mut oracle = PairwiseOracle {
+            store: &self.store,
+            bisection_id: &self.bisection_id,
+            deployment: deployment.cid(),
+            indexers: [&indexer1, &indexer2],
+            responses: vec![],
+            report: &mut self.report,
+            progress,
+        };
+        let bisection =
+            bisection_engine::bisect(&mut oracle, &[0, 1], self.poi1_data.block.number()).await;
+        let evidence = match bisection {
+            Ok(bisection) => {
+                self.collect_evidence(bisection.bounds.upper, req, ctx)
+                    .await
+            }
+            Err(err) => {
+                self.report.error = Some(DivergenceInvestigationError::from(err).to_string());
+                vec![]
+            }
+        };
 
-        loop {
-            let block_number = (bounds.start() + bounds.end()) / 2;
+        BisectionRunRecord {
+            sg_deployment_id: Some(deployment.id()),
+            indexer1_id: Some(self.poi1_data.indexer.id()),
+            indexer2_id: Some(self.poi2_data.indexer.id()),
+            evidence,
+            report: self.report,
+        }
+    }
 
-            debug!(
-                bisection_id = %self.bisection_id,
-                deployment = ?deployment.cid(),
-                lower_bound = ?bounds.start(),
-                upper_bound = ?bounds.end(),
-                block_number,
-                "Bisecting Pois"
-            );
+    /// Queries both indexers for the `graph-node` debugging data that was
+    /// requested, at the diverging block. Failures are logged and skipped.
+    async fn collect_evidence(
+        &self,
+        block_number: u64,
+        req: &DivergenceInvestigationRequest,
+        ctx: &GraphixState,
+    ) -> Vec<NewBisectionEvidence> {
+        let deployment = self.deployment();
+        let (network, is_evm) = match deployment.network(ctx).await {
+            Ok(network) => (network.name().to_string(), is_evm_chain(network.caip2())),
+            Err(err) => {
+                warn!(bisection_id = %self.bisection_id, error = %err, "Failed to load network");
+                return vec![];
+            }
+        };
 
-            let poi1 = indexer1
-                .clone()
-                .proof_of_indexing(PoiRequest {
-                    deployment: deployment.cid().clone(),
-                    block_number,
-                })
-                .await;
-            let poi2 = indexer2
-                .clone()
-                .proof_of_indexing(PoiRequest {
-                    deployment: deployment.cid().clone(),
-                    block_number,
-                })
-                .await;
-
-            let bisect = BisectionReport {
-                block: PartialBlock {
-                    number: block_number as _,
-                    hash: None,
-                },
-                indexer1_response: format!("{:?}", poi1),
-                indexer2_response: format!("{:?}", poi2),
+        let mut evidence = vec![];
+        for poi_data in [&self.poi1_data, &self.poi2_data] {
+            let indexer = &poi_data.indexer_client;
+            let block_hash = match self.poi(indexer, deployment.cid(), block_number).await {
+                Ok(poi) => poi.block.hash,
+                Err(_) => None,
             };
-            self.report.bisects.push(bisect);
-
-            if poi1.ok() == poi2.ok() {
-                bounds = block_number..=*bounds.end();
-                self.report.divergence_block_bounds.lower_bound.number = block_number as _;
-            } else {
-                bounds = *bounds.start()..=block_number;
-                self.report.divergence_block_bounds.upper_bound.number = block_number as _;
+
+            let mut contents = vec![];
+            if req.query_entity_changes {
+                let start = Instant::now();
+                let entity_changes = indexer
+                    .clone()
+                    .entity_changes(&deployment.cid().to_string(), block_number)
+                    .await;
+                metrics().observe_response_time(
+                    &indexer.metrics_label(),
+                    IndexerQueryKind::EntityChanges,
+                    start.elapsed(),
+                );
+                let entity_changes = entity_changes.map(|changes| {
+                    serde_json::json!({
+                        "updates": changes.updates,
+                        "deletions": changes.deletions,
+                    })
+                });
+                contents.push((BisectionEvidenceKind::EntityChanges, entity_changes));
+            }
+            if let Some(block_hash) = &block_hash {
+                // Only EVM chains have an `eth_call` cache.
+                if req.query_eth_call_caches && is_evm {
+                    let eth_calls = indexer
+                        .clone()
+                        .cached_eth_calls(&network, &block_hash.0)
+                        .await
+                        .map(|calls| {
+                            calls
+                                .into_iter()
+                                .map(|call| {
+                                    serde_json::json!({
+                                        "idHash": hex::encode(call.id_hash),
+                                        "contractAddress": hex::encode(call.contract_address),
+                                        "returnValue": hex::encode(call.return_value),
+                                    })
+                                })
+                                .collect()
+                        });
+                    contents.push((BisectionEvidenceKind::EthCallCache, eth_calls));
+                }
+                if req.query_block_caches {
+                    let block_cache = indexer
+                        .clone()
+                        .block_cache_contents(&network, &block_hash.0)
+                        .await
+                        .map(|contents| contents.unwrap_or_default());
+                    contents.push((BisectionEvidenceKind::BlockCache, block_cache));
+                }
             }
 
-            if bounds.start() == bounds.end() {
-                break;
+            for (kind, result) in contents {
+                match result {
+                    Ok(contents) => evidence.push(NewBisectionEvidence {
+                        bisection_run_uuid: self.report.uuid,
+                        indexer_id: poi_data.indexer.id(),
+                        block_number: block_number as _,
+                        kind,
+                        contents,
+                    }),
+                    Err(err) => warn!(
+                        bisection_id = %self.bisection_id,
+                        indexer = %indexer.address_string(),
+                        ?kind,
+                        error = %err,
+                        "Failed to collect bisection evidence"
+                    ),
+                }
             }
         }
 
-        let diverging_block = *bounds.start();
-        (self.report, diverging_block)
+        evidence
+    }
+
+    async fn poi(
+        &self,
+        indexer: &Arc<dyn IndexerClient>,
+        deployment: &IpfsCid,
+        block_number: u64,
+    ) -> anyhow::Result<ProofOfIndexing> {
+        bisection_poi(
+            &self.store,
+            &self.bisection_id,
+            indexer,
+            deployment,
+            block_number,
+        )
+        .await
+    }
+}
+
+/// Returns the PoI of `indexer` for the given deployment and block. PoIs that
+/// were already collected are reused from the database; PoIs that must be
+/// fetched from the indexer are persisted for future investigations.
+async fn bisection_poi(
+    store: &dyn PoiStore,
+    bisection_id: &Uuid,
+    indexer: &Arc<dyn IndexerClient>,
+    deployment: &IpfsCid,
+    block_number: u64,
+) -> anyhow::Result<ProofOfIndexing> {
+    match store
+        .indexer_poi_at_block(indexer, deployment, block_number)
+        .await
+    {
+        Ok(Some((poi, block))) => {
+            debug!(
+                %bisection_id,
+                indexer = %indexer.address_string(),
+                block_number,
+                "Reusing stored Poi"
+            );
+            return Ok(ProofOfIndexing {
+                indexer: indexer.clone(),
+                deployment: deployment.clone(),
+                block: BlockPointer {
+                    number: block_number,
+                    hash: block.hash,
+                },
+                proof_of_indexing: poi.poi,
+            });
+        }
+        Ok(None) => {}
+        Err(err) => {
+            warn!(
+                %bisection_id,
+                error = %err,
+                "Failed to look up stored Poi"
+            );
+        }
+    }
+
+    let poi = indexer
+        .clone()
+        .proof_of_indexing(PoiRequest {
+            deployment: deployment.clone(),
+            block_number,
+        })
+        .await?;
+
+    if let Err(err) = store
+        .write_pois(vec![poi.clone()], PoiLiveness::NotLive)
+        .await
+    {
+        warn!(
+            %bisection_id,
+            error = %err,
+            "Failed to persist Poi fetched during bisection"
+        );
+    }
+
+    Ok(poi)
+}
+
+/// Answers the PoI queries of a bisection run between two indexers, and
+/// records each probed block in the run's report.
+struct PairwiseOracle<'a, 'b> {
+    store: &'a Store,
+    bisection_id: &'a Uuid,
+    deployment: &'a IpfsCid,
+    indexers: [&'a Arc<dyn IndexerClient>; 2],
+    /// The responses of the indexers at the block that's being probed.
+    responses: Vec<(usize, anyhow::Result<ProofOfIndexing>)>,
+    report: &'a mut BisectionRunReport,
+    progress: &'a mut ProgressTracker<'b>,
+}
+
+impl PoiOracle for PairwiseOracle<'_, '_> {
+    type Participant = usize;
+
+    async fn poi(&mut self, participant: &usize, block_number: u64) -> Option<PoiBytes> {
+        let poi = bisection_poi(
+            self.store,
+            self.bisection_id,
+            self.indexers[*participant],
+            self.deployment,
+            block_number,
+        )
+        .await;
+        let poi_bytes = poi.as_ref().ok().map(|poi| poi.proof_of_indexing);
+        self.responses.push((*participant, poi));
+        poi_bytes
+    }
+
+    async fn probed(&mut self, probe: &Probe<usize>, bounds: &BisectionBounds) {
+        debug!(
+            bisection_id = %self.bisection_id,
+            deployment = ?self.deployment,
+            lower_bound = bounds.lower,
+            upper_bound = bounds.upper,
+            block_number = probe.block_number,
+            "Bisecting Pois"
+        );
+
+        let responses = std::mem::take(&mut self.responses);
+        let block_hash = responses
+            .iter()
+            .find_map(|(_, poi)| poi.as_ref().ok()?.block.hash.clone());
+        let response = |participant: usize| {
+            responses
+                .iter()
+                .find(|(other, _)| *other == participant)
+                .map(|(_, poi)| format!("{:?}", poi))
+                .unwrap_or_default()
+        };
+        self.report.bisects.push(BisectionReport {
+            block: PartialBlock {
+                number: probe.block_number as _,
+                hash: block_hash,
+            },
+            indexer1_response: response(0),
+            indexer2_response: response(1),
+        });
+        let block_bounds = &mut self.report.divergence_block_bounds;
+        block_bounds.lower_bound.number = bounds.lower as _;
+        block_bounds.upper_bound.number = bounds.upper as _;
+
+        let errors = indexer_probe_errors(
+            probe.block_number,
+            responses
+                .iter()
+                .map(|(participant, poi)| (self.indexers[*participant], poi)),
+        );
+        self.progress
+            .probed(&self.report.divergence_block_bounds, errors)
+            .await;
+    }
+}
+
+/// Answers the PoI queries of a cohort bisection, and records each probed
+/// block in its report. Participants are indices into `members`.
+struct CohortOracle<'a, 'b> {
+    store: &'a Store,
+    members: &'a [PoiWithRelatedData],
+    /// The responses and errors of the members at the block that's being
+    /// probed.
+    responses: Vec<(IndexerAddress, Option<PoiBytes>)>,
+    errors: Vec<IndexerProbeError>,
+    block_hash: Option<BlockHash>,
+    report: &'a mut CohortBisectionReport,
+    progress: &'a mut ProgressTracker<'b>,
+}
+
+impl PoiOracle for CohortOracle<'_, '_> {
+    type Participant = usize;
+
+    async fn poi(&mut self, participant: &usize, block_number: u64) -> Option<PoiBytes> {
+        let member = &self.members[*participant];
+        let poi = bisection_poi(
+            self.store,
+            &self.report.uuid,
+            &member.indexer_client,
+            member.deployment.cid(),
+            block_number,
+        )
+        .await;
+        self.errors.extend(indexer_probe_errors(
+            block_number,
+            [(&member.indexer_client, &poi)],
+        ));
+
+        let poi = poi.ok();
+        if self.block_hash.is_none() {
+            self.block_hash = poi.as_ref().and_then(|poi| poi.block.hash.clone());
+        }
+        let poi_bytes = poi.map(|poi| poi.proof_of_indexing);
+        self.responses.push((member.indexer.address(), poi_bytes));
+        poi_bytes
+    }
+
+    async fn probed(&mut self, probe: &Probe<usize>, bounds: &BisectionBounds) {
+        debug!(
+            bisection_id = %self.report.uuid,
+            lower_bound = bounds.lower,
+            upper_bound = bounds.upper,
+            block_number = probe.block_number,
+            "Bisecting indexer cohorts"
+        );
+
+        let step = CohortBisectionStep {
+            block: PartialBlock {
+                number: probe.block_number as _,
+                hash: self.block_hash.take(),
+            },
+            cohorts: agreement_cohorts(std::mem::take(&mut self.responses)),
+        };
+        // The first probe confirms the upper bound, all others move either
+        // bound.
+        if probe.block_number == bounds.upper {
+            self.report.divergence_block_bounds.upper_bound = step.block.clone();
+            self.report.diverging_cohorts = step.cohorts.clone();
+        } else {
+            self.report.divergence_block_bounds.lower_bound = step.block.clone();
+        }
+        self.report.bisects.push(step);
+
+        let errors = std::mem::take(&mut self.errors);
+        self.progress
+            .probed(&self.report.divergence_block_bounds, errors)
+            .await;
     }
 }
 
@@ -174,61 +507,354 @@ pub enum DivergenceInvestigationError {
         poi1_block: i64,
         poi2_block: i64,
     },
+    #[error("Cohort bisection needs Pois from at least two different indexers")]
+    TooFewIndexers,
+    #[error("The indexers agree at block {block}, there's no divergence to bisect")]
+    NoDivergence { block: i64 },
+    #[error("The investigation was cancelled")]
+    Cancelled,
+    #[error("The investigation was cancelled after exceeding its time limit of {seconds} seconds")]
+    TimedOut { seconds: u64 },
+    #[error("The investigation failed after {attempts} attempts: {error}")]
+    Failed { attempts: i32, error: String },
     #[error(transparent)]
     Database(anyhow::Error),
 }
 
+impl From<BisectionError> for DivergenceInvestigationError {
+    fn from(err: BisectionError) -> Self {
+        match err {
+            BisectionError::TooFewParticipants => Self::TooFewIndexers,
+            BisectionError::NoDivergence { block_number } => Self::NoDivergence {
+                block: block_number as _,
+            },
+        }
+    }
+}
+
+/// Works through the divergence investigation job queue, one job at a time.
+/// Failed attempts are retried with a delay, up to
+/// [`MAX_INVESTIGATION_ATTEMPTS`] times, after which the job is
+/// dead-lettered.
 pub async fn handle_divergence_investigation_requests(
     store: &Store,
     indexers: watch::Receiver<Vec<Arc<dyn IndexerClient>>>,
     ctx: &GraphixState,
 ) -> anyhow::Result<()> {
+    let worker = worker_id();
     loop {
         debug!("Checking for new divergence investigation requests");
 
-        let (req_uuid, req_contents_blob) = {
-            loop {
-                let req_opt = store
-                    .get_first_pending_divergence_investigation_request()
-                    .await?;
-                if let Some(req) = req_opt {
-                    break req;
-                } else {
-                    tokio::time::sleep(Duration::from_secs(3)).await;
-                    continue;
+        let job = loop {
+            // Investigations time out, so jobs that have been running for
+            // much longer than that lost their worker, e.g. to a crash.
+            let stale_after =
+                Duration::from_secs(ctx.config().investigation_timeout_in_seconds) * 2;
+            match store
+                .requeue_stale_divergence_investigation_jobs(
+                    stale_after,
+                    MAX_INVESTIGATION_ATTEMPTS,
+                )
+                .await
+            {
+                Ok(0) => {}
+                Ok(jobs) => warn!(
+                    jobs,
+                    "Requeued or dead-lettered stale divergence investigation jobs"
+                ),
+                Err(err) => {
+                    warn!(error = %err, "Failed to requeue stale divergence investigation jobs")
                 }
             }
+            match store.claim_divergence_investigation_job(&worker).await {
+                Ok(Some(job)) => break job,
+                Ok(None) => {}
+                Err(err) => warn!(error = %err, "Failed to claim divergence investigation job"),
+            }
+            tokio::time::sleep(Duration::from_secs(3)).await;
         };
-        debug!(?req_uuid, "Found new divergence investigation request");
-
-        let req_contents =
-            serde_json::from_value(req_contents_blob).expect("invalid request blob; this is a bug");
-        let report = handle_divergence_investigation_request(
-            store,
-            &req_uuid,
-            req_contents,
-            indexers.clone(),
-            ctx,
-        )
-        .await;
-
-        let serialized_report = serde_json::to_value(&report).unwrap();
+        let req_uuid = job.uuid;
         debug!(
             ?req_uuid,
-            "Writing divergence investigation report to database"
+            attempt = job.attempts,
+            "Found new divergence investigation request"
         );
-        store
-            .create_or_update_divergence_investigation_report(&req_uuid, serialized_report)
+
+        // The job is only acknowledged once its report is stored, so that
+        // it's processed at least once even if this worker dies midway.
+        match run_divergence_investigation_job(store, &job, indexers.clone(), ctx).await {
+            Ok(()) => match store
+                .ack_divergence_investigation_job(&req_uuid, &worker)
+                .await
+            {
+                Ok(true) => {}
+                Ok(false) => {
+                    warn!(
+                        ?req_uuid,
+                        "Divergence investigation job was taken over by another worker"
+                    )
+                }
+                Err(err) => {
+                    error!(?req_uuid, error = %err, "Failed to acknowledge divergence investigation job")
+                }
+            },
+            Err(err) => nack_divergence_investigation_job(store, &worker, &job, err).await,
+        }
+    }
+}
+
+/// Identifies this process in the jobs it claims.
+fn worker_id() -> String {
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "graphix".to_string());
+    format!("{host}:{}", std::process::id())
+}
+
+async fn run_divergence_investigation_job(
+    store: &Store,
+    job: &DivergenceInvestigationJob,
+    indexers: watch::Receiver<Vec<Arc<dyn IndexerClient>>>,
+    ctx: &GraphixState,
+) -> anyhow::Result<()> {
+    let req_uuid = job.uuid;
+    let req_contents: DivergenceInvestigationRequest =
+        serde_json::from_value(job.request.clone()).context("invalid request")?;
+    let timeout = Duration::from_secs(ctx.config().investigation_timeout_in_seconds);
+    let capture = (req_contents.capture_raw_responses
+        || ctx.config().capture_raw_indexer_responses)
+        .then(RawResponseCapture::default);
+    let investigation =
+        handle_divergence_investigation_request(store, &req_uuid, req_contents, indexers, ctx);
+    let investigation = async {
+        match &capture {
+            Some(capture) => capture.scope(investigation).await,
+            None => investigation.await,
+        }
+    };
+    let mut report = tokio::select! {
+        report = investigation => report,
+        cancellation = tokio::time::timeout(timeout, cancellation_requested(store, &req_uuid)) => {
+            let error = match cancellation {
+                Ok(()) => DivergenceInvestigationError::Cancelled,
+                Err(_) => DivergenceInvestigationError::TimedOut {
+                    seconds: timeout.as_secs(),
+                },
+            };
+            info!(?req_uuid, %error, "Aborted divergence investigation");
+            aborted_report(store, &req_uuid, DivergenceInvestigationStatus::Cancelled, error).await?
+        }
+    };
+
+    if report.status == DivergenceInvestigationStatus::Complete {
+        match ctx.config().report_signer() {
+            Ok(Some(signer)) => sign_report(&mut report, &signer),
+            Ok(None) => {}
+            Err(err) => {
+                warn!(?req_uuid, error = %err, "Failed to load the report signing key, storing the report unsigned")
+            }
+        }
+    }
+
+    let serialized_report = serde_json::to_value(&report).unwrap();
+    debug!(
+        ?req_uuid,
+        "Writing divergence investigation report to database"
+    );
+    store
+        .create_or_update_divergence_investigation_report(&req_uuid, serialized_report)
+        .await?;
+    // Responses captured before a cancellation are kept too.
+    if let Some(capture) = capture {
+        if let Err(err) = store
+            .write_raw_indexer_responses(&req_uuid, capture.take())
+            .await
+        {
+            warn!(?req_uuid, error = %err, "Failed to store raw indexer responses");
+        }
+    }
+
+    Ok(())
+}
+
+/// Signs the report as it is, without any earlier signature.
+fn sign_report(report: &mut DivergenceInvestigationReport, signer: &Signer) {
+    report.signature = None;
+    let payload = serde_json::to_string(report).unwrap();
+    report.signature = Some(ReportSignature {
+        signature: hex::encode(signer.sign(payload.as_bytes())),
+        public_key: signer.public_key(),
+        payload,
+    });
+}
+
+/// Schedules a retry of a failed divergence investigation job, or
+/// dead-letters it and reports the error once it's out of attempts.
+async fn nack_divergence_investigation_job(
+    store: &dyn ReportStore,
+    worker: &str,
+    job: &DivergenceInvestigationJob,
+    err: anyhow::Error,
+) {
+    let req_uuid = job.uuid;
+    let retry_at = (job.attempts < MAX_INVESTIGATION_ATTEMPTS).then(|| {
+        // 1, 2, 4... times the retry delay.
+        let delay = INVESTIGATION_RETRY_DELAY * 2u32.pow(job.attempts.max(1) as u32 - 1);
+        chrono::Utc::now().naive_utc() + chrono::Duration::from_std(delay).unwrap_or_default()
+    });
+    warn!(
+        ?req_uuid,
+        attempt = job.attempts,
+        retry = retry_at.is_some(),
+        error = %err,
+        "Divergence investigation failed"
+    );
+
+    if retry_at.is_none() {
+        let error = DivergenceInvestigationError::Failed {
+            attempts: job.attempts,
+            error: format!("{err:#}"),
+        };
+        let written = async {
+            let report = aborted_report(
+                store,
+                &req_uuid,
+                DivergenceInvestigationStatus::Complete,
+                error,
+            )
             .await?;
+            store
+                .create_or_update_divergence_investigation_report(
+                    &req_uuid,
+                    serde_json::to_value(report)?,
+                )
+                .await
+        }
+        .await;
+        if let Err(write_err) = written {
+            warn!(?req_uuid, error = %write_err, "Failed to report failed divergence investigation");
+        }
+    }
+    match store
+        .nack_divergence_investigation_job(&req_uuid, worker, &format!("{err:#}"), retry_at)
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            warn!(
+                ?req_uuid,
+                "Divergence investigation job was taken over by another worker"
+            )
+        }
+        Err(write_err) => {
+            error!(?req_uuid, error = %write_err, "Failed to record failed divergence investigation attempt")
+        }
+    }
+}
+
+/// Resolves once the divergence investigation request with the given UUID is
+/// cancelled.
+async fn cancellation_requested(store: &dyn ReportStore, req_uuid: &Uuid) {
+    loop {
+        match store
+            .divergence_investigation_request_cancelled(req_uuid)
+            .await
+        {
+            Ok(true) => return,
+            Ok(false) => {}
+            Err(err) => {
+                warn!(?req_uuid, error = %err, "Failed to check for investigation cancellation")
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(3)).await;
+    }
+}
+
+/// The report of an investigation that was cut short, which keeps the
+/// bisection runs that were completed and stored before.
+async fn aborted_report(
+    store: &dyn ReportStore,
+    req_uuid: &Uuid,
+    status: DivergenceInvestigationStatus,
+    error: DivergenceInvestigationError,
+) -> anyhow::Result<DivergenceInvestigationReport> {
+    let partial_report: Option<DivergenceInvestigationReport> = store
+        .divergence_investigation_report(req_uuid)
+        .await?
+        .map(serde_json::from_value)
+        .transpose()?;
+    let mut report = partial_report.unwrap_or_else(|| DivergenceInvestigationReport {
+        uuid: *req_uuid,
+        status,
+        bisection_runs: vec![],
+        cohort_bisection: None,
+        error: None,
+        progress: None,
+        signature: None,
+    });
+    report.status = status;
+    report.error = Some(error.to_string());
+
+    Ok(report)
+}
+
+/// A [`BisectionRunReport`] together with everything else that is stored
+/// about the bisection run in the database.
+pub struct BisectionRunRecord {
+    pub report: BisectionRunReport,
+    pub sg_deployment_id: Option<IntId>,
+    pub indexer1_id: Option<IntId>,
+    pub indexer2_id: Option<IntId>,
+    pub evidence: Vec<NewBisectionEvidence>,
+}
+
+impl From<BisectionRunReport> for BisectionRunRecord {
+    fn from(report: BisectionRunReport) -> Self {
+        Self {
+            report,
+            sg_deployment_id: None,
+            indexer1_id: None,
+            indexer2_id: None,
+            evidence: vec![],
+        }
+    }
+}
+
+impl BisectionRunRecord {
+    async fn write(self, store: &dyn ReportStore, investigation_uuid: &Uuid) -> anyhow::Result<()> {
+        let report = self.report;
+        let run = NewBisectionRun {
+            uuid: report.uuid,
+            investigation_uuid: *investigation_uuid,
+            sg_deployment_id: self.sg_deployment_id,
+            indexer1_id: self.indexer1_id,
+            indexer2_id: self.indexer2_id,
+            poi1: report.poi1,
+            poi2: report.poi2,
+            lower_bound: report.divergence_block_bounds.lower_bound.number,
+            upper_bound: report.divergence_block_bounds.upper_bound.number,
+            error: report.error,
+        };
+        let attempts = report
+            .bisects
+            .into_iter()
+            .map(|bisect| NewBisectionAttempt {
+                bisection_run_uuid: report.uuid,
+                block_number: bisect.block.number,
+                block_hash: bisect.block.hash,
+                indexer1_response: bisect.indexer1_response,
+                indexer2_response: bisect.indexer2_response,
+            })
+            .collect();
+
         store
-            .delete_divergence_investigation_request(&req_uuid)
-            .await?;
+            .write_bisection_run(run, attempts, self.evidence)
+            .await
     }
 }
 
 /// Just a group of data related to a PoI, that is needed to perform a
 /// bisection.
 struct PoiWithRelatedData {
+    poi: PoiBytes,
     deployment: api_types::SubgraphDeployment,
     block: api_types::Block,
     indexer: Indexer,
@@ -246,6 +872,14 @@ impl PoiWithRelatedData {
             return Ok(None);
         };
 
+        Self::from_model(poi_model, indexers, ctx).await.map(Some)
+    }
+
+    async fn from_model(
+        poi_model: Poi,
+        indexers: &[Arc<dyn IndexerClient>],
+        ctx: &GraphixState,
+    ) -> anyhow::Result<Self> {
         let poi = api_types::ProofOfIndexing { model: poi_model };
 
         let deployment = poi
@@ -265,27 +899,31 @@ impl PoiWithRelatedData {
 
         let indexer_client = indexers
             .iter()
-            .find(|indexer| indexer.address() == indexer.address())
+            .find(|client| client.address() == indexer.address())
             .cloned()
             .ok_or_else(|| anyhow!("indexer not found"))?;
 
-        Ok(Some(Self {
+        Ok(Self {
+            poi: poi.model.poi,
             deployment,
             block,
             indexer,
             indexer_client,
-        }))
+        })
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_divergence_investigation_request_pair(
     store: &Store,
     indexers: &[Arc<dyn IndexerClient>],
     req_uuid: &Uuid,
     poi1_s: &PoiBytes,
     poi2_s: &PoiBytes,
+    req: &DivergenceInvestigationRequest,
+    progress: &mut ProgressTracker<'_>,
     ctx: &GraphixState,
-) -> BisectionRunReport {
+) -> BisectionRunRecord {
     debug!(?req_uuid, poi1 = %poi1_s, poi2 = %poi2_s, "Bisecting Pois");
 
     let mut report = BisectionRunReport {
@@ -303,30 +941,32 @@ async fn handle_divergence_investigation_request_pair(
                 hash: None,
             },
         },
+        manifest_flags: vec![],
         error: None,
     };
 
     debug!(?req_uuid, poi1 = %poi1_s, poi2 = %poi2_s, "Fetching Pois");
     let poi1_data = match PoiWithRelatedData::new(poi1_s, store, indexers, ctx).await {
         Ok(Some(data)) => data,
-        Ok(None) => return report,
+        Ok(None) => return report.into(),
         Err(err) => {
             report.error = Some(err.to_string());
-            return report;
+            return report.into();
         }
     };
     let poi2_data = match PoiWithRelatedData::new(poi2_s, store, indexers, ctx).await {
         Ok(Some(data)) => data,
-        Ok(None) => return report,
+        Ok(None) => return report.into(),
         Err(err) => {
             report.error = Some(err.to_string());
-            return report;
+            return report.into();
         }
     };
 
     debug!(?req_uuid, poi1 = %poi1_s, poi2 = %poi2_s, "Fetched Pois");
 
     report.divergence_block_bounds.upper_bound.number = poi1_data.block.number_i64();
+    report.manifest_flags = deployment_manifest_flags(store, &poi1_data.deployment).await;
 
     // Two PoIs need to relate to the same subgraph deployment to be comparable.
     if poi1_data.deployment.cid() != poi2_data.deployment.cid() {
@@ -360,18 +1000,170 @@ async fn handle_divergence_investigation_request_pair(
     if poi1_data.indexer.address() == poi2_data.indexer.address() {
         let indexer_id = poi1_data.indexer.address().to_string();
         report.error = Some(DivergenceInvestigationError::SameIndexer { indexer_id }.to_string());
-        return report;
+        return BisectionRunRecord {
+            sg_deployment_id: Some(poi1_data.deployment.id()),
+            indexer1_id: Some(poi1_data.indexer.id()),
+            indexer2_id: Some(poi2_data.indexer.id()),
+            ..report.into()
+        };
     }
 
     let bisection_uuid = Uuid::new_v4();
 
-    let context = PoiBisectingContext::new(report, bisection_uuid, poi1_data, poi2_data)
-        .expect("bisect context creation failed");
-    let (report, _block_num) = context.start().await;
+    let context =
+        PoiBisectingContext::new(store.clone(), report, bisection_uuid, poi1_data, poi2_data)
+            .expect("bisect context creation failed");
+    context.start(req, progress, ctx).await
+}
+
+/// Bisects all indexers that produced one of `pois` at once: at each probed
+/// block, the indexers are partitioned into cohorts that agree on the PoI, and
+/// the search continues below the block if there's more than one cohort.
+async fn handle_cohort_bisection_request(
+    store: &Store,
+    indexers: &[Arc<dyn IndexerClient>],
+    req_uuid: &Uuid,
+    pois: &[PoiBytes],
+    progress: &mut ProgressTracker<'_>,
+    ctx: &GraphixState,
+) -> CohortBisectionReport {
+    debug!(?req_uuid, pois = pois.len(), "Bisecting indexer cohorts");
+
+    let mut report = CohortBisectionReport {
+        uuid: Uuid::new_v4(),
+        indexers: vec![],
+        divergence_block_bounds: DivergenceBlockBounds {
+            lower_bound: PartialBlock {
+                number: 0,
+                hash: None,
+            },
+            upper_bound: PartialBlock {
+                number: 0,
+                hash: None,
+            },
+        },
+        diverging_cohorts: vec![],
+        bisects: vec![],
+        manifest_flags: vec![],
+        error: None,
+    };
+
+    let members = match cohort_members(store, indexers, pois, ctx).await {
+        Ok(members) => members,
+        Err(err) => {
+            report.error = Some(err.to_string());
+            return report;
+        }
+    };
+    let deployment = members[0].deployment.clone();
+    report.indexers = members
+        .iter()
+        .map(|member| member.indexer.address())
+        .collect();
+    report.manifest_flags = deployment_manifest_flags(store, &deployment).await;
+
+    // The investigated PoIs are at the upper bound, so they're already
+    // stored and the first probe only confirms that the indexers disagree.
+    let upper_bound = members[0].block.number();
+    let participants = (0..members.len()).collect::<Vec<_>>();
+    let mut oracle = CohortOracle {
+        store,
+        members: &members,
+        responses: vec![],
+        errors: vec![],
+        block_hash: None,
+        report: &mut report,
+        progress,
+    };
+    if let Err(err) = bisection_engine::bisect(&mut oracle, &participants, upper_bound).await {
+        report.error = Some(DivergenceInvestigationError::from(err).to_string());
+    }
 
     report
 }
 
+/// Looks up all PoIs with one of the given values and the indexers that
+/// produced them, which must all be for the same deployment and block.
+async fn cohort_members(
+    store: &Store,
+    indexers: &[Arc<dyn IndexerClient>],
+    pois: &[PoiBytes],
+    ctx: &GraphixState,
+) -> anyhow::Result<Vec<PoiWithRelatedData>> {
+    let mut members: Vec<PoiWithRelatedData> = vec![];
+    for poi_model in store.pois_by_value(pois).await? {
+        let member = PoiWithRelatedData::from_model(poi_model, indexers, ctx).await?;
+        if let Some(first) = members.first() {
+            if member.deployment.cid() != first.deployment.cid() {
+                return Err(DivergenceInvestigationError::DifferentDeployments {
+                    poi1: first.poi.to_string(),
+                    poi2: member.poi.to_string(),
+                    poi1_deployment: first.deployment.cid().to_string(),
+                    poi2_deployment: member.deployment.cid().to_string(),
+                }
+                .into());
+            }
+            if member.block.number() != first.block.number() {
+                return Err(DivergenceInvestigationError::DifferentBlocks {
+                    poi1: first.poi.to_string(),
+                    poi2: member.poi.to_string(),
+                    poi1_block: first.block.number_i64(),
+                    poi2_block: member.block.number_i64(),
+                }
+                .into());
+            }
+        }
+        // Indexers that reported the same PoI more than once only count once.
+        if !members
+            .iter()
+            .any(|other| other.indexer.address() == member.indexer.address())
+        {
+            members.push(member);
+        }
+    }
+
+    if members.len() < 2 {
+        return Err(DivergenceInvestigationError::TooFewIndexers.into());
+    }
+    Ok(members)
+}
+
+/// Partitions indexers into cohorts that reported the same PoI, largest
+/// cohort first. Indexers that failed to report a PoI form a cohort of their
+/// own.
+fn agreement_cohorts(
+    responses: impl IntoIterator<Item = (IndexerAddress, Option<PoiBytes>)>,
+) -> Vec<PoiCohort> {
+    bisection_engine::cohorts(responses)
+        .into_iter()
+        .map(|cohort| PoiCohort {
+            poi: cohort.poi,
+            indexers: cohort.participants,
+        })
+        .collect()
+}
+
+/// Returns the [`ManifestFlag`]s of the deployment's manifest, if it has
+/// been fetched already.
+async fn deployment_manifest_flags(
+    store: &Store,
+    deployment: &api_types::SubgraphDeployment,
+) -> Vec<ManifestFlag> {
+    let manifest = match store.subgraph_manifest(deployment.id()).await {
+        Ok(Some(manifest)) => manifest,
+        Ok(None) => return vec![],
+        Err(error) => {
+            warn!(deployment = %deployment.cid(), %error, "Failed to load subgraph manifest");
+            return vec![];
+        }
+    };
+
+    manifest_flags(&manifest.raw_manifest).unwrap_or_else(|error| {
+        warn!(deployment = %deployment.cid(), %error, "Failed to parse subgraph manifest");
+        vec![]
+    })
+}
+
 async fn handle_divergence_investigation_request(
     store: &Store,
     req_uuid: &Uuid,
@@ -383,46 +1175,312 @@ async fn handle_divergence_investigation_request(
         uuid: *req_uuid,
         status: DivergenceInvestigationStatus::Complete,
         bisection_runs: vec![],
+        cohort_bisection: None,
         error: None,
+        progress: None,
+        signature: None,
     };
 
-    // The number of bisections is quadratic to the number of Pois, so it's
-    // important not to allow too many in a single request.
-    const MAX_NUMBER_OF_POIS_PER_REQUEST: u32 = 4;
-
-    if req_contents.pois.len() > MAX_NUMBER_OF_POIS_PER_REQUEST as usize {
-        report.error = Some(
-            DivergenceInvestigationError::TooManyPois {
-                max: MAX_NUMBER_OF_POIS_PER_REQUEST,
-            }
-            .to_string(),
-        );
+    let max_pois = if req_contents.cohort_bisection {
+        MAX_NUMBER_OF_POIS_PER_COHORT_REQUEST
+    } else {
+        MAX_NUMBER_OF_POIS_PER_REQUEST
+    };
+    if req_contents.pois.len() > max_pois as usize {
+        report.error =
+            Some(DivergenceInvestigationError::TooManyPois { max: max_pois }.to_string());
         return report;
     }
 
     let indexers = indexers.borrow().clone();
 
-    let poi_pairs = unordered_pairs_combinations(req_contents.pois.into_iter());
+    if req_contents.cohort_bisection {
+        let mut progress = ProgressTracker::new(store, report, 1);
+        let cohort_bisection = handle_cohort_bisection_request(
+            store,
+            &indexers,
+            req_uuid,
+            &req_contents.pois,
+            &mut progress,
+            ctx,
+        )
+        .await;
+        progress.complete_bisection_run();
+        let mut report = progress.finish();
+        report.cohort_bisection = Some(cohort_bisection);
+        info!(?req_uuid, "Finished bisecting indexer cohorts");
+        return report;
+    }
+
+    let poi_pairs = unordered_pairs_combinations(req_contents.pois.iter().copied());
+    let mut progress = ProgressTracker::new(store, report, poi_pairs.len());
 
     for (poi1_s, poi2_s) in poi_pairs.into_iter() {
-        let bisection_run_report = handle_divergence_investigation_request_pair(
-            store, &indexers, req_uuid, &poi1_s, &poi2_s, ctx,
+        let bisection_run = handle_divergence_investigation_request_pair(
+            store,
+            &indexers,
+            req_uuid,
+            &poi1_s,
+            &poi2_s,
+            &req_contents,
+            &mut progress,
+            ctx,
         )
         .await;
         debug!(?req_uuid, poi1 = %poi1_s, poi2 = %poi2_s, "Finished bisection run");
-        report.bisection_runs.push(bisection_run_report);
-        let report_json = serde_json::to_value(&report).unwrap();
-        if let Err(err) = store
-            .create_or_update_divergence_investigation_report(req_uuid, report_json)
+        progress
+            .report
+            .bisection_runs
+            .push(bisection_run.report.clone());
+        progress.complete_bisection_run();
+        progress.write().await;
+        if let Err(err) = bisection_run.write(store, req_uuid).await {
+            error!(?req_uuid, error = %err, "Failed to write bisection run to the database");
+        }
+    }
+
+    info!(?req_uuid, "Finished bisecting Pois");
+
+    progress.finish()
+}
+
+/// The most recent indexer errors that are kept in the progress of an
+/// investigation.
+const MAX_PROGRESS_INDEXER_ERRORS: usize = 20;
+
+/// Keeps the report of an in-progress divergence investigation up to date in
+/// the database, so that its progress can be followed.
+struct ProgressTracker<'a> {
+    store: &'a Store,
+    report: DivergenceInvestigationReport,
+}
+
+impl<'a> ProgressTracker<'a> {
+    fn new(
+        store: &'a Store,
+        mut report: DivergenceInvestigationReport,
+        total_bisection_runs: usize,
+    ) -> Self {
+        report.status = DivergenceInvestigationStatus::InProgress;
+        report.progress = Some(DivergenceInvestigationProgress {
+            completed_bisection_runs: 0,
+            total_bisection_runs: total_bisection_runs as u32,
+            current_block_bounds: None,
+            blocks_probed: 0,
+            indexer_errors: vec![],
+            updated_at: chrono::Utc::now().naive_utc(),
+        });
+        Self { store, report }
+    }
+
+    fn progress(&mut self) -> &mut DivergenceInvestigationProgress {
+        self.report
+            .progress
+            .as_mut()
+            .expect("progress is set on creation")
+    }
+
+    /// Records a probed block and the errors of the indexers that were
+    /// probed, and persists the report.
+    async fn probed(
+        &mut self,
+        block_bounds: &DivergenceBlockBounds,
+        indexer_errors: Vec<IndexerProbeError>,
+    ) {
+        let progress = self.progress();
+        progress.current_block_bounds = Some(block_bounds.clone());
+        progress.blocks_probed += 1;
+        progress.indexer_errors.extend(indexer_errors);
+        let excess = progress
+            .indexer_errors
+            .len()
+            .saturating_sub(MAX_PROGRESS_INDEXER_ERRORS);
+        progress.indexer_errors.drain(..excess);
+        progress.updated_at = chrono::Utc::now().naive_utc();
+
+        self.write().await;
+    }
+
+    fn complete_bisection_run(&mut self) {
+        let progress = self.progress();
+        progress.completed_bisection_runs += 1;
+        progress.current_block_bounds = None;
+        progress.blocks_probed = 0;
+        progress.updated_at = chrono::Utc::now().naive_utc();
+    }
+
+    async fn write(&self) {
+        let report_json = serde_json::to_value(&self.report).unwrap();
+        if let Err(err) = self
+            .store
+            .create_or_update_divergence_investigation_report(&self.report.uuid, report_json)
             .await
         {
-            error!(?req_uuid, error = %err, "Failed to upsert divergence investigation report to the database");
+            error!(req_uuid = ?self.report.uuid, error = %err, "Failed to upsert divergence investigation report to the database");
         }
     }
 
-    info!(?req_uuid, "Finished bisecting Pois");
+    /// Returns the report of the concluded investigation.
+    fn finish(mut self) -> DivergenceInvestigationReport {
+        self.report.status = DivergenceInvestigationStatus::Complete;
+        self.report
+    }
+}
 
-    report
+/// Turns the failed PoI requests of a probed block into [`IndexerProbeError`]s.
+fn indexer_probe_errors<'a>(
+    block_number: u64,
+    results: impl IntoIterator<
+        Item = (
+            &'a Arc<dyn IndexerClient>,
+            &'a anyhow::Result<ProofOfIndexing>,
+        ),
+    >,
+) -> Vec<IndexerProbeError> {
+    results
+        .into_iter()
+        .filter_map(|(indexer, result)| {
+            let error = result.as_ref().err()?;
+            Some(IndexerProbeError {
+                indexer: indexer.address(),
+                block_number: block_number as _,
+                error: error.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Enqueues the given divergence investigation requests, as long as the
+/// number of pending requests stays within the configured budget. The PoIs
+/// they refer to must already be stored.
+pub async fn enqueue_divergence_investigations(
+    store: &dyn ReportStore,
+    config: &AutoInvestigationConfig,
+    requests: Vec<DivergenceInvestigationRequest>,
+) -> anyhow::Result<Vec<Uuid>> {
+    if requests.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let pending = store
+        .pending_divergence_investigation_requests_count()
+        .await?;
+    let budget = config.max_pending_requests.saturating_sub(pending) as usize;
+    if requests.len() > budget {
+        warn!(
+            skipped = requests.len() - budget,
+            max_pending_requests = config.max_pending_requests,
+            "Too many pending divergence investigations; not investigating some divergences"
+        );
+    }
+
+    let mut uuids = vec![];
+    for req in requests.into_iter().take(budget) {
+        let uuid = store
+            .create_divergence_investigation_request(serde_json::to_value(&req)?)
+            .await?;
+        info!(
+            ?uuid,
+            pois = req.pois.len(),
+            "Launched divergence investigation"
+        );
+        uuids.push(uuid);
+    }
+
+    Ok(uuids)
+}
+
+/// Builds a divergence investigation request out of the distinct PoIs at the
+/// diverging block of every [`DivergenceEventKind::Diverged`] event, unless
+/// automatic investigations are disabled.
+pub fn divergence_investigation_requests(
+    config: &AutoInvestigationConfig,
+    events: &[DivergenceEvent],
+    pois: &[ProofOfIndexing],
+) -> Vec<DivergenceInvestigationRequest> {
+    if !config.enabled {
+        return vec![];
+    }
+
+    events
+        .iter()
+        .filter_map(|event| {
+            let DivergenceEventKind::Diverged { block_number, .. } = event.kind else {
+                return None;
+            };
+
+            let mut distinct_pois = vec![];
+            for poi in pois {
+                if poi.deployment == event.deployment
+                    && poi.block.number == block_number
+                    && !distinct_pois.contains(&poi.proof_of_indexing)
+                {
+                    distinct_pois.push(poi.proof_of_indexing);
+                }
+            }
+            distinct_pois.truncate(MAX_NUMBER_OF_POIS_PER_REQUEST as usize);
+
+            (distinct_pois.len() > 1).then_some(DivergenceInvestigationRequest {
+                pois: distinct_pois,
+                query_block_caches: config.query_block_caches,
+                query_eth_call_caches: config.query_eth_call_caches,
+                query_entity_changes: config.query_entity_changes,
+                cohort_bisection: false,
+                capture_raw_responses: false,
+            })
+        })
+        .collect()
+}
+
+/// Builds a divergence investigation request for every subgraph deployment
+/// whose live PoIs disagree at the same block, out of the distinct PoIs at
+/// that block, most reported first. If `indexer_id` is given, only
+/// disagreements that involve that indexer are considered, and its PoI is
+/// always investigated. All other fields are taken from `template`.
+pub fn live_divergence_investigation_requests(
+    live_pois: &[Poi],
+    indexer_id: Option<IntId>,
+    template: &DivergenceInvestigationRequest,
+) -> Vec<DivergenceInvestigationRequest> {
+    let max_pois = if template.cohort_bisection {
+        MAX_NUMBER_OF_POIS_PER_COHORT_REQUEST
+    } else {
+        MAX_NUMBER_OF_POIS_PER_REQUEST
+    };
+
+    let mut pois_by_deployment_and_block: BTreeMap<_, Vec<&Poi>> = BTreeMap::new();
+    for poi in live_pois {
+        pois_by_deployment_and_block
+            .entry((poi.sg_deployment_id, poi.block_id))
+            .or_default()
+            .push(poi);
+    }
+
+    pois_by_deployment_and_block
+        .into_values()
+        .filter_map(|pois| {
+            let mut clusters = poi_clusters(pois);
+            if clusters.len() < 2 {
+                return None;
+            }
+            if let Some(indexer_id) = indexer_id {
+                let i = clusters
+                    .iter()
+                    .position(|(_, indexer_ids)| indexer_ids.contains(&indexer_id))?;
+                let cluster = clusters.remove(i);
+                clusters.insert(0, cluster);
+            }
+
+            Some(DivergenceInvestigationRequest {
+                pois: clusters
+                    .into_iter()
+                    .take(max_pois as usize)
+                    .map(|(poi, _)| poi)
+                    .collect(),
+                ..template.clone()
+            })
+        })
+        .collect()
 }
 
 /// Creates all combinations of elements in the iterator, without duplicates.
@@ -451,6 +1509,208 @@ mod unit_tests {
         );
     }
 
+    #[test]
+    fn divergence_investigation_requests_only_for_diverged_deployments() {
+        use crate::test_utils::mocks::MockIndexer;
+
+        let deployment: IpfsCid = "QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA"
+            .parse()
+            .unwrap();
+        let poi = |block_number: u64, poi_byte: u8| ProofOfIndexing {
+            indexer: Arc::new(MockIndexer {
+                name: format!("indexer-{}", poi_byte),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }),
+            deployment: deployment.clone(),
+            block: BlockPointer {
+                number: block_number,
+                hash: None,
+            },
+            proof_of_indexing: [poi_byte; 32].into(),
+        };
+        let pois = [poi(10, 1), poi(10, 2), poi(10, 2), poi(11, 3)];
+        let event = |kind| DivergenceEvent {
+            deployment: deployment.clone(),
+            tier: None,
+            indexer_tags: Default::default(),
+            kind,
+        };
+        let config = AutoInvestigationConfig {
+            enabled: true,
+            ..Default::default()
+        };
+
+        let requests = divergence_investigation_requests(
+            &config,
+            &[
+                event(DivergenceEventKind::Resolved),
+                event(DivergenceEventKind::Diverged {
+                    block_number: 10,
+                    distinct_pois: 2,
+                    indexers: 3,
+                }),
+            ],
+            &pois,
+        );
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].pois, vec![[1; 32].into(), [2; 32].into()]);
+
+        let config = AutoInvestigationConfig::default();
+        let events = [event(DivergenceEventKind::Diverged {
+            block_number: 10,
+            distinct_pois: 2,
+            indexers: 3,
+        })];
+        assert!(divergence_investigation_requests(&config, &events, &pois).is_empty());
+    }
+
+    #[test]
+    fn indexer_probe_errors_only_for_failures() {
+        use crate::test_utils::mocks::MockIndexer;
+
+        let indexer = |name: &str| -> Arc<dyn IndexerClient> {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            })
+        };
+        let (indexer1, indexer2) = (indexer("indexer-1"), indexer("indexer-2"));
+        let poi1 = Ok(ProofOfIndexing {
+            indexer: indexer1.clone(),
+            deployment: "QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA"
+                .parse()
+                .unwrap(),
+            block: BlockPointer {
+                number: 42,
+                hash: None,
+            },
+            proof_of_indexing: [1; 32].into(),
+        });
+        let poi2 = Err(anyhow::anyhow!("connection refused"));
+
+        let errors = indexer_probe_errors(42, [(&indexer1, &poi1), (&indexer2, &poi2)]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].indexer, indexer2.address());
+        assert_eq!(errors[0].block_number, 42);
+        assert_eq!(errors[0].error, "connection refused");
+    }
+
+    #[tokio::test]
+    async fn enqueued_divergence_investigations_are_limited() {
+        use graphix_store::InMemoryStore;
+
+        let store = InMemoryStore::new();
+        let config = AutoInvestigationConfig {
+            enabled: true,
+            max_pending_requests: 3,
+            ..Default::default()
+        };
+        let request = |poi_byte: u8| DivergenceInvestigationRequest {
+            pois: vec![[poi_byte; 32].into(), [poi_byte + 1; 32].into()],
+            query_block_caches: false,
+            query_eth_call_caches: false,
+            query_entity_changes: false,
+            cohort_bisection: false,
+            capture_raw_responses: false,
+        };
+
+        let uuids =
+            enqueue_divergence_investigations(&store, &config, vec![request(1), request(3)])
+                .await
+                .unwrap();
+        assert_eq!(uuids.len(), 2);
+
+        // Only one more fits before the limit is reached.
+        let uuids =
+            enqueue_divergence_investigations(&store, &config, vec![request(5), request(7)])
+                .await
+                .unwrap();
+        assert_eq!(uuids.len(), 1);
+        assert_eq!(
+            store
+                .pending_divergence_investigation_requests_count()
+                .await
+                .unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn live_divergence_investigation_requests_per_deployment() {
+        let poi = |sg_deployment_id: IntId, indexer_id: IntId, poi_byte: u8| Poi {
+            id: 0,
+            poi: [poi_byte; 32].into(),
+            sg_deployment_id,
+            indexer_id,
+            block_id: 0,
+            created_at: Default::default(),
+        };
+        let live_pois = [
+            // Deployment 1 diverges, and indexer 3 is in the minority.
+            poi(1, 1, 1),
+            poi(1, 2, 1),
+            poi(1, 3, 2),
+            // All indexers agree on deployment 2.
+            poi(2, 1, 3),
+            poi(2, 2, 3),
+            // Deployment 3 diverges without indexer 3.
+            poi(3, 1, 4),
+            poi(3, 2, 5),
+        ];
+        let template = DivergenceInvestigationRequest {
+            pois: vec![],
+            query_block_caches: false,
+            query_eth_call_caches: false,
+            query_entity_changes: false,
+            cohort_bisection: false,
+            capture_raw_responses: false,
+        };
+
+        let requests = live_divergence_investigation_requests(&live_pois, None, &template);
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].pois, vec![[1; 32].into(), [2; 32].into()]);
+        assert_eq!(requests[1].pois, vec![[4; 32].into(), [5; 32].into()]);
+
+        let requests = live_divergence_investigation_requests(&live_pois, Some(3), &template);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].pois, vec![[2; 32].into(), [1; 32].into()]);
+    }
+
+    #[test]
+    fn agreement_cohorts_largest_first() {
+        let indexer = |byte: u8| -> IndexerAddress { [byte; 20].into() };
+        let poi = |byte: u8| -> Option<PoiBytes> { Some([byte; 32].into()) };
+
+        let cohorts = agreement_cohorts([
+            (indexer(1), poi(1)),
+            (indexer(2), poi(2)),
+            (indexer(3), None),
+            (indexer(4), poi(2)),
+            (indexer(5), poi(1)),
+            (indexer(6), poi(2)),
+        ]);
+        assert_eq!(
+            cohorts,
+            vec![
+                PoiCohort {
+                    poi: poi(2),
+                    indexers: vec![indexer(2), indexer(4), indexer(6)],
+                },
+                PoiCohort {
+                    poi: poi(1),
+                    indexers: vec![indexer(1), indexer(5)],
+                },
+                PoiCohort {
+                    poi: None,
+                    indexers: vec![indexer(3)],
+                },
+            ]
+        );
+        assert_eq!(agreement_cohorts([(indexer(1), poi(1))]).len(), 1);
+    }
+
     #[test]
     fn unordered_pairs_combinations_test_cases() {
         test_unordered_pairs_combinations(vec![], vec![]);
@@ -0,0 +1,403 @@
+//! The bisection algorithm that locates the block at which the PoIs of
+//! indexers first diverged. It doesn't care how PoIs are obtained, so that it
+//! can be tested without indexers or a database; see [`crate::bisect`] for
+//! divergence investigations proper.
+
+use std::future::Future;
+
+use graphix_common_types::PoiBytes;
+use thiserror::Error;
+
+/// Answers which PoI a participant of a bisection, e.g. an indexer, reports
+/// for the bisected deployment at a given block.
+pub trait PoiOracle {
+    type Participant: Clone;
+
+    /// Returns the PoI of `participant` at the given block, or [`None`] if it
+    /// doesn't have one, e.g. because the block is before the deployment's
+    /// start block or the request failed.
+    fn poi(
+        &mut self,
+        participant: &Self::Participant,
+        block_number: u64,
+    ) -> impl Future<Output = Option<PoiBytes>> + Send;
+
+    /// Called after each probed block with the bounds that it resulted in,
+    /// e.g. to report progress.
+    fn probed(
+        &mut self,
+        _probe: &Probe<Self::Participant>,
+        _bounds: &BisectionBounds,
+    ) -> impl Future<Output = ()> + Send {
+        std::future::ready(())
+    }
+}
+
+/// The range of blocks that the divergence is known to be in: participants
+/// agree at `lower` and diverge at `upper`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BisectionBounds {
+    pub lower: u64,
+    pub upper: u64,
+}
+
+/// A group of participants that reported the same PoI for a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cohort<P> {
+    /// [`None`] for the participants that didn't report a PoI.
+    pub poi: Option<PoiBytes>,
+    pub participants: Vec<P>,
+}
+
+/// The PoIs that all participants reported for a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Probe<P> {
+    pub block_number: u64,
+    /// Largest first.
+    pub cohorts: Vec<Cohort<P>>,
+}
+
+impl<P> Probe<P> {
+    /// Whether the participants disagree at this block. Participants without
+    /// a PoI disagree with those that have one.
+    pub fn diverged(&self) -> bool {
+        self.cohorts.len() > 1
+    }
+}
+
+/// The result of a bisection that reached a conclusion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bisection<P> {
+    /// Adjacent blocks, unless the participants diverge at block 0 already.
+    /// The upper bound is the diverging block.
+    pub bounds: BisectionBounds,
+    /// The cohorts at the diverging block.
+    pub diverging_cohorts: Vec<Cohort<P>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum BisectionError {
+    #[error("at least two participants are needed to bisect")]
+    TooFewParticipants,
+    #[error("the participants agree at block {block_number}, there's no divergence to bisect")]
+    NoDivergence { block_number: u64 },
+}
+
+/// Finds the first block at or below `upper_bound` at which `participants`
+/// diverge. It probes `upper_bound` first to confirm the divergence, and
+/// assumes that the participants agree at block 0.
+///
+/// The search range halves with every probe, whatever the oracle answers, so
+/// it takes at most `log2(upper_bound) + 2` probes. If the PoIs of the
+/// participants diverge at some block and stay diverged after it, that block
+/// is found.
+pub async fn bisect<O: PoiOracle>(
+    oracle: &mut O,
+    participants: &[O::Participant],
+    upper_bound: u64,
+) -> Result<Bisection<O::Participant>, BisectionError> {
+    if participants.len() < 2 {
+        return Err(BisectionError::TooFewParticipants);
+    }
+
+    let mut bounds = BisectionBounds {
+        lower: 0,
+        upper: upper_bound,
+    };
+    let mut diverging = probe(oracle, participants, upper_bound).await;
+    oracle.probed(&diverging, &bounds).await;
+    if !diverging.diverged() {
+        return Err(BisectionError::NoDivergence {
+            block_number: upper_bound,
+        });
+    }
+
+    while bounds.upper - bounds.lower > 1 {
+        let block_number = bounds.lower + (bounds.upper - bounds.lower) / 2;
+        let probe = probe(oracle, participants, block_number).await;
+        if probe.diverged() {
+            bounds.upper = block_number;
+        } else {
+            bounds.lower = block_number;
+        }
+        oracle.probed(&probe, &bounds).await;
+
+        if probe.diverged() {
+            diverging = probe;
+        }
+    }
+
+    Ok(Bisection {
+        bounds,
+        diverging_cohorts: diverging.cohorts,
+    })
+}
+
+async fn probe<O: PoiOracle>(
+    oracle: &mut O,
+    participants: &[O::Participant],
+    block_number: u64,
+) -> Probe<O::Participant> {
+    let mut responses = vec![];
+    for participant in participants {
+        let poi = oracle.poi(participant, block_number).await;
+        responses.push((participant.clone(), poi));
+    }
+
+    Probe {
+        block_number,
+        cohorts: cohorts(responses),
+    }
+}
+
+/// Partitions participants into cohorts that reported the same PoI, largest
+/// cohort first. Participants that didn't report a PoI form a cohort of their
+/// own.
+pub fn cohorts<P>(responses: impl IntoIterator<Item = (P, Option<PoiBytes>)>) -> Vec<Cohort<P>> {
+    let mut cohorts: Vec<Cohort<P>> = vec![];
+    for (participant, poi) in responses {
+        match cohorts.iter_mut().find(|cohort| cohort.poi == poi) {
+            Some(cohort) => cohort.participants.push(participant),
+            None => cohorts.push(Cohort {
+                poi,
+                participants: vec![participant],
+            }),
+        }
+    }
+    cohorts.sort_by_key(|cohort| std::cmp::Reverse(cohort.participants.len()));
+    cohorts
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use futures::executor::block_on;
+    use quickcheck::{Arbitrary, Gen};
+    use quickcheck_macros::quickcheck;
+
+    use super::*;
+
+    /// An indexer whose PoIs diverge from the canonical ones at some block,
+    /// if ever, and which may not have PoIs for some blocks.
+    #[derive(Debug, Clone)]
+    struct SimulatedIndexer {
+        diverges_at: Option<u64>,
+        missing_blocks: Vec<u64>,
+    }
+
+    impl SimulatedIndexer {
+        fn poi(&self, id: u8, block_number: u64) -> Option<PoiBytes> {
+            if self.missing_blocks.contains(&block_number) {
+                return None;
+            }
+
+            let mut poi = [0; 32];
+            poi[..8].copy_from_slice(&block_number.to_be_bytes());
+            if self.diverges_at.is_some_and(|block| block_number >= block) {
+                poi[31] = id + 1;
+            }
+            Some(poi.into())
+        }
+    }
+
+    /// A bisection scenario: the block at which the divergence was noticed,
+    /// and the indexers involved.
+    #[derive(Debug, Clone)]
+    struct Scenario {
+        upper_bound: u64,
+        indexers: Vec<SimulatedIndexer>,
+    }
+
+    impl Arbitrary for Scenario {
+        fn arbitrary(g: &mut Gen) -> Self {
+            let upper_bound = u64::arbitrary(g) % 1_000_000_000;
+            let block = |g: &mut Gen| u64::arbitrary(g) % (upper_bound + 1);
+            let indexers = (0..2 + usize::arbitrary(g) % 4)
+                .map(|_| SimulatedIndexer {
+                    diverges_at: bool::arbitrary(g).then(|| block(g)),
+                    missing_blocks: (0..usize::arbitrary(g) % 3).map(|_| block(g)).collect(),
+                })
+                .collect();
+
+            Self {
+                upper_bound,
+                indexers,
+            }
+        }
+    }
+
+    /// Answers with the PoIs of simulated indexers, and records all probes.
+    #[derive(Default)]
+    struct Oracle {
+        indexers: Vec<SimulatedIndexer>,
+        queries: BTreeMap<(u8, u64), usize>,
+        probes: Vec<(Probe<u8>, BisectionBounds)>,
+    }
+
+    impl PoiOracle for Oracle {
+        type Participant = u8;
+
+        async fn poi(&mut self, participant: &u8, block_number: u64) -> Option<PoiBytes> {
+            *self
+                .queries
+                .entry((*participant, block_number))
+                .or_default() += 1;
+            self.indexers[*participant as usize].poi(*participant, block_number)
+        }
+
+        async fn probed(&mut self, probe: &Probe<u8>, bounds: &BisectionBounds) {
+            self.probes.push((probe.clone(), *bounds));
+        }
+    }
+
+    fn run(scenario: &Scenario) -> (Oracle, Result<Bisection<u8>, BisectionError>) {
+        let mut oracle = Oracle {
+            indexers: scenario.indexers.clone(),
+            ..Default::default()
+        };
+        let participants = (0..scenario.indexers.len() as u8).collect::<Vec<_>>();
+        let result = block_on(bisect(&mut oracle, &participants, scenario.upper_bound));
+        (oracle, result)
+    }
+
+    fn max_probes(upper_bound: u64) -> usize {
+        (u64::BITS - upper_bound.leading_zeros()) as usize + 1
+    }
+
+    #[quickcheck]
+    fn always_terminates_within_logarithmic_probes(scenario: Scenario) -> bool {
+        let (oracle, _) = run(&scenario);
+
+        oracle.probes.len() <= max_probes(scenario.upper_bound)
+            && oracle.queries.values().all(|&count| count == 1)
+    }
+
+    #[quickcheck]
+    fn converges_to_adjacent_blocks_or_fails_cleanly(scenario: Scenario) -> bool {
+        let (oracle, result) = run(&scenario);
+        let (last_probe, _) = oracle.probes.last().unwrap();
+
+        match result {
+            Ok(bisection) => {
+                let diverged_at = |block_number| {
+                    oracle
+                        .probes
+                        .iter()
+                        .any(|(probe, _)| probe.block_number == block_number && probe.diverged())
+                };
+                let agreed_at = |block_number| {
+                    block_number == 0
+                        || oracle.probes.iter().any(|(probe, _)| {
+                            probe.block_number == block_number && !probe.diverged()
+                        })
+                };
+
+                let BisectionBounds { lower, upper } = bisection.bounds;
+                (upper - lower == 1 || upper == 0)
+                    && diverged_at(upper)
+                    && agreed_at(lower)
+                    && bisection.diverging_cohorts.len() > 1
+            }
+            Err(BisectionError::NoDivergence { block_number }) => {
+                oracle.probes.len() == 1
+                    && block_number == scenario.upper_bound
+                    && !last_probe.diverged()
+            }
+            Err(BisectionError::TooFewParticipants) => false,
+        }
+    }
+
+    #[quickcheck]
+    fn finds_the_first_diverging_block(scenario: Scenario) -> bool {
+        // Without missing PoIs, PoIs never converge again once diverged.
+        let indexers = scenario
+            .indexers
+            .into_iter()
+            .map(|indexer| SimulatedIndexer {
+                missing_blocks: vec![],
+                ..indexer
+            })
+            .collect::<Vec<_>>();
+        let first_divergence = indexers
+            .iter()
+            .filter_map(|indexer| indexer.diverges_at)
+            .min();
+        let scenario = Scenario {
+            upper_bound: scenario.upper_bound,
+            indexers,
+        };
+
+        let (_, result) = run(&scenario);
+
+        match (first_divergence, result) {
+            // Participants are assumed to agree at block 0.
+            (Some(block), Ok(bisection)) => {
+                bisection.bounds.upper == block.max(1).min(scenario.upper_bound)
+            }
+            (None, Err(BisectionError::NoDivergence { .. })) => true,
+            _ => false,
+        }
+    }
+
+    #[quickcheck]
+    fn inconsistent_answers_still_terminate(upper_bound: u64, answers: Vec<bool>) -> bool {
+        /// Answers whether the participants agree at random, ignoring the
+        /// block entirely.
+        struct Inconsistent(Vec<bool>, usize);
+
+        impl PoiOracle for Inconsistent {
+            type Participant = u8;
+
+            async fn poi(&mut self, participant: &u8, _block_number: u64) -> Option<PoiBytes> {
+                let agree = self.0.get(self.1 / 2).copied().unwrap_or(*participant == 0);
+                self.1 += 1;
+                Some([if agree { 0 } else { *participant }; 32].into())
+            }
+        }
+
+        let mut oracle = Inconsistent(answers, 0);
+        let result = block_on(bisect(&mut oracle, &[0, 1], upper_bound));
+
+        let converged = match result {
+            Ok(bisection) => bisection.bounds.upper - bisection.bounds.lower <= 1,
+            Err(_) => true,
+        };
+        oracle.1 <= 2 * max_probes(upper_bound) && converged
+    }
+
+    #[test]
+    fn too_few_participants() {
+        let mut oracle = Oracle::default();
+
+        let result = block_on(bisect(&mut oracle, &[0], 100));
+
+        assert_eq!(result, Err(BisectionError::TooFewParticipants));
+        assert!(oracle.probes.is_empty());
+    }
+
+    #[test]
+    fn cohorts_largest_first() {
+        let poi = |byte: u8| -> Option<PoiBytes> { Some([byte; 32].into()) };
+
+        let cohorts = cohorts([(1, poi(1)), (2, poi(2)), (3, None), (4, poi(2))]);
+
+        assert_eq!(
+            cohorts,
+            vec![
+                Cohort {
+                    poi: poi(2),
+                    participants: vec![2, 4],
+                },
+                Cohort {
+                    poi: poi(1),
+                    participants: vec![1],
+                },
+                Cohort {
+                    poi: None,
+                    participants: vec![3],
+                },
+            ]
+        );
+    }
+}
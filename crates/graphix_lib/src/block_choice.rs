@@ -2,7 +2,18 @@ use graphix_indexer_client::IndexingStatus;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    async_graphql::Enum,
+)]
 #[serde(rename_all = "camelCase")]
 pub enum BlockChoicePolicy {
     // Use the earliest block that all indexers have in common
@@ -30,19 +41,30 @@ impl BlockChoicePolicy {
                 let mut indexers_ascending: Vec<&'a IndexingStatus> = statuses.collect();
                 indexers_ascending.sort_by_key(|status| status.latest_block.number);
 
+                // Not all indexers report the earliest block, e.g. for
+                // Substreams-powered subgraphs, so fall back to the one
+                // reported by others.
+                let fallback_earliest_block_num = indexers_ascending
+                    .iter()
+                    .filter_map(|status| status.earliest_block_num)
+                    .min()
+                    .unwrap_or(0);
+
                 let mut max_utility = 0;
                 let mut best_block: Option<u64> = None;
 
                 for (i, status) in indexers_ascending.iter().enumerate() {
                     let remaining_statuses = indexers_ascending.len() - i;
                     let block_number = status.latest_block.number;
-                    if block_number < status.earliest_block_num {
+                    let earliest_block_num = status
+                        .earliest_block_num
+                        .unwrap_or(fallback_earliest_block_num);
+                    if block_number < earliest_block_num {
                         // This status is inconsistent, ignore it, avoiding overflow.
                         continue;
                     }
 
-                    let utility =
-                        remaining_statuses as u64 * (block_number - status.earliest_block_num);
+                    let utility = remaining_statuses as u64 * (block_number - earliest_block_num);
 
                     if utility > max_utility {
                         max_utility = utility;
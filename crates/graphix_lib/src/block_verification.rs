@@ -0,0 +1,344 @@
+//! Verification of the block hashes that indexers report alongside PoIs
+//! against the canonical chain, as seen by a chain's JSON-RPC endpoint. PoIs
+//! on non-canonical blocks, e.g. during deep reorgs, aren't comparable with
+//! the PoIs of other indexers. Neither are the PoIs of indexers that disagree
+//! about the hash of a block, which are reported as chain disagreements.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::anyhow;
+use chrono::NaiveDateTime;
+use graphix_common_types::{BlockHash, IpfsCid};
+use graphix_indexer_client::{IndexingStatus, ProofOfIndexing};
+use graphix_store::models::DetectedChainDisagreement;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::*;
+use url::Url;
+
+use crate::config::Config;
+
+/// Maps every deployment to the network it indexes.
+pub fn deployment_networks(indexing_statuses: &[IndexingStatus]) -> HashMap<IpfsCid, String> {
+    indexing_statuses
+        .iter()
+        .map(|status| (status.deployment.clone(), status.network.clone()))
+        .collect()
+}
+
+/// The outcome of [`verify_block_hashes`].
+#[derive(Debug, Default)]
+pub struct BlockVerification {
+    /// Whether each verified block hash is canonical.
+    pub canonical_by_hash: HashMap<BlockHash, bool>,
+    /// The timestamps of canonical blocks, as reported by the RPC endpoint.
+    pub timestamps: HashMap<BlockHash, NaiveDateTime>,
+}
+
+/// Verifies the block hashes of `pois` on all EVM networks with an RPC
+/// endpoint, and returns whether each of them is canonical. Blocks without a
+/// hash, on other networks, or that failed verification are left out.
+#[instrument(skip_all)]
+pub async fn verify_block_hashes(
+    config: &Config,
+    pois: &[ProofOfIndexing],
+    deployment_networks: &HashMap<IpfsCid, String>,
+) -> BlockVerification {
+    let mut blocks: HashMap<(&Url, u64), HashSet<&BlockHash>> = HashMap::new();
+    for poi in pois {
+        let Some(hash) = &poi.block.hash else {
+            continue;
+        };
+        let Some(rpc_url) = deployment_networks
+            .get(&poi.deployment)
+            .and_then(|network| config.chains.get(network))
+            .filter(|chain| chain.is_evm())
+            .and_then(|chain| chain.rpc_url.as_ref())
+        else {
+            continue;
+        };
+
+        blocks
+            .entry((rpc_url, poi.block.number))
+            .or_default()
+            .insert(hash);
+    }
+
+    let client = reqwest::Client::new();
+    let mut verification = BlockVerification::default();
+    for ((rpc_url, number), hashes) in blocks {
+        match canonical_block(&client, rpc_url, number).await {
+            Ok((canonical_hash, timestamp)) => {
+                for hash in hashes {
+                    let canonical = *hash == canonical_hash;
+                    if !canonical {
+                        warn!(
+                            block_number = number,
+                            %hash,
+                            %canonical_hash,
+                            "Indexer reported a PoI for a non-canonical block"
+                        );
+                    }
+                    verification
+                        .canonical_by_hash
+                        .insert(hash.clone(), canonical);
+                }
+                verification.timestamps.insert(canonical_hash, timestamp);
+            }
+            Err(error) => {
+                warn!(block_number = number, %error, "Failed to verify block hash");
+            }
+        }
+    }
+
+    verification
+}
+
+/// Returns the timestamps of the blocks of `pois`: the ones in
+/// `verification` if known, and otherwise estimated from the block speed of
+/// the network, if configured. Blocks without a hash are left out.
+pub fn block_timestamps(
+    config: &Config,
+    pois: &[ProofOfIndexing],
+    deployment_networks: &HashMap<IpfsCid, String>,
+    verification: &BlockVerification,
+) -> HashMap<BlockHash, NaiveDateTime> {
+    let mut timestamps = verification.timestamps.clone();
+    for poi in pois {
+        let Some(hash) = &poi.block.hash else {
+            continue;
+        };
+        if timestamps.contains_key(hash) || verification.canonical_by_hash.contains_key(hash) {
+            continue;
+        }
+        let estimated_timestamp = deployment_networks
+            .get(&poi.deployment)
+            .and_then(|network| config.chains.get(network))
+            .and_then(|chain| chain.speed.as_ref())
+            .and_then(|speed| speed.estimated_timestamp(poi.block.number));
+        if let Some(timestamp) = estimated_timestamp {
+            timestamps.insert(hash.clone(), timestamp.naive_utc());
+        }
+    }
+    timestamps
+}
+
+/// Returns the PoIs that weren't found to be on non-canonical blocks.
+pub fn canonical_pois(
+    pois: &[ProofOfIndexing],
+    canonical_by_hash: &HashMap<BlockHash, bool>,
+) -> Vec<ProofOfIndexing> {
+    pois.iter()
+        .filter(|poi| {
+            poi.block
+                .hash
+                .as_ref()
+                .and_then(|hash| canonical_by_hash.get(hash))
+                .copied()
+                .unwrap_or(true)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Finds the blocks for which indexers reported PoIs with different block
+/// hashes. The causes are entirely different from those of PoI divergences,
+/// e.g. reorgs or faulty RPC providers, and so are the remedies.
+pub fn chain_disagreements(pois: &[ProofOfIndexing]) -> Vec<DetectedChainDisagreement> {
+    let mut blocks: HashMap<(&IpfsCid, u64), Vec<_>> = HashMap::new();
+    for poi in pois {
+        if let Some(hash) = &poi.block.hash {
+            blocks
+                .entry((&poi.deployment, poi.block.number))
+                .or_default()
+                .push((poi.indexer.clone(), hash.clone()));
+        }
+    }
+
+    blocks
+        .into_iter()
+        .filter(|(_, block_hashes)| {
+            block_hashes
+                .iter()
+                .map(|(_, hash)| hash)
+                .collect::<HashSet<_>>()
+                .len()
+                > 1
+        })
+        .map(|((deployment, block_number), block_hashes)| {
+            warn!(
+                %deployment,
+                block_number,
+                indexers = block_hashes.len(),
+                "Indexers disagree about the hash of a block"
+            );
+            DetectedChainDisagreement {
+                deployment: deployment.clone(),
+                block_number,
+                block_hashes,
+            }
+        })
+        .collect()
+}
+
+/// Returns the PoIs that aren't part of any of `disagreements`.
+pub fn without_chain_disagreements(
+    pois: &[ProofOfIndexing],
+    disagreements: &[DetectedChainDisagreement],
+) -> Vec<ProofOfIndexing> {
+    let blocks: HashSet<(&IpfsCid, u64)> = disagreements
+        .iter()
+        .map(|disagreement| (&disagreement.deployment, disagreement.block_number))
+        .collect();
+    pois.iter()
+        .filter(|poi| !blocks.contains(&(&poi.deployment, poi.block.number)))
+        .cloned()
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct JsonRpcResponse {
+    result: Option<JsonRpcBlock>,
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct JsonRpcBlock {
+    hash: String,
+    /// UNIX timestamp, hex-encoded.
+    timestamp: String,
+}
+
+/// Returns the hash and timestamp of the canonical block with the given
+/// number.
+async fn canonical_block(
+    client: &reqwest::Client,
+    rpc_url: &Url,
+    number: u64,
+) -> anyhow::Result<(BlockHash, NaiveDateTime)> {
+    let response: JsonRpcResponse = client
+        .post(rpc_url.clone())
+        .json(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_getBlockByNumber",
+            "params": [format!("0x{:x}", number), false],
+        }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    if let Some(error) = response.error {
+        return Err(anyhow!("JSON-RPC error: {}", error));
+    }
+    let block = response
+        .result
+        .ok_or_else(|| anyhow!("block {} not found", number))?;
+
+    let hash = block
+        .hash
+        .parse()
+        .map_err(|e| anyhow!("invalid block hash: {}", e))?;
+    let timestamp = i64::from_str_radix(block.timestamp.trim_start_matches("0x"), 16)
+        .ok()
+        .and_then(|timestamp| chrono::DateTime::from_timestamp(timestamp, 0))
+        .ok_or_else(|| anyhow!("invalid block timestamp: {}", block.timestamp))?;
+    Ok((hash, timestamp.naive_utc()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use graphix_indexer_client::BlockPointer;
+
+    use super::*;
+    use crate::test_utils::mocks::MockIndexer;
+
+    fn poi(block_number: u64, hash_byte: Option<u8>) -> ProofOfIndexing {
+        ProofOfIndexing {
+            indexer: Arc::new(MockIndexer {
+                name: "indexer".to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }),
+            deployment: "QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA"
+                .parse()
+                .unwrap(),
+            block: BlockPointer {
+                number: block_number,
+                hash: hash_byte.map(|byte| vec![byte; 32].into()),
+            },
+            proof_of_indexing: [1; 32].into(),
+        }
+    }
+
+    #[test]
+    fn pois_on_non_canonical_blocks_are_left_out() {
+        let poi = |hash_byte: Option<u8>| poi(1, hash_byte);
+        let pois = [poi(Some(1)), poi(Some(2)), poi(Some(3)), poi(None)];
+        let canonical_by_hash =
+            HashMap::from([(vec![1; 32].into(), true), (vec![2; 32].into(), false)]);
+
+        let hashes: Vec<_> = canonical_pois(&pois, &canonical_by_hash)
+            .into_iter()
+            .map(|poi| poi.block.hash)
+            .collect();
+        assert_eq!(
+            hashes,
+            vec![Some(vec![1; 32].into()), Some(vec![3; 32].into()), None]
+        );
+    }
+
+    #[test]
+    fn block_timestamps_are_estimated_if_unverified() {
+        let mut config = Config::default();
+        config.chains.insert(
+            "mainnet".to_string(),
+            serde_yaml::from_str(
+                "sampleBlockHeight: 100\nsampleTimestamp: 2024-01-01T00:00:00Z\navgBlockTimeInMsecs: 12000",
+            )
+            .unwrap(),
+        );
+        let pois = [poi(110, Some(1)), poi(120, Some(2)), poi(130, None)];
+        let deployment_networks =
+            HashMap::from([(pois[0].deployment.clone(), "mainnet".to_string())]);
+        let verified_timestamp = chrono::DateTime::from_timestamp(0, 0).unwrap().naive_utc();
+        let verification = BlockVerification {
+            canonical_by_hash: HashMap::from([(vec![2; 32].into(), true)]),
+            timestamps: HashMap::from([(vec![2; 32].into(), verified_timestamp)]),
+        };
+
+        let timestamps = block_timestamps(&config, &pois, &deployment_networks, &verification);
+        assert_eq!(timestamps.len(), 2);
+        assert_eq!(
+            timestamps[&vec![1; 32].into()].to_string(),
+            "2024-01-01 00:02:00"
+        );
+        assert_eq!(timestamps[&vec![2; 32].into()], verified_timestamp);
+    }
+
+    #[test]
+    fn block_hash_disagreements_are_left_out() {
+        let pois = [
+            poi(1, Some(1)),
+            poi(1, Some(2)),
+            poi(1, None),
+            poi(2, Some(3)),
+            poi(2, Some(3)),
+        ];
+
+        let disagreements = chain_disagreements(&pois);
+        assert_eq!(disagreements.len(), 1);
+        assert_eq!(disagreements[0].block_number, 1);
+        assert_eq!(disagreements[0].block_hashes.len(), 2);
+
+        let blocks: Vec<_> = without_chain_disagreements(&pois, &disagreements)
+            .into_iter()
+            .map(|poi| poi.block.number)
+            .collect();
+        assert_eq!(blocks, vec![2, 2]);
+    }
+}
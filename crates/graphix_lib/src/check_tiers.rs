@@ -0,0 +1,181 @@
+//! Cross-checking important subgraph deployments more often than others.
+//! Deployments are assigned to numbered tiers, explicitly or by curation
+//! signal: tier 1 is cross-checked every polling round, lower tiers only
+//! every few rounds. Their deployments are spread across those rounds by
+//! hash, so that the workload stays even.
+
+use std::collections::HashMap;
+
+use graphix_common_types::IpfsCid;
+use graphix_indexer_client::IndexingStatus;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::*;
+
+/// GRT wei per GRT.
+const WEI_PER_GRT: f64 = 1e18;
+
+/// A cross-check tier of subgraph deployments. Tiers are numbered by their
+/// position in `checkTiers`, starting from 1.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckTierConfig {
+    /// Cross-check the deployments of this tier every this many polling
+    /// rounds. Must be 1 for the first tier.
+    #[serde(default = "CheckTierConfig::default_every_n_rounds")]
+    pub every_n_rounds: u32,
+    /// IPFS hashes of subgraph deployments in this tier, regardless of their
+    /// curation signal.
+    #[serde(default)]
+    pub deployments: Vec<String>,
+    /// Deployments with at least this much curation signal, in GRT, are in
+    /// this tier, unless they're listed in `deployments` of another tier or
+    /// an earlier tier's threshold already matches them.
+    #[serde(default)]
+    pub min_signal: Option<f64>,
+}
+
+impl CheckTierConfig {
+    fn default_every_n_rounds() -> u32 {
+        1
+    }
+}
+
+/// Assigns each of `deployments` to the first tier that lists it, or else to
+/// the first tier whose signal threshold it meets, or else to the last tier.
+/// `signal_amounts` are in GRT wei. Returns tier numbers starting from 1, or
+/// nothing if no tiers are configured.
+pub fn assign_check_tiers<'a>(
+    tiers: &[CheckTierConfig],
+    deployments: impl IntoIterator<Item = &'a IpfsCid>,
+    signal_amounts: &HashMap<IpfsCid, f64>,
+) -> HashMap<IpfsCid, u32> {
+    if tiers.is_empty() {
+        return HashMap::new();
+    }
+
+    // Reversed, so that the first tier that lists a deployment wins.
+    let listed: HashMap<&str, u32> = tiers
+        .iter()
+        .enumerate()
+        .rev()
+        .flat_map(|(i, tier)| {
+            let number = i as u32 + 1;
+            tier.deployments.iter().map(move |d| (d.as_str(), number))
+        })
+        .collect();
+    let last = tiers.len() as u32;
+    deployments
+        .into_iter()
+        .map(|deployment| {
+            let signal = signal_amounts.get(deployment).map(|wei| wei / WEI_PER_GRT);
+            let number = listed
+                .get(deployment.to_string().as_str())
+                .copied()
+                .or_else(|| {
+                    let signal = signal?;
+                    let i = tiers
+                        .iter()
+                        .position(|tier| tier.min_signal.is_some_and(|min| signal >= min))?;
+                    Some(i as u32 + 1)
+                })
+                .unwrap_or(last);
+            (deployment.clone(), number)
+        })
+        .collect()
+}
+
+/// Whether a deployment that is cross-checked every `every_n_rounds` polling
+/// rounds is due in round `round_id`. Deployments are offset by hash, so
+/// that each round checks a similar share of them.
+pub fn is_due(deployment: &IpfsCid, every_n_rounds: u32, round_id: u64) -> bool {
+    if every_n_rounds <= 1 {
+        return true;
+    }
+
+    // Not the bytes that shards are chosen by, or deployments of the same
+    // shard would all be due in the same rounds.
+    let hash = Sha256::digest(deployment.to_string().as_bytes());
+    let offset = u64::from_be_bytes(hash[8..16].try_into().unwrap());
+    offset.wrapping_add(round_id) % every_n_rounds as u64 == 0
+}
+
+/// Restricts `indexing_statuses` to the subgraph deployments whose tier is
+/// due in round `round_id`, given the tier of each deployment as returned
+/// by [`assign_check_tiers`]. Deployments without a tier are always due.
+pub fn retain_due_deployments(
+    indexing_statuses: &mut Vec<IndexingStatus>,
+    tiers: &[CheckTierConfig],
+    check_tiers: &HashMap<IpfsCid, u32>,
+    round_id: u64,
+) {
+    if tiers.is_empty() {
+        return;
+    }
+
+    indexing_statuses.retain(|status| {
+        let every_n_rounds = check_tiers
+            .get(&status.deployment)
+            .and_then(|number| tiers.get(*number as usize - 1))
+            .map_or(1, |tier| tier.every_n_rounds);
+        is_due(&status.deployment, every_n_rounds, round_id)
+    });
+
+    debug!(
+        round_id,
+        indexing_statuses = indexing_statuses.len(),
+        "Restricted indexing statuses to deployments due this round"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::gen::gen_synthetic_deployments;
+
+    fn tier(
+        every_n_rounds: u32,
+        deployments: &[&IpfsCid],
+        min_signal: Option<f64>,
+    ) -> CheckTierConfig {
+        CheckTierConfig {
+            every_n_rounds,
+            deployments: deployments.iter().map(|d| d.to_string()).collect(),
+            min_signal,
+        }
+    }
+
+    #[test]
+    fn tiers_by_listing_then_signal_then_last() {
+        let deployments = gen_synthetic_deployments(4);
+        let tiers = [
+            tier(1, &[], Some(1000.0)),
+            tier(2, &[&deployments[0]], Some(10.0)),
+            tier(4, &[], None),
+        ];
+        let signal_amounts = HashMap::from([
+            // Listed in tier 2, despite the signal of tier 1.
+            (deployments[0].clone(), 5000.0 * WEI_PER_GRT),
+            (deployments[1].clone(), 5000.0 * WEI_PER_GRT),
+            (deployments[2].clone(), 50.0 * WEI_PER_GRT),
+        ]);
+
+        let check_tiers = assign_check_tiers(&tiers, &deployments, &signal_amounts);
+
+        assert_eq!(check_tiers[&deployments[0]], 2);
+        assert_eq!(check_tiers[&deployments[1]], 1);
+        assert_eq!(check_tiers[&deployments[2]], 2);
+        assert_eq!(check_tiers[&deployments[3]], 3);
+        assert!(assign_check_tiers(&[], &deployments, &signal_amounts).is_empty());
+    }
+
+    #[test]
+    fn deployments_are_due_once_per_rotation() {
+        for deployment in gen_synthetic_deployments(20) {
+            assert!((1..=10).all(|round_id| is_due(&deployment, 1, round_id)));
+            let due_rounds = (1..=12).filter(|round_id| is_due(&deployment, 3, *round_id));
+            assert_eq!(due_rounds.count(), 4);
+        }
+    }
+}
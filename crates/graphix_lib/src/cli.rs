@@ -1,16 +1,395 @@
-use clap::Parser;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use graphix_common_types::ApiKeyPermissionLevel;
+use ipnet::IpNet;
+use uuid::Uuid;
+
+use crate::config::read_secret_file;
+use crate::graphql_api::{OidcOptions, RateLimit};
+#[cfg(feature = "loadtest")]
+use crate::loadtest::LoadtestOptions;
+use crate::sharding::Shard;
+use crate::signing::Signer;
+use crate::tls::TlsOptions;
 
 #[derive(Parser, Debug)]
-#[clap(author, about, version)]
+#[clap(author, about, version, subcommand_negates_reqs = true)]
 pub struct CliOptions {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
     /// The URL of the PostgreSQL database to use. Can also be set via env.
     /// var..
-    #[clap(long, env = "GRAPHIX_DB_URL")]
-    pub database_url: String,
+    #[clap(
+        long,
+        env = "GRAPHIX_DB_URL",
+        required_unless_present = "database_url_file"
+    )]
+    pub database_url: Option<String>,
+    /// A file to read the database URL from instead, e.g. a mounted
+    /// Kubernetes or Docker secret. It's only read on startup.
+    #[clap(long, env = "GRAPHIX_DB_URL_FILE", conflicts_with = "database_url")]
+    pub database_url_file: Option<PathBuf>,
+    /// Database statements of the GraphQL API server that run longer than
+    /// this, in seconds, are cancelled, so that expensive queries can't hold
+    /// on to connections. 0 disables the timeout.
+    #[clap(
+        long,
+        env = "GRAPHIX_API_STATEMENT_TIMEOUT_IN_SECONDS",
+        default_value_t = 30
+    )]
+    pub api_statement_timeout_in_seconds: u64,
+    /// Database statements that collect and write data, e.g. PoIs, that run
+    /// longer than this, in seconds, are cancelled. Maintenance jobs aren't
+    /// subject to it. 0 disables the timeout.
+    #[clap(
+        long,
+        env = "GRAPHIX_COLLECTOR_STATEMENT_TIMEOUT_IN_SECONDS",
+        default_value_t = 300
+    )]
+    pub collector_statement_timeout_in_seconds: u64,
+    /// A YAML configuration file that is stored in the database on startup,
+    /// unless the database already contains a configuration. From then on,
+    /// the configuration can be edited through the GraphQL API.
+    #[clap(long, env = "GRAPHIX_BASE_CONFIG")]
+    pub base_config: Option<PathBuf>,
+    /// The IP address on which the GraphQL API server should listen.
+    #[clap(long, env = "GRAPHIX_LISTEN_ADDRESS", default_value = "0.0.0.0")]
+    pub listen_address: IpAddr,
     /// The port on which the GraphQL API server should listen.
-    #[clap(long, default_value_t = 8000)]
+    #[clap(long, env = "GRAPHIX_PORT", default_value_t = 8000)]
     pub port: u16,
+    /// Comma-separated list of origins that browsers may send cross-origin
+    /// requests to the GraphQL API from, e.g. `https://graphix.example.com`.
+    /// `*` allows any origin. Cross-origin requests are rejected by default.
+    #[clap(long, env = "GRAPHIX_CORS_ALLOWED_ORIGINS", value_delimiter = ',')]
+    pub cors_allowed_origins: Vec<String>,
+    /// Upper limit on the size of request bodies accepted by the GraphQL API
+    /// server, in bytes.
+    #[clap(long, env = "GRAPHIX_MAX_REQUEST_BODY_SIZE", default_value_t = 2 * 1024 * 1024)]
+    pub max_request_body_size: usize,
+    /// How long the responses to expensive GraphQL queries, e.g.
+    /// `poiAgreementRatios`, are cached in memory, in seconds. Cached
+    /// responses are dropped early when new PoIs are collected. 0 disables
+    /// the cache.
+    #[clap(
+        long,
+        env = "GRAPHIX_RESPONSE_CACHE_TTL_IN_SECONDS",
+        default_value_t = 60
+    )]
+    pub response_cache_ttl_in_seconds: u64,
+    /// Path to a JSON file that maps the hex-encoded SHA-256 hashes of
+    /// GraphQL queries to the queries. If given, requests without an API key
+    /// may only execute these queries. Clients may send just the hashes of
+    /// persisted queries either way.
+    #[clap(long, env = "GRAPHIX_PERSISTED_QUERIES_ALLOWLIST")]
+    pub persisted_queries_allowlist: Option<PathBuf>,
+    /// The number of GraphQL API requests per second that each client IP
+    /// address may make on average. Requests aren't limited by IP address
+    /// if not given. Behind a reverse proxy, all requests come from the
+    /// address of the proxy.
+    #[clap(long, env = "GRAPHIX_RATE_LIMIT_PER_IP")]
+    pub rate_limit_per_ip: Option<f64>,
+    /// The number of GraphQL API requests per second that may be made with
+    /// each API key on average. Requests aren't limited by API key if not
+    /// given.
+    #[clap(long, env = "GRAPHIX_RATE_LIMIT_PER_API_KEY")]
+    pub rate_limit_per_api_key: Option<f64>,
+    /// How many requests clients may make at once, beyond their average
+    /// rate, after being idle.
+    #[clap(long, env = "GRAPHIX_RATE_LIMIT_BURST", default_value_t = 20)]
+    pub rate_limit_burst: u32,
+    /// Comma-separated list of networks in CIDR notation, e.g.
+    /// `10.0.0.0/8`, that admin-level requests, such as admin mutations and
+    /// the `configuration` query, must come from on top of carrying an admin
+    /// API key. Admin requests may come from anywhere by default.
+    #[clap(long, env = "GRAPHIX_ADMIN_ALLOWED_CIDRS", value_delimiter = ',')]
+    pub admin_allowed_cidrs: Vec<IpNet>,
+    /// The OpenID Connect provider whose bearer tokens are accepted as an
+    /// alternative to API keys, e.g. `https://accounts.example.com`. Tokens
+    /// are only accepted if given.
+    #[clap(long, env = "GRAPHIX_OIDC_ISSUER", requires = "oidc_audience")]
+    pub oidc_issuer: Option<String>,
+    /// The audience that bearer tokens must be issued for, usually the client
+    /// ID of Graphix at the OpenID Connect provider.
+    #[clap(long, env = "GRAPHIX_OIDC_AUDIENCE", requires = "oidc_issuer")]
+    pub oidc_audience: Option<String>,
+    /// The claim of bearer tokens that lists the roles of users. Nested
+    /// claims are separated by dots, e.g. `realm_access.roles`.
+    #[clap(long, env = "GRAPHIX_OIDC_ROLE_CLAIM", default_value = "roles")]
+    pub oidc_role_claim: String,
+    /// Comma-separated list of roles and the permission levels they grant,
+    /// e.g. `graphix-admins=admin`. Users without any of these roles have no
+    /// permissions beyond those of anonymous requests.
+    #[clap(long, env = "GRAPHIX_OIDC_ROLES", value_delimiter = ',')]
+    pub oidc_roles: Vec<String>,
+    /// Path to a PEM-encoded Ed25519 private key that dispute evidence
+    /// bundles are signed with, e.g. as generated by `openssl genpkey
+    /// -algorithm ed25519`. Bundles can only be exported if given.
+    #[clap(long, env = "GRAPHIX_DISPUTE_EVIDENCE_SIGNING_KEY")]
+    pub dispute_evidence_signing_key: Option<PathBuf>,
+    /// Path to a PEM-encoded TLS certificate chain. If given together with
+    /// `--tls-key`, the GraphQL API is served over HTTPS, and Prometheus
+    /// metrics are also served at its `/metrics` endpoint.
+    #[clap(long, env = "GRAPHIX_TLS_CERT", requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+    /// Path to the PEM-encoded private key of the TLS certificate.
+    #[clap(long, env = "GRAPHIX_TLS_KEY", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+    /// If set, the TLS certificate and key files are checked for changes at
+    /// this interval and reloaded without a restart, e.g. after a renewal.
+    #[clap(long, env = "GRAPHIX_TLS_RELOAD_INTERVAL_IN_SECONDS")]
+    pub tls_reload_interval_in_seconds: Option<u64>,
+    /// The IP address on which the Prometheus exporter should listen, e.g.
+    /// `127.0.0.1` or an internal network interface to keep metrics private.
+    #[clap(
+        long,
+        env = "GRAPHIX_PROMETHEUS_LISTEN_ADDRESS",
+        default_value = "0.0.0.0"
+    )]
+    pub prometheus_listen_address: IpAddr,
     /// The port on which the Prometheus exporter should listen.
     #[clap(long, default_value_t = 9184)]
     pub prometheus_port: u16,
+    /// A token that requests for Prometheus metrics must carry in an
+    /// `Authorization: Bearer` header, both at the exporter port and at the
+    /// `/metrics` endpoint of the GraphQL API server.
+    #[clap(long, env = "GRAPHIX_METRICS_BEARER_TOKEN")]
+    pub metrics_bearer_token: Option<String>,
+    /// A file to read the metrics bearer token from instead. It's only read
+    /// on startup.
+    #[clap(
+        long,
+        env = "GRAPHIX_METRICS_BEARER_TOKEN_FILE",
+        conflicts_with = "metrics_bearer_token"
+    )]
+    pub metrics_bearer_token_file: Option<PathBuf>,
+    /// Only serve the GraphQL API against an existing database, without
+    /// collecting any data, writing to the database, or accepting mutations.
+    /// Useful for public mirrors of another Graphix instance.
+    #[clap(long, env = "GRAPHIX_READ_ONLY")]
+    pub read_only: bool,
+    /// Splits the cross-checking of subgraph deployments across this many
+    /// instances that share one database. Each of them must be started with
+    /// the same shard count and a different `--shard-index`.
+    #[clap(long, env = "GRAPHIX_SHARD_COUNT", default_value_t = 1)]
+    pub shard_count: u32,
+    /// Which of the `--shard-count` shards this instance cross-checks, from 0.
+    /// Only shard 0 runs divergence investigations, uptime probes, and other
+    /// work that isn't split across shards.
+    #[clap(long, env = "GRAPHIX_SHARD_INDEX", default_value_t = 0)]
+    pub shard_index: u32,
+    /// The format of log output. `json` emits one JSON object per line, for
+    /// log aggregation systems.
+    #[clap(long, env = "GRAPHIX_LOG_FORMAT", value_enum, default_value_t = LogFormat::Pretty)]
+    pub log_format: LogFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable text.
+    Pretty,
+    /// One JSON object per event, with its fields and those of its spans.
+    Json,
+}
+
+/// Commands to run instead of Graphix itself.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Work with configuration files.
+    #[clap(subcommand)]
+    Config(ConfigCommand),
+    /// Maintain the database and report on its size.
+    #[clap(subcommand)]
+    Db(DbCommand),
+    /// Print the GraphQL schema of the API in SDL, e.g. to generate typed
+    /// clients without a running Graphix instance.
+    Schema,
+    /// Export the signed evidence bundle of a divergence that a bisection
+    /// run confirmed, for filing or defending a PoI dispute. Needs
+    /// `--database-url` and `--dispute-evidence-signing-key`.
+    DisputeEvidence {
+        /// The UUID of the bisection run.
+        bisection_run: Uuid,
+        /// Where to write the tar archive.
+        #[clap(long, short)]
+        output: PathBuf,
+    },
+    /// Fill the database with synthetic indexers and PoIs, and report how
+    /// quickly they're written and how long API queries take. Needs
+    /// `--database-url` of a database without any indexers, which is left
+    /// with the generated data.
+    #[cfg(feature = "loadtest")]
+    Loadtest(LoadtestOptions),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Checks a configuration file and reports all problems found in it,
+    /// without starting Graphix.
+    Validate {
+        /// The YAML configuration file to check.
+        path: PathBuf,
+    },
+}
+
+/// Database maintenance commands. They need `--database-url` like Graphix
+/// itself.
+#[derive(Subcommand, Debug)]
+pub enum DbCommand {
+    /// Vacuum and analyze the tables that Graphix writes to on every polling
+    /// round, to reclaim the space of deleted rows and keep query plans up to
+    /// date.
+    Vacuum {
+        /// Only update the planner statistics, which is much quicker but
+        /// doesn't reclaim any space.
+        #[clap(long)]
+        analyze_only: bool,
+    },
+    /// Print the size of each table and its indexes, largest first.
+    Sizes,
+    /// Print an estimate of the space wasted by each index, most bloated
+    /// first. Bloated indexes can be rebuilt with `REINDEX INDEX
+    /// CONCURRENTLY`.
+    IndexBloat,
+    /// Look for inconsistencies in the database, e.g. after a crash or manual
+    /// database surgery, and print how many rows are affected.
+    CheckIntegrity {
+        /// Repair the inconsistencies by deleting the inconsistent rows.
+        #[clap(long)]
+        repair: bool,
+    },
+    /// Recompute which PoIs are live from the stored PoIs, and report the
+    /// live PoIs that would have to be corrected.
+    RecomputeLivePois {
+        /// Correct the live PoIs instead of only reporting them.
+        #[clap(long)]
+        repair: bool,
+    },
+    /// Inspect or revert database migrations. Unlike other commands, these
+    /// don't run pending migrations first.
+    #[clap(subcommand)]
+    Migrations(MigrationsCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MigrationsCommand {
+    /// List all migrations and when they were applied.
+    Status,
+    /// Revert the most recently applied migration. All Graphix instances that
+    /// share the database must be stopped or rolled back to the previous
+    /// version first, since they apply pending migrations on startup.
+    Revert {
+        /// The name of the migration to revert, as listed by `graphix db
+        /// migrations status`, as a guard against reverting the wrong one.
+        #[clap(long)]
+        confirm: String,
+    },
+}
+
+impl CliOptions {
+    /// The database URL, read from `--database-url-file` if given. It's only
+    /// missing when running a [`Command`].
+    pub fn database_url(&self) -> anyhow::Result<String> {
+        if let Some(path) = &self.database_url_file {
+            return read_secret_file(path);
+        }
+
+        self.database_url
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("missing database URL"))
+    }
+
+    /// The bearer token that requests for metrics must carry, if any.
+    pub fn metrics_bearer_token(&self) -> anyhow::Result<Option<String>> {
+        match &self.metrics_bearer_token_file {
+            Some(path) => read_secret_file(path).map(Some),
+            None => Ok(self.metrics_bearer_token.clone()),
+        }
+    }
+
+    /// The rate limit of requests from each client IP address, if any.
+    pub fn rate_limit_per_ip(&self) -> anyhow::Result<Option<RateLimit>> {
+        self.rate_limit(self.rate_limit_per_ip)
+    }
+
+    /// The rate limit of requests with each API key, if any.
+    pub fn rate_limit_per_api_key(&self) -> anyhow::Result<Option<RateLimit>> {
+        self.rate_limit(self.rate_limit_per_api_key)
+    }
+
+    fn rate_limit(&self, requests_per_second: Option<f64>) -> anyhow::Result<Option<RateLimit>> {
+        let Some(requests_per_second) = requests_per_second else {
+            return Ok(None);
+        };
+        if requests_per_second.is_nan() || requests_per_second <= 0.0 {
+            anyhow::bail!("rate limits must be positive, got {requests_per_second}");
+        }
+
+        Ok(Some(RateLimit {
+            requests_per_second,
+            burst: self.rate_limit_burst,
+        }))
+    }
+
+    /// The settings of OpenID Connect authentication, if it's enabled.
+    pub fn oidc_options(&self) -> anyhow::Result<Option<OidcOptions>> {
+        let (Some(issuer), Some(audience)) = (&self.oidc_issuer, &self.oidc_audience) else {
+            return Ok(None);
+        };
+        let role_permission_levels = self
+            .oidc_roles
+            .iter()
+            .map(|mapping| {
+                let (role, permission_level) = mapping.split_once('=').ok_or_else(|| {
+                    anyhow::anyhow!("invalid role mapping {mapping}, expected ROLE=LEVEL")
+                })?;
+                let permission_level = match permission_level.to_ascii_lowercase().as_str() {
+                    "admin" => ApiKeyPermissionLevel::Admin,
+                    _ => anyhow::bail!("unknown permission level {permission_level}"),
+                };
+                Ok((role.to_string(), permission_level))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Some(OidcOptions {
+            issuer: issuer.clone(),
+            audience: audience.clone(),
+            role_claim: self.oidc_role_claim.clone(),
+            role_permission_levels,
+        }))
+    }
+
+    /// The key to sign dispute evidence bundles with, if any.
+    pub fn evidence_signer(&self) -> anyhow::Result<Option<Signer>> {
+        self.dispute_evidence_signing_key
+            .as_deref()
+            .map(Signer::from_pem_file)
+            .transpose()
+    }
+
+    pub fn shard(&self) -> anyhow::Result<Shard> {
+        Shard::new(self.shard_index, self.shard_count)
+    }
+
+    pub fn api_statement_timeout(&self) -> Option<Duration> {
+        (self.api_statement_timeout_in_seconds > 0)
+            .then(|| Duration::from_secs(self.api_statement_timeout_in_seconds))
+    }
+
+    pub fn collector_statement_timeout(&self) -> Option<Duration> {
+        (self.collector_statement_timeout_in_seconds > 0)
+            .then(|| Duration::from_secs(self.collector_statement_timeout_in_seconds))
+    }
+
+    /// The TLS certificate to serve the GraphQL API with, if any.
+    pub fn tls_options(&self) -> Option<TlsOptions> {
+        Some(TlsOptions {
+            cert_path: self.tls_cert.clone()?,
+            key_path: self.tls_key.clone()?,
+            reload_interval: self.tls_reload_interval_in_seconds.map(Duration::from_secs),
+        })
+    }
 }
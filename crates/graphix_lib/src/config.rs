@@ -1,18 +1,25 @@
 //! Graphix configuration parsing and validation.
 
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use graphix_common_types::IndexerAddress;
-use graphix_indexer_client::{IndexerClient, IndexerId, IndexerInterceptor, RealIndexer};
+use graphix_common_types::{IndexerAddress, IpfsCid};
+use graphix_indexer_client::{
+    FaultProfile, IndexerAuth, IndexerClient, IndexerId, IndexerInterceptor, RealIndexer,
+};
 use graphix_network_sg_client::NetworkSubgraphClient;
+use graphix_store::models::RegisteredIndexer;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 use url::Url;
 
 use crate::block_choice::BlockChoicePolicy;
+use crate::check_tiers::CheckTierConfig;
+use crate::notifications::{DeploymentTierConfig, NotifierConfig, PagerDutyConfig, TelegramConfig};
+use crate::signing::Signer;
 use crate::PrometheusMetrics;
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -33,11 +40,26 @@ pub struct ChainSpeedConfig {
     pub avg_block_time_in_msecs: u64,
 }
 
+impl ChainSpeedConfig {
+    /// Approximates the timestamp of the block with the given number.
+    pub fn estimated_timestamp(&self, block_number: u64) -> Option<chrono::DateTime<chrono::Utc>> {
+        let blocks =
+            i64::try_from(block_number).ok()? - i64::try_from(self.sample_block_height).ok()?;
+        let duration_per_block =
+            chrono::Duration::milliseconds(self.avg_block_time_in_msecs.try_into().ok()?);
+        Some(self.sample_timestamp + duration_per_block * i32::try_from(blocks).ok()?)
+    }
+}
+
 /// Chain-specific configuration.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ChainConfig {
     pub caip2: Option<String>,
+    /// Other names that indexers report for this chain, e.g. `ethereum` for
+    /// `mainnet`. Data about the chain is stored under its own name.
+    #[serde(default)]
+    pub aliases: Vec<String>,
     /// Specifies an approximation of the standard block time for this chain, to
     /// approximate block timestamps.
     #[serde(flatten, default)]
@@ -46,6 +68,77 @@ pub struct ChainConfig {
     /// for the block number.
     #[serde(default)]
     pub block_explorer_url_template_for_block: Option<BlockExplorerUrlTemplateForBlock>,
+    /// JSON-RPC endpoint of a node for this chain. If set, the block hashes
+    /// that indexers report alongside PoIs are verified against it.
+    #[serde(default)]
+    pub rpc_url: Option<Url>,
+}
+
+/// Automatic divergence investigations, launched whenever indexers start
+/// disagreeing about the live PoIs of a subgraph deployment.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoInvestigationConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Upper limit on the number of pending divergence investigation
+    /// requests. No new investigations are launched while it's reached.
+    #[serde(default = "AutoInvestigationConfig::default_max_pending_requests")]
+    pub max_pending_requests: u64,
+    #[serde(default = "AutoInvestigationConfig::default_true")]
+    pub query_block_caches: bool,
+    #[serde(default = "AutoInvestigationConfig::default_true")]
+    pub query_eth_call_caches: bool,
+    #[serde(default = "AutoInvestigationConfig::default_true")]
+    pub query_entity_changes: bool,
+}
+
+impl AutoInvestigationConfig {
+    fn default_max_pending_requests() -> u64 {
+        2
+    }
+
+    fn default_true() -> bool {
+        true
+    }
+}
+
+impl Default for AutoInvestigationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_pending_requests: Self::default_max_pending_requests(),
+            query_block_caches: true,
+            query_eth_call_caches: true,
+            query_entity_changes: true,
+        }
+    }
+}
+
+/// Limits on the Prometheus metrics that Graphix exports.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsConfig {
+    /// Upper limit on the number of indexers whose metrics are labeled with
+    /// their own address, to keep the number of time series in check on big
+    /// networks. The first indexers, in the order of `sources`, get their own
+    /// label, and the metrics of all others are aggregated under `other`.
+    /// Unlimited by default.
+    #[serde(default)]
+    pub max_indexer_labels: Option<usize>,
+}
+
+impl ChainConfig {
+    /// Whether the chain is EVM-compatible.
+    pub fn is_evm(&self) -> bool {
+        is_evm_chain(self.caip2.as_deref())
+    }
+}
+
+/// Whether the chain with the given CAIP-2 ID is EVM-compatible. Chains
+/// without a known ID are assumed to be.
+pub fn is_evm_chain(caip2: Option<&str>) -> bool {
+    caip2.map_or(true, |caip2| caip2.starts_with("eip155:"))
 }
 
 /// A [`serde`]-compatible representation of Graphix's YAML configuration file.
@@ -64,6 +157,62 @@ pub struct Config {
     pub block_choice_policy: BlockChoicePolicy,
     #[serde(default = "Config::default_polling_period_in_seconds")]
     pub polling_period_in_seconds: u64,
+    /// Tiers of subgraph deployments that are cross-checked at different
+    /// frequencies. All deployments are cross-checked every polling round if
+    /// empty.
+    #[serde(default)]
+    pub check_tiers: Vec<CheckTierConfig>,
+    #[serde(default)]
+    pub auto_investigations: AutoInvestigationConfig,
+    /// Wall-clock limit on a single divergence investigation, after which
+    /// it's cancelled so that it doesn't hold up the ones queued after it.
+    #[serde(default = "Config::default_investigation_timeout_in_seconds")]
+    pub investigation_timeout_in_seconds: u64,
+    /// Stores the raw response bodies returned by indexers during all
+    /// divergence investigations, as if each of them requested it. Meant for
+    /// debugging, as it takes a lot of space.
+    #[serde(default)]
+    pub capture_raw_indexer_responses: bool,
+    /// PEM-encoded Ed25519 private key to sign the reports of completed
+    /// divergence investigations with, e.g. as generated by `openssl genpkey
+    /// -algorithm ed25519`. The file is read whenever a report is signed, so
+    /// that rotated keys are picked up without a restart.
+    #[serde(default)]
+    pub report_signing_key_file: Option<PathBuf>,
+    /// IPFS node to fetch subgraph manifests from, through its RPC API.
+    #[serde(default = "Config::default_ipfs_url")]
+    pub ipfs_url: Url,
+    /// How often indexers are pinged to track their uptime.
+    #[serde(default = "Config::default_uptime_probe_interval_in_seconds")]
+    pub uptime_probe_interval_in_seconds: u64,
+    /// Upper limit on the number of concurrent `indexingStatuses` requests.
+    #[serde(default = "Config::default_max_concurrent_indexing_status_requests")]
+    pub max_concurrent_indexing_status_requests: usize,
+    /// Upper limit on the number of concurrent requests to indexers, across
+    /// all kinds of queries of the main loop.
+    #[serde(default = "Config::default_max_concurrent_indexer_requests")]
+    pub max_concurrent_indexer_requests: usize,
+    /// Upper limit on the number of concurrent PoI requests to a single
+    /// indexer, so that indexers with many deployments take turns with the
+    /// others.
+    #[serde(default = "Config::default_max_concurrent_requests_per_indexer")]
+    pub max_concurrent_requests_per_indexer: usize,
+    /// HTTP(S) proxy to send all requests to indexers through, unless an
+    /// indexer source overrides it. If unset, the `HTTP_PROXY`,
+    /// `HTTPS_PROXY` and `NO_PROXY` environment variables are honored.
+    #[serde(default)]
+    pub indexer_proxy: Option<Url>,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+
+    // Notification options
+    // --------------------
+    /// Groups of subgraph deployments that notifiers can treat differently.
+    #[serde(default)]
+    pub deployment_tiers: Vec<DeploymentTierConfig>,
+    /// Where to send notifications about PoI divergences.
+    #[serde(default)]
+    pub notifiers: Vec<NotifierConfig>,
 }
 
 impl Default for Config {
@@ -73,12 +222,27 @@ impl Default for Config {
             sources: Default::default(),
             block_choice_policy: Default::default(),
             polling_period_in_seconds: Self::default_polling_period_in_seconds(),
+            check_tiers: Default::default(),
+            auto_investigations: Default::default(),
+            investigation_timeout_in_seconds: Self::default_investigation_timeout_in_seconds(),
+            capture_raw_indexer_responses: false,
+            report_signing_key_file: None,
+            ipfs_url: Self::default_ipfs_url(),
+            uptime_probe_interval_in_seconds: Self::default_uptime_probe_interval_in_seconds(),
+            max_concurrent_indexing_status_requests:
+                Self::default_max_concurrent_indexing_status_requests(),
+            max_concurrent_indexer_requests: Self::default_max_concurrent_indexer_requests(),
+            max_concurrent_requests_per_indexer: Self::default_max_concurrent_requests_per_indexer(
+            ),
+            indexer_proxy: None,
+            metrics: Default::default(),
+            deployment_tiers: Default::default(),
+            notifiers: Default::default(),
         }
     }
 }
 
 impl Config {
-    #[cfg(test)]
     pub fn read(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
         use anyhow::Context;
 
@@ -130,9 +294,295 @@ impl Config {
             .collect()
     }
 
+    /// Adds indexers that were registered at runtime through the GraphQL API
+    /// as [`ConfigSource::Indexer`] sources, so they're picked up by
+    /// [`config_to_indexers`]. Indexers with invalid endpoints are skipped,
+    /// and indexers registered in several workspaces are only added once.
+    pub fn add_registered_indexers(&mut self, registered_indexers: Vec<RegisteredIndexer>) {
+        let mut added = HashSet::new();
+        for indexer in registered_indexers {
+            if added.contains(&indexer.address) {
+                continue;
+            }
+            match Url::parse(&indexer.index_node_endpoint) {
+                Ok(index_node_endpoint) => {
+                    added.insert(indexer.address);
+                    self.sources.push(ConfigSource::Indexer(IndexerConfig {
+                        name: indexer.name,
+                        address: indexer.address,
+                        index_node_endpoint,
+                        auth: None,
+                        proxy: None,
+                        headers: Default::default(),
+                        management_endpoint: None,
+                        tags: vec![],
+                    }))
+                }
+                Err(err) => warn!(
+                    indexer_address = %indexer.address,
+                    error = %err,
+                    "Skipping registered indexer with invalid endpoint"
+                ),
+            }
+        }
+    }
+
+    /// The tags that `indexer` and `indexerByAddress` sources assign to
+    /// indexers, by indexer address.
+    pub fn indexer_tags(&self) -> Vec<(IndexerAddress, Vec<String>)> {
+        self.sources
+            .iter()
+            .filter_map(|source| match source {
+                ConfigSource::Indexer(config) => Some((config.address, config.tags.clone())),
+                ConfigSource::IndexerByAddress(config) => {
+                    Some((config.address, config.tags.clone()))
+                }
+                _ => None,
+            })
+            .filter(|(_, tags)| !tags.is_empty())
+            .collect()
+    }
+
+    /// The name of the first deployment tier that contains the given
+    /// deployment, if any.
+    pub fn deployment_tier(&self, deployment: &IpfsCid) -> Option<&str> {
+        let deployment = deployment.to_string();
+        self.deployment_tiers
+            .iter()
+            .find(|tier| tier.deployments.contains(&deployment))
+            .map(|tier| tier.name.as_str())
+    }
+
+    /// The key to sign divergence investigation reports with, if any.
+    pub fn report_signer(&self) -> anyhow::Result<Option<Signer>> {
+        self.report_signing_key_file
+            .as_deref()
+            .map(Signer::from_pem_file)
+            .transpose()
+    }
+
+    /// Reads all secrets given as files, e.g. mounted Kubernetes or Docker
+    /// secrets, into the corresponding inline fields. Meant to be called
+    /// whenever the configuration is (re)loaded, so that rotated secrets are
+    /// picked up.
+    pub fn load_secrets(&mut self) -> anyhow::Result<()> {
+        for source in &mut self.sources {
+            let auth = match source {
+                ConfigSource::Indexer(config) => config.auth.as_mut(),
+                ConfigSource::IndexerByAddress(config) => config.auth.as_mut(),
+                _ => None,
+            };
+            match auth {
+                Some(IndexerAuth::Bearer {
+                    token,
+                    token_file: Some(path),
+                }) => *token = read_secret_file(path)?,
+                Some(IndexerAuth::Basic {
+                    password,
+                    password_file: Some(path),
+                    ..
+                }) => *password = Some(read_secret_file(path)?),
+                _ => {}
+            }
+        }
+        for notifier in &mut self.notifiers {
+            match notifier {
+                NotifierConfig::PagerDuty(PagerDutyConfig {
+                    routing_key,
+                    routing_key_file: Some(path),
+                    ..
+                }) => *routing_key = read_secret_file(path)?,
+                NotifierConfig::Telegram(TelegramConfig {
+                    bot_token,
+                    bot_token_file: Some(path),
+                    ..
+                }) => *bot_token = read_secret_file(path)?,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
     fn default_polling_period_in_seconds() -> u64 {
         120
     }
+
+    fn default_investigation_timeout_in_seconds() -> u64 {
+        60 * 60
+    }
+
+    fn default_ipfs_url() -> Url {
+        "https://ipfs.network.thegraph.com".parse().unwrap()
+    }
+
+    fn default_uptime_probe_interval_in_seconds() -> u64 {
+        60
+    }
+
+    fn default_max_concurrent_indexing_status_requests() -> usize {
+        50
+    }
+
+    fn default_max_concurrent_indexer_requests() -> usize {
+        100
+    }
+
+    fn default_max_concurrent_requests_per_indexer() -> usize {
+        4
+    }
+}
+
+/// Reads a secret from the given file, without the trailing newline that
+/// most tools add.
+pub fn read_secret_file(path: &Path) -> anyhow::Result<String> {
+    use anyhow::Context;
+
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read secret file {}", path.display()))?;
+    Ok(contents.trim_end_matches(['\n', '\r']).to_string())
+}
+
+/// The prefix of environment variables that override configuration fields,
+/// e.g. `GRAPHIX_POLLING_PERIOD_IN_SECONDS`.
+pub const CONFIG_ENV_VAR_PREFIX: &str = "GRAPHIX_";
+
+/// A configuration field set through an environment variable. Nested fields
+/// are separated by `__`, e.g. `GRAPHIX_CHAINS__MAINNET__RPC_URL` or
+/// `GRAPHIX_SOURCES__0__INDEX_NODE_ENDPOINT`, where numbers index into
+/// lists.
+#[derive(Debug, Clone)]
+struct EnvOverride {
+    var: String,
+    path: Vec<String>,
+    value: serde_json::Value,
+}
+
+/// Parses the environment variables that set configuration fields, i.e.
+/// those with [`CONFIG_ENV_VAR_PREFIX`] whose first key is a top-level field
+/// of [`Config`]. Others, like `GRAPHIX_DB_URL`, are left to the CLI.
+fn env_overrides(vars: impl IntoIterator<Item = (String, String)>) -> Vec<EnvOverride> {
+    let root_schema = schemars::schema_for!(Config);
+    let fields: Vec<&String> = root_schema
+        .schema
+        .object
+        .as_ref()
+        .map(|object| object.properties.keys().collect())
+        .unwrap_or_default();
+
+    let mut overrides: Vec<EnvOverride> = vars
+        .into_iter()
+        .filter_map(|(var, value)| {
+            let mut path: Vec<String> = var
+                .strip_prefix(CONFIG_ENV_VAR_PREFIX)?
+                .split("__")
+                .map(str::to_string)
+                .collect();
+            let field = fields
+                .iter()
+                .find(|field| normalized_key(field) == normalized_key(&path[0]))?;
+            path[0] = field.to_string();
+
+            // Values are JSON if they parse as such, e.g. numbers, booleans
+            // and whole lists, and strings otherwise. Quoting forces strings.
+            let value =
+                serde_json::from_str(&value).unwrap_or_else(|_| serde_json::Value::String(value));
+            Some(EnvOverride { var, path, value })
+        })
+        .collect();
+    // Whole lists or objects first, so that nested fields are set within
+    // them rather than overwritten.
+    overrides.sort_by(|a, b| (a.path.len(), &a.var).cmp(&(b.path.len(), &b.var)));
+
+    overrides
+}
+
+/// Sets the field that `env_override` refers to in `config`, a JSON
+/// representation of [`Config`]. Keys match existing ones regardless of case
+/// and separators, e.g. `ARBITRUM_ONE` matches an `arbitrum-one` chain, and
+/// are camel-cased otherwise. Lists are padded with `null` as necessary.
+fn apply_env_override(config: &mut serde_json::Value, env_override: &EnvOverride) {
+    use serde_json::Value;
+
+    let mut target = config;
+    for key in &env_override.path {
+        target = match key.parse::<usize>() {
+            Ok(index) => {
+                if !target.is_array() {
+                    *target = Value::Array(vec![]);
+                }
+                let list = target.as_array_mut().unwrap();
+                if list.len() <= index {
+                    list.resize(index + 1, Value::Null);
+                }
+                &mut list[index]
+            }
+            Err(_) => {
+                if !target.is_object() {
+                    *target = Value::Object(Default::default());
+                }
+                let object = target.as_object_mut().unwrap();
+                let key = object
+                    .keys()
+                    .find(|existing| normalized_key(existing) == normalized_key(key))
+                    .cloned()
+                    .unwrap_or_else(|| camel_case(key));
+                object.entry(key).or_insert(Value::Null)
+            }
+        };
+    }
+    *target = env_override.value.clone();
+}
+
+/// The effective configuration as JSON: the stored one, or the default one
+/// if there's none, with the fields set through `GRAPHIX_*` environment
+/// variables applied on top. `None` if there's neither.
+pub fn effective_config_json(
+    stored: Option<serde_json::Value>,
+) -> anyhow::Result<Option<serde_json::Value>> {
+    merge_env_overrides(stored, std::env::vars())
+}
+
+fn merge_env_overrides(
+    stored: Option<serde_json::Value>,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> anyhow::Result<Option<serde_json::Value>> {
+    let overrides = env_overrides(vars);
+    if overrides.is_empty() {
+        return Ok(stored);
+    }
+
+    let mut config = match stored {
+        Some(json) => json,
+        None => serde_json::to_value(Config::default())?,
+    };
+    for env_override in &overrides {
+        apply_env_override(&mut config, env_override);
+    }
+
+    Ok(Some(config))
+}
+
+fn normalized_key(key: &str) -> String {
+    key.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// `SNAKE_CASE` to `camelCase`.
+fn camel_case(key: &str) -> String {
+    let mut words = key.split('_').filter(|word| !word.is_empty());
+    let mut camel = words.next().unwrap_or_default().to_lowercase();
+    for word in words {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            camel.push(first.to_ascii_uppercase());
+            camel.push_str(&chars.as_str().to_lowercase());
+        }
+    }
+
+    camel
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
@@ -141,6 +591,26 @@ pub struct IndexerConfig {
     pub name: Option<String>,
     pub address: IndexerAddress,
     pub index_node_endpoint: Url,
+    /// Credentials for index nodes whose status API requires authentication.
+    #[serde(default)]
+    pub auth: Option<IndexerAuth>,
+    /// HTTP(S) proxy to send requests to this indexer through, overriding
+    /// [`Config::indexer_proxy`].
+    #[serde(default)]
+    pub proxy: Option<Url>,
+    /// Static headers to attach to all requests to this indexer, e.g. API
+    /// keys required to reach its status endpoint.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// URL of the indexer management API of the indexer's `indexer-agent`,
+    /// e.g. `http://indexer-agent:18000`. When set, Graphix collects the
+    /// indexer's active allocations from it.
+    #[serde(default)]
+    pub management_endpoint: Option<Url>,
+    /// Arbitrary tags of the indexer, e.g. `upgrade-canary` or `eu-west`,
+    /// which queries can filter by and notifiers can be restricted to.
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl IndexerId for IndexerConfig {
@@ -157,6 +627,20 @@ impl IndexerId for IndexerConfig {
 #[serde(rename_all = "camelCase")]
 pub struct IndexerByAddressConfig {
     pub address: IndexerAddress,
+    /// Credentials for index nodes whose status API requires authentication.
+    #[serde(default)]
+    pub auth: Option<IndexerAuth>,
+    /// HTTP(S) proxy to send requests to this indexer through, overriding
+    /// [`Config::indexer_proxy`].
+    #[serde(default)]
+    pub proxy: Option<Url>,
+    /// Static headers to attach to all requests to this indexer, e.g. API
+    /// keys required to reach its status endpoint.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Arbitrary tags of the indexer, like [`IndexerConfig::tags`].
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
@@ -184,7 +668,13 @@ pub enum NetworkSubgraphQuery {
 pub struct InterceptorConfig {
     pub name: String,
     pub target: IndexerAddress,
-    pub poi_byte: u8,
+    /// If set, all PoIs returned by the target indexer are replaced with a
+    /// repetition of this byte.
+    #[serde(default)]
+    pub poi_byte: Option<u8>,
+    /// Faults to inject into requests to the target indexer.
+    #[serde(default)]
+    pub faults: FaultProfile,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
@@ -201,16 +691,23 @@ pub async fn config_to_indexers(
     metrics: &PrometheusMetrics,
 ) -> anyhow::Result<Vec<Arc<dyn IndexerClient>>> {
     let mut indexers: Vec<Arc<dyn IndexerClient>> = vec![];
+    let indexer_proxy = config.indexer_proxy.as_ref();
 
     // First, configure all the real, static indexers.
     for config in config.indexers() {
         info!(indexer_address = %config.address_string(), "Configuring indexer");
-        indexers.push(Arc::new(RealIndexer::new(
-            config.name().map(|s| s.into_owned()),
-            config.address(),
-            config.index_node_endpoint.to_string(),
-            metrics.public_proofs_of_indexing_requests.clone(),
-        )));
+        indexers.push(Arc::new(
+            RealIndexer::new(
+                config.name().map(|s| s.into_owned()),
+                config.address(),
+                config.index_node_endpoint.to_string(),
+                metrics.public_proofs_of_indexing_requests.clone(),
+            )
+            .with_auth(config.auth.clone())
+            .with_proxy(config.proxy.as_ref().or(indexer_proxy))?
+            .with_headers(&config.headers)?
+            .with_management_endpoint(config.management_endpoint.as_ref()),
+        ));
     }
 
     // Then, configure the network subgraphs, if required, resulting in "dynamic"
@@ -220,7 +717,8 @@ pub async fn config_to_indexers(
         let network_subgraph = NetworkSubgraphClient::new(
             config.endpoint.as_str().parse()?,
             metrics.public_proofs_of_indexing_requests.clone(),
-        );
+        )
+        .with_indexer_proxy(indexer_proxy.cloned());
         let network_subgraph_indexers_res = match config.query {
             NetworkSubgraphQuery::ByAllocations => {
                 network_subgraph.indexers_by_allocations(config.limit).await
@@ -263,11 +761,14 @@ pub async fn config_to_indexers(
                 .endpoint
                 .parse()?,
             metrics.public_proofs_of_indexing_requests.clone(),
-        );
+        )
+        .with_indexer_proxy(indexer_config.proxy.clone().or(indexer_proxy.cloned()));
         let indexer = network_subgraph
             .indexer_by_address(&indexer_config.address)
-            .await?;
-        indexers.push(indexer);
+            .await?
+            .with_auth(indexer_config.auth.clone())
+            .with_headers(&indexer_config.headers)?;
+        indexers.push(Arc::new(indexer));
     }
 
     // Finally, configure all the interceptors, referring to the real, static
@@ -278,15 +779,461 @@ pub async fn config_to_indexers(
             .iter()
             .find(|indexer| indexer.address() == config.target)
             .expect("interceptor target indexer not found");
-        indexers.push(Arc::new(IndexerInterceptor::new(
+        indexers.push(Arc::new(IndexerInterceptor::with_faults(
             target.clone(),
             config.poi_byte,
+            config.faults,
         )));
     }
 
     Ok(indexers)
 }
 
+/// A problem found in a configuration file by [`validate_config`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigDiagnostic {
+    /// Where the problem is, e.g. `sources[2].indexNodeEndpoint`.
+    pub location: String,
+    pub message: String,
+}
+
+impl ConfigDiagnostic {
+    fn new(location: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            location: location.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Checks the given YAML configuration for problems that would otherwise
+/// only surface at runtime, and reports all of them at once. The
+/// configuration is valid if there are none.
+///
+/// List items and chains are deserialized one by one, so that a single
+/// malformed item doesn't hide the problems of the others.
+pub fn validate_config(yaml: &str) -> Vec<ConfigDiagnostic> {
+    let mut value: serde_yaml::Value = match serde_yaml::from_str(yaml) {
+        Ok(value) => value,
+        Err(err) => {
+            let location = match err.location() {
+                Some(location) => format!("line {}, column {}", location.line(), location.column()),
+                None => "(root)".to_string(),
+            };
+            return vec![ConfigDiagnostic::new(location, err.to_string())];
+        }
+    };
+
+    let mut diagnostics = vec![];
+    let mut positions = ItemPositions::default();
+    if let Some(mapping) = value.as_mapping_mut() {
+        retain_valid_items::<ConfigSource>(mapping, "sources", &mut positions, &mut diagnostics);
+        retain_valid_items::<NotifierConfig>(
+            mapping,
+            "notifiers",
+            &mut positions,
+            &mut diagnostics,
+        );
+        retain_valid_items::<DeploymentTierConfig>(
+            mapping,
+            "deploymentTiers",
+            &mut positions,
+            &mut diagnostics,
+        );
+        retain_valid_items::<CheckTierConfig>(
+            mapping,
+            "checkTiers",
+            &mut positions,
+            &mut diagnostics,
+        );
+        retain_valid_chains(mapping, &mut diagnostics);
+    }
+
+    match serde_yaml::from_value::<Config>(value) {
+        Ok(config) => diagnostics.extend(semantic_diagnostics(&config, &positions)),
+        Err(err) => diagnostics.push(ConfigDiagnostic::new("(root)", err.to_string())),
+    }
+    diagnostics
+}
+
+/// Maps the positions of list items in a [`Config`] back to their positions
+/// in the configuration file, which differ once invalid items are left out.
+#[derive(Default)]
+struct ItemPositions(HashMap<&'static str, Vec<usize>>);
+
+impl ItemPositions {
+    fn location(&self, list: &str, index: usize) -> String {
+        let position = self.0.get(list).map_or(index, |positions| positions[index]);
+        format!("{}[{}]", list, position)
+    }
+}
+
+fn retain_valid_items<T: serde::de::DeserializeOwned>(
+    mapping: &mut serde_yaml::Mapping,
+    list: &'static str,
+    positions: &mut ItemPositions,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) {
+    let Some(serde_yaml::Value::Sequence(items)) = mapping.get_mut(list) else {
+        return;
+    };
+
+    let mut valid_positions = vec![];
+    let mut position = 0;
+    items.retain(|item| {
+        let result = serde_yaml::from_value::<T>(item.clone());
+        if let Err(err) = &result {
+            diagnostics.push(ConfigDiagnostic::new(
+                format!("{}[{}]", list, position),
+                err.to_string(),
+            ));
+        } else {
+            valid_positions.push(position);
+        }
+        position += 1;
+        result.is_ok()
+    });
+    positions.0.insert(list, valid_positions);
+}
+
+fn retain_valid_chains(mapping: &mut serde_yaml::Mapping, diagnostics: &mut Vec<ConfigDiagnostic>) {
+    let Some(serde_yaml::Value::Mapping(chains)) = mapping.get_mut("chains") else {
+        return;
+    };
+
+    chains.retain(|name, chain| {
+        let result = serde_yaml::from_value::<ChainConfig>(chain.clone());
+        if let Err(err) = &result {
+            let name = name.as_str().unwrap_or("?");
+            diagnostics.push(ConfigDiagnostic::new(
+                format!("chains.{}", name),
+                err.to_string(),
+            ));
+        }
+        result.is_ok()
+    });
+}
+
+/// Problems that deserialization alone doesn't catch.
+fn semantic_diagnostics(config: &Config, positions: &ItemPositions) -> Vec<ConfigDiagnostic> {
+    let mut diagnostics = vec![];
+    let mut push = |location: String, message: String| {
+        diagnostics.push(ConfigDiagnostic::new(location, message));
+    };
+    let source = |i: usize| positions.location("sources", i);
+    let notifier = |i: usize| positions.location("notifiers", i);
+    let tier = |i: usize| positions.location("deploymentTiers", i);
+    let check_tier = |i: usize| positions.location("checkTiers", i);
+
+    // Sources.
+    let mut indexer_addresses: HashMap<IndexerAddress, String> = HashMap::new();
+    let has_network_subgraph = !config.network_subgraphs().is_empty();
+    for (i, source_config) in config.sources.iter().enumerate() {
+        match source_config {
+            ConfigSource::Indexer(IndexerConfig {
+                address,
+                index_node_endpoint,
+                management_endpoint,
+                ..
+            }) => {
+                if let Some(message) = non_http_url(index_node_endpoint) {
+                    push(format!("{}.indexNodeEndpoint", source(i)), message);
+                }
+                if let Some(message) = management_endpoint.as_ref().and_then(non_http_url) {
+                    push(format!("{}.managementEndpoint", source(i)), message);
+                }
+                if let Some(other) = indexer_addresses.insert(*address, source(i)) {
+                    push(
+                        format!("{}.address", source(i)),
+                        format!("indexer address {} is also used by {}", address, other),
+                    );
+                }
+            }
+            ConfigSource::IndexerByAddress(IndexerByAddressConfig { address, .. }) => {
+                if !has_network_subgraph {
+                    push(
+                        source(i),
+                        "indexers by address are looked up in a network subgraph, but there's no \
+                         networkSubgraph source"
+                            .to_string(),
+                    );
+                }
+                if let Some(other) = indexer_addresses.insert(*address, source(i)) {
+                    push(
+                        format!("{}.address", source(i)),
+                        format!("indexer address {} is also used by {}", address, other),
+                    );
+                }
+            }
+            ConfigSource::NetworkSubgraph(NetworkSubgraphConfig { endpoint, .. }) => {
+                let message = match endpoint.parse::<Url>() {
+                    Ok(url) => non_http_url(&url),
+                    Err(err) => Some(format!("invalid URL: {}", err)),
+                };
+                if let Some(message) = message {
+                    push(format!("{}.endpoint", source(i)), message);
+                }
+            }
+            ConfigSource::Interceptor(_) => {}
+        }
+    }
+    for (i, source_config) in config.sources.iter().enumerate() {
+        let auth = match source_config {
+            ConfigSource::Indexer(indexer) => indexer.auth.as_ref(),
+            ConfigSource::IndexerByAddress(indexer) => indexer.auth.as_ref(),
+            _ => None,
+        };
+        let message = match auth {
+            Some(IndexerAuth::Bearer { token, token_file }) => {
+                secret_diagnostic("token", !token.is_empty(), token_file.is_some())
+            }
+            Some(IndexerAuth::Basic {
+                password,
+                password_file,
+                ..
+            }) if password.is_some() && password_file.is_some() => {
+                Some("set either password or passwordFile, not both".to_string())
+            }
+            _ => None,
+        };
+        if let Some(message) = message {
+            push(format!("{}.auth", source(i)), message);
+        }
+    }
+    // Interceptors may also target indexers from network subgraphs, which are
+    // only known at runtime.
+    if !has_network_subgraph {
+        for (i, source_config) in config.sources.iter().enumerate() {
+            if let ConfigSource::Interceptor(interceptor) = source_config {
+                if !indexer_addresses.contains_key(&interceptor.target) {
+                    push(
+                        format!("{}.target", source(i)),
+                        format!(
+                            "interceptor target {} doesn't match any indexer source",
+                            interceptor.target
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    // Chains, in a stable order.
+    let mut chains: Vec<(&String, &ChainConfig)> = config.chains.iter().collect();
+    chains.sort_by_key(|(name, _)| *name);
+    let mut caip2_ids: HashMap<&str, &str> = HashMap::new();
+    let mut chain_aliases: HashMap<&str, &str> = HashMap::new();
+    for (name, chain) in chains {
+        if let Some(caip2) = chain.caip2.as_deref() {
+            if !is_caip2_chain_id(caip2) {
+                push(
+                    format!("chains.{}.caip2", name),
+                    format!("unknown network {:?}, expected a CAIP-2 chain ID", caip2),
+                );
+            } else if let Some(other) = caip2_ids.insert(caip2, name) {
+                push(
+                    format!("chains.{}.caip2", name),
+                    format!("chain ID {} is also used by chains.{}", caip2, other),
+                );
+            }
+        }
+        for alias in &chain.aliases {
+            if config.chains.contains_key(alias) {
+                push(
+                    format!("chains.{}.aliases", name),
+                    format!("alias {} is also the name of a chain", alias),
+                );
+            } else if let Some(other) = chain_aliases.insert(alias, name) {
+                push(
+                    format!("chains.{}.aliases", name),
+                    format!("alias {} is also used by chains.{}", alias, other),
+                );
+            }
+        }
+        if let Some(template) = &chain.block_explorer_url_template_for_block {
+            if !template.0.contains("{block}") {
+                push(
+                    format!("chains.{}.blockExplorerUrlTemplateForBlock", name),
+                    "missing the {block} placeholder".to_string(),
+                );
+            }
+        }
+        if let Some(message) = chain.rpc_url.as_ref().and_then(non_http_url) {
+            push(format!("chains.{}.rpcUrl", name), message);
+        }
+    }
+
+    // Deployment tiers and the notifiers that refer to them.
+    let mut tier_names: HashMap<&str, String> = HashMap::new();
+    let mut tiered_deployments: HashMap<&str, String> = HashMap::new();
+    for (i, tier_config) in config.deployment_tiers.iter().enumerate() {
+        if let Some(other) = tier_names.insert(&tier_config.name, tier(i)) {
+            push(
+                format!("{}.name", tier(i)),
+                format!("tier name {:?} is also used by {}", tier_config.name, other),
+            );
+        }
+        for (j, deployment) in tier_config.deployments.iter().enumerate() {
+            let location = format!("{}.deployments[{}]", tier(i), j);
+            if let Err(err) = deployment.parse::<IpfsCid>() {
+                push(location, format!("invalid IPFS hash: {}", err));
+            } else if let Some(other) = tiered_deployments.insert(deployment, tier(i)) {
+                push(
+                    location,
+                    format!(
+                        "deployment {} is also in {}, whose tier takes precedence",
+                        deployment, other
+                    ),
+                );
+            }
+        }
+    }
+    for (i, notifier_config) in config.notifiers.iter().enumerate() {
+        let (tiers, urls): (Vec<&String>, Vec<(&str, &Url)>) = match notifier_config {
+            NotifierConfig::PagerDuty(pagerduty) => (
+                pagerduty.severities.keys().collect(),
+                vec![("eventsApiUrl", &pagerduty.events_api_url)],
+            ),
+            NotifierConfig::Discord(discord) => (
+                discord.tiers.iter().collect(),
+                vec![("webhookUrl", &discord.webhook_url)],
+            ),
+            NotifierConfig::Telegram(telegram) => (
+                telegram.tiers.iter().collect(),
+                vec![("apiUrl", &telegram.api_url)],
+            ),
+        };
+        let secret = match notifier_config {
+            NotifierConfig::PagerDuty(pagerduty) => secret_diagnostic(
+                "routingKey",
+                !pagerduty.routing_key.is_empty(),
+                pagerduty.routing_key_file.is_some(),
+            ),
+            NotifierConfig::Telegram(telegram) => secret_diagnostic(
+                "botToken",
+                !telegram.bot_token.is_empty(),
+                telegram.bot_token_file.is_some(),
+            ),
+            NotifierConfig::Discord(_) => None,
+        };
+        if let Some(message) = secret {
+            push(notifier(i), message);
+        }
+        for tier_name in tiers {
+            if !tier_names.contains_key(tier_name.as_str()) {
+                push(
+                    notifier(i),
+                    format!("unknown deployment tier {:?}", tier_name),
+                );
+            }
+        }
+        for (field, url) in urls {
+            if let Some(message) = non_http_url(url) {
+                push(format!("{}.{}", notifier(i), field), message);
+            }
+        }
+    }
+
+    // Cross-check tiers.
+    for (i, tier_config) in config.check_tiers.iter().enumerate() {
+        if i == 0 && tier_config.every_n_rounds != 1 {
+            push(
+                format!("{}.everyNRounds", check_tier(i)),
+                "the first tier must be cross-checked every round".to_string(),
+            );
+        } else if tier_config.every_n_rounds == 0 {
+            push(
+                format!("{}.everyNRounds", check_tier(i)),
+                "must be greater than 0".to_string(),
+            );
+        }
+        for (j, deployment) in tier_config.deployments.iter().enumerate() {
+            if let Err(err) = deployment.parse::<IpfsCid>() {
+                push(
+                    format!("{}.deployments[{}]", check_tier(i), j),
+                    format!("invalid IPFS hash: {}", err),
+                );
+            }
+        }
+    }
+
+    // Limits and intervals.
+    if let Some(message) = non_http_url(&config.ipfs_url) {
+        push("ipfsUrl".to_string(), message);
+    }
+    for (field, value) in [
+        ("pollingPeriodInSeconds", config.polling_period_in_seconds),
+        (
+            "uptimeProbeIntervalInSeconds",
+            config.uptime_probe_interval_in_seconds,
+        ),
+        (
+            "maxConcurrentIndexingStatusRequests",
+            config.max_concurrent_indexing_status_requests as u64,
+        ),
+        (
+            "maxConcurrentIndexerRequests",
+            config.max_concurrent_indexer_requests as u64,
+        ),
+        (
+            "maxConcurrentRequestsPerIndexer",
+            config.max_concurrent_requests_per_indexer as u64,
+        ),
+    ] {
+        if value == 0 {
+            push(field.to_string(), "must be greater than 0".to_string());
+        }
+    }
+    if config.auto_investigations.enabled && config.auto_investigations.max_pending_requests == 0 {
+        push(
+            "autoInvestigations.maxPendingRequests".to_string(),
+            "automatic investigations are enabled, but none can ever be launched".to_string(),
+        );
+    }
+
+    diagnostics
+}
+
+/// Secrets must be given either inline or as a file, but not both.
+fn secret_diagnostic(field: &str, inline: bool, file: bool) -> Option<String> {
+    match (inline, file) {
+        (true, true) => Some(format!("set either {} or {}File, not both", field, field)),
+        (false, false) => Some(format!("missing {} or {}File", field, field)),
+        _ => None,
+    }
+}
+
+fn non_http_url(url: &Url) -> Option<String> {
+    match url.scheme() {
+        "http" | "https" => None,
+        scheme => Some(format!(
+            "expected an HTTP(S) URL, found scheme {:?}",
+            scheme
+        )),
+    }
+}
+
+/// Whether `id` is a well-formed CAIP-2 chain ID, e.g. `eip155:1`.
+fn is_caip2_chain_id(id: &str) -> bool {
+    let Some((namespace, reference)) = id.split_once(':') else {
+        return false;
+    };
+    (3..=8).contains(&namespace.len())
+        && namespace
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && (1..=32).contains(&reference.len())
+        && reference
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -297,4 +1244,233 @@ mod tests {
         Config::read("../../configs/network.graphix.yml").unwrap();
         Config::read("../../configs/readonly.graphix.yml").unwrap();
     }
+
+    #[test]
+    fn example_configs_are_valid() {
+        for path in [
+            "../../configs/testnet.graphix.yml",
+            "../../configs/network.graphix.yml",
+            "../../configs/readonly.graphix.yml",
+        ] {
+            let yaml = std::fs::read_to_string(path).unwrap();
+            assert_eq!(validate_config(&yaml), vec![], "{}", path);
+        }
+    }
+
+    #[test]
+    fn validation_reports_all_problems() {
+        let diagnostics = validate_config(
+            r#"
+chains:
+  mainnet:
+    caip2: "eip155:1"
+  other-mainnet:
+    caip2: "eip155:1"
+  weird:
+    caip2: "not a chain"
+sources:
+  - type: indexer
+    address: "0x0101010101010101010101010101010101010101"
+    indexNodeEndpoint: https://index-node.example.com/graphql
+  - type: indexer
+    address: "0x0202020202020202020202020202020202020202"
+  - type: indexer
+    address: "0x0101010101010101010101010101010101010101"
+    indexNodeEndpoint: ftp://index-node.example.com/graphql
+  - type: interceptor
+    name: evil
+    target: "0x0303030303030303030303030303030303030303"
+deploymentTiers:
+  - name: critical
+    deployments: [QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA, foo]
+notifiers:
+  - type: discord
+    webhookUrl: https://discord.example.com/webhook
+    tiers: [important]
+checkTiers:
+  - everyNRounds: 2
+    deployments: [foo]
+maxConcurrentIndexerRequests: 0
+"#,
+        );
+        let locations: Vec<&str> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.location.as_str())
+            .collect();
+        assert_eq!(
+            locations,
+            vec![
+                // The missing endpoint doesn't hide the problems after it, and
+                // the positions of later items are preserved.
+                "sources[1]",
+                "sources[2].indexNodeEndpoint",
+                "sources[2].address",
+                "sources[3].target",
+                "chains.other-mainnet.caip2",
+                "chains.weird.caip2",
+                "deploymentTiers[0].deployments[1]",
+                "notifiers[0]",
+                "checkTiers[0].everyNRounds",
+                "checkTiers[0].deployments[0]",
+                "maxConcurrentIndexerRequests",
+            ]
+        );
+    }
+
+    #[test]
+    fn validation_reports_syntax_errors_with_line_numbers() {
+        let diagnostics = validate_config("sources:\n  - type: indexer\n   bad: [");
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].location.starts_with("line "));
+    }
+
+    #[test]
+    fn evm_chains_by_caip2() {
+        assert!(is_evm_chain(Some("eip155:1")));
+        assert!(is_evm_chain(None));
+        assert!(!is_evm_chain(Some("near:mainnet")));
+        assert!(!is_evm_chain(Some("cosmos:osmosis-1")));
+    }
+
+    #[test]
+    fn registered_indexers_become_sources() {
+        let registered_indexer = |endpoint: &str| RegisteredIndexer {
+            id: 1,
+            address: [1; 20].into(),
+            name: Some("foo".to_string()),
+            index_node_endpoint: endpoint.to_string(),
+            created_at: chrono::Utc::now().naive_utc(),
+            workspace_id: None,
+        };
+
+        let mut config = Config::default();
+        config.add_registered_indexers(vec![
+            registered_indexer("not a url"),
+            registered_indexer("http://localhost:8030/graphql"),
+            registered_indexer("http://localhost:8030/graphql"),
+        ]);
+
+        let indexers = config.indexers();
+        assert_eq!(indexers.len(), 1);
+        assert_eq!(indexers[0].address, [1; 20].into());
+    }
+
+    #[test]
+    fn indexer_sources_with_auth() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+sources:
+  - type: indexer
+    address: "0x0101010101010101010101010101010101010101"
+    indexNodeEndpoint: https://index-node.example.com/graphql
+    auth:
+      type: bearer
+      token: secret
+  - type: indexer
+    address: "0x0202020202020202020202020202020202020202"
+    indexNodeEndpoint: https://index-node.example.com/graphql
+"#,
+        )
+        .unwrap();
+
+        let indexers = config.indexers();
+        assert!(matches!(
+            &indexers[0].auth,
+            Some(IndexerAuth::Bearer { token, .. }) if token == "secret"
+        ));
+        assert!(indexers[1].auth.is_none());
+    }
+
+    #[test]
+    fn secrets_are_loaded_from_files() {
+        let path = std::env::temp_dir().join(format!("graphix-token-{}", std::process::id()));
+        std::fs::write(&path, "secret\n").unwrap();
+
+        let mut config: Config = serde_yaml::from_str(&format!(
+            r#"
+sources:
+  - type: indexer
+    address: "0x0101010101010101010101010101010101010101"
+    indexNodeEndpoint: https://index-node.example.com/graphql
+    auth:
+      type: bearer
+      tokenFile: {}
+"#,
+            path.display()
+        ))
+        .unwrap();
+        config.load_secrets().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            &config.indexers()[0].auth,
+            Some(IndexerAuth::Bearer { token, .. }) if token == "secret"
+        ));
+        // Missing files are an error rather than an empty secret.
+        assert!(config.load_secrets().is_err());
+    }
+
+    #[test]
+    fn env_vars_override_config_fields() {
+        let stored = serde_json::json!({
+            "chains": { "arbitrum-one": { "caip2": "eip155:42161" } },
+            "pollingPeriodInSeconds": 60,
+        });
+        let vars = [
+            ("GRAPHIX_POLLING_PERIOD_IN_SECONDS", "30"),
+            (
+                "GRAPHIX_CHAINS__ARBITRUM_ONE__RPC_URL",
+                "https://rpc.example.com",
+            ),
+            ("GRAPHIX_SOURCES__0__TYPE", "indexer"),
+            ("GRAPHIX_SOURCES__0__NAME", "\"42\""),
+            (
+                "GRAPHIX_SOURCES__0__ADDRESS",
+                "0x0101010101010101010101010101010101010101",
+            ),
+            (
+                "GRAPHIX_SOURCES__0__INDEX_NODE_ENDPOINT",
+                "https://index-node.example.com/graphql",
+            ),
+            ("GRAPHIX_AUTO_INVESTIGATIONS", r#"{"enabled": true}"#),
+            ("GRAPHIX_AUTO_INVESTIGATIONS__MAX_PENDING_REQUESTS", "5"),
+            // Not configuration fields.
+            ("GRAPHIX_DB_URL", "postgres://localhost"),
+            ("POLLING_PERIOD_IN_SECONDS", "1"),
+        ]
+        .map(|(var, value)| (var.to_string(), value.to_string()));
+
+        let json = merge_env_overrides(Some(stored), vars).unwrap().unwrap();
+        assert!(json.get("dbUrl").is_none());
+        let config: Config = serde_json::from_value(json).unwrap();
+        assert_eq!(config.polling_period_in_seconds, 30);
+        let chain = &config.chains["arbitrum-one"];
+        assert_eq!(chain.caip2.as_deref(), Some("eip155:42161"));
+        assert_eq!(
+            chain.rpc_url.as_ref().unwrap().host_str(),
+            Some("rpc.example.com")
+        );
+        let indexers = config.indexers();
+        assert_eq!(indexers[0].name.as_deref(), Some("42"));
+        assert_eq!(indexers[0].address, [1; 20].into());
+        assert!(config.auto_investigations.enabled);
+        assert_eq!(config.auto_investigations.max_pending_requests, 5);
+    }
+
+    #[test]
+    fn env_vars_alone_make_a_config() {
+        assert!(merge_env_overrides(None, vec![]).unwrap().is_none());
+
+        let vars = vec![(
+            "GRAPHIX_IPFS_URL".to_string(),
+            "https://ipfs.example.com".to_string(),
+        )];
+        let json = merge_env_overrides(None, vars).unwrap().unwrap();
+        let config: Config = serde_json::from_value(json).unwrap();
+        assert_eq!(config.ipfs_url.host_str(), Some("ipfs.example.com"));
+        assert_eq!(
+            config.polling_period_in_seconds,
+            Config::default_polling_period_in_seconds()
+        );
+    }
 }
@@ -0,0 +1,23 @@
+//! Periodic refreshing of the daily agreement rollups, from which trend
+//! queries are served without aggregating over all PoIs.
+
+use std::time::Duration;
+
+use graphix_store::Store;
+use tracing::*;
+
+/// The current day's rollups are at most this much behind.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Refreshes the daily agreement rollups every [`REFRESH_INTERVAL`]. Never
+/// returns.
+pub async fn run_daily_agreement_refresher(store: Store) {
+    loop {
+        match store.refresh_daily_agreement().await {
+            Ok(()) => info!("Refreshed daily agreement rollups"),
+            Err(err) => error!(error = %err, "Failed to refresh daily agreement rollups"),
+        }
+
+        tokio::time::sleep(REFRESH_INTERVAL).await;
+    }
+}
@@ -0,0 +1,82 @@
+//! Periodic syncing of the display metadata and curation signal of subgraph
+//! deployments from the network subgraph, so that API consumers can label
+//! deployments without a second data source.
+
+use std::time::{Duration, Instant};
+
+use graphix_network_sg_client::{NetworkSubgraphClient, SubgraphDeploymentMetadata};
+use graphix_store::models::FetchedSgDeploymentMetadata;
+use graphix_store::Store;
+use tracing::*;
+
+/// How often the metadata of all deployments is refreshed. Display names and
+/// signal change rarely, so there's no need to do it on every main loop
+/// iteration.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Periodically refreshes the network subgraph metadata of all deployments
+/// in the database.
+#[derive(Debug, Default)]
+pub struct DeploymentMetadataSync {
+    last_refreshed_at: Option<Instant>,
+}
+
+impl DeploymentMetadataSync {
+    /// Fetches and stores the metadata of all deployments, unless that was
+    /// already done within the last [`REFRESH_INTERVAL`].
+    #[instrument(skip_all)]
+    pub async fn refresh(
+        &mut self,
+        store: &Store,
+        network_subgraph: &NetworkSubgraphClient,
+    ) -> anyhow::Result<()> {
+        if self
+            .last_refreshed_at
+            .is_some_and(|refreshed_at| refreshed_at.elapsed() < REFRESH_INTERVAL)
+        {
+            return Ok(());
+        }
+
+        let ipfs_hashes: Vec<String> = store
+            .sg_deployments(Default::default())
+            .await?
+            .into_iter()
+            .map(|deployment| deployment.cid.to_string())
+            .collect();
+        let metadata: Vec<_> = network_subgraph
+            .subgraph_deployments_metadata(&ipfs_hashes)
+            .await?
+            .into_iter()
+            .filter_map(to_fetched_metadata)
+            .collect();
+        store
+            .write_sg_deployment_network_subgraph_metadata(&metadata)
+            .await?;
+        self.last_refreshed_at = Some(Instant::now());
+
+        info!(
+            deployments = ipfs_hashes.len(),
+            published = metadata.len(),
+            "Refreshed deployment metadata from the network subgraph"
+        );
+        Ok(())
+    }
+}
+
+fn to_fetched_metadata(
+    metadata: SubgraphDeploymentMetadata,
+) -> Option<FetchedSgDeploymentMetadata> {
+    let deployment = match metadata.ipfs_hash.parse() {
+        Ok(deployment) => deployment,
+        Err(error) => {
+            warn!(ipfs_hash = metadata.ipfs_hash, %error, "Invalid IPFS hash in the network subgraph");
+            return None;
+        }
+    };
+    Some(FetchedSgDeploymentMetadata {
+        deployment,
+        display_name: metadata.display_name,
+        image: metadata.image,
+        signal_amount: metadata.signal_amount,
+    })
+}
@@ -0,0 +1,367 @@
+//! Evidence bundles for PoI disputes. A bundle packages everything that an
+//! arbitrator needs to judge a divergence that a bisection run confirmed into
+//! a single tar archive:
+//!
+//! - `manifest.json` describes the divergence, i.e. the deployment, the
+//!   diverging block, both PoIs and the indexers that produced them, and
+//!   lists all other files with their SHA-256 hashes.
+//! - `manifest.sig` is the raw Ed25519 signature of `manifest.json`, by the
+//!   key whose public half is listed in the manifest.
+//! - `bisection-trace.json` lists all blocks that were queried while
+//!   bisecting.
+//! - `evidence/<indexer>/<kind>.json` holds the `graph-node` debugging data
+//!   collected from each indexer at the diverging block.
+//! - `diffs/<kind>.json` holds the differences between the entity changes
+//!   and `eth_call` caches of the two indexers, if both returned them.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, Context as _};
+use async_graphql::dataloader::Loader;
+use graphix_common_types::BisectionEvidenceKind;
+use graphix_store::models::{self, BisectionEvidence, IntId};
+use graphix_store::{ReportStore, Store, StoreLoader};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::signing::Signer;
+use crate::GRAPHIX_VERSION;
+
+/// Bumped whenever the layout of bundles changes.
+const FORMAT_VERSION: u32 = 1;
+
+/// Builds the evidence bundle of the given bisection run, which must have
+/// found the diverging block, and returns the tar archive.
+pub async fn evidence_bundle(
+    store: &Store,
+    reports: &dyn ReportStore,
+    bisection_run_uuid: &Uuid,
+    signer: &Signer,
+) -> anyhow::Result<Vec<u8>> {
+    let run = reports
+        .bisection_run(bisection_run_uuid)
+        .await?
+        .with_context(|| format!("no bisection run {bisection_run_uuid}"))?;
+    if let Some(error) = &run.error {
+        anyhow::bail!("bisection run {bisection_run_uuid} didn't confirm a divergence: {error}");
+    }
+    let attempts = reports.bisection_attempts(&run.uuid).await?;
+    let evidence = reports.bisection_evidence(&run.uuid).await?;
+
+    let deployment = match run.sg_deployment_id {
+        Some(id) => load::<models::SgDeployment>(store, id).await?,
+        None => None,
+    };
+    let network = match &deployment {
+        Some(deployment) => load::<models::Network>(store, deployment.network_id).await?,
+        None => None,
+    };
+    let diverging_block_hash = attempts
+        .iter()
+        .find(|attempt| attempt.block_number == run.upper_bound)
+        .and_then(|attempt| attempt.block_hash.clone());
+
+    let mut indexers = vec![];
+    let mut addresses = HashMap::new();
+    for (indexer_id, poi) in [(run.indexer1_id, run.poi1), (run.indexer2_id, run.poi2)] {
+        let indexer = match indexer_id {
+            Some(id) => load::<models::Indexer>(store, id).await?,
+            None => None,
+        };
+        let version = match indexer.as_ref().and_then(|i| i.graph_node_version) {
+            Some(id) => load::<models::GraphNodeCollectedVersion>(store, id).await?,
+            None => None,
+        };
+        if let Some(indexer) = &indexer {
+            addresses.insert(indexer.id, indexer.address.to_string());
+        }
+        indexers.push(json!({
+            "address": indexer.as_ref().map(|i| i.address.to_string()),
+            "name": indexer.as_ref().and_then(|i| i.name.clone()),
+            "poi": poi,
+            "graphNodeVersion": version.as_ref().and_then(|v| v.version_string.clone()),
+            "graphNodeCommit": version.as_ref().and_then(|v| v.version_commit.clone()),
+        }));
+    }
+
+    // Sorted by name, so that archives list files in a stable order.
+    let mut files = BTreeMap::new();
+    files.insert(
+        "bisection-trace.json".to_string(),
+        serde_json::to_value(&attempts)?,
+    );
+    for evidence in &evidence {
+        let indexer = addresses
+            .get(&evidence.indexer_id)
+            .cloned()
+            .unwrap_or_else(|| evidence.indexer_id.to_string());
+        files.insert(
+            format!("evidence/{indexer}/{}.json", kind_name(evidence.kind)),
+            evidence.contents.clone(),
+        );
+    }
+    for (kind, diff) in [
+        (
+            BisectionEvidenceKind::EntityChanges,
+            entity_changes_diff as DiffFn,
+        ),
+        (BisectionEvidenceKind::EthCallCache, eth_call_cache_diff),
+    ] {
+        if let Some((contents1, contents2)) = evidence_pair(&run, &evidence, kind) {
+            files.insert(
+                format!("diffs/{}.json", kind_name(kind)),
+                diff(contents1, contents2),
+            );
+        }
+    }
+
+    let files = files
+        .into_iter()
+        .map(|(name, contents)| Ok((name, serde_json::to_vec_pretty(&contents)?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let manifest = json!({
+        "formatVersion": FORMAT_VERSION,
+        "graphixVersion": GRAPHIX_VERSION,
+        "createdAt": chrono::Utc::now().naive_utc(),
+        "investigationUuid": run.investigation_uuid,
+        "bisectionRunUuid": run.uuid,
+        "deployment": deployment.as_ref().map(|d| d.cid.to_string()),
+        "network": network.as_ref().map(|n| n.name.clone()),
+        "lastAgreeingBlock": run.lower_bound,
+        "divergingBlock": {
+            "number": run.upper_bound,
+            "hash": diverging_block_hash,
+        },
+        "indexers": indexers,
+        "files": files
+            .iter()
+            .map(|(name, contents)| json!({
+                "name": name,
+                "sha256": hex::encode(Sha256::digest(contents)),
+            }))
+            .collect::<Vec<_>>(),
+        "signingKey": signer.public_key(),
+    });
+    let manifest = serde_json::to_vec_pretty(&manifest)?;
+    let signature = signer.sign(&manifest);
+
+    let mut archive = tar::Builder::new(vec![]);
+    let mtime = run.created_at.and_utc().timestamp().max(0) as u64;
+    let entries = [
+        ("manifest.json".to_string(), manifest),
+        ("manifest.sig".to_string(), signature),
+    ];
+    for (name, contents) in entries.into_iter().chain(files) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        archive.append_data(&mut header, name, contents.as_slice())?;
+    }
+
+    Ok(archive.into_inner()?)
+}
+
+type DiffFn = fn(&Value, &Value) -> Value;
+
+async fn load<T>(store: &Store, id: IntId) -> anyhow::Result<Option<T>>
+where
+    StoreLoader<T>: Loader<IntId, Value = T, Error = String>,
+{
+    let mut values = StoreLoader::<T>::new(store.clone())
+        .load(&[id])
+        .await
+        .map_err(|err| anyhow!(err))?;
+
+    Ok(values.remove(&id))
+}
+
+fn kind_name(kind: BisectionEvidenceKind) -> &'static str {
+    match kind {
+        BisectionEvidenceKind::EntityChanges => "entity-changes",
+        BisectionEvidenceKind::EthCallCache => "eth-call-cache",
+        BisectionEvidenceKind::BlockCache => "block-cache",
+    }
+}
+
+/// The evidence of the given kind from both indexers of the run, if both
+/// returned it.
+fn evidence_pair<'a>(
+    run: &models::BisectionRun,
+    evidence: &'a [BisectionEvidence],
+    kind: BisectionEvidenceKind,
+) -> Option<(&'a Value, &'a Value)> {
+    let contents = |indexer_id: Option<IntId>| {
+        evidence
+            .iter()
+            .find(|evidence| Some(evidence.indexer_id) == indexer_id && evidence.kind == kind)
+            .map(|evidence| &evidence.contents)
+    };
+
+    Some((contents(run.indexer1_id)?, contents(run.indexer2_id)?))
+}
+
+/// Compares the entity changes of two indexers, per entity type. Entities are
+/// matched by their ID, and only types with differences are listed.
+fn entity_changes_diff(changes1: &Value, changes2: &Value) -> Value {
+    let by_type = |changes: &Value, field: &str| -> BTreeMap<String, Vec<Value>> {
+        changes
+            .get(field)
+            .and_then(Value::as_object)
+            .map(|types| {
+                types
+                    .iter()
+                    .map(|(entity_type, values)| {
+                        let values = values.as_array().cloned().unwrap_or_default();
+                        (entity_type.clone(), values)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let mut diff = json!({ "updates": {}, "deletions": {} });
+    for (field, key) in [("updates", entity_id as KeyFn), ("deletions", json_string)] {
+        let values1 = by_type(changes1, field);
+        let values2 = by_type(changes2, field);
+        let mut entity_types: Vec<&String> = values1.keys().chain(values2.keys()).collect();
+        entity_types.sort();
+        entity_types.dedup();
+        for entity_type in entity_types {
+            let type_diff = diff_by_key(
+                values1.get(entity_type).map_or(&[], Vec::as_slice),
+                values2.get(entity_type).map_or(&[], Vec::as_slice),
+                key,
+            );
+            if let Some(type_diff) = type_diff {
+                diff[field][entity_type] = type_diff;
+            }
+        }
+    }
+
+    diff
+}
+
+/// Compares the cached `eth_call` results of two indexers, matched by the
+/// hash of the call.
+fn eth_call_cache_diff(calls1: &Value, calls2: &Value) -> Value {
+    let calls = |calls: &Value| calls.as_array().cloned().unwrap_or_default();
+    let call_id = |call: &Value| json_string(&call["idHash"]);
+
+    diff_by_key(&calls(calls1), &calls(calls2), call_id).unwrap_or_else(|| json!({}))
+}
+
+type KeyFn = fn(&Value) -> String;
+
+fn entity_id(entity: &Value) -> String {
+    match entity.get("id") {
+        Some(id) => json_string(id),
+        None => json_string(entity),
+    }
+}
+
+fn json_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Lists the values that only one of the two lists has, and those whose
+/// key is in both lists with different values. Returns [`None`] if the lists
+/// are the same.
+fn diff_by_key(
+    values1: &[Value],
+    values2: &[Value],
+    key: impl Fn(&Value) -> String,
+) -> Option<Value> {
+    let index = |values: &[Value]| -> BTreeMap<String, Value> {
+        values
+            .iter()
+            .map(|value| (key(value), value.clone()))
+            .collect()
+    };
+    let index1 = index(values1);
+    let index2 = index(values2);
+
+    let only_indexer1: Vec<&Value> = index1
+        .iter()
+        .filter(|(key, _)| !index2.contains_key(*key))
+        .map(|(_, value)| value)
+        .collect();
+    let only_indexer2: Vec<&Value> = index2
+        .iter()
+        .filter(|(key, _)| !index1.contains_key(*key))
+        .map(|(_, value)| value)
+        .collect();
+    let different: Vec<Value> = index1
+        .iter()
+        .filter_map(|(key, value1)| {
+            let value2 = index2.get(key)?;
+            (value1 != value2)
+                .then(|| json!({ "key": key, "indexer1": value1, "indexer2": value2 }))
+        })
+        .collect();
+
+    if only_indexer1.is_empty() && only_indexer2.is_empty() && different.is_empty() {
+        return None;
+    }
+
+    Some(json!({
+        "onlyIndexer1": only_indexer1,
+        "onlyIndexer2": only_indexer2,
+        "different": different,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entity_changes_are_diffed_by_id() {
+        let changes1 = json!({
+            "updates": {
+                "Pool": [{ "id": "1", "liquidity": "10" }, { "id": "2", "liquidity": "5" }],
+                "Token": [{ "id": "a" }],
+            },
+            "deletions": { "Swap": ["x"] },
+        });
+        let changes2 = json!({
+            "updates": {
+                "Pool": [{ "id": "1", "liquidity": "11" }, { "id": "3", "liquidity": "5" }],
+                "Token": [{ "id": "a" }],
+            },
+            "deletions": {},
+        });
+
+        let diff = entity_changes_diff(&changes1, &changes2);
+        assert_eq!(
+            diff["updates"]["Pool"],
+            json!({
+                "onlyIndexer1": [{ "id": "2", "liquidity": "5" }],
+                "onlyIndexer2": [{ "id": "3", "liquidity": "5" }],
+                "different": [{
+                    "key": "1",
+                    "indexer1": { "id": "1", "liquidity": "10" },
+                    "indexer2": { "id": "1", "liquidity": "11" },
+                }],
+            })
+        );
+        assert!(diff["updates"].get("Token").is_none());
+        assert_eq!(diff["deletions"]["Swap"]["onlyIndexer1"], json!(["x"]));
+    }
+
+    #[test]
+    fn eth_calls_are_diffed_by_hash() {
+        let call = |id_hash: &str, return_value: &str| json!({ "idHash": id_hash, "contractAddress": "00", "returnValue": return_value });
+        let calls1 = json!([call("aa", "01"), call("bb", "02")]);
+        let calls2 = json!([call("aa", "01"), call("bb", "03")]);
+
+        let diff = eth_call_cache_diff(&calls1, &calls2);
+        assert_eq!(diff["onlyIndexer1"], json!([]));
+        assert_eq!(diff["different"][0]["key"], "bb");
+        assert_eq!(eth_call_cache_diff(&calls1, &calls1), json!({}));
+    }
+}
@@ -0,0 +1,151 @@
+//! Monitors indexing disputes in the `DisputeManager` contract through the
+//! network subgraph, and correlates the disputed PoIs with the PoIs Graphix
+//! collected from other indexers, which tells whether Graphix's data supports
+//! or contradicts each dispute.
+
+use chrono::{DateTime, Utc};
+use graphix_network_sg_client::{IndexingDispute, NetworkSubgraphClient};
+use graphix_store::models::{OnChainPoiDispute, PoiDispute};
+use graphix_store::Store;
+use tracing::*;
+
+/// How far back to look for disputes on the first check, if none were stored
+/// before. Disputes are rare, and stay relevant for a long time.
+const INITIAL_LOOKBACK_IN_SECONDS: i64 = 90 * 24 * 60 * 60;
+
+/// Upper limit on the number of new disputes fetched per check.
+const MAX_DISPUTES_PER_CHECK: u32 = 1000;
+
+/// Whether the PoIs Graphix collected back a dispute up.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, async_graphql::Enum)]
+pub enum DisputeAssessment {
+    /// Most other indexers reported a different PoI than the disputed one.
+    Supported,
+    /// Most other indexers reported the disputed PoI.
+    Contradicted,
+    /// Graphix didn't collect PoIs from other indexers for the disputed
+    /// block, or they're split evenly.
+    Inconclusive,
+}
+
+impl DisputeAssessment {
+    pub fn of(dispute: &PoiDispute) -> Self {
+        Self::from_counts(dispute.agreeing_indexers, dispute.disagreeing_indexers)
+    }
+
+    fn from_counts(agreeing_indexers: i32, disagreeing_indexers: i32) -> Self {
+        match agreeing_indexers.cmp(&disagreeing_indexers) {
+            std::cmp::Ordering::Less => Self::Supported,
+            std::cmp::Ordering::Greater => Self::Contradicted,
+            std::cmp::Ordering::Equal => Self::Inconclusive,
+        }
+    }
+}
+
+/// Periodically fetches new and updated disputes from a network subgraph.
+#[derive(Debug, Default)]
+pub struct DisputeMonitor {
+    /// UNIX timestamp of the most recently opened dispute seen so far.
+    opened_after: Option<i64>,
+}
+
+impl DisputeMonitor {
+    /// Stores all disputes opened since the last check, and updates stored
+    /// ones that weren't resolved yet. Returns the newly stored or updated
+    /// disputes.
+    #[instrument(skip_all)]
+    pub async fn check(
+        &mut self,
+        store: &Store,
+        network_subgraph: &NetworkSubgraphClient,
+    ) -> anyhow::Result<Vec<PoiDispute>> {
+        let opened_after = match self.opened_after {
+            Some(opened_after) => opened_after,
+            None => match store.latest_poi_dispute_opened_at().await? {
+                Some(opened_at) => opened_at.and_utc().timestamp(),
+                None => Utc::now().timestamp() - INITIAL_LOOKBACK_IN_SECONDS,
+            },
+        };
+
+        let undecided_ids = store.undecided_poi_dispute_ids().await?;
+        let mut disputes = network_subgraph
+            .indexing_disputes_by_id(&undecided_ids)
+            .await?;
+        let new_disputes = network_subgraph
+            .indexing_disputes(opened_after, Some(MAX_DISPUTES_PER_CHECK))
+            .await?;
+        self.opened_after = Some(
+            new_disputes
+                .iter()
+                .map(|dispute| dispute.created_at)
+                .fold(opened_after, i64::max),
+        );
+        disputes.extend(new_disputes);
+
+        let on_chain_disputes: Vec<_> = disputes.into_iter().filter_map(on_chain_dispute).collect();
+        let poi_disputes = store.write_poi_disputes(&on_chain_disputes).await?;
+
+        for poi_dispute in &poi_disputes {
+            let assessment = DisputeAssessment::of(poi_dispute);
+            if assessment != DisputeAssessment::Inconclusive {
+                info!(
+                    dispute_id = %poi_dispute.dispute_id,
+                    indexer_id = poi_dispute.indexer_id,
+                    block_number = poi_dispute.block_number,
+                    status = ?poi_dispute.status,
+                    ?assessment,
+                    "Correlated dispute with collected PoIs"
+                );
+            }
+        }
+        info!(
+            disputes = on_chain_disputes.len(),
+            stored = poi_disputes.len(),
+            "Finished checking disputes"
+        );
+
+        Ok(poi_disputes)
+    }
+}
+
+fn on_chain_dispute(dispute: IndexingDispute) -> Option<OnChainPoiDispute> {
+    Some(OnChainPoiDispute {
+        deployment: dispute.deployment.parse().ok()?,
+        block_number: dispute.epoch_start_block.try_into().ok()?,
+        opened_at: DateTime::from_timestamp(dispute.created_at, 0)?.naive_utc(),
+        closed_at: match dispute.closed_at {
+            Some(closed_at) => Some(DateTime::from_timestamp(closed_at, 0)?.naive_utc()),
+            None => None,
+        },
+        dispute_id: dispute.id,
+        indexer_address: dispute.indexer,
+        allocation_id: dispute.allocation_id,
+        poi: dispute.poi,
+        status: dispute.status,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn majority_of_other_indexers_decides() {
+        assert_eq!(
+            DisputeAssessment::from_counts(1, 3),
+            DisputeAssessment::Supported
+        );
+        assert_eq!(
+            DisputeAssessment::from_counts(2, 0),
+            DisputeAssessment::Contradicted
+        );
+        assert_eq!(
+            DisputeAssessment::from_counts(1, 1),
+            DisputeAssessment::Inconclusive
+        );
+        assert_eq!(
+            DisputeAssessment::from_counts(0, 0),
+            DisputeAssessment::Inconclusive
+        );
+    }
+}
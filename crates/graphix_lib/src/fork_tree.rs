@@ -0,0 +1,117 @@
+//! Grouping of indexers by the PoIs they report, and how these groups evolve
+//! over the blocks of a subgraph deployment.
+
+use graphix_common_types::PoiBytes;
+use graphix_store::models::{IntId, Poi};
+
+/// A group of indexers, by ID, that reported the same PoI.
+pub type PoiCluster = (PoiBytes, Vec<IntId>);
+
+/// How the PoI clusters at a block differ from those at the previous checked
+/// block.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, async_graphql::Enum)]
+pub enum ForkChange {
+    /// The indexers split into more clusters.
+    Split,
+    /// Some clusters merged, but the indexers still disagree.
+    PartiallyHealed,
+    /// All indexers agree again.
+    Healed,
+}
+
+/// The PoI clusters at a single block.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ForkTreeBlock<B> {
+    pub block: B,
+    /// All distinct PoIs at the block, most reported first.
+    pub clusters: Vec<PoiCluster>,
+    /// [`None`] for the first block, and if the number of clusters didn't
+    /// change.
+    pub change: Option<ForkChange>,
+}
+
+/// Groups PoIs by value, largest cluster first. Indexers within a cluster keep
+/// the order of `pois`.
+pub fn poi_clusters<'a>(pois: impl IntoIterator<Item = &'a Poi>) -> Vec<PoiCluster> {
+    let mut clusters: Vec<PoiCluster> = vec![];
+    for poi in pois {
+        match clusters.iter_mut().find(|(bytes, _)| *bytes == poi.poi) {
+            Some((_, indexer_ids)) => indexer_ids.push(poi.indexer_id),
+            None => clusters.push((poi.poi, vec![poi.indexer_id])),
+        }
+    }
+    clusters.sort_by_key(|(_, indexer_ids)| std::cmp::Reverse(indexer_ids.len()));
+    clusters
+}
+
+/// Returns the PoI clusters at each block of `pois_by_block`, which must be
+/// ordered by block, and how they changed from one block to the next.
+pub fn fork_tree<B>(pois_by_block: Vec<(B, Vec<Poi>)>) -> Vec<ForkTreeBlock<B>> {
+    let mut tree: Vec<ForkTreeBlock<B>> = vec![];
+    for (block, pois) in pois_by_block {
+        let clusters = poi_clusters(&pois);
+        let change = tree.last().and_then(|previous| {
+            let (before, after) = (previous.clusters.len(), clusters.len());
+            if after > before {
+                Some(ForkChange::Split)
+            } else if after < before && after <= 1 {
+                Some(ForkChange::Healed)
+            } else if after < before {
+                Some(ForkChange::PartiallyHealed)
+            } else {
+                None
+            }
+        });
+        tree.push(ForkTreeBlock {
+            block,
+            clusters,
+            change,
+        });
+    }
+    tree
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+
+    fn poi(indexer_id: IntId, byte: u8) -> Poi {
+        Poi {
+            id: 0,
+            poi: [byte; 32].into(),
+            sg_deployment_id: 1,
+            indexer_id,
+            block_id: 0,
+            created_at: NaiveDateTime::default(),
+        }
+    }
+
+    #[test]
+    fn splits_and_heals_are_detected() {
+        let tree = fork_tree(vec![
+            (1, vec![poi(1, 1), poi(2, 1), poi(3, 1)]),
+            (2, vec![poi(1, 2), poi(2, 3), poi(3, 4)]),
+            (3, vec![poi(1, 5), poi(2, 6), poi(3, 6)]),
+            (4, vec![poi(1, 7), poi(2, 8), poi(3, 8)]),
+            (5, vec![poi(1, 9), poi(2, 9), poi(3, 9)]),
+        ]);
+
+        let changes: Vec<_> = tree.iter().map(|block| block.change).collect();
+        assert_eq!(
+            changes,
+            vec![
+                None,
+                Some(ForkChange::Split),
+                Some(ForkChange::PartiallyHealed),
+                None,
+                Some(ForkChange::Healed),
+            ]
+        );
+        assert_eq!(
+            tree[2].clusters,
+            vec![([6; 32].into(), vec![2, 3]), ([5; 32].into(), vec![1])]
+        );
+    }
+}
@@ -3,11 +3,15 @@
 
 use async_graphql::{ComplexObject, Context, Object, SimpleObject};
 use common::{IndexerAddress, IpfsCid};
-use graphix_common_types::{self as common, ApiKeyPermissionLevel};
+use graphix_common_types::{self as common, ApiKeyPermissionLevel, ManifestFlag};
 use graphix_store::models::{self, IntId};
 use num_traits::cast::ToPrimitive;
 
 use super::{ctx_data, GraphixState};
+use crate::disputes::DisputeAssessment;
+use crate::fork_tree::ForkChange;
+use crate::ipfs::manifest_flags;
+use crate::uptime::uptime_ratio;
 
 #[derive(Clone, derive_more::From)]
 pub struct SubgraphDeployment {
@@ -15,6 +19,10 @@ pub struct SubgraphDeployment {
 }
 
 impl SubgraphDeployment {
+    pub fn id(&self) -> IntId {
+        self.model.id
+    }
+
     pub fn cid(&self) -> &IpfsCid {
         &self.model.cid
     }
@@ -54,6 +62,165 @@ impl SubgraphDeployment {
     async fn graphql_network(&self, ctx: &Context<'_>) -> Result<Network, String> {
         self.network(ctx_data(ctx)).await
     }
+
+    /// Subgraph API versions supported by the subgraph deployment, as last
+    /// reported by an indexer. `null` if not yet collected.
+    #[graphql(name = "apiVersions")]
+    async fn graphql_api_versions(&self, ctx: &Context<'_>) -> Result<Option<Vec<String>>, String> {
+        ctx_data(ctx)
+            .store
+            .subgraph_api_versions(self.model.id)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// The subgraph manifest of the deployment, as fetched from IPFS. `null`
+    /// if not yet fetched.
+    #[graphql(name = "manifest")]
+    async fn graphql_manifest(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Option<SubgraphManifest>, String> {
+        ctx_data(ctx)
+            .loader_subgraph_manifest
+            .load_one(self.model.id)
+            .await
+            .map(|opt| opt.map(Into::into))
+    }
+
+    /// The display metadata and curation signal of the deployment, as
+    /// published on the network subgraph. `null` if not published or not yet
+    /// fetched.
+    #[graphql(name = "networkSubgraphMetadata")]
+    async fn graphql_network_subgraph_metadata(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Option<SubgraphDeploymentNetworkSubgraphMetadata>, String> {
+        ctx_data(ctx)
+            .loader_subgraph_network_subgraph_metadata
+            .load_one(self.model.id)
+            .await
+            .map(|opt| opt.map(Into::into))
+    }
+
+    /// The cross-check tier of the deployment, numbered from 1, as assigned
+    /// by `checkTiers` in the configuration. Tier 1 is cross-checked every
+    /// polling round, lower tiers less often. `null` if no tiers are
+    /// configured.
+    #[graphql(name = "checkTier")]
+    async fn graphql_check_tier(&self, ctx: &Context<'_>) -> Result<Option<i32>, String> {
+        ctx_data(ctx)
+            .store
+            .sg_deployment_check_tier(self.model.id)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[derive(derive_more::From)]
+pub struct SubgraphDeploymentNetworkSubgraphMetadata {
+    model: models::SgDeploymentNetworkSubgraphMetadata,
+}
+
+#[Object]
+impl SubgraphDeploymentNetworkSubgraphMetadata {
+    /// The display name of the latest subgraph version that points to the
+    /// deployment.
+    async fn display_name(&self) -> Option<String> {
+        self.model.display_name.clone()
+    }
+
+    /// URL of the subgraph's image.
+    async fn image(&self) -> Option<String> {
+        self.model.image.clone()
+    }
+
+    /// The curation signal on the deployment, in GRT wei.
+    async fn signal_amount(&self) -> f64 {
+        self.model.signal_amount.to_f64().unwrap()
+    }
+
+    async fn last_updated_at(&self) -> chrono::NaiveDateTime {
+        self.model.last_updated_at
+    }
+}
+
+/// A subgraph manifest that was fetched from IPFS.
+#[derive(derive_more::From)]
+pub struct SubgraphManifest {
+    model: models::SgDeploymentManifest,
+}
+
+impl SubgraphManifest {
+    async fn data_sources(
+        &self,
+        ctx: &Context<'_>,
+        is_template: bool,
+    ) -> Result<Vec<models::SgDeploymentDataSource>, String> {
+        let data_sources = ctx_data(ctx)
+            .store
+            .subgraph_data_sources(self.model.sg_deployment_id)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(data_sources
+            .into_iter()
+            .filter(|data_source| data_source.is_template == is_template)
+            .collect())
+    }
+}
+
+#[Object]
+impl SubgraphManifest {
+    #[graphql(name = "specVersion")]
+    async fn graphql_spec_version(&self) -> &str {
+        &self.model.spec_version
+    }
+
+    #[graphql(name = "features")]
+    async fn graphql_features(&self) -> &[String] {
+        &self.model.features
+    }
+
+    /// Features of the manifest that have historically been sources of
+    /// non-determinism, and thus likely root causes of PoI divergences.
+    #[graphql(name = "flags")]
+    async fn graphql_flags(&self) -> Result<Vec<ManifestFlag>, String> {
+        manifest_flags(&self.model.raw_manifest).map_err(|e| e.to_string())
+    }
+
+    /// The deployment that this deployment was grafted onto, if any.
+    #[graphql(name = "graftBase")]
+    async fn graphql_graft_base(&self) -> Option<&str> {
+        self.model.graft_base.as_deref()
+    }
+
+    /// The block at which this deployment was grafted onto `graftBase`.
+    #[graphql(name = "graftBlock")]
+    async fn graphql_graft_block(&self) -> Option<i64> {
+        self.model.graft_block
+    }
+
+    #[graphql(name = "dataSources")]
+    async fn graphql_data_sources(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<models::SgDeploymentDataSource>, String> {
+        self.data_sources(ctx, false).await
+    }
+
+    #[graphql(name = "templates")]
+    async fn graphql_templates(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<models::SgDeploymentDataSource>, String> {
+        self.data_sources(ctx, true).await
+    }
+
+    #[graphql(name = "fetchedAt")]
+    async fn graphql_fetched_at(&self) -> chrono::NaiveDateTime {
+        self.model.fetched_at
+    }
 }
 
 pub struct ApiKey {
@@ -117,6 +284,10 @@ pub struct Indexer {
 }
 
 impl Indexer {
+    pub fn id(&self) -> IntId {
+        self.model.id
+    }
+
     pub fn address(&self) -> IndexerAddress {
         self.model.address
     }
@@ -150,6 +321,12 @@ impl Indexer {
         self.model.name.clone()
     }
 
+    /// When the indexer was archived, or null if it isn't. Archived indexers
+    /// are no longer cross-checked.
+    async fn archived_at(&self) -> Option<chrono::NaiveDateTime> {
+        self.model.archived_at
+    }
+
     /// The version of the indexer.
     #[graphql(name = "graphNodeVersion")]
     async fn graphql_graph_node_version(
@@ -159,6 +336,87 @@ impl Indexer {
         self.graph_node_version(ctx_data(ctx)).await
     }
 
+    /// All distinct `graph-node` versions that were observed for the indexer,
+    /// oldest first.
+    async fn version_history(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<models::IndexerGraphNodeVersion>, String> {
+        ctx_data(ctx)
+            .store
+            .graph_node_version_history(self.model.id)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// The tags of the indexer, from both the configuration and the
+    /// `setIndexerTags` mutation, sorted.
+    async fn tags(&self, ctx: &Context<'_>) -> Result<Vec<String>, String> {
+        ctx_data(ctx)
+            .store
+            .indexer_tags(self.model.id)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// All names and endpoints that the indexer had, oldest first.
+    async fn identity_history(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<models::IndexerIdentity>, String> {
+        ctx_data(ctx)
+            .store
+            .indexer_identity_history(self.model.id)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// The name and endpoint that the indexer had at the given time, e.g.
+    /// when a report that references it was created. Null if it wasn't
+    /// known yet.
+    async fn identity_at(
+        &self,
+        ctx: &Context<'_>,
+        time: chrono::NaiveDateTime,
+    ) -> Result<Option<models::IndexerIdentity>, String> {
+        let history = ctx_data(ctx)
+            .store
+            .indexer_identity_history(self.model.id)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(history.into_iter().find(|identity| {
+            identity.valid_from <= time && identity.valid_until.map_or(true, |until| time < until)
+        }))
+    }
+
+    /// How long the indexer took to respond to each kind of request since
+    /// Graphix started. Slow indexers delay every main loop iteration.
+    #[graphql(name = "responseTimes")]
+    async fn graphql_response_times(&self) -> Vec<crate::IndexerResponseTimes> {
+        crate::metrics().response_times(&self.model.address.to_string())
+    }
+
+    /// How much of the time the indexer responded to Graphix's periodic
+    /// pings.
+    #[graphql(name = "uptime")]
+    async fn graphql_uptime(&self, ctx: &Context<'_>) -> Result<IndexerUptime, String> {
+        let now = chrono::Utc::now().naive_utc();
+        let since = |days| now - chrono::Duration::days(days);
+        let transitions = ctx_data(ctx)
+            .store
+            .indexer_uptime_transitions(self.model.id, since(30))
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(IndexerUptime {
+            currently_up: transitions.last().map(|transition| transition.up),
+            last_24h: uptime_ratio(&transitions, since(1), now),
+            last_7d: uptime_ratio(&transitions, since(7), now),
+            last_30d: uptime_ratio(&transitions, since(30), now),
+        })
+    }
+
     /// The network subgraph metadata of the indexer.
     async fn network_subgraph_metadata(
         &self,
@@ -178,6 +436,76 @@ impl Indexer {
     }
 }
 
+/// The fraction of time an indexer was up within several time windows, or
+/// null if its availability within the window is unknown.
+#[derive(SimpleObject)]
+pub struct IndexerUptime {
+    /// Whether the indexer responded to the latest ping.
+    pub currently_up: Option<bool>,
+    #[graphql(name = "last24h")]
+    pub last_24h: Option<f64>,
+    #[graphql(name = "last7d")]
+    pub last_7d: Option<f64>,
+    #[graphql(name = "last30d")]
+    pub last_30d: Option<f64>,
+}
+
+/// How reliable an indexer was within a time window, for data consumers
+/// choosing which indexers to query. Ratios are null if there's not enough
+/// data within the window to compute them.
+#[derive(SimpleObject)]
+pub struct IndexerSlaReport {
+    pub indexer: Indexer,
+    /// The fraction of time the indexer responded to Graphix's periodic
+    /// pings.
+    pub availability: Option<f64>,
+    /// The fraction of requested PoIs that the indexer returned.
+    pub poi_response_success_rate: Option<f64>,
+    /// How many of the other indexers agreed with the indexer's PoIs on
+    /// average, between 0 and 1.
+    pub average_agreement_ratio: Option<f64>,
+    /// The number of bisection runs involving the indexer that found the
+    /// block at which PoIs diverged.
+    pub confirmed_divergences: u64,
+}
+
+/// An indexer that was added at runtime through the `addIndexer` mutation,
+/// on top of those listed in the configuration file.
+#[derive(derive_more::From)]
+pub struct RegisteredIndexer {
+    model: models::RegisteredIndexer,
+}
+
+#[Object]
+impl RegisteredIndexer {
+    #[graphql(name = "address")]
+    async fn graphql_address(&self) -> String {
+        self.model.address.to_string()
+    }
+
+    #[graphql(name = "name")]
+    async fn graphql_name(&self) -> Option<&str> {
+        self.model.name.as_deref()
+    }
+
+    /// The URL of the indexer's `graph-node` index-node endpoint.
+    #[graphql(name = "indexNodeEndpoint")]
+    async fn graphql_index_node_endpoint(&self) -> &str {
+        &self.model.index_node_endpoint
+    }
+
+    #[graphql(name = "createdAt")]
+    async fn graphql_created_at(&self) -> chrono::NaiveDateTime {
+        self.model.created_at
+    }
+
+    /// The workspace whose registry the indexer was added to, if any.
+    #[graphql(name = "workspaceId")]
+    async fn graphql_workspace_id(&self) -> Option<IntId> {
+        self.model.workspace_id
+    }
+}
+
 #[derive(derive_more::From)]
 pub struct IndexerNetworkSubgraphMetadata {
     model: models::IndexerNetworkSubgraphMetadata,
@@ -245,7 +573,7 @@ impl Block {
         self.model.number
     }
 
-    pub fn hash(&self) -> common::BlockHash {
+    pub fn hash(&self) -> Option<common::BlockHash> {
         self.model.hash.clone()
     }
 
@@ -275,12 +603,19 @@ impl Block {
         let network = self.network(ctx_data(ctx)).await.ok()?;
         let config = ctx_data(ctx).config();
         let chain_config = config.chains.get(network.name())?;
-        let speed_config = chain_config.speed.as_ref()?;
 
-        let duration_per_block =
-            chrono::Duration::milliseconds(speed_config.avg_block_time_in_msecs.try_into().ok()?);
+        chain_config
+            .speed
+            .as_ref()?
+            .estimated_timestamp(self.number())
+    }
 
-        Some(speed_config.sample_timestamp + duration_per_block * self.number().try_into().ok()?)
+    /// The stored timestamp of the block, which is exact if the network has
+    /// an `rpcUrl` and estimated from its block speed otherwise. Blocks
+    /// without one don't match time bounds of block ranges.
+    #[graphql(name = "timestamp")]
+    pub async fn graphql_timestamp(&self) -> Option<chrono::NaiveDateTime> {
+        self.model.timestamp
     }
 
     /// Returns an URL to a block explorer page for the block, if configured.
@@ -303,12 +638,20 @@ impl Block {
         self.model.number.try_into().unwrap()
     }
 
-    /// The block hash, expressed as a hex string with a '0x' prefix.
+    /// The block hash, expressed as a hex string with a '0x' prefix. `null`
+    /// if indexers didn't report it.
     #[graphql(name = "hash")]
-    async fn graphql_hash(&self) -> common::BlockHash {
+    async fn graphql_hash(&self) -> Option<common::BlockHash> {
         self.model.hash.clone()
     }
 
+    /// Whether the block hash matches the canonical chain, according to the
+    /// network's configured RPC endpoint. `null` if it wasn't verified.
+    #[graphql(name = "canonical")]
+    async fn graphql_canonical(&self) -> Option<bool> {
+        self.model.canonical
+    }
+
     /// The network that this block belongs to.
     #[graphql(name = "network")]
     pub async fn graphql_network(&self, ctx: &Context<'_>) -> Result<Network, String> {
@@ -389,6 +732,127 @@ impl ProofOfIndexing {
     }
 }
 
+/// An entry of the `graph-node` version compatibility matrix: all
+/// subgraph deployments on which indexers running `versionA` and indexers
+/// running `versionB` currently disagree.
+#[derive(SimpleObject)]
+pub struct VersionDivergence {
+    /// A `graph-node` version, or null if unknown.
+    pub version_a: Option<String>,
+    /// Another (or the same) `graph-node` version, or null if unknown.
+    pub version_b: Option<String>,
+    /// The number of disagreeing indexer pairs across all deployments.
+    pub diverging_pairs: u32,
+    pub deployments: Vec<SubgraphDeployment>,
+}
+
+/// The current divergences on a network, i.e. its subgraph deployments whose
+/// live PoIs disagree for the same block.
+#[derive(SimpleObject)]
+pub struct DivergenceSummary {
+    pub network: String,
+    /// The number of subgraph deployments with live PoIs.
+    pub deployments_checked: u32,
+    /// The number of subgraph deployments whose live PoIs disagree.
+    pub diverging_deployments: u32,
+    /// The number of indexers with a live PoI for a diverging deployment.
+    pub affected_indexers: u32,
+    /// When the most recent of the disagreeing live PoIs was collected, or
+    /// null if there are none.
+    pub newest_divergence_at: Option<chrono::NaiveDateTime>,
+}
+
+/// Aggregate statistics about a network.
+#[derive(SimpleObject)]
+pub struct NetworkStats {
+    pub network: String,
+    /// The number of subgraph deployments on the watchlist.
+    pub tracked_deployments: u32,
+    /// The number of indexers with live PoIs.
+    pub active_indexers: u32,
+    /// The fraction of indexers that agree with each live PoI, on average,
+    /// or null if there are no live PoIs.
+    pub average_agreement_ratio: Option<f64>,
+    /// How far behind the chain head indexers are, according to the indexing
+    /// statuses of the last hour, or null if there are none. The chain head
+    /// is approximated by the highest block any indexer reported.
+    pub chain_head_lag: Option<ChainHeadLag>,
+}
+
+impl NetworkStats {
+    pub fn new(network: String, stats: models::NetworkStats) -> Self {
+        let chain_head_lag = match (
+            stats.chain_head_lag_median,
+            stats.chain_head_lag_p90,
+            stats.chain_head_lag_p99,
+            stats.chain_head_lag_max,
+        ) {
+            (Some(median), Some(p90), Some(p99), Some(max)) => Some(ChainHeadLag {
+                median: median as u64,
+                p90: p90 as u64,
+                p99: p99 as u64,
+                max: max as u64,
+            }),
+            _ => None,
+        };
+
+        Self {
+            network,
+            tracked_deployments: stats.tracked_deployments as u32,
+            active_indexers: stats.active_indexers as u32,
+            average_agreement_ratio: stats.average_agreement_ratio,
+            chain_head_lag,
+        }
+    }
+}
+
+/// The distribution of how many blocks indexers are behind the chain head,
+/// across all their subgraph deployments on a network.
+#[derive(SimpleObject)]
+pub struct ChainHeadLag {
+    pub median: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+/// The subgraph deployments and indexers matching a search term, best
+/// matches first.
+#[derive(SimpleObject)]
+pub struct SearchResults {
+    pub deployments: Vec<SubgraphDeployment>,
+    pub indexers: Vec<Indexer>,
+}
+
+/// The indexers that currently disagree on the PoI of a subgraph deployment
+/// at a block, grouped by the PoI they report.
+#[derive(SimpleObject)]
+pub struct DivergenceClusters {
+    pub deployment: SubgraphDeployment,
+    pub block: Block,
+    /// All distinct PoIs at the block, most reported first.
+    pub clusters: Vec<PoiCluster>,
+}
+
+/// The PoI clusters of a subgraph deployment at a block, as part of its
+/// fork tree.
+#[derive(SimpleObject)]
+pub struct PoiForkTreeBlock {
+    pub block: Block,
+    /// All distinct PoIs at the block, most reported first.
+    pub clusters: Vec<PoiCluster>,
+    /// How the clusters changed since the previous block in the fork tree,
+    /// if they did.
+    pub change: Option<ForkChange>,
+}
+
+/// A group of indexers that reported the same PoI.
+#[derive(SimpleObject)]
+pub struct PoiCluster {
+    pub poi: common::PoiBytes,
+    pub indexers: Vec<Indexer>,
+}
+
 /// A specific indexer can use `PoiAgreementRatio` to check in how much agreement it is with other
 /// indexers, given its own poi for each deployment. A consensus currently means a majority of
 /// indexers agreeing on a particular POI.
@@ -430,3 +894,724 @@ impl PoiAgreementRatio {
             .map(Into::into)
     }
 }
+
+/// A bisection run that was performed as part of a divergence investigation,
+/// comparing the PoIs of two indexers for the same subgraph deployment.
+#[derive(derive_more::From)]
+pub struct BisectionRun {
+    model: models::BisectionRun,
+}
+
+impl BisectionRun {
+    pub async fn deployment(
+        &self,
+        ctx: &GraphixState,
+    ) -> Result<Option<SubgraphDeployment>, String> {
+        let loader = &ctx.loader_subgraph_deployment;
+
+        if let Some(id) = self.model.sg_deployment_id {
+            loader
+                .load_one(id)
+                .await
+                .map_err(Into::into)
+                .map(|opt| opt.map(Into::into))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn indexer(
+        &self,
+        ctx: &GraphixState,
+        id: Option<IntId>,
+    ) -> Result<Option<Indexer>, String> {
+        let loader = &ctx.loader_indexer;
+
+        if let Some(id) = id {
+            loader
+                .load_one(id)
+                .await
+                .map_err(Into::into)
+                .map(|opt| opt.map(Into::into))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[Object]
+impl BisectionRun {
+    /// The UUID of the bisection run.
+    #[graphql(name = "uuid")]
+    async fn graphql_uuid(&self) -> uuid::Uuid {
+        self.model.uuid
+    }
+
+    /// The UUID of the divergence investigation that this bisection run is
+    /// part of.
+    #[graphql(name = "investigationUuid")]
+    async fn graphql_investigation_uuid(&self) -> uuid::Uuid {
+        self.model.investigation_uuid
+    }
+
+    /// The subgraph deployment that was bisected.
+    #[graphql(name = "deployment")]
+    async fn graphql_deployment(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Option<SubgraphDeployment>, String> {
+        self.deployment(ctx_data(ctx)).await
+    }
+
+    /// The indexer that produced `poi1`.
+    #[graphql(name = "indexer1")]
+    async fn graphql_indexer1(&self, ctx: &Context<'_>) -> Result<Option<Indexer>, String> {
+        self.indexer(ctx_data(ctx), self.model.indexer1_id).await
+    }
+
+    /// The indexer that produced `poi2`.
+    #[graphql(name = "indexer2")]
+    async fn graphql_indexer2(&self, ctx: &Context<'_>) -> Result<Option<Indexer>, String> {
+        self.indexer(ctx_data(ctx), self.model.indexer2_id).await
+    }
+
+    /// The first PoI that was used to start the bisection run.
+    #[graphql(name = "poi1")]
+    async fn graphql_poi1(&self) -> common::PoiBytes {
+        self.model.poi1
+    }
+
+    /// The second PoI that was used to start the bisection run.
+    #[graphql(name = "poi2")]
+    async fn graphql_poi2(&self) -> common::PoiBytes {
+        self.model.poi2
+    }
+
+    /// The highest block number at which the two PoIs are known to agree.
+    #[graphql(name = "lowerBound")]
+    async fn graphql_lower_bound(&self) -> u64 {
+        self.model.lower_bound.try_into().unwrap()
+    }
+
+    /// The lowest block number at which the two PoIs are known to diverge.
+    #[graphql(name = "upperBound")]
+    async fn graphql_upper_bound(&self) -> u64 {
+        self.model.upper_bound.try_into().unwrap()
+    }
+
+    /// If the bisection run failed before reaching a conclusion at a single
+    /// block, this field contains the error message.
+    #[graphql(name = "error")]
+    async fn graphql_error(&self) -> Option<String> {
+        self.model.error.clone()
+    }
+
+    #[graphql(name = "createdAt")]
+    async fn graphql_created_at(&self) -> chrono::NaiveDateTime {
+        self.model.created_at
+    }
+
+    /// All blocks that were queried during the bisection run, in order.
+    #[graphql(name = "attempts")]
+    async fn graphql_attempts(&self, ctx: &Context<'_>) -> Result<Vec<BisectionAttempt>, String> {
+        ctx_data(ctx)
+            .reports
+            .bisection_attempts(&self.model.uuid)
+            .await
+            .map(|attempts| attempts.into_iter().map(Into::into).collect())
+            .map_err(|e| e.to_string())
+    }
+
+    /// `graph-node` debugging data collected at the diverging block.
+    #[graphql(name = "evidence")]
+    async fn graphql_evidence(&self, ctx: &Context<'_>) -> Result<Vec<BisectionEvidence>, String> {
+        ctx_data(ctx)
+            .reports
+            .bisection_evidence(&self.model.uuid)
+            .await
+            .map(|evidence| evidence.into_iter().map(Into::into).collect())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// A single block that was queried during a bisection run.
+#[derive(derive_more::From)]
+pub struct BisectionAttempt {
+    model: models::BisectionAttempt,
+}
+
+#[Object]
+impl BisectionAttempt {
+    #[graphql(name = "blockNumber")]
+    async fn graphql_block_number(&self) -> u64 {
+        self.model.block_number.try_into().unwrap()
+    }
+
+    /// The block hash, if any of the two indexers reported it.
+    #[graphql(name = "blockHash")]
+    async fn graphql_block_hash(&self) -> Option<common::BlockHash> {
+        self.model.block_hash.clone()
+    }
+
+    /// The metadata that was collected from the first indexer's `graph-node`
+    /// instance.
+    #[graphql(name = "indexer1Response")]
+    async fn graphql_indexer1_response(&self) -> &str {
+        &self.model.indexer1_response
+    }
+
+    /// The metadata that was collected from the second indexer's
+    /// `graph-node` instance.
+    #[graphql(name = "indexer2Response")]
+    async fn graphql_indexer2_response(&self) -> &str {
+        &self.model.indexer2_response
+    }
+}
+
+/// `graph-node` debugging data that one indexer returned for the diverging
+/// block of a bisection run.
+#[derive(derive_more::From)]
+pub struct BisectionEvidence {
+    model: models::BisectionEvidence,
+}
+
+#[Object]
+impl BisectionEvidence {
+    /// The indexer that returned this data.
+    #[graphql(name = "indexer")]
+    async fn graphql_indexer(&self, ctx: &Context<'_>) -> Result<Indexer, String> {
+        let loader = &ctx_data(ctx).loader_indexer;
+
+        loader
+            .load_one(self.model.indexer_id)
+            .await
+            .map_err(Into::into)
+            .and_then(|opt| opt.ok_or_else(|| "Indexer not found".to_string()))
+            .map(Into::into)
+    }
+
+    #[graphql(name = "blockNumber")]
+    async fn graphql_block_number(&self) -> u64 {
+        self.model.block_number.try_into().unwrap()
+    }
+
+    #[graphql(name = "kind")]
+    async fn graphql_kind(&self) -> common::BisectionEvidenceKind {
+        self.model.kind
+    }
+
+    #[graphql(name = "contents")]
+    async fn graphql_contents(&self) -> &serde_json::Value {
+        &self.model.contents
+    }
+}
+
+/// A raw response body that an indexer returned during a divergence
+/// investigation, captured for debugging.
+#[derive(derive_more::From)]
+pub struct RawIndexerResponse {
+    model: models::RawIndexerResponse,
+}
+
+#[Object]
+impl RawIndexerResponse {
+    /// The indexer that returned this response.
+    #[graphql(name = "indexer")]
+    async fn graphql_indexer(&self, ctx: &Context<'_>) -> Result<Indexer, String> {
+        let loader = &ctx_data(ctx).loader_indexer;
+
+        loader
+            .load_one(self.model.indexer_id)
+            .await
+            .map_err(Into::into)
+            .and_then(|opt| opt.ok_or_else(|| "Indexer not found".to_string()))
+            .map(Into::into)
+    }
+
+    /// The name of the GraphQL operation that was sent to the indexer, e.g.
+    /// `ProofsOfIndexing`.
+    #[graphql(name = "operationName")]
+    async fn graphql_operation_name(&self) -> &str {
+        &self.model.operation_name
+    }
+
+    /// The response body, exactly as the indexer returned it. It's not
+    /// necessarily valid JSON.
+    #[graphql(name = "body")]
+    async fn graphql_body(&self) -> &str {
+        &self.model.body
+    }
+
+    #[graphql(name = "createdAt")]
+    async fn graphql_created_at(&self) -> chrono::NaiveDateTime {
+        self.model.created_at
+    }
+}
+
+/// A divergence investigation request in the job queue of investigation
+/// workers.
+#[derive(derive_more::From)]
+pub struct DivergenceInvestigationJob {
+    model: models::DivergenceInvestigationJob,
+}
+
+#[Object]
+impl DivergenceInvestigationJob {
+    /// The UUID of the divergence investigation.
+    #[graphql(name = "uuid")]
+    async fn graphql_uuid(&self) -> uuid::Uuid {
+        self.model.uuid
+    }
+
+    #[graphql(name = "status")]
+    async fn graphql_status(&self) -> common::DivergenceInvestigationJobStatus {
+        self.model.status
+    }
+
+    /// How many times a worker started investigating, including the current
+    /// attempt if the job is running.
+    #[graphql(name = "attempts")]
+    async fn graphql_attempts(&self) -> i32 {
+        self.model.attempts
+    }
+
+    /// The worker that claimed the job most recently, as `hostname:pid`.
+    #[graphql(name = "lockedBy")]
+    async fn graphql_locked_by(&self) -> Option<&str> {
+        self.model.locked_by.as_deref()
+    }
+
+    #[graphql(name = "lockedAt")]
+    async fn graphql_locked_at(&self) -> Option<chrono::NaiveDateTime> {
+        self.model.locked_at
+    }
+
+    /// Pending jobs aren't claimed before this time, in UTC.
+    #[graphql(name = "runAfter")]
+    async fn graphql_run_after(&self) -> chrono::NaiveDateTime {
+        self.model.run_after
+    }
+
+    /// The error of the last failed attempt, if any.
+    #[graphql(name = "lastError")]
+    async fn graphql_last_error(&self) -> Option<&str> {
+        self.model.last_error.as_deref()
+    }
+
+    /// When the job ran out of attempts, if it's dead-lettered.
+    #[graphql(name = "deadLetteredAt")]
+    async fn graphql_dead_lettered_at(&self) -> Option<chrono::NaiveDateTime> {
+        self.model.dead_lettered_at
+    }
+
+    #[graphql(name = "cancelled")]
+    async fn graphql_cancelled(&self) -> bool {
+        self.model.cancelled
+    }
+
+    #[graphql(name = "createdAt")]
+    async fn graphql_created_at(&self) -> chrono::NaiveDateTime {
+        self.model.created_at
+    }
+}
+
+/// A PoI that an indexer submitted on chain when closing an allocation,
+/// compared against the PoI that Graphix collected from the same indexer for
+/// the same deployment and block.
+#[derive(derive_more::From)]
+pub struct AllocationPoi {
+    model: models::AllocationPoi,
+}
+
+#[Object]
+impl AllocationPoi {
+    /// The ID of the closed allocation.
+    #[graphql(name = "allocationId")]
+    async fn graphql_allocation_id(&self) -> &str {
+        &self.model.allocation_id
+    }
+
+    #[graphql(name = "indexer")]
+    async fn graphql_indexer(&self, ctx: &Context<'_>) -> Result<Indexer, String> {
+        ctx_data(ctx)
+            .loader_indexer
+            .load_one(self.model.indexer_id)
+            .await?
+            .map(Into::into)
+            .ok_or_else(|| "Indexer not found".to_string())
+    }
+
+    #[graphql(name = "deployment")]
+    async fn graphql_deployment(&self, ctx: &Context<'_>) -> Result<SubgraphDeployment, String> {
+        ctx_data(ctx)
+            .loader_subgraph_deployment
+            .load_one(self.model.sg_deployment_id)
+            .await?
+            .map(Into::into)
+            .ok_or_else(|| "Subgraph deployment not found".to_string())
+    }
+
+    /// The epoch in which the allocation was closed.
+    #[graphql(name = "closedAtEpoch")]
+    async fn graphql_closed_at_epoch(&self) -> i32 {
+        self.model.closed_at_epoch
+    }
+
+    /// The block number the submitted PoI was computed for.
+    #[graphql(name = "blockNumber")]
+    async fn graphql_block_number(&self) -> u64 {
+        self.model.block_number.try_into().unwrap()
+    }
+
+    /// The PoI submitted on chain.
+    #[graphql(name = "submittedPoi")]
+    async fn graphql_submitted_poi(&self) -> common::PoiBytes {
+        self.model.submitted_poi
+    }
+
+    /// The PoI that Graphix collected for the same indexer, deployment and
+    /// block, if any.
+    #[graphql(name = "collectedPoi")]
+    async fn graphql_collected_poi(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Option<ProofOfIndexing>, String> {
+        let Some(id) = self.model.collected_poi_id else {
+            return Ok(None);
+        };
+
+        ctx_data(ctx)
+            .loader_poi
+            .load_one(id)
+            .await
+            .map(|opt| opt.map(Into::into))
+    }
+
+    /// Whether the submitted PoI matches the collected one. `null` if Graphix
+    /// didn't collect a PoI to compare against.
+    #[graphql(name = "matches")]
+    async fn graphql_matches(&self) -> Option<bool> {
+        self.model.matches
+    }
+
+    #[graphql(name = "closedAt")]
+    async fn graphql_closed_at(&self) -> chrono::NaiveDateTime {
+        self.model.closed_at
+    }
+}
+
+/// An on-chain dispute about a PoI that an indexer submitted when closing an
+/// allocation, correlated with the PoIs that Graphix collected for the same
+/// deployment and block.
+#[derive(derive_more::From)]
+pub struct PoiDispute {
+    model: models::PoiDispute,
+}
+
+#[Object]
+impl PoiDispute {
+    /// The ID of the dispute in the `DisputeManager` contract.
+    #[graphql(name = "disputeId")]
+    async fn graphql_dispute_id(&self) -> &str {
+        &self.model.dispute_id
+    }
+
+    /// The disputed indexer.
+    #[graphql(name = "indexer")]
+    async fn graphql_indexer(&self, ctx: &Context<'_>) -> Result<Indexer, String> {
+        ctx_data(ctx)
+            .loader_indexer
+            .load_one(self.model.indexer_id)
+            .await?
+            .map(Into::into)
+            .ok_or_else(|| "Indexer not found".to_string())
+    }
+
+    #[graphql(name = "deployment")]
+    async fn graphql_deployment(&self, ctx: &Context<'_>) -> Result<SubgraphDeployment, String> {
+        ctx_data(ctx)
+            .loader_subgraph_deployment
+            .load_one(self.model.sg_deployment_id)
+            .await?
+            .map(Into::into)
+            .ok_or_else(|| "Subgraph deployment not found".to_string())
+    }
+
+    /// The ID of the allocation whose PoI is disputed.
+    #[graphql(name = "allocationId")]
+    async fn graphql_allocation_id(&self) -> &str {
+        &self.model.allocation_id
+    }
+
+    /// The block number the disputed PoI was computed for.
+    #[graphql(name = "blockNumber")]
+    async fn graphql_block_number(&self) -> u64 {
+        self.model.block_number.try_into().unwrap()
+    }
+
+    /// The PoI that the indexer submitted on chain.
+    #[graphql(name = "disputedPoi")]
+    async fn graphql_disputed_poi(&self) -> common::PoiBytes {
+        self.model.disputed_poi
+    }
+
+    #[graphql(name = "status")]
+    async fn graphql_status(&self) -> common::DisputeStatus {
+        self.model.status
+    }
+
+    /// The PoI that Graphix collected from the disputed indexer for the same
+    /// deployment and block, if any.
+    #[graphql(name = "collectedPoi")]
+    async fn graphql_collected_poi(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Option<ProofOfIndexing>, String> {
+        let Some(id) = self.model.collected_poi_id else {
+            return Ok(None);
+        };
+
+        ctx_data(ctx)
+            .loader_poi
+            .load_one(id)
+            .await
+            .map(|opt| opt.map(Into::into))
+    }
+
+    /// How many other indexers Graphix collected the disputed PoI from, for
+    /// the same deployment and block.
+    #[graphql(name = "agreeingIndexers")]
+    async fn graphql_agreeing_indexers(&self) -> i32 {
+        self.model.agreeing_indexers
+    }
+
+    /// How many other indexers Graphix collected a different PoI from, for
+    /// the same deployment and block.
+    #[graphql(name = "disagreeingIndexers")]
+    async fn graphql_disagreeing_indexers(&self) -> i32 {
+        self.model.disagreeing_indexers
+    }
+
+    /// Whether the PoIs Graphix collected support or contradict the dispute.
+    #[graphql(name = "assessment")]
+    async fn graphql_assessment(&self) -> DisputeAssessment {
+        DisputeAssessment::of(&self.model)
+    }
+
+    #[graphql(name = "openedAt")]
+    async fn graphql_opened_at(&self) -> chrono::NaiveDateTime {
+        self.model.opened_at
+    }
+
+    /// When the dispute was resolved, if it was.
+    #[graphql(name = "closedAt")]
+    async fn graphql_closed_at(&self) -> Option<chrono::NaiveDateTime> {
+        self.model.closed_at
+    }
+}
+
+/// An active allocation that the allocating indexer's `indexer-agent`
+/// reports, but that the indexer doesn't seem to serve.
+#[derive(derive_more::From)]
+pub struct AllocationDiscrepancy {
+    model: models::AllocationDiscrepancy,
+}
+
+#[Object]
+impl AllocationDiscrepancy {
+    /// The allocating indexer.
+    #[graphql(name = "indexer")]
+    async fn graphql_indexer(&self, ctx: &Context<'_>) -> Result<Indexer, String> {
+        ctx_data(ctx)
+            .loader_indexer
+            .load_one(self.model.allocation.indexer_id)
+            .await?
+            .map(Into::into)
+            .ok_or_else(|| "Indexer not found".to_string())
+    }
+
+    #[graphql(name = "allocationId")]
+    async fn graphql_allocation_id(&self) -> &str {
+        &self.model.allocation.allocation_id
+    }
+
+    /// The IPFS CID of the allocated deployment, which Graphix may not know
+    /// about.
+    #[graphql(name = "deployment")]
+    async fn graphql_deployment(&self) -> &str {
+        &self.model.allocation.deployment
+    }
+
+    #[graphql(name = "createdAtEpoch")]
+    async fn graphql_created_at_epoch(&self) -> u64 {
+        self.model.allocation.created_at_epoch.try_into().unwrap()
+    }
+
+    /// Whether the indexer reports an indexing status for the deployment.
+    #[graphql(name = "indexing")]
+    async fn graphql_indexing(&self) -> bool {
+        self.model.allocation.indexing
+    }
+
+    /// Whether Graphix has current PoIs of the indexer for the deployment.
+    #[graphql(name = "hasLivePois")]
+    async fn graphql_has_live_pois(&self) -> bool {
+        self.model.has_live_pois
+    }
+
+    /// When the allocation was last collected from the indexer.
+    #[graphql(name = "collectedAt")]
+    async fn graphql_collected_at(&self) -> chrono::NaiveDateTime {
+        self.model.allocation.collected_at
+    }
+}
+
+/// Indexers that reported PoIs for the same block number of a subgraph
+/// deployment, but for different block hashes. Unlike PoI divergences, these
+/// point at chain issues like reorgs or faulty RPC providers.
+#[derive(derive_more::From)]
+pub struct ChainDisagreement {
+    model: models::ChainDisagreement,
+}
+
+#[Object]
+impl ChainDisagreement {
+    #[graphql(name = "deployment")]
+    async fn graphql_deployment(&self, ctx: &Context<'_>) -> Result<SubgraphDeployment, String> {
+        ctx_data(ctx)
+            .loader_subgraph_deployment
+            .load_one(self.model.sg_deployment_id)
+            .await?
+            .map(Into::into)
+            .ok_or_else(|| "Subgraph deployment not found".to_string())
+    }
+
+    #[graphql(name = "blockNumber")]
+    async fn graphql_block_number(&self) -> u64 {
+        self.model.block_number.try_into().unwrap()
+    }
+
+    /// The block hash that each indexer reported.
+    #[graphql(name = "reports")]
+    async fn graphql_reports(&self) -> Vec<ChainDisagreementReport> {
+        self.model.reports.iter().cloned().map(Into::into).collect()
+    }
+
+    /// When the disagreement was first detected.
+    #[graphql(name = "detectedAt")]
+    async fn graphql_detected_at(&self) -> Option<chrono::NaiveDateTime> {
+        self.model
+            .reports
+            .iter()
+            .map(|report| report.created_at)
+            .min()
+    }
+}
+
+/// The block hash that a single indexer reported as part of a
+/// [`ChainDisagreement`].
+#[derive(derive_more::From)]
+pub struct ChainDisagreementReport {
+    model: models::ChainDisagreementReport,
+}
+
+#[Object]
+impl ChainDisagreementReport {
+    #[graphql(name = "indexer")]
+    async fn graphql_indexer(&self, ctx: &Context<'_>) -> Result<Indexer, String> {
+        ctx_data(ctx)
+            .loader_indexer
+            .load_one(self.model.indexer_id)
+            .await?
+            .map(Into::into)
+            .ok_or_else(|| "Indexer not found".to_string())
+    }
+
+    #[graphql(name = "blockHash")]
+    async fn graphql_block_hash(&self) -> common::BlockHash {
+        self.model.block_hash.clone()
+    }
+
+    #[graphql(name = "reportedAt")]
+    async fn graphql_reported_at(&self) -> chrono::NaiveDateTime {
+        self.model.created_at
+    }
+}
+
+/// The number of entities an indexer reported for a subgraph deployment.
+#[derive(derive_more::From)]
+pub struct EntityCount {
+    model: models::EntityCount,
+}
+
+#[Object]
+impl EntityCount {
+    #[graphql(name = "indexer")]
+    async fn graphql_indexer(&self, ctx: &Context<'_>) -> Result<Indexer, String> {
+        ctx_data(ctx)
+            .loader_indexer
+            .load_one(self.model.indexer_id)
+            .await?
+            .map(Into::into)
+            .ok_or_else(|| "Indexer not found".to_string())
+    }
+
+    /// The latest block the indexer had indexed when it reported the count.
+    #[graphql(name = "blockNumber")]
+    async fn graphql_block_number(&self) -> u64 {
+        self.model.block_number.try_into().unwrap()
+    }
+
+    #[graphql(name = "entityCount")]
+    async fn graphql_entity_count(&self) -> u64 {
+        self.model.entity_count.try_into().unwrap()
+    }
+
+    /// When Graphix collected the count.
+    #[graphql(name = "collectedAt")]
+    async fn graphql_collected_at(&self) -> chrono::NaiveDateTime {
+        self.model.created_at
+    }
+}
+
+/// The indexing status an indexer reported for a subgraph deployment at some
+/// point in time.
+#[derive(derive_more::From)]
+pub struct IndexingStatusSnapshot {
+    model: models::IndexingStatusSnapshot,
+}
+
+#[Object]
+impl IndexingStatusSnapshot {
+    #[graphql(name = "indexer")]
+    async fn graphql_indexer(&self, ctx: &Context<'_>) -> Result<Indexer, String> {
+        ctx_data(ctx)
+            .loader_indexer
+            .load_one(self.model.indexer_id)
+            .await?
+            .map(Into::into)
+            .ok_or_else(|| "Indexer not found".to_string())
+    }
+
+    #[graphql(name = "latestBlockNumber")]
+    async fn graphql_latest_block_number(&self) -> u64 {
+        self.model.latest_block_number.try_into().unwrap()
+    }
+
+    #[graphql(name = "health")]
+    async fn graphql_health(&self) -> common::SubgraphHealth {
+        self.model.health
+    }
+
+    /// Whether the deployment had caught up with the chain head.
+    #[graphql(name = "synced")]
+    async fn graphql_synced(&self) -> bool {
+        self.model.synced
+    }
+
+    /// When Graphix collected the indexing status.
+    #[graphql(name = "collectedAt")]
+    async fn graphql_collected_at(&self) -> chrono::NaiveDateTime {
+        self.model.created_at
+    }
+}
@@ -0,0 +1,170 @@
+//! `ETag` and `Last-Modified` headers for GraphQL requests that only select
+//! expensive but slowly-changing data. Clients that send the `ETag` back in
+//! `If-None-Match` get a `304 Not Modified` without the query being executed
+//! as long as the underlying data hasn't changed.
+
+use async_graphql::parser::types::{OperationType, Selection};
+use axum::http::header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH, LAST_MODIFIED};
+use axum::http::{HeaderMap, HeaderValue};
+use chrono::NaiveDateTime;
+use graphix_store::Store;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Top-level query fields whose results only depend on the data covered by
+/// [`Store::api_data_version`].
+const CACHEABLE_FIELDS: &[&str] = &["deployments", "networks", "liveProofsOfIndexing"];
+
+/// Cache validators for a single GraphQL request.
+pub struct CacheValidators {
+    etag: String,
+    last_modified: Option<NaiveDateTime>,
+}
+
+impl CacheValidators {
+    /// Computes the validators for the given raw request body, or returns
+    /// [`None`] if the request selects anything that's not cacheable.
+    pub async fn for_request(store: &Store, body: &[u8]) -> anyhow::Result<Option<Self>> {
+        if !is_cacheable(body) {
+            return Ok(None);
+        }
+
+        let (data_version, last_modified) = store.api_data_version().await?;
+        let mut hasher = Sha256::new();
+        hasher.update(body);
+        hasher.update(data_version.as_bytes());
+        let etag = format!("\"{}\"", hex::encode(hasher.finalize()));
+
+        Ok(Some(Self {
+            etag,
+            last_modified,
+        }))
+    }
+
+    /// Whether the client's cached response, if any, is still fresh.
+    pub fn matches(&self, request_headers: &HeaderMap) -> bool {
+        request_headers
+            .get_all(IF_NONE_MATCH)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|etag| {
+                let etag = etag.trim();
+                etag == "*" || etag.trim_start_matches("W/") == self.etag
+            })
+    }
+
+    pub fn add_headers(&self, response_headers: &mut HeaderMap) {
+        if let Ok(etag) = HeaderValue::from_str(&self.etag) {
+            response_headers.insert(ETAG, etag);
+        }
+        if let Some(last_modified) = self.last_modified {
+            let last_modified = last_modified
+                .and_utc()
+                .format("%a, %d %b %Y %H:%M:%S GMT")
+                .to_string();
+            if let Ok(last_modified) = HeaderValue::from_str(&last_modified) {
+                response_headers.insert(LAST_MODIFIED, last_modified);
+            }
+        }
+        // Clients may reuse the response, but only after revalidating it.
+        response_headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlRequestBody {
+    query: String,
+    #[serde(default)]
+    operation_name: Option<String>,
+}
+
+fn is_cacheable(body: &[u8]) -> bool {
+    selects_only(body, CACHEABLE_FIELDS)
+}
+
+/// Whether the raw request body is a single query that only selects the
+/// given top-level fields.
+pub(super) fn selects_only(body: &[u8], fields: &[&str]) -> bool {
+    // Batch requests are never cacheable.
+    let Ok(request) = serde_json::from_slice::<GraphQlRequestBody>(body) else {
+        return false;
+    };
+    let Ok(document) = async_graphql::parser::parse_query(&request.query) else {
+        return false;
+    };
+
+    let mut operations =
+        document
+            .operations
+            .iter()
+            .filter(|(name, _)| match (name, &request.operation_name) {
+                (Some(name), Some(operation_name)) => name.as_str() == operation_name,
+                _ => true,
+            });
+    let (Some((_, operation)), None) = (operations.next(), operations.next()) else {
+        return false;
+    };
+
+    operation.node.ty == OperationType::Query
+        && operation
+            .node
+            .selection_set
+            .node
+            .items
+            .iter()
+            .all(|selection| match &selection.node {
+                Selection::Field(field) => {
+                    let name = field.node.name.node.as_str();
+                    name == "__typename" || fields.contains(&name)
+                }
+                _ => false,
+            })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(query: &str) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({ "query": query })).unwrap()
+    }
+
+    #[test]
+    fn only_slowly_changing_queries_are_cacheable() {
+        assert!(is_cacheable(&body("{ networks { name } }")));
+        assert!(is_cacheable(&body(
+            "query { deployments { cid } liveProofsOfIndexing(filter: {}) { hash } }"
+        )));
+        assert!(!is_cacheable(&body(
+            "{ networks { name } apiKeys { notes } }"
+        )));
+        assert!(!is_cacheable(&body(
+            "mutation { deleteNetwork(network: \"foo\") }"
+        )));
+        assert!(!is_cacheable(&body(
+            "{ ...F } fragment F on QueryRoot { networks { name } }"
+        )));
+        assert!(!is_cacheable(b"[]"));
+    }
+
+    #[test]
+    fn etags_match_if_none_match() {
+        let validators = CacheValidators {
+            etag: "\"abc\"".to_string(),
+            last_modified: None,
+        };
+        let mut headers = HeaderMap::new();
+        assert!(!validators.matches(&headers));
+
+        headers.insert(
+            IF_NONE_MATCH,
+            HeaderValue::from_static("\"foo\", W/\"abc\""),
+        );
+        assert!(validators.matches(&headers));
+
+        headers.insert(IF_NONE_MATCH, HeaderValue::from_static("\"foo\""));
+        assert!(!validators.matches(&headers));
+    }
+}
@@ -1,45 +1,95 @@
 pub mod api_types;
+mod http_caching;
 mod mutations;
+mod oidc;
+mod persisted_queries;
 mod queries;
+mod rate_limits;
+mod read_only;
+mod request_id;
+mod response_cache;
 
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use async_graphql::dataloader::DataLoader;
 use async_graphql::http::GraphiQLSource;
 use async_graphql::{Context, EmptySubscription, Schema, SchemaBuilder};
 use async_graphql_axum::GraphQL;
-use axum::extract::State;
+use axum::extract::{ConnectInfo, State};
 use axum::http::StatusCode;
+use axum::response::IntoResponse;
 use axum::Json;
+use chrono::NaiveDateTime;
 use graphix_common_types::ApiKeyPermissionLevel;
-use graphix_store::models::{self, ApiKey};
-use graphix_store::{Store, StoreLoader};
+use graphix_store::models::{self, ApiKey, ApiKeyLimits, IntId};
+use graphix_store::{ReportStore, Store, StoreLoader};
+use ipnet::IpNet;
+use prometheus_exporter::prometheus;
+use tokio::net::TcpListener;
 use tokio::sync::watch;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::limit::RequestBodyLimitLayer;
 use tower_service::Service;
+use tracing::warn;
 
+use self::http_caching::CacheValidators;
 use self::mutations::MutationRoot;
+use self::oidc::OidcAuthenticator;
+use self::persisted_queries::PersistedQueries;
 use self::queries::QueryRoot;
+use self::rate_limits::RateLimits;
+use self::read_only::ReadOnly;
+use self::request_id::{request_id, with_request_ids, RequestIdErrors, REQUEST_ID_HEADER};
+use self::response_cache::{CacheKey, ResponseCache};
 use crate::config::Config;
-use crate::GRAPHIX_VERSION;
+use crate::signing::Signer;
+use crate::tls::TlsOptions;
+use crate::{metrics_router, GRAPHIX_VERSION};
+
+pub use self::oidc::OidcOptions;
+pub use self::rate_limits::RateLimit;
 
 pub type ApiSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
 
 pub const GRAPHIX_API_KEY_HEADER_NAME: &str = "Graphix-Api-Key";
 
 /// A [`GraphixState`] paired with an [`ApiKey`] that was supplied with a GraphQL
-/// request.
+/// request, and the IP address of the client, if known.
 #[derive(derive_more::Deref)]
 pub struct RequestState {
     api_key: Option<ApiKey>,
+    /// The permission level granted by the OpenID Connect bearer token of
+    /// the request, if it has one and no API key.
+    token_permission_level: Option<ApiKeyPermissionLevel>,
+    client_ip: Option<IpAddr>,
     #[deref]
     data: Arc<GraphixState>,
 }
 
+impl RequestState {
+    /// The state of requests without an API key or bearer token, from an
+    /// unknown client.
+    #[cfg(feature = "loadtest")]
+    pub(crate) fn anonymous(data: Arc<GraphixState>) -> Self {
+        Self {
+            api_key: None,
+            token_permission_level: None,
+            client_ip: None,
+            data,
+        }
+    }
+}
+
 /// Global Graphix state.
 pub struct GraphixState {
     pub store: Store,
+    /// Divergence investigation requests and reports. It's backed by `store`
+    /// unless replaced with [`GraphixState::with_report_store`].
+    pub reports: Arc<dyn ReportStore>,
     pub loader_poi: DataLoader<StoreLoader<models::Poi>>,
     pub loader_network: DataLoader<StoreLoader<models::Network>>,
     pub loader_graph_node_collected_version:
@@ -49,7 +99,26 @@ pub struct GraphixState {
     pub loader_block: DataLoader<StoreLoader<models::Block>>,
     pub loader_indexer: DataLoader<StoreLoader<models::Indexer>>,
     pub loader_subgraph_deployment: DataLoader<StoreLoader<models::SgDeployment>>,
+    /// Keyed by subgraph deployment ID, like
+    /// [`GraphixState::loader_subgraph_network_subgraph_metadata`].
+    pub loader_subgraph_manifest: DataLoader<StoreLoader<models::SgDeploymentManifest>>,
+    pub loader_subgraph_network_subgraph_metadata:
+        DataLoader<StoreLoader<models::SgDeploymentNetworkSubgraphMetadata>>,
     config_receiver: watch::Receiver<Config>,
+    /// Whether mutations and writes to the database are disabled.
+    read_only: bool,
+    /// Responses to expensive queries, if they're cached at all.
+    response_cache: Option<ResponseCache>,
+    persisted_queries: PersistedQueries,
+    /// The rate limits of requests, if they're limited at all.
+    rate_limits: Option<RateLimits>,
+    /// The networks that admin requests may come from. Any network may if
+    /// empty.
+    admin_allowed_networks: Vec<IpNet>,
+    /// Verifies OpenID Connect bearer tokens, if they're accepted at all.
+    oidc: Option<OidcAuthenticator>,
+    /// Signs dispute evidence bundles, if they can be exported at all.
+    evidence_signer: Option<Signer>,
 }
 
 impl GraphixState {
@@ -62,11 +131,28 @@ impl GraphixState {
             loader_block: new_data_loader(&store),
             loader_indexer: new_data_loader(&store),
             loader_subgraph_deployment: new_data_loader(&store),
+            loader_subgraph_manifest: new_data_loader(&store),
+            loader_subgraph_network_subgraph_metadata: new_data_loader(&store),
+            reports: Arc::new(store.clone()),
             store,
             config_receiver,
+            read_only: false,
+            response_cache: None,
+            persisted_queries: PersistedQueries::default(),
+            rate_limits: None,
+            admin_allowed_networks: vec![],
+            oidc: None,
+            evidence_signer: None,
         }
     }
 
+    /// Replaces the backend of divergence investigation requests and
+    /// reports, e.g. with a test double.
+    pub fn with_report_store(mut self, reports: Arc<dyn ReportStore>) -> Self {
+        self.reports = reports;
+        self
+    }
+
     pub fn config(&self) -> Config {
         self.config_receiver.borrow().clone()
     }
@@ -89,16 +175,84 @@ pub fn ctx_data<'a>(ctx: &'a Context) -> &'a RequestState {
         .expect("Failed to get API context")
 }
 
+/// Settings of the GraphQL API server that are fixed at startup.
+#[derive(Debug, Clone)]
+pub struct ApiServerOptions {
+    /// If true, the database is expected to be fully migrated already, and
+    /// all mutations are rejected.
+    pub read_only: bool,
+    /// Origins that may send cross-origin requests. `*` allows any origin.
+    pub cors_allowed_origins: Vec<String>,
+    /// Upper limit on the size of request bodies, in bytes.
+    pub max_request_body_size: usize,
+    /// Whether to also serve Prometheus metrics at `/metrics`, e.g. because
+    /// the API server is the only one served over HTTPS.
+    pub serve_metrics: bool,
+    /// The bearer token that requests for metrics must carry, if any.
+    pub metrics_bearer_token: Option<String>,
+    /// How long to cache the responses to expensive queries, if at all.
+    pub response_cache_ttl: Option<Duration>,
+    /// A JSON file of persisted queries, which restricts what requests
+    /// without an API key may execute, if given.
+    pub persisted_queries_allowlist: Option<PathBuf>,
+    /// The rate limit of requests from each client IP address, if any.
+    pub rate_limit_per_ip: Option<RateLimit>,
+    /// The rate limit of requests with each API key, if any.
+    pub rate_limit_per_api_key: Option<RateLimit>,
+    /// The networks that requests for admin-level operations must come
+    /// from, in addition to having an admin API key. Any network may if
+    /// empty.
+    pub admin_allowed_networks: Vec<IpNet>,
+    /// Accepts OpenID Connect bearer tokens as an alternative to API keys,
+    /// if given.
+    pub oidc: Option<OidcOptions>,
+    /// A PEM file with the Ed25519 key that dispute evidence bundles are
+    /// signed with. Bundles can only be exported if given.
+    pub dispute_evidence_signing_key: Option<PathBuf>,
+    /// Database statements that run longer than this are cancelled, if
+    /// given.
+    pub statement_timeout: Option<Duration>,
+}
+
+/// The router of the GraphQL API server.
 pub async fn axum_router(
     database_url: &str,
     config_receiver: watch::Receiver<Config>,
+    options: ApiServerOptions,
 ) -> anyhow::Result<axum::Router<()>> {
     use axum::routing::get;
 
-    let store = Store::new(database_url).await?;
-    let server_state = GraphixState::new(store.clone(), config_receiver);
+    let store = if options.read_only {
+        Store::new_read_only(database_url, options.statement_timeout).await?
+    } else {
+        // Only the leader of the collection rounds migrates the database.
+        Store::new_without_migrations_with_statement_timeout(
+            database_url,
+            options.statement_timeout,
+        )
+        .await?
+    };
+    tokio::spawn(crate::run_pool_metrics("api", store.clone()));
+    let mut server_state = GraphixState::new(store.clone(), config_receiver);
+    server_state.read_only = options.read_only;
+    server_state.response_cache = options.response_cache_ttl.map(ResponseCache::new);
+    let allowlist = options
+        .persisted_queries_allowlist
+        .as_deref()
+        .map(PersistedQueries::load_allowlist)
+        .transpose()?;
+    server_state.persisted_queries = PersistedQueries::new(allowlist);
+    server_state.rate_limits =
+        RateLimits::new(options.rate_limit_per_ip, options.rate_limit_per_api_key);
+    server_state.admin_allowed_networks = options.admin_allowed_networks;
+    server_state.oidc = options.oidc.map(OidcAuthenticator::new);
+    server_state.evidence_signer = options
+        .dispute_evidence_signing_key
+        .as_deref()
+        .map(Signer::from_pem_file)
+        .transpose()?;
 
-    Ok(axum::Router::new()
+    let router = axum::Router::new()
         .route(
             "/",
             get(|| async {
@@ -108,13 +262,120 @@ pub async fn axum_router(
                 )
             }),
         )
-        .route("/graphql", get(graphiql_route).post(graphql_handler))
-        .with_state(Arc::new(server_state)))
+        .route("/graphql", get(graphiql_route).post(graphql_handler));
+    let mut router = router.with_state(Arc::new(server_state));
+    if options.serve_metrics {
+        router = router.merge(metrics_router(
+            prometheus::default_registry().clone(),
+            options.metrics_bearer_token,
+        ));
+    }
+    let mut router =
+        with_request_ids(router.layer(RequestBodyLimitLayer::new(options.max_request_body_size)));
+    if let Some(cors) = cors_layer(&options.cors_allowed_origins)? {
+        router = router.layer(cors);
+    }
+
+    Ok(router)
+}
+
+/// Serves `router` at `address`, over HTTPS if `tls` is given. Handlers can
+/// extract the address of clients as [`ConnectInfo`].
+pub async fn serve_api(
+    address: SocketAddr,
+    router: axum::Router<()>,
+    tls: Option<&TlsOptions>,
+) -> anyhow::Result<()> {
+    match tls {
+        Some(tls) => {
+            axum_server::bind_rustls(address, tls.load().await?)
+                .serve(router.into_make_service_with_connect_info::<SocketAddr>())
+                .await?
+        }
+        None => {
+            axum::serve(
+                TcpListener::bind(address).await?,
+                router.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await?
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns a CORS layer that allows GraphQL requests from the given origins,
+/// or [`None`] if no origins are allowed.
+fn cors_layer(allowed_origins: &[String]) -> anyhow::Result<Option<CorsLayer>> {
+    use axum::http::header::{AUTHORIZATION, CONTENT_TYPE, ETAG, IF_NONE_MATCH};
+    use axum::http::{HeaderName, HeaderValue, Method};
+
+    let allow_origin = if allowed_origins.is_empty() {
+        return Ok(None);
+    } else if allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        let origins = allowed_origins
+            .iter()
+            .map(|origin| HeaderValue::from_str(origin))
+            .collect::<Result<Vec<_>, _>>()?;
+        AllowOrigin::list(origins)
+    };
+
+    Ok(Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::GET, Method::POST])
+            .allow_headers([
+                AUTHORIZATION,
+                CONTENT_TYPE,
+                IF_NONE_MATCH,
+                HeaderName::from_bytes(GRAPHIX_API_KEY_HEADER_NAME.as_bytes())?,
+                HeaderName::from_static(REQUEST_ID_HEADER),
+            ])
+            .expose_headers([ETAG, HeaderName::from_static(REQUEST_ID_HEADER)]),
+    ))
 }
 
 async fn graphql_handler(
     State(state): State<Arc<GraphixState>>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    request: axum::extract::Request,
+) -> Result<axum::response::Response, (StatusCode, Json<serde_json::Value>)> {
+    let request_id = request_id(request.headers());
+    let client_ip = connect_info.map(|ConnectInfo(address)| address.ip());
+    // Rate limits by IP address are enforced before anything else, so that
+    // rejected requests are as cheap as possible. Those by API key need the
+    // key to be looked up first.
+    if let Some(rate_limits) = &state.rate_limits {
+        if let Err(rate_limited) = rate_limits.check_ip(client_ip) {
+            return Ok(rate_limited.response());
+        }
+    }
+    // The request is dropped if its client disconnects, in which case there's
+    // no point in finishing its database statements.
+    let store = state.store.clone();
+    store
+        .cancel_statements_on_drop(handle_graphql_request(
+            state,
+            request,
+            client_ip,
+            request_id.clone(),
+        ))
+        .await
+        .map_err(|(status, Json(mut body))| {
+            if let (Some(request_id), Some(body)) = (request_id, body.as_object_mut()) {
+                body.insert("requestId".to_string(), request_id.into());
+            }
+            (status, Json(body))
+        })
+}
+
+async fn handle_graphql_request(
+    state: Arc<GraphixState>,
     request: axum::extract::Request,
+    client_ip: Option<IpAddr>,
+    request_id: Option<String>,
 ) -> Result<axum::response::Response, (StatusCode, Json<serde_json::Value>)> {
     let api_key = match request.headers().get(GRAPHIX_API_KEY_HEADER_NAME) {
         None => None,
@@ -125,19 +386,208 @@ async fn graphql_handler(
             Some(api_key)
         }
     };
+    // API keys take precedence over bearer tokens.
+    let bearer_token = request
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let token_permission_level = match (&state.oidc, &api_key, bearer_token) {
+        (Some(oidc), None, Some(token)) => oidc
+            .permission_level(token)
+            .await
+            .map_err(bearer_token_error)?,
+        _ => None,
+    };
+    if let Some(api_key) = &api_key {
+        let limits = state
+            .store
+            .api_key_limits(api_key)
+            .await
+            .map_err(|e| request_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        if let Some(limits) = limits {
+            if let Some(rate_limits) = &state.rate_limits {
+                if let Err(rate_limited) = rate_limits.check_api_key(&api_key.to_string()) {
+                    return Ok(rate_limited.response());
+                }
+            }
+            check_api_key_limits(&limits, chrono::Utc::now().naive_utc())?;
+        }
+    }
 
-    let api_schema = api_schema_builder()
-        .data(RequestState {
-            api_key,
-            data: state.clone(),
-        })
-        .finish();
-
-    let mut service = GraphQL::new(api_schema);
-    service
-        .call(request)
+    let (parts, body) = request.into_parts();
+    let body = axum::body::to_bytes(body, usize::MAX)
         .await
-        .map_err(|_| api_key_error("Internal server error"))
+        .map_err(|e| request_error(StatusCode::BAD_REQUEST, e))?;
+    // Unknown API keys don't exempt requests from the allowlist.
+    let authenticated = match &api_key {
+        Some(api_key) if state.persisted_queries.has_allowlist() => state
+            .store
+            .permission_level(api_key)
+            .await
+            .map_err(|e| request_error(StatusCode::INTERNAL_SERVER_ERROR, e))?
+            .is_some(),
+        Some(_) => true,
+        None => token_permission_level.is_some(),
+    };
+    let content_type = parts
+        .headers
+        .get(axum::http::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    let body = state
+        .persisted_queries
+        .resolve(content_type, body, authenticated)
+        .map_err(|e| e.response())?;
+    let cache_validators = CacheValidators::for_request(&state.store, &body)
+        .await
+        .map_err(|e| request_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    if let Some(validators) = &cache_validators {
+        if validators.matches(&parts.headers) {
+            let mut response = StatusCode::NOT_MODIFIED.into_response();
+            validators.add_headers(response.headers_mut());
+            return Ok(response);
+        }
+    }
+    let (cached_response, response_cache_key) = match &state.response_cache {
+        Some(cache) if cache.is_cacheable(&body) => {
+            let (key, latest_poi_id) = response_cache_key(
+                &state.store,
+                api_key.as_ref(),
+                token_permission_level,
+                &body,
+            )
+            .await
+            .map_err(|e| request_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+            (cache.get(&key, latest_poi_id), Some((key, latest_poi_id)))
+        }
+        _ => (None, None),
+    };
+    let request = axum::extract::Request::from_parts(parts, axum::body::Body::from(body));
+
+    let mut api_schema = api_schema_builder().data(RequestState {
+        api_key: api_key.clone(),
+        token_permission_level,
+        client_ip,
+        data: state.clone(),
+    });
+    if state.read_only {
+        api_schema = api_schema.extension(ReadOnly);
+    }
+    if let Some(request_id) = request_id {
+        api_schema = api_schema.extension(RequestIdErrors::new(request_id));
+    }
+    let api_schema = api_schema.finish();
+
+    let started_at = Instant::now();
+    let mut response = match cached_response {
+        Some(response) => response,
+        None => {
+            let response = GraphQL::new(api_schema)
+                .call(request)
+                .await
+                .map_err(|_| api_key_error("Internal server error"))?;
+            match (&state.response_cache, response_cache_key) {
+                (Some(cache), Some((key, latest_poi_id))) => cache
+                    .insert(key, latest_poi_id, response)
+                    .await
+                    .map_err(|e| request_error(StatusCode::INTERNAL_SERVER_ERROR, e))?,
+                _ => response,
+            }
+        }
+    };
+
+    if let Some(api_key) = api_key.filter(|_| !state.read_only) {
+        let execution_time = started_at.elapsed();
+        let store = state.store.clone();
+        tokio::spawn(async move {
+            if let Err(err) = store.record_api_key_usage(&api_key, execution_time).await {
+                warn!(error = %err, "Failed to record API key usage");
+            }
+        });
+    }
+
+    if let Some(validators) = cache_validators {
+        if response.status() == StatusCode::OK {
+            validators.add_headers(response.headers_mut());
+        }
+    }
+
+    Ok(response)
+}
+
+/// Returns the key under which the response to a request is cached, and the
+/// latest PoI that cached responses must be up to date with.
+async fn response_cache_key(
+    store: &Store,
+    api_key: Option<&ApiKey>,
+    token_permission_level: Option<ApiKeyPermissionLevel>,
+    body: &[u8],
+) -> anyhow::Result<(CacheKey, Option<IntId>)> {
+    let (permission_level, workspace_id) = match api_key {
+        Some(api_key) => (
+            store.permission_level(api_key).await?,
+            store.api_key_workspace_id(api_key).await?,
+        ),
+        None => (token_permission_level, None),
+    };
+    let latest_poi_id = store.latest_poi_id().await?;
+
+    Ok((
+        CacheKey::new(body, permission_level, workspace_id),
+        latest_poi_id,
+    ))
+}
+
+/// Rejects requests made with expired API keys or API keys that have used up
+/// their daily quota.
+fn check_api_key_limits(
+    limits: &ApiKeyLimits,
+    now: NaiveDateTime,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    if let Some(expires_at) = limits.expires_at {
+        if expires_at <= now {
+            return Err(api_key_error(format!(
+                "API key expired at {} UTC",
+                expires_at.format("%Y-%m-%d %H:%M:%S")
+            )));
+        }
+    }
+    if let Some(quota) = limits.daily_request_quota {
+        if limits.requests_today >= i64::from(quota) {
+            return Err((
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(serde_json::json!({
+                    "message": "API key quota exceeded",
+                    "error": format!(
+                        "daily quota of {} requests exceeded, resets at midnight UTC",
+                        quota
+                    ),
+                })),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn request_error(status: StatusCode, err: impl ToString) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        status,
+        Json(serde_json::json!({
+            "message": "Invalid request",
+            "error": err.to_string(),
+        })),
+    )
+}
+
+fn bearer_token_error(err: impl ToString) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(serde_json::json!({
+            "message": "Invalid bearer token",
+            "error": err.to_string(),
+        })),
+    )
 }
 
 fn api_key_error(err: impl ToString) -> (StatusCode, Json<serde_json::Value>) {
@@ -159,13 +609,20 @@ async fn require_permission_level(
     required_permission_level: ApiKeyPermissionLevel,
 ) -> async_graphql::Result<()> {
     let ctx_data = ctx_data(ctx);
-    let api_key = ctx_data
-        .api_key
-        .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("No API key provided"))?;
-
-    let Some(actual_permission_level) = ctx_data.store.permission_level(api_key).await? else {
-        return Err(anyhow::anyhow!("No permission level for API key").into());
+    if required_permission_level >= ApiKeyPermissionLevel::Admin
+        && !is_in_networks(ctx_data.client_ip, &ctx_data.admin_allowed_networks)
+    {
+        return Err(anyhow::anyhow!("Admin operations aren't allowed from this IP address").into());
+    }
+    let actual_permission_level = match (&ctx_data.api_key, ctx_data.token_permission_level) {
+        (Some(api_key), _) => {
+            let Some(permission_level) = ctx_data.store.permission_level(api_key).await? else {
+                return Err(anyhow::anyhow!("No permission level for API key").into());
+            };
+            permission_level
+        }
+        (None, Some(permission_level)) => permission_level,
+        (None, None) => return Err(anyhow::anyhow!("No API key provided").into()),
     };
 
     if actual_permission_level < required_permission_level {
@@ -179,3 +636,141 @@ async fn require_permission_level(
 
     Ok(())
 }
+
+/// Whether `client_ip` is in one of `networks`, which allow any address,
+/// even an unknown one, if empty.
+fn is_in_networks(client_ip: Option<IpAddr>, networks: &[IpNet]) -> bool {
+    networks.is_empty()
+        || client_ip.is_some_and(|client_ip| {
+            networks
+                .iter()
+                .any(|network| network.contains(&client_ip.to_canonical()))
+        })
+}
+
+/// Returns the workspace that the API key of the request is scoped to, if
+/// any. Requests without an API key aren't scoped to any workspace.
+async fn request_workspace_id(ctx: &Context<'_>) -> async_graphql::Result<Option<IntId>> {
+    let ctx_data = ctx_data(ctx);
+    let Some(api_key) = &ctx_data.api_key else {
+        return Ok(None);
+    };
+
+    Ok(ctx_data.store.api_key_workspace_id(api_key).await?)
+}
+
+/// Rejects requests with an API key that is scoped to a workspace, unless
+/// `api_key` is scoped to the same workspace. Unscoped API keys can manage
+/// all API keys.
+async fn require_api_key_in_workspace(
+    ctx: &Context<'_>,
+    api_key: &str,
+) -> async_graphql::Result<()> {
+    let Some(workspace_id) = request_workspace_id(ctx).await? else {
+        return Ok(());
+    };
+
+    let api_key =
+        ApiKey::from_str(api_key).map_err(|e| anyhow::anyhow!("invalid api key: {}", e))?;
+    let api_key_workspace_id = ctx_data(ctx).store.api_key_workspace_id(&api_key).await?;
+    if api_key_workspace_id != Some(workspace_id) {
+        return Err(anyhow::anyhow!(
+            "API keys scoped to a workspace can only manage API keys of the same workspace"
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Like [`require_permission_level`] with the `admin` permission level, but
+/// also rejects API keys that are scoped to a workspace.
+async fn require_unscoped_admin(ctx: &Context<'_>) -> async_graphql::Result<()> {
+    require_permission_level(ctx, ApiKeyPermissionLevel::Admin).await?;
+
+    if request_workspace_id(ctx).await?.is_some() {
+        return Err(anyhow::anyhow!("API keys scoped to a workspace can't do this").into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::*;
+
+    #[test]
+    fn expired_and_over_quota_api_keys_are_rejected() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0)
+            .unwrap()
+            .naive_utc();
+        let limits = |expires_in: Option<i64>, daily_request_quota, requests_today| ApiKeyLimits {
+            expires_at: expires_in.map(|seconds| now + chrono::Duration::seconds(seconds)),
+            daily_request_quota,
+            requests_today,
+        };
+        let status = |limits: ApiKeyLimits| check_api_key_limits(&limits, now).err().map(|e| e.0);
+
+        assert_eq!(status(limits(None, None, 1000)), None);
+        assert_eq!(status(limits(Some(1), Some(10), 9)), None);
+        assert_eq!(
+            status(limits(Some(0), None, 0)),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            status(limits(None, Some(10), 10)),
+            Some(StatusCode::TOO_MANY_REQUESTS)
+        );
+    }
+
+    #[test]
+    fn admin_requests_from_allowed_networks() {
+        let networks: Vec<IpNet> = vec!["10.0.0.0/8".parse().unwrap(), "::1/128".parse().unwrap()];
+        let ip = |ip: &str| Some(ip.parse::<IpAddr>().unwrap());
+
+        assert!(is_in_networks(None, &[]));
+        assert!(is_in_networks(ip("10.1.2.3"), &networks));
+        assert!(is_in_networks(ip("::1"), &networks));
+        // IPv4 clients of dual-stack listeners have IPv4-mapped addresses.
+        assert!(is_in_networks(ip("::ffff:10.1.2.3"), &networks));
+        assert!(!is_in_networks(ip("192.168.1.1"), &networks));
+        assert!(!is_in_networks(None, &networks));
+    }
+
+    #[tokio::test]
+    async fn cors_requests_from_allowed_origins() {
+        use axum::http::header::{
+            ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN,
+        };
+        use axum::http::Method;
+
+        assert!(cors_layer(&[]).unwrap().is_none());
+
+        let cors = cors_layer(&["https://graphix.example.com".to_string()])
+            .unwrap()
+            .unwrap();
+        let mut router = axum::Router::new()
+            .route("/graphql", axum::routing::post(|| async {}))
+            .layer(cors);
+        let mut preflight = |origin: &str| {
+            let request = axum::extract::Request::builder()
+                .method(Method::OPTIONS)
+                .uri("/graphql")
+                .header(ORIGIN, origin)
+                .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                .body(axum::body::Body::empty())
+                .unwrap();
+            router.call(request)
+        };
+
+        let response = preflight("https://graphix.example.com").await.unwrap();
+        assert_eq!(
+            response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://graphix.example.com"
+        );
+        let response = preflight("https://example.com").await.unwrap();
+        assert!(!response.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+}
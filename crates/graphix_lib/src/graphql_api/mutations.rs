@@ -1,22 +1,33 @@
+use anyhow::Context as _;
 use async_graphql::{Context, Object, Result};
 use graphix_common_types::*;
-use graphix_store::models::{DivergenceInvestigationRequest, NewlyCreatedApiKey};
+use graphix_store::models::{
+    DivergenceInvestigationRequest, NewRegisteredIndexer, NewlyCreatedApiKey, TrackedDeployment,
+    Workspace,
+};
+use uuid::Uuid;
 
-use super::{ctx_data, require_permission_level};
+use super::{
+    api_types, ctx_data, request_workspace_id, require_api_key_in_workspace,
+    require_permission_level, require_unscoped_admin,
+};
+use crate::bisect::{enqueue_divergence_investigations, live_divergence_investigation_requests};
+use crate::block_choice::BlockChoicePolicy;
+use crate::config::{ChainConfig, Config, ConfigSource};
 
 pub struct MutationRoot;
 
 #[Object]
 impl MutationRoot {
     /// Launches a divergence investigation, which is a process of comparing
-    /// two or more PoIs (up to four) and running a binary search to find the first
-    /// diverging block.
+    /// two or more PoIs (up to four, or up to sixteen for cohort bisection) and
+    /// running a binary search to find the first diverging block.
     async fn launch_divergence_investigation(
         &self,
         ctx: &Context<'_>,
         #[graphql(
-            validator(min_items = 2, max_items = 4),
-            desc = "A list of PoI hashes that should be investigated for divergence. If this list contains more than two PoIs, a new bisection run will be performed for each unordered pair of PoIs."
+            validator(min_items = 2, max_items = 16),
+            desc = "A list of PoI hashes that should be investigated for divergence. If this list contains more than two PoIs, a new bisection run will be performed for each unordered pair of PoIs, unless `cohortBisection` is set."
         )]
         pois: Vec<PoiBytes>,
         #[graphql(
@@ -34,18 +45,30 @@ impl MutationRoot {
             desc = "Indicates whether to collect `graph-node`'s entity changes during bisection runs to include in the report."
         )]
         query_entity_changes: bool,
+        #[graphql(
+            default = false,
+            desc = "Bisects all indexers that produced one of the PoIs at once, partitioning them into cohorts that agree with each other at each probed block, instead of bisecting each pair of PoIs. No `graph-node` debugging data is collected in this mode."
+        )]
+        cohort_bisection: bool,
+        #[graphql(
+            default = false,
+            desc = "Stores the raw response bodies returned by indexers during the investigation, for debugging. They can be retrieved with the `rawIndexerResponses` query."
+        )]
+        capture_raw_responses: bool,
     ) -> Result<DivergenceInvestigationReport> {
         let ctx_data = ctx_data(ctx);
-        let store = &ctx_data.store;
 
         let req = DivergenceInvestigationRequest {
             pois,
             query_block_caches,
             query_eth_call_caches,
             query_entity_changes,
+            cohort_bisection,
+            capture_raw_responses,
         };
         let request_serialized = serde_json::to_value(req).unwrap();
-        let uuid = store
+        let uuid = ctx_data
+            .reports
             .create_divergence_investigation_request(request_serialized)
             .await?;
 
@@ -53,27 +76,200 @@ impl MutationRoot {
             uuid,
             status: DivergenceInvestigationStatus::Pending,
             bisection_runs: vec![],
+            cohort_bisection: None,
             error: None,
+            progress: None,
+            signature: None,
         };
 
         Ok(report)
     }
 
-    async fn set_configuration(
+    /// Launches a divergence investigation for every subgraph deployment on
+    /// which indexers currently disagree, i.e. whose live PoIs for the same
+    /// block differ. Each investigation covers the most reported distinct PoIs
+    /// at that block. No more investigations are launched than the
+    /// `maxPendingRequests` of the `autoInvestigations` configuration allows.
+    /// Returns the UUIDs of the launched investigations. Requires the `admin`
+    /// permission level.
+    async fn launch_investigations_for_all_divergences(
         &self,
         ctx: &Context<'_>,
-        #[graphql(desc = "The configuration file to use")] config: serde_json::Value,
-    ) -> Result<bool> {
+        #[graphql(default)] filter: inputs::DivergencesQuery,
+        #[graphql(default = true)] query_block_caches: bool,
+        #[graphql(default = true)] query_eth_call_caches: bool,
+        #[graphql(default = true)] query_entity_changes: bool,
+        #[graphql(default = false)] cohort_bisection: bool,
+        #[graphql(default = false)] capture_raw_responses: bool,
+    ) -> Result<Vec<Uuid>> {
         require_permission_level(ctx, ApiKeyPermissionLevel::Admin).await?;
 
         let ctx_data = ctx_data(ctx);
         let store = &ctx_data.store;
 
-        store.overwrite_config(config).await?;
+        let indexer_id = match filter.indexer {
+            Some(address) => {
+                let indexers = store
+                    .indexers(inputs::IndexersQuery {
+                        address: Some(address),
+                        ..Default::default()
+                    })
+                    .await?;
+                match indexers.first() {
+                    Some(indexer) => Some(indexer.id),
+                    None => return Ok(vec![]),
+                }
+            }
+            None => None,
+        };
+        let deployments = (!filter.deployments.is_empty()).then_some(filter.deployments.as_slice());
+        let live_pois = store.live_pois(None, &[], deployments, None, None).await?;
+
+        let template = DivergenceInvestigationRequest {
+            pois: vec![],
+            query_block_caches,
+            query_eth_call_caches,
+            query_entity_changes,
+            cohort_bisection,
+            capture_raw_responses,
+        };
+        let requests = live_divergence_investigation_requests(&live_pois, indexer_id, &template);
+        let uuids = enqueue_divergence_investigations(
+            ctx_data.reports.as_ref(),
+            &ctx_data.config().auto_investigations,
+            requests,
+        )
+        .await?;
+
+        Ok(uuids)
+    }
+
+    /// Cancels a pending or running divergence investigation. Its report keeps
+    /// the results gathered until then, with the `CANCELLED` status. Returns
+    /// false if there's no such investigation or it's already over. Requires
+    /// the `admin` permission level.
+    async fn cancel_divergence_investigation(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(
+            desc = "The UUID of the divergence investigation, as returned by the `launchDivergenceInvestigation` mutation."
+        )]
+        uuid: Uuid,
+    ) -> Result<bool> {
+        require_permission_level(ctx, ApiKeyPermissionLevel::Admin).await?;
+        let ctx_data = ctx_data(ctx);
+
+        Ok(ctx_data
+            .reports
+            .cancel_divergence_investigation_request(&uuid)
+            .await?)
+    }
+
+    /// Moves a dead-lettered divergence investigation back to the job queue,
+    /// with a fresh set of attempts. Its report is replaced once the
+    /// investigation is over again. Returns false if there's no such
+    /// dead-lettered investigation. Requires the `admin` permission level.
+    async fn requeue_dead_lettered_divergence_investigation(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "The UUID of the divergence investigation.")] uuid: Uuid,
+    ) -> Result<bool> {
+        require_permission_level(ctx, ApiKeyPermissionLevel::Admin).await?;
+
+        let ctx_data = ctx_data(ctx);
+
+        Ok(ctx_data
+            .reports
+            .requeue_dead_lettered_divergence_investigation_job(&uuid)
+            .await?)
+    }
+
+    /// Replaces the whole configuration, which Graphix reloads on its next
+    /// polling round. Requires the `admin` permission level.
+    async fn set_configuration(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "The configuration file to use")] config: serde_json::Value,
+    ) -> Result<bool> {
+        require_unscoped_admin(ctx).await?;
+
+        let config: Config = serde_json::from_value(config).context("invalid configuration")?;
+        update_configuration(ctx, |current| *current = config).await?;
 
         Ok(true)
     }
 
+    /// Replaces the configuration sources (indexers, network subgraphs,
+    /// etc.). Returns the new configuration. Requires the `admin` permission
+    /// level.
+    async fn set_configuration_sources(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(
+            desc = "A list of configuration sources, in the same format as the `sources` of a configuration file"
+        )]
+        sources: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        require_unscoped_admin(ctx).await?;
+
+        let sources: Vec<ConfigSource> =
+            serde_json::from_value(sources).context("invalid configuration sources")?;
+        update_configuration(ctx, |config| config.sources = sources).await
+    }
+
+    /// Sets how often Graphix polls indexers. Returns the new configuration.
+    /// Requires the `admin` permission level.
+    async fn set_polling_period(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(minimum = 1))] polling_period_in_seconds: u64,
+    ) -> Result<serde_json::Value> {
+        require_unscoped_admin(ctx).await?;
+
+        update_configuration(ctx, |config| {
+            config.polling_period_in_seconds = polling_period_in_seconds
+        })
+        .await
+    }
+
+    /// Sets the policy used to choose the block at which PoIs are compared.
+    /// Returns the new configuration. Requires the `admin` permission level.
+    async fn set_block_choice_policy(
+        &self,
+        ctx: &Context<'_>,
+        policy: BlockChoicePolicy,
+    ) -> Result<serde_json::Value> {
+        require_unscoped_admin(ctx).await?;
+
+        update_configuration(ctx, |config| config.block_choice_policy = policy).await
+    }
+
+    /// Adds, replaces or (if `chain` is null) removes the configuration of a
+    /// single chain. Returns the new configuration. Requires the `admin`
+    /// permission level.
+    async fn set_chain_configuration(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "The network name of the chain")] name: String,
+        chain: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value> {
+        require_unscoped_admin(ctx).await?;
+
+        let chain: Option<ChainConfig> = chain
+            .map(serde_json::from_value)
+            .transpose()
+            .context("invalid chain configuration")?;
+        update_configuration(ctx, |config| match chain {
+            Some(chain) => {
+                config.chains.insert(name, chain);
+            }
+            None => {
+                config.chains.remove(&name);
+            }
+        })
+        .await
+    }
+
     /// Create a new API key with the given permission level. You'll need to
     /// authenticate with another API key with the `admin` permission level to
     /// do this.
@@ -94,15 +290,22 @@ impl MutationRoot {
 
         let ctx_data = ctx_data(ctx);
 
+        // API keys created with a key that's scoped to a workspace are scoped
+        // to the same workspace.
+        let workspace_id = request_workspace_id(ctx).await?;
         let api_key = ctx_data
             .store
-            .create_api_key(notes.as_deref(), permission_level)
+            .create_api_key(notes.as_deref(), permission_level, workspace_id)
             .await?;
 
         Ok(api_key)
     }
 
+    /// Deletes an API key. Requires the `admin` permission level and an API
+    /// key that isn't scoped to a workspace.
     async fn delete_api_key(&self, ctx: &Context<'_>, api_key: String) -> Result<bool> {
+        require_unscoped_admin(ctx).await?;
+
         let ctx_data = ctx_data(ctx);
 
         ctx_data.store.delete_api_key(&api_key).await?;
@@ -110,6 +313,8 @@ impl MutationRoot {
         Ok(true)
     }
 
+    /// Changes the notes and permission level of an API key. API keys scoped
+    /// to a workspace can only modify API keys of the same workspace.
     async fn modify_api_key(
         &self,
         ctx: &Context<'_>,
@@ -121,6 +326,7 @@ impl MutationRoot {
         permission_level: ApiKeyPermissionLevel,
     ) -> Result<bool> {
         require_permission_level(ctx, permission_level).await?;
+        require_api_key_in_workspace(ctx, &api_key).await?;
 
         let ctx_data = ctx_data(ctx);
 
@@ -132,12 +338,91 @@ impl MutationRoot {
         Ok(true)
     }
 
+    /// Sets the expiry time and daily request quota of an API key; null
+    /// removes the respective limit. Requests made with an expired API key,
+    /// or beyond its quota, are rejected. Requires the `admin` permission
+    /// level.
+    async fn set_api_key_limits(
+        &self,
+        ctx: &Context<'_>,
+        api_key: String,
+        #[graphql(desc = "The time after which the API key is rejected, in UTC.")]
+        expires_at: Option<chrono::NaiveDateTime>,
+        #[graphql(desc = "The maximum number of GraphQL requests per UTC day.")]
+        daily_request_quota: Option<u32>,
+    ) -> Result<bool> {
+        require_unscoped_admin(ctx).await?;
+
+        let daily_request_quota = daily_request_quota
+            .map(i32::try_from)
+            .transpose()
+            .context("daily request quota is too large")?;
+
+        let ctx_data = ctx_data(ctx);
+        ctx_data
+            .store
+            .set_api_key_limits(&api_key, expires_at, daily_request_quota)
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Creates a workspace, to which API keys can then be scoped with
+    /// `setApiKeyWorkspace`. Requires the `admin` permission level and an API
+    /// key that isn't scoped to a workspace.
+    async fn create_workspace(&self, ctx: &Context<'_>, name: String) -> Result<Workspace> {
+        require_unscoped_admin(ctx).await?;
+
+        let ctx_data = ctx_data(ctx);
+        let workspace = ctx_data.store.create_workspace(&name).await?;
+
+        Ok(workspace)
+    }
+
+    /// Deletes a workspace together with its API keys, registered indexers
+    /// and tracked deployments. Returns `false` if there was no such
+    /// workspace. Requires the `admin` permission level and an API key that
+    /// isn't scoped to a workspace.
+    async fn delete_workspace(&self, ctx: &Context<'_>, name: String) -> Result<bool> {
+        require_unscoped_admin(ctx).await?;
+
+        let ctx_data = ctx_data(ctx);
+        let deleted = ctx_data.store.delete_workspace(&name).await?;
+
+        Ok(deleted)
+    }
+
+    /// Scopes an API key to a workspace, or unscopes it if `workspace` is
+    /// null. Requires the `admin` permission level and an API key that isn't
+    /// scoped to a workspace.
+    async fn set_api_key_workspace(
+        &self,
+        ctx: &Context<'_>,
+        api_key: String,
+        #[graphql(desc = "The name of the workspace")] workspace: Option<String>,
+    ) -> Result<bool> {
+        require_unscoped_admin(ctx).await?;
+
+        let ctx_data = ctx_data(ctx);
+        ctx_data
+            .store
+            .set_api_key_workspace(&api_key, workspace.as_deref())
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Sets the human-readable name of a subgraph deployment. Requires the
+    /// `admin` permission level and an API key that isn't scoped to a
+    /// workspace.
     async fn set_deployment_name(
         &self,
         ctx: &Context<'_>,
         deployment_ipfs_cid: String,
         name: String,
     ) -> Result<Deployment> {
+        require_unscoped_admin(ctx).await?;
+
         let ctx_data = ctx_data(ctx);
         let store = &ctx_data.store;
 
@@ -150,11 +435,209 @@ impl MutationRoot {
         })
     }
 
+    /// Adds an indexer to the registry of indexers that are cross-checked on
+    /// top of those in the configuration file, starting from the next polling
+    /// round. Registering an already registered address updates its name and
+    /// endpoint. Requires the `admin` permission level.
+    async fn add_indexer(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "The address of the indexer, encoded as a hex string with a '0x' prefix")]
+        address: IndexerAddress,
+        #[graphql(desc = "The URL of the indexer's `graph-node` index-node endpoint")]
+        endpoint: String,
+        #[graphql(desc = "Human-readable name of the indexer")] name: Option<String>,
+    ) -> Result<api_types::RegisteredIndexer> {
+        require_permission_level(ctx, ApiKeyPermissionLevel::Admin).await?;
+
+        let endpoint = url::Url::parse(&endpoint)?;
+
+        let ctx_data = ctx_data(ctx);
+        let indexer = ctx_data
+            .store
+            .register_indexer(&NewRegisteredIndexer {
+                address,
+                name,
+                index_node_endpoint: endpoint.to_string(),
+                workspace_id: request_workspace_id(ctx).await?,
+            })
+            .await?;
+
+        Ok(indexer.into())
+    }
+
+    /// Removes an indexer that was added with `addIndexer`, starting from the
+    /// next polling round. Data collected from the indexer is kept; see
+    /// `deleteIndexerData`. Returns `false` if the indexer wasn't registered.
+    /// Requires the `admin` permission level.
+    async fn remove_indexer(&self, ctx: &Context<'_>, address: IndexerAddress) -> Result<bool> {
+        require_permission_level(ctx, ApiKeyPermissionLevel::Admin).await?;
+
+        let ctx_data = ctx_data(ctx);
+        let workspace_id = request_workspace_id(ctx).await?;
+        let removed = ctx_data
+            .store
+            .unregister_indexer(&address, workspace_id)
+            .await?;
+
+        Ok(removed)
+    }
+
+    /// Archives an indexer, e.g. because it left the network, or unarchives
+    /// it. Archived indexers are hidden from `indexers` by default and are
+    /// no longer cross-checked, starting from the next polling round, but
+    /// their PoIs are kept. Returns null if there's no such indexer.
+    /// Requires the `admin` permission level.
+    async fn set_indexer_archived(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "The address of the indexer, encoded as a hex string with a '0x' prefix")]
+        address: IndexerAddress,
+        archived: bool,
+    ) -> Result<Option<api_types::Indexer>> {
+        require_unscoped_admin(ctx).await?;
+
+        let ctx_data = ctx_data(ctx);
+        let indexer = ctx_data
+            .store
+            .set_indexer_archived(&address, archived)
+            .await?;
+
+        Ok(indexer.map(Into::into))
+    }
+
+    /// Replaces the tags of an indexer, e.g. `upgrade-canary` or `eu-west`,
+    /// that were set through this mutation. Tags from the configuration are
+    /// kept. Returns null if there's no such indexer. Requires the `admin`
+    /// permission level.
+    async fn set_indexer_tags(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "The address of the indexer, encoded as a hex string with a '0x' prefix")]
+        address: IndexerAddress,
+        tags: Vec<String>,
+    ) -> Result<Option<api_types::Indexer>> {
+        require_unscoped_admin(ctx).await?;
+
+        let ctx_data = ctx_data(ctx);
+        let indexer = ctx_data.store.set_indexer_tags(&address, &tags).await?;
+
+        Ok(indexer.map(Into::into))
+    }
+
+    /// Adds a subgraph deployment to the watchlist of deployments to
+    /// cross-check, starting from the next polling round. As long as the
+    /// watchlist is not empty, deployments that are not on it are ignored.
+    /// Requires the `admin` permission level.
+    async fn track_deployment(
+        &self,
+        ctx: &Context<'_>,
+        ipfs_cid: IpfsCid,
+        #[graphql(desc = "The name of the network that the deployment indexes")] network: String,
+    ) -> Result<TrackedDeployment> {
+        require_permission_level(ctx, ApiKeyPermissionLevel::Admin).await?;
+
+        let ctx_data = ctx_data(ctx);
+        let workspace_id = request_workspace_id(ctx).await?;
+        let deployment = ctx_data
+            .store
+            .track_deployment(&ipfs_cid, &network, workspace_id)
+            .await?;
+
+        Ok(deployment)
+    }
+
+    /// Removes a subgraph deployment from the watchlist of deployments to
+    /// cross-check. Returns `false` if it wasn't on it. Requires the `admin`
+    /// permission level.
+    async fn untrack_deployment(&self, ctx: &Context<'_>, ipfs_cid: IpfsCid) -> Result<bool> {
+        require_permission_level(ctx, ApiKeyPermissionLevel::Admin).await?;
+
+        let ctx_data = ctx_data(ctx);
+        let workspace_id = request_workspace_id(ctx).await?;
+        let removed = ctx_data
+            .store
+            .untrack_deployment(&ipfs_cid, workspace_id)
+            .await?;
+
+        Ok(removed)
+    }
+
+    /// Deletes all PoIs, failed queries and divergence investigation reports
+    /// that belong to the given indexer, e.g. because it sent garbage data or
+    /// requested removal. Requires the `admin` permission level.
+    async fn delete_indexer_data(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "The address of the indexer, encoded as a hex string with a '0x' prefix")]
+        address: IndexerAddress,
+        #[graphql(
+            default = true,
+            desc = "If true, only counts the rows that would be deleted without deleting anything."
+        )]
+        dry_run: bool,
+    ) -> Result<IndexerDataDeletion> {
+        require_unscoped_admin(ctx).await?;
+
+        let ctx_data = ctx_data(ctx);
+        let deletion = ctx_data
+            .store
+            .delete_indexer_data(&address, dry_run)
+            .await?;
+
+        Ok(deletion)
+    }
+
+    /// Looks for inconsistencies in the database, e.g. after a crash or
+    /// manual database surgery, and repairs them by deleting the inconsistent
+    /// rows. Requires the `admin` permission level.
+    async fn check_database_integrity(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(
+            default = true,
+            desc = "If true, only counts the inconsistent rows without repairing anything."
+        )]
+        dry_run: bool,
+    ) -> Result<IntegrityReport> {
+        require_unscoped_admin(ctx).await?;
+
+        let ctx_data = ctx_data(ctx);
+        let report = ctx_data.store.check_integrity(dry_run).await?;
+
+        Ok(report)
+    }
+
     /// Completely deletes a network and all related data (PoIs, indexers, subgraphs, etc.).
+    /// Requires the `admin` permission level and an API key that isn't scoped
+    /// to a workspace.
     async fn delete_network(&self, ctx: &Context<'_>, network: String) -> Result<String> {
+        require_unscoped_admin(ctx).await?;
+
         let ctx_data = ctx_data(ctx);
         ctx_data.store.delete_network(&network).await?;
 
         Ok(network)
     }
 }
+
+/// Applies `f` to the current configuration and stores the result as a new
+/// configuration version, which the daemon picks up on its next polling
+/// round. Returns the new configuration.
+async fn update_configuration(
+    ctx: &Context<'_>,
+    f: impl FnOnce(&mut Config),
+) -> Result<serde_json::Value> {
+    let store = &ctx_data(ctx).store;
+
+    let mut config: Config = match store.current_config().await? {
+        Some(json) => serde_json::from_value(json).context("invalid stored configuration")?,
+        None => Config::default(),
+    };
+    f(&mut config);
+
+    let json = serde_json::to_value(config)?;
+    store.overwrite_config(json.clone()).await?;
+
+    Ok(json)
+}
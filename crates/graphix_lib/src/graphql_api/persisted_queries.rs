@@ -0,0 +1,336 @@
+//! Automatic persisted queries (APQ) as implemented by Apollo clients. Clients
+//! send the SHA-256 hash of a query instead of the query itself, and only
+//! send the full query when the server doesn't know the hash yet. An
+//! allowlist of persisted queries can also restrict which operations
+//! requests without an API key may execute.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::Context as _;
+use axum::body::Bytes;
+use axum::http::StatusCode;
+use axum::Json;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// Upper limit on the number of queries registered at runtime, which bounds
+/// memory usage. Clients fall back to sending full queries beyond it.
+const MAX_REGISTERED_QUERIES: usize = 10_000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PersistedQueryError {
+    /// The client should retry with the full query.
+    NotFound,
+    HashMismatch,
+    /// The request has no API key and the query isn't allowlisted.
+    NotAllowed,
+}
+
+impl PersistedQueryError {
+    /// The response to send instead of executing the request. Errors are
+    /// shaped like GraphQL errors, which is what Apollo clients look for.
+    pub fn response(&self) -> (StatusCode, Json<Value>) {
+        let (status, message, code) = match self {
+            Self::NotFound => (
+                StatusCode::OK,
+                "PersistedQueryNotFound",
+                "PERSISTED_QUERY_NOT_FOUND",
+            ),
+            Self::HashMismatch => (
+                StatusCode::BAD_REQUEST,
+                "provided sha does not match query",
+                "BAD_REQUEST",
+            ),
+            Self::NotAllowed => (
+                StatusCode::FORBIDDEN,
+                "only persisted queries may be executed without an API key",
+                "PERSISTED_QUERY_NOT_ALLOWED",
+            ),
+        };
+
+        (
+            status,
+            Json(serde_json::json!({
+                "errors": [{ "message": message, "extensions": { "code": code } }],
+            })),
+        )
+    }
+}
+
+/// The queries that clients registered by their hash, and the allowlist, if
+/// any.
+#[derive(Default)]
+pub struct PersistedQueries {
+    registered: Mutex<HashMap<String, String>>,
+    allowlist: Option<HashMap<String, String>>,
+}
+
+impl PersistedQueries {
+    pub fn new(allowlist: Option<HashMap<String, String>>) -> Self {
+        Self {
+            registered: Mutex::new(HashMap::new()),
+            allowlist,
+        }
+    }
+
+    /// Reads an allowlist from a JSON file that maps the SHA-256 hashes of
+    /// queries, hex-encoded, to the queries.
+    pub fn load_allowlist(path: &Path) -> anyhow::Result<HashMap<String, String>> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let allowlist: HashMap<String, String> = serde_json::from_str(&contents)
+            .with_context(|| format!("invalid persisted queries in {}", path.display()))?;
+        for (hash, query) in &allowlist {
+            if &query_hash(query) != hash {
+                anyhow::bail!("persisted query {hash} doesn't match its SHA-256 hash");
+            }
+        }
+
+        Ok(allowlist)
+    }
+
+    /// Whether requests without an API key are restricted to the allowlist.
+    pub fn has_allowlist(&self) -> bool {
+        self.allowlist.is_some()
+    }
+
+    /// Replaces persisted query hashes in the raw request body with their
+    /// queries, registers new queries, and enforces the allowlist unless the
+    /// request is `authenticated`. Returns the body to execute.
+    ///
+    /// The allowlist only admits JSON requests, because the GraphQL server
+    /// also executes e.g. multipart requests, whose queries aren't checked.
+    pub fn resolve(
+        &self,
+        content_type: Option<&str>,
+        body: Bytes,
+        authenticated: bool,
+    ) -> Result<Bytes, PersistedQueryError> {
+        let enforce_allowlist = self.allowlist.is_some() && !authenticated;
+        if enforce_allowlist && !content_type.is_some_and(is_json) {
+            return Err(PersistedQueryError::NotAllowed);
+        }
+        // Most requests don't use persisted queries at all.
+        if !enforce_allowlist && !contains(&body, b"persistedQuery") {
+            return Ok(body);
+        }
+        let Ok(mut request) = serde_json::from_slice::<Value>(&body) else {
+            if enforce_allowlist {
+                return Err(PersistedQueryError::NotAllowed);
+            }
+            // Malformed requests are rejected by the GraphQL server.
+            return Ok(body);
+        };
+
+        match &mut request {
+            Value::Array(requests) => {
+                for request in requests {
+                    self.resolve_request(request, enforce_allowlist)?;
+                }
+            }
+            request => self.resolve_request(request, enforce_allowlist)?,
+        }
+
+        Ok(serde_json::to_vec(&request)
+            .expect("JSON values are serializable")
+            .into())
+    }
+
+    fn resolve_request(
+        &self,
+        request: &mut Value,
+        enforce_allowlist: bool,
+    ) -> Result<(), PersistedQueryError> {
+        let Some(request) = request.as_object_mut() else {
+            if enforce_allowlist {
+                return Err(PersistedQueryError::NotAllowed);
+            }
+            return Ok(());
+        };
+        let hash = request
+            .get("extensions")
+            .and_then(|extensions| extensions.get("persistedQuery"))
+            .and_then(|persisted_query| persisted_query.get("sha256Hash"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let query = request
+            .get("query")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        // Only queries sent together with their hash are registered.
+        let (hash, query, register) = match (hash, query) {
+            (Some(hash), Some(query)) => {
+                if query_hash(&query) != hash {
+                    return Err(PersistedQueryError::HashMismatch);
+                }
+                (hash, query, true)
+            }
+            (Some(hash), None) => {
+                let query = self.lookup(&hash).ok_or(PersistedQueryError::NotFound)?;
+                request.insert("query".to_string(), Value::String(query.clone()));
+                (hash, query, false)
+            }
+            (None, Some(query)) => (query_hash(&query), query, false),
+            (None, None) => return Ok(()),
+        };
+
+        let allowlisted = self
+            .allowlist
+            .as_ref()
+            .is_some_and(|allowlist| allowlist.contains_key(&hash));
+        if enforce_allowlist && !allowlisted {
+            return Err(PersistedQueryError::NotAllowed);
+        }
+        if register && !allowlisted {
+            let mut registered = self.registered.lock().unwrap();
+            if registered.len() < MAX_REGISTERED_QUERIES {
+                registered.insert(hash, query);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn lookup(&self, hash: &str) -> Option<String> {
+        self.allowlist
+            .as_ref()
+            .and_then(|allowlist| allowlist.get(hash).cloned())
+            .or_else(|| self.registered.lock().unwrap().get(hash).cloned())
+    }
+}
+
+/// The hex-encoded SHA-256 hash of a query, which identifies it as a
+/// persisted query.
+fn query_hash(query: &str) -> String {
+    hex::encode(Sha256::digest(query.as_bytes()))
+}
+
+/// Whether `content_type` is `application/json`, with any parameters.
+fn is_json(content_type: &str) -> bool {
+    content_type
+        .split(';')
+        .next()
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUERY: &str = "{ networks { name } }";
+    const JSON: Option<&str> = Some("application/json");
+
+    fn request(query: Option<&str>, hash: Option<&str>) -> Bytes {
+        let mut request = serde_json::json!({});
+        if let Some(query) = query {
+            request["query"] = query.into();
+        }
+        if let Some(hash) = hash {
+            request["extensions"] = serde_json::json!({
+                "persistedQuery": { "version": 1, "sha256Hash": hash },
+            });
+        }
+        serde_json::to_vec(&request).unwrap().into()
+    }
+
+    fn query(body: Bytes) -> String {
+        let request: Value = serde_json::from_slice(&body).unwrap();
+        request["query"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn hashes_are_resolved_once_registered() {
+        let queries = PersistedQueries::default();
+        let hash = query_hash(QUERY);
+
+        assert_eq!(
+            queries.resolve(JSON, request(None, Some(&hash)), false),
+            Err(PersistedQueryError::NotFound)
+        );
+        assert_eq!(
+            queries.resolve(
+                JSON,
+                request(Some("{ indexers { id } }"), Some(&hash)),
+                false
+            ),
+            Err(PersistedQueryError::HashMismatch)
+        );
+        queries
+            .resolve(JSON, request(Some(QUERY), Some(&hash)), false)
+            .unwrap();
+        let body = queries
+            .resolve(JSON, request(None, Some(&hash)), false)
+            .unwrap();
+        assert_eq!(query(body), QUERY);
+    }
+
+    #[test]
+    fn allowlist_only_applies_without_api_key() {
+        let hash = query_hash(QUERY);
+        let queries =
+            PersistedQueries::new(Some(HashMap::from([(hash.clone(), QUERY.to_string())])));
+
+        let body = queries
+            .resolve(JSON, request(None, Some(&hash)), false)
+            .unwrap();
+        assert_eq!(query(body), QUERY);
+        assert!(queries
+            .resolve(JSON, request(Some(QUERY), None), false)
+            .is_ok());
+
+        let other = "{ indexers { id } }";
+        assert_eq!(
+            queries.resolve(JSON, request(Some(other), None), false),
+            Err(PersistedQueryError::NotAllowed)
+        );
+        assert!(queries
+            .resolve(JSON, request(Some(other), None), true)
+            .is_ok());
+    }
+
+    #[test]
+    fn allowlist_rejects_non_json_requests() {
+        let hash = query_hash(QUERY);
+        let queries =
+            PersistedQueries::new(Some(HashMap::from([(hash.clone(), QUERY.to_string())])));
+        let multipart = Some("multipart/form-data; boundary=x");
+        let body = Bytes::from(
+            "--x\r\nContent-Disposition: form-data; name=\"operations\"\r\n\r\n\
+             {\"query\": \"{ indexers { id } }\"}\r\n--x--\r\n",
+        );
+
+        assert_eq!(
+            queries.resolve(multipart, body.clone(), false),
+            Err(PersistedQueryError::NotAllowed)
+        );
+        assert!(queries.resolve(multipart, body.clone(), true).is_ok());
+        assert_eq!(
+            queries.resolve(JSON, body, false),
+            Err(PersistedQueryError::NotAllowed)
+        );
+        assert_eq!(
+            queries.resolve(None, request(Some(QUERY), None), false),
+            Err(PersistedQueryError::NotAllowed)
+        );
+        assert_eq!(
+            queries.resolve(JSON, Bytes::from("[1]"), false),
+            Err(PersistedQueryError::NotAllowed)
+        );
+        assert!(queries
+            .resolve(
+                Some("application/json; charset=utf-8"),
+                request(Some(QUERY), None),
+                false
+            )
+            .is_ok());
+    }
+}
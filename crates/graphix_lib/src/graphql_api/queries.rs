@@ -1,16 +1,63 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use anyhow::Context as _;
 use async_graphql::{Context, Object, Result};
+use base64::Engine as _;
 use futures::future::try_join_all;
 use graphix_common_types::*;
-use graphix_store::models::ApiKeyPublicMetadata;
+use graphix_store::models::{
+    self, ApiKeyPublicMetadata, ApiKeyUsage, BigIntId, IntId, Poi, TrackedDeployment, Workspace,
+};
 use uuid::Uuid;
 
-use super::{api_types, ctx_data, require_permission_level};
+use super::{
+    api_types, ctx_data, request_workspace_id, require_permission_level, require_unscoped_admin,
+};
+use crate::config::effective_config_json;
+use crate::dispute_evidence::evidence_bundle;
+use crate::fork_tree::{fork_tree, poi_clusters, PoiCluster};
+use crate::sla::average_agreement_ratio;
+use crate::uptime::uptime_ratio;
 
 pub struct QueryRoot;
 
+#[derive(Debug, Default)]
+struct NetworkDivergences {
+    deployments: BTreeSet<IntId>,
+    diverging_deployments: BTreeSet<IntId>,
+    affected_indexers: BTreeSet<IntId>,
+    newest_divergence_at: Option<chrono::NaiveDateTime>,
+}
+
+/// Tallies the divergences among live PoIs, grouped by deployment and block,
+/// by the ID of the network that their deployment is stored on.
+fn divergences_by_network(
+    pois_by_deployment_and_block: &BTreeMap<(IntId, BigIntId), Vec<Poi>>,
+    deployments: &HashMap<IntId, models::SgDeployment>,
+) -> anyhow::Result<HashMap<IntId, NetworkDivergences>> {
+    let mut divergences_by_network: HashMap<IntId, NetworkDivergences> = HashMap::new();
+    for ((deployment_id, _), pois) in pois_by_deployment_and_block {
+        let network_id = deployments
+            .get(deployment_id)
+            .context("inconsistent pois table, no deployment for poi")?
+            .network_id;
+        let divergences = divergences_by_network.entry(network_id).or_default();
+        divergences.deployments.insert(*deployment_id);
+        if poi_clusters(pois).len() < 2 {
+            continue;
+        }
+
+        divergences.diverging_deployments.insert(*deployment_id);
+        divergences
+            .affected_indexers
+            .extend(pois.iter().map(|poi| poi.indexer_id));
+        let newest = pois.iter().map(|poi| poi.created_at).max();
+        divergences.newest_divergence_at = divergences.newest_divergence_at.max(newest);
+    }
+
+    Ok(divergences_by_network)
+}
+
 #[Object]
 impl QueryRoot {
     /// Returns the version of the Graphix instance.
@@ -20,6 +67,8 @@ impl QueryRoot {
 
     /// Fetches all tracked subgraph deploymens in this Graphix instance and
     /// filters them according to some filtering rules.
+    // Each argument is a GraphQL argument.
+    #[allow(clippy::too_many_arguments)]
     async fn deployments(
         &self,
         ctx: &Context<'_>,
@@ -27,6 +76,9 @@ impl QueryRoot {
         network_name: Option<String>,
         name: Option<String>,
         ipfs_cid: Option<IpfsCid>,
+        #[graphql(default, desc = "The field by which to sort the results.")]
+        order_by: inputs::SgDeploymentsOrderBy,
+        #[graphql(default)] order_direction: inputs::OrderDirection,
         #[graphql(
             default = 100,
             validator(maximum = 250),
@@ -40,6 +92,8 @@ impl QueryRoot {
             network_name,
             name,
             ipfs_cid,
+            order_by,
+            order_direction,
             limit: Some(limit),
         };
         let deployments = ctx_data.store.sg_deployments(filter).await?;
@@ -54,26 +108,123 @@ impl QueryRoot {
         ctx: &Context<'_>,
         #[graphql(desc = "The address of the indexer, encoded as a hex string with a '0x' prefix")]
         address: Option<IndexerAddress>,
+        #[graphql(default, desc = "The field by which to sort the results.")]
+        order_by: inputs::IndexersOrderBy,
+        #[graphql(default)] order_direction: inputs::OrderDirection,
         #[graphql(
             default = 100,
             validator(maximum = 250),
             desc = "Upper limit on the number of shown results."
         )]
         limit: u16,
+        #[graphql(
+            default = false,
+            desc = "Whether to include archived indexers. Indexers that are looked up by address are always included."
+        )]
+        include_archived: bool,
+        #[graphql(
+            default,
+            desc = "Restricts the query to indexers with any of these tags."
+        )]
+        tags: Vec<String>,
     ) -> Result<Vec<api_types::Indexer>> {
         let ctx_data = ctx_data(ctx);
 
         let filter = inputs::IndexersQuery {
             address,
+            order_by,
+            order_direction,
             limit: Some(limit),
+            include_archived,
+            tags,
         };
         let indexers = ctx_data.store.indexers(filter).await?;
 
         Ok(indexers.into_iter().map(Into::into).collect())
     }
 
+    /// Searches subgraph deployments by IPFS CID and name, and indexers by
+    /// name and address. Prefix matches come first, followed by names that are
+    /// similar to the search term.
+    async fn search(
+        &self,
+        ctx: &Context<'_>,
+        term: String,
+        #[graphql(
+            default = 20,
+            validator(maximum = 100),
+            desc = "Upper limit on the number of deployments and indexers each."
+        )]
+        limit: u16,
+    ) -> Result<api_types::SearchResults> {
+        let ctx_data = ctx_data(ctx);
+
+        let term = term.trim();
+        if term.is_empty() {
+            return Ok(api_types::SearchResults {
+                deployments: vec![],
+                indexers: vec![],
+            });
+        }
+
+        let deployments = ctx_data.store.search_sg_deployments(term, limit).await?;
+        let indexers = ctx_data.store.search_indexers(term, limit).await?;
+
+        Ok(api_types::SearchResults {
+            deployments: deployments.into_iter().map(Into::into).collect(),
+            indexers: indexers.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    /// Returns the watchlist of subgraph deployments to cross-check. If empty,
+    /// all deployments are cross-checked. API keys scoped to a workspace only
+    /// see the watchlist of their workspace, others see those of all
+    /// workspaces.
+    async fn tracked_deployments(&self, ctx: &Context<'_>) -> Result<Vec<TrackedDeployment>> {
+        let ctx_data = ctx_data(ctx);
+        let workspace_id = request_workspace_id(ctx).await?;
+        let mut deployments = ctx_data.store.tracked_deployments().await?;
+        if workspace_id.is_some() {
+            deployments.retain(|deployment| deployment.workspace_id == workspace_id);
+        }
+
+        Ok(deployments)
+    }
+
+    /// Returns all indexers that were added at runtime with the `addIndexer`
+    /// mutation. API keys scoped to a workspace only see the indexers of
+    /// their workspace. Requires the `admin` permission level.
+    async fn registered_indexers(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<api_types::RegisteredIndexer>> {
+        require_permission_level(ctx, ApiKeyPermissionLevel::Admin).await?;
+
+        let ctx_data = ctx_data(ctx);
+        let workspace_id = request_workspace_id(ctx).await?;
+        let mut indexers = ctx_data.store.registered_indexers().await?;
+        if workspace_id.is_some() {
+            indexers.retain(|indexer| indexer.workspace_id == workspace_id);
+        }
+
+        Ok(indexers.into_iter().map(Into::into).collect())
+    }
+
+    /// Returns all workspaces. Requires the `admin` permission level and an
+    /// API key that isn't scoped to a workspace.
+    async fn workspaces(&self, ctx: &Context<'_>) -> Result<Vec<Workspace>> {
+        require_unscoped_admin(ctx).await?;
+
+        let ctx_data = ctx_data(ctx);
+        let workspaces = ctx_data.store.workspaces().await?;
+
+        Ok(workspaces)
+    }
+
     /// Filters through all PoIs ever collected by this Graphix
     /// instance, according to some filtering rules specified in `filter`.
+    // Each argument is a GraphQL argument.
+    #[allow(clippy::too_many_arguments)]
     async fn proofs_of_indexing(
         &self,
         ctx: &Context<'_>,
@@ -90,6 +241,15 @@ impl QueryRoot {
             desc = "Restricts the query to PoIs that were collected in the given block range."
         )]
         block_range: Option<inputs::BlockRange>,
+        #[graphql(
+            default,
+            desc = "Restricts the query to PoIs of indexers with any of these tags."
+        )]
+        indexer_tags: Vec<String>,
+        #[graphql(default, desc = "The field by which to sort the results.")]
+        order_by: inputs::PoisOrderBy,
+        #[graphql(desc = "Defaults to descending, i.e. the most recent block first.")]
+        order_direction: Option<inputs::OrderDirection>,
         #[graphql(
             default = 100,
             validator(maximum = 250),
@@ -103,22 +263,33 @@ impl QueryRoot {
             network,
             deployments,
             block_range,
+            indexer_tags,
             limit: Some(limit),
         };
         let pois = ctx_data
             .store
-            .pois(&filter.deployments, filter.block_range, filter.limit)
+            .pois(
+                &filter.deployments,
+                &filter.indexer_tags,
+                filter.block_range,
+                filter.limit,
+                inputs::PoisOrder {
+                    by: order_by,
+                    direction: order_direction.unwrap_or(inputs::OrderDirection::Desc),
+                },
+            )
             .await?;
 
         Ok(pois.into_iter().map(Into::into).collect())
     }
 
-    /// A copy of the configuration file used to run Graphix.
+    /// A copy of the configuration used to run Graphix, including the
+    /// fields set through `GRAPHIX_*` environment variables.
     async fn configuration(&self, ctx: &Context<'_>) -> Result<Option<serde_json::Value>> {
-        require_permission_level(ctx, ApiKeyPermissionLevel::Admin).await?;
+        require_unscoped_admin(ctx).await?;
 
         let ctx_data = ctx_data(ctx);
-        let config = ctx_data.store.current_config().await?;
+        let config = effective_config_json(ctx_data.store.current_config().await?)?;
 
         Ok(config)
     }
@@ -136,6 +307,7 @@ impl QueryRoot {
             .store
             .live_pois(
                 None,
+                &filter.indexer_tags,
                 Some(&filter.deployments),
                 filter.block_range,
                 filter.limit,
@@ -145,13 +317,30 @@ impl QueryRoot {
         Ok(pois.into_iter().map(Into::into).collect())
     }
 
+    /// Returns all API keys. API keys scoped to a workspace only see the API
+    /// keys of their workspace.
     async fn api_keys(&self, ctx: &Context<'_>) -> Result<Vec<ApiKeyPublicMetadata>> {
         let ctx_data = ctx_data(ctx);
-        let api_keys = ctx_data.store.api_keys().await?;
+        let workspace_id = request_workspace_id(ctx).await?;
+        let mut api_keys = ctx_data.store.api_keys().await?;
+        if workspace_id.is_some() {
+            api_keys.retain(|api_key| api_key.workspace_id == workspace_id);
+        }
 
         Ok(api_keys)
     }
 
+    /// Returns how much each API key was used to query the GraphQL API, most
+    /// used first. Requires the `admin` permission level.
+    async fn api_key_usage(&self, ctx: &Context<'_>) -> Result<Vec<ApiKeyUsage>> {
+        require_unscoped_admin(ctx).await?;
+
+        let ctx_data = ctx_data(ctx);
+        let usage = ctx_data.store.api_key_usage().await?;
+
+        Ok(usage)
+    }
+
     async fn poi_agreement_ratios(
         &self,
         ctx: &Context<'_>,
@@ -159,81 +348,328 @@ impl QueryRoot {
     ) -> Result<Vec<api_types::PoiAgreementRatio>> {
         let ctx_data = ctx_data(ctx);
 
-        // Query live POIs of a the requested indexer.
-        let indexer_pois = live_pois(ctx, indexer_address).await?;
+        // Aggregating in the database keeps this fast for deployments with
+        // hundreds of indexers.
+        let counts = ctx_data
+            .store
+            .poi_agreement_counts(&indexer_address)
+            .await?;
 
-        let deployments =
-            try_join_all(indexer_pois.iter().map(|poi| poi.deployment(ctx_data))).await?;
+        Ok(counts
+            .into_iter()
+            .map(|counts| api_types::PoiAgreementRatio {
+                poi_id: counts.poi_id,
+                total_indexers: counts.total_indexers as u32,
+                n_agreeing_indexers: counts.agreeing_indexers as u32,
+                n_disagreeing_indexers: (counts.total_indexers - counts.agreeing_indexers) as u32,
+                has_consensus: counts.has_consensus,
+                in_consensus: counts.in_consensus,
+            })
+            .collect())
+    }
 
-        let deployment_cids: Vec<IpfsCid> = deployments.iter().map(|d| d.cid().clone()).collect();
+    /// Groups all current divergences (i.e. live PoIs that disagree for the
+    /// same deployment and block) by the `graph-node` versions of the two
+    /// disagreeing indexers. Each pair of versions appears only once, with
+    /// `versionA` sorting before `versionB`.
+    async fn version_divergence_matrix(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<api_types::VersionDivergence>> {
+        let ctx_data = ctx_data(ctx);
 
-        // Query all live POIs for the specific deployments.
-        let all_deployment_pois = ctx_data
+        let indexers = ctx_data
             .store
-            .live_pois(None, Some(&deployment_cids), None, None)
+            .indexers(inputs::IndexersQuery::default())
+            .await?;
+        let versions = ctx_data
+            .loader_graph_node_collected_version
+            .load_many(indexers.iter().filter_map(|i| i.graph_node_version))
             .await?;
+        let indexer_versions: HashMap<IntId, Option<String>> = indexers
+            .iter()
+            .map(|indexer| {
+                let version = indexer
+                    .graph_node_version
+                    .and_then(|id| versions.get(&id))
+                    .and_then(|version| version.version_string.clone());
+                (indexer.id, version)
+            })
+            .collect();
 
-        // Convert POIs to ProofOfIndexing and group by deployment
-        let mut deployment_to_pois: BTreeMap<String, Vec<api_types::ProofOfIndexing>> =
-            BTreeMap::new();
-        for poi in all_deployment_pois {
-            let proof_of_indexing: api_types::ProofOfIndexing = poi.into();
-            deployment_to_pois
-                .entry(
-                    proof_of_indexing
-                        .deployment(ctx_data)
-                        .await?
-                        .cid()
-                        .to_string(),
-                )
+        let mut pois_by_deployment_and_block: BTreeMap<_, Vec<Poi>> = BTreeMap::new();
+        for poi in ctx_data
+            .store
+            .live_pois(None, &[], None, None, None)
+            .await?
+        {
+            pois_by_deployment_and_block
+                .entry((poi.sg_deployment_id, poi.block_id))
                 .or_default()
-                .push(proof_of_indexing);
+                .push(poi);
         }
 
-        let mut agreement_ratios: Vec<api_types::PoiAgreementRatio> = Vec::new();
+        let mut matrix: BTreeMap<_, (u32, BTreeSet<IntId>)> = BTreeMap::new();
+        for pois in pois_by_deployment_and_block.values() {
+            for (i, poi1) in pois.iter().enumerate() {
+                for poi2 in pois[i + 1..].iter().filter(|poi2| poi2.poi != poi1.poi) {
+                    let version1 = indexer_versions.get(&poi1.indexer_id).cloned().flatten();
+                    let version2 = indexer_versions.get(&poi2.indexer_id).cloned().flatten();
+                    let key = if version1 <= version2 {
+                        (version1, version2)
+                    } else {
+                        (version2, version1)
+                    };
 
-        for poi in indexer_pois {
-            let deployment_pois = deployment_to_pois
-                .get(&poi.deployment(ctx_data).await?.cid().to_string())
-                .context("inconsistent pois table, no pois for deployment")?;
+                    let (diverging_pairs, deployment_ids) = matrix.entry(key).or_default();
+                    *diverging_pairs += 1;
+                    deployment_ids.insert(poi1.sg_deployment_id);
+                }
+            }
+        }
 
-            let total_indexers = deployment_pois.len() as u32;
+        let deployments = ctx_data
+            .loader_subgraph_deployment
+            .load_many(matrix.values().flat_map(|(_, ids)| ids.iter().copied()))
+            .await?;
 
-            // Calculate POI agreement by creating a map to count unique POIs and their occurrence.
-            let mut poi_counts: BTreeMap<PoiBytes, u32> = BTreeMap::new();
-            for dp in deployment_pois {
-                *poi_counts.entry(dp.hash()).or_insert(0) += 1;
-            }
+        Ok(matrix
+            .into_iter()
+            .map(
+                |((version_a, version_b), (diverging_pairs, deployment_ids))| {
+                    api_types::VersionDivergence {
+                        version_a,
+                        version_b,
+                        diverging_pairs,
+                        deployments: deployment_ids
+                            .iter()
+                            .filter_map(|id| deployments.get(id).cloned())
+                            .map(Into::into)
+                            .collect(),
+                    }
+                },
+            )
+            .collect())
+    }
 
-            // Define consensus and agreement based on the map.
-            let (max_poi, max_poi_count) = poi_counts
-                .iter()
-                .max_by_key(|(_, &v)| v)
-                .context("inconsistent pois table, no pois")?;
+    /// Summarizes the current divergences (i.e. live PoIs that disagree for
+    /// the same deployment and block) of every network.
+    async fn divergence_summary(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<api_types::DivergenceSummary>> {
+        let ctx_data = ctx_data(ctx);
 
-            let has_consensus = *max_poi_count > total_indexers / 2;
+        let mut pois_by_deployment_and_block: BTreeMap<_, Vec<Poi>> = BTreeMap::new();
+        for poi in ctx_data
+            .store
+            .live_pois(None, &[], None, None, None)
+            .await?
+        {
+            pois_by_deployment_and_block
+                .entry((poi.sg_deployment_id, poi.block_id))
+                .or_default()
+                .push(poi);
+        }
+        let deployments = ctx_data
+            .loader_subgraph_deployment
+            .load_many(
+                pois_by_deployment_and_block
+                    .keys()
+                    .map(|(deployment_id, _)| *deployment_id),
+            )
+            .await?;
+        let mut divergences_by_network =
+            divergences_by_network(&pois_by_deployment_and_block, &deployments)?;
 
-            let n_agreeing_indexers = *poi_counts
-                .get(&poi.hash())
-                .context("inconsistent pois table, no matching poi")?;
+        let mut summaries: Vec<_> = ctx_data
+            .store
+            .networks()
+            .await?
+            .into_iter()
+            .map(|network| {
+                let divergences = divergences_by_network
+                    .remove(&network.id)
+                    .unwrap_or_default();
+                api_types::DivergenceSummary {
+                    network: network.name,
+                    deployments_checked: divergences.deployments.len() as u32,
+                    diverging_deployments: divergences.diverging_deployments.len() as u32,
+                    affected_indexers: divergences.affected_indexers.len() as u32,
+                    newest_divergence_at: divergences.newest_divergence_at,
+                }
+            })
+            .collect();
+        summaries.sort_by(|a, b| a.network.cmp(&b.network));
 
-            let n_disagreeing_indexers = total_indexers - n_agreeing_indexers;
+        Ok(summaries)
+    }
 
-            let in_consensus = has_consensus && max_poi == &poi.hash();
+    /// Aggregate statistics about the network with the given name, or null if
+    /// there's no such network.
+    async fn network_stats(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+    ) -> Result<Option<api_types::NetworkStats>> {
+        let ctx_data = ctx_data(ctx);
+        let workspace_id = request_workspace_id(ctx).await?;
 
-            let ratio = api_types::PoiAgreementRatio {
-                poi_id: poi.model.id,
-                total_indexers,
-                n_agreeing_indexers,
-                n_disagreeing_indexers,
-                has_consensus,
-                in_consensus,
-            };
+        let stats = ctx_data.store.network_stats(&name, workspace_id).await?;
+        Ok(stats.map(|stats| api_types::NetworkStats::new(name, stats)))
+    }
 
-            agreement_ratios.push(ratio);
+    /// How well indexers agreed on the PoIs of a subgraph deployment, per day,
+    /// oldest first. The current day is updated hourly.
+    async fn deployment_agreement_trend(
+        &self,
+        ctx: &Context<'_>,
+        deployment: IpfsCid,
+        #[graphql(
+            default = 30,
+            validator(maximum = 366),
+            desc = "The number of days to include, counting today."
+        )]
+        days: u32,
+    ) -> Result<Vec<models::DailyDeploymentAgreement>> {
+        let ctx_data = ctx_data(ctx);
+
+        Ok(ctx_data
+            .store
+            .daily_deployment_agreement(&deployment, days)
+            .await?)
+    }
+
+    /// How well the PoIs of an indexer agreed with those of other indexers,
+    /// per day, oldest first. The current day is updated hourly.
+    async fn indexer_agreement_trend(
+        &self,
+        ctx: &Context<'_>,
+        indexer_address: IndexerAddress,
+        #[graphql(
+            default = 30,
+            validator(maximum = 366),
+            desc = "The number of days to include, counting today."
+        )]
+        days: u32,
+    ) -> Result<Vec<models::DailyIndexerAgreement>> {
+        let ctx_data = ctx_data(ctx);
+
+        Ok(ctx_data
+            .store
+            .daily_indexer_agreement(&indexer_address, days)
+            .await?)
+    }
+
+    /// Groups the indexers that currently disagree on a subgraph deployment
+    /// (i.e. whose live PoIs for the same block differ) by the PoI they
+    /// report, e.g. "7 indexers report X, 2 report Y". Unlike pairwise
+    /// comparisons, this scales linearly with the number of indexers.
+    async fn poi_clusters(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(
+            default,
+            desc = "Restricts the query to these subgraph deployments. All deployments are included if empty."
+        )]
+        deployments: Vec<IpfsCid>,
+    ) -> Result<Vec<api_types::DivergenceClusters>> {
+        let ctx_data = ctx_data(ctx);
+
+        let deployments = (!deployments.is_empty()).then_some(deployments.as_slice());
+        let mut pois_by_deployment_and_block: BTreeMap<_, Vec<Poi>> = BTreeMap::new();
+        for poi in ctx_data
+            .store
+            .live_pois(None, &[], deployments, None, None)
+            .await?
+        {
+            pois_by_deployment_and_block
+                .entry((poi.sg_deployment_id, poi.block_id))
+                .or_default()
+                .push(poi);
         }
 
-        Ok(agreement_ratios)
+        let diverging: Vec<_> = pois_by_deployment_and_block
+            .into_iter()
+            .map(|(key, pois)| (key, poi_clusters(&pois)))
+            .filter(|(_, clusters)| clusters.len() >= 2)
+            .collect();
+        // Loading everything up front, and the clusters concurrently, keeps
+        // the number of queries constant.
+        let deployments = ctx_data
+            .loader_subgraph_deployment
+            .load_many(
+                diverging
+                    .iter()
+                    .map(|((deployment_id, _), _)| *deployment_id),
+            )
+            .await?;
+        let blocks = ctx_data
+            .loader_block
+            .load_many(diverging.iter().map(|((_, block_id), _)| *block_id))
+            .await?;
+
+        try_join_all(
+            diverging
+                .into_iter()
+                .map(|((deployment_id, block_id), clusters)| {
+                    let deployment = deployments
+                        .get(&deployment_id)
+                        .cloned()
+                        .context("inconsistent pois table, no deployment for poi");
+                    let block = blocks
+                        .get(&block_id)
+                        .cloned()
+                        .context("inconsistent pois table, no block for poi");
+                    async move {
+                        Ok::<_, async_graphql::Error>(api_types::DivergenceClusters {
+                            deployment: deployment?.into(),
+                            block: block?.into(),
+                            clusters: load_poi_clusters(ctx, clusters).await?,
+                        })
+                    }
+                }),
+        )
+        .await
+    }
+
+    /// Returns the history of PoI clusters of a subgraph deployment, i.e. the
+    /// indexers grouped by the PoI they reported, at every block for which
+    /// Graphix collected PoIs, oldest first. This shows when the indexers split
+    /// into disagreeing groups and whether the splits healed.
+    async fn poi_fork_tree(
+        &self,
+        ctx: &Context<'_>,
+        deployment: IpfsCid,
+        block_range: Option<inputs::BlockRange>,
+    ) -> Result<Vec<api_types::PoiForkTreeBlock>> {
+        let ctx_data = ctx_data(ctx);
+
+        let mut pois_by_block: Vec<(models::Block, Vec<Poi>)> = vec![];
+        for (poi, block) in ctx_data
+            .store
+            .deployment_poi_history(&deployment, block_range)
+            .await?
+        {
+            match pois_by_block.last_mut() {
+                Some((last_block, pois)) if last_block.number == block.number => pois.push(poi),
+                _ => pois_by_block.push((block, vec![poi])),
+            }
+        }
+
+        // Concurrent loads are batched into a single query.
+        try_join_all(
+            fork_tree(pois_by_block)
+                .into_iter()
+                .map(|fork_tree_block| async move {
+                    Ok::<_, async_graphql::Error>(api_types::PoiForkTreeBlock {
+                        block: fork_tree_block.block.into(),
+                        clusters: load_poi_clusters(ctx, fork_tree_block.clusters).await?,
+                        change: fork_tree_block.change,
+                    })
+                }),
+        )
+        .await
     }
 
     async fn divergence_investigation_report(
@@ -247,7 +683,7 @@ impl QueryRoot {
         let ctx_data = ctx_data(ctx);
 
         if let Some(report_json) = ctx_data
-            .store
+            .reports
             .divergence_investigation_report(&uuid)
             .await?
         {
@@ -256,7 +692,7 @@ impl QueryRoot {
                     .expect("Can't deserialize report from database"),
             )
         } else if ctx_data
-            .store
+            .reports
             .divergence_investigation_request_exists(&uuid)
             .await?
         {
@@ -264,13 +700,411 @@ impl QueryRoot {
                 uuid,
                 status: DivergenceInvestigationStatus::InProgress,
                 bisection_runs: vec![],
+                cohort_bisection: None,
                 error: None,
+                progress: None,
+                signature: None,
             }))
         } else {
             Ok(None)
         }
     }
 
+    /// The hex-encoded Ed25519 public key that the signatures of divergence
+    /// investigation reports verify against, or `null` if reports aren't
+    /// signed.
+    async fn report_signing_public_key(&self, ctx: &Context<'_>) -> Result<Option<String>> {
+        let signer = ctx_data(ctx).config().report_signer()?;
+
+        Ok(signer.map(|signer| signer.public_key()))
+    }
+
+    /// Lists past divergence investigation reports, most recent first.
+    /// Investigations that haven't started yet have no report and aren't
+    /// listed.
+    #[allow(clippy::too_many_arguments)]
+    async fn divergence_investigation_reports(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(
+            desc = "The subgraph deployment that was bisected. Cohort bisections are never matched."
+        )]
+        deployment: Option<IpfsCid>,
+        #[graphql(
+            desc = "One of the indexers whose PoIs were compared, by address. Cohort bisections are never matched."
+        )]
+        indexer: Option<IndexerAddress>,
+        status: Option<DivergenceInvestigationStatus>,
+        #[graphql(desc = "Only investigations that started at or after this time, in UTC.")]
+        created_after: Option<chrono::NaiveDateTime>,
+        #[graphql(desc = "Only investigations that started at or before this time, in UTC.")]
+        created_before: Option<chrono::NaiveDateTime>,
+        #[graphql(
+            default = 100,
+            validator(maximum = 250),
+            desc = "Upper limit on the number of shown results."
+        )]
+        limit: u16,
+    ) -> Result<Vec<DivergenceInvestigationReport>> {
+        let ctx_data = ctx_data(ctx);
+
+        let filter = inputs::DivergenceInvestigationsQuery {
+            deployment,
+            indexer,
+            status,
+            created_after,
+            created_before,
+            limit: Some(limit),
+        };
+        let reports = ctx_data
+            .reports
+            .divergence_investigation_reports(filter)
+            .await?
+            .into_iter()
+            .map(|report_json| {
+                serde_json::from_value(report_json)
+                    .context("invalid stored divergence investigation report")
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(reports)
+    }
+
+    /// Filters through the bisection runs of all divergence investigations,
+    /// e.g. to find all investigations that involved a given indexer.
+    async fn bisection_runs(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "The subgraph deployment that was bisected.")] deployment: Option<IpfsCid>,
+        #[graphql(
+            desc = "One of the two indexers whose PoIs were compared, by address."
+        )]
+        indexer: Option<IndexerAddress>,
+        #[graphql(
+            desc = "Restricts the query to bisection runs whose divergence block bounds contain this block number."
+        )]
+        block_number: Option<u64>,
+        #[graphql(
+            default = 100,
+            validator(maximum = 250),
+            desc = "Upper limit on the number of shown results."
+        )]
+        limit: u16,
+    ) -> Result<Vec<api_types::BisectionRun>> {
+        let ctx_data = ctx_data(ctx);
+
+        let filter = inputs::BisectionRunsQuery {
+            deployment,
+            indexer,
+            block_number,
+            limit: Some(limit),
+        };
+        let runs = ctx_data.reports.bisection_runs(filter).await?;
+
+        Ok(runs.into_iter().map(Into::into).collect())
+    }
+
+    /// Returns the raw response bodies that indexers returned during a
+    /// divergence investigation, in the order they were received. They're
+    /// only captured for investigations launched with `captureRawResponses`,
+    /// or if the `captureRawIndexerResponses` configuration option is set.
+    async fn raw_indexer_responses(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "The UUID of the divergence investigation.")] investigation_uuid: Uuid,
+    ) -> Result<Vec<api_types::RawIndexerResponse>> {
+        let ctx_data = ctx_data(ctx);
+
+        let responses = ctx_data
+            .reports
+            .raw_indexer_responses(&investigation_uuid)
+            .await?;
+
+        Ok(responses.into_iter().map(Into::into).collect())
+    }
+
+    /// Packages everything that an arbitrator needs to judge the divergence
+    /// that a bisection run confirmed, i.e. both PoIs, the diverging block,
+    /// the bisection trace, the collected evidence and its diffs, and the
+    /// `graph-node` versions of both indexers, into a signed tar archive.
+    /// Returns the archive, base64-encoded. Requires the `admin` permission
+    /// level and a configured signing key.
+    async fn dispute_evidence_bundle(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "The UUID of the bisection run.")] bisection_run_uuid: Uuid,
+    ) -> Result<String> {
+        require_unscoped_admin(ctx).await?;
+
+        let ctx_data = ctx_data(ctx);
+        let signer = ctx_data
+            .evidence_signer
+            .as_ref()
+            .context("no signing key is configured for dispute evidence bundles")?;
+        let bundle = evidence_bundle(
+            &ctx_data.store,
+            ctx_data.reports.as_ref(),
+            &bisection_run_uuid,
+            signer,
+        )
+        .await?;
+
+        Ok(base64::engine::general_purpose::STANDARD.encode(bundle))
+    }
+
+    /// Lists the divergence investigation requests that are waiting for,
+    /// or being handled by, an investigation worker, oldest first.
+    /// Dead-lettered jobs are only listed by
+    /// `deadLetteredDivergenceInvestigationJobs`.
+    async fn divergence_investigation_jobs(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "Only jobs with this status.")] status: Option<
+            DivergenceInvestigationJobStatus,
+        >,
+    ) -> Result<Vec<api_types::DivergenceInvestigationJob>> {
+        let ctx_data = ctx_data(ctx);
+
+        let mut jobs = ctx_data
+            .reports
+            .divergence_investigation_jobs(status)
+            .await?;
+        jobs.retain(|job| job.status != DivergenceInvestigationJobStatus::DeadLettered);
+
+        Ok(jobs.into_iter().map(Into::into).collect())
+    }
+
+    /// Lists the dead-letter queue, i.e. the divergence investigation jobs
+    /// that failed too many times, oldest first. They can be retried with the
+    /// `requeueDeadLetteredDivergenceInvestigation` mutation. Requires the
+    /// `admin` permission level.
+    async fn dead_lettered_divergence_investigation_jobs(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<api_types::DivergenceInvestigationJob>> {
+        require_permission_level(ctx, ApiKeyPermissionLevel::Admin).await?;
+
+        let ctx_data = ctx_data(ctx);
+        let jobs = ctx_data
+            .reports
+            .divergence_investigation_jobs(Some(DivergenceInvestigationJobStatus::DeadLettered))
+            .await?;
+
+        Ok(jobs.into_iter().map(Into::into).collect())
+    }
+
+    /// Returns PoIs that indexers submitted on chain when closing allocations,
+    /// most recently closed first. By default, only those that disagree with
+    /// the PoIs Graphix collected from the same indexers are returned.
+    async fn allocation_pois(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "The subgraph deployment of the allocation.")] deployment: Option<IpfsCid>,
+        #[graphql(desc = "The address of the indexer that closed the allocation.")] indexer: Option<
+            IndexerAddress,
+        >,
+        #[graphql(
+            default = true,
+            desc = "Only return PoIs that differ from the ones Graphix collected."
+        )]
+        only_mismatches: bool,
+        #[graphql(
+            default = 100,
+            validator(maximum = 250),
+            desc = "Upper limit on the number of shown results."
+        )]
+        limit: u16,
+    ) -> Result<Vec<api_types::AllocationPoi>> {
+        let ctx_data = ctx_data(ctx);
+
+        let filter = inputs::AllocationPoisQuery {
+            deployment,
+            indexer,
+            only_mismatches,
+            limit: Some(limit),
+        };
+        let allocation_pois = ctx_data.store.allocation_pois(filter).await?;
+
+        Ok(allocation_pois.into_iter().map(Into::into).collect())
+    }
+
+    /// Returns indexing disputes in the `DisputeManager` contract about PoIs
+    /// of tracked indexers, most recently opened first, with whether the PoIs
+    /// Graphix collected from other indexers support or contradict them.
+    async fn disputes(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "The subgraph deployment of the disputed PoI.")] deployment: Option<
+            IpfsCid,
+        >,
+        #[graphql(desc = "The address of the disputed indexer.")] indexer: Option<IndexerAddress>,
+        #[graphql(
+            default = false,
+            desc = "Only return disputes that weren't resolved yet."
+        )]
+        only_undecided: bool,
+        #[graphql(
+            default = 100,
+            validator(maximum = 250),
+            desc = "Upper limit on the number of shown results."
+        )]
+        limit: u16,
+    ) -> Result<Vec<api_types::PoiDispute>> {
+        let ctx_data = ctx_data(ctx);
+
+        let filter = inputs::PoiDisputesQuery {
+            deployment,
+            indexer,
+            only_undecided,
+            limit: Some(limit),
+        };
+        let disputes = ctx_data.store.poi_disputes(filter).await?;
+
+        Ok(disputes.into_iter().map(Into::into).collect())
+    }
+
+    /// Returns active allocations that indexers' `indexer-agent`s report, but
+    /// that the indexers either don't index the deployment of or serve no
+    /// PoIs for, most recently created first. Only indexers with a configured
+    /// `managementEndpoint` are covered.
+    async fn allocation_discrepancies(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "The address of the allocating indexer.")] indexer: Option<IndexerAddress>,
+        #[graphql(
+            default = 100,
+            validator(maximum = 250),
+            desc = "Upper limit on the number of shown results."
+        )]
+        limit: u16,
+    ) -> Result<Vec<api_types::AllocationDiscrepancy>> {
+        let ctx_data = ctx_data(ctx);
+
+        let filter = inputs::AllocationDiscrepanciesQuery {
+            indexer,
+            limit: Some(limit),
+        };
+        let discrepancies = ctx_data.store.allocation_discrepancies(filter).await?;
+
+        Ok(discrepancies.into_iter().map(Into::into).collect())
+    }
+
+    /// Returns blocks of subgraph deployments whose hash indexers disagree
+    /// on, most recently detected first. The PoIs of these blocks are left
+    /// out of PoI divergence detection.
+    async fn chain_disagreements(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "The subgraph deployment of the disagreement.")] deployment: Option<
+            IpfsCid,
+        >,
+        #[graphql(
+            default = 100,
+            validator(maximum = 250),
+            desc = "Upper limit on the number of shown results."
+        )]
+        limit: u16,
+    ) -> Result<Vec<api_types::ChainDisagreement>> {
+        let ctx_data = ctx_data(ctx);
+
+        let filter = inputs::ChainDisagreementsQuery {
+            deployment,
+            limit: Some(limit),
+        };
+        let disagreements = ctx_data.store.chain_disagreements(filter).await?;
+
+        Ok(disagreements.into_iter().map(Into::into).collect())
+    }
+
+    /// Returns the latest entity count that each indexer reported for the
+    /// given subgraph deployment, largest first. Indexers whose counts differ
+    /// at similar blocks are likely to have diverged.
+    async fn entity_counts(
+        &self,
+        ctx: &Context<'_>,
+        deployment: IpfsCid,
+    ) -> Result<Vec<api_types::EntityCount>> {
+        let ctx_data = ctx_data(ctx);
+        let mut entity_counts = ctx_data.store.latest_entity_counts(&deployment).await?;
+        entity_counts.sort_by_key(|count| std::cmp::Reverse(count.entity_count));
+
+        Ok(entity_counts.into_iter().map(Into::into).collect())
+    }
+
+    /// Returns an SLA-style report on the reliability of every indexer within
+    /// the given time window.
+    async fn sla_report(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(
+            default = 168,
+            validator(maximum = 720),
+            desc = "How many hours back to look."
+        )]
+        hours: u32,
+    ) -> Result<Vec<api_types::IndexerSlaReport>> {
+        let ctx_data = ctx_data(ctx);
+        let store = &ctx_data.store;
+
+        let now = chrono::Utc::now().naive_utc();
+        let since = now - chrono::Duration::hours(hours.into());
+        let indexers = store.indexers(inputs::IndexersQuery::default()).await?;
+
+        let mut reports = vec![];
+        for indexer in indexers {
+            let transitions = store.indexer_uptime_transitions(indexer.id, since).await?;
+            let comparable_pois = store
+                .pois_comparable_with_indexer(indexer.id, since)
+                .await?;
+
+            reports.push(api_types::IndexerSlaReport {
+                availability: uptime_ratio(&transitions, since, now),
+                poi_response_success_rate: store
+                    .poi_request_success_rate(indexer.id, since)
+                    .await?,
+                average_agreement_ratio: average_agreement_ratio(indexer.id, &comparable_pois),
+                confirmed_divergences: store.confirmed_divergences_count(indexer.id, since).await?,
+                indexer: indexer.into(),
+            });
+        }
+
+        Ok(reports)
+    }
+
+    /// Returns the indexing statuses that indexers reported for the given
+    /// subgraph deployment over time, oldest first. Indexers whose latest
+    /// block stops advancing have stalled.
+    async fn sync_progress(
+        &self,
+        ctx: &Context<'_>,
+        deployment: IpfsCid,
+        #[graphql(desc = "Only return the indexing statuses of this indexer.")] indexer: Option<
+            IndexerAddress,
+        >,
+        #[graphql(
+            default = 24,
+            validator(maximum = 720),
+            desc = "How many hours back to look."
+        )]
+        hours: u32,
+        #[graphql(
+            default = 1000,
+            validator(maximum = 10000),
+            desc = "Upper limit on the number of shown results."
+        )]
+        limit: u32,
+    ) -> Result<Vec<api_types::IndexingStatusSnapshot>> {
+        let ctx_data = ctx_data(ctx);
+
+        let since = chrono::Utc::now().naive_utc() - chrono::Duration::hours(hours.into());
+        let history = ctx_data
+            .store
+            .indexing_status_history(&deployment, indexer.as_ref(), since, limit)
+            .await?;
+
+        Ok(history.into_iter().map(Into::into).collect())
+    }
+
     /// Returns all networks known to Graphix. Subgraphs indexing other networks
     /// won't be available in this Graphix database.
     async fn networks(&self, ctx: &Context<'_>) -> Result<Vec<api_types::Network>> {
@@ -281,16 +1115,76 @@ impl QueryRoot {
     }
 }
 
-async fn live_pois(
+async fn load_poi_clusters(
     ctx: &Context<'_>,
-    indexer_address: IndexerAddress,
-) -> Result<Vec<api_types::ProofOfIndexing>> {
+    clusters: Vec<PoiCluster>,
+) -> Result<Vec<api_types::PoiCluster>> {
     let ctx_data = ctx_data(ctx);
 
-    let pois = ctx_data
-        .store
-        .live_pois(Some(&indexer_address), None, None, None)
+    let indexers = ctx_data
+        .loader_indexer
+        .load_many(
+            clusters
+                .iter()
+                .flat_map(|(_, indexer_ids)| indexer_ids.iter().copied()),
+        )
         .await?;
 
-    Ok(pois.into_iter().map(Into::into).collect())
+    Ok(clusters
+        .into_iter()
+        .map(|(poi, indexer_ids)| api_types::PoiCluster {
+            poi,
+            indexers: indexer_ids
+                .iter()
+                .filter_map(|id| indexers.get(id).cloned())
+                .map(Into::into)
+                .collect(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::gen::gen_synthetic_deployments;
+
+    fn poi(sg_deployment_id: IntId, indexer_id: IntId, value: u8) -> Poi {
+        Poi {
+            id: 0,
+            poi: [value; 32].into(),
+            sg_deployment_id,
+            indexer_id,
+            block_id: 1,
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+
+    #[test]
+    fn divergences_are_tallied_by_the_network_of_their_deployment() {
+        let deployments: HashMap<IntId, models::SgDeployment> = gen_synthetic_deployments(2)
+            .into_iter()
+            .zip([(1, 10), (2, 20)])
+            .map(|(cid, (id, network_id))| {
+                let deployment = models::SgDeployment {
+                    id,
+                    cid,
+                    name: None,
+                    network_id,
+                    created_at: chrono::Utc::now().naive_utc(),
+                };
+                (id, deployment)
+            })
+            .collect();
+        let pois = BTreeMap::from([
+            ((1, 1), vec![poi(1, 1, 0), poi(1, 2, 1)]),
+            ((2, 1), vec![poi(2, 1, 0), poi(2, 2, 0)]),
+        ]);
+
+        let divergences = divergences_by_network(&pois, &deployments).unwrap();
+
+        assert_eq!(divergences[&10].diverging_deployments, BTreeSet::from([1]));
+        assert_eq!(divergences[&10].affected_indexers, BTreeSet::from([1, 2]));
+        assert_eq!(divergences[&20].deployments, BTreeSet::from([2]));
+        assert!(divergences[&20].diverging_deployments.is_empty());
+    }
 }
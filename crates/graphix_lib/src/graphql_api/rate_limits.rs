@@ -0,0 +1,297 @@
+//! Rate limits for GraphQL API requests, per client IP address and per API
+//! key. Each client gets a token bucket that refills at a steady rate and
+//! holds up to a burst of requests, which protects instances that are exposed
+//! to the public internet from being overwhelmed by single clients.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+use crate::metrics;
+
+/// Buckets of clients that haven't made requests in a while are full, and
+/// are dropped once there are this many buckets, which bounds memory usage.
+/// If that isn't enough, the least recently used buckets are dropped too,
+/// down to half as many, so that evictions are rare.
+const MAX_BUCKETS: usize = 100_000;
+
+/// How many requests a client may make.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RateLimit {
+    /// The sustained number of requests per second.
+    pub requests_per_second: f64,
+    /// How many requests a client may make at once after being idle.
+    pub burst: u32,
+}
+
+/// A request that was rejected because a client exceeded one of its limits.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RateLimited {
+    /// Which limit was exceeded, i.e. `ip` or `api_key`.
+    pub limit: &'static str,
+    /// How long until the client may make another request.
+    pub retry_after: Duration,
+}
+
+impl RateLimited {
+    /// A `429 Too Many Requests` response with a `Retry-After` header.
+    pub fn response(&self) -> Response {
+        let retry_after = self.retry_after.as_secs_f64().ceil().max(1.0) as u64;
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.to_string())],
+            Json(serde_json::json!({
+                "message": "Rate limit exceeded",
+                "error": format!("too many requests, retry in {retry_after} seconds"),
+            })),
+        )
+            .into_response()
+    }
+}
+
+/// The rate limits of the API server. Requests count against the limit of
+/// their client IP address and, if they carry one that exists, of their API
+/// key. Unknown API keys only count against the IP address limit, so that
+/// made-up keys can't fill up the buckets.
+pub struct RateLimits {
+    per_ip: Option<RateLimiter<IpAddr>>,
+    per_api_key: Option<RateLimiter<String>>,
+}
+
+impl RateLimits {
+    /// Returns [`None`] if no limits are given.
+    pub fn new(per_ip: Option<RateLimit>, per_api_key: Option<RateLimit>) -> Option<Self> {
+        if per_ip.is_none() && per_api_key.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            per_ip: per_ip.map(RateLimiter::new),
+            per_api_key: per_api_key.map(RateLimiter::new),
+        })
+    }
+
+    /// Takes a request from the bucket of the client IP address. Requests
+    /// from unknown IP addresses are only limited by their API key.
+    pub fn check_ip(&self, client_ip: Option<IpAddr>) -> Result<(), RateLimited> {
+        self.check(client_ip, None)
+    }
+
+    /// Takes a request from the bucket of an API key, which must exist.
+    pub fn check_api_key(&self, api_key: &str) -> Result<(), RateLimited> {
+        self.check(None, Some(api_key))
+    }
+
+    /// Takes a request from the buckets of the client, and records rejected
+    /// requests in metrics.
+    fn check(&self, client_ip: Option<IpAddr>, api_key: Option<&str>) -> Result<(), RateLimited> {
+        let now = Instant::now();
+        let result = self.check_at(client_ip, api_key, now);
+        if let Err(rate_limited) = &result {
+            metrics()
+                .rate_limited_requests
+                .with_label_values(&[rate_limited.limit])
+                .inc();
+        }
+
+        result
+    }
+
+    fn check_at(
+        &self,
+        client_ip: Option<IpAddr>,
+        api_key: Option<&str>,
+        now: Instant,
+    ) -> Result<(), RateLimited> {
+        if let (Some(limiter), Some(client_ip)) = (&self.per_ip, client_ip) {
+            limiter
+                .take(client_ip, now)
+                .map_err(|retry_after| RateLimited {
+                    limit: "ip",
+                    retry_after,
+                })?;
+        }
+        if let (Some(limiter), Some(api_key)) = (&self.per_api_key, api_key) {
+            limiter
+                .take(api_key.to_string(), now)
+                .map_err(|retry_after| RateLimited {
+                    limit: "api_key",
+                    retry_after,
+                })?;
+        }
+
+        Ok(())
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Token buckets of clients identified by `K`, which all share the same
+/// limit.
+struct RateLimiter<K> {
+    limit: RateLimit,
+    buckets: Mutex<HashMap<K, Bucket>>,
+}
+
+impl<K: Hash + Eq> RateLimiter<K> {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn capacity(&self) -> f64 {
+        f64::from(self.limit.burst.max(1))
+    }
+
+    fn tokens(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated_at);
+        (bucket.tokens + elapsed.as_secs_f64() * self.limit.requests_per_second)
+            .min(self.capacity())
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        bucket.tokens = self.tokens(bucket, now);
+        bucket.updated_at = now;
+    }
+
+    /// Drops full buckets and then the least recently used ones, until at
+    /// most half of [`MAX_BUCKETS`] are left.
+    fn evict(&self, buckets: &mut HashMap<K, Bucket>, now: Instant) {
+        buckets.retain(|_, bucket| self.tokens(bucket, now) < self.capacity());
+
+        let keep = MAX_BUCKETS / 2;
+        if buckets.len() > keep {
+            let mut updated_at: Vec<Instant> = buckets.values().map(|b| b.updated_at).collect();
+            let evicted = updated_at.len() - keep;
+            let (_, cutoff, _) = updated_at.select_nth_unstable(evicted);
+            let cutoff = *cutoff;
+            buckets.retain(|_, bucket| bucket.updated_at >= cutoff);
+        }
+    }
+
+    /// Takes a token from the bucket of `key`, or returns how long until the
+    /// bucket has one again.
+    fn take(&self, key: K, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_BUCKETS && !buckets.contains_key(&key) {
+            self.evict(&mut buckets, now);
+        }
+
+        let bucket = buckets.entry(key).or_insert_with(|| Bucket {
+            tokens: self.capacity(),
+            updated_at: now,
+        });
+        self.refill(bucket, now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else if self.limit.requests_per_second > 0.0 {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.limit.requests_per_second,
+            ))
+        } else {
+            Err(Duration::MAX)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: RateLimit = RateLimit {
+        requests_per_second: 2.0,
+        burst: 3,
+    };
+
+    #[test]
+    fn buckets_allow_bursts_and_refill() {
+        let limiter = RateLimiter::new(LIMIT);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert_eq!(limiter.take("a", start), Ok(()));
+        }
+        assert_eq!(limiter.take("a", start), Err(Duration::from_millis(500)));
+        // Other clients have buckets of their own.
+        assert_eq!(limiter.take("b", start), Ok(()));
+
+        let later = start + Duration::from_millis(500);
+        assert_eq!(limiter.take("a", later), Ok(()));
+        assert!(limiter.take("a", later).is_err());
+        // Buckets don't hold more than the burst.
+        let much_later = start + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert_eq!(limiter.take("a", much_later), Ok(()));
+        }
+        assert!(limiter.take("a", much_later).is_err());
+    }
+
+    #[test]
+    fn evictions_drop_least_recently_used_buckets() {
+        let limiter = RateLimiter::new(LIMIT);
+        let start = Instant::now();
+
+        for i in 0..MAX_BUCKETS {
+            let now = start + Duration::from_micros(i as u64);
+            limiter.take(i, now).unwrap();
+        }
+        // Not long enough for any bucket to be full again.
+        let now = start + Duration::from_micros(MAX_BUCKETS as u64);
+        limiter.take(MAX_BUCKETS, now).unwrap();
+
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.len(), MAX_BUCKETS / 2 + 1);
+        assert!(!buckets.contains_key(&0));
+        assert!(buckets.contains_key(&(MAX_BUCKETS - 1)));
+    }
+
+    #[test]
+    fn requests_count_against_ip_and_api_key() {
+        let limits = RateLimits::new(Some(LIMIT), Some(RateLimit { burst: 1, ..LIMIT })).unwrap();
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        let other_ip = IpAddr::from([127, 0, 0, 2]);
+        let now = Instant::now();
+
+        assert!(limits.check_at(Some(ip), Some("key"), now).is_ok());
+        let rate_limited = limits
+            .check_at(Some(other_ip), Some("key"), now)
+            .unwrap_err();
+        assert_eq!(rate_limited.limit, "api_key");
+
+        for _ in 0..2 {
+            assert!(limits.check_at(Some(ip), None, now).is_ok());
+        }
+        let rate_limited = limits.check_at(Some(ip), None, now).unwrap_err();
+        assert_eq!(rate_limited.limit, "ip");
+        // Requests from unknown addresses are only limited by API key.
+        assert!(limits.check_at(None, None, now).is_ok());
+    }
+
+    #[test]
+    fn no_limits_means_no_rate_limiting() {
+        assert!(RateLimits::new(None, None).is_none());
+    }
+
+    #[test]
+    fn rejections_have_a_retry_after_header() {
+        let response = RateLimited {
+            limit: "ip",
+            retry_after: Duration::from_millis(1500),
+        }
+        .response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+    }
+}
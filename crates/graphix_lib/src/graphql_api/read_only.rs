@@ -0,0 +1,66 @@
+//! Rejection of mutations when Graphix only serves the GraphQL API, e.g. as a
+//! public mirror of another instance's database.
+
+use std::sync::Arc;
+
+use async_graphql::extensions::{Extension, ExtensionContext, ExtensionFactory, NextParseQuery};
+use async_graphql::parser::types::{ExecutableDocument, OperationType};
+use async_graphql::{ServerError, ServerResult, Variables};
+
+/// An extension that rejects all documents containing a mutation.
+pub struct ReadOnly;
+
+impl ExtensionFactory for ReadOnly {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(ReadOnlyExtension)
+    }
+}
+
+struct ReadOnlyExtension;
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for ReadOnlyExtension {
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+        let has_mutation = document
+            .operations
+            .iter()
+            .any(|(_, operation)| operation.node.ty == OperationType::Mutation);
+        if has_mutation {
+            return Err(ServerError::new(
+                "Graphix is running in read-only mode, mutations are disabled",
+                None,
+            ));
+        }
+
+        Ok(document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphql_api::api_schema_builder;
+
+    #[tokio::test]
+    async fn mutations_are_rejected() {
+        let schema = api_schema_builder().extension(ReadOnly).finish();
+
+        let response = schema.execute("{ version }").await;
+        assert!(response.errors.is_empty());
+
+        let response = schema
+            .execute("mutation { deleteNetwork(network: \"foo\") }")
+            .await;
+        assert_eq!(
+            response.errors[0].message,
+            "Graphix is running in read-only mode, mutations are disabled"
+        );
+    }
+}
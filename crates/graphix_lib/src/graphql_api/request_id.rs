@@ -0,0 +1,132 @@
+//! Request IDs, which correlate API requests with the server logs they
+//! produce. Clients may send their own in an `x-request-id` header, otherwise
+//! one is generated. Either way, it's returned in the same header.
+
+use std::sync::Arc;
+
+use async_graphql::extensions::{Extension, ExtensionContext, ExtensionFactory, NextRequest};
+use axum::http::{HeaderMap, HeaderName, Request};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
+use tracing::info_span;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Returns the ID of the request with the given headers, if it has one.
+pub fn request_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Assigns request IDs to all requests to `router`, records them in a
+/// tracing span around each request, and returns them to clients.
+pub fn with_request_ids(router: axum::Router<()>) -> axum::Router<()> {
+    // The last layer runs first.
+    router
+        .layer(PropagateRequestIdLayer::new(HeaderName::from_static(
+            REQUEST_ID_HEADER,
+        )))
+        .layer(
+            TraceLayer::new_for_http().make_span_with(|request: &Request<axum::body::Body>| {
+                info_span!(
+                    "api_request",
+                    method = %request.method(),
+                    uri = %request.uri(),
+                    request_id = request_id(request.headers()).unwrap_or_default(),
+                )
+            }),
+        )
+        .layer(SetRequestIdLayer::new(
+            HeaderName::from_static(REQUEST_ID_HEADER),
+            MakeRequestUuid,
+        ))
+}
+
+/// An extension that adds the request ID to the `extensions` of all GraphQL
+/// errors, as `requestId`.
+pub struct RequestIdErrors {
+    request_id: String,
+}
+
+impl RequestIdErrors {
+    pub fn new(request_id: String) -> Self {
+        Self { request_id }
+    }
+}
+
+impl ExtensionFactory for RequestIdErrors {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(RequestIdErrorsExtension {
+            request_id: self.request_id.clone(),
+        })
+    }
+}
+
+struct RequestIdErrorsExtension {
+    request_id: String,
+}
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for RequestIdErrorsExtension {
+    async fn request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        next: NextRequest<'_>,
+    ) -> async_graphql::Response {
+        let mut response = next.run(ctx).await;
+        for error in &mut response.errors {
+            error
+                .extensions
+                .get_or_insert_with(Default::default)
+                .set("requestId", self.request_id.as_str());
+        }
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_service::Service;
+
+    use super::*;
+    use crate::graphql_api::api_schema_builder;
+
+    #[tokio::test]
+    async fn request_ids_are_returned() {
+        let mut router = with_request_ids(
+            axum::Router::new().route("/graphql", axum::routing::post(|| async {})),
+        );
+        let mut send = |request_id: Option<&str>| {
+            let mut request = axum::extract::Request::builder()
+                .method("POST")
+                .uri("/graphql");
+            if let Some(request_id) = request_id {
+                request = request.header(REQUEST_ID_HEADER, request_id);
+            }
+            router.call(request.body(axum::body::Body::empty()).unwrap())
+        };
+
+        let response = send(Some("abc")).await.unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "abc");
+        let response = send(None).await.unwrap();
+        let generated = request_id(response.headers()).unwrap();
+        assert!(uuid::Uuid::parse_str(&generated).is_ok());
+    }
+
+    #[tokio::test]
+    async fn request_ids_are_added_to_errors() {
+        let schema = api_schema_builder()
+            .extension(RequestIdErrors::new("abc".to_string()))
+            .finish();
+
+        let response = schema.execute("{ noSuchField }").await;
+        let extensions = response.errors[0].extensions.as_ref().unwrap();
+        assert_eq!(
+            extensions.get("requestId"),
+            Some(&async_graphql::Value::from("abc"))
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! An in-process cache of the responses to expensive GraphQL queries, e.g.
+//! `poiAgreementRatios`. Unlike the validators in [`super::http_caching`],
+//! it spares the database even if clients don't cache responses themselves.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::body::{Body, Bytes};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::Response;
+use graphix_common_types::ApiKeyPermissionLevel;
+use graphix_store::models::IntId;
+use sha2::{Digest, Sha256};
+
+use super::http_caching::selects_only;
+
+/// Top-level query fields that are expensive to resolve, and whose results
+/// only change when new PoIs are collected.
+const CACHED_FIELDS: &[&str] = &[
+    "divergenceSummary",
+    "poiAgreementRatios",
+    "poiClusters",
+    "poiForkTree",
+    "versionDivergenceMatrix",
+];
+
+/// Upper limit on the number of cached responses, which bounds memory usage.
+const MAX_ENTRIES: usize = 1000;
+
+/// Identifies a GraphQL request by its query and variables, and the
+/// permission level and workspace of its API key, which can change the
+/// response.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey([u8; 32]);
+
+impl CacheKey {
+    pub fn new(
+        body: &[u8],
+        permission_level: Option<ApiKeyPermissionLevel>,
+        workspace_id: Option<IntId>,
+    ) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(body);
+        hasher.update(format!("{permission_level:?}/{workspace_id:?}").as_bytes());
+        Self(hasher.finalize().into())
+    }
+}
+
+struct CachedResponse {
+    /// Responses are stale as soon as a PoI is written after this one.
+    latest_poi_id: Option<IntId>,
+    cached_at: Instant,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+/// Caches successful responses for up to a fixed time, and drops them as soon
+/// as new PoIs are collected.
+pub struct ResponseCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<CacheKey, CachedResponse>>,
+}
+
+impl ResponseCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether the response to the request with the given raw body is
+    /// cached at all.
+    pub fn is_cacheable(&self, body: &[u8]) -> bool {
+        selects_only(body, CACHED_FIELDS)
+    }
+
+    /// Returns the cached response for `key`, unless it's expired or PoIs
+    /// were written since it was cached.
+    pub fn get(&self, key: &CacheKey, latest_poi_id: Option<IntId>) -> Option<Response> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get(key)?;
+        if entry.latest_poi_id != latest_poi_id || entry.cached_at.elapsed() >= self.ttl {
+            entries.remove(key);
+            return None;
+        }
+
+        let mut response = Response::new(Body::from(entry.body.clone()));
+        *response.headers_mut() = entry.headers.clone();
+        Some(response)
+    }
+
+    /// Caches `response` unless it failed or contains GraphQL errors, which
+    /// may be transient, and returns it.
+    pub async fn insert(
+        &self,
+        key: CacheKey,
+        latest_poi_id: Option<IntId>,
+        response: Response,
+    ) -> anyhow::Result<Response> {
+        if response.status() != StatusCode::OK {
+            return Ok(response);
+        }
+        let (parts, body) = response.into_parts();
+        let body = axum::body::to_bytes(body, usize::MAX).await?;
+        let has_errors = serde_json::from_slice::<serde_json::Value>(&body)
+            .map_or(true, |response| response.get("errors").is_some());
+
+        if !has_errors {
+            let mut entries = self.entries.lock().unwrap();
+            if entries.len() >= MAX_ENTRIES {
+                entries.retain(|_, entry| entry.cached_at.elapsed() < self.ttl);
+            }
+            if entries.len() < MAX_ENTRIES {
+                entries.insert(
+                    key,
+                    CachedResponse {
+                        latest_poi_id,
+                        cached_at: Instant::now(),
+                        headers: parts.headers.clone(),
+                        body: body.clone(),
+                    },
+                );
+            }
+        }
+
+        Ok(Response::from_parts(parts, Body::from(body)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(body: serde_json::Value) -> Response {
+        Response::new(Body::from(body.to_string()))
+    }
+
+    async fn body(response: Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    fn keys_depend_on_permission_level_and_workspace() {
+        let body = br#"{"query": "{ poiClusters { indexers } }"}"#;
+        let admin = CacheKey::new(body, Some(ApiKeyPermissionLevel::Admin), None);
+        assert_eq!(
+            admin,
+            CacheKey::new(body, Some(ApiKeyPermissionLevel::Admin), None)
+        );
+        assert_ne!(admin, CacheKey::new(body, None, None));
+        assert_ne!(
+            admin,
+            CacheKey::new(body, Some(ApiKeyPermissionLevel::Admin), Some(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn new_pois_invalidate_responses() {
+        let cache = ResponseCache::new(Duration::from_secs(60));
+        let key = CacheKey::new(b"{}", None, None);
+        let data = serde_json::json!({ "data": { "poiClusters": [] } });
+
+        let returned = cache.insert(key.clone(), Some(1), response(data.clone()));
+        assert_eq!(body(returned.await.unwrap()).await, data);
+        assert_eq!(body(cache.get(&key, Some(1)).unwrap()).await, data);
+        assert!(cache.get(&key, Some(2)).is_none());
+        // Stale responses are dropped.
+        assert!(cache.get(&key, Some(1)).is_none());
+    }
+
+    #[tokio::test]
+    async fn errors_and_expired_responses_are_not_served() {
+        let cache = ResponseCache::new(Duration::from_secs(60));
+        let key = CacheKey::new(b"{}", None, None);
+        let errors = serde_json::json!({ "data": null, "errors": [{ "message": "oops" }] });
+        cache
+            .insert(key.clone(), None, response(errors))
+            .await
+            .unwrap();
+        assert!(cache.get(&key, None).is_none());
+
+        let cache = ResponseCache::new(Duration::ZERO);
+        let data = serde_json::json!({ "data": { "poiClusters": [] } });
+        cache
+            .insert(key.clone(), None, response(data))
+            .await
+            .unwrap();
+        assert!(cache.get(&key, None).is_none());
+    }
+}
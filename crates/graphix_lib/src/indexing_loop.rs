@@ -1,27 +1,94 @@
 //! Logic related to the main indexing loop performed by Graphix:
 //!  1. Query `indexingStatuses` for all indexers.
 //!  2. Query PoIs for recent common blocks across all indexers.
-//!  3. Store the PoIs in the database.
+//!  3. Store the PoIs in the database, as they come in.
 
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Instant;
 
 use futures::stream::FuturesUnordered;
-use futures::StreamExt;
+use futures::{future, Stream, StreamExt};
 use graphix_common_types::{GraphNodeCollectedVersion, IpfsCid};
 use graphix_indexer_client::{
-    IndexerClient, IndexerId, IndexingStatus, PoiRequest, ProofOfIndexing,
+    ActiveAllocation, IndexerClient, IndexerId, IndexingStatus, PoiRequest, ProofOfIndexing,
 };
+use graphix_store::models::PoiRequestOutcome;
+use graphix_store::{PoiLiveness, PoiStore};
+use tokio::sync::{Semaphore, SemaphorePermit};
 use tracing::*;
 
 use crate::block_choice::BlockChoicePolicy;
-use crate::PrometheusMetrics;
+use crate::config::Config;
+use crate::{metrics, IndexerQueryKind, PrometheusMetrics};
+
+/// Lower limit on the number of PoIs written to the database at once while
+/// indexers are still responding.
+const POI_WRITE_CHUNK_SIZE: usize = 100;
+
+/// Upper limit on the number of PoIs requested from an indexer in one go.
+/// Larger workloads are split up, so that indexers with many deployments
+/// take turns with the others instead of holding up the round.
+const POI_REQUEST_CHUNK_SIZE: usize = 10;
+
+/// Caps the number of concurrent requests to indexers, so that configs with
+/// hundreds of indexers don't exhaust sockets or trip upstream rate limits.
+/// Requests of all kinds count towards the overall limit, and
+/// `indexingStatuses` requests are additionally subject to their own limit.
+/// PoI requests are also limited per indexer.
+#[derive(Debug, Clone)]
+pub struct IndexerRequestLimits {
+    all: Arc<Semaphore>,
+    indexing_statuses: Arc<Semaphore>,
+    per_indexer: usize,
+}
+
+impl IndexerRequestLimits {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            all: Arc::new(Semaphore::new(
+                config.max_concurrent_indexer_requests.max(1),
+            )),
+            indexing_statuses: Arc::new(Semaphore::new(
+                config.max_concurrent_indexing_status_requests.max(1),
+            )),
+            per_indexer: config.max_concurrent_requests_per_indexer.max(1),
+        }
+    }
+
+    /// A new limit on the concurrent requests to a single indexer.
+    fn per_indexer(&self) -> Arc<Semaphore> {
+        Arc::new(Semaphore::new(self.per_indexer))
+    }
+
+    async fn acquire(&self) -> SemaphorePermit<'_> {
+        self.all.acquire().await.expect("semaphore is never closed")
+    }
+
+    async fn acquire_indexing_statuses(&self) -> (SemaphorePermit<'_>, SemaphorePermit<'_>) {
+        // Always acquired in the same order, so that requests of different
+        // kinds can't deadlock.
+        let indexing_statuses = self
+            .indexing_statuses
+            .acquire()
+            .await
+            .expect("semaphore is never closed");
+        (indexing_statuses, self.acquire().await)
+    }
+}
+
+impl Default for IndexerRequestLimits {
+    fn default() -> Self {
+        Self::new(&Config::default())
+    }
+}
 
 /// Queries all `indexingStatuses` for all the given indexers.
 #[instrument(skip_all)]
 pub async fn query_indexing_statuses(
     indexers: &[Arc<dyn IndexerClient>],
     metrics: &PrometheusMetrics,
+    limits: &IndexerRequestLimits,
 ) -> Vec<IndexingStatus> {
     let indexers_count = indexers.len();
     debug!(
@@ -29,12 +96,20 @@ pub async fn query_indexing_statuses(
         "Querying indexing statuses..."
     );
 
-    let indexing_statuses_results = indexers
-        .iter()
-        .map(|indexer| async move { (indexer.clone(), indexer.clone().indexing_statuses().await) })
-        .collect::<FuturesUnordered<_>>()
-        .collect::<Vec<_>>()
-        .await;
+    // Results are kept in the order of `indexers`, regardless of the order
+    // in which the request limits let the requests through.
+    let indexing_statuses_results = future::join_all(indexers.iter().map(|indexer| async move {
+        let _permits = limits.acquire_indexing_statuses().await;
+        let start = Instant::now();
+        let statuses = indexer.clone().indexing_statuses().await;
+        metrics.observe_response_time(
+            &indexer.metrics_label(),
+            IndexerQueryKind::IndexingStatuses,
+            start.elapsed(),
+        );
+        (indexer.clone(), statuses)
+    }))
+    .await;
 
     assert_eq!(indexing_statuses_results.len(), indexers.len());
 
@@ -48,7 +123,7 @@ pub async fn query_indexing_statuses(
                 query_successes += 1;
                 metrics
                     .indexing_statuses_requests
-                    .get_metric_with_label_values(&[&indexer.address_string(), "1"])
+                    .get_metric_with_label_values(&[&indexer.metrics_label(), "1"])
                     .unwrap()
                     .inc();
 
@@ -64,7 +139,7 @@ pub async fn query_indexing_statuses(
                 query_failures += 1;
                 metrics
                     .indexing_statuses_requests
-                    .get_metric_with_label_values(&[&indexer.address_string(), "0"])
+                    .get_metric_with_label_values(&[&indexer.metrics_label(), "0"])
                     .unwrap()
                     .inc();
 
@@ -90,11 +165,36 @@ pub async fn query_indexing_statuses(
     indexing_statuses
 }
 
+/// Restricts `indexing_statuses` to the given subgraph deployments, identified
+/// by their IPFS CID and network name. If `deployments` is empty, all indexing
+/// statuses are kept.
+pub fn retain_tracked_deployments(
+    indexing_statuses: &mut Vec<IndexingStatus>,
+    deployments: &[(IpfsCid, String)],
+) {
+    if deployments.is_empty() {
+        return;
+    }
+
+    let deployments: HashSet<(&IpfsCid, &str)> = deployments
+        .iter()
+        .map(|(cid, network)| (cid, network.as_str()))
+        .collect();
+    indexing_statuses
+        .retain(|status| deployments.contains(&(&status.deployment, status.network.as_str())));
+
+    debug!(
+        indexing_statuses = indexing_statuses.len(),
+        "Restricted indexing statuses to tracked deployments"
+    );
+}
+
 /// Queries all `indexers` for their `graph-node` versions.
 #[instrument(skip_all)]
 pub async fn query_graph_node_versions(
     indexers: &[Arc<dyn IndexerClient>],
     _metrics: &PrometheusMetrics,
+    limits: &IndexerRequestLimits,
 ) -> HashMap<Arc<dyn IndexerClient>, anyhow::Result<GraphNodeCollectedVersion>> {
     let span = span!(Level::TRACE, "query_graph_node_versions");
     let _enter_span = span.enter();
@@ -103,7 +203,10 @@ pub async fn query_graph_node_versions(
 
     let graph_node_versions_results = indexers
         .iter()
-        .map(|indexer| async move { (indexer.clone(), indexer.clone().version().await) })
+        .map(|indexer| async move {
+            let _permit = limits.acquire().await;
+            (indexer.clone(), indexer.clone().version().await)
+        })
         .collect::<FuturesUnordered<_>>()
         .collect::<Vec<_>>()
         .await;
@@ -142,11 +245,111 @@ pub async fn query_graph_node_versions(
     versions
 }
 
+/// Queries all `indexers` that expose their `indexer-agent` for their active
+/// allocations. Indexers that don't, or fail to respond, are left out.
+#[instrument(skip_all)]
+pub async fn query_active_allocations(
+    indexers: &[Arc<dyn IndexerClient>],
+    limits: &IndexerRequestLimits,
+) -> HashMap<Arc<dyn IndexerClient>, Vec<ActiveAllocation>> {
+    let results = indexers
+        .iter()
+        .map(|indexer| async move {
+            let _permit = limits.acquire().await;
+            (indexer.clone(), indexer.clone().active_allocations().await)
+        })
+        .collect::<FuturesUnordered<_>>()
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut allocations = HashMap::new();
+    for (indexer, result) in results {
+        match result {
+            Ok(Some(active_allocations)) => {
+                allocations.insert(indexer, active_allocations);
+            }
+            Ok(None) => {}
+            Err(error) => {
+                warn!(
+                    indexer_id = %indexer.address_string(),
+                    %error,
+                    "Failed to query active allocations"
+                );
+            }
+        }
+    }
+
+    info!(
+        indexers = allocations.len(),
+        "Finished querying active allocations"
+    );
+
+    allocations
+}
+
+/// Queries the subgraph API versions of all deployments in
+/// `indexing_statuses`, except those in `known_deployments`. Each deployment
+/// is queried from a single indexer that indexes it.
+#[instrument(skip_all)]
+pub async fn query_subgraph_api_versions(
+    indexing_statuses: &[IndexingStatus],
+    known_deployments: &HashSet<IpfsCid>,
+    limits: &IndexerRequestLimits,
+) -> HashMap<IpfsCid, anyhow::Result<Vec<String>>> {
+    let mut indexer_by_deployment = HashMap::new();
+    for status in indexing_statuses {
+        if !known_deployments.contains(&status.deployment) {
+            indexer_by_deployment
+                .entry(status.deployment.clone())
+                .or_insert_with(|| status.indexer.clone());
+        }
+    }
+
+    debug!(
+        deployments = indexer_by_deployment.len(),
+        "Querying subgraph API versions..."
+    );
+
+    indexer_by_deployment
+        .into_iter()
+        .map(|(deployment, indexer)| async move {
+            let _permit = limits.acquire().await;
+            let result = indexer
+                .clone()
+                .subgraph_api_versions(&deployment.to_string())
+                .await;
+            if let Err(error) = &result {
+                debug!(
+                    indexer_id = %indexer.address_string(),
+                    %deployment,
+                    %error,
+                    "Failed to query subgraph API versions"
+                );
+            }
+            (deployment, result)
+        })
+        .collect::<FuturesUnordered<_>>()
+        .collect()
+        .await
+}
+
 #[instrument(skip_all)]
 pub async fn query_proofs_of_indexing(
     indexing_statuses: Vec<IndexingStatus>,
     block_choice_policy: BlockChoicePolicy,
+    limits: &IndexerRequestLimits,
 ) -> Vec<ProofOfIndexing> {
+    let poi_requests = choose_poi_requests(&indexing_statuses, block_choice_policy);
+    query_requested_proofs_of_indexing(&poi_requests, limits).await
+}
+
+/// Chooses the PoIs to request from every indexer: for each deployment, the
+/// PoI at the block chosen by `block_choice_policy`, if the indexer has
+/// indexed it.
+pub fn choose_poi_requests(
+    indexing_statuses: &[IndexingStatus],
+    block_choice_policy: BlockChoicePolicy,
+) -> HashMap<Arc<dyn IndexerClient>, Vec<PoiRequest>> {
     info!("Query POIs for recent common blocks across indexers");
 
     // Identify all indexers
@@ -185,10 +388,9 @@ pub async fn query_proofs_of_indexing(
             )
         }));
 
-    // Fetch POIs for the most recent common blocks
     indexers
-        .iter()
-        .map(|indexer| async {
+        .into_iter()
+        .map(|indexer| {
             let poi_requests = latest_blocks
                 .iter()
                 .filter(|(deployment, &block_number)| {
@@ -197,7 +399,7 @@ pub async fn query_proofs_of_indexing(
                         .expect("bug in matching deployments to latest blocks and indexers")
                         .iter()
                         .any(|status| {
-                            status.indexer.eq(indexer)
+                            status.indexer.eq(&indexer)
                                 && Some(status.latest_block.number) >= block_number
                         })
                 })
@@ -209,19 +411,259 @@ pub async fn query_proofs_of_indexing(
                 })
                 .collect::<Vec<_>>();
 
-            let pois = indexer.clone().proofs_of_indexing(poi_requests).await;
+            (indexer, poi_requests)
+        })
+        .collect()
+}
 
-            debug!(
-                id = %indexer.address_string(), pois = %pois.len(),
-                "Successfully queried POIs from indexer"
-            );
+/// Requests the given PoIs from each indexer.
+#[instrument(skip_all)]
+pub async fn query_requested_proofs_of_indexing(
+    poi_requests: &HashMap<Arc<dyn IndexerClient>, Vec<PoiRequest>>,
+    limits: &IndexerRequestLimits,
+) -> Vec<ProofOfIndexing> {
+    proofs_of_indexing_stream(poi_requests, limits)
+        .concat()
+        .await
+}
 
-            pois
+/// Like [`query_requested_proofs_of_indexing`], but also writes the PoIs to
+/// `store` as live PoIs, in chunks of at least [`POI_WRITE_CHUNK_SIZE`] as
+/// indexers respond, instead of all at once at the end. Returns all PoIs and
+/// the first error that occurred while writing them, if any.
+#[instrument(skip_all)]
+pub async fn query_and_write_proofs_of_indexing(
+    store: &dyn PoiStore,
+    poi_requests: &HashMap<Arc<dyn IndexerClient>, Vec<PoiRequest>>,
+    limits: &IndexerRequestLimits,
+) -> (Vec<ProofOfIndexing>, anyhow::Result<()>) {
+    let mut responses = proofs_of_indexing_stream(poi_requests, limits);
+    let mut pois = vec![];
+    let mut written = 0;
+    let mut write_result = Ok(());
+
+    while let Some(indexer_pois) = responses.next().await {
+        pois.extend(indexer_pois);
+        if pois.len() - written >= POI_WRITE_CHUNK_SIZE {
+            let chunk = pois[written..].to_vec();
+            write_result = write_result.and(store.write_pois(chunk, PoiLiveness::Live).await);
+            written = pois.len();
+        }
+    }
+    if written < pois.len() {
+        let chunk = pois[written..].to_vec();
+        write_result = write_result.and(store.write_pois(chunk, PoiLiveness::Live).await);
+    }
+
+    (pois, write_result)
+}
+
+/// Requests the given PoIs from each indexer in chunks, and yields the PoIs
+/// of each chunk as soon as the indexer responds.
+///
+/// Chunks are queued round-robin across indexers, and each one waits for a
+/// slot of its indexer before it waits for an overall one. Both semaphores
+/// are fair, so an indexer with many deployments gets its turn after every
+/// other waiting indexer had one, and a round takes about as long as the
+/// slowest indexer's fair share of it rather than its whole workload.
+fn proofs_of_indexing_stream<'a>(
+    poi_requests: &'a HashMap<Arc<dyn IndexerClient>, Vec<PoiRequest>>,
+    limits: &'a IndexerRequestLimits,
+) -> impl Stream<Item = Vec<ProofOfIndexing>> + Unpin + 'a {
+    let indexer_limits: HashMap<&Arc<dyn IndexerClient>, Arc<Semaphore>> = poi_requests
+        .keys()
+        .map(|indexer| (indexer, limits.per_indexer()))
+        .collect();
+
+    interleave_poi_requests(poi_requests)
+        .into_iter()
+        .map(|(indexer, poi_requests)| {
+            let indexer_limit = indexer_limits[indexer].clone();
+            async move {
+                let _indexer_permit = indexer_limit
+                    .acquire()
+                    .await
+                    .expect("semaphore is never closed");
+                let _permit = limits.acquire().await;
+                let start = Instant::now();
+                let pois = indexer
+                    .clone()
+                    .proofs_of_indexing(poi_requests.to_vec())
+                    .await;
+                metrics().observe_response_time(
+                    &indexer.metrics_label(),
+                    IndexerQueryKind::ProofsOfIndexing,
+                    start.elapsed(),
+                );
+
+                debug!(
+                    id = %indexer.address_string(), pois = %pois.len(),
+                    "Successfully queried POIs from indexer"
+                );
+
+                pois
+            }
         })
         .collect::<FuturesUnordered<_>>()
-        .collect::<Vec<_>>()
-        .await
-        .into_iter()
-        .flatten()
-        .collect::<Vec<_>>()
+}
+
+/// Splits the PoI requests of each indexer into chunks of
+/// [`POI_REQUEST_CHUNK_SIZE`], and orders them round-robin: the first chunk
+/// of every indexer, then the second one, and so on.
+fn interleave_poi_requests(
+    poi_requests: &HashMap<Arc<dyn IndexerClient>, Vec<PoiRequest>>,
+) -> Vec<(&Arc<dyn IndexerClient>, &[PoiRequest])> {
+    let mut chunks: Vec<_> = poi_requests
+        .iter()
+        .map(|(indexer, requests)| (indexer, requests.chunks(POI_REQUEST_CHUNK_SIZE)))
+        .collect();
+
+    let mut interleaved = vec![];
+    while !chunks.is_empty() {
+        chunks.retain_mut(|(indexer, indexer_chunks)| match indexer_chunks.next() {
+            Some(chunk) => {
+                interleaved.push((*indexer, chunk));
+                true
+            }
+            None => false,
+        });
+    }
+    interleaved
+}
+
+/// Counts how many of the requested PoIs each indexer returned. Indexers
+/// that weren't asked for any PoIs are left out.
+pub fn poi_request_outcomes(
+    poi_requests: &HashMap<Arc<dyn IndexerClient>, Vec<PoiRequest>>,
+    pois: &[ProofOfIndexing],
+) -> Vec<PoiRequestOutcome> {
+    poi_requests
+        .iter()
+        .filter(|(_, requests)| !requests.is_empty())
+        .map(|(indexer, requests)| PoiRequestOutcome {
+            indexer: indexer.clone(),
+            requested: requests.len(),
+            returned: pois.iter().filter(|poi| &poi.indexer == indexer).count(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn indexing_statuses_requests_are_limited() {
+        let limits = IndexerRequestLimits::new(&Config {
+            max_concurrent_indexing_status_requests: 1,
+            max_concurrent_indexer_requests: 2,
+            ..Default::default()
+        });
+
+        let permits = limits.acquire_indexing_statuses().await;
+        assert!(limits.acquire_indexing_statuses().now_or_never().is_none());
+        // Other kinds of requests are only subject to the overall limit.
+        let permit = limits.acquire().await;
+        assert!(limits.acquire().now_or_never().is_none());
+
+        drop(permit);
+        drop(permits);
+        assert!(limits.acquire_indexing_statuses().now_or_never().is_some());
+    }
+
+    #[test]
+    fn poi_requests_are_interleaved_across_indexers() {
+        use crate::test_utils::gen::{gen_deployments, gen_synthetic_deployments};
+        use crate::test_utils::mocks::MockIndexer;
+
+        let indexer = |name: &str| -> Arc<dyn IndexerClient> {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            })
+        };
+        let requests = |deployments: Vec<IpfsCid>| -> Vec<PoiRequest> {
+            deployments
+                .into_iter()
+                .map(|deployment| PoiRequest {
+                    deployment,
+                    block_number: 1,
+                })
+                .collect()
+        };
+        let (busy, idle, unused) = (indexer("busy"), indexer("idle"), indexer("unused"));
+        let poi_requests = HashMap::from([
+            (
+                busy.clone(),
+                requests(gen_synthetic_deployments(3 * POI_REQUEST_CHUNK_SIZE)),
+            ),
+            (idle.clone(), requests(gen_deployments()[..1].to_vec())),
+            (unused, vec![]),
+        ]);
+
+        let interleaved = interleave_poi_requests(&poi_requests);
+
+        let order: Vec<&Arc<dyn IndexerClient>> =
+            interleaved.iter().map(|(indexer, _)| *indexer).collect();
+        assert_eq!(order.len(), 4);
+        // Both indexers get a turn before the busy one gets its second.
+        assert!(order[..2].contains(&&busy) && order[..2].contains(&&idle));
+        assert_eq!(order[2..], [&busy, &busy]);
+        assert!(interleaved
+            .iter()
+            .all(|(_, chunk)| chunk.len() <= POI_REQUEST_CHUNK_SIZE));
+    }
+
+    #[tokio::test]
+    async fn proofs_of_indexing_are_written_as_live() {
+        use graphix_store::InMemoryStore;
+
+        use crate::test_utils::gen::{gen_blocks, gen_deployments};
+        use crate::test_utils::mocks::{DeploymentDetails, MockIndexer, PartialProofOfIndexing};
+
+        let deployment = gen_deployments()[0].clone();
+        let block = gen_blocks()[3].clone();
+        let indexer = |name: &str, poi_byte: u8| -> Arc<dyn IndexerClient> {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![DeploymentDetails {
+                    deployment: deployment.clone(),
+                    network: "mainnet".to_string(),
+                    latest_block: block.clone(),
+                    canonical_pois: vec![PartialProofOfIndexing {
+                        block: block.clone(),
+                        proof_of_indexing: [poi_byte; 32].into(),
+                    }],
+                    earliest_block_num: 0,
+                }],
+                fail_indexing_statuses: false,
+            })
+        };
+        let indexers = [indexer("foo", 1), indexer("bar", 2)];
+        let poi_requests = indexers
+            .iter()
+            .map(|indexer| {
+                let request = PoiRequest {
+                    deployment: deployment.clone(),
+                    block_number: block.number,
+                };
+                (indexer.clone(), vec![request])
+            })
+            .collect();
+
+        let store = InMemoryStore::new();
+        store.write_indexers(&indexers).await.unwrap();
+        let limits = IndexerRequestLimits::new(&Config::default());
+        let (pois, result) =
+            query_and_write_proofs_of_indexing(&store, &poi_requests, &limits).await;
+        result.unwrap();
+        assert_eq!(pois.len(), 2);
+
+        let live_pois = store.live_pois(None, None, None, None).await.unwrap();
+        assert_eq!(live_pois.len(), 2);
+        assert_ne!(live_pois[0].poi, live_pois[1].poi);
+    }
 }
@@ -0,0 +1,343 @@
+//! Fetching and parsing of subgraph manifests from IPFS. Manifests are
+//! fetched once per deployment and cached in the database.
+
+use anyhow::anyhow;
+use graphix_common_types::{IpfsCid, ManifestFlag};
+use graphix_store::models::{FetchedDataSource, FetchedSubgraphManifest};
+use graphix_store::Store;
+use serde::Deserialize;
+use serde_yaml::Value;
+use tracing::*;
+use url::Url;
+
+/// Upper limit on the number of manifests fetched per main loop iteration,
+/// so that a large backlog doesn't delay PoI collection.
+const MAX_MANIFESTS_PER_ITERATION: usize = 50;
+
+/// A client for the `cat` endpoint of an IPFS node's RPC API.
+#[derive(Debug, Clone)]
+pub struct IpfsClient {
+    url: Url,
+    client: reqwest::Client,
+}
+
+impl IpfsClient {
+    pub fn new(url: Url) -> Self {
+        Self {
+            url,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Returns the contents of the file with the given CID.
+    pub async fn cat(&self, cid: &IpfsCid) -> anyhow::Result<String> {
+        let mut url = self.url.join("api/v0/cat")?;
+        url.query_pairs_mut().append_pair("arg", &cid.to_string());
+
+        Ok(self
+            .client
+            .post(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SubgraphManifest {
+    spec_version: String,
+    #[serde(default)]
+    features: Vec<String>,
+    graft: Option<Graft>,
+    #[serde(default)]
+    data_sources: Vec<DataSource>,
+    #[serde(default)]
+    templates: Vec<DataSource>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Graft {
+    base: String,
+    block: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DataSource {
+    kind: String,
+    name: String,
+    network: Option<String>,
+    source: Option<DataSourceSource>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DataSourceSource {
+    address: Option<String>,
+    start_block: Option<i64>,
+}
+
+impl DataSource {
+    fn into_fetched(self, is_template: bool) -> FetchedDataSource {
+        let source = self.source.unwrap_or(DataSourceSource {
+            address: None,
+            start_block: None,
+        });
+        FetchedDataSource {
+            is_template,
+            kind: self.kind,
+            name: self.name,
+            network: self.network,
+            address: source.address,
+            start_block: source.start_block,
+        }
+    }
+}
+
+/// Parses the YAML contents of a subgraph manifest.
+pub fn parse_manifest(raw_manifest: &str) -> anyhow::Result<FetchedSubgraphManifest> {
+    let manifest: SubgraphManifest = serde_yaml::from_str(raw_manifest)
+        .map_err(|e| anyhow!("invalid subgraph manifest: {}", e))?;
+
+    let data_sources = manifest
+        .data_sources
+        .into_iter()
+        .map(|data_source| data_source.into_fetched(false))
+        .chain(
+            manifest
+                .templates
+                .into_iter()
+                .map(|template| template.into_fetched(true)),
+        )
+        .collect();
+
+    Ok(FetchedSubgraphManifest {
+        spec_version: manifest.spec_version,
+        features: manifest.features,
+        graft_base: manifest.graft.as_ref().map(|graft| graft.base.clone()),
+        graft_block: manifest.graft.map(|graft| graft.block),
+        raw_manifest: raw_manifest.to_string(),
+        data_sources,
+    })
+}
+
+/// Returns the features of a subgraph manifest that have historically been
+/// sources of non-determinism, in [`ManifestFlag`] declaration order.
+pub fn manifest_flags(raw_manifest: &str) -> anyhow::Result<Vec<ManifestFlag>> {
+    let manifest: Value = serde_yaml::from_str(raw_manifest)
+        .map_err(|e| anyhow!("invalid subgraph manifest: {}", e))?;
+
+    let has_feature = |feature: &str| {
+        manifest["features"]
+            .as_sequence()
+            .is_some_and(|features| features.iter().any(|f| f.as_str() == Some(feature)))
+    };
+    let data_sources: Vec<&Value> = ["dataSources", "templates"]
+        .iter()
+        .filter_map(|key| manifest[key].as_sequence())
+        .flatten()
+        .collect();
+    let any_data_source = |f: &dyn Fn(&Value) -> bool| data_sources.iter().any(|ds| f(ds));
+
+    let mut flags = vec![];
+    if has_feature("ipfsOnEthereumContracts") {
+        flags.push(ManifestFlag::IpfsOnEthereumContracts);
+    }
+    if has_feature("nonFatalErrors") {
+        flags.push(ManifestFlag::NonFatalErrors);
+    }
+    if !manifest["graft"].is_null() {
+        flags.push(ManifestFlag::Grafted);
+    }
+    if any_data_source(&|ds| {
+        ds["mapping"]["callHandlers"]
+            .as_sequence()
+            .is_some_and(|handlers| !handlers.is_empty())
+    }) {
+        flags.push(ManifestFlag::CallHandlers);
+    }
+    if any_data_source(&|ds| {
+        ds["mapping"]["blockHandlers"]
+            .as_sequence()
+            .is_some_and(|handlers| {
+                handlers
+                    .iter()
+                    .any(|handler| handler["filter"]["kind"].as_str() == Some("call"))
+            })
+    }) {
+        flags.push(ManifestFlag::BlockHandlerCallFilter);
+    }
+    if any_data_source(&|ds| {
+        ds["mapping"]["apiVersion"]
+            .as_str()
+            .is_some_and(is_legacy_api_version)
+    }) {
+        flags.push(ManifestFlag::LegacyApiVersion);
+    }
+    if any_data_source(&|ds| ds["kind"].as_str().is_some_and(|k| k.starts_with("file/"))) {
+        flags.push(ManifestFlag::FileDataSources);
+    }
+
+    Ok(flags)
+}
+
+fn is_legacy_api_version(api_version: &str) -> bool {
+    let parts: Option<Vec<u64>> = api_version.split('.').map(|p| p.parse().ok()).collect();
+    match parts.as_deref() {
+        Some([major, minor, patch]) => (*major, *minor, *patch) < (0, 0, 5),
+        _ => false,
+    }
+}
+
+/// Fetches and stores the manifests of the deployments that don't have one
+/// yet. Failures are logged and retried on the next call.
+#[instrument(skip_all)]
+pub async fn fetch_subgraph_manifests(store: &Store, ipfs: &IpfsClient) -> anyhow::Result<()> {
+    let deployments = store.sg_deployments_without_manifest().await?;
+
+    for deployment in deployments.iter().take(MAX_MANIFESTS_PER_ITERATION) {
+        let manifest = match ipfs.cat(deployment).await {
+            Ok(raw_manifest) => parse_manifest(&raw_manifest),
+            Err(err) => Err(err),
+        };
+
+        match manifest {
+            Ok(manifest) => store.write_subgraph_manifest(deployment, manifest).await?,
+            Err(error) => {
+                warn!(%deployment, %error, "Failed to fetch subgraph manifest");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_grafted_manifest() {
+        let manifest = parse_manifest(
+            r#"
+specVersion: 0.0.5
+features:
+  - grafting
+  - nonFatalErrors
+graft:
+  base: QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA
+  block: 100
+dataSources:
+  - kind: ethereum/contract
+    name: Factory
+    network: mainnet
+    source:
+      address: "0x1f98431c8ad98523631ae4a59f267346ea31f984"
+      abi: Factory
+      startBlock: 12369621
+    mapping:
+      kind: ethereum/events
+templates:
+  - kind: ethereum/contract
+    name: Pool
+    network: mainnet
+    source:
+      abi: Pool
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.spec_version, "0.0.5");
+        assert_eq!(manifest.features, vec!["grafting", "nonFatalErrors"]);
+        assert_eq!(
+            manifest.graft_base.as_deref(),
+            Some("QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA")
+        );
+        assert_eq!(manifest.graft_block, Some(100));
+        assert_eq!(
+            manifest.data_sources,
+            vec![
+                FetchedDataSource {
+                    is_template: false,
+                    kind: "ethereum/contract".to_string(),
+                    name: "Factory".to_string(),
+                    network: Some("mainnet".to_string()),
+                    address: Some("0x1f98431c8ad98523631ae4a59f267346ea31f984".to_string()),
+                    start_block: Some(12369621),
+                },
+                FetchedDataSource {
+                    is_template: true,
+                    kind: "ethereum/contract".to_string(),
+                    name: "Pool".to_string(),
+                    network: Some("mainnet".to_string()),
+                    address: None,
+                    start_block: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn flag_non_deterministic_manifest_features() {
+        let flags = manifest_flags(
+            r#"
+specVersion: 0.0.4
+features:
+  - ipfsOnEthereumContracts
+dataSources:
+  - kind: ethereum/contract
+    name: Token
+    mapping:
+      apiVersion: 0.0.4
+      blockHandlers:
+        - handler: handleBlock
+        - handler: handleBlockWithCall
+          filter:
+            kind: call
+templates:
+  - kind: file/ipfs
+    name: Metadata
+    mapping:
+      apiVersion: 0.0.7
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            flags,
+            vec![
+                ManifestFlag::IpfsOnEthereumContracts,
+                ManifestFlag::BlockHandlerCallFilter,
+                ManifestFlag::LegacyApiVersion,
+                ManifestFlag::FileDataSources,
+            ]
+        );
+
+        let flags = manifest_flags(
+            r#"
+specVersion: 0.0.5
+graft:
+  base: QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA
+  block: 100
+dataSources:
+  - kind: ethereum/contract
+    name: Token
+    mapping:
+      apiVersion: 0.0.6
+      eventHandlers:
+        - event: Transfer(address,address,uint256)
+          handler: handleTransfer
+"#,
+        )
+        .unwrap();
+        assert_eq!(flags, vec![ManifestFlag::Grafted]);
+    }
+
+    #[test]
+    fn reject_invalid_manifest() {
+        assert!(parse_manifest("dataSources: []").is_err());
+    }
+}
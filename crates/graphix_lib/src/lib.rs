@@ -1,15 +1,38 @@
+pub mod allocation_pois;
 pub mod bisect;
+pub mod bisection_engine;
 pub mod block_choice;
+pub mod block_verification;
+pub mod check_tiers;
 mod cli;
 pub mod config;
+pub mod daily_agreement;
+pub mod deployment_metadata;
+pub mod dispute_evidence;
+pub mod disputes;
+pub mod fork_tree;
 pub mod graphql_api;
 pub mod indexing_loop;
+pub mod ipfs;
+pub mod live_pois;
+#[cfg(feature = "loadtest")]
+pub mod loadtest;
+pub mod networks;
+pub mod notifications;
 mod prometheus_metrics;
+pub mod sharding;
+pub mod signing;
+pub mod sla;
+pub mod tls;
+pub mod uptime;
 
 #[cfg(feature = "tests")]
 pub mod test_utils;
 
-pub use cli::CliOptions;
-pub use prometheus_metrics::{metrics, PrometheusExporter, PrometheusMetrics};
+pub use cli::{CliOptions, Command, ConfigCommand, DbCommand, LogFormat, MigrationsCommand};
+pub use prometheus_metrics::{
+    metrics, metrics_router, run_pool_metrics, IndexerQueryKind, IndexerResponseTimes,
+    PrometheusExporter, PrometheusMetrics,
+};
 
 pub const GRAPHIX_VERSION: &str = env!("CARGO_PKG_VERSION");
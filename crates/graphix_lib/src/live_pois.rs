@@ -0,0 +1,23 @@
+//! Periodic recomputation of live PoIs, which can drift from the stored
+//! PoIs after pruning, backfills or manual imports.
+
+use std::time::Duration;
+
+use graphix_store::Store;
+use tracing::*;
+
+/// Drifted live PoIs are corrected at most this long after the fact.
+const RECOMPUTATION_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Recomputes live PoIs every [`RECOMPUTATION_INTERVAL`]. Corrections are
+/// logged by the store. Never returns.
+pub async fn run_live_poi_recomputation(store: Store) {
+    loop {
+        match store.recompute_live_pois(false).await {
+            Ok(report) => debug!(?report, "Recomputed live PoIs"),
+            Err(err) => error!(error = %err, "Failed to recompute live PoIs"),
+        }
+
+        tokio::time::sleep(RECOMPUTATION_INTERVAL).await;
+    }
+}
@@ -0,0 +1,255 @@
+//! `graphix loadtest`, which fills a database with synthetic indexers and
+//! PoIs, and measures how quickly the store writes them and answers API
+//! queries. It's meant for comparing changes to the store with numbers,
+//! never for production databases.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_graphql::{Request, Variables};
+use clap::Args;
+use graphix_common_types::inputs;
+use graphix_indexer_client::{IndexerClient, PoiRequest};
+use graphix_store::Store;
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::Serialize;
+use tokio::sync::watch;
+use tracing::info;
+
+use crate::config::Config;
+use crate::graphql_api::{api_schema_builder, ApiSchema, GraphixState, RequestState};
+use crate::indexing_loop::{query_and_write_proofs_of_indexing, IndexerRequestLimits};
+use crate::test_utils::gen::{gen_agreeing_indexers, gen_block, gen_synthetic_deployments};
+
+/// The API queries whose latency is measured, by name. `$indexer` is the
+/// address of a random synthetic indexer.
+const API_QUERIES: &[(&str, &str)] = &[
+    ("deployments", "{ deployments(limit: 250) { cid } }"),
+    ("indexers", "{ indexers(limit: 250) { address } }"),
+    (
+        "proofsOfIndexing",
+        "{ proofsOfIndexing(limit: 250) { hash block { number } deployment { cid } indexer { address } } }",
+    ),
+    (
+        "poiAgreementRatios",
+        "query ($indexer: HexString!) { poiAgreementRatios(indexerAddress: $indexer) { totalIndexers nAgreeingIndexers hasConsensus } }",
+    ),
+    (
+        "divergenceSummary",
+        "{ divergenceSummary { deploymentsChecked divergingDeployments affectedIndexers } }",
+    ),
+];
+
+#[derive(Args, Debug)]
+pub struct LoadtestOptions {
+    /// The number of synthetic indexers.
+    #[clap(long, default_value_t = 100)]
+    pub indexers: usize,
+    /// The number of synthetic subgraph deployments. Every indexer indexes
+    /// all of them.
+    #[clap(long, default_value_t = 50)]
+    pub deployments: usize,
+    /// The number of polling rounds. In each round, every indexer reports a
+    /// PoI for every deployment at a new block.
+    #[clap(long, default_value_t = 10)]
+    pub rounds: u64,
+    /// How often each API query is sent after every round.
+    #[clap(long, default_value_t = 10)]
+    pub api_queries_per_round: usize,
+    /// The probability of each PoI to diverge from those of other indexers.
+    #[clap(long, default_value_t = 0.05)]
+    pub divergence_rate: f64,
+    /// The seed of the generated data, so that runs are comparable.
+    #[clap(long, default_value_t = 0)]
+    pub seed: u64,
+}
+
+/// The results of a load test. Durations are in milliseconds.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadtestReport {
+    pub indexers: usize,
+    pub deployments: usize,
+    pub rounds: u64,
+    pub pois_written: usize,
+    pub pois_per_second: f64,
+    /// How long it took to write the PoIs of each round.
+    pub round_write_latency: Percentiles,
+    /// The latency of each query in [`API_QUERIES`], by name.
+    pub api_latency: BTreeMap<&'static str, Percentiles>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Percentiles {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Percentiles {
+    /// Nearest-rank percentiles of the given durations, in milliseconds.
+    fn new(mut durations: Vec<Duration>) -> Self {
+        durations.sort();
+        let percentile = |p: f64| {
+            let rank = (p * durations.len() as f64).ceil() as usize;
+            durations
+                .get(rank.saturating_sub(1))
+                .map(|duration| duration.as_nanos() as f64 / 1e6)
+                .unwrap_or_default()
+        };
+
+        Self {
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            max: percentile(1.0),
+        }
+    }
+}
+
+/// Runs a load test against `store`, which must not contain any indexers
+/// yet. The generated data is left in the database afterwards.
+pub async fn run_loadtest(
+    store: &Store,
+    options: &LoadtestOptions,
+) -> anyhow::Result<LoadtestReport> {
+    anyhow::ensure!(
+        (0.0..=1.0).contains(&options.divergence_rate),
+        "--divergence-rate must be between 0 and 1"
+    );
+    anyhow::ensure!(
+        options.indexers > 0 && options.deployments > 0 && options.rounds > 0,
+        "--indexers, --deployments and --rounds must be at least 1"
+    );
+    let existing_indexers = store
+        .indexers(inputs::IndexersQuery {
+            limit: Some(1),
+            include_archived: true,
+            ..Default::default()
+        })
+        .await?;
+    anyhow::ensure!(
+        existing_indexers.is_empty(),
+        "the database already contains indexers; load tests need a database of their own"
+    );
+
+    let mut rng = SmallRng::seed_from_u64(options.seed);
+    let deployments = gen_synthetic_deployments(options.deployments);
+    let blocks: Vec<_> = (1..=options.rounds).map(gen_block).collect();
+    let indexers = gen_agreeing_indexers(
+        &mut rng,
+        options.indexers,
+        &deployments,
+        &blocks,
+        options.divergence_rate,
+    );
+    store.write_indexers(&indexers).await?;
+
+    let (_config_sender, config_receiver) = watch::channel(Config::default());
+    let state = Arc::new(GraphixState::new(store.clone(), config_receiver));
+    let schema = api_schema_builder()
+        .data(RequestState::anonymous(state))
+        .finish();
+    let limits = IndexerRequestLimits::default();
+
+    let mut pois_written = 0;
+    let mut write_latencies = vec![];
+    let mut api_latencies: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for block in &blocks {
+        let poi_requests: HashMap<Arc<dyn IndexerClient>, Vec<PoiRequest>> = indexers
+            .iter()
+            .map(|indexer| {
+                let requests = deployments
+                    .iter()
+                    .map(|deployment| PoiRequest {
+                        deployment: deployment.clone(),
+                        block_number: block.number,
+                    })
+                    .collect();
+                (indexer.clone(), requests)
+            })
+            .collect();
+
+        let start = Instant::now();
+        let (pois, write_result) =
+            query_and_write_proofs_of_indexing(store, &poi_requests, &limits).await;
+        write_result?;
+        let write_latency = start.elapsed();
+        info!(
+            round = block.number,
+            pois = pois.len(),
+            elapsed_ms = write_latency.as_millis(),
+            "Wrote PoIs"
+        );
+        pois_written += pois.len();
+        write_latencies.push(write_latency);
+
+        for _ in 0..options.api_queries_per_round {
+            for (name, query) in API_QUERIES {
+                let indexer = indexers.choose(&mut rng).unwrap();
+                let latency = time_api_query(&schema, query, indexer.as_ref()).await?;
+                api_latencies.entry(*name).or_default().push(latency);
+            }
+        }
+    }
+
+    let write_seconds = write_latencies.iter().sum::<Duration>().as_secs_f64();
+    Ok(LoadtestReport {
+        indexers: options.indexers,
+        deployments: options.deployments,
+        rounds: options.rounds,
+        pois_written,
+        pois_per_second: pois_written as f64 / write_seconds,
+        round_write_latency: Percentiles::new(write_latencies),
+        api_latency: api_latencies
+            .into_iter()
+            .map(|(name, latencies)| (name, Percentiles::new(latencies)))
+            .collect(),
+    })
+}
+
+async fn time_api_query(
+    schema: &ApiSchema,
+    query: &str,
+    indexer: &dyn IndexerClient,
+) -> anyhow::Result<Duration> {
+    let variables = Variables::from_json(serde_json::json!({
+        "indexer": indexer.address_string(),
+    }));
+    let request = Request::new(query).variables(variables);
+
+    let start = Instant::now();
+    let response = schema.execute(request).await;
+    let latency = start.elapsed();
+    if let Some(error) = response.errors.first() {
+        anyhow::bail!("API query failed: {}", error.message);
+    }
+
+    Ok(latency)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        let durations = (1..=100).rev().map(Duration::from_millis).collect();
+
+        assert_eq!(
+            Percentiles::new(durations),
+            Percentiles {
+                p50: 50.0,
+                p90: 90.0,
+                p99: 99.0,
+                max: 100.0,
+            }
+        );
+        assert_eq!(Percentiles::new(vec![]), Percentiles::default());
+    }
+}
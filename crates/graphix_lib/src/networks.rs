@@ -0,0 +1,210 @@
+//! Normalization of the names that indexers report for chains. Indexers
+//! report e.g. `mainnet`, `ethereum` or `eip155:1` for the same chain, which
+//! would otherwise end up as separate networks, and keep PoIs of the same
+//! blocks from being compared.
+
+use std::collections::HashMap;
+
+use graphix_indexer_client::IndexingStatus;
+use tracing::*;
+
+use crate::config::ChainConfig;
+
+/// A well-known chain, under the name that `graph-node` uses for it.
+struct KnownNetwork {
+    name: &'static str,
+    caip2: &'static str,
+    aliases: &'static [&'static str],
+}
+
+/// Chains that are normalized even without any configuration, after The
+/// Graph's networks registry.
+const KNOWN_NETWORKS: &[KnownNetwork] = &[
+    KnownNetwork {
+        name: "mainnet",
+        caip2: "eip155:1",
+        aliases: &["ethereum", "eth", "ethereum-mainnet"],
+    },
+    KnownNetwork {
+        name: "sepolia",
+        caip2: "eip155:11155111",
+        aliases: &["ethereum-sepolia"],
+    },
+    KnownNetwork {
+        name: "holesky",
+        caip2: "eip155:17000",
+        aliases: &["ethereum-holesky"],
+    },
+    KnownNetwork {
+        name: "gnosis",
+        caip2: "eip155:100",
+        aliases: &["xdai"],
+    },
+    KnownNetwork {
+        name: "matic",
+        caip2: "eip155:137",
+        aliases: &["polygon", "polygon-mainnet"],
+    },
+    KnownNetwork {
+        name: "arbitrum-one",
+        caip2: "eip155:42161",
+        aliases: &["arbitrum", "arbitrum-mainnet"],
+    },
+    KnownNetwork {
+        name: "arbitrum-sepolia",
+        caip2: "eip155:421614",
+        aliases: &[],
+    },
+    KnownNetwork {
+        name: "optimism",
+        caip2: "eip155:10",
+        aliases: &["optimism-mainnet"],
+    },
+    KnownNetwork {
+        name: "base",
+        caip2: "eip155:8453",
+        aliases: &["base-mainnet"],
+    },
+    KnownNetwork {
+        name: "bsc",
+        caip2: "eip155:56",
+        aliases: &["bnb", "binance"],
+    },
+    KnownNetwork {
+        name: "avalanche",
+        caip2: "eip155:43114",
+        aliases: &["avax"],
+    },
+    KnownNetwork {
+        name: "celo",
+        caip2: "eip155:42220",
+        aliases: &[],
+    },
+    KnownNetwork {
+        name: "fantom",
+        caip2: "eip155:250",
+        aliases: &[],
+    },
+    KnownNetwork {
+        name: "linea",
+        caip2: "eip155:59144",
+        aliases: &[],
+    },
+    KnownNetwork {
+        name: "scroll",
+        caip2: "eip155:534352",
+        aliases: &[],
+    },
+    KnownNetwork {
+        name: "zksync-era",
+        caip2: "eip155:324",
+        aliases: &["zksync"],
+    },
+];
+
+/// Maps the names that indexers report for chains to a single name per
+/// chain. Configured chains take precedence over the built-in ones: a chain
+/// configured with the CAIP-2 ID of a well-known chain replaces its name.
+#[derive(Debug, Clone, Default)]
+pub struct NetworkAliases {
+    canonical_names: HashMap<String, String>,
+}
+
+impl NetworkAliases {
+    pub fn new(chains: &HashMap<String, ChainConfig>) -> Self {
+        let mut canonical_names = HashMap::new();
+        for network in KNOWN_NETWORKS {
+            let configured_name = chains
+                .iter()
+                .find(|(_, chain)| chain.caip2.as_deref() == Some(network.caip2))
+                .map(|(name, _)| name.as_str());
+            let name = configured_name.unwrap_or(network.name);
+            for alias in [network.name, network.caip2].iter().chain(network.aliases) {
+                canonical_names.insert(alias.to_string(), name.to_string());
+            }
+        }
+        // Configured chains override built-in names, and their explicit
+        // aliases override everything else.
+        for (name, chain) in chains {
+            canonical_names.insert(name.clone(), name.clone());
+            if let Some(caip2) = &chain.caip2 {
+                canonical_names.insert(caip2.clone(), name.clone());
+            }
+        }
+        for (name, chain) in chains {
+            for alias in &chain.aliases {
+                canonical_names.insert(alias.clone(), name.clone());
+            }
+        }
+
+        Self { canonical_names }
+    }
+
+    /// The name under which data about the given chain is stored. Unknown
+    /// names are kept as they are.
+    pub fn canonical<'a>(&'a self, network: &'a str) -> &'a str {
+        self.canonical_names
+            .get(network)
+            .map_or(network, String::as_str)
+    }
+
+    /// Replaces the network names in `indexing_statuses` with their
+    /// canonical ones.
+    pub fn normalize(&self, indexing_statuses: &mut [IndexingStatus]) {
+        for status in indexing_statuses {
+            let canonical = self.canonical(&status.network);
+            if canonical != status.network {
+                trace!(
+                    network = %status.network,
+                    canonical,
+                    deployment = %status.deployment,
+                    "Normalized network name"
+                );
+                status.network = canonical.to_string();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(caip2: Option<&str>, aliases: &[&str]) -> ChainConfig {
+        ChainConfig {
+            caip2: caip2.map(str::to_string),
+            aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+            speed: None,
+            block_explorer_url_template_for_block: None,
+            rpc_url: None,
+        }
+    }
+
+    #[test]
+    fn built_in_aliases() {
+        let aliases = NetworkAliases::new(&HashMap::new());
+        assert_eq!(aliases.canonical("mainnet"), "mainnet");
+        assert_eq!(aliases.canonical("ethereum"), "mainnet");
+        assert_eq!(aliases.canonical("eip155:1"), "mainnet");
+        assert_eq!(aliases.canonical("xdai"), "gnosis");
+        assert_eq!(aliases.canonical("some-appchain"), "some-appchain");
+    }
+
+    #[test]
+    fn configured_chains_take_precedence() {
+        let chains = HashMap::from([
+            ("ethereum".to_string(), chain(Some("eip155:1"), &[])),
+            (
+                "appchain".to_string(),
+                chain(None, &["appchain-mainnet", "xdai"]),
+            ),
+        ]);
+        let aliases = NetworkAliases::new(&chains);
+        assert_eq!(aliases.canonical("mainnet"), "ethereum");
+        assert_eq!(aliases.canonical("eip155:1"), "ethereum");
+        assert_eq!(aliases.canonical("eth"), "ethereum");
+        assert_eq!(aliases.canonical("appchain-mainnet"), "appchain");
+        assert_eq!(aliases.canonical("xdai"), "appchain");
+        assert_eq!(aliases.canonical("gnosis"), "gnosis");
+    }
+}
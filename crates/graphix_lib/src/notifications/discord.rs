@@ -0,0 +1,97 @@
+//! Discord integration through [incoming webhooks](https://discord.com/developers/docs/resources/webhook#execute-webhook).
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::*;
+use url::Url;
+
+use super::{involves_indexer_tags, is_in_tiers, DivergenceEvent};
+
+/// Posts a message to a Discord channel whenever a deployment starts or stops
+/// diverging.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscordConfig {
+    pub webhook_url: Url,
+    /// Only notify about deployments in these tiers. All deployments if
+    /// empty.
+    #[serde(default)]
+    pub tiers: Vec<String>,
+    /// Only notify about deployments that indexers with one of these tags
+    /// reported PoIs for. All deployments if empty.
+    #[serde(default)]
+    pub indexer_tags: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct DiscordNotifier {
+    config: DiscordConfig,
+    client: reqwest::Client,
+}
+
+impl DiscordNotifier {
+    pub fn new(config: &DiscordConfig) -> Self {
+        Self {
+            config: config.clone(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn notify(&self, event: &DivergenceEvent) -> anyhow::Result<()> {
+        let Some(body) = self.message_body(event) else {
+            return Ok(());
+        };
+
+        debug!(deployment = %event.deployment, "Sending Discord message");
+        self.client
+            .post(self.config.webhook_url.clone())
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn message_body(&self, event: &DivergenceEvent) -> Option<serde_json::Value> {
+        let notify = is_in_tiers(event, &self.config.tiers)
+            && involves_indexer_tags(event, &self.config.indexer_tags);
+        notify.then(|| {
+            json!({
+                "username": "Graphix",
+                "content": event.message(),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::DivergenceEventKind;
+
+    #[test]
+    fn messages_are_filtered_by_tier() {
+        let notifier = DiscordNotifier::new(&DiscordConfig {
+            webhook_url: "https://discord.com/api/webhooks/1/foo".parse().unwrap(),
+            tiers: vec!["critical".to_string()],
+            indexer_tags: vec![],
+        });
+        let event = |tier: Option<&str>| DivergenceEvent {
+            deployment: "QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA"
+                .parse()
+                .unwrap(),
+            tier: tier.map(str::to_string),
+            indexer_tags: Default::default(),
+            kind: DivergenceEventKind::Resolved,
+        };
+
+        assert!(notifier.message_body(&event(None)).is_none());
+        assert!(notifier.message_body(&event(Some("other"))).is_none());
+
+        let event = event(Some("critical"));
+        let body = notifier.message_body(&event).unwrap();
+        assert_eq!(body["content"], event.message());
+    }
+}
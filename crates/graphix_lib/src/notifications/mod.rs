@@ -0,0 +1,305 @@
+//! Notifications about PoI divergences, sent to external services:
+//!  1. [`DivergenceTracker`] turns the PoIs of each main loop iteration into
+//!     [`DivergenceEvent`]s whenever a deployment starts or stops diverging.
+//!  2. Each configured [`Notifier`] forwards those events to its service.
+
+mod discord;
+mod pagerduty;
+mod telegram;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use graphix_common_types::{IndexerAddress, IpfsCid, PoiBytes};
+use graphix_indexer_client::ProofOfIndexing;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::*;
+
+pub use self::discord::{DiscordConfig, DiscordNotifier};
+pub use self::pagerduty::{PagerDutyConfig, PagerDutyNotifier, PagerDutySeverity};
+pub use self::telegram::{TelegramConfig, TelegramNotifier};
+use crate::config::Config;
+
+/// A named group of subgraph deployments, which notifiers can treat
+/// differently, e.g. by alerting with a higher severity.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentTierConfig {
+    pub name: String,
+    /// IPFS hashes of the subgraph deployments in this tier.
+    pub deployments: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum NotifierConfig {
+    PagerDuty(PagerDutyConfig),
+    Discord(DiscordConfig),
+    Telegram(TelegramConfig),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DivergenceEventKind {
+    /// Indexers started disagreeing about the PoI of the deployment.
+    Diverged {
+        block_number: u64,
+        /// The number of distinct PoIs at `block_number`.
+        distinct_pois: usize,
+        indexers: usize,
+    },
+    /// All indexers agree about the PoI of the deployment again.
+    Resolved,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DivergenceEvent {
+    pub deployment: IpfsCid,
+    /// The name of the [`DeploymentTierConfig`] the deployment belongs to, if
+    /// any.
+    pub tier: Option<String>,
+    /// The tags of the indexers that reported PoIs for the deployment.
+    pub indexer_tags: BTreeSet<String>,
+    pub kind: DivergenceEventKind,
+}
+
+impl DivergenceEvent {
+    /// A short, human-readable description of the event.
+    pub fn summary(&self) -> String {
+        match &self.kind {
+            DivergenceEventKind::Diverged {
+                block_number,
+                distinct_pois,
+                indexers,
+            } => format!(
+                "PoI divergence for deployment {} at block {}: {} indexers reported {} distinct PoIs",
+                self.deployment, block_number, indexers, distinct_pois
+            ),
+            DivergenceEventKind::Resolved => format!(
+                "PoI divergence for deployment {} resolved: all indexers agree again",
+                self.deployment
+            ),
+        }
+    }
+
+    /// The chat message for the event, shared by all chat notifiers.
+    pub fn message(&self) -> String {
+        match &self.tier {
+            Some(tier) => format!("[{}] {}", tier, self.summary()),
+            None => self.summary(),
+        }
+    }
+}
+
+/// Whether the deployment of `event` belongs to one of `tiers`, or `tiers`
+/// is empty.
+fn is_in_tiers(event: &DivergenceEvent, tiers: &[String]) -> bool {
+    tiers.is_empty() || event.tier.as_ref().is_some_and(|tier| tiers.contains(tier))
+}
+
+/// Whether one of the indexers that reported PoIs for the deployment of
+/// `event` has one of `tags`, or `tags` is empty.
+fn involves_indexer_tags(event: &DivergenceEvent, tags: &[String]) -> bool {
+    tags.is_empty() || tags.iter().any(|tag| event.indexer_tags.contains(tag))
+}
+
+/// Keeps track of which deployments are currently diverging, across main
+/// loop iterations.
+#[derive(Debug, Default)]
+pub struct DivergenceTracker {
+    diverging: HashSet<IpfsCid>,
+}
+
+impl DivergenceTracker {
+    /// Compares the PoIs of a single main loop iteration and returns an event
+    /// for every deployment that started or stopped diverging. Deployments
+    /// without any PoIs keep their previous state. Events carry the tags of
+    /// the indexers that reported PoIs, looked up in `indexer_tags`.
+    pub fn observe(
+        &mut self,
+        pois: &[ProofOfIndexing],
+        config: &Config,
+        indexer_tags: &HashMap<IndexerAddress, BTreeSet<String>>,
+    ) -> Vec<DivergenceEvent> {
+        let mut pois_by_block: HashMap<&IpfsCid, BTreeMap<u64, Vec<PoiBytes>>> = HashMap::new();
+        let mut tags_by_deployment: HashMap<&IpfsCid, BTreeSet<String>> = HashMap::new();
+        for poi in pois {
+            pois_by_block
+                .entry(&poi.deployment)
+                .or_default()
+                .entry(poi.block.number)
+                .or_default()
+                .push(poi.proof_of_indexing);
+            if let Some(tags) = indexer_tags.get(&poi.indexer.address()) {
+                tags_by_deployment
+                    .entry(&poi.deployment)
+                    .or_default()
+                    .extend(tags.iter().cloned());
+            }
+        }
+
+        let mut events = vec![];
+        for (deployment, blocks) in pois_by_block {
+            let divergence = blocks.into_iter().find_map(|(block_number, pois)| {
+                let distinct_pois = pois.iter().collect::<HashSet<_>>().len();
+                (distinct_pois > 1).then_some(DivergenceEventKind::Diverged {
+                    block_number,
+                    distinct_pois,
+                    indexers: pois.len(),
+                })
+            });
+
+            let kind = match divergence {
+                Some(kind) if self.diverging.insert(deployment.clone()) => kind,
+                None if self.diverging.remove(deployment) => DivergenceEventKind::Resolved,
+                _ => continue,
+            };
+            events.push(DivergenceEvent {
+                deployment: deployment.clone(),
+                tier: config.deployment_tier(deployment).map(str::to_string),
+                indexer_tags: tags_by_deployment.remove(deployment).unwrap_or_default(),
+                kind,
+            });
+        }
+
+        events
+    }
+}
+
+/// A sink for [`DivergenceEvent`]s.
+#[derive(Debug)]
+pub enum Notifier {
+    PagerDuty(PagerDutyNotifier),
+    Discord(DiscordNotifier),
+    Telegram(TelegramNotifier),
+}
+
+impl Notifier {
+    pub fn from_config(config: &NotifierConfig) -> Self {
+        match config {
+            NotifierConfig::PagerDuty(config) => Self::PagerDuty(PagerDutyNotifier::new(config)),
+            NotifierConfig::Discord(config) => Self::Discord(DiscordNotifier::new(config)),
+            NotifierConfig::Telegram(config) => Self::Telegram(TelegramNotifier::new(config)),
+        }
+    }
+
+    pub async fn notify(&self, event: &DivergenceEvent) -> anyhow::Result<()> {
+        match self {
+            Self::PagerDuty(notifier) => notifier.notify(event).await,
+            Self::Discord(notifier) => notifier.notify(event).await,
+            Self::Telegram(notifier) => notifier.notify(event).await,
+        }
+    }
+}
+
+/// Sends all `events` to all notifiers configured in `config`. Failures are
+/// logged, but otherwise ignored.
+pub async fn send_notifications(config: &Config, events: &[DivergenceEvent]) {
+    if events.is_empty() {
+        return;
+    }
+
+    for notifier_config in &config.notifiers {
+        let notifier = Notifier::from_config(notifier_config);
+        for event in events {
+            if let Err(error) = notifier.notify(event).await {
+                warn!(
+                    deployment = %event.deployment,
+                    %error,
+                    "Failed to send divergence notification"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use graphix_indexer_client::{BlockPointer, IndexerClient};
+
+    use super::*;
+    use crate::test_utils::mocks::MockIndexer;
+
+    fn poi(deployment: &IpfsCid, block_number: u64, poi_byte: u8) -> ProofOfIndexing {
+        let indexer: Arc<dyn IndexerClient> = Arc::new(MockIndexer {
+            name: format!("indexer-{}", poi_byte),
+            deployment_details: vec![],
+            fail_indexing_statuses: false,
+        });
+        ProofOfIndexing {
+            indexer,
+            deployment: deployment.clone(),
+            block: BlockPointer {
+                number: block_number,
+                hash: None,
+            },
+            proof_of_indexing: [poi_byte; 32].into(),
+        }
+    }
+
+    #[test]
+    fn divergence_is_reported_once_until_resolved() {
+        let deployment: IpfsCid = "QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA"
+            .parse()
+            .unwrap();
+        let config = Config {
+            deployment_tiers: vec![DeploymentTierConfig {
+                name: "critical".to_string(),
+                deployments: vec![deployment.to_string()],
+            }],
+            ..Default::default()
+        };
+        let mut tracker = DivergenceTracker::default();
+        let no_tags = HashMap::new();
+
+        let agreeing = [poi(&deployment, 10, 1), poi(&deployment, 10, 1)];
+        let diverging = [poi(&deployment, 11, 1), poi(&deployment, 11, 2)];
+
+        assert!(tracker.observe(&agreeing, &config, &no_tags).is_empty());
+
+        let events = tracker.observe(&diverging, &config, &no_tags);
+        assert_eq!(
+            events,
+            vec![DivergenceEvent {
+                deployment: deployment.clone(),
+                tier: Some("critical".to_string()),
+                indexer_tags: BTreeSet::new(),
+                kind: DivergenceEventKind::Diverged {
+                    block_number: 11,
+                    distinct_pois: 2,
+                    indexers: 2,
+                },
+            }]
+        );
+        assert!(tracker.observe(&diverging, &config, &no_tags).is_empty());
+        assert!(tracker.observe(&[], &config, &no_tags).is_empty());
+
+        let events = tracker.observe(&agreeing, &config, &no_tags);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, DivergenceEventKind::Resolved);
+        assert!(tracker.observe(&agreeing, &config, &no_tags).is_empty());
+    }
+
+    #[test]
+    fn events_carry_indexer_tags() {
+        let deployment: IpfsCid = "QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA"
+            .parse()
+            .unwrap();
+        let pois = [poi(&deployment, 10, 1), poi(&deployment, 10, 2)];
+        let indexer_tags = HashMap::from([(
+            pois[1].indexer.address(),
+            BTreeSet::from(["upgrade-canary".to_string()]),
+        )]);
+
+        let events = DivergenceTracker::default().observe(&pois, &Config::default(), &indexer_tags);
+
+        assert_eq!(events.len(), 1);
+        assert!(involves_indexer_tags(&events[0], &[]));
+        assert!(involves_indexer_tags(
+            &events[0],
+            &["eu-west".to_string(), "upgrade-canary".to_string()]
+        ));
+        assert!(!involves_indexer_tags(&events[0], &["eu-west".to_string()]));
+    }
+}
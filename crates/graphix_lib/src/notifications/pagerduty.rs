@@ -0,0 +1,217 @@
+//! [PagerDuty Events API v2](https://developer.pagerduty.com/docs/events-api-v2/overview/)
+//! integration.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::*;
+use url::Url;
+
+use super::{involves_indexer_tags, DivergenceEvent, DivergenceEventKind};
+
+/// Opens a PagerDuty incident when a deployment diverges and resolves it once
+/// all indexers agree again. Only deployments with a severity, i.e. those
+/// considered high-stake, trigger incidents.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PagerDutyConfig {
+    /// The integration key of the PagerDuty service.
+    #[serde(default)]
+    pub routing_key: String,
+    /// A file to read `routingKey` from instead, e.g. a mounted Kubernetes
+    /// or Docker secret.
+    #[serde(default)]
+    pub routing_key_file: Option<PathBuf>,
+    /// Incident severity by deployment tier name.
+    #[serde(default)]
+    pub severities: HashMap<String, PagerDutySeverity>,
+    /// Incident severity for deployments without a tier, or whose tier isn't
+    /// listed in `severities`. Such deployments don't trigger incidents if
+    /// not set.
+    #[serde(default)]
+    pub default_severity: Option<PagerDutySeverity>,
+    /// Only trigger incidents for deployments that indexers with one of these
+    /// tags reported PoIs for. All deployments if empty.
+    #[serde(default)]
+    pub indexer_tags: Vec<String>,
+    #[serde(default = "PagerDutyConfig::default_events_api_url")]
+    pub events_api_url: Url,
+}
+
+impl PagerDutyConfig {
+    fn default_events_api_url() -> Url {
+        "https://events.pagerduty.com/v2/enqueue".parse().unwrap()
+    }
+
+    /// The incident severity for the given deployment tier, if any.
+    pub fn severity(&self, tier: Option<&str>) -> Option<PagerDutySeverity> {
+        tier.and_then(|tier| self.severities.get(tier).copied())
+            .or(self.default_severity)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum PagerDutySeverity {
+    Critical,
+    Error,
+    Warning,
+    Info,
+}
+
+impl PagerDutySeverity {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Critical => "critical",
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Info => "info",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PagerDutyNotifier {
+    config: PagerDutyConfig,
+    client: reqwest::Client,
+}
+
+impl PagerDutyNotifier {
+    pub fn new(config: &PagerDutyConfig) -> Self {
+        Self {
+            config: config.clone(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn notify(&self, event: &DivergenceEvent) -> anyhow::Result<()> {
+        let Some(body) = self.event_body(event) else {
+            return Ok(());
+        };
+
+        debug!(deployment = %event.deployment, "Sending PagerDuty event");
+        self.client
+            .post(self.config.events_api_url.clone())
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Builds the Events API request body for `event`, or returns [`None`] if
+    /// the deployment shouldn't trigger incidents.
+    fn event_body(&self, event: &DivergenceEvent) -> Option<serde_json::Value> {
+        if !involves_indexer_tags(event, &self.config.indexer_tags) {
+            return None;
+        }
+        let severity = self.config.severity(event.tier.as_deref())?;
+        // Triggering and resolving the same deduplication key opens and closes
+        // a single incident.
+        let dedup_key = format!("graphix-divergence-{}", event.deployment);
+
+        Some(match &event.kind {
+            DivergenceEventKind::Diverged {
+                block_number,
+                distinct_pois,
+                indexers,
+            } => json!({
+                "routing_key": self.config.routing_key,
+                "event_action": "trigger",
+                "dedup_key": dedup_key,
+                "payload": {
+                    "summary": event.summary(),
+                    "source": "graphix",
+                    "severity": severity.as_str(),
+                    "group": event.tier,
+                    "custom_details": {
+                        "deployment": event.deployment.to_string(),
+                        "block_number": block_number,
+                        "distinct_pois": distinct_pois,
+                        "indexers": indexers,
+                    },
+                },
+            }),
+            DivergenceEventKind::Resolved => json!({
+                "routing_key": self.config.routing_key,
+                "event_action": "resolve",
+                "dedup_key": dedup_key,
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> PagerDutyConfig {
+        serde_yaml::from_str(
+            r#"
+            routingKey: foo
+            severities:
+              critical: critical
+              important: warning
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn event(tier: Option<&str>, kind: DivergenceEventKind) -> DivergenceEvent {
+        DivergenceEvent {
+            deployment: "QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA"
+                .parse()
+                .unwrap(),
+            tier: tier.map(str::to_string),
+            indexer_tags: Default::default(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn severity_by_tier() {
+        let mut config = config();
+        assert_eq!(
+            config.severity(Some("critical")),
+            Some(PagerDutySeverity::Critical)
+        );
+        assert_eq!(
+            config.severity(Some("important")),
+            Some(PagerDutySeverity::Warning)
+        );
+        assert_eq!(config.severity(Some("other")), None);
+        assert_eq!(config.severity(None), None);
+
+        config.default_severity = Some(PagerDutySeverity::Info);
+        assert_eq!(config.severity(None), Some(PagerDutySeverity::Info));
+    }
+
+    #[test]
+    fn trigger_and_resolve_share_dedup_key() {
+        let notifier = PagerDutyNotifier::new(&config());
+        let diverged = DivergenceEventKind::Diverged {
+            block_number: 1,
+            distinct_pois: 2,
+            indexers: 3,
+        };
+
+        assert!(notifier
+            .event_body(&event(None, diverged.clone()))
+            .is_none());
+
+        let trigger = notifier
+            .event_body(&event(Some("critical"), diverged))
+            .unwrap();
+        let resolve = notifier
+            .event_body(&event(Some("critical"), DivergenceEventKind::Resolved))
+            .unwrap();
+        assert_eq!(trigger["event_action"], "trigger");
+        assert_eq!(trigger["payload"]["severity"], "critical");
+        assert_eq!(resolve["event_action"], "resolve");
+        assert_eq!(trigger["dedup_key"], resolve["dedup_key"]);
+    }
+}
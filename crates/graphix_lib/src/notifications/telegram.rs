@@ -0,0 +1,128 @@
+//! Telegram integration through the [Bot API](https://core.telegram.org/bots/api#sendmessage).
+
+use std::path::PathBuf;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::*;
+use url::Url;
+
+use super::{involves_indexer_tags, is_in_tiers, DivergenceEvent};
+
+/// Sends a message to a Telegram chat whenever a deployment starts or stops
+/// diverging. The bot must be a member of the chat.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TelegramConfig {
+    /// The token of the bot that sends the messages, as issued by
+    /// `@BotFather`.
+    #[serde(default)]
+    pub bot_token: String,
+    /// A file to read `botToken` from instead, e.g. a mounted Kubernetes or
+    /// Docker secret.
+    #[serde(default)]
+    pub bot_token_file: Option<PathBuf>,
+    /// The chat ID, or `@username` of a public channel.
+    pub chat_id: String,
+    /// Only notify about deployments in these tiers. All deployments if
+    /// empty.
+    #[serde(default)]
+    pub tiers: Vec<String>,
+    /// Only notify about deployments that indexers with one of these tags
+    /// reported PoIs for. All deployments if empty.
+    #[serde(default)]
+    pub indexer_tags: Vec<String>,
+    #[serde(default = "TelegramConfig::default_api_url")]
+    pub api_url: Url,
+}
+
+impl TelegramConfig {
+    fn default_api_url() -> Url {
+        "https://api.telegram.org".parse().unwrap()
+    }
+}
+
+#[derive(Debug)]
+pub struct TelegramNotifier {
+    config: TelegramConfig,
+    client: reqwest::Client,
+}
+
+impl TelegramNotifier {
+    pub fn new(config: &TelegramConfig) -> Self {
+        Self {
+            config: config.clone(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn notify(&self, event: &DivergenceEvent) -> anyhow::Result<()> {
+        let Some(body) = self.message_body(event) else {
+            return Ok(());
+        };
+
+        debug!(deployment = %event.deployment, "Sending Telegram message");
+        self.client
+            .post(self.send_message_url()?)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn send_message_url(&self) -> anyhow::Result<Url> {
+        // Bot tokens contain a colon, so they can't be joined as a relative
+        // URL.
+        let api_url = self.config.api_url.as_str().trim_end_matches('/');
+        Ok(format!("{}/bot{}/sendMessage", api_url, self.config.bot_token).parse()?)
+    }
+
+    fn message_body(&self, event: &DivergenceEvent) -> Option<serde_json::Value> {
+        let notify = is_in_tiers(event, &self.config.tiers)
+            && involves_indexer_tags(event, &self.config.indexer_tags);
+        notify.then(|| {
+            json!({
+                "chat_id": self.config.chat_id,
+                "text": event.message(),
+                "disable_web_page_preview": true,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::DivergenceEventKind;
+
+    #[test]
+    fn send_message_request() {
+        let notifier = TelegramNotifier::new(&TelegramConfig {
+            bot_token: "123:abc".to_string(),
+            bot_token_file: None,
+            chat_id: "@graphix".to_string(),
+            tiers: vec![],
+            indexer_tags: vec![],
+            api_url: TelegramConfig::default_api_url(),
+        });
+        let event = DivergenceEvent {
+            deployment: "QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA"
+                .parse()
+                .unwrap(),
+            tier: None,
+            indexer_tags: Default::default(),
+            kind: DivergenceEventKind::Resolved,
+        };
+
+        assert_eq!(
+            notifier.send_message_url().unwrap().as_str(),
+            "https://api.telegram.org/bot123:abc/sendMessage"
+        );
+        let body = notifier.message_body(&event).unwrap();
+        assert_eq!(body["chat_id"], "@graphix");
+        assert_eq!(body["text"], event.message());
+    }
+}
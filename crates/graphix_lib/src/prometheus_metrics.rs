@@ -1,14 +1,71 @@
-use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
-use std::sync::OnceLock;
+use std::net::SocketAddr;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use graphix_store::{PoolStatus, Store};
 // It's important to use the exported crate `prometheus_exporter::prometheus`
 // instead of `prometheus`, as different versions of that crate have
 // incompatible global registries.
 use prometheus_exporter::prometheus;
+use prometheus_exporter::prometheus::core::Collector;
+use prometheus_exporter::prometheus::Encoder;
+use sha2::{Digest, Sha256};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+/// Indexers can take a long time to respond to PoI requests for many
+/// deployments, so the default buckets are too fine-grained.
+const RESPONSE_TIME_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// How often the database connection pool metrics are updated.
+const POOL_METRICS_INTERVAL: Duration = Duration::from_secs(15);
+
+/// The kinds of requests to indexers whose response times are recorded.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, async_graphql::Enum)]
+pub enum IndexerQueryKind {
+    IndexingStatuses,
+    ProofsOfIndexing,
+    EntityChanges,
+}
+
+impl IndexerQueryKind {
+    pub const ALL: [Self; 3] = [
+        Self::IndexingStatuses,
+        Self::ProofsOfIndexing,
+        Self::EntityChanges,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::IndexingStatuses => "indexing_statuses",
+            Self::ProofsOfIndexing => "proofs_of_indexing",
+            Self::EntityChanges => "entity_changes",
+        }
+    }
+}
+
+/// Aggregated response times of an indexer for one kind of request, since
+/// Graphix started.
+#[derive(Debug, Clone, PartialEq, async_graphql::SimpleObject)]
+pub struct IndexerResponseTimes {
+    pub query: IndexerQueryKind,
+    pub count: u64,
+    pub average_seconds: f64,
+}
 
 pub struct PrometheusMetrics {
     pub indexing_statuses_requests: prometheus::IntCounterVec,
     pub public_proofs_of_indexing_requests: prometheus::IntCounterVec,
+    pub indexer_response_times: prometheus::HistogramVec,
+    pub rate_limited_requests: prometheus::IntCounterVec,
+    pub database_pool_connections: prometheus::IntGaugeVec,
+    pub database_pool_max_size: prometheus::IntGaugeVec,
+    pub database_pool_waiting: prometheus::IntGaugeVec,
+    pub database_pool_checkout_timeouts: prometheus::IntCounterVec,
 }
 
 static METRICS: OnceLock<PrometheusMetrics> = OnceLock::new();
@@ -37,34 +94,214 @@ impl PrometheusMetrics {
             )
             .unwrap();
 
+        let indexer_response_times = prometheus::register_histogram_vec_with_registry!(
+            "indexer_response_times",
+            "Response times of indexers in seconds",
+            &["indexer", "query"],
+            RESPONSE_TIME_BUCKETS.to_vec(),
+            registry
+        )
+        .unwrap();
+
+        let rate_limited_requests = prometheus::register_int_counter_vec_with_registry!(
+            "api_rate_limited_requests",
+            "Number of API requests rejected by rate limits",
+            &["limit"],
+            registry
+        )
+        .unwrap();
+
+        let database_pool_connections = prometheus::register_int_gauge_vec_with_registry!(
+            "database_pool_connections",
+            "Number of open database connections, by whether they're idle or in use",
+            &["pool", "state"],
+            registry
+        )
+        .unwrap();
+        let database_pool_max_size = prometheus::register_int_gauge_vec_with_registry!(
+            "database_pool_max_size",
+            "Maximum number of open database connections",
+            &["pool"],
+            registry
+        )
+        .unwrap();
+        let database_pool_waiting = prometheus::register_int_gauge_vec_with_registry!(
+            "database_pool_waiting",
+            "Number of callers waiting for a database connection",
+            &["pool"],
+            registry
+        )
+        .unwrap();
+        let database_pool_checkout_timeouts = prometheus::register_int_counter_vec_with_registry!(
+            "database_pool_checkout_timeouts",
+            "Number of times waiting for a database connection timed out",
+            &["pool"],
+            registry
+        )
+        .unwrap();
+
         Self {
             indexing_statuses_requests,
             public_proofs_of_indexing_requests,
+            indexer_response_times,
+            rate_limited_requests,
+            database_pool_connections,
+            database_pool_max_size,
+            database_pool_waiting,
+            database_pool_checkout_timeouts,
+        }
+    }
+
+    pub fn observe_pool_status(&self, pool: &str, status: &PoolStatus) {
+        let in_use = status.size.saturating_sub(status.idle);
+        self.database_pool_connections
+            .with_label_values(&[pool, "idle"])
+            .set(status.idle as i64);
+        self.database_pool_connections
+            .with_label_values(&[pool, "in_use"])
+            .set(in_use as i64);
+        self.database_pool_max_size
+            .with_label_values(&[pool])
+            .set(status.max_size as i64);
+        self.database_pool_waiting
+            .with_label_values(&[pool])
+            .set(status.waiting as i64);
+        // The store counts timeouts itself, so only the difference is added.
+        let timeouts = self
+            .database_pool_checkout_timeouts
+            .with_label_values(&[pool]);
+        timeouts.inc_by(status.checkout_timeouts.saturating_sub(timeouts.get()));
+    }
+
+    pub fn observe_response_time(&self, indexer: &str, query: IndexerQueryKind, time: Duration) {
+        self.indexer_response_times
+            .with_label_values(&[indexer, query.label()])
+            .observe(time.as_secs_f64());
+    }
+
+    /// Returns the response times recorded for the given indexer, for each
+    /// kind of request it has responded to.
+    pub fn response_times(&self, indexer: &str) -> Vec<IndexerResponseTimes> {
+        // Looking metrics up by label would create them if missing.
+        let metrics: Vec<_> = self
+            .indexer_response_times
+            .collect()
+            .into_iter()
+            .flat_map(|family| family.get_metric().to_vec())
+            .collect();
+
+        IndexerQueryKind::ALL
+            .into_iter()
+            .filter_map(|query| {
+                let metric = metrics.iter().find(|metric| {
+                    let label = |name: &str| {
+                        metric
+                            .get_label()
+                            .iter()
+                            .find(|pair| pair.get_name() == name)
+                            .map(|pair| pair.get_value())
+                    };
+                    label("indexer") == Some(indexer) && label("query") == Some(query.label())
+                })?;
+                let histogram = metric.get_histogram();
+                let count = histogram.get_sample_count();
+                (count > 0).then(|| IndexerResponseTimes {
+                    query,
+                    count,
+                    average_seconds: histogram.get_sample_sum() / count as f64,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Updates the connection pool metrics of `store`, labeled with `pool`, every
+/// [`POOL_METRICS_INTERVAL`]. Never returns.
+pub async fn run_pool_metrics(pool: &'static str, store: Store) {
+    loop {
+        metrics().observe_pool_status(pool, &store.pool_status());
+        tokio::time::sleep(POOL_METRICS_INTERVAL).await;
+    }
+}
+
+/// Serves the metrics of `registry` at `/metrics`, in the Prometheus text
+/// format. If `bearer_token` is given, requests must carry it in an
+/// `Authorization: Bearer` header.
+pub fn metrics_router(
+    registry: prometheus::Registry,
+    bearer_token: Option<String>,
+) -> axum::Router<()> {
+    let state = Arc::new(MetricsState {
+        registry,
+        bearer_token,
+    });
+
+    axum::Router::new()
+        .route("/metrics", axum::routing::get(metrics_handler))
+        .with_state(state)
+}
+
+struct MetricsState {
+    registry: prometheus::Registry,
+    bearer_token: Option<String>,
+}
+
+async fn metrics_handler(State(state): State<Arc<MetricsState>>, headers: HeaderMap) -> Response {
+    if let Some(token) = &state.bearer_token {
+        let provided = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        // Comparing digests doesn't leak how much of the token matched.
+        let authorized = provided.is_some_and(|provided| {
+            Sha256::digest(provided.as_bytes()) == Sha256::digest(token.as_bytes())
+        });
+        if !authorized {
+            return (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+            )
+                .into_response();
         }
     }
+
+    let mut buffer = vec![];
+    match prometheus::TextEncoder::new().encode(&state.registry.gather(), &mut buffer) {
+        Ok(()) => ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], buffer).into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
 }
 
 #[derive(Debug)]
 pub struct PrometheusExporter {
     binding: SocketAddr,
-    _exporter: prometheus_exporter::Exporter,
+    server: JoinHandle<()>,
 }
 
 impl PrometheusExporter {
-    /// Starts exporting Prometheus metrics at `http://0.0.0.0:{port}/metrics`. The server
-    /// will keep running until the returned [`PrometheusExporter`] is dropped.
-    pub fn start(port: u16, registry: prometheus::Registry) -> anyhow::Result<Self> {
-        let binding = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port));
-        let exporter = {
-            let mut builder = prometheus_exporter::Builder::new(binding);
-            builder.with_registry(registry);
-            builder.start()?
-        };
+    /// Starts exporting Prometheus metrics at `http://{address}/metrics`. The
+    /// server will keep running until the returned [`PrometheusExporter`] is
+    /// dropped. Must be called from within a Tokio runtime.
+    pub fn start(
+        address: SocketAddr,
+        registry: prometheus::Registry,
+        bearer_token: Option<String>,
+    ) -> anyhow::Result<Self> {
+        // Binding synchronously surfaces errors, e.g. a port that's in use,
+        // right away.
+        let listener = std::net::TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let binding = listener.local_addr()?;
+        let listener = TcpListener::from_std(listener)?;
 
-        Ok(Self {
-            binding,
-            _exporter: exporter,
-        })
+        let router = metrics_router(registry, bearer_token);
+        let server = tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, router).await {
+                error!(error = %err, "Prometheus exporter failed");
+            }
+        });
+
+        Ok(Self { binding, server })
     }
 
     /// Returns the port this Prometheus exporter is bound to.
@@ -73,15 +310,114 @@ impl PrometheusExporter {
     }
 }
 
+impl Drop for PrometheusExporter {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn response_times_per_indexer_and_query() {
+        let metrics = PrometheusMetrics::new(prometheus::Registry::new());
+        metrics.observe_response_time(
+            "0x01",
+            IndexerQueryKind::IndexingStatuses,
+            Duration::from_secs(1),
+        );
+        metrics.observe_response_time(
+            "0x01",
+            IndexerQueryKind::IndexingStatuses,
+            Duration::from_secs(3),
+        );
+        metrics.observe_response_time(
+            "0x02",
+            IndexerQueryKind::ProofsOfIndexing,
+            Duration::from_secs(5),
+        );
+
+        assert_eq!(
+            metrics.response_times("0x01"),
+            vec![IndexerResponseTimes {
+                query: IndexerQueryKind::IndexingStatuses,
+                count: 2,
+                average_seconds: 2.0,
+            }]
+        );
+        assert!(metrics.response_times("0x03").is_empty());
+    }
+
+    #[test]
+    fn pool_status() {
+        let metrics = PrometheusMetrics::new(prometheus::Registry::new());
+        let mut status = PoolStatus {
+            max_size: 10,
+            size: 4,
+            idle: 1,
+            waiting: 0,
+            checkout_timeouts: 2,
+        };
+        metrics.observe_pool_status("main", &status);
+        status.checkout_timeouts = 3;
+        metrics.observe_pool_status("main", &status);
+
+        let connections = |state| {
+            metrics
+                .database_pool_connections
+                .with_label_values(&["main", state])
+                .get()
+        };
+        assert_eq!(connections("idle"), 1);
+        assert_eq!(connections("in_use"), 3);
+        assert_eq!(
+            metrics
+                .database_pool_checkout_timeouts
+                .with_label_values(&["main"])
+                .get(),
+            3
+        );
+    }
+
     #[tokio::test]
     async fn server_is_alive() {
-        let exporter = PrometheusExporter::start(13370, prometheus::Registry::new()).unwrap();
+        let exporter = PrometheusExporter::start(
+            "0.0.0.0:13370".parse().unwrap(),
+            prometheus::Registry::new(),
+            None,
+        )
+        .unwrap();
         reqwest::get(&format!("http://0.0.0.0:{}/metrics", exporter.port()))
             .await
+            .unwrap()
+            .error_for_status()
             .unwrap();
     }
+
+    #[tokio::test]
+    async fn metrics_require_bearer_token() {
+        let exporter = PrometheusExporter::start(
+            "127.0.0.1:0".parse().unwrap(),
+            prometheus::Registry::new(),
+            Some("secret".to_string()),
+        )
+        .unwrap();
+        let url = format!("http://127.0.0.1:{}/metrics", exporter.port());
+        let status = |token: Option<&str>| {
+            let mut request = reqwest::Client::new().get(&url);
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            async move { request.send().await.unwrap().status() }
+        };
+
+        assert_eq!(status(None).await, reqwest::StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            status(Some("wrong")).await,
+            reqwest::StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(status(Some("secret")).await, reqwest::StatusCode::OK);
+    }
 }
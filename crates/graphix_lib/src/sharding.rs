@@ -0,0 +1,105 @@
+//! Splitting the cross-checking workload across several Graphix instances
+//! that share one database. Subgraph deployments are assigned to shards by
+//! hash, so that all PoIs of a deployment are still compared by the same
+//! instance.
+
+use graphix_common_types::IpfsCid;
+use graphix_indexer_client::IndexingStatus;
+use sha2::{Digest, Sha256};
+use tracing::*;
+
+/// The share of subgraph deployments that a Graphix instance cross-checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    index: u32,
+    count: u32,
+}
+
+impl Shard {
+    /// All deployments, i.e. an instance that isn't sharded.
+    pub const ALL: Shard = Shard { index: 0, count: 1 };
+
+    pub fn new(index: u32, count: u32) -> anyhow::Result<Self> {
+        anyhow::ensure!(count > 0, "the shard count must be positive");
+        anyhow::ensure!(
+            index < count,
+            "the shard index must be less than the shard count ({count})"
+        );
+
+        Ok(Self { index, count })
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Whether `deployment` is assigned to this shard. This only depends on
+    /// the deployment and the number of shards, so that all instances agree
+    /// on it.
+    pub fn contains(&self, deployment: &IpfsCid) -> bool {
+        let hash = Sha256::digest(deployment.to_string().as_bytes());
+        let hash = u64::from_be_bytes(hash[..8].try_into().unwrap());
+        hash % self.count as u64 == self.index as u64
+    }
+
+    /// Whether this shard is responsible for the work that isn't split
+    /// across shards, e.g. divergence investigations and uptime probes. Only
+    /// the first shard is, so that it's not done several times.
+    pub fn is_primary(&self) -> bool {
+        self.index == 0
+    }
+}
+
+impl Default for Shard {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl std::fmt::Display for Shard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+/// Restricts `indexing_statuses` to the subgraph deployments of `shard`.
+pub fn retain_shard_deployments(indexing_statuses: &mut Vec<IndexingStatus>, shard: Shard) {
+    if shard == Shard::ALL {
+        return;
+    }
+
+    indexing_statuses.retain(|status| shard.contains(&status.deployment));
+
+    debug!(
+        %shard,
+        indexing_statuses = indexing_statuses.len(),
+        "Restricted indexing statuses to shard"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::gen::gen_deployments;
+
+    #[test]
+    fn every_deployment_is_in_exactly_one_shard() {
+        let shards: Vec<Shard> = (0..3).map(|index| Shard::new(index, 3).unwrap()).collect();
+
+        for deployment in gen_deployments() {
+            let containing = shards
+                .iter()
+                .filter(|shard| shard.contains(&deployment))
+                .count();
+            assert_eq!(containing, 1);
+            assert!(Shard::ALL.contains(&deployment));
+        }
+    }
+
+    #[test]
+    fn invalid_shards() {
+        assert!(Shard::new(0, 0).is_err());
+        assert!(Shard::new(2, 2).is_err());
+        assert!(!Shard::new(1, 2).unwrap().is_primary());
+    }
+}
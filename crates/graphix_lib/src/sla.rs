@@ -0,0 +1,87 @@
+//! Aggregation of the data Graphix collects about an indexer into an
+//! SLA-style report, for data consumers choosing which indexers to use.
+
+use std::collections::HashMap;
+
+use graphix_store::models::{IntId, Poi};
+
+/// Returns how many of the other indexers agreed with the given indexer's
+/// PoIs on average, as a fraction between 0 and 1. Only the latest PoI of
+/// each indexer for a deployment and block is taken into account, and PoIs
+/// that no other indexer reported are left out. [`None`] if the indexer has
+/// no comparable PoIs in `pois`.
+pub fn average_agreement_ratio(indexer_id: IntId, pois: &[Poi]) -> Option<f64> {
+    let mut latest_pois: HashMap<_, HashMap<IntId, &Poi>> = HashMap::new();
+    for poi in pois {
+        latest_pois
+            .entry((poi.sg_deployment_id, poi.block_id))
+            .or_default()
+            .entry(poi.indexer_id)
+            .and_modify(|latest| {
+                if poi.created_at > latest.created_at {
+                    *latest = poi;
+                }
+            })
+            .or_insert(poi);
+    }
+
+    let ratios: Vec<f64> = latest_pois
+        .values()
+        .filter_map(|pois_by_indexer| {
+            let own_poi = pois_by_indexer.get(&indexer_id)?;
+            let others = pois_by_indexer.len() - 1;
+            if others == 0 {
+                return None;
+            }
+            let agreeing = pois_by_indexer
+                .iter()
+                .filter(|(id, poi)| **id != indexer_id && poi.poi == own_poi.poi)
+                .count();
+            Some(agreeing as f64 / others as f64)
+        })
+        .collect();
+
+    if ratios.is_empty() {
+        None
+    } else {
+        Some(ratios.iter().sum::<f64>() / ratios.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::*;
+
+    fn poi(indexer_id: IntId, block_id: i64, byte: u8, created_at: i64) -> Poi {
+        Poi {
+            id: 0,
+            poi: [byte; 32].into(),
+            sg_deployment_id: 1,
+            indexer_id,
+            block_id,
+            created_at: DateTime::from_timestamp(created_at, 0).unwrap().naive_utc(),
+        }
+    }
+
+    #[test]
+    fn agreement_with_other_indexers_is_averaged_over_blocks() {
+        let pois = [
+            // Block 1: one of two other indexers agrees.
+            poi(1, 1, 1, 0),
+            poi(2, 1, 1, 0),
+            poi(3, 1, 2, 0),
+            // Block 2: the only other indexer agrees with the latest PoI.
+            poi(1, 2, 3, 0),
+            poi(1, 2, 4, 1),
+            poi(2, 2, 4, 0),
+            // Block 3: no other indexer to compare with.
+            poi(1, 3, 5, 0),
+        ];
+
+        assert_eq!(average_agreement_ratio(1, &pois), Some(0.75));
+        assert_eq!(average_agreement_ratio(3, &pois), Some(0.0));
+        assert_eq!(average_agreement_ratio(4, &pois), None);
+    }
+}
@@ -1,11 +1,14 @@
 use std::iter::repeat_with;
 use std::sync::Arc;
 
-use graphix_common_types::{BlockHash, IpfsCid, PoiBytes};
+use cid::multihash::Multihash;
+use cid::Cid;
+use graphix_common_types::{IpfsCid, PoiBytes};
 use graphix_indexer_client::{BlockPointer, IndexerClient};
 use rand::distributions::Alphanumeric;
 use rand::seq::IteratorRandom;
 use rand::Rng;
+use sha2::{Digest, Sha256};
 use std::str::FromStr;
 
 use super::mocks::{DeploymentDetails, MockIndexer, PartialProofOfIndexing};
@@ -22,20 +25,35 @@ pub fn gen_deployments() -> Vec<IpfsCid> {
     .collect()
 }
 
-pub fn gen_blocks() -> Vec<BlockPointer> {
-    let block_hash = |n: u64| -> BlockHash {
-        let mut buf = [0u8; 32];
-        buf[24..32].clone_from_slice(&n.to_be_bytes());
-        buf.to_vec().into()
-    };
-    (0..10)
-        .map(|number| BlockPointer {
-            number,
-            hash: Some(block_hash(number)),
+/// Generates `count` distinct deployments with made-up IPFS CIDs, for when
+/// the handful of [`gen_deployments`] isn't enough.
+pub fn gen_synthetic_deployments(count: usize) -> Vec<IpfsCid> {
+    // The multicodec code of SHA2-256, which CIDv0 requires.
+    const SHA2_256: u64 = 0x12;
+
+    (0..count)
+        .map(|i| {
+            let digest = Sha256::digest(format!("synthetic-deployment-{i}"));
+            let hash = Multihash::wrap(SHA2_256, &digest).unwrap();
+            IpfsCid::from_str(&Cid::new_v0(hash).unwrap().to_string()).unwrap()
         })
         .collect()
 }
 
+/// A block whose hash is derived from its number.
+pub fn gen_block(number: u64) -> BlockPointer {
+    let mut hash = [0u8; 32];
+    hash[24..32].clone_from_slice(&number.to_be_bytes());
+    BlockPointer {
+        number,
+        hash: Some(hash.to_vec().into()),
+    }
+}
+
+pub fn gen_blocks() -> Vec<BlockPointer> {
+    (0..10).map(gen_block).collect()
+}
+
 pub fn gen_poi_bytes<R>(rng: &mut R) -> PoiBytes
 where
     R: Rng,
@@ -105,3 +123,77 @@ where
     .take(number_of_indexers)
     .collect()
 }
+
+/// Generates `number_of_indexers` indexers that all index every deployment up
+/// to the last of `blocks`, and mostly agree on their PoIs: each of their PoIs
+/// diverges from the canonical one with probability `divergence_rate`.
+pub fn gen_agreeing_indexers<R>(
+    rng: &mut R,
+    number_of_indexers: usize,
+    deployments: &[IpfsCid],
+    blocks: &[BlockPointer],
+    divergence_rate: f64,
+) -> Vec<Arc<dyn IndexerClient>>
+where
+    R: Rng,
+{
+    let names: Vec<_> = (0..number_of_indexers)
+        .map(|i| format!("indexer-{i:012}"))
+        .collect();
+
+    gen_mock_indexers(rng, &names, deployments, blocks, divergence_rate)
+        .into_iter()
+        .map(|indexer| Arc::new(indexer) as Arc<dyn IndexerClient>)
+        .collect()
+}
+
+/// Like [`gen_agreeing_indexers`], but with the given names, and returns the
+/// [`MockIndexer`]s themselves so that callers can make them deviate further.
+pub fn gen_mock_indexers<R>(
+    rng: &mut R,
+    names: &[String],
+    deployments: &[IpfsCid],
+    blocks: &[BlockPointer],
+    divergence_rate: f64,
+) -> Vec<MockIndexer>
+where
+    R: Rng,
+{
+    let canonical_pois: Vec<_> = deployments
+        .iter()
+        .map(|_| gen_pois(blocks.to_vec(), rng))
+        .collect();
+
+    names
+        .iter()
+        .map(|name| {
+            let deployment_details = deployments
+                .iter()
+                .zip(&canonical_pois)
+                .map(|(deployment, pois)| DeploymentDetails {
+                    deployment: deployment.clone(),
+                    network: "mainnet".into(),
+                    latest_block: blocks.last().unwrap().clone(),
+                    canonical_pois: pois
+                        .iter()
+                        .map(|poi| PartialProofOfIndexing {
+                            block: poi.block.clone(),
+                            proof_of_indexing: if rng.gen_bool(divergence_rate) {
+                                gen_poi_bytes(rng)
+                            } else {
+                                poi.proof_of_indexing
+                            },
+                        })
+                        .collect(),
+                    earliest_block_num: blocks[0].number,
+                })
+                .collect();
+
+            MockIndexer {
+                name: name.clone(),
+                deployment_details,
+                fail_indexing_statuses: false,
+            }
+        })
+        .collect()
+}
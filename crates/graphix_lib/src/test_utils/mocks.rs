@@ -1,13 +1,21 @@
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
 use std::sync::Arc;
 
 use anyhow::anyhow;
 use async_trait::async_trait;
-use graphix_common_types::{GraphNodeCollectedVersion, IndexerAddress, IpfsCid, PoiBytes};
+use graphix_common_types::{
+    GraphNodeCollectedVersion, IndexerAddress, IpfsCid, PoiBytes, SubgraphHealth,
+};
 use graphix_indexer_client::{
     BlockPointer, CachedEthereumCall, EntityChanges, IndexerClient, IndexingStatus, PoiRequest,
     ProofOfIndexing,
 };
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use super::gen::gen_poi_bytes;
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct DeploymentDetails {
@@ -25,6 +33,59 @@ pub struct MockIndexer {
     pub fail_indexing_statuses: bool,
 }
 
+impl MockIndexer {
+    /// Drops all PoIs after block `number`, as if the indexer lagged behind.
+    pub fn lag_behind(&mut self, number: u64) {
+        for details in &mut self.deployment_details {
+            details
+                .canonical_pois
+                .retain(|poi| poi.block.number <= number);
+            if let Some(poi) = details.canonical_pois.last() {
+                details.latest_block = poi.block.clone();
+            }
+        }
+    }
+
+    /// Replaces the PoIs of `deployments` (or of all deployments, if empty)
+    /// from block `number` on with random ones.
+    pub fn diverge<R: Rng>(&mut self, rng: &mut R, number: u64, deployments: &[IpfsCid]) {
+        for details in &mut self.deployment_details {
+            if !deployments.is_empty() && !deployments.contains(&details.deployment) {
+                continue;
+            }
+            for poi in &mut details.canonical_pois {
+                if poi.block.number >= number {
+                    poi.proof_of_indexing = gen_poi_bytes(rng);
+                }
+            }
+        }
+    }
+
+    /// Follows a fork for all `blocks`, with random block hashes and PoIs.
+    pub fn reorg<R: Rng>(&mut self, rng: &mut R, blocks: RangeInclusive<u64>) {
+        // Block hashes are shared by all deployments.
+        let mut forked_hashes = HashMap::new();
+        let mut fork = |block: &mut BlockPointer, rng: &mut R| {
+            if blocks.contains(&block.number) {
+                let hash = forked_hashes
+                    .entry(block.number)
+                    .or_insert_with(|| rng.gen::<[u8; 32]>().to_vec());
+                block.hash = Some(hash.clone().into());
+            }
+        };
+
+        for details in &mut self.deployment_details {
+            fork(&mut details.latest_block, rng);
+            for poi in &mut details.canonical_pois {
+                if blocks.contains(&poi.block.number) {
+                    fork(&mut poi.block, rng);
+                    poi.proof_of_indexing = gen_poi_bytes(rng);
+                }
+            }
+        }
+    }
+}
+
 #[async_trait]
 impl IndexerClient for MockIndexer {
     fn name(&self) -> Option<Cow<str>> {
@@ -32,9 +93,7 @@ impl IndexerClient for MockIndexer {
     }
 
     fn address(&self) -> IndexerAddress {
-        let mut addr = self.name.clone().into_bytes();
-        addr.resize(20, 0);
-        <[u8; 20]>::try_from(addr).unwrap().into()
+        address_from_name(&self.name)
     }
 
     async fn indexing_statuses(self: Arc<Self>) -> Result<Vec<IndexingStatus>, anyhow::Error> {
@@ -50,7 +109,10 @@ impl IndexerClient for MockIndexer {
                     deployment: details.deployment,
                     network: details.network,
                     latest_block: details.latest_block,
-                    earliest_block_num: details.earliest_block_num,
+                    earliest_block_num: Some(details.earliest_block_num),
+                    synced: true,
+                    health: SubgraphHealth::Healthy,
+                    entity_count: 0,
                 })
                 .collect())
         }
@@ -138,3 +200,10 @@ pub struct PartialProofOfIndexing {
     pub block: BlockPointer,
     pub proof_of_indexing: PoiBytes,
 }
+
+/// Derives a fake indexer address from the indexer's name. Hashed rather than
+/// truncated, so that names with a common prefix don't collide.
+pub fn address_from_name(name: &str) -> IndexerAddress {
+    let hash = Sha256::digest(name.as_bytes());
+    <[u8; 20]>::try_from(&hash[..20]).unwrap().into()
+}
@@ -55,6 +55,11 @@ pub fn test_indexer_from_url(url: impl Into<String>) -> Arc<impl IndexerClient>
         name: Some(url.host().unwrap().to_string()),
         address,
         index_node_endpoint: url.join("status").unwrap(),
+        auth: None,
+        proxy: None,
+        headers: Default::default(),
+        management_endpoint: None,
+        tags: vec![],
     };
     Arc::new(RealIndexer::new(
         conf.name,
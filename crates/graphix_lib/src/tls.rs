@@ -0,0 +1,118 @@
+//! TLS termination for the GraphQL API server, for deployments that can't or
+//! don't want to front Graphix with a reverse proxy.
+
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use axum_server::tls_rustls::RustlsConfig;
+use tracing::*;
+
+/// The certificate that the GraphQL API server is served with.
+#[derive(Debug, Clone)]
+pub struct TlsOptions {
+    /// Path to the PEM-encoded certificate chain.
+    pub cert_path: PathBuf,
+    /// Path to the PEM-encoded private key.
+    pub key_path: PathBuf,
+    /// How often to check the certificate and key files for changes, e.g.
+    /// after a renewal. The certificate is never reloaded if [`None`].
+    pub reload_interval: Option<Duration>,
+}
+
+impl TlsOptions {
+    /// Loads the certificate and, if enabled, spawns a task that reloads it
+    /// whenever the files change.
+    pub async fn load(&self) -> anyhow::Result<RustlsConfig> {
+        // `axum-server` doesn't pick a crypto provider by itself. Installing
+        // it only fails if one was installed already.
+        let _ = rustls::crypto::ring::default_provider().install_default();
+
+        let config = RustlsConfig::from_pem_file(&self.cert_path, &self.key_path).await?;
+        if let Some(interval) = self.reload_interval {
+            tokio::spawn(reload_on_change(config.clone(), self.clone(), interval));
+        }
+
+        Ok(config)
+    }
+
+    /// The time at which the certificate or key file was last modified.
+    fn modified_at(&self) -> Option<SystemTime> {
+        [&self.cert_path, &self.key_path]
+            .into_iter()
+            .filter_map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+            .max()
+    }
+}
+
+async fn reload_on_change(config: RustlsConfig, options: TlsOptions, interval: Duration) {
+    let mut loaded_modified_at = options.modified_at();
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let modified_at = options.modified_at();
+        if modified_at == loaded_modified_at {
+            continue;
+        }
+        // Failures are retried on the next check, e.g. if the files were only
+        // partially written.
+        match config
+            .reload_from_pem_file(&options.cert_path, &options.key_path)
+            .await
+        {
+            Ok(()) => {
+                info!(cert_path = %options.cert_path.display(), "Reloaded TLS certificate");
+                loaded_modified_at = modified_at;
+            }
+            Err(error) => {
+                warn!(cert_path = %options.cert_path.display(), %error, "Failed to reload TLS certificate");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr, TcpListener};
+
+    use super::*;
+    use crate::graphql_api::serve_api;
+
+    #[tokio::test]
+    async fn serve_over_https() {
+        let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let tls = TlsOptions {
+            cert_path: fixtures.join("localhost.crt"),
+            key_path: fixtures.join("localhost.key"),
+            reload_interval: None,
+        };
+        let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let router = axum::Router::new().route("/", axum::routing::get(|| async { "Hello" }));
+        tokio::spawn(async move {
+            serve_api(
+                SocketAddr::from((Ipv4Addr::LOCALHOST, port)),
+                router,
+                Some(&tls),
+            )
+            .await
+        });
+
+        let cert = std::fs::read(fixtures.join("localhost.crt")).unwrap();
+        let client = reqwest::Client::builder()
+            .add_root_certificate(reqwest::Certificate::from_pem(&cert).unwrap())
+            .build()
+            .unwrap();
+        let url = format!("https://localhost:{port}/");
+        for _ in 0..50 {
+            if let Ok(response) = client.get(&url).send().await {
+                assert_eq!(response.text().await.unwrap(), "Hello");
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("no HTTPS response");
+    }
+}
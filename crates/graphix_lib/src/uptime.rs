@@ -0,0 +1,123 @@
+//! Periodic probing of indexers' availability. Only changes between up and
+//! down are stored, from which uptime percentages are derived.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use graphix_indexer_client::{IndexerClient, IndexerId};
+use graphix_store::models::IndexerUptimeTransition;
+use graphix_store::Store;
+use tokio::sync::watch;
+use tracing::*;
+
+use crate::config::Config;
+
+/// Indexers that don't respond within this time are considered down.
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pings all indexers every `uptimeProbeIntervalInSeconds` and records
+/// whenever one of them goes up or down. Never returns.
+pub async fn run_uptime_prober(
+    store: Store,
+    indexers: watch::Receiver<Vec<Arc<dyn IndexerClient>>>,
+    config: watch::Receiver<Config>,
+) {
+    loop {
+        let indexers = indexers.borrow().clone();
+        if let Err(err) = probe_indexers(&store, &indexers).await {
+            error!(error = %err, "Failed to record indexer uptime");
+        }
+
+        let interval = config.borrow().uptime_probe_interval_in_seconds;
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+    }
+}
+
+/// Pings the given indexers, which must already exist in the database, and
+/// records which of them went up or down since the last probe.
+#[instrument(skip_all)]
+pub async fn probe_indexers(
+    store: &Store,
+    indexers: &[Arc<dyn IndexerClient>],
+) -> anyhow::Result<()> {
+    let results = indexers
+        .iter()
+        .map(|indexer| async move {
+            let up = matches!(
+                tokio::time::timeout(PING_TIMEOUT, indexer.clone().ping()).await,
+                Ok(Ok(()))
+            );
+            (indexer, up)
+        })
+        .collect::<FuturesUnordered<_>>()
+        .collect::<Vec<_>>()
+        .await;
+
+    for (indexer, up) in results {
+        if store.record_indexer_uptime(indexer.as_ref(), up).await? {
+            info!(indexer = %indexer.address_string(), up, "Indexer availability changed");
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the fraction of time between `since` and `now` during which the
+/// indexer was up, according to `transitions` (see
+/// [`Store::indexer_uptime_transitions`]). Time before the first transition
+/// is unknown and not taken into account; [`None`] if no time is known.
+pub fn uptime_ratio(
+    transitions: &[IndexerUptimeTransition],
+    since: NaiveDateTime,
+    now: NaiveDateTime,
+) -> Option<f64> {
+    let mut known = chrono::Duration::zero();
+    let mut up = chrono::Duration::zero();
+    for (i, transition) in transitions.iter().enumerate() {
+        let start = transition.observed_at.max(since);
+        let end = transitions
+            .get(i + 1)
+            .map_or(now, |next| next.observed_at)
+            .max(start);
+        known += end - start;
+        if transition.up {
+            up += end - start;
+        }
+    }
+
+    (known > chrono::Duration::zero())
+        .then(|| up.num_milliseconds() as f64 / known.num_milliseconds() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transition(up: bool, hours_ago: i64, now: NaiveDateTime) -> IndexerUptimeTransition {
+        IndexerUptimeTransition {
+            id: 0,
+            indexer_id: 1,
+            up,
+            observed_at: now - chrono::Duration::hours(hours_ago),
+        }
+    }
+
+    #[test]
+    fn uptime_within_window() {
+        let now = chrono::Utc::now().naive_utc();
+        let since = now - chrono::Duration::hours(24);
+
+        assert_eq!(uptime_ratio(&[], since, now), None);
+
+        // Up since before the window, then down for the last 6 hours.
+        let transitions = [transition(true, 48, now), transition(false, 6, now)];
+        assert_eq!(uptime_ratio(&transitions, since, now), Some(0.75));
+
+        // Only known for the last 12 hours, half of which it was up.
+        let transitions = [transition(false, 12, now), transition(true, 6, now)];
+        assert_eq!(uptime_ratio(&transitions, since, now), Some(0.5));
+    }
+}
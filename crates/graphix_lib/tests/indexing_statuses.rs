@@ -1,7 +1,11 @@
+use std::collections::HashSet;
+
 use futures::stream::FuturesUnordered;
 use futures::{future, StreamExt};
 use graphix_indexer_client::IndexingStatus;
-use graphix_lib::indexing_loop::query_indexing_statuses;
+use graphix_lib::indexing_loop::{
+    query_indexing_statuses, query_subgraph_api_versions, retain_tracked_deployments,
+};
 use graphix_lib::metrics;
 use graphix_lib::test_utils::fast_rng;
 use graphix_lib::test_utils::gen::*;
@@ -29,11 +33,53 @@ async fn indexing_statuses() {
             .flatten()
             .collect::<Vec<_>>();
 
-        let queried_statuses: Vec<IndexingStatus> = query_indexing_statuses(&indexers, metrics())
-            .await
-            .into_iter()
-            .collect();
+        let queried_statuses: Vec<IndexingStatus> =
+            query_indexing_statuses(&indexers, metrics(), &Default::default())
+                .await
+                .into_iter()
+                .collect();
 
         assert_eq!(expected_statuses, queried_statuses);
     }
 }
+
+#[tokio::test]
+async fn indexing_statuses_of_tracked_deployments() {
+    let mut rng = fast_rng(0);
+    let indexers = gen_indexers(&mut rng, 10);
+    let statuses = query_indexing_statuses(&indexers, metrics(), &Default::default()).await;
+
+    let mut all_statuses = statuses.clone();
+    retain_tracked_deployments(&mut all_statuses, &[]);
+    assert_eq!(all_statuses, statuses);
+
+    let Some(status) = statuses.first() else {
+        return;
+    };
+    let tracked = [(status.deployment.clone(), status.network.clone())];
+    let mut tracked_statuses = statuses.clone();
+    retain_tracked_deployments(&mut tracked_statuses, &tracked);
+    assert!(!tracked_statuses.is_empty());
+    assert!(tracked_statuses
+        .iter()
+        .all(|s| s.deployment == status.deployment && s.network == status.network));
+}
+
+#[tokio::test]
+async fn subgraph_api_versions_of_unknown_deployments() {
+    let mut rng = fast_rng(0);
+    let indexers = gen_indexers(&mut rng, 10);
+    let statuses = query_indexing_statuses(&indexers, metrics(), &Default::default()).await;
+    let deployments: HashSet<_> = statuses.iter().map(|s| s.deployment.clone()).collect();
+
+    let api_versions =
+        query_subgraph_api_versions(&statuses, &HashSet::new(), &Default::default()).await;
+    assert_eq!(
+        api_versions.keys().cloned().collect::<HashSet<_>>(),
+        deployments
+    );
+
+    let api_versions =
+        query_subgraph_api_versions(&statuses, &deployments, &Default::default()).await;
+    assert!(api_versions.is_empty());
+}
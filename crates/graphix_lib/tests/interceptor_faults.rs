@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+use graphix_indexer_client::{
+    FaultProfile, IndexerClient, IndexerInterceptor, MethodErrorRates, PoiRequest,
+};
+use graphix_lib::test_utils::fast_rng;
+use graphix_lib::test_utils::gen::{gen_blocks, gen_deployments, gen_pois};
+use graphix_lib::test_utils::mocks::{DeploymentDetails, MockIndexer};
+
+fn mock_indexer() -> Arc<MockIndexer> {
+    let mut rng = fast_rng(0);
+    let blocks = gen_blocks();
+
+    Arc::new(MockIndexer {
+        name: "mock".to_string(),
+        deployment_details: vec![DeploymentDetails {
+            deployment: gen_deployments()[0].clone(),
+            network: "mainnet".to_string(),
+            latest_block: blocks.last().unwrap().clone(),
+            canonical_pois: gen_pois(blocks, &mut rng),
+            earliest_block_num: 0,
+        }],
+        fail_indexing_statuses: false,
+    })
+}
+
+fn poi_requests() -> Vec<PoiRequest> {
+    gen_blocks()
+        .into_iter()
+        .map(|block| PoiRequest {
+            deployment: gen_deployments()[0].clone(),
+            block_number: block.number,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn no_faults_by_default() {
+    let target = mock_indexer();
+    let interceptor = Arc::new(IndexerInterceptor::new(target.clone(), None));
+
+    let expected = target.proofs_of_indexing(poi_requests()).await;
+    let actual = interceptor.clone().proofs_of_indexing(poi_requests()).await;
+
+    assert_eq!(expected.len(), actual.len());
+    for (expected, actual) in expected.iter().zip(actual.iter()) {
+        assert_eq!(expected.proof_of_indexing, actual.proof_of_indexing);
+    }
+    assert!(interceptor.indexing_statuses().await.is_ok());
+}
+
+#[tokio::test]
+async fn injected_errors() {
+    let faults = FaultProfile {
+        seed: Some(0),
+        error_rates: MethodErrorRates {
+            indexing_statuses: 1.0,
+            proofs_of_indexing: 1.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let interceptor = Arc::new(IndexerInterceptor::with_faults(
+        mock_indexer(),
+        None,
+        faults,
+    ));
+
+    assert!(interceptor.clone().indexing_statuses().await.is_err());
+    assert!(interceptor.clone().version().await.is_ok());
+    assert!(interceptor
+        .proofs_of_indexing(poi_requests())
+        .await
+        .is_empty());
+}
+
+#[tokio::test]
+async fn corrupted_pois() {
+    let target = mock_indexer();
+    let faults = FaultProfile {
+        seed: Some(0),
+        poi_corruption_rate: 1.0,
+        ..Default::default()
+    };
+    let interceptor = Arc::new(IndexerInterceptor::with_faults(
+        target.clone(),
+        None,
+        faults,
+    ));
+
+    let expected = target.proofs_of_indexing(poi_requests()).await;
+    let actual = interceptor.proofs_of_indexing(poi_requests()).await;
+
+    assert_eq!(expected.len(), actual.len());
+    for (expected, actual) in expected.iter().zip(actual.iter()) {
+        assert_ne!(expected.proof_of_indexing, actual.proof_of_indexing);
+    }
+}
+
+#[tokio::test]
+async fn stale_pois() {
+    let faults = FaultProfile {
+        seed: Some(0),
+        stale_poi_rate: 1.0,
+        ..Default::default()
+    };
+    let interceptor = Arc::new(IndexerInterceptor::with_faults(
+        mock_indexer(),
+        None,
+        faults,
+    ));
+
+    let pois = interceptor.proofs_of_indexing(poi_requests()).await;
+
+    // The first PoI is returned over and over again.
+    assert!(pois
+        .iter()
+        .all(|poi| poi.proof_of_indexing == pois[0].proof_of_indexing));
+}
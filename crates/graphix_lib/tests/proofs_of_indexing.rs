@@ -1,6 +1,7 @@
 use std::collections::BTreeSet;
 
 use graphix_lib::block_choice::BlockChoicePolicy;
+use graphix_lib::indexing_loop::IndexerRequestLimits;
 use graphix_lib::test_utils::fast_rng;
 use graphix_lib::test_utils::gen::gen_indexers;
 use graphix_lib::{indexing_loop, metrics};
@@ -14,9 +15,14 @@ async fn proofs_of_indexing() {
         let max_indexers = i;
         let indexers = gen_indexers(&mut rng, max_indexers as usize);
 
-        let indexing_statuses = indexing_loop::query_indexing_statuses(&indexers, metrics()).await;
-        let pois =
-            indexing_loop::query_proofs_of_indexing(indexing_statuses, BlockChoicePolicy::Earliest);
+        let limits = IndexerRequestLimits::default();
+        let indexing_statuses =
+            indexing_loop::query_indexing_statuses(&indexers, metrics(), &limits).await;
+        let pois = indexing_loop::query_proofs_of_indexing(
+            indexing_statuses,
+            BlockChoicePolicy::Earliest,
+            &limits,
+        );
 
         let actual_pois = pois.await.into_iter().collect::<BTreeSet<_>>();
 
@@ -1,27 +1,150 @@
 //! A indexer interceptor, for test configs only.
 
 use std::borrow::Cow;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use anyhow::anyhow;
 use async_trait::async_trait;
-use graphix_common_types::{GraphNodeCollectedVersion, IndexerAddress};
+use graphix_common_types::{GraphNodeCollectedVersion, IndexerAddress, IpfsCid, PoiBytes};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 use super::{CachedEthereumCall, EntityChanges};
-use crate::{IndexerClient, IndexingStatus, PoiRequest, ProofOfIndexing};
+use crate::{ActiveAllocation, IndexerClient, IndexingStatus, PoiRequest, ProofOfIndexing};
+
+/// Faults that an [`IndexerInterceptor`] injects into the requests it
+/// forwards, for soak-testing Graphix' resilience to misbehaving indexers.
+/// All rates are probabilities between 0 and 1.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FaultProfile {
+    /// Seeds the random number generator, for reproducible runs.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Each request is delayed by a random amount of milliseconds between
+    /// `minLatencyInMsecs` and `maxLatencyInMsecs`.
+    #[serde(default)]
+    pub min_latency_in_msecs: u64,
+    #[serde(default)]
+    pub max_latency_in_msecs: u64,
+    #[serde(default)]
+    pub error_rates: MethodErrorRates,
+    /// The rate at which a PoI previously returned for the same deployment is
+    /// returned instead of the fresh one.
+    #[serde(default)]
+    pub stale_poi_rate: f64,
+    /// The rate at which a single random byte of a PoI is flipped.
+    #[serde(default)]
+    pub poi_corruption_rate: f64,
+}
+
+/// Per-method error rates of a [`FaultProfile`]. A failed
+/// [`IndexerClient::proofs_of_indexing`] call returns no PoIs at all.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MethodErrorRates {
+    #[serde(default)]
+    pub ping: f64,
+    #[serde(default)]
+    pub indexing_statuses: f64,
+    #[serde(default)]
+    pub proofs_of_indexing: f64,
+    #[serde(default)]
+    pub version: f64,
+    /// Applies to all other methods.
+    #[serde(default)]
+    pub other: f64,
+}
 
 /// Pretends to be an indexer by routing requests a
 /// [`RealIndexer`](crate::indexer::RealIndexer) and then intercepting the
 /// responses to generate diverging Pois. The divergent pois will consist of a
-/// repetition of `poi_byte`. Interceptors have no [`IndexerClient::address`].
+/// repetition of `poi_byte`, if any. Additional faults can be injected
+/// according to a [`FaultProfile`]. Interceptors have no
+/// [`IndexerClient::address`].
 #[derive(Debug)]
 pub struct IndexerInterceptor {
     target: Arc<dyn IndexerClient>,
-    poi_byte: u8,
+    poi_byte: Option<u8>,
+    faults: FaultProfile,
+    rng: Mutex<SmallRng>,
+    last_pois: Mutex<HashMap<IpfsCid, PoiBytes>>,
 }
 
 impl IndexerInterceptor {
-    pub fn new(target: Arc<dyn IndexerClient>, poi_byte: u8) -> Self {
-        Self { target, poi_byte }
+    pub fn new(target: Arc<dyn IndexerClient>, poi_byte: Option<u8>) -> Self {
+        Self::with_faults(target, poi_byte, FaultProfile::default())
+    }
+
+    pub fn with_faults(
+        target: Arc<dyn IndexerClient>,
+        poi_byte: Option<u8>,
+        faults: FaultProfile,
+    ) -> Self {
+        let rng = match faults.seed {
+            Some(seed) => SmallRng::seed_from_u64(seed),
+            None => SmallRng::from_entropy(),
+        };
+
+        Self {
+            target,
+            poi_byte,
+            faults,
+            rng: Mutex::new(rng),
+            last_pois: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn happens(&self, rate: f64) -> bool {
+        rate > 0.0 && self.rng.lock().unwrap().gen_bool(rate.min(1.0))
+    }
+
+    /// Sleeps according to the configured latency, then fails with the given
+    /// error rate.
+    async fn inject_faults(&self, method: &str, error_rate: f64) -> anyhow::Result<()> {
+        let FaultProfile {
+            min_latency_in_msecs: min,
+            max_latency_in_msecs: max,
+            ..
+        } = self.faults;
+        if max > 0 {
+            let latency = self.rng.lock().unwrap().gen_range(min.min(max)..=max);
+            tokio::time::sleep(Duration::from_millis(latency)).await;
+        }
+
+        if self.happens(error_rate) {
+            Err(anyhow!("injected fault in `{}`", method))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn intercept_poi(&self, deployment: &IpfsCid, poi: PoiBytes) -> PoiBytes {
+        let mut poi = match self.poi_byte {
+            Some(poi_byte) => [poi_byte; 32].into(),
+            None => poi,
+        };
+
+        let mut last_pois = self.last_pois.lock().unwrap();
+        if self.happens(self.faults.stale_poi_rate) {
+            if let Some(last_poi) = last_pois.get(deployment) {
+                poi = *last_poi;
+            }
+        }
+        last_pois.insert(deployment.clone(), poi);
+
+        if self.happens(self.faults.poi_corruption_rate) {
+            let mut bytes = poi.0;
+            let i = self.rng.lock().unwrap().gen_range(0..bytes.len());
+            bytes[i] = !bytes[i];
+            poi = bytes.into();
+        }
+
+        poi
     }
 }
 
@@ -38,11 +161,23 @@ impl IndexerClient for IndexerInterceptor {
         self.target.address()
     }
 
+    fn endpoint(&self) -> Option<Cow<str>> {
+        self.target.endpoint()
+    }
+
     async fn ping(self: Arc<Self>) -> anyhow::Result<()> {
+        self.inject_faults("ping", self.faults.error_rates.ping)
+            .await?;
         self.target.clone().ping().await
     }
 
     async fn indexing_statuses(self: Arc<Self>) -> Result<Vec<IndexingStatus>, anyhow::Error> {
+        self.inject_faults(
+            "indexing_statuses",
+            self.faults.error_rates.indexing_statuses,
+        )
+        .await?;
+
         let statuses = self.target.clone().indexing_statuses().await?;
         let hijacked_statuses = statuses
             .into_iter()
@@ -52,12 +187,17 @@ impl IndexerClient for IndexerInterceptor {
                 network: status.network,
                 latest_block: status.latest_block,
                 earliest_block_num: status.earliest_block_num,
+                synced: status.synced,
+                health: status.health,
+                entity_count: status.entity_count,
             })
             .collect();
         Ok(hijacked_statuses)
     }
 
     async fn version(self: Arc<Self>) -> anyhow::Result<GraphNodeCollectedVersion> {
+        self.inject_faults("version", self.faults.error_rates.version)
+            .await?;
         self.target.clone().version().await
     }
 
@@ -65,17 +205,25 @@ impl IndexerClient for IndexerInterceptor {
         self: Arc<Self>,
         requests: Vec<PoiRequest>,
     ) -> Vec<ProofOfIndexing> {
+        if self
+            .inject_faults(
+                "proofs_of_indexing",
+                self.faults.error_rates.proofs_of_indexing,
+            )
+            .await
+            .is_err()
+        {
+            return vec![];
+        }
+
         let pois = self.target.clone().proofs_of_indexing(requests).await;
 
         pois.into_iter()
-            .map(|poi| {
-                let divergent_poi = [self.poi_byte; 32].into();
-                ProofOfIndexing {
-                    indexer: self.clone(),
-                    deployment: poi.deployment,
-                    block: poi.block,
-                    proof_of_indexing: divergent_poi,
-                }
+            .map(|poi| ProofOfIndexing {
+                indexer: self.clone(),
+                proof_of_indexing: self.intercept_poi(&poi.deployment, poi.proof_of_indexing),
+                deployment: poi.deployment,
+                block: poi.block,
             })
             .collect()
     }
@@ -84,6 +232,8 @@ impl IndexerClient for IndexerInterceptor {
         self: Arc<Self>,
         subgraph_id: &str,
     ) -> anyhow::Result<Vec<String>> {
+        self.inject_faults("subgraph_api_versions", self.faults.error_rates.other)
+            .await?;
         self.target.clone().subgraph_api_versions(subgraph_id).await
     }
 
@@ -92,6 +242,8 @@ impl IndexerClient for IndexerInterceptor {
         network: &str,
         block_hash: &[u8],
     ) -> anyhow::Result<Vec<CachedEthereumCall>> {
+        self.inject_faults("cached_eth_calls", self.faults.error_rates.other)
+            .await?;
         self.target
             .clone()
             .cached_eth_calls(network, block_hash)
@@ -103,6 +255,8 @@ impl IndexerClient for IndexerInterceptor {
         network: &str,
         block_hash: &[u8],
     ) -> anyhow::Result<Option<serde_json::Value>> {
+        self.inject_faults("block_cache_contents", self.faults.error_rates.other)
+            .await?;
         self.target
             .clone()
             .block_cache_contents(network, block_hash)
@@ -114,9 +268,17 @@ impl IndexerClient for IndexerInterceptor {
         subgraph_id: &str,
         block_number: u64,
     ) -> anyhow::Result<EntityChanges> {
+        self.inject_faults("entity_changes", self.faults.error_rates.other)
+            .await?;
         self.target
             .clone()
             .entity_changes(subgraph_id, block_number)
             .await
     }
+
+    async fn active_allocations(self: Arc<Self>) -> anyhow::Result<Option<Vec<ActiveAllocation>>> {
+        self.inject_faults("active_allocations", self.faults.error_rates.other)
+            .await?;
+        self.target.clone().active_allocations().await
+    }
 }
@@ -1,5 +1,8 @@
 mod interceptor;
+mod metrics;
+mod raw_responses;
 mod real_indexer;
+mod validation;
 
 use std::borrow::Cow;
 use std::collections::HashMap;
@@ -10,10 +13,12 @@ use std::sync::Arc;
 use anyhow::anyhow;
 use async_trait::async_trait;
 use graphix_common_types::{
-    BlockHash, GraphNodeCollectedVersion, IndexerAddress, IpfsCid, PoiBytes,
+    BlockHash, GraphNodeCollectedVersion, IndexerAddress, IpfsCid, PoiBytes, SubgraphHealth,
 };
-pub use interceptor::IndexerInterceptor;
-pub use real_indexer::RealIndexer;
+pub use interceptor::{FaultProfile, IndexerInterceptor, MethodErrorRates};
+pub use metrics::{indexer_label, limit_indexer_labels, OTHER_INDEXERS_LABEL};
+pub use raw_responses::{RawResponse, RawResponseCapture};
+pub use real_indexer::{IndexerAuth, RealIndexer};
 use serde::Serialize;
 
 /// An indexer is a `graph-node` instance that can be queried for information.
@@ -29,6 +34,12 @@ pub trait IndexerClient: Send + Sync + Debug {
     /// Human-readable name of the indexer.
     fn name(&self) -> Option<Cow<str>>;
 
+    /// The URL of the indexer's `graph-node` index-node endpoint, if it's
+    /// reached through one.
+    fn endpoint(&self) -> Option<Cow<str>> {
+        None
+    }
+
     async fn ping(self: Arc<Self>) -> anyhow::Result<()>;
 
     async fn indexing_statuses(self: Arc<Self>) -> anyhow::Result<Vec<IndexingStatus>>;
@@ -77,6 +88,12 @@ pub trait IndexerClient: Send + Sync + Debug {
         subgraph_id: &str,
         block_number: u64,
     ) -> anyhow::Result<EntityChanges>;
+
+    /// Returns the allocations that the indexer's `indexer-agent` reports as
+    /// active, or [`None`] if Graphix has no access to the agent.
+    async fn active_allocations(self: Arc<Self>) -> anyhow::Result<Option<Vec<ActiveAllocation>>> {
+        Ok(None)
+    }
 }
 
 /// Graphix defines an indexer's ID as either its Ethereum address (if it has
@@ -91,6 +108,12 @@ pub trait IndexerId {
     fn address_string(&self) -> String {
         self.address().to_string()
     }
+
+    /// Returns the value of the `indexer` label in metrics about this
+    /// indexer, which is either its address or [`OTHER_INDEXERS_LABEL`].
+    fn metrics_label(&self) -> String {
+        indexer_label(&self.address())
+    }
 }
 
 impl<T> IndexerId for T
@@ -175,6 +198,8 @@ pub struct EntityChanges {
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Ord, PartialOrd)]
 pub struct BlockPointer {
     pub number: u64,
+    /// Block hashes vary in length across chains, and chains that only
+    /// identify blocks by their height don't have any.
     pub hash: Option<BlockHash>,
 }
 
@@ -191,13 +216,29 @@ impl fmt::Display for BlockPointer {
     }
 }
 
+/// An allocation of an indexer that wasn't closed yet, as reported by the
+/// indexer itself rather than the network subgraph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveAllocation {
+    pub id: String,
+    pub deployment: IpfsCid,
+    pub created_at_epoch: u64,
+}
+
 #[derive(Debug, Clone, Eq)]
 pub struct IndexingStatus {
     pub indexer: Arc<dyn IndexerClient>,
     pub deployment: IpfsCid,
     pub network: String,
     pub latest_block: BlockPointer,
-    pub earliest_block_num: u64,
+    /// [`None`] if the indexer doesn't report it, which is common for
+    /// Substreams-powered subgraphs.
+    pub earliest_block_num: Option<u64>,
+    /// Whether the deployment has caught up with the chain head.
+    pub synced: bool,
+    pub health: SubgraphHealth,
+    /// The number of entities stored by the deployment.
+    pub entity_count: u64,
 }
 
 impl PartialEq for IndexingStatus {
@@ -0,0 +1,129 @@
+//! Prometheus metrics that indexer clients record themselves, in the default
+//! Prometheus registry.
+
+use std::collections::HashSet;
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+use graphix_common_types::IndexerAddress;
+
+/// The label that the metrics of all indexers without a label of their own
+/// share.
+pub const OTHER_INDEXERS_LABEL: &str = "other";
+
+static INDEXER_LABELS: RwLock<IndexerLabels> = RwLock::new(IndexerLabels { labeled: None });
+
+/// Which indexers are labeled with their own address in metrics. Labeling
+/// all of them makes for too many time series on big networks.
+#[derive(Debug, Default)]
+struct IndexerLabels {
+    /// All indexers are labeled individually if [`None`].
+    labeled: Option<HashSet<IndexerAddress>>,
+}
+
+impl IndexerLabels {
+    fn limit(&mut self, indexers: impl IntoIterator<Item = IndexerAddress>, limit: Option<usize>) {
+        self.labeled = limit.map(|limit| indexers.into_iter().take(limit).collect());
+    }
+
+    fn label(&self, indexer: &IndexerAddress) -> String {
+        match &self.labeled {
+            Some(labeled) if !labeled.contains(indexer) => OTHER_INDEXERS_LABEL.to_string(),
+            _ => indexer.to_string(),
+        }
+    }
+}
+
+/// Only labels the metrics of the first `limit` of `indexers` with their own
+/// address, if a limit is given. The metrics of all other indexers are
+/// aggregated under [`OTHER_INDEXERS_LABEL`].
+pub fn limit_indexer_labels(
+    indexers: impl IntoIterator<Item = IndexerAddress>,
+    limit: Option<usize>,
+) {
+    INDEXER_LABELS.write().unwrap().limit(indexers, limit);
+}
+
+/// The value of the `indexer` label in metrics about the given indexer.
+pub fn indexer_label(indexer: &IndexerAddress) -> String {
+    INDEXER_LABELS.read().unwrap().label(indexer)
+}
+
+/// Indexers answer in milliseconds when they have a response cached, but
+/// large PoI batches can take until the request times out.
+const REQUEST_DURATION_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0];
+
+struct RequestDurations {
+    public_pois: prometheus::HistogramVec,
+    indexing_statuses: prometheus::HistogramVec,
+    other: prometheus::HistogramVec,
+}
+
+impl RequestDurations {
+    fn register() -> Self {
+        let histogram = |name: &str, help: &str| {
+            prometheus::register_histogram_vec!(
+                name,
+                help,
+                &["indexer", "query"],
+                REQUEST_DURATION_BUCKETS.to_vec()
+            )
+            .unwrap()
+        };
+
+        Self {
+            public_pois: histogram(
+                "graphix_public_poi_request_duration_seconds",
+                "Durations of publicProofsOfIndexing requests to indexers, in seconds",
+            ),
+            indexing_statuses: histogram(
+                "graphix_indexing_statuses_request_duration_seconds",
+                "Durations of indexingStatuses requests to indexers, in seconds",
+            ),
+            other: histogram(
+                "graphix_indexer_request_duration_seconds",
+                "Durations of all other requests to indexers, e.g. while bisecting, in seconds",
+            ),
+        }
+    }
+}
+
+/// Records how long the given indexer took to answer a GraphQL request, or
+/// to fail to. `operation_name` is the name of the GraphQL operation, e.g.
+/// `ProofsOfIndexing`.
+pub fn observe_request_duration(
+    indexer: &IndexerAddress,
+    operation_name: &str,
+    duration: Duration,
+) {
+    static REQUEST_DURATIONS: OnceLock<RequestDurations> = OnceLock::new();
+
+    let durations = REQUEST_DURATIONS.get_or_init(RequestDurations::register);
+    let histogram = match operation_name {
+        "ProofsOfIndexing" => &durations.public_pois,
+        "IndexingStatuses" => &durations.indexing_statuses,
+        _ => &durations.other,
+    };
+    histogram
+        .with_label_values(&[&indexer_label(indexer), operation_name])
+        .observe(duration.as_secs_f64());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indexers_beyond_the_limit_share_a_label() {
+        let indexers: Vec<IndexerAddress> = (1..=3).map(|i| [i; 20].into()).collect();
+        let mut labels = IndexerLabels::default();
+        assert_eq!(labels.label(&indexers[2]), indexers[2].to_string());
+
+        labels.limit(indexers.clone(), Some(2));
+        assert_eq!(labels.label(&indexers[1]), indexers[1].to_string());
+        assert_eq!(labels.label(&indexers[2]), OTHER_INDEXERS_LABEL);
+
+        labels.limit(indexers.clone(), None);
+        assert_eq!(labels.label(&indexers[2]), indexers[2].to_string());
+    }
+}
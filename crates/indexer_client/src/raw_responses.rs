@@ -0,0 +1,78 @@
+//! Opt-in capture of the raw response bodies returned by indexers, so that
+//! parse failures and indexer-side bugs can be reproduced after the fact.
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use graphix_common_types::IndexerAddress;
+
+tokio::task_local! {
+    static CAPTURE: RawResponseCapture;
+}
+
+/// A raw GraphQL response body, exactly as an indexer returned it.
+#[derive(Debug, Clone)]
+pub struct RawResponse {
+    pub indexer_address: IndexerAddress,
+    pub indexer_name: Option<String>,
+    /// The name of the GraphQL operation, e.g. `ProofsOfIndexing`.
+    pub operation_name: String,
+    pub body: String,
+}
+
+/// Collects the [`RawResponse`]s of all indexer requests made within
+/// [`RawResponseCapture::scope`]. It uses [`Arc`] internally, so responses
+/// captured by a future that is dropped half-way through are still available.
+#[derive(Debug, Clone, Default)]
+pub struct RawResponseCapture {
+    responses: Arc<Mutex<Vec<RawResponse>>>,
+}
+
+impl RawResponseCapture {
+    /// Runs `future`, capturing the raw responses of all indexer requests it
+    /// makes on the current task.
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        CAPTURE.scope(self.clone(), future).await
+    }
+
+    /// Removes and returns all responses captured so far.
+    pub fn take(&self) -> Vec<RawResponse> {
+        std::mem::take(&mut *self.responses.lock().unwrap())
+    }
+}
+
+/// Records `response` if the current task runs within a
+/// [`RawResponseCapture::scope`], and does nothing otherwise.
+pub(crate) fn record_raw_response(response: impl FnOnce() -> RawResponse) {
+    let _ = CAPTURE.try_with(|capture| capture.responses.lock().unwrap().push(response()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(operation_name: &str) -> RawResponse {
+        RawResponse {
+            indexer_address: IndexerAddress::from([0; 20]),
+            indexer_name: None,
+            operation_name: operation_name.to_string(),
+            body: "{}".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn only_captures_within_scope() {
+        record_raw_response(|| response("Outside"));
+
+        let capture = RawResponseCapture::default();
+        capture
+            .scope(async { record_raw_response(|| response("Inside")) })
+            .await;
+        record_raw_response(|| response("Outside"));
+
+        let responses = capture.take();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].operation_name, "Inside");
+        assert!(capture.take().is_empty());
+    }
+}
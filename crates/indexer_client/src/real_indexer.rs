@@ -1,28 +1,85 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::{anyhow, Context};
 use async_trait::async_trait;
 use graphix_common_types::IndexerAddress;
-use graphql_client::{GraphQLQuery, Response};
+use graphql_client::{GraphQLQuery, QueryBody, Response};
+use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tracing::*;
 
 use super::{CachedEthereumCall, EntityChanges, IndexerClient};
+use crate::metrics::observe_request_duration;
+use crate::raw_responses::{record_raw_response, RawResponse};
+use crate::validation::{parse_poi, record_invalid_response, InvalidPoi};
 use crate::{
-    GraphNodeCollectedVersion, IndexerId, IndexingStatus, PoiRequest, ProofOfIndexing, WithIndexer,
+    ActiveAllocation, GraphNodeCollectedVersion, IndexerId, IndexingStatus, PoiRequest,
+    ProofOfIndexing, WithIndexer,
 };
 
 const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
 
+/// Credentials that a [`RealIndexer`] attaches to all requests, for index
+/// nodes whose status API is only exposed behind authentication.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum IndexerAuth {
+    /// An `Authorization: Bearer <token>` header.
+    Bearer {
+        #[serde(default)]
+        token: String,
+        /// A file to read `token` from instead, e.g. a mounted Kubernetes or
+        /// Docker secret.
+        #[serde(default, rename = "tokenFile")]
+        token_file: Option<PathBuf>,
+    },
+    /// HTTP basic authentication.
+    Basic {
+        username: String,
+        #[serde(default)]
+        password: Option<String>,
+        /// A file to read `password` from instead.
+        #[serde(default, rename = "passwordFile")]
+        password_file: Option<PathBuf>,
+    },
+}
+
+impl IndexerAuth {
+    fn authenticate(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self {
+            Self::Bearer { token, .. } => request.bearer_auth(token),
+            Self::Basic {
+                username, password, ..
+            } => request.basic_auth(username, password.as_ref()),
+        }
+    }
+}
+
+// Credentials must not end up in logs.
+impl fmt::Debug for IndexerAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bearer { .. } => f.write_str("Bearer(..)"),
+            Self::Basic { username, .. } => write!(f, "Basic({username}, ..)"),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct RealIndexer {
     address: IndexerAddress,
     name: Option<String>,
     endpoint: String,
+    management_endpoint: Option<String>,
     client: reqwest::Client,
+    auth: Option<IndexerAuth>,
+    headers: reqwest::header::HeaderMap,
     // Metrics
     // -------
     public_poi_requests: prometheus::IntCounterVec,
@@ -40,28 +97,95 @@ impl RealIndexer {
             name,
             address,
             endpoint,
+            management_endpoint: None,
             client: reqwest::Client::new(),
+            auth: None,
+            headers: Default::default(),
             public_poi_requests,
         }
     }
 
+    /// Sets the credentials that are attached to all requests to the index
+    /// node.
+    pub fn with_auth(mut self, auth: Option<IndexerAuth>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Sets static headers that are attached to all requests to the index
+    /// node, e.g. API keys required by a gateway in front of it. Header values
+    /// are treated as secrets and never logged.
+    pub fn with_headers(mut self, headers: &HashMap<String, String>) -> anyhow::Result<Self> {
+        for (name, value) in headers {
+            let name = reqwest::header::HeaderName::try_from(name.as_str())
+                .with_context(|| format!("invalid header name `{name}`"))?;
+            let mut value = reqwest::header::HeaderValue::try_from(value.as_str())
+                .with_context(|| format!("invalid value for header `{name}`"))?;
+            value.set_sensitive(true);
+            self.headers.insert(name, value);
+        }
+        Ok(self)
+    }
+
+    /// Sets the URL of the indexer management API of the indexer's
+    /// `indexer-agent`, which [`IndexerClient::active_allocations`] queries.
+    /// Credentials and headers for the index node aren't sent to it.
+    pub fn with_management_endpoint(mut self, endpoint: Option<&reqwest::Url>) -> Self {
+        self.management_endpoint = endpoint.map(|url| url.to_string());
+        self
+    }
+
+    /// Routes all requests to the index node through the given HTTP(S) proxy.
+    /// Without an explicit proxy, the `HTTP_PROXY`, `HTTPS_PROXY` and
+    /// `NO_PROXY` environment variables are honored.
+    pub fn with_proxy(mut self, proxy: Option<&reqwest::Url>) -> anyhow::Result<Self> {
+        if let Some(proxy) = proxy {
+            self.client = reqwest::Client::builder()
+                .proxy(reqwest::Proxy::all(proxy.clone())?)
+                .build()?;
+        }
+        Ok(self)
+    }
+
     /// Internal utility method to make a GraphQL query to the indexer. `error`
     /// and `data` fields are treated as mutually exclusive (which is generally
     /// a good assumption, but some callers may want more control over error
     /// handling).
-    async fn graphql_query<I: Serialize, O: DeserializeOwned>(
+    async fn graphql_query<V: Serialize, O: DeserializeOwned>(
         &self,
-        request: I,
+        query: QueryBody<V>,
     ) -> anyhow::Result<O> {
-        let response_raw = self
+        let mut request = self
             .client
             .post(self.endpoint.clone())
-            .timeout(REQUEST_TIMEOUT)
-            .json(&request)
-            .send()
-            .await?;
+            .headers(self.headers.clone())
+            .json(&query);
+        if let Some(auth) = &self.auth {
+            request = auth.authenticate(request);
+        }
+        self.send_graphql_query(request, query.operation_name).await
+    }
 
-        let response: Response<O> = response_raw.json().await?;
+    async fn send_graphql_query<O: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+        operation_name: &str,
+    ) -> anyhow::Result<O> {
+        let request = request.timeout(REQUEST_TIMEOUT);
+        let started_at = Instant::now();
+        // The body is read as text first so that it can be captured for
+        // debugging even if it fails to parse.
+        let body = async { request.send().await?.text().await }.await;
+        // Failed requests are timed too, as timeouts make up the tail.
+        observe_request_duration(&self.address, operation_name, started_at.elapsed());
+        let body = body?;
+        record_raw_response(|| RawResponse {
+            indexer_address: self.address,
+            indexer_name: self.name.clone(),
+            operation_name: operation_name.to_string(),
+            body: body.clone(),
+        });
+        let response: Response<O> = serde_json::from_str(&body)?;
 
         if let Some(errors) = response.errors {
             let errors = errors
@@ -95,12 +219,31 @@ impl RealIndexer {
 
         let response: ResponseData = self.graphql_query(request).await?;
 
-        // Parse POI results
-        response
-            .public_proofs_of_indexing
-            .into_iter()
-            .map(|result| WithIndexer::new(self.clone(), result).try_into())
-            .collect::<Result<Vec<_>, _>>()
+        // Parse POI results. Invalid ones are skipped, so that they don't
+        // take the rest of the batch down with them.
+        let mut pois = vec![];
+        for result in response.public_proofs_of_indexing {
+            let deployment = result.deployment.clone();
+            let poi: anyhow::Result<ProofOfIndexing> =
+                WithIndexer::new(self.clone(), result).try_into();
+            match poi {
+                Ok(poi) => pois.push(poi),
+                Err(error) => {
+                    let reason = error
+                        .downcast_ref::<InvalidPoi>()
+                        .map_or("malformed_poi", InvalidPoi::label);
+                    record_invalid_response(&self.metrics_label(), reason);
+                    warn!(
+                        indexer = %self.address_string(),
+                        %deployment,
+                        %error,
+                        "Indexer returned an invalid PoI, skipping it"
+                    );
+                }
+            }
+        }
+
+        Ok(pois)
     }
 }
 
@@ -114,6 +257,10 @@ impl IndexerClient for RealIndexer {
         self.name.as_ref().map(|s| Cow::Borrowed(s.as_str()))
     }
 
+    fn endpoint(&self) -> Option<Cow<str>> {
+        Some(Cow::Borrowed(self.endpoint.as_str()))
+    }
+
     async fn ping(self: Arc<Self>) -> anyhow::Result<()> {
         let request = gql_types::Typename::build_query(gql_types::typename::Variables);
         self.graphql_query::<_, serde_json::Value>(request).await?;
@@ -171,7 +318,7 @@ impl IndexerClient for RealIndexer {
             match result {
                 Ok(batch_pois) => {
                     self.public_poi_requests
-                        .get_metric_with_label_values(&[&self.address_string(), "1"])
+                        .get_metric_with_label_values(&[&self.metrics_label(), "1"])
                         .unwrap()
                         .inc();
 
@@ -179,7 +326,7 @@ impl IndexerClient for RealIndexer {
                 }
                 Err(error) => {
                     self.public_poi_requests
-                        .get_metric_with_label_values(&[&self.address_string(), "0"])
+                        .get_metric_with_label_values(&[&self.metrics_label(), "0"])
                         .unwrap()
                         .inc();
 
@@ -316,12 +463,41 @@ impl IndexerClient for RealIndexer {
 
         Ok(EntityChanges { updates, deletions })
     }
+
+    async fn active_allocations(self: Arc<Self>) -> anyhow::Result<Option<Vec<ActiveAllocation>>> {
+        let Some(endpoint) = &self.management_endpoint else {
+            return Ok(None);
+        };
+        let query =
+            gql_types::ActiveAllocations::build_query(gql_types::active_allocations::Variables);
+        let request = self.client.post(endpoint.clone()).json(&query);
+
+        let response: gql_types::active_allocations::ResponseData = self
+            .send_graphql_query(request, query.operation_name)
+            .await?;
+
+        let allocations = response
+            .allocations
+            .into_iter()
+            .map(|allocation| {
+                Ok(ActiveAllocation {
+                    deployment: allocation
+                        .subgraph_deployment
+                        .parse()
+                        .map_err(|e| anyhow!("invalid deployment: {}", e))?,
+                    created_at_epoch: allocation.created_at_epoch.try_into()?,
+                    id: allocation.id,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Some(allocations))
+    }
 }
 
 mod gql_types {
     use std::str::FromStr;
 
-    use graphix_common_types::{BlockHash, IpfsCid, PoiBytes};
+    use graphix_common_types::{BlockHash, IpfsCid, SubgraphHealth};
 
     use super::*;
     use crate::BlockPointer;
@@ -330,6 +506,13 @@ mod gql_types {
     pub type BigInt = String;
     pub type Bytes = String;
 
+    /// Parses a block hash of any length. Chains that only identify blocks by
+    /// their height report empty hashes, which are returned as [`None`].
+    pub fn parse_block_hash(s: &str) -> anyhow::Result<Option<BlockHash>> {
+        let hash = str::parse::<BlockHash>(s).map_err(|e| anyhow!("invalid block hash: {}", e))?;
+        Ok(Some(hash).filter(|hash| !hash.0.is_empty()))
+    }
+
     pub fn decode_bytes(s: &str) -> anyhow::Result<Vec<u8>> {
         if !s.starts_with("0x") {
             anyhow::bail!("hexstring must start with 0x");
@@ -368,26 +551,34 @@ mod gql_types {
                 .first()
                 .ok_or_else(|| anyhow!("chain status missing"))?;
 
-            let (latest_block, earliest_block_num) = match &chain.on {
-            indexing_statuses::IndexingStatusesIndexingStatusesChainsOn::EthereumIndexingStatus(
-                indexing_statuses::IndexingStatusesIndexingStatusesChainsOnEthereumIndexingStatus {
-                    latest_block,
-                    earliest_block,
-                    ..
-                },
-            ) => match (latest_block, earliest_block) {
-                (Some(block), Some(earliest_block)) => (BlockPointer {
-                    number: block.number.parse()?,
-                    hash: Some(str::parse::<BlockHash>(block.hash.as_str()).map_err(|e| anyhow!("invalid block hash: {}", e))?),
-                }, earliest_block.number.parse()?),
-                _ => {
-                    return Err(anyhow!("deployment has not started indexing yet"));
-                }
-            },
-        };
+            // Only the fields shared by all chain kinds are used, so that
+            // non-Ethereum and Substreams-powered deployments are supported
+            // too.
+            let latest_block = chain
+                .latest_block
+                .as_ref()
+                .ok_or_else(|| anyhow!("deployment has not started indexing yet"))?;
+            let latest_block = BlockPointer {
+                number: latest_block.number.parse()?,
+                hash: parse_block_hash(&latest_block.hash)?,
+            };
+            // Substreams-powered deployments often don't report it.
+            let earliest_block_num = chain
+                .earliest_block
+                .as_ref()
+                .map(|block| block.number.parse())
+                .transpose()?;
 
             let deployment = IpfsCid::from_str(&self.inner.subgraph)
                 .map_err(|e| anyhow!("invalid subgraph CID: {}", e))?;
+            let health = match &self.inner.health {
+                indexing_statuses::Health::healthy => SubgraphHealth::Healthy,
+                indexing_statuses::Health::unhealthy => SubgraphHealth::Unhealthy,
+                indexing_statuses::Health::failed => SubgraphHealth::Failed,
+                indexing_statuses::Health::Other(health) => {
+                    return Err(anyhow!("unknown subgraph health: {}", health));
+                }
+            };
 
             Ok(IndexingStatus {
                 indexer: self.indexer,
@@ -395,6 +586,9 @@ mod gql_types {
                 network: chain.network.clone(),
                 latest_block,
                 earliest_block_num,
+                synced: self.inner.synced,
+                health,
+                entity_count: self.inner.entity_count.parse()?,
             })
         }
     }
@@ -428,12 +622,12 @@ mod gql_types {
                         .inner
                         .block
                         .hash
-                        .map(|hash_string| str::parse::<BlockHash>(hash_string.as_str()))
-                        .transpose()
-                        .map_err(|e| anyhow!("invalid block hash: {}", e))?,
+                        .as_deref()
+                        .map(parse_block_hash)
+                        .transpose()?
+                        .flatten(),
                 },
-                proof_of_indexing: str::parse::<PoiBytes>(self.inner.proof_of_indexing.as_str())
-                    .map_err(|e| anyhow!("invalid PoI value: {}", e))?,
+                proof_of_indexing: parse_poi(&self.inner.proof_of_indexing)?,
             })
         }
     }
@@ -482,4 +676,284 @@ mod gql_types {
         variables_derives = "Debug"
     )]
     pub struct BlockData;
+
+    #[derive(GraphQLQuery)]
+    #[graphql(
+        schema_path = "graphql/indexer_management/schema.gql",
+        query_path = "graphql/indexer_management/queries/active-allocations.gql",
+        response_derives = "Debug",
+        variables_derives = "Debug"
+    )]
+    pub struct ActiveAllocations;
+}
+
+#[cfg(test)]
+mod tests {
+    use graphix_common_types::SubgraphHealth;
+
+    use super::*;
+
+    fn real_indexer(endpoint: &str) -> RealIndexer {
+        let public_poi_requests =
+            prometheus::IntCounterVec::new(prometheus::Opts::new("requests", "requests"), &["a"])
+                .unwrap();
+        RealIndexer::new(
+            None,
+            IndexerAddress::from([0; 20]),
+            endpoint.to_string(),
+            public_poi_requests,
+        )
+    }
+
+    fn indexer() -> Arc<dyn IndexerClient> {
+        Arc::new(real_indexer("http://localhost:8030/graphql"))
+    }
+
+    #[test]
+    fn auth_headers() {
+        let authorization = |auth: IndexerAuth| {
+            let request = auth
+                .authenticate(reqwest::Client::new().post("http://localhost:8030/graphql"))
+                .build()
+                .unwrap();
+            request.headers()[reqwest::header::AUTHORIZATION].clone()
+        };
+
+        assert_eq!(
+            authorization(IndexerAuth::Bearer {
+                token: "secret".to_string(),
+                token_file: None,
+            }),
+            "Bearer secret"
+        );
+        assert_eq!(
+            authorization(IndexerAuth::Basic {
+                username: "user".to_string(),
+                password: Some("pass".to_string()),
+                password_file: None,
+            }),
+            "Basic dXNlcjpwYXNz"
+        );
+        assert_eq!(
+            format!(
+                "{:?}",
+                IndexerAuth::Bearer {
+                    token: "secret".to_string(),
+                    token_file: None,
+                }
+            ),
+            "Bearer(..)"
+        );
+    }
+
+    #[test]
+    fn custom_headers() {
+        let headers = HashMap::from([("X-Api-Key".to_string(), "secret".to_string())]);
+        let indexer = real_indexer("http://localhost:8030/graphql")
+            .with_headers(&headers)
+            .unwrap();
+        assert_eq!(indexer.headers["x-api-key"], "secret");
+        assert!(!format!("{indexer:?}").contains("secret"));
+
+        let headers = HashMap::from([("X Api Key".to_string(), "secret".to_string())]);
+        assert!(real_indexer("http://localhost:8030/graphql")
+            .with_headers(&headers)
+            .is_err());
+    }
+
+    /// A bare-bones HTTP server that answers a single request with the given
+    /// JSON body, and returns the request line it received.
+    fn respond_once(body: String) -> (reqwest::Url, std::thread::JoinHandle<String>) {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            write!(
+                reader.get_mut(),
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+            request_line
+        });
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn requests_through_proxy() {
+        // The proxy answers the request itself.
+        let (proxy_url, proxied) = respond_once(r#"{"data":{"__typename":"Query"}}"#.to_string());
+
+        let indexer = real_indexer("http://indexer.invalid/status")
+            .with_proxy(Some(&proxy_url))
+            .unwrap();
+        Arc::new(indexer).ping().await.unwrap();
+
+        assert_eq!(
+            proxied.join().unwrap().trim_end(),
+            "POST http://indexer.invalid/status HTTP/1.1"
+        );
+    }
+
+    #[tokio::test]
+    async fn skip_invalid_pois() {
+        let poi = |poi: &str| {
+            serde_json::json!({
+                "deployment": "QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA",
+                "proofOfIndexing": poi,
+                "block": { "number": "42", "hash": null }
+            })
+        };
+        let body = serde_json::json!({
+            "data": {
+                "publicProofsOfIndexing": [
+                    poi(&format!("0x{}", "AB".repeat(32))),
+                    poi("0xabcd"),
+                    poi("not a poi"),
+                ]
+            }
+        });
+        let (url, _) = respond_once(body.to_string());
+
+        let pois = Arc::new(real_indexer(url.as_str()))
+            .proofs_of_indexing_batch(&[])
+            .await
+            .unwrap();
+        assert_eq!(pois.len(), 1);
+        assert_eq!(pois[0].proof_of_indexing.0, [0xab; 32]);
+    }
+
+    #[tokio::test]
+    async fn request_durations_are_recorded() {
+        let (url, _) = respond_once(r#"{"data":{"publicProofsOfIndexing":[]}}"#.to_string());
+        // A unique address, as other tests record durations too.
+        let address = IndexerAddress::from([0x17; 20]);
+        let indexer = RealIndexer {
+            address,
+            ..real_indexer(url.as_str())
+        };
+        Arc::new(indexer)
+            .proofs_of_indexing_batch(&[])
+            .await
+            .unwrap();
+
+        let family = prometheus::gather()
+            .into_iter()
+            .find(|family| family.get_name() == "graphix_public_poi_request_duration_seconds")
+            .unwrap();
+        let metric = family
+            .get_metric()
+            .iter()
+            .find(|metric| {
+                metric
+                    .get_label()
+                    .iter()
+                    .any(|pair| pair.get_value() == address.to_string())
+            })
+            .unwrap();
+        assert!(metric
+            .get_label()
+            .iter()
+            .any(|pair| pair.get_name() == "query" && pair.get_value() == "ProofsOfIndexing"));
+        assert_eq!(metric.get_histogram().get_sample_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn capture_unparsable_responses() {
+        let (url, _) = respond_once("not json".to_string());
+
+        let capture = crate::RawResponseCapture::default();
+        let result = capture
+            .scope(Arc::new(real_indexer(url.as_str())).ping())
+            .await;
+        assert!(result.is_err());
+
+        let responses = capture.take();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].operation_name, "Typename");
+        assert_eq!(responses[0].body, "not json");
+    }
+
+    #[tokio::test]
+    async fn active_allocations_from_management_api() {
+        assert_eq!(indexer().active_allocations().await.unwrap(), None);
+
+        let body = serde_json::json!({
+            "data": {
+                "allocations": [{
+                    "id": "0xa0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0",
+                    "subgraphDeployment": "QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA",
+                    "createdAtEpoch": 812
+                }]
+            }
+        });
+        let (url, request_line) = respond_once(body.to_string());
+        let indexer =
+            real_indexer("http://indexer.invalid/status").with_management_endpoint(Some(&url));
+
+        let allocations = Arc::new(indexer)
+            .active_allocations()
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(request_line.join().unwrap().starts_with("POST / "));
+        assert_eq!(allocations.len(), 1);
+        assert_eq!(allocations[0].created_at_epoch, 812);
+        assert_eq!(
+            allocations[0].deployment.to_string(),
+            "QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA"
+        );
+    }
+
+    #[test]
+    fn block_hashes_of_any_length() {
+        // NEAR and Cosmos use 32-byte hashes, Arweave 48-byte ones.
+        for len in [32, 48] {
+            let hash = format!("0x{}", "ab".repeat(len));
+            let parsed = gql_types::parse_block_hash(&hash).unwrap().unwrap();
+            assert_eq!(parsed.0.len(), len);
+        }
+        assert_eq!(gql_types::parse_block_hash("0x").unwrap(), None);
+        assert!(gql_types::parse_block_hash("0xzz").is_err());
+    }
+
+    #[test]
+    fn substreams_indexing_status_without_earliest_block() {
+        let response: gql_types::indexing_statuses::ResponseData =
+            serde_json::from_value(serde_json::json!({
+                "indexingStatuses": [{
+                    "subgraph": "QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA",
+                    "synced": false,
+                    "health": "unhealthy",
+                    "entityCount": "1337",
+                    "chains": [{
+                        "__typename": "EthereumIndexingStatus",
+                        "network": "mainnet",
+                        "latestBlock": { "number": "42", "hash": "0x0102" },
+                        "earliestBlock": null
+                    }]
+                }]
+            }))
+            .unwrap();
+
+        let status: IndexingStatus = WithIndexer::new(
+            indexer(),
+            response.indexing_statuses.into_iter().next().unwrap(),
+        )
+        .try_into()
+        .unwrap();
+        assert_eq!(status.network, "mainnet");
+        assert_eq!(status.latest_block.number, 42);
+        assert_eq!(status.earliest_block_num, None);
+        assert!(!status.synced);
+        assert_eq!(status.health, SubgraphHealth::Unhealthy);
+        assert_eq!(status.entity_count, 1337);
+    }
 }
@@ -0,0 +1,92 @@
+//! Validation of the values that indexers report, so that malformed responses
+//! are rejected in a single place and show up in metrics instead of being
+//! dropped silently.
+
+use std::fmt;
+use std::sync::OnceLock;
+
+use graphix_common_types::{HexString, PoiBytes};
+
+/// Why a PoI reported by an indexer was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidPoi {
+    NotHex,
+    WrongLength(usize),
+}
+
+impl InvalidPoi {
+    /// The label of the invalid response metric for this kind of PoI.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::NotHex => "poi_not_hex",
+            Self::WrongLength(_) => "poi_wrong_length",
+        }
+    }
+}
+
+impl fmt::Display for InvalidPoi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotHex => write!(f, "PoI is not a hex string"),
+            Self::WrongLength(len) => write!(f, "PoI is {len} bytes long instead of 32"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidPoi {}
+
+/// Parses a PoI as reported by an indexer. Surrounding whitespace, the casing
+/// of hex digits and the `0x` prefix are all normalized away.
+pub fn parse_poi(s: &str) -> Result<PoiBytes, InvalidPoi> {
+    let normalized = s.trim().to_ascii_lowercase();
+    let hex = normalized.strip_prefix("0x").unwrap_or(&normalized);
+    let bytes = hex::decode(hex).map_err(|_| InvalidPoi::NotHex)?;
+    let bytes =
+        <[u8; 32]>::try_from(bytes).map_err(|bytes| InvalidPoi::WrongLength(bytes.len()))?;
+
+    Ok(HexString(bytes))
+}
+
+/// Counts a response of the given indexer that was rejected for the given
+/// reason, in the default Prometheus registry.
+pub fn record_invalid_response(indexer: &str, reason: &str) {
+    static INVALID_RESPONSES: OnceLock<prometheus::IntCounterVec> = OnceLock::new();
+
+    INVALID_RESPONSES
+        .get_or_init(|| {
+            prometheus::register_int_counter_vec!(
+                "invalid_indexer_responses",
+                "Number of values in indexer responses that were rejected as invalid",
+                &["indexer", "reason"]
+            )
+            .unwrap()
+        })
+        .with_label_values(&[indexer, reason])
+        .inc();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_pois() {
+        let poi = parse_poi(&format!("0x{}", "ab".repeat(32))).unwrap();
+        assert_eq!(parse_poi(&format!(" 0X{} ", "AB".repeat(32))), Ok(poi));
+        assert_eq!(parse_poi(&"aB".repeat(32)), Ok(poi));
+    }
+
+    #[test]
+    fn reject_malformed_pois() {
+        assert_eq!(
+            parse_poi(&format!("0x{}", "zz".repeat(32))),
+            Err(InvalidPoi::NotHex)
+        );
+        assert_eq!(parse_poi("0xabc"), Err(InvalidPoi::NotHex));
+        assert_eq!(parse_poi("0x"), Err(InvalidPoi::WrongLength(0)));
+        assert_eq!(
+            parse_poi(&format!("0x{}", "ab".repeat(20))),
+            Err(InvalidPoi::WrongLength(20))
+        );
+    }
+}
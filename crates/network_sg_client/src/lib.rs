@@ -1,11 +1,12 @@
 #![allow(dead_code)]
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::anyhow;
-use graphix_common_types::IndexerAddress;
+use bigdecimal::BigDecimal;
+use graphix_common_types::{DisputeStatus, IndexerAddress, PoiBytes};
 use graphix_indexer_client::{IndexerClient as IndexerTrait, RealIndexer};
 use prometheus::IntCounterVec;
 use serde::de::DeserializeOwned;
@@ -27,6 +28,7 @@ pub struct NetworkSubgraphClient {
     endpoint: Url,
     timeout: Duration,
     client: reqwest::Client,
+    indexer_proxy: Option<Url>,
     // Metrics
     // -------
     public_poi_requests: IntCounterVec,
@@ -39,6 +41,7 @@ impl NetworkSubgraphClient {
             endpoint,
             timeout: DEFAULT_TIMEOUT,
             client: reqwest::Client::new(),
+            indexer_proxy: None,
             public_poi_requests,
         }
     }
@@ -51,6 +54,13 @@ impl NetworkSubgraphClient {
         self
     }
 
+    /// Sets the HTTP(S) proxy that the indexers found through the network
+    /// subgraph are queried through. See [`RealIndexer::with_proxy`].
+    pub fn with_indexer_proxy(mut self, proxy: Option<Url>) -> Self {
+        self.indexer_proxy = proxy;
+        self
+    }
+
     pub async fn indexers_by_staked_tokens(&self) -> anyhow::Result<Vec<Arc<dyn IndexerTrait>>> {
         let response_data: GraphqlResponseTopIndexers = self
             .graphql_query_no_errors(
@@ -66,7 +76,8 @@ impl NetworkSubgraphClient {
             let real_indexer = indexer_allocation_data_to_real_indexer(
                 IndexerAllocation { indexer },
                 self.public_poi_requests.clone(),
-            );
+            )
+            .and_then(|indexer| indexer.with_proxy(self.indexer_proxy.as_ref()));
 
             match real_indexer {
                 Ok(indexer) => indexers.push(Arc::new(indexer)),
@@ -105,7 +116,8 @@ impl NetworkSubgraphClient {
                     address,
                     Url::parse(&format!("{}/status", url))?.to_string(),
                     self.public_poi_requests.clone(),
-                );
+                )
+                .with_proxy(self.indexer_proxy.as_ref())?;
                 indexer_clients.push(Arc::new(real_indexer));
             }
         }
@@ -118,7 +130,7 @@ impl NetworkSubgraphClient {
     pub async fn indexer_by_address(
         &self,
         address: &IndexerAddress,
-    ) -> anyhow::Result<Arc<dyn IndexerTrait>> {
+    ) -> anyhow::Result<RealIndexer> {
         let hex_encoded_addr_json = serde_json::to_value(address).unwrap();
         let response_data: ResponseData = self
             .graphql_query_no_errors(
@@ -151,9 +163,10 @@ impl NetworkSubgraphClient {
             *address,
             Url::parse(&format!("{}/status", indexer_data.url))?.to_string(),
             self.public_poi_requests.clone(),
-        );
+        )
+        .with_proxy(self.indexer_proxy.as_ref())?;
 
-        Ok(Arc::new(indexer))
+        Ok(indexer)
     }
 
     /// Returns all subgraph deployments, ordered by curation signal amounts.
@@ -174,6 +187,266 @@ impl NetworkSubgraphClient {
         Ok(subgraph_deployments)
     }
 
+    /// Returns the display metadata and curation signal of the subgraph
+    /// deployments with the given IPFS hashes. Deployments that aren't
+    /// published on the network are left out.
+    pub async fn subgraph_deployments_metadata(
+        &self,
+        ipfs_hashes: &[String],
+    ) -> anyhow::Result<Vec<SubgraphDeploymentMetadata>> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ResponseData {
+            subgraph_deployments: Vec<DeploymentData>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct DeploymentData {
+            ipfs_hash: String,
+            signal_amount: BigDecimal,
+            versions: Vec<VersionData>,
+        }
+
+        #[derive(Deserialize)]
+        struct VersionData {
+            subgraph: SubgraphData,
+        }
+
+        #[derive(Deserialize)]
+        struct SubgraphData {
+            metadata: Option<MetadataData>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct MetadataData {
+            display_name: Option<String>,
+            image: Option<String>,
+        }
+
+        let mut deployments = vec![];
+        for ipfs_hashes in ipfs_hashes.chunks(PAGINATION_SIZE) {
+            let response_data: ResponseData = self
+                .graphql_query_no_errors(
+                    queries::DEPLOYMENTS_METADATA_QUERY,
+                    vec![
+                        ("ipfsHashes".to_string(), serde_json::to_value(ipfs_hashes)?),
+                        ("first".to_string(), PAGINATION_SIZE.into()),
+                    ],
+                    "error(s) querying deployment metadata from the network subgraph",
+                )
+                .await?;
+            deployments.extend(
+                response_data
+                    .subgraph_deployments
+                    .into_iter()
+                    .map(|deployment| {
+                        let metadata = deployment
+                            .versions
+                            .into_iter()
+                            .next()
+                            .and_then(|version| version.subgraph.metadata);
+                        SubgraphDeploymentMetadata {
+                            ipfs_hash: deployment.ipfs_hash,
+                            display_name: metadata.as_ref().and_then(|m| m.display_name.clone()),
+                            image: metadata.and_then(|m| m.image),
+                            signal_amount: deployment.signal_amount,
+                        }
+                    }),
+            );
+        }
+
+        Ok(deployments)
+    }
+
+    /// Returns all allocations that were closed with a PoI after the given
+    /// UNIX timestamp, oldest first. The PoI of each allocation was computed
+    /// for the start block of the epoch the allocation was closed in.
+    pub async fn closed_allocations(
+        &self,
+        closed_after: i64,
+        limit: Option<u32>,
+    ) -> anyhow::Result<Vec<ClosedAllocation>> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ResponseData {
+            allocations: Vec<AllocationData>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct AllocationData {
+            id: String,
+            indexer: IdData,
+            subgraph_deployment: DeploymentData,
+            closed_at: i64,
+            closed_at_epoch: i64,
+            poi: PoiBytes,
+        }
+
+        #[derive(Deserialize)]
+        struct IdData {
+            id: IndexerAddress,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct DeploymentData {
+            ipfs_hash: String,
+        }
+
+        let allocations = self
+            .paginate::<ResponseData, _>(
+                queries::CLOSED_ALLOCATIONS_QUERY,
+                vec![("closedAfter".to_string(), closed_after.into())],
+                "error(s) querying closed allocations from the network subgraph",
+                |response_data| response_data.allocations,
+                limit,
+            )
+            .await?;
+
+        let epoch_start_blocks = self
+            .epoch_start_blocks(
+                allocations
+                    .iter()
+                    .map(|allocation| allocation.closed_at_epoch),
+            )
+            .await?;
+
+        Ok(allocations
+            .into_iter()
+            .filter_map(|allocation| {
+                let epoch_start_block = *epoch_start_blocks.get(&allocation.closed_at_epoch)?;
+                Some(ClosedAllocation {
+                    id: allocation.id,
+                    indexer: allocation.indexer.id,
+                    deployment: allocation.subgraph_deployment.ipfs_hash,
+                    closed_at: allocation.closed_at,
+                    closed_at_epoch: allocation.closed_at_epoch,
+                    epoch_start_block,
+                    poi: allocation.poi,
+                })
+            })
+            .collect())
+    }
+
+    /// Returns all indexing disputes, i.e. disputes about PoIs that indexers
+    /// submitted when closing allocations, that were created after the given
+    /// UNIX timestamp, oldest first.
+    pub async fn indexing_disputes(
+        &self,
+        created_after: i64,
+        limit: Option<u32>,
+    ) -> anyhow::Result<Vec<IndexingDispute>> {
+        let disputes = self
+            .paginate::<DisputesResponseData, _>(
+                queries::INDEXING_DISPUTES_QUERY,
+                vec![("createdAfter".to_string(), created_after.into())],
+                "error(s) querying disputes from the network subgraph",
+                |response_data| response_data.disputes,
+                limit,
+            )
+            .await?;
+
+        self.indexing_disputes_with_blocks(disputes).await
+    }
+
+    /// Returns the indexing disputes with the given IDs, e.g. to find out
+    /// whether they were resolved in the meantime. Unknown IDs are skipped.
+    pub async fn indexing_disputes_by_id(
+        &self,
+        ids: &[String],
+    ) -> anyhow::Result<Vec<IndexingDispute>> {
+        let mut disputes = vec![];
+        for ids in ids.chunks(PAGINATION_SIZE) {
+            let response_data: DisputesResponseData = self
+                .graphql_query_no_errors(
+                    queries::DISPUTES_BY_ID_QUERY,
+                    vec![("ids".to_string(), serde_json::to_value(ids)?)],
+                    "error(s) querying disputes from the network subgraph",
+                )
+                .await?;
+            disputes.extend(response_data.disputes);
+        }
+
+        self.indexing_disputes_with_blocks(disputes).await
+    }
+
+    async fn indexing_disputes_with_blocks(
+        &self,
+        disputes: Vec<DisputeData>,
+    ) -> anyhow::Result<Vec<IndexingDispute>> {
+        let epoch_start_blocks = self
+            .epoch_start_blocks(
+                disputes
+                    .iter()
+                    .filter_map(|dispute| Some(dispute.allocation.as_ref()?.closed_at_epoch)),
+            )
+            .await?;
+
+        Ok(disputes
+            .into_iter()
+            .filter_map(|dispute| {
+                // Disputes about allocations that were closed without a PoI
+                // can't be about a PoI.
+                let allocation = dispute.allocation?;
+                let poi = allocation.poi?;
+                let epoch_start_block = *epoch_start_blocks.get(&allocation.closed_at_epoch)?;
+                Some(IndexingDispute {
+                    id: dispute.id,
+                    indexer: dispute.indexer.id,
+                    deployment: dispute.subgraph_deployment.ipfs_hash,
+                    allocation_id: allocation.id,
+                    epoch_start_block,
+                    poi,
+                    status: dispute.status,
+                    created_at: dispute.created_at,
+                    // The network subgraph uses 0 for open disputes.
+                    closed_at: Some(dispute.closed_at).filter(|closed_at| *closed_at > 0),
+                })
+            })
+            .collect())
+    }
+
+    /// Returns the start blocks of the given epochs, by epoch number.
+    async fn epoch_start_blocks(
+        &self,
+        epochs: impl IntoIterator<Item = i64>,
+    ) -> anyhow::Result<BTreeMap<i64, u64>> {
+        #[derive(Deserialize)]
+        struct EpochsResponseData {
+            epoches: Vec<EpochData>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct EpochData {
+            id: String,
+            start_block: u64,
+        }
+
+        let epoch_ids: BTreeSet<String> =
+            epochs.into_iter().map(|epoch| epoch.to_string()).collect();
+        let mut epoch_start_blocks = BTreeMap::new();
+        for epoch_ids in Vec::from_iter(epoch_ids).chunks(PAGINATION_SIZE) {
+            let response_data: EpochsResponseData = self
+                .graphql_query_no_errors(
+                    queries::EPOCHS_QUERY,
+                    vec![("ids".to_string(), serde_json::to_value(epoch_ids)?)],
+                    "error(s) querying epochs from the network subgraph",
+                )
+                .await?;
+            for epoch in response_data.epoches {
+                if let Ok(id) = epoch.id.parse() {
+                    epoch_start_blocks.insert(id, epoch.start_block);
+                }
+            }
+        }
+
+        Ok(epoch_start_blocks)
+    }
+
     /// A wrapper around [`NetworkSubgraphClient::graphql_query`] that requires
     /// no errors in the response, and deserializes the response data into the
     /// given type.
@@ -330,13 +603,100 @@ pub struct Indexer {
     pub url: Option<String>,
 }
 
+/// The display metadata and curation signal of a subgraph deployment.
+#[derive(Debug, Clone)]
+pub struct SubgraphDeploymentMetadata {
+    pub ipfs_hash: String,
+    pub display_name: Option<String>,
+    pub image: Option<String>,
+    /// The curation signal on the deployment, in GRT wei.
+    pub signal_amount: BigDecimal,
+}
+
+/// An allocation that was closed with a PoI.
+#[derive(Debug, Clone)]
+pub struct ClosedAllocation {
+    pub id: String,
+    pub indexer: IndexerAddress,
+    /// The IPFS hash of the subgraph deployment.
+    pub deployment: String,
+    /// UNIX timestamp.
+    pub closed_at: i64,
+    pub closed_at_epoch: i64,
+    /// The block the PoI was computed for.
+    pub epoch_start_block: u64,
+    pub poi: PoiBytes,
+}
+
+/// A dispute about a PoI that an indexer submitted on chain when closing an
+/// allocation.
+#[derive(Debug, Clone)]
+pub struct IndexingDispute {
+    pub id: String,
+    pub indexer: IndexerAddress,
+    /// The IPFS hash of the subgraph deployment.
+    pub deployment: String,
+    pub allocation_id: String,
+    /// The block the disputed PoI was computed for.
+    pub epoch_start_block: u64,
+    /// The disputed PoI.
+    pub poi: PoiBytes,
+    pub status: DisputeStatus,
+    /// UNIX timestamp.
+    pub created_at: i64,
+    /// UNIX timestamp, if the dispute was resolved.
+    pub closed_at: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct DisputesResponseData {
+    disputes: Vec<DisputeData>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DisputeData {
+    id: String,
+    indexer: DisputeIndexerData,
+    subgraph_deployment: DisputeDeploymentData,
+    allocation: Option<DisputeAllocationData>,
+    status: DisputeStatus,
+    created_at: i64,
+    closed_at: i64,
+}
+
+#[derive(Deserialize)]
+struct DisputeIndexerData {
+    id: IndexerAddress,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DisputeDeploymentData {
+    ipfs_hash: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DisputeAllocationData {
+    id: String,
+    closed_at_epoch: i64,
+    poi: Option<PoiBytes>,
+}
+
 mod queries {
     pub const INDEXERS_BY_STAKED_TOKENS_QUERY: &str =
         include_str!("queries/indexers_by_staked_tokens.graphql");
     pub const INDEXERS_BY_ALLOCATIONS_QUERY: &str =
         include_str!("queries/indexers_by_allocations.graphql");
     pub const DEPLOYMENTS_QUERY: &str = include_str!("queries/deployments.graphql");
+    pub const DEPLOYMENTS_METADATA_QUERY: &str =
+        include_str!("queries/deployments_metadata.graphql");
     pub const INDEXER_BY_ADDRESS_QUERY: &str = include_str!("queries/indexer_by_address.graphql");
+    pub const CLOSED_ALLOCATIONS_QUERY: &str = include_str!("queries/closed_allocations.graphql");
+    pub const EPOCHS_QUERY: &str = include_str!("queries/epochs.graphql");
+    pub const INDEXING_DISPUTES_QUERY: &str = include_str!("queries/indexing_disputes.graphql");
+    pub const DISPUTES_BY_ID_QUERY: &str = include_str!("queries/disputes_by_id.graphql");
 }
 
 #[cfg(test)]
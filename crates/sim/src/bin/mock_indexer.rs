@@ -0,0 +1,83 @@
+use std::env;
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::Context;
+use clap::Parser;
+use graphix_sim::mock::{serve, MockIndexer, MockScript};
+use tokio::net::TcpListener;
+use tokio::task::JoinSet;
+use tracing::*;
+
+/// Serves `graph-node`'s index-node API with scripted responses, one mock
+/// indexer per script file.
+#[derive(Parser, Debug)]
+#[clap(author, about, version)]
+struct CliOptions {
+    /// Paths to the YAML script files.
+    #[clap(long = "script", required = true)]
+    scripts: Vec<PathBuf>,
+    /// Mock indexers listen on consecutive ports starting from this one.
+    #[clap(long, default_value_t = 7700)]
+    base_port: u16,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    init_tracing();
+
+    let cli_options = CliOptions::parse();
+
+    let mut sources = vec![];
+    let mut servers = JoinSet::new();
+    for (i, script) in cli_options.scripts.iter().enumerate() {
+        let indexer = MockIndexer::new(MockScript::read(script)?)?;
+        let port = u16::try_from(i)
+            .ok()
+            .and_then(|i| cli_options.base_port.checked_add(i))
+            .with_context(|| {
+                format!(
+                    "not enough ports above --base-port {} for all mock indexers",
+                    cli_options.base_port
+                )
+            })?;
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port)).await?;
+        info!(name = %indexer.name, port, "Starting mock indexer");
+
+        sources.push(serde_json::json!({
+            "type": "indexer",
+            "name": indexer.name,
+            "address": indexer.address,
+            "indexNodeEndpoint": format!("http://localhost:{}/status", port),
+        }));
+        servers.spawn(serve(listener, Arc::new(indexer)));
+    }
+
+    // Print a Graphix configuration pointing at all mock indexers, which can
+    // be fed to the `setConfiguration` mutation.
+    let graphix_config = serde_json::json!({ "sources": sources });
+    println!("{}", serde_json::to_string_pretty(&graphix_config)?);
+
+    while let Some(result) = servers.join_next().await {
+        result??;
+    }
+
+    Ok(())
+}
+
+fn init_tracing() {
+    use tracing_subscriber::prelude::*;
+    use tracing_subscriber::{fmt, EnvFilter};
+
+    tracing_subscriber::registry()
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(
+            EnvFilter::from_str(
+                &env::var("RUST_LOG").unwrap_or_else(|_| "graphix_sim=info".to_string()),
+            )
+            .unwrap(),
+        )
+        .init();
+}
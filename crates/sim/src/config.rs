@@ -0,0 +1,119 @@
+//! Simulation scenario parsing.
+
+use std::str::FromStr;
+
+use graphix_common_types::IpfsCid;
+use graphix_lib::test_utils::gen::gen_deployments;
+use serde::{Deserialize, Serialize};
+
+/// A [`serde`]-compatible representation of a `graphix-sim` YAML scenario
+/// file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimConfig {
+    /// All PoIs, forked block hashes, and intermittent failures are derived
+    /// from this seed, so two runs with the same scenario file behave exactly
+    /// the same.
+    #[serde(default)]
+    pub seed: u64,
+    /// The network name reported by all simulated indexers.
+    #[serde(default = "SimConfig::default_network")]
+    pub network: String,
+    /// The earliest block of all simulated subgraph deployments. PoIs are
+    /// generated up front for all blocks up to the chain head, so keep the
+    /// range small.
+    #[serde(default)]
+    pub earliest_block: u64,
+    /// The chain head.
+    pub head_block: u64,
+    /// IPFS hashes of the subgraph deployments indexed by all simulated
+    /// indexers. A few well-known deployments are used if empty.
+    #[serde(default)]
+    pub deployments: Vec<String>,
+    pub indexers: Vec<SimIndexerConfig>,
+}
+
+impl SimConfig {
+    pub fn read(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        use anyhow::Context;
+
+        let file_contents = std::fs::read_to_string(path)?;
+        serde_yaml::from_str(&file_contents).context("invalid simulation scenario file")
+    }
+
+    /// The deployments that are indexed by all simulated indexers.
+    pub fn deployments(&self) -> anyhow::Result<Vec<IpfsCid>> {
+        if self.deployments.is_empty() {
+            return Ok(gen_deployments());
+        }
+
+        parse_deployments(&self.deployments)
+    }
+
+    fn default_network() -> String {
+        "mainnet".to_string()
+    }
+}
+
+/// A simulated indexer, or a group of identical simulated indexers if
+/// `count` is greater than one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimIndexerConfig {
+    pub name: String,
+    /// How many indexers with this same configuration to spin up. Names get a
+    /// numeric suffix if greater than one. Addresses are derived from names.
+    #[serde(default = "SimIndexerConfig::default_count")]
+    pub count: u32,
+    /// How many blocks this indexer lags behind the chain head.
+    #[serde(default)]
+    pub lag: u64,
+    #[serde(default)]
+    pub scenarios: Vec<Scenario>,
+}
+
+impl SimIndexerConfig {
+    fn default_count() -> u32 {
+        1
+    }
+}
+
+pub(crate) fn parse_deployments(deployments: &[String]) -> anyhow::Result<Vec<IpfsCid>> {
+    deployments
+        .iter()
+        .map(|s| IpfsCid::from_str(s).map_err(|e| anyhow::anyhow!("invalid deployment {s}: {e}")))
+        .collect()
+}
+
+/// Deviations from an honest, always available indexer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Scenario {
+    /// The indexer produces diverging PoIs starting from `atBlock`, for the
+    /// given deployment IPFS hashes (or all deployments, if none are given).
+    #[serde(rename_all = "camelCase")]
+    Diverge {
+        at_block: u64,
+        #[serde(default)]
+        deployments: Vec<String>,
+    },
+    /// The indexer follows a non-canonical fork for all blocks within
+    /// `fromBlock..=toBlock`, reporting different block hashes and PoIs for
+    /// those blocks.
+    #[serde(rename_all = "camelCase")]
+    Reorg { from_block: u64, to_block: u64 },
+    /// A fraction of all requests sent to the indexer fail.
+    #[serde(rename_all = "camelCase")]
+    IntermittentFailures { failure_rate: f64 },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_example_scenario() {
+        let config = SimConfig::read("../../configs/simulation.graphix-sim.yml").unwrap();
+        assert!(!config.indexers.is_empty());
+    }
+}
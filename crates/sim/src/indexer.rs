@@ -0,0 +1,112 @@
+//! Generating the indexers of a simulation, as [`MockIndexer`]s that deviate
+//! from the canonical PoIs according to their [`Scenario`]s.
+
+use graphix_indexer_client::BlockPointer;
+use graphix_lib::test_utils::gen::{gen_block, gen_mock_indexers};
+use graphix_lib::test_utils::mocks::MockIndexer;
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+
+use crate::config::{parse_deployments, Scenario, SimConfig, SimIndexerConfig};
+
+/// A simulated indexer, along with the fraction of requests to it that fail,
+/// which its HTTP server takes care of.
+#[derive(Debug)]
+pub struct SimIndexer {
+    pub indexer: MockIndexer,
+    pub failure_rate: f64,
+    /// Which requests fail is derived from this seed.
+    pub failure_seed: u64,
+}
+
+/// The chain head of the simulation.
+pub fn chain_head(config: &SimConfig) -> BlockPointer {
+    gen_block(config.head_block)
+}
+
+/// Generates all simulated indexers described by `config`. All PoIs from the
+/// earliest block to the chain head are generated up front.
+pub fn gen_sim_indexers(config: &SimConfig) -> anyhow::Result<Vec<SimIndexer>> {
+    if config.head_block < config.earliest_block {
+        anyhow::bail!("the chain head is before the earliest block");
+    }
+
+    let deployments = config.deployments()?;
+    let blocks: Vec<_> = (config.earliest_block..=config.head_block)
+        .map(gen_block)
+        .collect();
+    let indexer_configs: Vec<_> = config
+        .indexers
+        .iter()
+        .flat_map(|indexer_config| {
+            (0..indexer_config.count).map(move |i| {
+                let name = if indexer_config.count > 1 {
+                    format!("{}-{}", indexer_config.name, i)
+                } else {
+                    indexer_config.name.clone()
+                };
+                (name, indexer_config)
+            })
+        })
+        .collect();
+    let names: Vec<_> = indexer_configs
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    let mut rng = SmallRng::seed_from_u64(config.seed);
+    let indexers = gen_mock_indexers(&mut rng, &names, &deployments, &blocks, 0.0);
+
+    indexers
+        .into_iter()
+        .zip(indexer_configs)
+        .enumerate()
+        .map(|(i, (mut indexer, (_, indexer_config)))| {
+            for details in &mut indexer.deployment_details {
+                details.network = config.network.clone();
+            }
+            let failure_rate = deviate(&mut indexer, indexer_config, config, &mut rng)?;
+            Ok(SimIndexer {
+                indexer,
+                failure_rate,
+                failure_seed: config.seed.wrapping_add(i as u64),
+            })
+        })
+        .collect()
+}
+
+/// Applies the lag and [`Scenario`]s of `indexer_config` to `indexer`, and
+/// returns its failure rate.
+fn deviate(
+    indexer: &mut MockIndexer,
+    indexer_config: &SimIndexerConfig,
+    config: &SimConfig,
+    rng: &mut SmallRng,
+) -> anyhow::Result<f64> {
+    let latest_block = config
+        .head_block
+        .saturating_sub(indexer_config.lag)
+        .max(config.earliest_block);
+    indexer.lag_behind(latest_block);
+
+    let mut failure_rate: f64 = 0.0;
+    for scenario in &indexer_config.scenarios {
+        match scenario {
+            Scenario::Diverge {
+                at_block,
+                deployments,
+            } => {
+                indexer.diverge(rng, *at_block, &parse_deployments(deployments)?);
+            }
+            Scenario::Reorg {
+                from_block,
+                to_block,
+            } => indexer.reorg(rng, *from_block..=*to_block),
+            Scenario::IntermittentFailures { failure_rate: rate } => {
+                failure_rate = failure_rate.max(*rate)
+            }
+        }
+    }
+
+    Ok(failure_rate.min(1.0))
+}
@@ -0,0 +1,8 @@
+//! Deterministic simulation of a network of `graph-node` indexers, and mock
+//! indexers with scripted responses, for exercising Graphix end-to-end
+//! without real indexers.
+
+pub mod config;
+pub mod indexer;
+pub mod mock;
+pub mod server;
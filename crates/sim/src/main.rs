@@ -0,0 +1,98 @@
+use std::env;
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::Context;
+use clap::Parser;
+use graphix_indexer_client::IndexerClient;
+use graphix_sim::config::SimConfig;
+use graphix_sim::indexer::{chain_head, gen_sim_indexers};
+use graphix_sim::server::serve;
+use tokio::net::TcpListener;
+use tokio::task::JoinSet;
+use tracing::*;
+
+/// Spins up a network of simulated indexers according to a scenario file.
+#[derive(Parser, Debug)]
+#[clap(author, about, version)]
+struct CliOptions {
+    /// Path to the YAML scenario file.
+    #[clap(long)]
+    config: PathBuf,
+    /// Simulated indexers listen on consecutive ports starting from this one.
+    #[clap(long, default_value_t = 7600)]
+    base_port: u16,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    init_tracing();
+
+    let cli_options = CliOptions::parse();
+    let config = SimConfig::read(&cli_options.config)?;
+
+    let chain_head = chain_head(&config);
+    let indexers = gen_sim_indexers(&config)?;
+
+    let mut sources = vec![];
+    let mut servers = JoinSet::new();
+    for (i, sim_indexer) in indexers.into_iter().enumerate() {
+        let indexer = Arc::new(sim_indexer.indexer);
+        let port = u16::try_from(i)
+            .ok()
+            .and_then(|i| cli_options.base_port.checked_add(i))
+            .with_context(|| {
+                format!(
+                    "not enough ports above --base-port {} for all simulated indexers",
+                    cli_options.base_port
+                )
+            })?;
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port)).await?;
+        info!(name = %indexer.name, port, "Starting simulated indexer");
+
+        sources.push(serde_json::json!({
+            "type": "indexer",
+            "name": indexer.name,
+            "address": indexer.address(),
+            "indexNodeEndpoint": format!("http://localhost:{}/status", port),
+        }));
+        servers.spawn(serve(
+            listener,
+            indexer,
+            chain_head.clone(),
+            sim_indexer.failure_rate,
+            sim_indexer.failure_seed,
+        ));
+    }
+
+    // Print a Graphix configuration pointing at all simulated indexers, which
+    // can be fed to the `setConfiguration` mutation.
+    let graphix_config = serde_json::json!({
+        "chains": { config.network.clone(): {} },
+        "sources": sources,
+    });
+    println!("{}", serde_json::to_string_pretty(&graphix_config)?);
+
+    while let Some(result) = servers.join_next().await {
+        result??;
+    }
+
+    Ok(())
+}
+
+fn init_tracing() {
+    use tracing_subscriber::prelude::*;
+    use tracing_subscriber::{fmt, EnvFilter};
+
+    tracing_subscriber::registry()
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(
+            EnvFilter::from_str(
+                &env::var("RUST_LOG").unwrap_or_else(|_| "graphix_sim=info".to_string()),
+            )
+            .unwrap(),
+        )
+        .init();
+}
@@ -0,0 +1,444 @@
+//! Mock indexers, which serve `graph-node`'s index-node API with scripted
+//! responses. Unlike [`crate::server`], which computes its responses, a mock
+//! indexer replays them verbatim, e.g. as captured from real indexers, so
+//! that clients are tested against the exact wire format.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use async_graphql::parser::types::{
+    BaseType, FragmentDefinition, OperationType, Selection, SelectionSet, Type, TypeKind,
+    TypeSystemDefinition,
+};
+use async_graphql::parser::{parse_query, parse_schema, Positioned};
+use async_graphql::Name;
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::Json;
+use graphix_common_types::IndexerAddress;
+use graphix_lib::test_utils::mocks::address_from_name;
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+
+/// The schema of `graph-node`'s index-node API, as fetched by the build script
+/// of `graphix_indexer_client`.
+const INDEX_NODE_SCHEMA: &str = include_str!("../../indexer_client/graphql/indexer/schema.gql");
+
+/// A [`serde`]-compatible representation of a `graphix-mock-indexer` YAML
+/// script file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MockScript {
+    pub name: String,
+    /// Derived from `name` if not given.
+    #[serde(default)]
+    pub address: Option<IndexerAddress>,
+    /// The responses to each GraphQL operation, by operation name, e.g.
+    /// `IndexingStatuses`. Requests get the responses to their operation in
+    /// order, and the last one again once all others are used up.
+    #[serde(default)]
+    pub responses: HashMap<String, Vec<ScriptedResponse>>,
+}
+
+impl MockScript {
+    pub fn read(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file_contents = std::fs::read_to_string(path)?;
+        let mut script: Self =
+            serde_yaml::from_str(&file_contents).context("invalid mock indexer script file")?;
+
+        // Fixtures are relative to the script file, not the working directory.
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for response in script.responses.values_mut().flatten() {
+            if let Some(fixture) = &mut response.fixture {
+                *fixture = dir.join(&*fixture);
+            }
+        }
+
+        Ok(script)
+    }
+}
+
+/// One response of a [`MockScript`]. Exactly one of `body`, `rawBody`, and
+/// `fixture` must be given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptedResponse {
+    /// The HTTP status code of the response.
+    #[serde(default = "ScriptedResponse::default_status")]
+    pub status: u16,
+    /// A JSON response body, e.g. `{ data: { ... } }`.
+    #[serde(default)]
+    pub body: Option<serde_json::Value>,
+    /// A response body that is sent verbatim, e.g. to test malformed
+    /// responses.
+    #[serde(default)]
+    pub raw_body: Option<String>,
+    /// A file that contains the response body.
+    #[serde(default)]
+    pub fixture: Option<PathBuf>,
+    /// How long to wait before responding.
+    #[serde(default)]
+    pub delay_in_msecs: u64,
+}
+
+impl ScriptedResponse {
+    fn default_status() -> u16 {
+        200
+    }
+
+    fn resolve(&self) -> anyhow::Result<MockResponse> {
+        let body = match (&self.body, &self.raw_body, &self.fixture) {
+            (Some(body), None, None) => body.to_string(),
+            (None, Some(raw_body), None) => raw_body.clone(),
+            (None, None, Some(fixture)) => std::fs::read_to_string(fixture)
+                .with_context(|| format!("failed to read fixture {}", fixture.display()))?,
+            _ => bail!("exactly one of `body`, `rawBody`, and `fixture` must be given"),
+        };
+
+        Ok(MockResponse {
+            status: StatusCode::from_u16(self.status)?,
+            body,
+            delay: Duration::from_millis(self.delay_in_msecs),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct MockResponse {
+    status: StatusCode,
+    body: String,
+    delay: Duration,
+}
+
+/// A GraphQL request received by a [`MockIndexer`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceivedRequest {
+    pub query: String,
+    #[serde(default)]
+    pub operation_name: Option<String>,
+    #[serde(default)]
+    pub variables: serde_json::Value,
+}
+
+/// A mock indexer, which answers requests according to its [`MockScript`]
+/// and records them, so that tests can check what was requested.
+#[derive(Debug)]
+pub struct MockIndexer {
+    pub name: String,
+    pub address: IndexerAddress,
+    responses: HashMap<String, Vec<MockResponse>>,
+    served_counts: Mutex<HashMap<String, usize>>,
+    requests: Mutex<Vec<ReceivedRequest>>,
+}
+
+impl MockIndexer {
+    pub fn new(script: MockScript) -> anyhow::Result<Self> {
+        let responses = script
+            .responses
+            .iter()
+            .map(|(operation_name, responses)| {
+                let responses = responses
+                    .iter()
+                    .map(ScriptedResponse::resolve)
+                    .collect::<anyhow::Result<_>>()
+                    .with_context(|| format!("invalid response to {operation_name}"))?;
+                Ok((operation_name.clone(), responses))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            address: script
+                .address
+                .unwrap_or_else(|| address_from_name(&script.name)),
+            name: script.name,
+            responses,
+            served_counts: Mutex::default(),
+            requests: Mutex::default(),
+        })
+    }
+
+    /// All requests received so far, in order.
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn next_response(&self, operation_name: &str) -> Option<&MockResponse> {
+        let responses = self.responses.get(operation_name)?;
+        let mut served_counts = self.served_counts.lock().unwrap();
+        let served_count = served_counts.entry(operation_name.to_string()).or_default();
+        let response = responses.get(*served_count).or(responses.last());
+        *served_count += 1;
+        response
+    }
+}
+
+/// Serves the index-node API of `indexer` at `/status` until the process
+/// exits.
+pub async fn serve(listener: TcpListener, indexer: Arc<MockIndexer>) -> anyhow::Result<()> {
+    let router = axum::Router::new()
+        .route("/status", post(graphql_handler))
+        .with_state(indexer);
+
+    axum::serve(listener, router).await?;
+    Ok(())
+}
+
+async fn graphql_handler(
+    State(indexer): State<Arc<MockIndexer>>,
+    Json(request): Json<ReceivedRequest>,
+) -> Response {
+    let operation_name =
+        index_node_schema().validate(&request.query, request.operation_name.as_deref());
+    indexer.requests.lock().unwrap().push(request);
+
+    // Invalid queries are rejected like `graph-node` does, with GraphQL
+    // errors rather than an HTTP error status.
+    let operation_name = match operation_name {
+        Ok(operation_name) => operation_name,
+        Err(error) => return graphql_error(error.to_string()),
+    };
+    let Some(response) = indexer.next_response(&operation_name) else {
+        return graphql_error(format!("no scripted response to {operation_name}"));
+    };
+
+    tokio::time::sleep(response.delay).await;
+    (
+        response.status,
+        [(header::CONTENT_TYPE, "application/json")],
+        response.body.clone(),
+    )
+        .into_response()
+}
+
+fn graphql_error(message: String) -> Response {
+    Json(serde_json::json!({ "errors": [{ "message": message }] })).into_response()
+}
+
+fn index_node_schema() -> &'static IndexNodeSchema {
+    static SCHEMA: OnceLock<IndexNodeSchema> = OnceLock::new();
+    SCHEMA.get_or_init(|| {
+        IndexNodeSchema::parse(INDEX_NODE_SCHEMA).expect("invalid index-node schema")
+    })
+}
+
+/// The fields of all object and interface types of a GraphQL schema, which
+/// is just enough to check that queries only select fields and pass
+/// arguments that `graph-node` knows about.
+struct IndexNodeSchema {
+    types: HashMap<String, HashMap<String, FieldDefinition>>,
+}
+
+struct FieldDefinition {
+    type_name: String,
+    arguments: Vec<String>,
+}
+
+impl IndexNodeSchema {
+    fn parse(sdl: &str) -> anyhow::Result<Self> {
+        let mut types: HashMap<String, HashMap<_, _>> = HashMap::new();
+        for definition in parse_schema(sdl)?.definitions {
+            let TypeSystemDefinition::Type(definition) = definition else {
+                continue;
+            };
+            let definition = definition.node;
+            let fields = match definition.kind {
+                TypeKind::Object(object) => object.fields,
+                TypeKind::Interface(interface) => interface.fields,
+                _ => vec![],
+            };
+
+            types
+                .entry(definition.name.node.to_string())
+                .or_default()
+                .extend(fields.into_iter().map(|field| {
+                    let field = field.node;
+                    let definition = FieldDefinition {
+                        type_name: named_type(&field.ty.node).to_string(),
+                        arguments: field
+                            .arguments
+                            .iter()
+                            .map(|argument| argument.node.name.node.to_string())
+                            .collect(),
+                    };
+                    (field.name.node.to_string(), definition)
+                }));
+        }
+
+        Ok(Self { types })
+    }
+
+    /// Checks `query` against the schema, and returns the name of the
+    /// operation to execute.
+    fn validate(&self, query: &str, operation_name: Option<&str>) -> anyhow::Result<String> {
+        let document = parse_query(query)?;
+        let (name, operation) = document
+            .operations
+            .iter()
+            .find(|(name, _)| {
+                operation_name.map_or(true, |operation_name| {
+                    name.map(Name::as_str) == Some(operation_name)
+                })
+            })
+            .ok_or_else(|| anyhow!("unknown operation"))?;
+        if !matches!(operation.node.ty, OperationType::Query) {
+            bail!("only queries are supported");
+        }
+
+        self.validate_selection_set(
+            "Query",
+            &operation.node.selection_set.node,
+            &document.fragments,
+        )?;
+
+        name.map(Name::to_string)
+            .ok_or_else(|| anyhow!("anonymous operations can't be scripted"))
+    }
+
+    fn validate_selection_set(
+        &self,
+        type_name: &str,
+        selection_set: &SelectionSet,
+        fragments: &HashMap<Name, Positioned<FragmentDefinition>>,
+    ) -> anyhow::Result<()> {
+        let fields = self
+            .types
+            .get(type_name)
+            .ok_or_else(|| anyhow!("unknown type `{type_name}`"))?;
+
+        for selection in &selection_set.items {
+            match &selection.node {
+                Selection::Field(field) => {
+                    let field = &field.node;
+                    let name = field.name.node.as_str();
+                    // Introspection fields, e.g. `__typename`.
+                    if name.starts_with("__") {
+                        continue;
+                    }
+
+                    let definition = fields
+                        .get(name)
+                        .ok_or_else(|| anyhow!("type `{type_name}` has no field `{name}`"))?;
+                    for (argument, _) in &field.arguments {
+                        let argument = argument.node.as_str();
+                        if !definition.arguments.iter().any(|a| a == argument) {
+                            bail!("field `{type_name}.{name}` has no argument `{argument}`");
+                        }
+                    }
+                    if !field.selection_set.node.items.is_empty() {
+                        self.validate_selection_set(
+                            &definition.type_name,
+                            &field.selection_set.node,
+                            fragments,
+                        )?;
+                    }
+                }
+                Selection::InlineFragment(fragment) => {
+                    let fragment = &fragment.node;
+                    let type_name = fragment
+                        .type_condition
+                        .as_ref()
+                        .map_or(type_name, |condition| condition.node.on.node.as_str());
+                    self.validate_selection_set(
+                        type_name,
+                        &fragment.selection_set.node,
+                        fragments,
+                    )?;
+                }
+                Selection::FragmentSpread(spread) => {
+                    let fragment_name = &spread.node.fragment_name.node;
+                    let fragment = &fragments
+                        .get(fragment_name)
+                        .ok_or_else(|| anyhow!("unknown fragment `{fragment_name}`"))?
+                        .node;
+                    self.validate_selection_set(
+                        fragment.type_condition.node.on.node.as_str(),
+                        &fragment.selection_set.node,
+                        fragments,
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn named_type(ty: &Type) -> &str {
+    match &ty.base {
+        BaseType::Named(name) => name.as_str(),
+        BaseType::List(ty) => named_type(ty),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUERIES_DIR: &str = "../indexer_client/graphql/indexer/queries";
+
+    #[test]
+    fn graphix_queries_are_valid() {
+        for entry in std::fs::read_dir(QUERIES_DIR).unwrap() {
+            let query = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            index_node_schema().validate(&query, None).unwrap();
+        }
+    }
+
+    #[test]
+    fn unknown_fields_and_arguments_are_invalid() {
+        let schema = index_node_schema();
+
+        assert!(schema
+            .validate("query A { indexingStatuses { subgraph } }", None)
+            .is_ok());
+        assert!(schema
+            .validate("query A { indexingStatuses { subgraphs } }", None)
+            .is_err());
+        assert!(schema
+            .validate(
+                "query A { indexingStatuses(deployments: []) { subgraph } }",
+                None
+            )
+            .is_err());
+        assert!(schema
+            .validate("query A { version { version } }", Some("B"))
+            .is_err());
+    }
+
+    #[test]
+    fn scripted_responses_are_served_in_order() {
+        let script: MockScript = serde_yaml::from_str(
+            r#"
+name: mock
+responses:
+  IndexerVersion:
+    - status: 500
+      rawBody: oops
+    - body: { data: { version: { version: "0.35.0", commit: abc } } }
+"#,
+        )
+        .unwrap();
+        let indexer = MockIndexer::new(script).unwrap();
+
+        let statuses = (0..3)
+            .map(|_| indexer.next_response("IndexerVersion").unwrap().status)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            statuses,
+            [
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::OK,
+                StatusCode::OK
+            ]
+        );
+        assert!(indexer.next_response("ProofsOfIndexing").is_none());
+    }
+}
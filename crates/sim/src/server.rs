@@ -0,0 +1,398 @@
+//! A subset of `graph-node`'s index-node GraphQL API, served over HTTP on
+//! behalf of any [`IndexerClient`], e.g. a simulated [`MockIndexer`].
+//!
+//! [`MockIndexer`]: graphix_lib::test_utils::mocks::MockIndexer
+
+use std::sync::{Arc, Mutex};
+
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, Enum, InputObject, InputValueError,
+    InputValueResult, Interface, Object, Scalar, ScalarType, Schema, SimpleObject, Value,
+};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::extract::State;
+use axum::routing::post;
+use graphix_common_types::{IpfsCid, SubgraphHealth};
+use graphix_indexer_client::{BlockPointer, IndexerClient, PoiRequest};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use tokio::net::TcpListener;
+
+type IndexNodeSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+struct SimState {
+    indexer: Arc<dyn IndexerClient>,
+    chain_head: BlockPointer,
+}
+
+/// Fails a fraction of all requests.
+struct Failures {
+    failure_rate: f64,
+    rng: Mutex<SmallRng>,
+}
+
+impl Failures {
+    fn should_fail(&self) -> bool {
+        self.failure_rate > 0.0 && self.rng.lock().unwrap().gen_bool(self.failure_rate)
+    }
+}
+
+/// Serves the index-node API of `indexer` at `/status` until the process
+/// exits. A `failure_rate` fraction of all requests fail, which ones is
+/// derived from `failure_seed`.
+pub async fn serve(
+    listener: TcpListener,
+    indexer: Arc<dyn IndexerClient>,
+    chain_head: BlockPointer,
+    failure_rate: f64,
+    failure_seed: u64,
+) -> anyhow::Result<()> {
+    let schema = Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(SimState {
+            indexer,
+            chain_head,
+        })
+        .finish();
+    let failures = Arc::new(Failures {
+        failure_rate: failure_rate.clamp(0.0, 1.0),
+        rng: Mutex::new(SmallRng::seed_from_u64(failure_seed)),
+    });
+
+    let router = axum::Router::new()
+        .route("/status", post(graphql_handler))
+        .with_state((schema, failures));
+
+    axum::serve(listener, router).await?;
+    Ok(())
+}
+
+async fn graphql_handler(
+    State((schema, failures)): State<(IndexNodeSchema, Arc<Failures>)>,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
+    if failures.should_fail() {
+        let error = async_graphql::ServerError::new("simulated indexer failure", None);
+        return async_graphql::Response::from_errors(vec![error]).into();
+    }
+
+    schema.execute(request.into_inner()).await.into()
+}
+
+fn state<'a>(ctx: &'a Context) -> &'a SimState {
+    ctx.data_unchecked::<SimState>()
+}
+
+/// `graph-node` encodes block numbers as strings.
+struct BigInt(u64);
+
+#[Scalar]
+impl ScalarType for BigInt {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        match &value {
+            Value::String(s) => Ok(BigInt(s.parse()?)),
+            Value::Number(n) => n
+                .as_u64()
+                .map(BigInt)
+                .ok_or_else(|| InputValueError::expected_type(value)),
+            _ => Err(InputValueError::expected_type(value)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(self.0.to_string())
+    }
+}
+
+/// Hex-encoded bytes with a `0x` prefix.
+struct Bytes(Vec<u8>);
+
+#[Scalar]
+impl ScalarType for Bytes {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        let Value::String(s) = &value else {
+            return Err(InputValueError::expected_type(value));
+        };
+        Ok(Bytes(hex::decode(s.strip_prefix("0x").unwrap_or(s))?))
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(format!("0x{}", hex::encode(&self.0)))
+    }
+}
+
+#[derive(SimpleObject)]
+struct Version {
+    version: String,
+    commit: String,
+}
+
+#[derive(SimpleObject)]
+struct Block {
+    hash: Bytes,
+    number: BigInt,
+}
+
+#[derive(SimpleObject)]
+struct EarliestBlock {
+    hash: Bytes,
+    number: BigInt,
+}
+
+#[derive(SimpleObject)]
+struct PartialBlock {
+    hash: Option<Bytes>,
+    number: BigInt,
+}
+
+#[derive(SimpleObject)]
+struct EthereumIndexingStatus {
+    network: String,
+    chain_head_block: Option<Block>,
+    earliest_block: Option<EarliestBlock>,
+    latest_block: Option<Block>,
+}
+
+// Like `graph-node`, declares the fields that Graphix queries on the interface,
+// so that clients can query them regardless of the chain kind.
+#[derive(Interface)]
+#[graphql(
+    field(name = "network", ty = "&String"),
+    field(name = "earliest_block", ty = "&Option<EarliestBlock>"),
+    field(name = "latest_block", ty = "&Option<Block>")
+)]
+enum ChainIndexingStatus {
+    EthereumIndexingStatus(EthereumIndexingStatus),
+}
+
+#[derive(Enum, Copy, Clone, PartialEq, Eq)]
+#[graphql(rename_items = "lowercase")]
+enum Health {
+    Healthy,
+    Unhealthy,
+    Failed,
+}
+
+impl From<SubgraphHealth> for Health {
+    fn from(health: SubgraphHealth) -> Self {
+        match health {
+            SubgraphHealth::Healthy => Health::Healthy,
+            SubgraphHealth::Unhealthy => Health::Unhealthy,
+            SubgraphHealth::Failed => Health::Failed,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct SubgraphIndexingStatus {
+    subgraph: String,
+    synced: bool,
+    health: Health,
+    entity_count: BigInt,
+    chains: Vec<ChainIndexingStatus>,
+}
+
+#[derive(InputObject)]
+struct PublicProofOfIndexingRequest {
+    deployment: String,
+    block_number: BigInt,
+}
+
+#[derive(SimpleObject)]
+struct PublicProofOfIndexingResult {
+    deployment: String,
+    block: PartialBlock,
+    proof_of_indexing: Bytes,
+}
+
+#[derive(SimpleObject)]
+struct ApiVersion {
+    version: String,
+}
+
+#[derive(SimpleObject)]
+struct EntityTypeUpdates {
+    #[graphql(name = "type")]
+    type_: String,
+    entities: Vec<serde_json::Value>,
+}
+
+#[derive(SimpleObject)]
+struct EntityTypeDeletions {
+    #[graphql(name = "type")]
+    type_: String,
+    entities: Vec<String>,
+}
+
+#[derive(SimpleObject)]
+struct EntityChanges {
+    updates: Vec<EntityTypeUpdates>,
+    deletions: Vec<EntityTypeDeletions>,
+}
+
+#[derive(SimpleObject)]
+struct CachedEthereumCall {
+    id_hash: Bytes,
+    block: Block,
+    contract_address: Bytes,
+    return_value: Bytes,
+}
+
+impl From<&BlockPointer> for Block {
+    fn from(block: &BlockPointer) -> Self {
+        Block {
+            hash: Bytes(block.hash.as_ref().map_or(vec![], |hash| hash.0.clone())),
+            number: BigInt(block.number),
+        }
+    }
+}
+
+struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn version(&self, ctx: &Context<'_>) -> async_graphql::Result<Version> {
+        let version = state(ctx).indexer.clone().version().await?;
+
+        Ok(Version {
+            version: version.version.unwrap_or_default(),
+            commit: version.commit.unwrap_or_default(),
+        })
+    }
+
+    async fn indexing_statuses(
+        &self,
+        ctx: &Context<'_>,
+        subgraphs: Option<Vec<String>>,
+    ) -> async_graphql::Result<Vec<SubgraphIndexingStatus>> {
+        let SimState {
+            indexer,
+            chain_head,
+        } = state(ctx);
+
+        Ok(indexer
+            .clone()
+            .indexing_statuses()
+            .await?
+            .into_iter()
+            .filter(|status| {
+                subgraphs.as_ref().map_or(true, |subgraphs| {
+                    subgraphs.contains(&status.deployment.to_string())
+                })
+            })
+            .map(|status| SubgraphIndexingStatus {
+                subgraph: status.deployment.to_string(),
+                synced: status.synced && status.latest_block.number >= chain_head.number,
+                health: status.health.into(),
+                entity_count: BigInt(status.entity_count),
+                chains: vec![ChainIndexingStatus::EthereumIndexingStatus(
+                    EthereumIndexingStatus {
+                        network: status.network,
+                        chain_head_block: Some(chain_head.into()),
+                        earliest_block: status.earliest_block_num.map(|number| EarliestBlock {
+                            hash: Bytes(vec![0]),
+                            number: BigInt(number),
+                        }),
+                        latest_block: Some((&status.latest_block).into()),
+                    },
+                )],
+            })
+            .collect())
+    }
+
+    async fn public_proofs_of_indexing(
+        &self,
+        ctx: &Context<'_>,
+        requests: Vec<PublicProofOfIndexingRequest>,
+    ) -> async_graphql::Result<Vec<PublicProofOfIndexingResult>> {
+        let requests = requests
+            .into_iter()
+            .map(|request| {
+                Ok(PoiRequest {
+                    deployment: request.deployment.parse::<IpfsCid>()?,
+                    block_number: request.block_number.0,
+                })
+            })
+            .collect::<async_graphql::Result<_>>()?;
+
+        Ok(state(ctx)
+            .indexer
+            .clone()
+            .proofs_of_indexing(requests)
+            .await
+            .into_iter()
+            .map(|poi| PublicProofOfIndexingResult {
+                deployment: poi.deployment.to_string(),
+                block: PartialBlock {
+                    hash: poi.block.hash.map(|hash| Bytes(hash.0)),
+                    number: BigInt(poi.block.number),
+                },
+                proof_of_indexing: Bytes(poi.proof_of_indexing.0.to_vec()),
+            })
+            .collect())
+    }
+
+    async fn api_versions(
+        &self,
+        ctx: &Context<'_>,
+        subgraph_id: String,
+    ) -> async_graphql::Result<Vec<ApiVersion>> {
+        let versions = state(ctx)
+            .indexer
+            .clone()
+            .subgraph_api_versions(&subgraph_id)
+            .await?;
+
+        Ok(versions
+            .into_iter()
+            .map(|version| ApiVersion { version })
+            .collect())
+    }
+
+    async fn entity_changes_in_block(
+        &self,
+        ctx: &Context<'_>,
+        subgraph_id: String,
+        block_number: i32,
+    ) -> async_graphql::Result<EntityChanges> {
+        let changes = state(ctx)
+            .indexer
+            .clone()
+            .entity_changes(&subgraph_id, block_number as u64)
+            .await?;
+
+        Ok(EntityChanges {
+            updates: changes
+                .updates
+                .into_iter()
+                .map(|(type_, entities)| EntityTypeUpdates { type_, entities })
+                .collect(),
+            deletions: changes
+                .deletions
+                .into_iter()
+                .map(|(type_, entities)| EntityTypeDeletions { type_, entities })
+                .collect(),
+        })
+    }
+
+    async fn cached_ethereum_calls(
+        &self,
+        _network: String,
+        _block_hash: Bytes,
+    ) -> Option<Vec<CachedEthereumCall>> {
+        Some(vec![])
+    }
+
+    async fn block_data(
+        &self,
+        ctx: &Context<'_>,
+        network: String,
+        block_hash: Bytes,
+    ) -> async_graphql::Result<Option<serde_json::Value>> {
+        let block_data = state(ctx)
+            .indexer
+            .clone()
+            .block_cache_contents(&network, &block_hash.0)
+            .await?;
+        Ok(block_data)
+    }
+}
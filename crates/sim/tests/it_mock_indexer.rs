@@ -0,0 +1,141 @@
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+
+use graphix_common_types::SubgraphHealth;
+use graphix_indexer_client::{IndexerClient, RealIndexer};
+use graphix_lib::block_choice::BlockChoicePolicy;
+use graphix_lib::indexing_loop::{self, IndexerRequestLimits};
+use graphix_lib::metrics;
+use graphix_sim::mock::{serve, MockIndexer, MockScript};
+use prometheus::{IntCounterVec, Opts};
+use tokio::net::TcpListener;
+
+const SCRIPT: &str = "../../configs/mock-indexer.graphix-mock-indexer.yml";
+
+const DEPLOYMENT: &str = "QmPfSbPF6PcT2ETAXMoFCPcz2CaKysRBXcUecHPySK66rk";
+
+const FLAKY_SCRIPT: &str = r#"
+name: flaky
+responses:
+  IndexingStatuses:
+    - body:
+        data:
+          indexingStatuses:
+            - subgraph: QmPfSbPF6PcT2ETAXMoFCPcz2CaKysRBXcUecHPySK66rk
+              synced: true
+              health: healthy
+              entityCount: "42"
+              chains:
+                - __typename: EthereumIndexingStatus
+                  network: mainnet
+                  latestBlock:
+                    number: "100"
+                    hash: "0x1111111111111111111111111111111111111111111111111111111111111111"
+                  earliestBlock:
+                    number: "0"
+  ProofsOfIndexing:
+    - status: 502
+      rawBody: Bad Gateway
+    - body:
+        data:
+          publicProofsOfIndexing:
+            - deployment: QmPfSbPF6PcT2ETAXMoFCPcz2CaKysRBXcUecHPySK66rk
+              block:
+                number: "100"
+                hash: "0x1111111111111111111111111111111111111111111111111111111111111111"
+              proofOfIndexing: "0x2222222222222222222222222222222222222222222222222222222222222222"
+"#;
+
+async fn spawn_indexer(script: MockScript) -> (Arc<MockIndexer>, Arc<RealIndexer>) {
+    let mock = Arc::new(MockIndexer::new(script).unwrap());
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let endpoint = format!("http://{}/status", listener.local_addr().unwrap());
+
+    let client = Arc::new(RealIndexer::new(
+        Some(mock.name.clone()),
+        mock.address,
+        endpoint,
+        IntCounterVec::new(Opts::new("foo", "bar"), &["a", "b"]).unwrap(),
+    ));
+    tokio::spawn(serve(listener, mock.clone()));
+    (mock, client)
+}
+
+#[tokio::test]
+async fn scripted_indexing_statuses() {
+    //// Given
+    let (mock, indexer) = spawn_indexer(MockScript::read(SCRIPT).unwrap()).await;
+
+    //// When
+    let statuses = indexer.indexing_statuses().await.unwrap();
+
+    //// Then
+    assert_eq!(statuses.len(), 2);
+    assert_eq!(statuses[0].latest_block.number, 19999990);
+    assert_eq!(statuses[0].earliest_block_num, Some(11446767));
+    assert_eq!(statuses[1].health, SubgraphHealth::Failed);
+    assert_eq!(statuses[1].earliest_block_num, None);
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0].operation_name.as_deref(),
+        Some("IndexingStatuses")
+    );
+}
+
+#[tokio::test]
+async fn collection_recovers_from_failed_requests() {
+    //// Given
+    let (mock, indexer) = spawn_indexer(serde_yaml::from_str(FLAKY_SCRIPT).unwrap()).await;
+    let indexers: Vec<Arc<dyn IndexerClient>> = vec![indexer];
+    let limits = IndexerRequestLimits::default();
+
+    let collect_pois = || async {
+        let statuses = indexing_loop::query_indexing_statuses(&indexers, metrics(), &limits).await;
+        indexing_loop::query_proofs_of_indexing(statuses, BlockChoicePolicy::Earliest, &limits)
+            .await
+    };
+
+    //// When
+    let first_pois = collect_pois().await;
+    let second_pois = collect_pois().await;
+
+    //// Then
+    // The first PoI request gets a `502 Bad Gateway`.
+    assert!(first_pois.is_empty());
+    assert_eq!(second_pois.len(), 1);
+    assert_eq!(second_pois[0].deployment.to_string(), DEPLOYMENT);
+    assert_eq!(second_pois[0].block.number, 100);
+
+    let poi_requests = mock
+        .requests()
+        .into_iter()
+        .filter(|request| request.operation_name.as_deref() == Some("ProofsOfIndexing"))
+        .count();
+    assert_eq!(poi_requests, 2);
+}
+
+#[tokio::test]
+async fn malformed_responses_are_errors() {
+    //// Given
+    let script = serde_yaml::from_str(
+        r#"
+name: malformed
+responses:
+  IndexerVersion:
+    - rawBody: '{"data": {"version": '
+"#,
+    )
+    .unwrap();
+    let (_mock, indexer) = spawn_indexer(script).await;
+
+    //// When
+    let version = indexer.clone().version().await;
+    let statuses = indexer.indexing_statuses().await;
+
+    //// Then
+    assert!(version.is_err());
+    // Operations without scripted responses get GraphQL errors.
+    assert!(statuses.is_err());
+}
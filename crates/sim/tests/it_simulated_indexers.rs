@@ -0,0 +1,100 @@
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+
+use graphix_indexer_client::{IndexerClient, PoiRequest, RealIndexer};
+use graphix_sim::config::SimConfig;
+use graphix_sim::indexer::{chain_head, gen_sim_indexers};
+use graphix_sim::server::serve;
+use prometheus::{IntCounterVec, Opts};
+use tokio::net::TcpListener;
+
+const SCENARIO: &str = r#"
+seed: 1
+headBlock: 1000
+indexers:
+  - name: honest
+    count: 2
+  - name: diverging
+    lag: 10
+    scenarios:
+      - type: diverge
+        atBlock: 500
+"#;
+
+async fn spawn_indexers(config: &SimConfig) -> Vec<Arc<RealIndexer>> {
+    let mut clients = vec![];
+    for sim_indexer in gen_sim_indexers(config).unwrap() {
+        let indexer = Arc::new(sim_indexer.indexer);
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let endpoint = format!("http://{}/status", listener.local_addr().unwrap());
+
+        clients.push(Arc::new(RealIndexer::new(
+            Some(indexer.name.clone()),
+            indexer.address(),
+            endpoint,
+            IntCounterVec::new(Opts::new("foo", "bar"), &["a", "b"]).unwrap(),
+        )));
+        tokio::spawn(serve(
+            listener,
+            indexer,
+            chain_head(config),
+            sim_indexer.failure_rate,
+            sim_indexer.failure_seed,
+        ));
+    }
+    clients
+}
+
+#[tokio::test]
+async fn simulated_indexing_statuses() {
+    //// Given
+    let config: SimConfig = serde_yaml::from_str(SCENARIO).unwrap();
+    let indexers = spawn_indexers(&config).await;
+
+    //// When
+    let statuses = indexers[2].clone().indexing_statuses().await.unwrap();
+
+    //// Then
+    assert_eq!(statuses.len(), config.deployments().unwrap().len());
+    for status in statuses {
+        assert_eq!(status.network, "mainnet");
+        assert_eq!(status.latest_block.number, 990);
+    }
+}
+
+#[tokio::test]
+async fn simulated_pois_diverge_at_configured_block() {
+    //// Given
+    let config: SimConfig = serde_yaml::from_str(SCENARIO).unwrap();
+    let indexers = spawn_indexers(&config).await;
+    let deployment = config.deployments().unwrap()[0].clone();
+
+    let poi_at = |indexer: Arc<RealIndexer>, block_number| {
+        let deployment = deployment.clone();
+        async move {
+            let request = PoiRequest {
+                deployment,
+                block_number,
+            };
+            indexer
+                .proof_of_indexing(request)
+                .await
+                .unwrap()
+                .proof_of_indexing
+        }
+    };
+
+    //// When
+    let honest_before = poi_at(indexers[0].clone(), 499).await;
+    let other_honest_before = poi_at(indexers[1].clone(), 499).await;
+    let diverging_before = poi_at(indexers[2].clone(), 499).await;
+    let honest_after = poi_at(indexers[0].clone(), 500).await;
+    let other_honest_after = poi_at(indexers[1].clone(), 500).await;
+    let diverging_after = poi_at(indexers[2].clone(), 500).await;
+
+    //// Then
+    assert_eq!(honest_before, other_honest_before);
+    assert_eq!(honest_before, diverging_before);
+    assert_eq!(honest_after, other_honest_after);
+    assert_ne!(honest_after, diverging_after);
+}
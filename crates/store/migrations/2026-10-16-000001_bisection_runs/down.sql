@@ -0,0 +1,3 @@
+DROP TABLE bisection_evidence;
+DROP TABLE bisection_attempts;
+DROP TABLE bisection_runs;
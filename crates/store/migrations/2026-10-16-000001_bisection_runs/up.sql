@@ -0,0 +1,48 @@
+-- Relational representation of divergence investigation results, which are
+-- otherwise only stored as opaque JSON reports.
+
+CREATE TABLE bisection_runs (
+  uuid UUID PRIMARY KEY,
+  investigation_uuid UUID NOT NULL REFERENCES divergence_investigation_reports(uuid) ON DELETE CASCADE,
+  -- These are missing if the bisection run failed before the PoIs could be
+  -- matched with known deployments and indexers.
+  sg_deployment_id INTEGER REFERENCES sg_deployments(id) ON DELETE CASCADE,
+  indexer1_id INTEGER REFERENCES indexers(id) ON DELETE CASCADE,
+  indexer2_id INTEGER REFERENCES indexers(id) ON DELETE CASCADE,
+  poi1 BYTEA NOT NULL,
+  poi2 BYTEA NOT NULL,
+  lower_bound BIGINT NOT NULL,
+  upper_bound BIGINT NOT NULL,
+  error TEXT,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX ON bisection_runs (investigation_uuid);
+CREATE INDEX ON bisection_runs (sg_deployment_id);
+CREATE INDEX ON bisection_runs (indexer1_id);
+CREATE INDEX ON bisection_runs (indexer2_id);
+
+-- Every single block that was queried during a bisection run.
+CREATE TABLE bisection_attempts (
+  id INTEGER PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+  bisection_run_uuid UUID NOT NULL REFERENCES bisection_runs(uuid) ON DELETE CASCADE,
+  block_number BIGINT NOT NULL,
+  block_hash BYTEA,
+  indexer1_response TEXT NOT NULL,
+  indexer2_response TEXT NOT NULL
+);
+
+CREATE INDEX ON bisection_attempts (bisection_run_uuid);
+CREATE INDEX ON bisection_attempts (block_number);
+
+-- `graph-node` debugging data collected at the first diverging block.
+CREATE TABLE bisection_evidence (
+  id INTEGER PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+  bisection_run_uuid UUID NOT NULL REFERENCES bisection_runs(uuid) ON DELETE CASCADE,
+  indexer_id INTEGER NOT NULL REFERENCES indexers(id) ON DELETE CASCADE,
+  block_number BIGINT NOT NULL,
+  kind INTEGER NOT NULL,
+  contents JSONB NOT NULL
+);
+
+CREATE INDEX ON bisection_evidence (bisection_run_uuid);
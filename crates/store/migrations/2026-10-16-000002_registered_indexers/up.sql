@@ -0,0 +1,9 @@
+-- Indexers that were added at runtime through the GraphQL API, on top of
+-- those listed in the configuration file.
+CREATE TABLE registered_indexers (
+  id SERIAL PRIMARY KEY,
+  address BYTEA NOT NULL UNIQUE,
+  name TEXT,
+  index_node_endpoint TEXT NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
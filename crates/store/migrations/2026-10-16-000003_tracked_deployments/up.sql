@@ -0,0 +1,8 @@
+-- Subgraph deployments that were added to the watchlist through the GraphQL
+-- API. If any are present, Graphix only cross-checks these.
+CREATE TABLE tracked_deployments (
+  id SERIAL PRIMARY KEY,
+  ipfs_cid TEXT NOT NULL UNIQUE,
+  network_id INTEGER NOT NULL REFERENCES networks(id) ON DELETE CASCADE,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
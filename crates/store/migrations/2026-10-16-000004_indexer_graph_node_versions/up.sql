@@ -0,0 +1,11 @@
+-- Every distinct `graph-node` version that was observed for each indexer.
+CREATE TABLE indexer_graph_node_versions (
+  id SERIAL PRIMARY KEY,
+  indexer_id INTEGER NOT NULL REFERENCES indexers(id) ON DELETE CASCADE,
+  version_string TEXT,
+  version_commit TEXT,
+  first_seen_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  last_seen_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX ON indexer_graph_node_versions (indexer_id);
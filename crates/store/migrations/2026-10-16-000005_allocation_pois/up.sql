@@ -0,0 +1,21 @@
+-- PoIs that indexers submitted on chain when closing allocations, compared
+-- against the PoIs that Graphix collected from the same indexers.
+CREATE TABLE allocation_pois (
+  id SERIAL PRIMARY KEY,
+  allocation_id TEXT NOT NULL UNIQUE,
+  indexer_id INTEGER NOT NULL REFERENCES indexers(id) ON DELETE CASCADE,
+  sg_deployment_id INTEGER NOT NULL REFERENCES sg_deployments(id) ON DELETE CASCADE,
+  closed_at_epoch INTEGER NOT NULL,
+  block_number BIGINT NOT NULL,
+  submitted_poi BYTEA NOT NULL,
+  -- The PoI that Graphix collected for the same indexer, deployment and
+  -- block, if any.
+  collected_poi_id INTEGER REFERENCES pois(id) ON DELETE SET NULL,
+  -- NULL if Graphix didn't collect a PoI to compare against.
+  matches BOOLEAN,
+  closed_at TIMESTAMP NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX ON allocation_pois (indexer_id);
+CREATE INDEX ON allocation_pois (sg_deployment_id);
@@ -0,0 +1,3 @@
+-- Whether the block hash was verified against the chain's canonical block at
+-- the same height. NULL if the block wasn't verified.
+ALTER TABLE blocks ADD COLUMN canonical BOOLEAN;
@@ -0,0 +1,2 @@
+DELETE FROM blocks WHERE hash IS NULL;
+ALTER TABLE blocks ALTER COLUMN hash SET NOT NULL;
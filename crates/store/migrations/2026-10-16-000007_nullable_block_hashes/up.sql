@@ -0,0 +1,3 @@
+-- Indexers don't report block hashes for PoIs of all deployments, e.g.
+-- Substreams-powered ones.
+ALTER TABLE blocks ALTER COLUMN hash DROP NOT NULL;
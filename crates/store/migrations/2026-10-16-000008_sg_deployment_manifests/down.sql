@@ -0,0 +1,2 @@
+DROP TABLE sg_deployment_data_sources;
+DROP TABLE sg_deployment_manifests;
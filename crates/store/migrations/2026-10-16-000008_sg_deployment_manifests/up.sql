@@ -0,0 +1,25 @@
+-- Subgraph manifests fetched from IPFS, one per deployment.
+CREATE TABLE sg_deployment_manifests (
+  sg_deployment_id INTEGER PRIMARY KEY REFERENCES sg_deployments(id) ON DELETE CASCADE,
+  spec_version TEXT NOT NULL,
+  features TEXT[] NOT NULL,
+  -- The deployment and block that the subgraph was grafted onto, if any.
+  graft_base TEXT,
+  graft_block BIGINT,
+  raw_manifest TEXT NOT NULL,
+  fetched_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- Data sources and data source templates of subgraph manifests.
+CREATE TABLE sg_deployment_data_sources (
+  id SERIAL PRIMARY KEY,
+  sg_deployment_id INTEGER NOT NULL REFERENCES sg_deployment_manifests(sg_deployment_id) ON DELETE CASCADE,
+  is_template BOOLEAN NOT NULL,
+  kind TEXT NOT NULL,
+  name TEXT NOT NULL,
+  network TEXT,
+  address TEXT,
+  start_block BIGINT
+);
+
+CREATE INDEX ON sg_deployment_data_sources (sg_deployment_id);
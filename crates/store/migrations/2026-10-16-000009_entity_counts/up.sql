@@ -0,0 +1,14 @@
+-- The number of entities that each indexer reported for each deployment in
+-- its indexing status, collected once per main loop iteration.
+CREATE TABLE entity_counts (
+  id SERIAL PRIMARY KEY,
+  indexer_id INTEGER NOT NULL REFERENCES indexers(id) ON DELETE CASCADE,
+  sg_deployment_id INTEGER NOT NULL REFERENCES sg_deployments(id) ON DELETE CASCADE,
+  -- The latest block the indexer had indexed when reporting the count.
+  block_number BIGINT NOT NULL,
+  entity_count BIGINT NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX ON entity_counts (sg_deployment_id, indexer_id, id);
+CREATE INDEX ON entity_counts (indexer_id);
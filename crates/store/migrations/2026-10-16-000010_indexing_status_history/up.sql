@@ -0,0 +1,15 @@
+-- A compact history of the indexing statuses that each indexer reported for
+-- each deployment, one row per main loop iteration.
+CREATE TABLE indexing_status_history (
+  id SERIAL PRIMARY KEY,
+  indexer_id INTEGER NOT NULL REFERENCES indexers(id) ON DELETE CASCADE,
+  sg_deployment_id INTEGER NOT NULL REFERENCES sg_deployments(id) ON DELETE CASCADE,
+  latest_block_number BIGINT NOT NULL,
+  -- See `SubgraphHealth`.
+  health INTEGER NOT NULL,
+  synced BOOLEAN NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX ON indexing_status_history (sg_deployment_id, created_at);
+CREATE INDEX ON indexing_status_history (indexer_id);
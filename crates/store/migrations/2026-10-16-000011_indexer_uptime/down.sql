@@ -0,0 +1 @@
+DROP TABLE indexer_uptime_transitions;
@@ -0,0 +1,9 @@
+-- Every time an indexer was found to go up or down by the uptime prober.
+CREATE TABLE indexer_uptime_transitions (
+  id SERIAL PRIMARY KEY,
+  indexer_id INTEGER NOT NULL REFERENCES indexers(id) ON DELETE CASCADE,
+  up BOOLEAN NOT NULL,
+  observed_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX ON indexer_uptime_transitions (indexer_id, observed_at);
@@ -0,0 +1,11 @@
+-- How many of the PoIs that Graphix requested from each indexer during a main
+-- loop iteration the indexer actually returned.
+CREATE TABLE poi_request_outcomes (
+  id SERIAL PRIMARY KEY,
+  indexer_id INTEGER NOT NULL REFERENCES indexers(id) ON DELETE CASCADE,
+  requested INTEGER NOT NULL,
+  returned INTEGER NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX ON poi_request_outcomes (indexer_id, created_at);
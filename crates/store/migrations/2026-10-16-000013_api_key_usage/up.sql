@@ -0,0 +1,8 @@
+-- Cumulative GraphQL API usage per API key, as groundwork for fair-use
+-- enforcement.
+CREATE TABLE api_key_usage (
+  public_prefix TEXT PRIMARY KEY REFERENCES graphix_api_tokens(public_prefix) ON DELETE CASCADE,
+  query_count BIGINT NOT NULL DEFAULT 0,
+  total_execution_time_ms BIGINT NOT NULL DEFAULT 0,
+  last_used_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,5 @@
+DROP TABLE api_key_daily_usage;
+
+ALTER TABLE graphix_api_tokens
+  DROP COLUMN expires_at,
+  DROP COLUMN daily_request_quota;
@@ -0,0 +1,11 @@
+ALTER TABLE graphix_api_tokens
+  ADD COLUMN expires_at TIMESTAMP,
+  ADD COLUMN daily_request_quota INTEGER;
+
+-- GraphQL requests per API key and UTC day, to enforce daily quotas.
+CREATE TABLE api_key_daily_usage (
+  public_prefix TEXT NOT NULL REFERENCES graphix_api_tokens(public_prefix) ON DELETE CASCADE,
+  day DATE NOT NULL,
+  request_count BIGINT NOT NULL DEFAULT 0,
+  PRIMARY KEY (public_prefix, day)
+);
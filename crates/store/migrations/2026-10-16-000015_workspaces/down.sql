@@ -0,0 +1,16 @@
+DELETE FROM tracked_deployments WHERE workspace_id IS NOT NULL;
+DROP INDEX tracked_deployments_unscoped_ipfs_cid;
+ALTER TABLE tracked_deployments
+  DROP COLUMN workspace_id,
+  ADD CONSTRAINT tracked_deployments_ipfs_cid_key UNIQUE (ipfs_cid);
+
+DELETE FROM registered_indexers WHERE workspace_id IS NOT NULL;
+DROP INDEX registered_indexers_unscoped_address;
+ALTER TABLE registered_indexers
+  DROP COLUMN workspace_id,
+  ADD CONSTRAINT registered_indexers_address_key UNIQUE (address);
+
+DELETE FROM graphix_api_tokens WHERE workspace_id IS NOT NULL;
+ALTER TABLE graphix_api_tokens DROP COLUMN workspace_id;
+
+DROP TABLE workspaces;
@@ -0,0 +1,26 @@
+-- Tenants that share one Graphix instance and its collection pipeline, but
+-- have isolated views of API keys, registered indexers and tracked
+-- deployments.
+CREATE TABLE workspaces (
+  id SERIAL PRIMARY KEY,
+  name TEXT NOT NULL UNIQUE,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- A NULL `workspace_id` means that the row isn't scoped to any workspace.
+ALTER TABLE graphix_api_tokens
+  ADD COLUMN workspace_id INTEGER REFERENCES workspaces(id) ON DELETE CASCADE;
+
+ALTER TABLE registered_indexers
+  ADD COLUMN workspace_id INTEGER REFERENCES workspaces(id) ON DELETE CASCADE,
+  DROP CONSTRAINT registered_indexers_address_key,
+  ADD UNIQUE (workspace_id, address);
+CREATE UNIQUE INDEX registered_indexers_unscoped_address
+  ON registered_indexers (address) WHERE workspace_id IS NULL;
+
+ALTER TABLE tracked_deployments
+  ADD COLUMN workspace_id INTEGER REFERENCES workspaces(id) ON DELETE CASCADE,
+  DROP CONSTRAINT tracked_deployments_ipfs_cid_key,
+  ADD UNIQUE (workspace_id, ipfs_cid);
+CREATE UNIQUE INDEX tracked_deployments_unscoped_ipfs_cid
+  ON tracked_deployments (ipfs_cid) WHERE workspace_id IS NULL;
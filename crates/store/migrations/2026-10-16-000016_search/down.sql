@@ -0,0 +1,6 @@
+DROP INDEX indexers_address_hex_trgm;
+DROP INDEX indexers_name_trgm;
+DROP INDEX sg_names_name_trgm;
+DROP INDEX sg_deployments_ipfs_cid_trgm;
+
+DROP EXTENSION IF EXISTS pg_trgm;
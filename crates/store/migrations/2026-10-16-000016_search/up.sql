@@ -0,0 +1,8 @@
+-- Trigram indexes for the `search` query. `gin_trgm_ops` serves both prefix
+-- (`ILIKE 'term%'`) and similarity (`%`) matching.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX sg_deployments_ipfs_cid_trgm ON sg_deployments USING gin (ipfs_cid gin_trgm_ops);
+CREATE INDEX sg_names_name_trgm ON sg_names USING gin (name gin_trgm_ops);
+CREATE INDEX indexers_name_trgm ON indexers USING gin (name gin_trgm_ops);
+CREATE INDEX indexers_address_hex_trgm ON indexers USING gin (encode(address, 'hex') gin_trgm_ops);
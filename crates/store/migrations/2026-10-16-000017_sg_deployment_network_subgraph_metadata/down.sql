@@ -0,0 +1 @@
+DROP TABLE sg_deployment_network_subgraph_metadata;
@@ -0,0 +1,9 @@
+-- Display metadata and curation signal of subgraph deployments, as published
+-- on the network subgraph.
+CREATE TABLE sg_deployment_network_subgraph_metadata (
+  sg_deployment_id INTEGER PRIMARY KEY REFERENCES sg_deployments(id) ON DELETE CASCADE,
+  display_name TEXT,
+  image TEXT,
+  signal_amount NUMERIC NOT NULL,
+  last_updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
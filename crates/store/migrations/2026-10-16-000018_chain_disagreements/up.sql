@@ -0,0 +1,15 @@
+-- Indexers that reported PoIs for the same block number of a subgraph
+-- deployment, but for different block hashes. Unlike PoI divergences, these
+-- point at chain issues like reorgs or faulty RPC providers. There's one row
+-- per indexer and block hash.
+CREATE TABLE chain_disagreements (
+  id SERIAL PRIMARY KEY,
+  sg_deployment_id INTEGER NOT NULL REFERENCES sg_deployments(id) ON DELETE CASCADE,
+  block_number BIGINT NOT NULL,
+  indexer_id INTEGER NOT NULL REFERENCES indexers(id) ON DELETE CASCADE,
+  block_hash BYTEA NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  UNIQUE (sg_deployment_id, block_number, indexer_id)
+);
+
+CREATE INDEX ON chain_disagreements (created_at);
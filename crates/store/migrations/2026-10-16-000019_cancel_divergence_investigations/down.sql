@@ -0,0 +1 @@
+ALTER TABLE pending_divergence_investigation_requests DROP COLUMN cancelled;
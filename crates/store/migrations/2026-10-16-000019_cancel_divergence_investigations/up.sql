@@ -0,0 +1,4 @@
+-- Set by the `cancelDivergenceInvestigation` mutation. The request is only
+-- deleted once the investigation handler has stopped working on it.
+ALTER TABLE pending_divergence_investigation_requests
+  ADD COLUMN cancelled BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1 @@
+DROP TABLE raw_indexer_responses;
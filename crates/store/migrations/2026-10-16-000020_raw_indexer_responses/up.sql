@@ -0,0 +1,12 @@
+-- Raw response bodies returned by indexers during divergence investigations
+-- that opted into capturing them, for debugging.
+CREATE TABLE raw_indexer_responses (
+  id INTEGER PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+  investigation_uuid UUID NOT NULL REFERENCES divergence_investigation_reports(uuid) ON DELETE CASCADE,
+  indexer_id INTEGER NOT NULL REFERENCES indexers(id) ON DELETE CASCADE,
+  operation_name TEXT NOT NULL,
+  body TEXT NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX ON raw_indexer_responses (investigation_uuid);
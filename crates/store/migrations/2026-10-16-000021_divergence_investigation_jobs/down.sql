@@ -0,0 +1,13 @@
+-- Failed jobs would be picked up again as pending requests.
+DELETE FROM divergence_investigation_jobs WHERE status = 3;
+
+ALTER TABLE divergence_investigation_jobs
+  DROP COLUMN status,
+  DROP COLUMN attempts,
+  DROP COLUMN locked_by,
+  DROP COLUMN locked_at,
+  DROP COLUMN run_after,
+  DROP COLUMN last_error;
+
+ALTER TABLE divergence_investigation_jobs
+  RENAME TO pending_divergence_investigation_requests;
@@ -0,0 +1,16 @@
+-- Pending divergence investigation requests become jobs that workers claim
+-- with `SELECT ... FOR UPDATE SKIP LOCKED`. Failed attempts are retried after
+-- `run_after`, and jobs that ran out of attempts are kept for inspection.
+ALTER TABLE pending_divergence_investigation_requests
+  RENAME TO divergence_investigation_jobs;
+
+-- 1 = pending, 2 = running, 3 = failed.
+ALTER TABLE divergence_investigation_jobs
+  ADD COLUMN status INTEGER NOT NULL DEFAULT 1,
+  ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0,
+  ADD COLUMN locked_by TEXT,
+  ADD COLUMN locked_at TIMESTAMP,
+  ADD COLUMN run_after TIMESTAMP NOT NULL DEFAULT NOW(),
+  ADD COLUMN last_error TEXT;
+
+CREATE INDEX ON divergence_investigation_jobs (status, run_after);
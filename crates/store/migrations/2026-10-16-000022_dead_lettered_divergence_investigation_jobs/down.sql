@@ -0,0 +1,2 @@
+ALTER TABLE divergence_investigation_jobs
+  DROP COLUMN dead_lettered_at;
@@ -0,0 +1,7 @@
+-- Jobs that ran out of attempts (status 3) form the dead-letter queue.
+ALTER TABLE divergence_investigation_jobs
+  ADD COLUMN dead_lettered_at TIMESTAMP;
+
+UPDATE divergence_investigation_jobs
+  SET dead_lettered_at = run_after
+  WHERE status = 3;
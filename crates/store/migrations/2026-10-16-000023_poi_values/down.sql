@@ -0,0 +1,6 @@
+ALTER TABLE pois ADD COLUMN poi BYTEA;
+UPDATE pois SET poi = poi_values.poi FROM poi_values WHERE poi_values.id = pois.poi_value_id;
+ALTER TABLE pois ALTER COLUMN poi SET NOT NULL;
+ALTER TABLE pois DROP COLUMN poi_value_id;
+
+DROP TABLE poi_values;
@@ -0,0 +1,15 @@
+-- Indexers that agree on a PoI, and the same indexer across polling rounds,
+-- report the same value over and over, so each value is stored only once.
+CREATE TABLE poi_values (
+  id INTEGER PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+  poi BYTEA NOT NULL UNIQUE
+);
+
+INSERT INTO poi_values (poi) SELECT DISTINCT poi FROM pois;
+
+ALTER TABLE pois ADD COLUMN poi_value_id INTEGER REFERENCES poi_values(id);
+UPDATE pois SET poi_value_id = poi_values.id FROM poi_values WHERE poi_values.poi = pois.poi;
+ALTER TABLE pois ALTER COLUMN poi_value_id SET NOT NULL;
+ALTER TABLE pois DROP COLUMN poi;
+
+CREATE INDEX ON pois (poi_value_id);
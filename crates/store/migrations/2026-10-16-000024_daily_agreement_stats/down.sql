@@ -0,0 +1,2 @@
+DROP TABLE daily_indexer_agreement;
+DROP TABLE daily_deployment_agreement;
@@ -0,0 +1,22 @@
+-- Daily rollups of how well indexers agree on PoIs, by the day the PoIs were
+-- collected. Trend queries read these instead of aggregating over `pois`,
+-- and a periodic job recomputes the most recent days.
+CREATE TABLE daily_deployment_agreement (
+  day DATE NOT NULL,
+  sg_deployment_id INTEGER NOT NULL REFERENCES sg_deployments(id) ON DELETE CASCADE,
+  poi_count BIGINT NOT NULL,
+  indexer_count BIGINT NOT NULL,
+  -- Blocks for which at least two indexers reported different PoIs.
+  diverging_blocks BIGINT NOT NULL,
+  average_agreement_ratio DOUBLE PRECISION NOT NULL,
+  PRIMARY KEY (sg_deployment_id, day)
+);
+
+CREATE TABLE daily_indexer_agreement (
+  day DATE NOT NULL,
+  indexer_id INTEGER NOT NULL REFERENCES indexers(id) ON DELETE CASCADE,
+  poi_count BIGINT NOT NULL,
+  deployment_count BIGINT NOT NULL,
+  average_agreement_ratio DOUBLE PRECISION NOT NULL,
+  PRIMARY KEY (indexer_id, day)
+);
@@ -0,0 +1 @@
+DROP TABLE poi_disputes;
@@ -0,0 +1,28 @@
+-- Disputes about PoIs that indexers submitted on chain when closing
+-- allocations, correlated with the PoIs that Graphix collected for the same
+-- deployments and blocks.
+CREATE TABLE poi_disputes (
+  id SERIAL PRIMARY KEY,
+  dispute_id TEXT NOT NULL UNIQUE,
+  indexer_id INTEGER NOT NULL REFERENCES indexers(id) ON DELETE CASCADE,
+  sg_deployment_id INTEGER NOT NULL REFERENCES sg_deployments(id) ON DELETE CASCADE,
+  allocation_id TEXT NOT NULL,
+  block_number BIGINT NOT NULL,
+  disputed_poi BYTEA NOT NULL,
+  -- See `DisputeStatus`.
+  status INTEGER NOT NULL,
+  -- The PoI that Graphix collected from the disputed indexer for the same
+  -- deployment and block, if any.
+  collected_poi_id INTEGER REFERENCES pois(id) ON DELETE SET NULL,
+  -- How many other indexers Graphix collected the disputed PoI from, and
+  -- how many a different one, for the same deployment and block.
+  agreeing_indexers INTEGER NOT NULL,
+  disagreeing_indexers INTEGER NOT NULL,
+  opened_at TIMESTAMP NOT NULL,
+  closed_at TIMESTAMP,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX ON poi_disputes (indexer_id);
+CREATE INDEX ON poi_disputes (sg_deployment_id);
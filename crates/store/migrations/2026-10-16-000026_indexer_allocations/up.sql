@@ -0,0 +1,17 @@
+-- The active allocations that indexers' own `indexer-agent`s report, as
+-- opposed to what the network subgraph says. Each collection replaces all
+-- previous rows of the indexer.
+CREATE TABLE indexer_allocations (
+  id SERIAL PRIMARY KEY,
+  indexer_id INTEGER NOT NULL REFERENCES indexers(id) ON DELETE CASCADE,
+  allocation_id TEXT NOT NULL,
+  -- Not a reference to `sg_deployments`, as indexers may allocate to
+  -- deployments that Graphix doesn't know about.
+  deployment TEXT NOT NULL,
+  created_at_epoch BIGINT NOT NULL,
+  -- Whether the indexer reported an indexing status for the deployment when
+  -- the allocation was collected.
+  indexing BOOLEAN NOT NULL,
+  collected_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  UNIQUE (indexer_id, allocation_id)
+);
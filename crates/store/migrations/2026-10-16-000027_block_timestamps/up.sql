@@ -0,0 +1,5 @@
+-- When blocks were produced, either as reported by the chain's JSON-RPC
+-- endpoint or estimated from the chain's block speed. NULL if unknown.
+ALTER TABLE blocks ADD COLUMN timestamp TIMESTAMP;
+
+CREATE INDEX ON blocks (timestamp);
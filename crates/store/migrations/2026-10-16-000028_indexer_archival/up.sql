@@ -0,0 +1,3 @@
+-- Archived indexers, e.g. because they left the network, are hidden by
+-- default and no longer cross-checked, but their data is kept for audits.
+ALTER TABLE indexers ADD COLUMN archived_at TIMESTAMP;
@@ -0,0 +1,15 @@
+-- The names and endpoints that each indexer had over time. The current
+-- identity of an indexer is the one that's still valid, i.e. whose
+-- `valid_until` is NULL.
+CREATE TABLE indexer_identities (
+  id SERIAL PRIMARY KEY,
+  indexer_id INTEGER NOT NULL REFERENCES indexers(id) ON DELETE CASCADE,
+  name TEXT,
+  endpoint TEXT,
+  valid_from TIMESTAMP NOT NULL DEFAULT NOW(),
+  valid_until TIMESTAMP
+);
+
+CREATE INDEX ON indexer_identities (indexer_id, valid_from);
+CREATE UNIQUE INDEX indexer_identities_current
+  ON indexer_identities (indexer_id) WHERE valid_until IS NULL;
@@ -0,0 +1,11 @@
+-- Arbitrary tags of indexers, e.g. `upgrade-canary` or `eu-west`. Tags from
+-- the configuration are replaced on every polling round, while those set
+-- through the GraphQL API are kept until they're set again.
+CREATE TABLE indexer_tags (
+  indexer_id INTEGER NOT NULL REFERENCES indexers(id) ON DELETE CASCADE,
+  tag TEXT NOT NULL,
+  from_config BOOLEAN NOT NULL,
+  PRIMARY KEY (indexer_id, tag, from_config)
+);
+
+CREATE INDEX ON indexer_tags (tag);
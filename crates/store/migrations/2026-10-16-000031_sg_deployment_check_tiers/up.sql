@@ -0,0 +1,6 @@
+-- The cross-check tier that each subgraph deployment was last assigned to,
+-- numbered from 1. Tier 1 is cross-checked every polling round.
+CREATE TABLE sg_deployment_check_tiers (
+  sg_deployment_id INTEGER PRIMARY KEY REFERENCES sg_deployments(id) ON DELETE CASCADE,
+  tier INTEGER NOT NULL
+);
@@ -0,0 +1,1024 @@
+//! An in-memory implementation of [`PoiStore`] and [`ReportStore`], for tests
+//! and demos that shouldn't need a database.
+//!
+//! It mirrors the behavior of the Postgres [`Store`](crate::Store) as closely
+//! as possible, with one exception: networks aren't stored, so filtering
+//! subgraph deployments by network name never matches anything.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, ensure};
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use graphix_common_types::{
+    inputs, DivergenceInvestigationJobStatus, IndexerAddress, IpfsCid, PoiBytes,
+};
+use graphix_indexer_client::{BlockPointer, IndexerClient, ProofOfIndexing, RawResponse};
+use uuid::Uuid;
+
+use crate::models::{
+    self, BigIntId, BisectionAttempt, BisectionEvidence, BisectionRun, DivergenceInvestigationJob,
+    IntId, NewBisectionAttempt, NewBisectionEvidence, NewBisectionRun, Poi, RawIndexerResponse,
+    SgDeployment,
+};
+use crate::{PoiLiveness, PoiStore, ReportStore};
+
+/// Like the Postgres store, all deployments and blocks are assumed to be on
+/// mainnet, see also: hardcoded-mainnet.
+const NETWORK_ID: IntId = 1;
+
+/// A [`PoiStore`] and [`ReportStore`] that keeps everything in memory. Clones
+/// share the same data.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryStore {
+    tables: Arc<Mutex<Tables>>,
+}
+
+#[derive(Debug, Default)]
+struct Tables {
+    indexers: Vec<models::Indexer>,
+    sg_deployments: Vec<SgDeployment>,
+    blocks: Vec<models::Block>,
+    pois: Vec<Poi>,
+    live_poi_ids: BTreeSet<IntId>,
+    jobs: Vec<DivergenceInvestigationJob>,
+    reports: Vec<Report>,
+    bisection_runs: Vec<BisectionRun>,
+    bisection_attempts: Vec<BisectionAttempt>,
+    bisection_evidence: Vec<BisectionEvidence>,
+    raw_indexer_responses: Vec<RawIndexerResponse>,
+}
+
+#[derive(Debug)]
+struct Report {
+    uuid: Uuid,
+    report: serde_json::Value,
+    created_at: NaiveDateTime,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn tables(&self) -> MutexGuard<Tables> {
+        self.tables.lock().unwrap()
+    }
+}
+
+// Rows are never deleted from tables with serial IDs, so the IDs are simply
+// their 1-based positions.
+impl Tables {
+    fn indexer(&self, id: IntId) -> &models::Indexer {
+        &self.indexers[id as usize - 1]
+    }
+
+    fn sg_deployment(&self, id: IntId) -> &SgDeployment {
+        &self.sg_deployments[id as usize - 1]
+    }
+
+    fn block(&self, id: BigIntId) -> &models::Block {
+        &self.blocks[id as usize - 1]
+    }
+
+    fn poi(&self, id: IntId) -> &Poi {
+        &self.pois[id as usize - 1]
+    }
+
+    fn indexer_id(&self, name: Option<&str>, address: &IndexerAddress) -> Option<IntId> {
+        self.indexers
+            .iter()
+            .find(|indexer| indexer.name.as_deref() == name && indexer.address == *address)
+            .map(|indexer| indexer.id)
+    }
+
+    fn indexer_ids_by_address(&self, address: &IndexerAddress) -> Vec<IntId> {
+        self.indexers
+            .iter()
+            .filter(|indexer| indexer.address == *address)
+            .map(|indexer| indexer.id)
+            .collect()
+    }
+
+    fn sg_deployment_id(&self, cid: &IpfsCid) -> Option<IntId> {
+        self.sg_deployments
+            .iter()
+            .find(|deployment| deployment.cid == *cid)
+            .map(|deployment| deployment.id)
+    }
+
+    fn get_or_insert_sg_deployment(&mut self, cid: &IpfsCid) -> IntId {
+        if let Some(id) = self.sg_deployment_id(cid) {
+            return id;
+        }
+
+        let id = self.sg_deployments.len() as IntId + 1;
+        self.sg_deployments.push(SgDeployment {
+            id,
+            cid: cid.clone(),
+            name: None,
+            network_id: NETWORK_ID,
+            created_at: Utc::now().naive_utc(),
+        });
+        id
+    }
+
+    /// Finds the block by hash, or by number if the hash is unknown.
+    fn get_or_insert_block(&mut self, block: &BlockPointer) -> anyhow::Result<BigIntId> {
+        let number = i64::try_from(block.number)?;
+        let existing_block = self.blocks.iter().find(|b| match &block.hash {
+            Some(hash) => b.hash.as_ref() == Some(hash),
+            None => b.number == number && b.hash.is_none(),
+        });
+        if let Some(existing_block) = existing_block {
+            return Ok(existing_block.id);
+        }
+
+        let id = self.blocks.len() as BigIntId + 1;
+        self.blocks.push(models::Block {
+            id,
+            network_id: NETWORK_ID,
+            number,
+            hash: block.hash.clone(),
+            canonical: None,
+            timestamp: None,
+        });
+        Ok(id)
+    }
+
+    /// The fraction of indexers that reported the same PoI as `poi` for the
+    /// same subgraph deployment and block.
+    fn poi_agreement_ratio(&self, poi: &Poi) -> f64 {
+        let mut indexers = BTreeSet::new();
+        let mut agreeing_indexers = BTreeSet::new();
+        for other in &self.pois {
+            if other.sg_deployment_id == poi.sg_deployment_id && other.block_id == poi.block_id {
+                indexers.insert(other.indexer_id);
+                if other.poi == poi.poi {
+                    agreeing_indexers.insert(other.indexer_id);
+                }
+            }
+        }
+        agreeing_indexers.len() as f64 / indexers.len() as f64
+    }
+
+    /// The fraction of live PoIs that agree with the live PoIs of the given
+    /// indexer, on average across subgraph deployments. [`None`] if the
+    /// indexer has no live PoIs.
+    fn indexer_agreement_ratio(&self, indexer_id: IntId) -> Option<f64> {
+        let live_pois: Vec<&Poi> = self.live_poi_ids.iter().map(|id| self.poi(*id)).collect();
+        let ratios: Vec<f64> = live_pois
+            .iter()
+            .filter(|own| own.indexer_id == indexer_id)
+            .map(|own| {
+                let others: Vec<&&Poi> = live_pois
+                    .iter()
+                    .filter(|other| other.sg_deployment_id == own.sg_deployment_id)
+                    .collect();
+                let agreeing = others.iter().filter(|other| other.poi == own.poi).count();
+                agreeing as f64 / others.len() as f64
+            })
+            .collect();
+
+        if ratios.is_empty() {
+            None
+        } else {
+            Some(ratios.iter().sum::<f64>() / ratios.len() as f64)
+        }
+    }
+
+    fn pois(
+        &self,
+        indexer_address: Option<&IndexerAddress>,
+        sg_deployments: Option<&[IpfsCid]>,
+        block_range: Option<inputs::BlockRange>,
+        limit: Option<u16>,
+        live_only: bool,
+        order: inputs::PoisOrder,
+    ) -> Vec<Poi> {
+        use inputs::PoisOrderBy as By;
+
+        let mut pois: Vec<&Poi> = self
+            .pois
+            .iter()
+            .filter(|poi| {
+                let block = self.block(poi.block_id);
+                block_range.as_ref().map_or(true, |range| {
+                    u64::try_from(block.number).is_ok_and(|number| range.contains(&number))
+                        && range.contains_timestamp(block.timestamp)
+                })
+            })
+            .filter(|poi| {
+                sg_deployments.map_or(true, |cids| {
+                    cids.contains(&self.sg_deployment(poi.sg_deployment_id).cid)
+                })
+            })
+            .filter(|poi| {
+                indexer_address.map_or(true, |address| {
+                    self.indexer(poi.indexer_id).address == *address
+                })
+            })
+            .filter(|poi| !live_only || self.live_poi_ids.contains(&poi.id))
+            .collect();
+
+        let agreement_ratios: HashMap<IntId, f64> = if order.by == By::AgreementRatio {
+            pois.iter()
+                .map(|poi| (poi.id, self.poi_agreement_ratio(poi)))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        pois.sort_by(|a, b| {
+            let ordering = match order.by {
+                By::BlockNumber => self
+                    .block(a.block_id)
+                    .number
+                    .cmp(&self.block(b.block_id).number),
+                By::CreatedAt => a.created_at.cmp(&b.created_at),
+                By::AgreementRatio => agreement_ratios[&a.id].total_cmp(&agreement_ratios[&b.id]),
+            };
+            // Most recent first among equals.
+            directed(ordering, order.direction)
+                .then_with(|| b.created_at.cmp(&a.created_at))
+                .then_with(|| b.id.cmp(&a.id))
+        });
+
+        pois.into_iter()
+            .take(limit.map_or(usize::MAX, usize::from))
+            .cloned()
+            .collect()
+    }
+
+    fn report(&self, uuid: &Uuid) -> Option<&Report> {
+        self.reports.iter().find(|report| report.uuid == *uuid)
+    }
+
+    /// The job if it's running on behalf of `worker`, the only one that may
+    /// acknowledge it.
+    fn running_job(
+        &mut self,
+        uuid: &Uuid,
+        worker: &str,
+    ) -> Option<&mut DivergenceInvestigationJob> {
+        self.jobs.iter_mut().find(|job| {
+            job.uuid == *uuid
+                && job.status == DivergenceInvestigationJobStatus::Running
+                && job.locked_by.as_deref() == Some(worker)
+        })
+    }
+
+    /// Jobs that aren't dead-lettered, like the requests that the Postgres
+    /// store considers to exist.
+    fn live_job(&mut self, uuid: &Uuid) -> Option<&mut DivergenceInvestigationJob> {
+        self.jobs.iter_mut().find(|job| {
+            job.uuid == *uuid && job.status != DivergenceInvestigationJobStatus::DeadLettered
+        })
+    }
+}
+
+fn directed(ordering: Ordering, direction: inputs::OrderDirection) -> Ordering {
+    match direction {
+        inputs::OrderDirection::Asc => ordering,
+        inputs::OrderDirection::Desc => ordering.reverse(),
+    }
+}
+
+/// Like `NULLS LAST` in SQL, [`None`] comes last in both directions.
+fn directed_nulls_last<T: PartialOrd>(
+    a: Option<T>,
+    b: Option<T>,
+    direction: inputs::OrderDirection,
+) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => directed(a.partial_cmp(&b).unwrap_or(Ordering::Equal), direction),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+#[async_trait]
+impl PoiStore for InMemoryStore {
+    async fn write_indexers(&self, indexers: &[Arc<dyn IndexerClient>]) -> anyhow::Result<()> {
+        let mut tables = self.tables();
+        for indexer in indexers {
+            let address = indexer.address();
+            if tables.indexers.iter().any(|i| i.address == address) {
+                continue;
+            }
+
+            let id = tables.indexers.len() as IntId + 1;
+            tables.indexers.push(models::Indexer {
+                id,
+                address,
+                name: indexer.name().map(|s| s.to_string()),
+                graph_node_version: None,
+                network_subgraph_metadata: None,
+                created_at: Utc::now().naive_utc(),
+                archived_at: None,
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn write_pois(
+        &self,
+        pois: Vec<ProofOfIndexing>,
+        live: PoiLiveness,
+    ) -> anyhow::Result<()> {
+        let mut tables = self.tables();
+
+        // Validate everything before writing anything, like the Postgres store
+        // does within a transaction.
+        let mut grouped_pois: BTreeMap<&IpfsCid, Vec<(IntId, &ProofOfIndexing)>> = BTreeMap::new();
+        for poi in &pois {
+            let name = poi.indexer.name();
+            let address = poi.indexer.address();
+            let indexer_id = tables
+                .indexer_id(name.as_deref(), &address)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Indexer with name {:?} and/or address {:?} not found",
+                        name,
+                        address
+                    )
+                })?;
+            grouped_pois
+                .entry(&poi.deployment)
+                .or_default()
+                .push((indexer_id, poi));
+        }
+        for poi_group in grouped_pois.values() {
+            let block_ptr = &poi_group[0].1.block;
+            if !poi_group.iter().all(|(_, poi)| poi.block == *block_ptr) {
+                bail!("All PoIs for a given deployment must have the same block");
+            }
+            i64::try_from(block_ptr.number)?;
+        }
+
+        for (deployment, poi_group) in grouped_pois {
+            let sg_deployment_id = tables.get_or_insert_sg_deployment(deployment);
+            let block_id = tables.get_or_insert_block(&poi_group[0].1.block)?;
+
+            let mut new_poi_ids = vec![];
+            for (indexer_id, poi) in &poi_group {
+                let id = tables.pois.len() as IntId + 1;
+                tables.pois.push(Poi {
+                    id,
+                    poi: poi.proof_of_indexing,
+                    sg_deployment_id,
+                    indexer_id: *indexer_id,
+                    block_id,
+                    created_at: Utc::now().naive_utc(),
+                });
+                new_poi_ids.push(id);
+            }
+
+            if live == PoiLiveness::Live {
+                // The new PoIs replace the live PoIs of the same indexers and
+                // those at other blocks, like in the Postgres store.
+                let replaced: Vec<IntId> = tables
+                    .live_poi_ids
+                    .iter()
+                    .copied()
+                    .filter(|id| {
+                        let poi = tables.poi(*id);
+                        poi.sg_deployment_id == sg_deployment_id
+                            && (poi_group
+                                .iter()
+                                .any(|(indexer_id, _)| poi.indexer_id == *indexer_id)
+                                || poi.block_id != block_id)
+                    })
+                    .collect();
+                for id in replaced {
+                    tables.live_poi_ids.remove(&id);
+                }
+                tables.live_poi_ids.extend(new_poi_ids);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn indexers(
+        &self,
+        filter: inputs::IndexersQuery,
+    ) -> anyhow::Result<Vec<models::Indexer>> {
+        use inputs::IndexersOrderBy as By;
+
+        let tables = self.tables();
+        let mut indexers: Vec<&models::Indexer> = tables
+            .indexers
+            .iter()
+            .filter(|indexer| {
+                filter
+                    .address
+                    .map_or(true, |address| indexer.address == address)
+            })
+            .filter(|indexer| {
+                filter.include_archived || filter.address.is_some() || indexer.archived_at.is_none()
+            })
+            // Indexers aren't tagged in memory.
+            .filter(|_| filter.tags.is_empty())
+            .collect();
+
+        let agreement_ratios: HashMap<IntId, Option<f64>> = if filter.order_by == By::AgreementRatio
+        {
+            indexers
+                .iter()
+                .map(|indexer| (indexer.id, tables.indexer_agreement_ratio(indexer.id)))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        let dir = filter.order_direction;
+        indexers.sort_by(|a, b| {
+            let ordering = match filter.order_by {
+                By::Address => directed(a.address.cmp(&b.address), dir),
+                By::Name => directed_nulls_last(a.name.as_ref(), b.name.as_ref(), dir),
+                By::CreatedAt => directed(a.created_at.cmp(&b.created_at), dir),
+                By::AgreementRatio => {
+                    directed_nulls_last(agreement_ratios[&a.id], agreement_ratios[&b.id], dir)
+                }
+            };
+            ordering.then_with(|| a.id.cmp(&b.id))
+        });
+
+        Ok(indexers
+            .into_iter()
+            .take(filter.limit.map_or(usize::MAX, usize::from))
+            .cloned()
+            .collect())
+    }
+
+    async fn sg_deployments(
+        &self,
+        filter: inputs::SgDeploymentsQuery,
+    ) -> anyhow::Result<Vec<SgDeployment>> {
+        use inputs::SgDeploymentsOrderBy as By;
+
+        let tables = self.tables();
+        let mut deployments: Vec<&SgDeployment> = tables
+            .sg_deployments
+            .iter()
+            .filter(|_| filter.network_name.is_none())
+            .filter(|deployment| {
+                filter
+                    .name
+                    .as_ref()
+                    .map_or(true, |name| deployment.name.as_ref() == Some(name))
+            })
+            .filter(|deployment| {
+                filter
+                    .ipfs_cid
+                    .as_ref()
+                    .map_or(true, |cid| deployment.cid == *cid)
+            })
+            .collect();
+
+        let dir = filter.order_direction;
+        deployments.sort_by(|a, b| {
+            let ordering = match filter.order_by {
+                By::IpfsCid => directed(a.cid.to_string().cmp(&b.cid.to_string()), dir),
+                By::Name => directed_nulls_last(a.name.as_ref(), b.name.as_ref(), dir),
+                By::CreatedAt => directed(a.created_at.cmp(&b.created_at), dir),
+            };
+            ordering.then_with(|| a.cid.to_string().cmp(&b.cid.to_string()))
+        });
+
+        Ok(deployments
+            .into_iter()
+            .take(filter.limit.map_or(usize::MAX, usize::from))
+            .cloned()
+            .collect())
+    }
+
+    async fn poi(&self, poi: &PoiBytes) -> anyhow::Result<Option<Poi>> {
+        Ok(self.tables().pois.iter().find(|p| p.poi == *poi).cloned())
+    }
+
+    async fn pois_by_value(&self, pois: &[PoiBytes]) -> anyhow::Result<Vec<Poi>> {
+        Ok(self
+            .tables()
+            .pois
+            .iter()
+            .filter(|p| pois.contains(&p.poi))
+            .cloned()
+            .collect())
+    }
+
+    async fn pois(
+        &self,
+        sg_deployments: &[IpfsCid],
+        block_range: Option<inputs::BlockRange>,
+        limit: Option<u16>,
+        order: inputs::PoisOrder,
+    ) -> anyhow::Result<Vec<Poi>> {
+        Ok(self
+            .tables()
+            .pois(None, Some(sg_deployments), block_range, limit, false, order))
+    }
+
+    async fn live_pois(
+        &self,
+        indexer_address: Option<&IndexerAddress>,
+        sg_deployments_cids: Option<&[IpfsCid]>,
+        block_range: Option<inputs::BlockRange>,
+        limit: Option<u16>,
+    ) -> anyhow::Result<Vec<Poi>> {
+        Ok(self.tables().pois(
+            indexer_address,
+            sg_deployments_cids,
+            block_range,
+            limit,
+            true,
+            inputs::PoisOrder::default(),
+        ))
+    }
+
+    async fn indexer_poi_at_block(
+        &self,
+        indexer: &Arc<dyn IndexerClient>,
+        deployment: &IpfsCid,
+        block_number: u64,
+    ) -> anyhow::Result<Option<(Poi, models::Block)>> {
+        let block_number = i64::try_from(block_number)?;
+        let tables = self.tables();
+        let Some(indexer_id) = tables.indexer_id(indexer.name().as_deref(), &indexer.address())
+        else {
+            return Ok(None);
+        };
+        let Some(sg_deployment_id) = tables.sg_deployment_id(deployment) else {
+            return Ok(None);
+        };
+
+        Ok(tables
+            .pois
+            .iter()
+            .filter(|poi| poi.indexer_id == indexer_id && poi.sg_deployment_id == sg_deployment_id)
+            .filter(|poi| tables.block(poi.block_id).number == block_number)
+            .max_by_key(|poi| (poi.created_at, poi.id))
+            .map(|poi| (poi.clone(), tables.block(poi.block_id).clone())))
+    }
+}
+
+#[async_trait]
+impl ReportStore for InMemoryStore {
+    async fn create_divergence_investigation_request(
+        &self,
+        request: serde_json::Value,
+    ) -> anyhow::Result<Uuid> {
+        let uuid = Uuid::new_v4();
+        let now = Utc::now().naive_utc();
+        self.tables().jobs.push(DivergenceInvestigationJob {
+            uuid,
+            request,
+            created_at: now,
+            cancelled: false,
+            status: DivergenceInvestigationJobStatus::Pending,
+            attempts: 0,
+            locked_by: None,
+            locked_at: None,
+            run_after: now,
+            last_error: None,
+            dead_lettered_at: None,
+        });
+        Ok(uuid)
+    }
+
+    async fn claim_divergence_investigation_job(
+        &self,
+        worker: &str,
+    ) -> anyhow::Result<Option<DivergenceInvestigationJob>> {
+        let now = Utc::now().naive_utc();
+        let mut tables = self.tables();
+        // Jobs are stored in creation order.
+        let Some(job) = tables.jobs.iter_mut().find(|job| {
+            job.status == DivergenceInvestigationJobStatus::Pending && job.run_after <= now
+        }) else {
+            return Ok(None);
+        };
+
+        job.status = DivergenceInvestigationJobStatus::Running;
+        job.attempts += 1;
+        job.locked_by = Some(worker.to_string());
+        job.locked_at = Some(now);
+        Ok(Some(job.clone()))
+    }
+
+    async fn requeue_stale_divergence_investigation_jobs(
+        &self,
+        stale_after: Duration,
+        max_attempts: i32,
+    ) -> anyhow::Result<u64> {
+        let now = Utc::now().naive_utc();
+        let locked_before = now - chrono::Duration::from_std(stale_after)?;
+        let mut stale = 0;
+        for job in &mut self.tables().jobs {
+            if job.status == DivergenceInvestigationJobStatus::Running
+                && job
+                    .locked_at
+                    .is_some_and(|locked_at| locked_at < locked_before)
+            {
+                if job.attempts >= max_attempts {
+                    job.status = DivergenceInvestigationJobStatus::DeadLettered;
+                    job.dead_lettered_at = Some(now);
+                } else {
+                    job.status = DivergenceInvestigationJobStatus::Pending;
+                }
+                stale += 1;
+            }
+        }
+        Ok(stale)
+    }
+
+    async fn ack_divergence_investigation_job(
+        &self,
+        uuid: &Uuid,
+        worker: &str,
+    ) -> anyhow::Result<bool> {
+        let mut tables = self.tables();
+        if tables.running_job(uuid, worker).is_none() {
+            return Ok(false);
+        }
+        tables.jobs.retain(|job| job.uuid != *uuid);
+        Ok(true)
+    }
+
+    async fn nack_divergence_investigation_job(
+        &self,
+        uuid: &Uuid,
+        worker: &str,
+        error: &str,
+        retry_at: Option<NaiveDateTime>,
+    ) -> anyhow::Result<bool> {
+        let now = Utc::now().naive_utc();
+        let mut tables = self.tables();
+        let Some(job) = tables.running_job(uuid, worker) else {
+            return Ok(false);
+        };
+        (job.status, job.dead_lettered_at) = match retry_at {
+            Some(_) => (DivergenceInvestigationJobStatus::Pending, None),
+            None => (DivergenceInvestigationJobStatus::DeadLettered, Some(now)),
+        };
+        job.run_after = retry_at.unwrap_or(now);
+        job.last_error = Some(error.to_string());
+        Ok(true)
+    }
+
+    async fn requeue_dead_lettered_divergence_investigation_job(
+        &self,
+        uuid: &Uuid,
+    ) -> anyhow::Result<bool> {
+        let mut tables = self.tables();
+        let Some(job) = tables.jobs.iter_mut().find(|job| {
+            job.uuid == *uuid && job.status == DivergenceInvestigationJobStatus::DeadLettered
+        }) else {
+            return Ok(false);
+        };
+        job.status = DivergenceInvestigationJobStatus::Pending;
+        job.attempts = 0;
+        job.run_after = Utc::now().naive_utc();
+        job.dead_lettered_at = None;
+        Ok(true)
+    }
+
+    async fn divergence_investigation_jobs(
+        &self,
+        status: Option<DivergenceInvestigationJobStatus>,
+    ) -> anyhow::Result<Vec<DivergenceInvestigationJob>> {
+        Ok(self
+            .tables()
+            .jobs
+            .iter()
+            .filter(|job| status.map_or(true, |status| job.status == status))
+            .cloned()
+            .collect())
+    }
+
+    async fn divergence_investigation_request_exists(&self, uuid: &Uuid) -> anyhow::Result<bool> {
+        Ok(self.tables().live_job(uuid).is_some())
+    }
+
+    async fn cancel_divergence_investigation_request(&self, uuid: &Uuid) -> anyhow::Result<bool> {
+        match self.tables().live_job(uuid) {
+            Some(job) => {
+                job.cancelled = true;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn divergence_investigation_request_cancelled(
+        &self,
+        uuid: &Uuid,
+    ) -> anyhow::Result<bool> {
+        Ok(self
+            .tables()
+            .jobs
+            .iter()
+            .any(|job| job.uuid == *uuid && job.cancelled))
+    }
+
+    async fn pending_divergence_investigation_requests_count(&self) -> anyhow::Result<u64> {
+        Ok(self
+            .tables()
+            .jobs
+            .iter()
+            .filter(|job| job.status != DivergenceInvestigationJobStatus::DeadLettered)
+            .count() as u64)
+    }
+
+    async fn delete_divergence_investigation_request(&self, uuid: &Uuid) -> anyhow::Result<()> {
+        self.tables().jobs.retain(|job| job.uuid != *uuid);
+        Ok(())
+    }
+
+    async fn create_or_update_divergence_investigation_report(
+        &self,
+        uuid: &Uuid,
+        report: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        let mut tables = self.tables();
+        match tables.reports.iter_mut().find(|r| r.uuid == *uuid) {
+            Some(existing) => existing.report = report,
+            None => tables.reports.push(Report {
+                uuid: *uuid,
+                report,
+                created_at: Utc::now().naive_utc(),
+            }),
+        }
+        Ok(())
+    }
+
+    async fn divergence_investigation_report(
+        &self,
+        uuid: &Uuid,
+    ) -> anyhow::Result<Option<serde_json::Value>> {
+        Ok(self.tables().report(uuid).map(|r| r.report.clone()))
+    }
+
+    async fn divergence_investigation_reports(
+        &self,
+        filter: inputs::DivergenceInvestigationsQuery,
+    ) -> anyhow::Result<Vec<serde_json::Value>> {
+        let tables = self.tables();
+        let status = filter.status.map(serde_json::to_value).transpose()?;
+        let indexer_ids = filter
+            .indexer
+            .map(|address| tables.indexer_ids_by_address(&address));
+        let runs = |uuid: Uuid| {
+            tables
+                .bisection_runs
+                .iter()
+                .filter(move |run| run.investigation_uuid == uuid)
+        };
+
+        // Most recent first, and among equals the last one written.
+        let mut reports: Vec<&Report> = tables
+            .reports
+            .iter()
+            .rev()
+            .filter(|report| {
+                filter.deployment.as_ref().map_or(true, |deployment| {
+                    runs(report.uuid).any(|run| {
+                        run.sg_deployment_id
+                            .is_some_and(|id| tables.sg_deployment(id).cid == *deployment)
+                    })
+                })
+            })
+            .filter(|report| {
+                indexer_ids.as_ref().map_or(true, |ids| {
+                    runs(report.uuid).any(|run| {
+                        [run.indexer1_id, run.indexer2_id]
+                            .iter()
+                            .flatten()
+                            .any(|id| ids.contains(id))
+                    })
+                })
+            })
+            .filter(|report| {
+                status
+                    .as_ref()
+                    .map_or(true, |status| report.report.get("status") == Some(status))
+            })
+            .filter(|report| {
+                filter
+                    .created_after
+                    .map_or(true, |t| report.created_at >= t)
+            })
+            .filter(|report| {
+                filter
+                    .created_before
+                    .map_or(true, |t| report.created_at <= t)
+            })
+            .collect();
+        reports.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+        Ok(reports
+            .into_iter()
+            .take(filter.limit.map_or(usize::MAX, usize::from))
+            .map(|report| report.report.clone())
+            .collect())
+    }
+
+    async fn write_bisection_run(
+        &self,
+        run: NewBisectionRun,
+        attempts: Vec<NewBisectionAttempt>,
+        evidence: Vec<NewBisectionEvidence>,
+    ) -> anyhow::Result<()> {
+        let mut tables = self.tables();
+        ensure!(
+            tables.report(&run.investigation_uuid).is_some(),
+            "Divergence investigation report {} not found",
+            run.investigation_uuid
+        );
+        ensure!(
+            !tables.bisection_runs.iter().any(|r| r.uuid == run.uuid),
+            "Bisection run {} already exists",
+            run.uuid
+        );
+        let run_uuids: BTreeSet<Uuid> = tables
+            .bisection_runs
+            .iter()
+            .map(|r| r.uuid)
+            .chain([run.uuid])
+            .collect();
+        ensure!(
+            attempts
+                .iter()
+                .all(|attempt| run_uuids.contains(&attempt.bisection_run_uuid))
+                && evidence
+                    .iter()
+                    .all(|evidence| run_uuids.contains(&evidence.bisection_run_uuid)),
+            "Bisection attempts and evidence must belong to an existing bisection run"
+        );
+
+        tables.bisection_runs.push(BisectionRun {
+            uuid: run.uuid,
+            investigation_uuid: run.investigation_uuid,
+            sg_deployment_id: run.sg_deployment_id,
+            indexer1_id: run.indexer1_id,
+            indexer2_id: run.indexer2_id,
+            poi1: run.poi1,
+            poi2: run.poi2,
+            lower_bound: run.lower_bound,
+            upper_bound: run.upper_bound,
+            error: run.error,
+            created_at: Utc::now().naive_utc(),
+        });
+        for attempt in attempts {
+            let id = tables.bisection_attempts.len() as IntId + 1;
+            tables.bisection_attempts.push(BisectionAttempt {
+                id,
+                bisection_run_uuid: attempt.bisection_run_uuid,
+                block_number: attempt.block_number,
+                block_hash: attempt.block_hash,
+                indexer1_response: attempt.indexer1_response,
+                indexer2_response: attempt.indexer2_response,
+            });
+        }
+        for evidence in evidence {
+            let id = tables.bisection_evidence.len() as IntId + 1;
+            tables.bisection_evidence.push(BisectionEvidence {
+                id,
+                bisection_run_uuid: evidence.bisection_run_uuid,
+                indexer_id: evidence.indexer_id,
+                block_number: evidence.block_number,
+                kind: evidence.kind,
+                contents: evidence.contents,
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn bisection_runs(
+        &self,
+        filter: inputs::BisectionRunsQuery,
+    ) -> anyhow::Result<Vec<BisectionRun>> {
+        let block_number = filter.block_number.map(i64::try_from).transpose()?;
+        let tables = self.tables();
+        let indexer_ids = filter
+            .indexer
+            .map(|address| tables.indexer_ids_by_address(&address));
+
+        let mut runs: Vec<&BisectionRun> = tables
+            .bisection_runs
+            .iter()
+            .rev()
+            .filter(|run| {
+                filter.deployment.as_ref().map_or(true, |deployment| {
+                    run.sg_deployment_id
+                        .is_some_and(|id| tables.sg_deployment(id).cid == *deployment)
+                })
+            })
+            .filter(|run| {
+                indexer_ids.as_ref().map_or(true, |ids| {
+                    [run.indexer1_id, run.indexer2_id]
+                        .iter()
+                        .flatten()
+                        .any(|id| ids.contains(id))
+                })
+            })
+            .filter(|run| {
+                block_number.map_or(true, |number| {
+                    run.lower_bound <= number && number <= run.upper_bound
+                })
+            })
+            .collect();
+        runs.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+        Ok(runs
+            .into_iter()
+            .take(filter.limit.map_or(usize::MAX, usize::from))
+            .cloned()
+            .collect())
+    }
+
+    async fn bisection_run(&self, uuid: &Uuid) -> anyhow::Result<Option<BisectionRun>> {
+        Ok(self
+            .tables()
+            .bisection_runs
+            .iter()
+            .find(|run| run.uuid == *uuid)
+            .cloned())
+    }
+
+    async fn bisection_attempts(
+        &self,
+        bisection_run_uuid: &Uuid,
+    ) -> anyhow::Result<Vec<BisectionAttempt>> {
+        Ok(self
+            .tables()
+            .bisection_attempts
+            .iter()
+            .filter(|attempt| attempt.bisection_run_uuid == *bisection_run_uuid)
+            .cloned()
+            .collect())
+    }
+
+    async fn bisection_evidence(
+        &self,
+        bisection_run_uuid: &Uuid,
+    ) -> anyhow::Result<Vec<BisectionEvidence>> {
+        Ok(self
+            .tables()
+            .bisection_evidence
+            .iter()
+            .filter(|evidence| evidence.bisection_run_uuid == *bisection_run_uuid)
+            .cloned()
+            .collect())
+    }
+
+    async fn write_raw_indexer_responses(
+        &self,
+        investigation_uuid: &Uuid,
+        responses: Vec<RawResponse>,
+    ) -> anyhow::Result<()> {
+        let mut tables = self.tables();
+        ensure!(
+            tables.report(investigation_uuid).is_some(),
+            "Divergence investigation report {} not found",
+            investigation_uuid
+        );
+
+        for response in responses {
+            let Some(indexer_id) =
+                tables.indexer_id(response.indexer_name.as_deref(), &response.indexer_address)
+            else {
+                continue;
+            };
+            let id = tables.raw_indexer_responses.len() as IntId + 1;
+            tables.raw_indexer_responses.push(RawIndexerResponse {
+                id,
+                investigation_uuid: *investigation_uuid,
+                indexer_id,
+                operation_name: response.operation_name,
+                body: response.body,
+                created_at: Utc::now().naive_utc(),
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn raw_indexer_responses(
+        &self,
+        investigation_uuid: &Uuid,
+    ) -> anyhow::Result<Vec<RawIndexerResponse>> {
+        Ok(self
+            .tables()
+            .raw_indexer_responses
+            .iter()
+            .filter(|response| response.investigation_uuid == *investigation_uuid)
+            .cloned()
+            .collect())
+    }
+}
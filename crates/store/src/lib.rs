@@ -1,9 +1,16 @@
 //! Database access (read and write) abstractions for the Graphix backend.
 
+mod in_memory;
 mod loader;
+mod metrics;
 pub mod models;
 mod schema;
 mod store;
+mod traits;
 
+pub use in_memory::InMemoryStore;
 pub use loader::StoreLoader;
-pub use store::{PoiLiveness, Store};
+pub use store::{
+    IndexBloat, LeaderLock, MigrationStatus, PoiLiveness, PoolStatus, Store, TableSize, HOT_TABLES,
+};
+pub use traits::{PoiStore, ReportStore};
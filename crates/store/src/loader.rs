@@ -44,9 +44,11 @@ impl async_graphql::dataloader::Loader<IntId> for StoreLoader<models::Poi> {
     type Error = String;
 
     async fn load(&self, keys: &[IntId]) -> Result<HashMap<IntId, Self::Value>, Self::Error> {
-        use schema::pois;
+        use schema::{poi_values, pois};
 
         Ok(pois::table
+            .inner_join(poi_values::table)
+            .select(models::Poi::as_select())
             .filter(pois::id.eq_any(keys))
             .load::<models::Poi>(&mut self.store.conn_err_string().await?)
             .await
@@ -83,6 +85,50 @@ impl async_graphql::dataloader::Loader<IntId> for StoreLoader<models::SgDeployme
     }
 }
 
+/// Keyed by subgraph deployment ID.
+impl async_graphql::dataloader::Loader<IntId> for StoreLoader<models::SgDeploymentManifest> {
+    type Value = models::SgDeploymentManifest;
+    type Error = String;
+
+    async fn load(&self, keys: &[IntId]) -> Result<HashMap<IntId, Self::Value>, Self::Error> {
+        use schema::sg_deployment_manifests as manifests;
+
+        Ok(manifests::table
+            .select(models::SgDeploymentManifest::as_select())
+            .filter(manifests::sg_deployment_id.eq_any(keys))
+            .load::<models::SgDeploymentManifest>(&mut self.store.conn_err_string().await?)
+            .await
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|manifest| (manifest.sg_deployment_id, manifest))
+            .collect())
+    }
+}
+
+/// Keyed by subgraph deployment ID.
+impl async_graphql::dataloader::Loader<IntId>
+    for StoreLoader<models::SgDeploymentNetworkSubgraphMetadata>
+{
+    type Value = models::SgDeploymentNetworkSubgraphMetadata;
+    type Error = String;
+
+    async fn load(&self, keys: &[IntId]) -> Result<HashMap<IntId, Self::Value>, Self::Error> {
+        use schema::sg_deployment_network_subgraph_metadata as metadata;
+
+        Ok(metadata::table
+            .select(models::SgDeploymentNetworkSubgraphMetadata::as_select())
+            .filter(metadata::sg_deployment_id.eq_any(keys))
+            .load::<models::SgDeploymentNetworkSubgraphMetadata>(
+                &mut self.store.conn_err_string().await?,
+            )
+            .await
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|metadata| (metadata.sg_deployment_id, metadata))
+            .collect())
+    }
+}
+
 impl async_graphql::dataloader::Loader<IntId> for StoreLoader<models::Network> {
     type Value = models::Network;
     type Error = String;
@@ -0,0 +1,133 @@
+//! Prometheus metrics of database queries, in the default Prometheus
+//! registry.
+
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// Most queries take milliseconds, but PoI queries with wide filters and
+/// maintenance work can take until the statement timeout.
+const DURATION_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+const ROW_COUNT_BUCKETS: &[f64] = &[
+    0.0,
+    1.0,
+    10.0,
+    100.0,
+    1_000.0,
+    10_000.0,
+    100_000.0,
+    1_000_000.0,
+];
+
+struct QueryMetrics {
+    durations: prometheus::HistogramVec,
+    rows: prometheus::HistogramVec,
+}
+
+impl QueryMetrics {
+    fn register() -> Self {
+        Self {
+            durations: prometheus::register_histogram_vec!(
+                "graphix_database_query_duration_seconds",
+                "Durations of database queries, including failed ones, in seconds",
+                &["query", "success"],
+                DURATION_BUCKETS.to_vec()
+            )
+            .unwrap(),
+            rows: prometheus::register_histogram_vec!(
+                "graphix_database_query_rows",
+                "Number of rows returned by database queries",
+                &["query"],
+                ROW_COUNT_BUCKETS.to_vec()
+            )
+            .unwrap(),
+        }
+    }
+}
+
+/// The results of queries whose number of rows is recorded.
+pub(crate) trait RowCount {
+    fn row_count(&self) -> Option<usize>;
+}
+
+impl<T> RowCount for Vec<T> {
+    fn row_count(&self) -> Option<usize> {
+        Some(self.len())
+    }
+}
+
+impl<T> RowCount for Option<T> {
+    fn row_count(&self) -> Option<usize> {
+        Some(self.is_some().into())
+    }
+}
+
+impl RowCount for () {
+    fn row_count(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Runs the database query `query`, and records how long it took and how
+/// many rows it returned under the given name.
+pub(crate) async fn instrumented<T: RowCount>(
+    name: &'static str,
+    query: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    static QUERY_METRICS: OnceLock<QueryMetrics> = OnceLock::new();
+
+    let start = Instant::now();
+    let result = query.await;
+    let metrics = QUERY_METRICS.get_or_init(QueryMetrics::register);
+    let success = if result.is_ok() { "true" } else { "false" };
+    metrics
+        .durations
+        .with_label_values(&[name, success])
+        .observe(start.elapsed().as_secs_f64());
+    if let Some(rows) = result.as_ref().ok().and_then(RowCount::row_count) {
+        metrics.rows.with_label_values(&[name]).observe(rows as f64);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_count(name: &str, labels: &[(&str, &str)]) -> u64 {
+        prometheus::gather()
+            .into_iter()
+            .filter(|family| family.get_name() == name)
+            .flat_map(|family| family.get_metric().to_vec())
+            .filter(|metric| {
+                labels.iter().all(|(name, value)| {
+                    metric
+                        .get_label()
+                        .iter()
+                        .any(|pair| pair.get_name() == *name && pair.get_value() == *value)
+                })
+            })
+            .map(|metric| metric.get_histogram().get_sample_count())
+            .sum()
+    }
+
+    #[tokio::test]
+    async fn records_durations_and_rows() {
+        instrumented("test_query", async { Ok(vec![1, 2, 3]) })
+            .await
+            .unwrap();
+        instrumented::<()>("test_query", async { Err(anyhow::anyhow!("failed")) })
+            .await
+            .unwrap_err();
+
+        let durations = "graphix_database_query_duration_seconds";
+        let query = ("query", "test_query");
+        assert_eq!(sample_count(durations, &[query, ("success", "true")]), 1);
+        assert_eq!(sample_count(durations, &[query, ("success", "false")]), 1);
+        assert_eq!(sample_count("graphix_database_query_rows", &[query]), 1);
+    }
+}
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::sync::Arc;
 
 use async_graphql::SimpleObject;
 use bigdecimal::BigDecimal;
@@ -7,9 +8,14 @@ use diesel::backend::Backend;
 use diesel::deserialize::FromSql;
 use diesel::pg::Pg;
 use diesel::sql_types::Jsonb;
-use diesel::{AsChangeset, AsExpression, FromSqlRow, Insertable, Queryable, Selectable};
-use graphix_common_types::{self as types, ApiKeyPermissionLevel};
-use graphix_indexer_client::IndexerId;
+use diesel::{
+    AsChangeset, AsExpression, FromSqlRow, Insertable, Queryable, QueryableByName, Selectable,
+};
+use graphix_common_types::{
+    self as types, ApiKeyPermissionLevel, BisectionEvidenceKind, DisputeStatus,
+    DivergenceInvestigationJobStatus, SubgraphHealth,
+};
+use graphix_indexer_client::{IndexerClient, IndexerId};
 use serde::{Deserialize, Serialize};
 use sha2::Digest;
 use types::{BlockHash, IndexerAddress, IpfsCid, PoiBytes};
@@ -30,9 +36,13 @@ pub struct FailedQueryRow {
     pub timestamp: NaiveDateTime,
 }
 
-#[derive(Queryable, Serialize, Debug, Clone)]
+#[derive(Queryable, Selectable, Serialize, Debug, Clone)]
+#[diesel(table_name = pois)]
 pub struct Poi {
     pub id: IntId,
+    /// PoI values are stored once in `poi_values`, which queries for PoIs
+    /// must join.
+    #[diesel(select_expression = poi_values::poi)]
     pub poi: PoiBytes,
     pub sg_deployment_id: IntId,
     pub indexer_id: IntId,
@@ -48,6 +58,16 @@ pub struct NewGraphNodeCollectedVersion {
     pub error_response: Option<String>,
 }
 
+/// The subgraph API versions of a deployment, or the error that occurred
+/// while querying them.
+#[derive(Insertable, Debug)]
+#[diesel(table_name = sg_deployment_api_versions)]
+pub struct NewSgDeploymentApiVersions {
+    pub sg_deployment_id: IntId,
+    pub api_versions: Option<Vec<Option<String>>>,
+    pub error: Option<String>,
+}
+
 #[derive(Queryable, Clone, Selectable, Debug, SimpleObject)]
 #[diesel(table_name = graph_node_collected_versions)]
 pub struct GraphNodeCollectedVersion {
@@ -59,6 +79,39 @@ pub struct GraphNodeCollectedVersion {
     pub collected_at: NaiveDateTime,
 }
 
+/// A `graph-node` version that was observed for an indexer, at least once
+/// between `firstSeenAt` and `lastSeenAt`.
+#[derive(Queryable, Clone, Selectable, Debug, SimpleObject)]
+#[diesel(table_name = indexer_graph_node_versions)]
+pub struct IndexerGraphNodeVersion {
+    #[graphql(skip)]
+    pub id: IntId,
+    #[graphql(skip)]
+    pub indexer_id: IntId,
+    pub version_string: Option<String>,
+    pub version_commit: Option<String>,
+    pub first_seen_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+}
+
+/// A name and endpoint that an indexer had from `validFrom` until
+/// `validUntil`.
+#[derive(Queryable, Clone, Selectable, Debug, SimpleObject)]
+#[diesel(table_name = indexer_identities)]
+pub struct IndexerIdentity {
+    #[graphql(skip)]
+    pub id: IntId,
+    #[graphql(skip)]
+    pub indexer_id: IntId,
+    pub name: Option<String>,
+    /// The URL of the indexer's `graph-node` index-node endpoint, if it was
+    /// reached through one.
+    pub endpoint: Option<String>,
+    pub valid_from: NaiveDateTime,
+    /// Null for the current identity.
+    pub valid_until: Option<NaiveDateTime>,
+}
+
 impl GraphNodeCollectedVersion {
     pub fn into_common_type(self) -> types::GraphNodeCollectedVersion {
         types::GraphNodeCollectedVersion {
@@ -73,7 +126,7 @@ impl GraphNodeCollectedVersion {
 #[derive(Insertable, Debug)]
 #[diesel(table_name = pois)]
 pub struct NewPoi {
-    pub poi: PoiBytes,
+    pub poi_value_id: IntId,
     pub created_at: NaiveDateTime,
     pub sg_deployment_id: IntId,
     pub indexer_id: IntId,
@@ -85,7 +138,12 @@ pub struct Block {
     pub id: BigIntId,
     pub network_id: IntId,
     pub number: i64,
-    pub hash: BlockHash,
+    /// [`None`] if indexers didn't report it.
+    pub hash: Option<BlockHash>,
+    /// Whether the block is part of the canonical chain, if verified.
+    pub canonical: Option<bool>,
+    /// When the block was produced, if known.
+    pub timestamp: Option<NaiveDateTime>,
 }
 
 #[derive(Debug, Insertable)]
@@ -93,7 +151,7 @@ pub struct Block {
 pub struct NewBlock {
     pub network_id: IntId,
     pub number: i64,
-    pub hash: BlockHash,
+    pub hash: Option<BlockHash>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -102,6 +160,524 @@ pub struct DivergenceInvestigationRequest {
     pub query_block_caches: bool,
     pub query_eth_call_caches: bool,
     pub query_entity_changes: bool,
+    /// Bisects all indexers that produced one of the PoIs at once, instead
+    /// of each pair of PoIs.
+    #[serde(default)]
+    pub cohort_bisection: bool,
+    /// Stores the raw response bodies returned by indexers during the
+    /// investigation, for debugging.
+    #[serde(default)]
+    pub capture_raw_responses: bool,
+}
+
+/// A PoI that an indexer submitted on chain when closing an allocation,
+/// compared against the PoI Graphix collected from it.
+#[derive(Debug, Clone, Queryable, Selectable, Serialize)]
+#[diesel(table_name = allocation_pois)]
+pub struct AllocationPoi {
+    pub id: IntId,
+    pub allocation_id: String,
+    pub indexer_id: IntId,
+    pub sg_deployment_id: IntId,
+    pub closed_at_epoch: i32,
+    pub block_number: i64,
+    pub submitted_poi: PoiBytes,
+    pub collected_poi_id: Option<IntId>,
+    pub matches: Option<bool>,
+    pub closed_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = allocation_pois)]
+pub struct NewAllocationPoi {
+    pub allocation_id: String,
+    pub indexer_id: IntId,
+    pub sg_deployment_id: IntId,
+    pub closed_at_epoch: i32,
+    pub block_number: i64,
+    pub submitted_poi: PoiBytes,
+    pub collected_poi_id: Option<IntId>,
+    pub matches: Option<bool>,
+    pub closed_at: NaiveDateTime,
+}
+
+/// An on-chain dispute about a PoI that an indexer submitted when closing an
+/// allocation, correlated with the PoIs Graphix collected for the same
+/// deployment and block.
+#[derive(Debug, Clone, Queryable, Selectable, Serialize)]
+#[diesel(table_name = poi_disputes)]
+pub struct PoiDispute {
+    pub id: IntId,
+    pub dispute_id: String,
+    pub indexer_id: IntId,
+    pub sg_deployment_id: IntId,
+    pub allocation_id: String,
+    pub block_number: i64,
+    pub disputed_poi: PoiBytes,
+    pub status: DisputeStatus,
+    pub collected_poi_id: Option<IntId>,
+    pub agreeing_indexers: i32,
+    pub disagreeing_indexers: i32,
+    pub opened_at: NaiveDateTime,
+    pub closed_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[diesel(table_name = poi_disputes)]
+#[diesel(treat_none_as_null = true)]
+pub struct NewPoiDispute {
+    pub dispute_id: String,
+    pub indexer_id: IntId,
+    pub sg_deployment_id: IntId,
+    pub allocation_id: String,
+    pub block_number: i64,
+    pub disputed_poi: PoiBytes,
+    pub status: DisputeStatus,
+    pub collected_poi_id: Option<IntId>,
+    pub agreeing_indexers: i32,
+    pub disagreeing_indexers: i32,
+    pub opened_at: NaiveDateTime,
+    pub closed_at: Option<NaiveDateTime>,
+}
+
+/// An active allocation, as reported by the indexer's `indexer-agent`.
+#[derive(Debug, Clone, Queryable, Selectable, Serialize)]
+#[diesel(table_name = indexer_allocations)]
+pub struct IndexerAllocation {
+    pub id: IntId,
+    pub indexer_id: IntId,
+    pub allocation_id: String,
+    pub deployment: String,
+    pub created_at_epoch: i64,
+    pub indexing: bool,
+    pub collected_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = indexer_allocations)]
+pub struct NewIndexerAllocation {
+    pub indexer_id: IntId,
+    pub allocation_id: String,
+    pub deployment: String,
+    pub created_at_epoch: i64,
+    pub indexing: bool,
+}
+
+/// An allocation that the allocating indexer doesn't seem to serve, either
+/// because it's not indexing the deployment or because Graphix has no PoIs
+/// of the indexer for it.
+#[derive(Debug, Clone, Serialize)]
+pub struct AllocationDiscrepancy {
+    pub allocation: IndexerAllocation,
+    pub has_live_pois: bool,
+}
+
+/// An indexer going up or down, as observed by the uptime prober.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable)]
+#[diesel(table_name = indexer_uptime_transitions)]
+pub struct IndexerUptimeTransition {
+    pub id: IntId,
+    pub indexer_id: IntId,
+    pub up: bool,
+    pub observed_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = indexer_uptime_transitions)]
+pub struct NewIndexerUptimeTransition {
+    pub indexer_id: IntId,
+    pub up: bool,
+}
+
+/// The number of entities an indexer reported for a deployment at some point
+/// in time.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = entity_counts)]
+pub struct EntityCount {
+    pub id: IntId,
+    pub indexer_id: IntId,
+    pub sg_deployment_id: IntId,
+    pub block_number: i64,
+    pub entity_count: i64,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = entity_counts)]
+pub struct NewEntityCount {
+    pub indexer_id: IntId,
+    pub sg_deployment_id: IntId,
+    pub block_number: i64,
+    pub entity_count: i64,
+}
+
+/// The indexing status an indexer reported for a deployment during one main
+/// loop iteration.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = indexing_status_history)]
+pub struct IndexingStatusSnapshot {
+    pub id: IntId,
+    pub indexer_id: IntId,
+    pub sg_deployment_id: IntId,
+    pub latest_block_number: i64,
+    pub health: SubgraphHealth,
+    pub synced: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = indexing_status_history)]
+pub struct NewIndexingStatusSnapshot {
+    pub indexer_id: IntId,
+    pub sg_deployment_id: IntId,
+    pub latest_block_number: i64,
+    pub health: SubgraphHealth,
+    pub synced: bool,
+}
+
+/// A subgraph manifest fetched from IPFS.
+#[derive(Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = sg_deployment_manifests)]
+pub struct SgDeploymentManifest {
+    pub sg_deployment_id: IntId,
+    pub spec_version: String,
+    pub features: Vec<String>,
+    pub graft_base: Option<String>,
+    pub graft_block: Option<i64>,
+    pub raw_manifest: String,
+    pub fetched_at: NaiveDateTime,
+}
+
+#[derive(Insertable, AsChangeset, Debug)]
+#[diesel(table_name = sg_deployment_manifests)]
+pub struct NewSgDeploymentManifest {
+    pub sg_deployment_id: IntId,
+    pub spec_version: String,
+    pub features: Vec<String>,
+    pub graft_base: Option<String>,
+    pub graft_block: Option<i64>,
+    pub raw_manifest: String,
+}
+
+/// A data source or data source template of a subgraph manifest.
+#[derive(Queryable, Selectable, Debug, Clone, SimpleObject)]
+#[diesel(table_name = sg_deployment_data_sources)]
+pub struct SgDeploymentDataSource {
+    #[graphql(skip)]
+    pub id: IntId,
+    #[graphql(skip)]
+    pub sg_deployment_id: IntId,
+    #[graphql(skip)]
+    pub is_template: bool,
+    pub kind: String,
+    pub name: String,
+    pub network: Option<String>,
+    /// The address of the contract the data source indexes, if any.
+    pub address: Option<String>,
+    pub start_block: Option<i64>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = sg_deployment_data_sources)]
+pub struct NewSgDeploymentDataSource {
+    pub sg_deployment_id: IntId,
+    pub is_template: bool,
+    pub kind: String,
+    pub name: String,
+    pub network: Option<String>,
+    pub address: Option<String>,
+    pub start_block: Option<i64>,
+}
+
+/// The display metadata and curation signal of a subgraph deployment, as
+/// published on the network subgraph.
+#[derive(Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = sg_deployment_network_subgraph_metadata)]
+pub struct SgDeploymentNetworkSubgraphMetadata {
+    pub sg_deployment_id: IntId,
+    pub display_name: Option<String>,
+    pub image: Option<String>,
+    pub signal_amount: BigDecimal,
+    pub last_updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable, AsChangeset, Debug)]
+#[diesel(table_name = sg_deployment_network_subgraph_metadata)]
+pub struct NewSgDeploymentNetworkSubgraphMetadata {
+    pub sg_deployment_id: IntId,
+    pub display_name: Option<String>,
+    pub image: Option<String>,
+    pub signal_amount: BigDecimal,
+}
+
+/// Subgraph deployment metadata fetched from the network subgraph, before
+/// it's stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchedSgDeploymentMetadata {
+    pub deployment: IpfsCid,
+    pub display_name: Option<String>,
+    pub image: Option<String>,
+    pub signal_amount: BigDecimal,
+}
+
+/// A parsed subgraph manifest, before it's stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchedSubgraphManifest {
+    pub spec_version: String,
+    pub features: Vec<String>,
+    pub graft_base: Option<String>,
+    pub graft_block: Option<i64>,
+    pub raw_manifest: String,
+    pub data_sources: Vec<FetchedDataSource>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchedDataSource {
+    pub is_template: bool,
+    pub kind: String,
+    pub name: String,
+    pub network: Option<String>,
+    pub address: Option<String>,
+    pub start_block: Option<i64>,
+}
+
+/// A PoI submitted on chain in a `closeAllocation` transaction, as reported
+/// by the network subgraph.
+#[derive(Debug, Clone)]
+pub struct SubmittedAllocationPoi {
+    pub allocation_id: String,
+    pub indexer_address: IndexerAddress,
+    pub deployment: IpfsCid,
+    pub closed_at_epoch: i32,
+    /// The block the PoI was computed for.
+    pub block_number: i64,
+    pub poi: PoiBytes,
+    pub closed_at: NaiveDateTime,
+}
+
+/// A dispute about a PoI submitted on chain, as reported by the network
+/// subgraph.
+#[derive(Debug, Clone)]
+pub struct OnChainPoiDispute {
+    pub dispute_id: String,
+    pub indexer_address: IndexerAddress,
+    pub deployment: IpfsCid,
+    pub allocation_id: String,
+    /// The block the disputed PoI was computed for.
+    pub block_number: i64,
+    pub poi: PoiBytes,
+    pub status: DisputeStatus,
+    pub opened_at: NaiveDateTime,
+    pub closed_at: Option<NaiveDateTime>,
+}
+
+/// Indexers that reported PoIs for the same block number of a subgraph
+/// deployment, but for different block hashes, during a main loop iteration.
+#[derive(Debug, Clone)]
+pub struct DetectedChainDisagreement {
+    pub deployment: IpfsCid,
+    pub block_number: u64,
+    pub block_hashes: Vec<(Arc<dyn IndexerClient>, BlockHash)>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = chain_disagreements)]
+pub struct NewChainDisagreementReport {
+    pub sg_deployment_id: IntId,
+    pub block_number: i64,
+    pub indexer_id: IntId,
+    pub block_hash: BlockHash,
+}
+
+/// The block hash that a single indexer reported as part of a
+/// [`ChainDisagreement`].
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = chain_disagreements)]
+pub struct ChainDisagreementReport {
+    pub id: IntId,
+    pub sg_deployment_id: IntId,
+    pub block_number: i64,
+    pub indexer_id: IntId,
+    pub block_hash: BlockHash,
+    pub created_at: NaiveDateTime,
+}
+
+/// All stored reports of indexers about a block of a subgraph deployment
+/// whose hash they disagree on.
+#[derive(Debug, Clone)]
+pub struct ChainDisagreement {
+    pub sg_deployment_id: IntId,
+    pub block_number: i64,
+    pub reports: Vec<ChainDisagreementReport>,
+}
+
+/// How many of the PoIs requested from an indexer during a main loop iteration
+/// it returned.
+#[derive(Debug, Clone)]
+pub struct PoiRequestOutcome {
+    pub indexer: Arc<dyn IndexerClient>,
+    pub requested: usize,
+    pub returned: usize,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = poi_request_outcomes)]
+pub struct NewPoiRequestOutcome {
+    pub indexer_id: IntId,
+    pub requested: i32,
+    pub returned: i32,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize)]
+#[diesel(table_name = bisection_runs)]
+pub struct BisectionRun {
+    pub uuid: Uuid,
+    pub investigation_uuid: Uuid,
+    pub sg_deployment_id: Option<IntId>,
+    pub indexer1_id: Option<IntId>,
+    pub indexer2_id: Option<IntId>,
+    pub poi1: PoiBytes,
+    pub poi2: PoiBytes,
+    pub lower_bound: i64,
+    pub upper_bound: i64,
+    pub error: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = bisection_runs)]
+pub struct NewBisectionRun {
+    pub uuid: Uuid,
+    pub investigation_uuid: Uuid,
+    pub sg_deployment_id: Option<IntId>,
+    pub indexer1_id: Option<IntId>,
+    pub indexer2_id: Option<IntId>,
+    pub poi1: PoiBytes,
+    pub poi2: PoiBytes,
+    pub lower_bound: i64,
+    pub upper_bound: i64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize)]
+#[diesel(table_name = bisection_attempts)]
+pub struct BisectionAttempt {
+    pub id: IntId,
+    pub bisection_run_uuid: Uuid,
+    pub block_number: i64,
+    pub block_hash: Option<BlockHash>,
+    pub indexer1_response: String,
+    pub indexer2_response: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = bisection_attempts)]
+pub struct NewBisectionAttempt {
+    pub bisection_run_uuid: Uuid,
+    pub block_number: i64,
+    pub block_hash: Option<BlockHash>,
+    pub indexer1_response: String,
+    pub indexer2_response: String,
+}
+
+#[derive(Debug, Clone, Queryable, Selectable, Serialize)]
+#[diesel(table_name = bisection_evidence)]
+pub struct BisectionEvidence {
+    pub id: IntId,
+    pub bisection_run_uuid: Uuid,
+    pub indexer_id: IntId,
+    pub block_number: i64,
+    pub kind: BisectionEvidenceKind,
+    pub contents: serde_json::Value,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = bisection_evidence)]
+pub struct NewBisectionEvidence {
+    pub bisection_run_uuid: Uuid,
+    pub indexer_id: IntId,
+    pub block_number: i64,
+    pub kind: BisectionEvidenceKind,
+    pub contents: serde_json::Value,
+}
+
+/// A divergence investigation request in the job queue.
+#[derive(Debug, Clone, Queryable, Selectable, Serialize)]
+#[diesel(table_name = divergence_investigation_jobs)]
+pub struct DivergenceInvestigationJob {
+    pub uuid: Uuid,
+    pub request: serde_json::Value,
+    pub created_at: NaiveDateTime,
+    pub cancelled: bool,
+    pub status: DivergenceInvestigationJobStatus,
+    /// The number of times a worker started on the job.
+    pub attempts: i32,
+    /// The worker that claimed the job most recently.
+    pub locked_by: Option<String>,
+    pub locked_at: Option<NaiveDateTime>,
+    /// Pending jobs aren't claimed before this time, so that failed attempts
+    /// are retried with a delay.
+    pub run_after: NaiveDateTime,
+    /// The error of the most recent failed attempt.
+    pub last_error: Option<String>,
+    /// When the job ran out of attempts, if it's in the dead-letter queue.
+    pub dead_lettered_at: Option<NaiveDateTime>,
+}
+
+/// A raw response body that an indexer returned during a divergence
+/// investigation, captured for debugging.
+#[derive(Debug, Clone, Queryable, Selectable, Serialize)]
+#[diesel(table_name = raw_indexer_responses)]
+pub struct RawIndexerResponse {
+    pub id: IntId,
+    pub investigation_uuid: Uuid,
+    pub indexer_id: IntId,
+    pub operation_name: String,
+    pub body: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = raw_indexer_responses)]
+pub struct NewRawIndexerResponse {
+    pub investigation_uuid: Uuid,
+    pub indexer_id: IntId,
+    pub operation_name: String,
+    pub body: String,
+}
+
+/// An indexer that was added at runtime through the GraphQL API.
+#[derive(Debug, Clone, Queryable, Selectable, Serialize)]
+#[diesel(table_name = registered_indexers)]
+pub struct RegisteredIndexer {
+    pub id: IntId,
+    pub address: IndexerAddress,
+    pub name: Option<String>,
+    pub index_node_endpoint: String,
+    pub created_at: NaiveDateTime,
+    pub workspace_id: Option<IntId>,
+}
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[diesel(table_name = registered_indexers)]
+pub struct NewRegisteredIndexer {
+    pub address: IndexerAddress,
+    pub name: Option<String>,
+    pub index_node_endpoint: String,
+    pub workspace_id: Option<IntId>,
+}
+
+/// A tenant of a Graphix instance, with its own API keys, registered
+/// indexers and tracked deployments.
+#[derive(Debug, Clone, Queryable, Selectable, SimpleObject)]
+#[diesel(table_name = workspaces)]
+pub struct Workspace {
+    pub id: IntId,
+    pub name: String,
+    pub created_at: NaiveDateTime,
 }
 
 #[derive(Debug, Clone, Queryable, Selectable, Serialize)]
@@ -114,6 +690,8 @@ pub struct Indexer {
     pub network_subgraph_metadata: Option<IntId>,
     #[serde(skip)]
     pub created_at: NaiveDateTime,
+    /// When the indexer was archived, if it was.
+    pub archived_at: Option<NaiveDateTime>,
 }
 
 impl IndexerId for Indexer {
@@ -133,6 +711,9 @@ pub struct ApiKeyDbRow {
     pub sha256_api_key_hash: Vec<u8>,
     pub notes: Option<String>,
     pub permission_level: ApiKeyPermissionLevel,
+    pub expires_at: Option<NaiveDateTime>,
+    pub daily_request_quota: Option<i32>,
+    pub workspace_id: Option<IntId>,
 }
 
 #[derive(Debug, Clone, SimpleObject)]
@@ -140,6 +721,12 @@ pub struct ApiKeyPublicMetadata {
     pub public_prefix: String,
     pub notes: Option<String>,
     pub permission_level: ApiKeyPermissionLevel,
+    /// After this time, requests made with the API key are rejected.
+    pub expires_at: Option<NaiveDateTime>,
+    /// The maximum number of GraphQL requests per UTC day.
+    pub daily_request_quota: Option<i32>,
+    /// The workspace that the API key is scoped to, if any.
+    pub workspace_id: Option<IntId>,
 }
 
 impl From<ApiKeyDbRow> for ApiKeyPublicMetadata {
@@ -148,10 +735,34 @@ impl From<ApiKeyDbRow> for ApiKeyPublicMetadata {
             public_prefix: sak.public_prefix,
             notes: sak.notes,
             permission_level: sak.permission_level,
+            expires_at: sak.expires_at,
+            daily_request_quota: sak.daily_request_quota,
+            workspace_id: sak.workspace_id,
         }
     }
 }
 
+/// The limits of an API key, together with how much of its daily quota has
+/// been used up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiKeyLimits {
+    pub expires_at: Option<NaiveDateTime>,
+    pub daily_request_quota: Option<i32>,
+    pub requests_today: i64,
+}
+
+/// Cumulative GraphQL API usage of an API key.
+#[derive(Debug, Clone, Queryable, Selectable, SimpleObject)]
+#[diesel(table_name = api_key_usage)]
+pub struct ApiKeyUsage {
+    pub public_prefix: String,
+    /// The number of GraphQL requests made with the API key.
+    pub query_count: i64,
+    /// The total time spent executing those requests, in milliseconds.
+    pub total_execution_time_ms: i64,
+    pub last_used_at: NaiveDateTime,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ApiKey {
     public_part: Uuid,
@@ -215,6 +826,77 @@ pub struct Network {
     pub caip2: Option<String>,
 }
 
+/// Aggregate statistics about a network.
+#[derive(QueryableByName, Debug)]
+pub struct NetworkStats {
+    /// Deployments on the watchlist, in any or a single workspace.
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub tracked_deployments: i64,
+    /// Indexers with live PoIs.
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub active_indexers: i64,
+    /// The fraction of indexers that agree with each live PoI, on average.
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Double>)]
+    pub average_agreement_ratio: Option<f64>,
+    /// How many blocks indexers are behind the chain head, according to
+    /// their indexing statuses of the last hour.
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::BigInt>)]
+    pub chain_head_lag_median: Option<i64>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::BigInt>)]
+    pub chain_head_lag_p90: Option<i64>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::BigInt>)]
+    pub chain_head_lag_p99: Option<i64>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::BigInt>)]
+    pub chain_head_lag_max: Option<i64>,
+}
+
+/// How many indexers agree with a live PoI, among all indexers with a live
+/// PoI for the same subgraph deployment.
+#[derive(QueryableByName, Debug)]
+pub struct PoiAgreementCounts {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub poi_id: IntId,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub total_indexers: i64,
+    /// Including the indexer of the PoI itself.
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub agreeing_indexers: i64,
+    /// Whether more than half of the indexers agree on any PoI.
+    #[diesel(sql_type = diesel::sql_types::Bool)]
+    pub has_consensus: bool,
+    /// Whether more than half of the indexers agree with this PoI.
+    #[diesel(sql_type = diesel::sql_types::Bool)]
+    pub in_consensus: bool,
+}
+
+/// How well indexers agreed on the PoIs of a subgraph deployment that were
+/// collected on a given day.
+#[derive(Debug, Clone, Queryable, Selectable, SimpleObject)]
+#[diesel(table_name = daily_deployment_agreement)]
+pub struct DailyDeploymentAgreement {
+    pub day: chrono::NaiveDate,
+    pub poi_count: i64,
+    /// The number of indexers that reported PoIs.
+    pub indexer_count: i64,
+    /// The number of blocks for which indexers reported different PoIs.
+    pub diverging_blocks: i64,
+    /// The fraction of indexers that agreed with each PoI, on average.
+    pub average_agreement_ratio: f64,
+}
+
+/// How well the PoIs that an indexer reported on a given day agreed with
+/// those of other indexers.
+#[derive(Debug, Clone, Queryable, Selectable, SimpleObject)]
+#[diesel(table_name = daily_indexer_agreement)]
+pub struct DailyIndexerAgreement {
+    pub day: chrono::NaiveDate,
+    pub poi_count: i64,
+    /// The number of subgraph deployments that the indexer reported PoIs for.
+    pub deployment_count: i64,
+    /// The fraction of indexers that agreed with each PoI, on average.
+    pub average_agreement_ratio: f64,
+}
+
 #[derive(Debug, Insertable, AsChangeset, Serialize)]
 #[diesel(table_name = indexer_network_subgraph_metadata)]
 pub struct NewIndexerNetworkSubgraphMetadata {
@@ -279,6 +961,17 @@ pub struct SgDeployment {
     pub created_at: NaiveDateTime,
 }
 
+/// A subgraph deployment on the watchlist of deployments to cross-check.
+#[derive(Debug, Clone, Queryable, Serialize, async_graphql::SimpleObject)]
+pub struct TrackedDeployment {
+    pub cid: IpfsCid,
+    /// The name of the network that the deployment indexes.
+    pub network: String,
+    pub created_at: NaiveDateTime,
+    /// The workspace whose watchlist the deployment is on, if any.
+    pub workspace_id: Option<IntId>,
+}
+
 #[derive(Debug, Insertable)]
 #[diesel(table_name = sg_deployments)]
 pub struct NewSgDeployment {
@@ -1,11 +1,95 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    allocation_pois (id) {
+        id -> Int4,
+        allocation_id -> Text,
+        indexer_id -> Int4,
+        sg_deployment_id -> Int4,
+        closed_at_epoch -> Int4,
+        block_number -> Int8,
+        submitted_poi -> Bytea,
+        collected_poi_id -> Nullable<Int4>,
+        matches -> Nullable<Bool>,
+        closed_at -> Timestamp,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    api_key_daily_usage (public_prefix, day) {
+        public_prefix -> Text,
+        day -> Date,
+        request_count -> Int8,
+    }
+}
+
+diesel::table! {
+    api_key_usage (public_prefix) {
+        public_prefix -> Text,
+        query_count -> Int8,
+        total_execution_time_ms -> Int8,
+        last_used_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    bisection_attempts (id) {
+        id -> Int4,
+        bisection_run_uuid -> Uuid,
+        block_number -> Int8,
+        block_hash -> Nullable<Bytea>,
+        indexer1_response -> Text,
+        indexer2_response -> Text,
+    }
+}
+
+diesel::table! {
+    bisection_evidence (id) {
+        id -> Int4,
+        bisection_run_uuid -> Uuid,
+        indexer_id -> Int4,
+        block_number -> Int8,
+        kind -> Int4,
+        contents -> Jsonb,
+    }
+}
+
+diesel::table! {
+    bisection_runs (uuid) {
+        uuid -> Uuid,
+        investigation_uuid -> Uuid,
+        sg_deployment_id -> Nullable<Int4>,
+        indexer1_id -> Nullable<Int4>,
+        indexer2_id -> Nullable<Int4>,
+        poi1 -> Bytea,
+        poi2 -> Bytea,
+        lower_bound -> Int8,
+        upper_bound -> Int8,
+        error -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     blocks (id) {
         id -> Int8,
         network_id -> Int4,
         number -> Int8,
-        hash -> Bytea,
+        hash -> Nullable<Bytea>,
+        canonical -> Nullable<Bool>,
+        timestamp -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    chain_disagreements (id) {
+        id -> Int4,
+        sg_deployment_id -> Int4,
+        block_number -> Int8,
+        indexer_id -> Int4,
+        block_hash -> Bytea,
+        created_at -> Timestamp,
     }
 }
 
@@ -16,6 +100,43 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    daily_deployment_agreement (sg_deployment_id, day) {
+        day -> Date,
+        sg_deployment_id -> Int4,
+        poi_count -> Int8,
+        indexer_count -> Int8,
+        diverging_blocks -> Int8,
+        average_agreement_ratio -> Float8,
+    }
+}
+
+diesel::table! {
+    daily_indexer_agreement (indexer_id, day) {
+        day -> Date,
+        indexer_id -> Int4,
+        poi_count -> Int8,
+        deployment_count -> Int8,
+        average_agreement_ratio -> Float8,
+    }
+}
+
+diesel::table! {
+    divergence_investigation_jobs (uuid) {
+        uuid -> Uuid,
+        request -> Jsonb,
+        created_at -> Timestamp,
+        cancelled -> Bool,
+        status -> Int4,
+        attempts -> Int4,
+        locked_by -> Nullable<Text>,
+        locked_at -> Nullable<Timestamp>,
+        run_after -> Timestamp,
+        last_error -> Nullable<Text>,
+        dead_lettered_at -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     divergence_investigation_reports (uuid) {
         uuid -> Uuid,
@@ -24,6 +145,17 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    entity_counts (id) {
+        id -> Int4,
+        indexer_id -> Int4,
+        sg_deployment_id -> Int4,
+        block_number -> Int8,
+        entity_count -> Int8,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     failed_queries (id) {
         id -> Int4,
@@ -51,6 +183,43 @@ diesel::table! {
         sha256_api_key_hash -> Bytea,
         notes -> Nullable<Text>,
         permission_level -> Int4,
+        expires_at -> Nullable<Timestamp>,
+        daily_request_quota -> Nullable<Int4>,
+        workspace_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    indexer_allocations (id) {
+        id -> Int4,
+        indexer_id -> Int4,
+        allocation_id -> Text,
+        deployment -> Text,
+        created_at_epoch -> Int8,
+        indexing -> Bool,
+        collected_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    indexer_graph_node_versions (id) {
+        id -> Int4,
+        indexer_id -> Int4,
+        version_string -> Nullable<Text>,
+        version_commit -> Nullable<Text>,
+        first_seen_at -> Timestamp,
+        last_seen_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    indexer_identities (id) {
+        id -> Int4,
+        indexer_id -> Int4,
+        name -> Nullable<Text>,
+        endpoint -> Nullable<Text>,
+        valid_from -> Timestamp,
+        valid_until -> Nullable<Timestamp>,
     }
 }
 
@@ -71,6 +240,23 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    indexer_tags (indexer_id, tag, from_config) {
+        indexer_id -> Int4,
+        tag -> Text,
+        from_config -> Bool,
+    }
+}
+
+diesel::table! {
+    indexer_uptime_transitions (id) {
+        id -> Int4,
+        indexer_id -> Int4,
+        up -> Bool,
+        observed_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     indexers (id) {
         id -> Int4,
@@ -79,6 +265,19 @@ diesel::table! {
         graph_node_version -> Nullable<Int4>,
         network_subgraph_metadata -> Nullable<Int4>,
         created_at -> Timestamp,
+        archived_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    indexing_status_history (id) {
+        id -> Int4,
+        indexer_id -> Int4,
+        sg_deployment_id -> Int4,
+        latest_block_number -> Int8,
+        health -> Int4,
+        synced -> Bool,
+        created_at -> Timestamp,
     }
 }
 
@@ -101,21 +300,72 @@ diesel::table! {
 }
 
 diesel::table! {
-    pending_divergence_investigation_requests (uuid) {
-        uuid -> Uuid,
-        request -> Jsonb,
+    poi_disputes (id) {
+        id -> Int4,
+        dispute_id -> Text,
+        indexer_id -> Int4,
+        sg_deployment_id -> Int4,
+        allocation_id -> Text,
+        block_number -> Int8,
+        disputed_poi -> Bytea,
+        status -> Int4,
+        collected_poi_id -> Nullable<Int4>,
+        agreeing_indexers -> Int4,
+        disagreeing_indexers -> Int4,
+        opened_at -> Timestamp,
+        closed_at -> Nullable<Timestamp>,
         created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
 diesel::table! {
-    pois (id) {
+    poi_request_outcomes (id) {
+        id -> Int4,
+        indexer_id -> Int4,
+        requested -> Int4,
+        returned -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    poi_values (id) {
         id -> Int4,
         poi -> Bytea,
+    }
+}
+
+diesel::table! {
+    pois (id) {
+        id -> Int4,
         sg_deployment_id -> Int4,
         indexer_id -> Int4,
         block_id -> Int8,
         created_at -> Timestamp,
+        poi_value_id -> Int4,
+    }
+}
+
+diesel::table! {
+    raw_indexer_responses (id) {
+        id -> Int4,
+        investigation_uuid -> Uuid,
+        indexer_id -> Int4,
+        operation_name -> Text,
+        body -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    registered_indexers (id) {
+        id -> Int4,
+        address -> Bytea,
+        name -> Nullable<Text>,
+        index_node_endpoint -> Text,
+        created_at -> Timestamp,
+        workspace_id -> Nullable<Int4>,
     }
 }
 
@@ -129,6 +379,48 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    sg_deployment_check_tiers (sg_deployment_id) {
+        sg_deployment_id -> Int4,
+        tier -> Int4,
+    }
+}
+
+diesel::table! {
+    sg_deployment_data_sources (id) {
+        id -> Int4,
+        sg_deployment_id -> Int4,
+        is_template -> Bool,
+        kind -> Text,
+        name -> Text,
+        network -> Nullable<Text>,
+        address -> Nullable<Text>,
+        start_block -> Nullable<Int8>,
+    }
+}
+
+diesel::table! {
+    sg_deployment_manifests (sg_deployment_id) {
+        sg_deployment_id -> Int4,
+        spec_version -> Text,
+        features -> Array<Text>,
+        graft_base -> Nullable<Text>,
+        graft_block -> Nullable<Int8>,
+        raw_manifest -> Text,
+        fetched_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    sg_deployment_network_subgraph_metadata (sg_deployment_id) {
+        sg_deployment_id -> Int4,
+        display_name -> Nullable<Text>,
+        image -> Nullable<Text>,
+        signal_amount -> Numeric,
+        last_updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     sg_deployments (id) {
         id -> Int4,
@@ -147,34 +439,117 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    tracked_deployments (id) {
+        id -> Int4,
+        ipfs_cid -> Text,
+        network_id -> Int4,
+        created_at -> Timestamp,
+        workspace_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    workspaces (id) {
+        id -> Int4,
+        name -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::joinable!(api_key_daily_usage -> graphix_api_tokens (public_prefix));
+diesel::joinable!(api_key_usage -> graphix_api_tokens (public_prefix));
+diesel::joinable!(bisection_attempts -> bisection_runs (bisection_run_uuid));
+diesel::joinable!(bisection_evidence -> bisection_runs (bisection_run_uuid));
+diesel::joinable!(bisection_evidence -> indexers (indexer_id));
+diesel::joinable!(bisection_runs -> divergence_investigation_reports (investigation_uuid));
+diesel::joinable!(bisection_runs -> sg_deployments (sg_deployment_id));
+diesel::joinable!(allocation_pois -> indexers (indexer_id));
+diesel::joinable!(allocation_pois -> pois (collected_poi_id));
+diesel::joinable!(allocation_pois -> sg_deployments (sg_deployment_id));
 diesel::joinable!(blocks -> networks (network_id));
+diesel::joinable!(chain_disagreements -> indexers (indexer_id));
+diesel::joinable!(chain_disagreements -> sg_deployments (sg_deployment_id));
+diesel::joinable!(daily_deployment_agreement -> sg_deployments (sg_deployment_id));
+diesel::joinable!(daily_indexer_agreement -> indexers (indexer_id));
+diesel::joinable!(entity_counts -> indexers (indexer_id));
+diesel::joinable!(entity_counts -> sg_deployments (sg_deployment_id));
 diesel::joinable!(failed_queries -> indexers (indexer_id));
+diesel::joinable!(graphix_api_tokens -> workspaces (workspace_id));
+diesel::joinable!(indexer_graph_node_versions -> indexers (indexer_id));
+diesel::joinable!(indexer_identities -> indexers (indexer_id));
+diesel::joinable!(indexer_allocations -> indexers (indexer_id));
+diesel::joinable!(indexer_tags -> indexers (indexer_id));
+diesel::joinable!(indexer_uptime_transitions -> indexers (indexer_id));
 diesel::joinable!(indexers -> graph_node_collected_versions (graph_node_version));
 diesel::joinable!(indexers -> indexer_network_subgraph_metadata (network_subgraph_metadata));
+diesel::joinable!(indexing_status_history -> indexers (indexer_id));
+diesel::joinable!(indexing_status_history -> sg_deployments (sg_deployment_id));
 diesel::joinable!(live_pois -> indexers (indexer_id));
 diesel::joinable!(live_pois -> pois (poi_id));
 diesel::joinable!(live_pois -> sg_deployments (sg_deployment_id));
+diesel::joinable!(poi_disputes -> indexers (indexer_id));
+diesel::joinable!(poi_disputes -> pois (collected_poi_id));
+diesel::joinable!(poi_disputes -> sg_deployments (sg_deployment_id));
+diesel::joinable!(poi_request_outcomes -> indexers (indexer_id));
 diesel::joinable!(pois -> blocks (block_id));
 diesel::joinable!(pois -> indexers (indexer_id));
+diesel::joinable!(pois -> poi_values (poi_value_id));
 diesel::joinable!(pois -> sg_deployments (sg_deployment_id));
+diesel::joinable!(raw_indexer_responses -> divergence_investigation_reports (investigation_uuid));
+diesel::joinable!(raw_indexer_responses -> indexers (indexer_id));
+diesel::joinable!(registered_indexers -> workspaces (workspace_id));
 diesel::joinable!(sg_deployment_api_versions -> sg_deployments (sg_deployment_id));
+diesel::joinable!(sg_deployment_check_tiers -> sg_deployments (sg_deployment_id));
+diesel::joinable!(sg_deployment_data_sources -> sg_deployment_manifests (sg_deployment_id));
+diesel::joinable!(sg_deployment_manifests -> sg_deployments (sg_deployment_id));
+diesel::joinable!(sg_deployment_network_subgraph_metadata -> sg_deployments (sg_deployment_id));
 diesel::joinable!(sg_deployments -> networks (network));
 diesel::joinable!(sg_names -> sg_deployments (sg_deployment_id));
+diesel::joinable!(tracked_deployments -> networks (network_id));
+diesel::joinable!(tracked_deployments -> workspaces (workspace_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    allocation_pois,
+    api_key_daily_usage,
+    api_key_usage,
+    bisection_attempts,
+    bisection_evidence,
+    bisection_runs,
     blocks,
+    chain_disagreements,
     configs,
+    daily_deployment_agreement,
+    daily_indexer_agreement,
+    divergence_investigation_jobs,
     divergence_investigation_reports,
+    entity_counts,
     failed_queries,
     graph_node_collected_versions,
     graphix_api_tokens,
+    indexer_allocations,
+    indexer_graph_node_versions,
+    indexer_identities,
     indexer_network_subgraph_metadata,
+    indexer_tags,
+    indexer_uptime_transitions,
     indexers,
+    indexing_status_history,
     live_pois,
     networks,
-    pending_divergence_investigation_requests,
+    poi_disputes,
+    poi_request_outcomes,
+    poi_values,
     pois,
+    raw_indexer_responses,
+    registered_indexers,
     sg_deployment_api_versions,
+    sg_deployment_check_tiers,
+    sg_deployment_data_sources,
+    sg_deployment_manifests,
+    sg_deployment_network_subgraph_metadata,
     sg_deployments,
     sg_names,
+    tracked_deployments,
+    workspaces,
 );
@@ -0,0 +1,87 @@
+//! Postgres advisory locks, which keep Graphix replicas that share a database
+//! from running the same singleton jobs at once.
+
+use std::fmt::Debug;
+
+use diesel::sql_types;
+use diesel_async::pooled_connection::deadpool::Object;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use tracing::info;
+
+use super::Store;
+
+/// The first key of all advisory locks taken by Graphix, to tell them apart
+/// from those of other applications that share the database. It spells
+/// "GIXI" in ASCII.
+const LOCK_CLASS: i32 = 0x47495849;
+const MIGRATIONS_LOCK: i32 = 0;
+/// The leader lock of shard `n` is `LEADER_LOCK_BASE + n`.
+const LEADER_LOCK_BASE: i32 = 1;
+
+/// Exclusive leadership over the singleton jobs of a shard, e.g. the
+/// collection loop. It's held by a dedicated database session, so it's
+/// released once this is dropped, or once that session ends in any other way,
+/// e.g. when the database restarts.
+pub struct LeaderLock {
+    conn: AsyncPgConnection,
+}
+
+impl Debug for LeaderLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LeaderLock").finish()
+    }
+}
+
+impl LeaderLock {
+    /// Whether the lock is still held, i.e. whether its database session is
+    /// still alive.
+    pub async fn is_held(&mut self) -> bool {
+        diesel::select(diesel::dsl::sql::<sql_types::Bool>("true"))
+            .get_result::<bool>(&mut self.conn)
+            .await
+            .is_ok()
+    }
+}
+
+/// Waits for the migrations lock, so that only one replica at a time
+/// migrates and sets up the database.
+pub(super) async fn lock_migrations(conn: &mut AsyncPgConnection) -> anyhow::Result<()> {
+    diesel::sql_query(format!(
+        "SELECT pg_advisory_lock({LOCK_CLASS}, {MIGRATIONS_LOCK})"
+    ))
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub(super) async fn unlock_migrations(conn: &mut AsyncPgConnection) -> anyhow::Result<()> {
+    diesel::sql_query(format!(
+        "SELECT pg_advisory_unlock({LOCK_CLASS}, {MIGRATIONS_LOCK})"
+    ))
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+impl Store {
+    /// Tries to become the leader of the given shard, without waiting.
+    /// Returns `None` if another replica already is.
+    pub async fn try_acquire_leader_lock(&self, shard: u32) -> anyhow::Result<Option<LeaderLock>> {
+        // Session-level locks outlive transactions, so the connection must
+        // not go back to the pool while holding one.
+        let mut conn = Object::take(self.conn().await?);
+        let key = LEADER_LOCK_BASE + i32::try_from(shard)?;
+
+        let acquired = diesel::select(diesel::dsl::sql::<sql_types::Bool>(&format!(
+            "pg_try_advisory_lock({LOCK_CLASS}, {key})"
+        )))
+        .get_result::<bool>(&mut conn)
+        .await?;
+        if !acquired {
+            return Ok(None);
+        }
+
+        info!(shard, "Acquired leader lock");
+        Ok(Some(LeaderLock { conn }))
+    }
+}
@@ -0,0 +1,102 @@
+//! Cancelling the database statements of futures that are dropped before they
+//! complete, e.g. GraphQL API requests whose client disconnected. Dropping a
+//! future doesn't stop the statement it's waiting for, which would otherwise
+//! keep running until it completes or times out.
+//!
+//! Connections that are checked out within a scope are tagged with its
+//! `application_name`, so that only its own statements are cancelled even if
+//! the connection is checked out again by the time the cancellation arrives.
+//! Once a store has used scopes, checkouts outside of any scope reset the
+//! tag. Statements of tasks that the future spawns, e.g. batched loads,
+//! aren't cancelled, because task-locals don't carry over to them.
+
+use std::future::Future;
+use std::sync::atomic::Ordering;
+
+use diesel::sql_types;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use super::Store;
+
+tokio::task_local! {
+    /// The `application_name` of the connections checked out in the current
+    /// scope.
+    static SCOPE: String;
+}
+
+/// Cancels the statements of a scope once dropped, unless it's disarmed.
+struct CancelOnDrop {
+    store: Store,
+    scope: Option<String>,
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        let Some(scope) = self.scope.take() else {
+            return;
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+
+        let store = self.store.clone();
+        runtime.spawn(async move {
+            match store.cancel_statements(&scope).await {
+                Ok(0) => {}
+                Ok(statements) => debug!(statements, "Cancelled statements of dropped future"),
+                Err(err) => warn!(error = %err, "Failed to cancel statements of dropped future"),
+            }
+        });
+    }
+}
+
+impl Store {
+    /// Tags `conn` with the `application_name` of the current scope, or
+    /// resets the tag it may have from an earlier scope, which would have its
+    /// statements cancelled along with that scope's.
+    pub(super) async fn tag_connection(&self, conn: &mut AsyncPgConnection) -> anyhow::Result<()> {
+        let query = match SCOPE.try_with(|scope| scope.clone()) {
+            // The scope is a UUID, so it doesn't need escaping.
+            Ok(scope) => format!("SET application_name = '{scope}'"),
+            Err(_) if self.cancellation_scopes.load(Ordering::Relaxed) => {
+                "RESET application_name".to_string()
+            }
+            Err(_) => return Ok(()),
+        };
+
+        diesel::sql_query(query).execute(conn).await?;
+        Ok(())
+    }
+
+    /// Runs `future` such that the database statements it's waiting for are
+    /// cancelled if it's dropped before it completes.
+    pub async fn cancel_statements_on_drop<F: Future>(&self, future: F) -> F::Output {
+        self.cancellation_scopes.store(true, Ordering::Relaxed);
+        let scope = format!("graphix-{}", Uuid::new_v4().simple());
+        let mut guard = CancelOnDrop {
+            store: self.clone(),
+            scope: Some(scope.clone()),
+        };
+
+        let output = SCOPE.scope(scope, future).await;
+        guard.scope = None;
+        output
+    }
+
+    /// Cancels the active statements of connections that were checked out
+    /// within the scope `application_name`. Returns how many there were.
+    /// Retagging a connection that is checked out again isn't cancelled.
+    async fn cancel_statements(&self, application_name: &str) -> anyhow::Result<usize> {
+        let cancelled = diesel::sql_query(
+            "SELECT pg_cancel_backend(pid) FROM pg_stat_activity \
+             WHERE application_name = $1 AND state = 'active' AND pid <> pg_backend_pid() \
+             AND query !~ '^(RE)?SET application_name'",
+        )
+        .bind::<sql_types::Text, _>(application_name)
+        .execute(&mut self.conn().await?)
+        .await?;
+        Ok(cancelled)
+    }
+}
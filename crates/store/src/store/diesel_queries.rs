@@ -2,39 +2,60 @@
 //! transactions.
 
 use std::borrow::Cow;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
 use chrono::Utc;
 use diesel::prelude::*;
 use diesel::sql_types;
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use graphix_common_types::IpfsCid;
-use graphix_common_types::{inputs, IndexerAddress};
-use graphix_indexer_client::{BlockPointer, IndexerClient, IndexerId, WritablePoi};
+use graphix_common_types::{inputs, GraphNodeCollectedVersion, IndexerAddress, PoiBytes};
+use graphix_indexer_client::{BlockPointer, IndexerClient, IndexerId, IndexingStatus, WritablePoi};
 use tracing::info;
 
 use super::PoiLiveness;
 use crate::models::{
-    self, Indexer as IndexerModel, NewIndexer, NewLivePoi, NewPoi, NewSgDeployment, SgDeployment,
+    self, Indexer as IndexerModel, IntId, NewIndexer, NewLivePoi, NewPoi, NewSgDeployment,
+    SgDeployment,
 };
 use crate::schema::{self, live_pois, sg_names};
 
+/// The fraction of indexers that reported the same PoI as `pois` for the same
+/// subgraph deployment and block.
+const POI_AGREEMENT_RATIO: &str = "(
+    SELECT count(DISTINCT other.indexer_id) FILTER (WHERE other.poi_value_id = pois.poi_value_id)::float8
+        / count(DISTINCT other.indexer_id)
+    FROM pois other
+    WHERE other.sg_deployment_id = pois.sg_deployment_id AND other.block_id = pois.block_id
+)";
+
+/// The fraction of live PoIs that agree with the live PoIs of `indexers`, on
+/// average across subgraph deployments.
+const INDEXER_AGREEMENT_RATIO: &str = "(
+    SELECT avg((
+        SELECT count(*) FILTER (WHERE other_poi.poi_value_id = own_poi.poi_value_id)::float8 / count(*)
+        FROM live_pois other
+        JOIN pois other_poi ON other_poi.id = other.poi_id
+        WHERE other.sg_deployment_id = own.sg_deployment_id
+    ))
+    FROM live_pois own
+    JOIN pois own_poi ON own_poi.id = own.poi_id
+    WHERE own.indexer_id = indexers.id
+)";
+
 // This is a single SQL statement, a transaction is not necessary.
 pub(super) async fn pois(
     conn: &mut AsyncPgConnection,
     indexer_address: Option<&IndexerAddress>,
+    indexer_tags: &[String],
     sg_deployments: Option<&[IpfsCid]>,
     block_range: Option<inputs::BlockRange>,
     limit: Option<u16>,
     live_only: bool,
+    order: inputs::PoisOrder,
 ) -> anyhow::Result<Vec<models::Poi>> {
-    #![allow(non_snake_case)]
-    use schema::{blocks, indexers, pois, sg_deployments as sgd};
-
-    let FALSE = diesel::dsl::sql::<sql_types::Bool>("false");
-    let TRUE = diesel::dsl::sql::<sql_types::Bool>("true");
-
-    let selection = pois::all_columns;
+    use inputs::{OrderDirection as Dir, PoisOrderBy as By};
+    use schema::{blocks, indexers, poi_values, pois, sg_deployments as sgd};
 
     // TODO: optimize this into a single comparison in the absence of lower or
     // upper bounds.
@@ -47,57 +68,441 @@ pub(super) async fn pois(
             .unwrap_or(0),
         block_range
             .as_ref()
-            .and_then(|b| b.start)
-            .map(|start| start.try_into())
+            .and_then(|b| b.end)
+            .map(|end| end.try_into())
             .transpose()?
             .unwrap_or(i64::MAX),
     );
 
-    let deployments_filter = match sg_deployments {
-        Some(sg_deployments) => sgd::ipfs_cid.eq_any(sg_deployments).or(FALSE.clone()),
-        None => sgd::ipfs_cid.eq_any([]).or(TRUE.clone()),
-    };
+    let mut query = pois::table
+        .inner_join(sgd::table)
+        .inner_join(indexers::table)
+        .inner_join(blocks::table)
+        .inner_join(poi_values::table)
+        .select(models::Poi::as_select())
+        .filter(blocks_filter)
+        .limit(limit.map(|l| l as i64).unwrap_or(i64::MAX))
+        .into_boxed();
+
+    if let Some(start_time) = block_range.as_ref().and_then(|b| b.start_time) {
+        query = query.filter(blocks::timestamp.ge(start_time));
+    }
+    if let Some(end_time) = block_range.as_ref().and_then(|b| b.end_time) {
+        query = query.filter(blocks::timestamp.le(end_time));
+    }
+    if let Some(sg_deployments) = sg_deployments {
+        query = query.filter(sgd::ipfs_cid.eq_any(sg_deployments));
+    }
+    if let Some(addr) = indexer_address {
+        query = query.filter(indexers::address.eq(addr));
+    }
+    if !indexer_tags.is_empty() {
+        query = query.filter(
+            pois::indexer_id.eq_any(
+                schema::indexer_tags::table
+                    .select(schema::indexer_tags::indexer_id)
+                    .filter(schema::indexer_tags::tag.eq_any(indexer_tags)),
+            ),
+        );
+    }
+    if live_only {
+        query = query.filter(pois::id.eq_any(live_pois::table.select(live_pois::poi_id)));
+    }
 
-    let default_indexer_address = IndexerAddress::default();
-    let indexer_filter = match indexer_address {
-        // Ugly hacks to have the match arms' types match.
-        Some(addr) => indexers::address.eq(addr).or(FALSE),
-        None => indexers::address.eq(&default_indexer_address).or(TRUE),
+    let agreement_ratio = diesel::dsl::sql::<sql_types::Double>(POI_AGREEMENT_RATIO);
+    query = match (order.by, order.direction) {
+        (By::BlockNumber, Dir::Asc) => query.order_by(blocks::number.asc()),
+        (By::BlockNumber, Dir::Desc) => query.order_by(blocks::number.desc()),
+        (By::CreatedAt, Dir::Asc) => query.order_by(pois::created_at.asc()),
+        (By::CreatedAt, Dir::Desc) => query.order_by(pois::created_at.desc()),
+        (By::AgreementRatio, Dir::Asc) => query.order_by(agreement_ratio.asc()),
+        (By::AgreementRatio, Dir::Desc) => query.order_by(agreement_ratio.desc()),
     };
+    // Most recent first among equals, which also keeps the default order
+    // stable.
+    let query = query.then_order_by(pois::created_at.desc());
+
+    Ok(query.load::<models::Poi>(conn).await?)
+}
+
+/// Aggregate statistics about the network with the given name, see
+/// [`models::NetworkStats`]. Only the tracked deployments of the given
+/// workspace are counted, if any.
+pub(super) async fn network_stats(
+    conn: &mut AsyncPgConnection,
+    network: &str,
+    workspace_id: Option<IntId>,
+) -> anyhow::Result<Option<models::NetworkStats>> {
+    // The chain head is approximated by the highest block that any indexer
+    // recently reported for any deployment on the network.
+    let query = format!(
+        "
+        WITH network AS (
+            SELECT id FROM networks WHERE name = $1
+        ),
+        latest_statuses AS (
+            SELECT DISTINCT ON (h.indexer_id, h.sg_deployment_id) h.latest_block_number
+            FROM indexing_status_history h
+            JOIN sg_deployments d ON d.id = h.sg_deployment_id
+            WHERE d.network = (SELECT id FROM network)
+                AND h.created_at >= now() - interval '1 hour'
+            ORDER BY h.indexer_id, h.sg_deployment_id, h.created_at DESC
+        ),
+        lags AS (
+            SELECT (SELECT max(latest_block_number) FROM latest_statuses) - latest_block_number
+                AS lag
+            FROM latest_statuses
+        )
+        SELECT
+            (
+                SELECT count(DISTINCT ipfs_cid) FROM tracked_deployments
+                WHERE network_id = network.id AND ($2::integer IS NULL OR workspace_id = $2)
+            ) AS tracked_deployments,
+            (
+                SELECT count(DISTINCT live_pois.indexer_id) FROM live_pois
+                JOIN sg_deployments d ON d.id = live_pois.sg_deployment_id
+                WHERE d.network = network.id
+            ) AS active_indexers,
+            (
+                SELECT avg({POI_AGREEMENT_RATIO}) FROM live_pois
+                JOIN pois ON pois.id = live_pois.poi_id
+                JOIN sg_deployments d ON d.id = live_pois.sg_deployment_id
+                WHERE d.network = network.id
+            ) AS average_agreement_ratio,
+            (SELECT percentile_disc(0.5) WITHIN GROUP (ORDER BY lag) FROM lags)
+                AS chain_head_lag_median,
+            (SELECT percentile_disc(0.9) WITHIN GROUP (ORDER BY lag) FROM lags)
+                AS chain_head_lag_p90,
+            (SELECT percentile_disc(0.99) WITHIN GROUP (ORDER BY lag) FROM lags)
+                AS chain_head_lag_p99,
+            (SELECT max(lag) FROM lags) AS chain_head_lag_max
+        FROM network
+        "
+    );
 
-    let order_by = (blocks::number.desc(), schema::pois::created_at.desc());
-    let limit = limit.map(|l| l as i64).unwrap_or(i64::MAX);
-
-    match live_only {
-        false => {
-            let query = pois::table
-                .inner_join(sgd::table)
-                .inner_join(indexers::table)
-                .inner_join(blocks::table)
-                .select(selection)
-                .order_by(order_by)
-                .filter(deployments_filter)
-                .filter(blocks_filter)
-                .filter(indexer_filter)
-                .limit(limit);
-            Ok(query.load::<models::Poi>(conn).await?)
+    Ok(diesel::sql_query(query)
+        .bind::<sql_types::Text, _>(network)
+        .bind::<sql_types::Nullable<sql_types::Integer>, _>(workspace_id)
+        .get_result(conn)
+        .await
+        .optional()?)
+}
+
+/// Counts how many indexers agree with each live PoI of the indexer(s) with
+/// the given address, in a single pass over the live PoIs of the same
+/// subgraph deployments.
+pub(super) async fn poi_agreement_counts(
+    conn: &mut AsyncPgConnection,
+    indexer: &IndexerAddress,
+) -> anyhow::Result<Vec<models::PoiAgreementCounts>> {
+    let query = "
+        WITH own_pois AS (
+            SELECT pois.id, pois.sg_deployment_id, pois.poi_value_id
+            FROM live_pois
+            JOIN pois ON pois.id = live_pois.poi_id
+            JOIN indexers ON indexers.id = live_pois.indexer_id
+            WHERE indexers.address = $1
+        ),
+        value_counts AS (
+            SELECT live_pois.sg_deployment_id, pois.poi_value_id, count(*) AS indexers
+            FROM live_pois
+            JOIN pois ON pois.id = live_pois.poi_id
+            WHERE live_pois.sg_deployment_id IN (SELECT sg_deployment_id FROM own_pois)
+            GROUP BY live_pois.sg_deployment_id, pois.poi_value_id
+        ),
+        deployment_counts AS (
+            SELECT sg_deployment_id, sum(indexers)::bigint AS total, max(indexers) AS largest
+            FROM value_counts
+            GROUP BY sg_deployment_id
+        )
+        SELECT
+            own_pois.id AS poi_id,
+            deployment_counts.total AS total_indexers,
+            value_counts.indexers AS agreeing_indexers,
+            deployment_counts.largest * 2 > deployment_counts.total AS has_consensus,
+            -- A majority is unique, so agreeing with as many indexers is
+            -- agreeing with the majority.
+            deployment_counts.largest * 2 > deployment_counts.total
+                AND value_counts.indexers = deployment_counts.largest AS in_consensus
+        FROM own_pois
+        JOIN deployment_counts ON deployment_counts.sg_deployment_id = own_pois.sg_deployment_id
+        JOIN value_counts
+            ON value_counts.sg_deployment_id = own_pois.sg_deployment_id
+            AND value_counts.poi_value_id = own_pois.poi_value_id
+        ORDER BY own_pois.id
+    ";
+
+    Ok(diesel::sql_query(query)
+        .bind::<sql_types::Binary, _>(indexer)
+        .load(conn)
+        .await?)
+}
+
+/// Recomputes the daily agreement rollups of all days since `since`, or of
+/// all days if [`None`].
+pub(super) async fn refresh_daily_agreement(
+    conn: &mut AsyncPgConnection,
+    since: Option<chrono::NaiveDate>,
+) -> anyhow::Result<()> {
+    use schema::{daily_deployment_agreement, daily_indexer_agreement};
+
+    let ratios = format!(
+        "
+        SELECT
+            pois.created_at::date AS day,
+            pois.sg_deployment_id,
+            pois.indexer_id,
+            pois.block_id,
+            {POI_AGREEMENT_RATIO} AS agreement_ratio
+        FROM pois
+        WHERE $1::date IS NULL OR pois.created_at >= $1
+        "
+    );
+
+    match since {
+        Some(since) => {
+            diesel::delete(daily_deployment_agreement::table)
+                .filter(daily_deployment_agreement::day.ge(since))
+                .execute(conn)
+                .await?;
+            diesel::delete(daily_indexer_agreement::table)
+                .filter(daily_indexer_agreement::day.ge(since))
+                .execute(conn)
+                .await?;
         }
-        // This will additionally join with `live_pois` to filter out any Pois that are not live.
-        true => {
-            let query = pois::table
-                .inner_join(sgd::table)
-                .inner_join(indexers::table)
-                .inner_join(blocks::table)
-                .inner_join(live_pois::table)
-                .select(selection)
-                .order_by(order_by)
-                .filter(deployments_filter)
-                .filter(blocks_filter)
-                .filter(indexer_filter)
-                .limit(limit);
-            Ok(query.load::<models::Poi>(conn).await?)
+        None => {
+            diesel::delete(daily_deployment_agreement::table)
+                .execute(conn)
+                .await?;
+            diesel::delete(daily_indexer_agreement::table)
+                .execute(conn)
+                .await?;
         }
     }
+
+    diesel::sql_query(format!(
+        "
+        INSERT INTO daily_deployment_agreement
+            (day, sg_deployment_id, poi_count, indexer_count, diverging_blocks, average_agreement_ratio)
+        SELECT
+            day,
+            sg_deployment_id,
+            count(*),
+            count(DISTINCT indexer_id),
+            count(DISTINCT block_id) FILTER (WHERE agreement_ratio < 1),
+            avg(agreement_ratio)
+        FROM ({ratios}) ratios
+        GROUP BY day, sg_deployment_id
+        "
+    ))
+    .bind::<sql_types::Nullable<sql_types::Date>, _>(since)
+    .execute(conn)
+    .await?;
+    diesel::sql_query(format!(
+        "
+        INSERT INTO daily_indexer_agreement
+            (day, indexer_id, poi_count, deployment_count, average_agreement_ratio)
+        SELECT
+            day,
+            indexer_id,
+            count(*),
+            count(DISTINCT sg_deployment_id),
+            avg(agreement_ratio)
+        FROM ({ratios}) ratios
+        GROUP BY day, indexer_id
+        "
+    ))
+    .bind::<sql_types::Nullable<sql_types::Date>, _>(since)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+pub(super) async fn sg_deployments(
+    conn: &mut AsyncPgConnection,
+    filter: inputs::SgDeploymentsQuery,
+) -> anyhow::Result<Vec<SgDeployment>> {
+    use inputs::{OrderDirection as Dir, SgDeploymentsOrderBy as By};
+    use schema::sg_deployments as sgd;
+
+    let mut query = sgd::table
+        .inner_join(schema::networks::table)
+        .left_join(schema::sg_names::table)
+        .select((
+            sgd::id,
+            sgd::ipfs_cid,
+            schema::sg_names::name.nullable(),
+            sgd::network,
+            sgd::created_at,
+        ))
+        .into_boxed();
+
+    if let Some(network_name) = filter.network_name {
+        query = query.filter(schema::networks::name.eq(network_name));
+    }
+    if let Some(name) = filter.name {
+        query = query.filter(schema::sg_names::name.eq(name));
+    }
+    if let Some(ipfs_cid) = filter.ipfs_cid {
+        query = query.filter(sgd::ipfs_cid.eq(ipfs_cid.to_string()));
+    }
+    if let Some(limit) = filter.limit {
+        query = query.limit(limit.into());
+    }
+
+    let name = schema::sg_names::name.nullable();
+    query = match (filter.order_by, filter.order_direction) {
+        (By::IpfsCid, Dir::Asc) => query.order_by(sgd::ipfs_cid.asc()),
+        (By::IpfsCid, Dir::Desc) => query.order_by(sgd::ipfs_cid.desc()),
+        (By::Name, Dir::Asc) => query.order_by(name.asc().nulls_last()),
+        (By::Name, Dir::Desc) => query.order_by(name.desc().nulls_last()),
+        (By::CreatedAt, Dir::Asc) => query.order_by(sgd::created_at.asc()),
+        (By::CreatedAt, Dir::Desc) => query.order_by(sgd::created_at.desc()),
+    };
+    let query = query.then_order_by(sgd::ipfs_cid.asc());
+
+    Ok(query.load::<SgDeployment>(conn).await?)
+}
+
+pub(super) async fn indexers(
+    conn: &mut AsyncPgConnection,
+    filter: inputs::IndexersQuery,
+) -> anyhow::Result<Vec<IndexerModel>> {
+    use inputs::{IndexersOrderBy as By, OrderDirection as Dir};
+    use schema::{indexer_tags, indexers};
+
+    let mut query = indexers::table.select(indexers::all_columns).into_boxed();
+
+    if let Some(address) = filter.address {
+        query = query.filter(indexers::address.eq(address));
+    } else if !filter.include_archived {
+        query = query.filter(indexers::archived_at.is_null());
+    }
+    if !filter.tags.is_empty() {
+        query = query.filter(
+            indexers::id.eq_any(
+                indexer_tags::table
+                    .select(indexer_tags::indexer_id)
+                    .filter(indexer_tags::tag.eq_any(filter.tags)),
+            ),
+        );
+    }
+    if let Some(limit) = filter.limit {
+        query = query.limit(limit.into());
+    }
+
+    let agreement_ratio =
+        diesel::dsl::sql::<sql_types::Nullable<sql_types::Double>>(INDEXER_AGREEMENT_RATIO);
+    query = match (filter.order_by, filter.order_direction) {
+        (By::Address, Dir::Asc) => query.order_by(indexers::address.asc()),
+        (By::Address, Dir::Desc) => query.order_by(indexers::address.desc()),
+        (By::Name, Dir::Asc) => query.order_by(indexers::name.asc().nulls_last()),
+        (By::Name, Dir::Desc) => query.order_by(indexers::name.desc().nulls_last()),
+        (By::CreatedAt, Dir::Asc) => query.order_by(indexers::created_at.asc()),
+        (By::CreatedAt, Dir::Desc) => query.order_by(indexers::created_at.desc()),
+        (By::AgreementRatio, Dir::Asc) => query.order_by(agreement_ratio.asc().nulls_last()),
+        (By::AgreementRatio, Dir::Desc) => query.order_by(agreement_ratio.desc().nulls_last()),
+    };
+    let query = query.then_order_by(indexers::id.asc());
+
+    Ok(query.load::<IndexerModel>(conn).await?)
+}
+
+/// Returns a `LIKE` pattern that matches strings starting with `term`.
+fn like_prefix_pattern(term: &str) -> String {
+    let escaped = term
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("{}%", escaped)
+}
+
+pub(super) async fn search_sg_deployments(
+    conn: &mut AsyncPgConnection,
+    term: &str,
+    limit: u16,
+) -> anyhow::Result<Vec<SgDeployment>> {
+    use diesel::dsl::sql;
+    use schema::sg_deployments as sgd;
+
+    let prefix = like_prefix_pattern(term);
+    let matches = sql::<sql_types::Bool>("(sg_deployments.ipfs_cid ILIKE ")
+        .bind::<sql_types::Text, _>(prefix.clone())
+        .sql(" OR sg_names.name ILIKE ")
+        .bind::<sql_types::Text, _>(prefix.clone())
+        .sql(" OR sg_names.name % ")
+        .bind::<sql_types::Text, _>(term.to_string())
+        .sql(")");
+    // Prefix matches first, then the most similar ones. Missing names count as
+    // no match.
+    let is_prefix_match = sql::<sql_types::Bool>("coalesce(sg_deployments.ipfs_cid ILIKE ")
+        .bind::<sql_types::Text, _>(prefix.clone())
+        .sql(" OR sg_names.name ILIKE ")
+        .bind::<sql_types::Text, _>(prefix)
+        .sql(", false)");
+    let similarity = sql::<sql_types::Nullable<sql_types::Float>>("similarity(sg_names.name, ")
+        .bind::<sql_types::Text, _>(term.to_string())
+        .sql(")");
+
+    let query = sgd::table
+        .left_join(sg_names::table)
+        .select((
+            sgd::id,
+            sgd::ipfs_cid,
+            sg_names::name.nullable(),
+            sgd::network,
+            sgd::created_at,
+        ))
+        .filter(matches)
+        .order_by(is_prefix_match.desc())
+        .then_order_by(similarity.desc().nulls_last())
+        .then_order_by(sgd::ipfs_cid.asc())
+        .limit(limit.into());
+
+    Ok(query.load::<SgDeployment>(conn).await?)
+}
+
+pub(super) async fn search_indexers(
+    conn: &mut AsyncPgConnection,
+    term: &str,
+    limit: u16,
+) -> anyhow::Result<Vec<IndexerModel>> {
+    use diesel::dsl::sql;
+    use schema::indexers;
+
+    let prefix = like_prefix_pattern(term);
+    // Addresses are stored as bytes, so they're matched against their
+    // lowercase hex encoding.
+    let address_prefix =
+        like_prefix_pattern(&term.strip_prefix("0x").unwrap_or(term).to_ascii_lowercase());
+    let matches = sql::<sql_types::Bool>("(indexers.name ILIKE ")
+        .bind::<sql_types::Text, _>(prefix.clone())
+        .sql(" OR indexers.name % ")
+        .bind::<sql_types::Text, _>(term.to_string())
+        .sql(" OR encode(indexers.address, 'hex') LIKE ")
+        .bind::<sql_types::Text, _>(address_prefix.clone())
+        .sql(")");
+    // Prefix matches first, then the most similar ones. Missing names count as
+    // no match.
+    let is_prefix_match = sql::<sql_types::Bool>("coalesce(indexers.name ILIKE ")
+        .bind::<sql_types::Text, _>(prefix)
+        .sql(" OR encode(indexers.address, 'hex') LIKE ")
+        .bind::<sql_types::Text, _>(address_prefix)
+        .sql(", false)");
+    let similarity = sql::<sql_types::Nullable<sql_types::Float>>("similarity(indexers.name, ")
+        .bind::<sql_types::Text, _>(term.to_string())
+        .sql(")");
+
+    let query = indexers::table
+        .select(indexers::all_columns)
+        .filter(matches)
+        .order_by(is_prefix_match.desc())
+        .then_order_by(similarity.desc().nulls_last())
+        .then_order_by(indexers::id.asc())
+        .limit(limit.into());
+
+    Ok(query.load::<IndexerModel>(conn).await?)
 }
 
 pub async fn write_indexers(
@@ -123,6 +528,89 @@ pub async fn write_indexers(
         .execute(conn)
         .await?;
 
+    record_indexer_identities(conn, indexers).await
+}
+
+/// Starts a new identity for the indexers whose name or endpoint differ from
+/// their current one, and ends the current one.
+///
+/// The caller must make sure that `conn` is within a transaction.
+async fn record_indexer_identities(
+    conn: &mut AsyncPgConnection,
+    indexers: &[impl AsRef<dyn IndexerClient>],
+) -> anyhow::Result<()> {
+    use schema::{indexer_identities as identities, indexers};
+
+    let addresses: Vec<IndexerAddress> = indexers
+        .iter()
+        .map(|indexer| indexer.as_ref().address())
+        .collect();
+    let ids: HashMap<IndexerAddress, i32> = indexers::table
+        .select((indexers::address, indexers::id))
+        .filter(indexers::address.eq_any(&addresses))
+        .load::<(IndexerAddress, i32)>(conn)
+        .await?
+        .into_iter()
+        .collect();
+    let current: HashMap<i32, (Option<String>, Option<String>)> = identities::table
+        .select((
+            identities::indexer_id,
+            identities::name,
+            identities::endpoint,
+        ))
+        .filter(identities::valid_until.is_null())
+        .filter(identities::indexer_id.eq_any(ids.values().copied().collect::<Vec<_>>()))
+        .load::<(i32, Option<String>, Option<String>)>(conn)
+        .await?
+        .into_iter()
+        .map(|(indexer_id, name, endpoint)| (indexer_id, (name, endpoint)))
+        .collect();
+
+    let mut changed = HashMap::new();
+    for indexer in indexers {
+        let indexer = indexer.as_ref();
+        let Some(&indexer_id) = ids.get(&indexer.address()) else {
+            continue;
+        };
+        let identity = (
+            indexer.name().map(Cow::into_owned),
+            indexer.endpoint().map(Cow::into_owned),
+        );
+        if current.get(&indexer_id) != Some(&identity) {
+            changed.insert(indexer_id, identity);
+        }
+    }
+    if changed.is_empty() {
+        return Ok(());
+    }
+
+    let now = Utc::now().naive_utc();
+    diesel::update(
+        identities::table
+            .filter(identities::valid_until.is_null())
+            .filter(identities::indexer_id.eq_any(changed.keys().copied().collect::<Vec<_>>())),
+    )
+    .set(identities::valid_until.eq(now))
+    .execute(conn)
+    .await?;
+    let new_identities: Vec<_> = changed
+        .into_iter()
+        .map(|(indexer_id, (name, endpoint))| {
+            (
+                identities::indexer_id.eq(indexer_id),
+                identities::name.eq(name),
+                identities::endpoint.eq(endpoint),
+                identities::valid_from.eq(now),
+            )
+        })
+        .collect();
+    // Another shard may have recorded the same change in the meantime.
+    diesel::insert_into(identities::table)
+        .values(new_identities)
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .await?;
+
     Ok(())
 }
 
@@ -151,7 +639,7 @@ where
     }
 
     for (deployment, poi_group) in grouped_pois {
-        let sg_deployment_id = get_or_insert_deployment(conn, &deployment).await?;
+        let (sg_deployment_id, network_id) = get_or_insert_deployment(conn, &deployment).await?;
         let block_ptr = poi_group[0].block();
 
         // Make sure all PoIs have the same block ptr
@@ -161,7 +649,11 @@ where
             ));
         }
 
-        let block_id = get_or_insert_block(conn, block_ptr).await?;
+        // Blocks are stored on the network of their deployment.
+        let block_id = get_or_insert_block(conn, block_ptr, network_id).await?;
+        let poi_value_ids =
+            get_or_insert_poi_values(conn, poi_group.iter().map(|poi| *poi.proof_of_indexing()))
+                .await?;
 
         let mut new_pois = vec![];
 
@@ -173,7 +665,7 @@ where
                 sg_deployment_id,
                 indexer_id,
                 block_id,
-                poi: *poi.proof_of_indexing(),
+                poi_value_id: poi_value_ids[poi.proof_of_indexing()],
                 created_at: Utc::now().naive_utc(),
             });
         }
@@ -186,9 +678,26 @@ where
             .await?;
 
         if live == PoiLiveness::Live {
-            // Clear any live pois for this deployment
+            // Clear the live PoIs for this deployment that the new PoIs
+            // replace, i.e. those of the same indexers and those at other
+            // blocks. PoIs of other indexers at the same block stay live, so
+            // that the PoIs of a polling round can be written in chunks.
+            let indexer_ids: Vec<i32> = id_and_indexer
+                .iter()
+                .map(|(_, indexer_id)| *indexer_id)
+                .collect();
+            let pois_at_other_blocks = pois::table
+                .select(pois::id)
+                .filter(pois::sg_deployment_id.eq(sg_deployment_id))
+                .filter(pois::block_id.ne(block_id));
             diesel::delete(
-                live_pois::table.filter(live_pois::sg_deployment_id.eq(sg_deployment_id)),
+                live_pois::table
+                    .filter(live_pois::sg_deployment_id.eq(sg_deployment_id))
+                    .filter(
+                        live_pois::indexer_id
+                            .eq_any(indexer_ids)
+                            .or(live_pois::poi_id.eq_any(pois_at_other_blocks)),
+                    ),
             )
             .execute(conn)
             .await?;
@@ -211,19 +720,60 @@ where
     Ok(())
 }
 
+/// Returns the IDs of the given PoI values, by value, inserting those that
+/// aren't stored yet.
+async fn get_or_insert_poi_values(
+    conn: &mut AsyncPgConnection,
+    pois: impl IntoIterator<Item = PoiBytes>,
+) -> anyhow::Result<HashMap<PoiBytes, IntId>> {
+    use schema::poi_values;
+
+    let mut pois: Vec<PoiBytes> = pois.into_iter().collect();
+    pois.sort();
+    pois.dedup();
+    let new_values: Vec<_> = pois.iter().map(|poi| poi_values::poi.eq(poi)).collect();
+    diesel::insert_into(poi_values::table)
+        .values(&new_values)
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .await?;
+
+    Ok(poi_values::table
+        .select((poi_values::poi, poi_values::id))
+        .filter(poi_values::poi.eq_any(&pois))
+        .load::<(PoiBytes, IntId)>(conn)
+        .await?
+        .into_iter()
+        .collect())
+}
+
 async fn get_or_insert_block(
     conn: &mut AsyncPgConnection,
     block: &BlockPointer,
+    network_id: IntId,
 ) -> anyhow::Result<i64> {
     use schema::blocks;
 
-    // First, attempt to find the existing block by hash
-    // TODO: also filter by network to be extra safe
-    let existing_block: Option<models::Block> = blocks::table
-        .filter(blocks::hash.eq(&block.hash.as_ref().unwrap().0.as_slice()))
-        .get_result(conn)
-        .await
-        .optional()?;
+    // First, attempt to find the existing block by hash, or by number if the
+    // hash is unknown.
+    let existing_block: Option<models::Block> = match &block.hash {
+        Some(hash) => {
+            blocks::table
+                .filter(blocks::network_id.eq(network_id))
+                .filter(blocks::hash.eq(hash))
+                .get_result(conn)
+                .await
+        }
+        None => {
+            blocks::table
+                .filter(blocks::network_id.eq(network_id))
+                .filter(blocks::number.eq(block.number as i64))
+                .filter(blocks::hash.is_null())
+                .get_result(conn)
+                .await
+        }
+    }
+    .optional()?;
 
     if let Some(existing_block) = existing_block {
         // If the block exists, return its id
@@ -232,8 +782,8 @@ async fn get_or_insert_block(
         // If the block doesn't exist, insert a new one and return its id
         let new_block = models::NewBlock {
             number: block.number as i64,
-            hash: block.hash.clone().unwrap(),
-            network_id: 1, // FIXME: network assumed to be mainnet, see also: hardcoded-mainnet
+            hash: block.hash.clone(),
+            network_id,
         };
         let block_id = diesel::insert_into(blocks::table)
             .values(&new_block)
@@ -244,6 +794,43 @@ async fn get_or_insert_block(
     }
 }
 
+/// Adds `version` to the version history of the given indexer, or bumps its
+/// last seen timestamp if it was already observed.
+pub(super) async fn record_graph_node_version(
+    conn: &mut AsyncPgConnection,
+    indexer_id: i32,
+    version: &GraphNodeCollectedVersion,
+) -> anyhow::Result<()> {
+    use schema::indexer_graph_node_versions as versions;
+
+    let existing_id: Option<i32> = versions::table
+        .select(versions::id)
+        .filter(versions::indexer_id.eq(indexer_id))
+        .filter(versions::version_string.is_not_distinct_from(&version.version))
+        .filter(versions::version_commit.is_not_distinct_from(&version.commit))
+        .get_result(conn)
+        .await
+        .optional()?;
+
+    if let Some(id) = existing_id {
+        diesel::update(versions::table.filter(versions::id.eq(id)))
+            .set(versions::last_seen_at.eq(diesel::dsl::now))
+            .execute(conn)
+            .await?;
+    } else {
+        diesel::insert_into(versions::table)
+            .values((
+                versions::indexer_id.eq(indexer_id),
+                versions::version_string.eq(&version.version),
+                versions::version_commit.eq(&version.commit),
+            ))
+            .execute(conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
 pub async fn get_indexer_id<'a>(
     conn: &mut AsyncPgConnection,
     name: Option<Cow<'a, str>>,
@@ -269,10 +856,33 @@ pub async fn get_indexer_id<'a>(
     }
 }
 
+/// Returns the IDs of the indexer and deployment of the given indexing status,
+/// or [`None`] if the deployment doesn't exist yet. The indexer must exist.
+pub async fn get_indexing_status_ids(
+    conn: &mut AsyncPgConnection,
+    status: &IndexingStatus,
+) -> anyhow::Result<Option<(i32, i32)>> {
+    use schema::sg_deployments;
+
+    let sg_deployment_id: Option<i32> = sg_deployments::table
+        .select(sg_deployments::id)
+        .filter(sg_deployments::ipfs_cid.eq(&status.deployment))
+        .first(conn)
+        .await
+        .optional()?;
+    let Some(sg_deployment_id) = sg_deployment_id else {
+        return Ok(None);
+    };
+    let indexer_id = get_indexer_id(conn, status.indexer.name(), &status.indexer.address()).await?;
+
+    Ok(Some((indexer_id, sg_deployment_id)))
+}
+
+/// Returns the IDs of the given deployment and of its network.
 async fn get_or_insert_deployment(
     conn: &mut AsyncPgConnection,
     deployment_cid: &IpfsCid,
-) -> Result<i32, anyhow::Error> {
+) -> Result<(i32, i32), anyhow::Error> {
     use schema::sg_deployments;
 
     let existing_sg_deployment: Option<SgDeployment> = sg_deployments::table
@@ -291,9 +901,12 @@ async fn get_or_insert_deployment(
     Ok(
         if let Some(existing_sg_deployment) = existing_sg_deployment {
             // If the sg_deployment exists, use its id
-            existing_sg_deployment.id
+            (existing_sg_deployment.id, existing_sg_deployment.network_id)
         } else {
-            // If the sg_deployment doesn't exist, insert a new one and return its id
+            // If the sg_deployment doesn't exist, insert a new one and return its id.
+            // The collection loop stores deployments on their network before
+            // their PoIs, so only PoIs that are written otherwise, e.g. in
+            // tests, end up here.
             let new_sg_deployment = NewSgDeployment {
                 ipfs_cid: deployment_cid.to_string(),
                 network: 1, // Network assumed to be mainnet, see also: hardcoded-mainnet
@@ -301,7 +914,7 @@ async fn get_or_insert_deployment(
             };
             diesel::insert_into(sg_deployments::table)
                 .values(&new_sg_deployment)
-                .returning(sg_deployments::id)
+                .returning((sg_deployments::id, sg_deployments::network))
                 .get_result(conn)
                 .await?
         },
@@ -0,0 +1,407 @@
+//! Database maintenance, e.g. vacuuming and repairing inconsistencies, and
+//! reports about the database itself rather than about the data in it.
+
+use diesel::sql_types::{BigInt, Nullable, Text};
+use diesel::QueryableByName;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use graphix_common_types::{IntegrityReport, LivePoiReport};
+use tracing::info;
+
+use super::Store;
+
+/// The tables that Graphix writes to on every polling round, and which hence
+/// accumulate the most dead rows.
+pub const HOT_TABLES: &[&str] = &[
+    "blocks",
+    "entity_counts",
+    "failed_queries",
+    "indexing_status_history",
+    "live_pois",
+    "poi_request_outcomes",
+    "poi_values",
+    "pois",
+];
+
+/// The size of a table on disk.
+#[derive(QueryableByName, Debug)]
+pub struct TableSize {
+    #[diesel(sql_type = Text)]
+    pub table_name: String,
+    /// The size of the table, including its indexes and TOAST data.
+    #[diesel(sql_type = BigInt)]
+    pub total_bytes: i64,
+    #[diesel(sql_type = BigInt)]
+    pub table_bytes: i64,
+    #[diesel(sql_type = BigInt)]
+    pub index_bytes: i64,
+    /// The row count as of the last vacuum or analyze, or -1 if the table
+    /// was never analyzed.
+    #[diesel(sql_type = BigInt)]
+    pub estimated_rows: i64,
+}
+
+/// An estimate of the space that a B-tree index wastes, e.g. on entries of
+/// deleted rows.
+#[derive(QueryableByName, Debug)]
+pub struct IndexBloat {
+    #[diesel(sql_type = Text)]
+    pub index_name: String,
+    #[diesel(sql_type = Text)]
+    pub table_name: String,
+    #[diesel(sql_type = BigInt)]
+    pub index_bytes: i64,
+    #[diesel(sql_type = BigInt)]
+    pub estimated_bloat_bytes: i64,
+    /// How often the index was used since statistics were last reset.
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub scans: Option<i64>,
+}
+
+const TABLE_SIZES: &str = "
+    SELECT
+        c.relname::text AS table_name,
+        pg_total_relation_size(c.oid) AS total_bytes,
+        pg_relation_size(c.oid) AS table_bytes,
+        pg_indexes_size(c.oid) AS index_bytes,
+        c.reltuples::bigint AS estimated_rows
+    FROM pg_class c
+    JOIN pg_namespace n ON n.oid = c.relnamespace
+    WHERE c.relkind = 'r' AND n.nspname = current_schema()
+    ORDER BY total_bytes DESC, table_name
+";
+
+// Leaf entries of B-tree indexes take their key, an 8-byte tuple header and a
+// 4-byte line pointer, and leaf pages are filled up to 90% by default. Any
+// space beyond that is counted as bloat. It's only an estimate, based on the
+// planner statistics of the indexed columns, but it doesn't need extensions
+// such as `pgstattuple`.
+const INDEX_BLOAT: &str = "
+    SELECT
+        i.relname::text AS index_name,
+        t.relname::text AS table_name,
+        pg_relation_size(i.oid) AS index_bytes,
+        greatest(
+            pg_relation_size(i.oid)
+                - (greatest(i.reltuples, 0) * (12 + coalesce(key_columns.width, 8)) / 0.9)::bigint,
+            0
+        ) AS estimated_bloat_bytes,
+        s.idx_scan AS scans
+    FROM pg_index x
+    JOIN pg_class i ON i.oid = x.indexrelid
+    JOIN pg_class t ON t.oid = x.indrelid
+    JOIN pg_namespace n ON n.oid = t.relnamespace
+    JOIN pg_am am ON am.oid = i.relam
+    LEFT JOIN pg_stat_user_indexes s ON s.indexrelid = i.oid
+    LEFT JOIN LATERAL (
+        SELECT sum(st.avg_width) AS width
+        FROM pg_attribute a
+        JOIN pg_stats st
+            ON st.schemaname = n.nspname AND st.tablename = t.relname AND st.attname = a.attname
+        WHERE a.attrelid = t.oid AND a.attnum = ANY(x.indkey)
+    ) key_columns ON true
+    WHERE n.nspname = current_schema() AND am.amname = 'btree'
+    ORDER BY estimated_bloat_bytes DESC, index_name
+";
+
+/// Rows of `table` that match `condition` are inconsistent, and repaired by
+/// deleting them.
+struct Inconsistency {
+    table: &'static str,
+    condition: &'static str,
+}
+
+const POIS_WITH_MISSING_BLOCKS: Inconsistency = Inconsistency {
+    table: "pois",
+    condition: "NOT EXISTS (SELECT 1 FROM blocks WHERE blocks.id = pois.block_id)",
+};
+
+const MISMATCHED_LIVE_POIS: Inconsistency = Inconsistency {
+    table: "live_pois",
+    condition: "NOT EXISTS (
+        SELECT 1 FROM pois
+        WHERE pois.id = live_pois.poi_id
+            AND pois.sg_deployment_id = live_pois.sg_deployment_id
+            AND pois.indexer_id = live_pois.indexer_id
+    )",
+};
+
+const STALE_LIVE_POIS: Inconsistency = Inconsistency {
+    table: "live_pois",
+    condition: "(
+        SELECT blocks.number FROM pois JOIN blocks ON blocks.id = pois.block_id
+        WHERE pois.id = live_pois.poi_id
+    ) < (
+        SELECT max(blocks.number) FROM live_pois latest
+        JOIN pois ON pois.id = latest.poi_id
+        JOIN blocks ON blocks.id = pois.block_id
+        WHERE latest.sg_deployment_id = live_pois.sg_deployment_id
+    )",
+};
+
+const ORPHANED_POI_VALUES: Inconsistency = Inconsistency {
+    table: "poi_values",
+    condition: "NOT EXISTS (SELECT 1 FROM pois WHERE pois.poi_value_id = poi_values.id)",
+};
+
+const ORPHANED_GRAPH_NODE_VERSIONS: Inconsistency = Inconsistency {
+    table: "graph_node_collected_versions",
+    condition: "NOT EXISTS (
+        SELECT 1 FROM indexers WHERE indexers.graph_node_version = graph_node_collected_versions.id
+    )",
+};
+
+const ORPHANED_INDEXER_NETWORK_SUBGRAPH_METADATA: Inconsistency = Inconsistency {
+    table: "indexer_network_subgraph_metadata",
+    condition: "NOT EXISTS (
+        SELECT 1 FROM indexers
+        WHERE indexers.network_subgraph_metadata = indexer_network_subgraph_metadata.id
+    )",
+};
+
+// The live block of a deployment is the block of its most recently
+// collected live PoI or, if it has none, e.g. because they were pruned, of
+// its most recently collected PoI. At that block, the most recently collected
+// PoI of each indexer is live.
+const EXPECTED_LIVE_POIS: &str = "
+    CREATE TEMPORARY TABLE expected_live_pois ON COMMIT DROP AS
+    WITH live_blocks AS (
+        SELECT DISTINCT ON (pois.sg_deployment_id) pois.sg_deployment_id, pois.block_id
+        FROM pois
+        LEFT JOIN live_pois ON live_pois.poi_id = pois.id
+        ORDER BY pois.sg_deployment_id, live_pois.id IS NOT NULL DESC,
+            pois.created_at DESC, pois.id DESC
+    )
+    SELECT DISTINCT ON (pois.sg_deployment_id, pois.indexer_id)
+        pois.sg_deployment_id, pois.indexer_id, pois.id AS poi_id
+    FROM pois
+    JOIN live_blocks
+        ON live_blocks.sg_deployment_id = pois.sg_deployment_id
+        AND live_blocks.block_id = pois.block_id
+    ORDER BY pois.sg_deployment_id, pois.indexer_id, pois.created_at DESC, pois.id DESC
+";
+
+// Conditions on `expected_live_pois expected`.
+const MISSING_LIVE_POIS: &str = "NOT EXISTS (
+    SELECT 1 FROM live_pois
+    WHERE live_pois.sg_deployment_id = expected.sg_deployment_id
+        AND live_pois.indexer_id = expected.indexer_id
+)";
+
+// Conditions on `live_pois` joined with `expected_live_pois expected`.
+const OUTDATED_LIVE_POIS: &str = "live_pois.sg_deployment_id = expected.sg_deployment_id
+    AND live_pois.indexer_id = expected.indexer_id
+    AND live_pois.poi_id <> expected.poi_id";
+
+// Conditions on `live_pois`.
+const UNEXPECTED_LIVE_POIS: &str = "NOT EXISTS (
+    SELECT 1 FROM expected_live_pois expected
+    WHERE expected.sg_deployment_id = live_pois.sg_deployment_id
+        AND expected.indexer_id = live_pois.indexer_id
+)";
+
+/// Lifts the statement timeout of the connection, if any, for the rest of
+/// the current transaction, for maintenance work that's expected to take
+/// long.
+pub(super) async fn lift_statement_timeout(conn: &mut AsyncPgConnection) -> anyhow::Result<()> {
+    diesel::sql_query("SET LOCAL statement_timeout = 0")
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[derive(QueryableByName)]
+struct Count {
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+/// Counts the rows of the given `FROM` clause.
+async fn count(conn: &mut AsyncPgConnection, from: &str) -> anyhow::Result<u64> {
+    let count = diesel::sql_query(format!("SELECT count(*) AS count FROM {from}"))
+        .get_result::<Count>(conn)
+        .await?
+        .count;
+    Ok(count as u64)
+}
+
+/// Counts the rows with the given inconsistency, or deletes them unless
+/// `dry_run` is set.
+async fn find_or_repair(
+    conn: &mut AsyncPgConnection,
+    inconsistency: Inconsistency,
+    dry_run: bool,
+) -> anyhow::Result<u64> {
+    let Inconsistency { table, condition } = inconsistency;
+    if dry_run {
+        count(conn, &format!("{table} WHERE {condition}")).await
+    } else {
+        let deleted = diesel::sql_query(format!("DELETE FROM {table} WHERE {condition}"))
+            .execute(conn)
+            .await?;
+        if deleted > 0 {
+            info!(table, deleted, "Repaired inconsistent rows");
+        }
+        Ok(deleted as u64)
+    }
+}
+
+impl Store {
+    /// Looks for inconsistencies in the database, and repairs them unless
+    /// `dry_run` is set.
+    pub async fn check_integrity(&self, dry_run: bool) -> anyhow::Result<IntegrityReport> {
+        self.conn()
+            .await?
+            .transaction::<_, anyhow::Error, _>(|conn| {
+                async move {
+                    lift_statement_timeout(conn).await?;
+                    // Fields are evaluated in order, which is the order in
+                    // which repairs must happen, e.g. deleting PoIs can
+                    // orphan PoI values.
+                    Ok(IntegrityReport {
+                        dry_run,
+                        pois_with_missing_blocks: find_or_repair(
+                            conn,
+                            POIS_WITH_MISSING_BLOCKS,
+                            dry_run,
+                        )
+                        .await?,
+                        mismatched_live_pois: find_or_repair(conn, MISMATCHED_LIVE_POIS, dry_run)
+                            .await?,
+                        stale_live_pois: find_or_repair(conn, STALE_LIVE_POIS, dry_run).await?,
+                        orphaned_poi_values: find_or_repair(conn, ORPHANED_POI_VALUES, dry_run)
+                            .await?,
+                        orphaned_graph_node_versions: find_or_repair(
+                            conn,
+                            ORPHANED_GRAPH_NODE_VERSIONS,
+                            dry_run,
+                        )
+                        .await?,
+                        orphaned_indexer_network_subgraph_metadata: find_or_repair(
+                            conn,
+                            ORPHANED_INDEXER_NETWORK_SUBGRAPH_METADATA,
+                            dry_run,
+                        )
+                        .await?,
+                    })
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+
+    /// Recomputes which PoI is live for each indexer and deployment from the
+    /// stored PoIs, and corrects the live PoIs that drifted from that, e.g.
+    /// after pruning or manual imports, unless `dry_run` is set.
+    pub async fn recompute_live_pois(&self, dry_run: bool) -> anyhow::Result<LivePoiReport> {
+        self.conn()
+            .await?
+            .transaction::<_, anyhow::Error, _>(|conn| {
+                async move {
+                    lift_statement_timeout(conn).await?;
+                    // Keeps the main loop from marking PoIs live in the
+                    // meantime. Reads aren't blocked.
+                    diesel::sql_query("LOCK TABLE live_pois IN SHARE ROW EXCLUSIVE MODE")
+                        .execute(conn)
+                        .await?;
+                    diesel::sql_query(EXPECTED_LIVE_POIS).execute(conn).await?;
+
+                    let report = if dry_run {
+                        LivePoiReport {
+                            dry_run,
+                            inserted: count(
+                                conn,
+                                &format!("expected_live_pois expected WHERE {MISSING_LIVE_POIS}"),
+                            )
+                            .await?,
+                            updated: count(
+                                conn,
+                                &format!(
+                                    "live_pois, expected_live_pois expected \
+                                     WHERE {OUTDATED_LIVE_POIS}"
+                                ),
+                            )
+                            .await?,
+                            deleted: count(
+                                conn,
+                                &format!("live_pois WHERE {UNEXPECTED_LIVE_POIS}"),
+                            )
+                            .await?,
+                        }
+                    } else {
+                        let deleted = diesel::sql_query(format!(
+                            "DELETE FROM live_pois WHERE {UNEXPECTED_LIVE_POIS}"
+                        ))
+                        .execute(conn)
+                        .await?;
+                        let updated = diesel::sql_query(format!(
+                            "UPDATE live_pois SET poi_id = expected.poi_id \
+                             FROM expected_live_pois expected WHERE {OUTDATED_LIVE_POIS}"
+                        ))
+                        .execute(conn)
+                        .await?;
+                        let inserted = diesel::sql_query(format!(
+                            "INSERT INTO live_pois (sg_deployment_id, indexer_id, poi_id) \
+                             SELECT expected.sg_deployment_id, expected.indexer_id, expected.poi_id \
+                             FROM expected_live_pois expected WHERE {MISSING_LIVE_POIS}"
+                        ))
+                        .execute(conn)
+                        .await?;
+                        LivePoiReport {
+                            dry_run,
+                            inserted: inserted as u64,
+                            updated: updated as u64,
+                            deleted: deleted as u64,
+                        }
+                    };
+                    if report.inserted + report.updated + report.deleted > 0 {
+                        info!(
+                            dry_run,
+                            inserted = report.inserted,
+                            updated = report.updated,
+                            deleted = report.deleted,
+                            "Recomputed live PoIs"
+                        );
+                    }
+
+                    Ok(report)
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+
+    /// Vacuums and analyzes all [`HOT_TABLES`], or only analyzes them if
+    /// `analyze_only` is set, which is much quicker but doesn't reclaim any
+    /// space.
+    pub async fn vacuum_hot_tables(&self, analyze_only: bool) -> anyhow::Result<()> {
+        // `VACUUM` can't run within a transaction, so neither can this.
+        let conn = &mut self.conn().await?;
+        for table in HOT_TABLES {
+            info!(table, analyze_only, "Vacuuming table");
+            let statement = if analyze_only {
+                format!("ANALYZE {table}")
+            } else {
+                format!("VACUUM (ANALYZE) {table}")
+            };
+            diesel::sql_query(statement).execute(conn).await?;
+        }
+
+        Ok(())
+    }
+
+    /// The sizes of all tables, largest first.
+    pub async fn table_sizes(&self) -> anyhow::Result<Vec<TableSize>> {
+        Ok(diesel::sql_query(TABLE_SIZES)
+            .load(&mut self.conn().await?)
+            .await?)
+    }
+
+    /// Estimates of the bloat of all B-tree indexes, most bloated first. The
+    /// estimates are only as recent as the last analyze of each table.
+    pub async fn index_bloat(&self) -> anyhow::Result<Vec<IndexBloat>> {
+        Ok(diesel::sql_query(INDEX_BLOAT)
+            .load(&mut self.conn().await?)
+            .await?)
+    }
+}
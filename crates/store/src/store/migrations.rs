@@ -0,0 +1,146 @@
+//! Inspecting and reverting the database migrations embedded in Graphix,
+//! without manual work against Diesel's `__diesel_schema_migrations` table.
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+use chrono::NaiveDateTime;
+use diesel::sql_types::{Bool, Text, Timestamp};
+use diesel::QueryableByName;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl, SimpleAsyncConnection};
+use tracing::warn;
+
+use super::{advisory_locks, Store};
+
+/// A migration embedded in Graphix, and whether it's applied.
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    /// The name of the migration directory, e.g.
+    /// `2026-10-16-000023_poi_values`.
+    pub name: String,
+    /// When the migration was applied, if it was.
+    pub run_on: Option<NaiveDateTime>,
+}
+
+#[derive(QueryableByName)]
+struct AppliedMigration {
+    #[diesel(sql_type = Text)]
+    version: String,
+    #[diesel(sql_type = Timestamp)]
+    run_on: NaiveDateTime,
+}
+
+#[derive(QueryableByName)]
+struct Exists {
+    #[diesel(sql_type = Bool)]
+    exists: bool,
+}
+
+/// Diesel identifies migrations by the digits of their name before the first
+/// underscore, e.g. `20261016000023`.
+fn version(name: &str) -> String {
+    name.split('_')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect()
+}
+
+/// The versions of all applied migrations, including those that this version
+/// of Graphix doesn't know about, and when they were applied.
+async fn applied_migrations(
+    conn: &mut AsyncPgConnection,
+) -> anyhow::Result<HashMap<String, NaiveDateTime>> {
+    // The table is only created by the first migration run.
+    let table_exists =
+        diesel::sql_query("SELECT to_regclass('__diesel_schema_migrations') IS NOT NULL AS exists")
+            .get_result::<Exists>(conn)
+            .await?
+            .exists;
+    if !table_exists {
+        return Ok(HashMap::new());
+    }
+
+    let applied: Vec<AppliedMigration> =
+        diesel::sql_query("SELECT version, run_on FROM __diesel_schema_migrations")
+            .load(conn)
+            .await?;
+    Ok(applied
+        .into_iter()
+        .map(|migration| (migration.version, migration.run_on))
+        .collect())
+}
+
+impl Store {
+    /// All migrations embedded in Graphix, oldest first, and when they were
+    /// applied.
+    pub async fn migration_status(&self) -> anyhow::Result<Vec<MigrationStatus>> {
+        let applied = applied_migrations(&mut self.conn().await?).await?;
+
+        Ok(Self::MIGRATIONS
+            .migrations
+            .iter()
+            .map(|migration| MigrationStatus {
+                name: migration.name.to_string(),
+                run_on: applied.get(&version(migration.name)).copied(),
+            })
+            .collect())
+    }
+
+    /// Reverts the most recently applied migration by running its down
+    /// migration. As a guard against reverting the wrong one, `name` must be
+    /// the name of that migration.
+    ///
+    /// Graphix applies all pending migrations on startup, so all instances
+    /// that share the database must be stopped or rolled back first.
+    pub async fn revert_migration(&self, name: &str) -> anyhow::Result<()> {
+        let mut conn = self.conn().await?;
+        // Keeps replicas that start in the meantime from migrating.
+        advisory_locks::lock_migrations(&mut conn).await?;
+        let result = revert_migration(&mut conn, name).await;
+        advisory_locks::unlock_migrations(&mut conn).await?;
+        result
+    }
+}
+
+async fn revert_migration(conn: &mut AsyncPgConnection, name: &str) -> anyhow::Result<()> {
+    let applied = applied_migrations(conn).await?;
+    let latest_version = applied
+        .keys()
+        .max()
+        .cloned()
+        .context("no migrations are applied")?;
+    let latest = Store::MIGRATIONS
+        .migrations
+        .iter()
+        .find(|migration| version(migration.name) == latest_version)
+        .with_context(|| {
+            format!("the latest applied migration, {latest_version}, is unknown to this Graphix")
+        })?;
+    if latest.name != name {
+        anyhow::bail!(
+            "{name} is not the latest applied migration, {}",
+            latest.name
+        );
+    }
+    let down = latest
+        .down
+        .filter(|down| !down.trim().is_empty())
+        .with_context(|| format!("{name} can't be reverted, it has no down migration"))?;
+
+    warn!(migration = name, "Reverting database migration");
+    conn.transaction::<_, anyhow::Error, _>(|conn| {
+        async move {
+            conn.batch_execute(down).await?;
+            diesel::sql_query("DELETE FROM __diesel_schema_migrations WHERE version = $1")
+                .bind::<Text, _>(latest_version)
+                .execute(conn)
+                .await?;
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await
+}
@@ -1,33 +1,79 @@
+mod advisory_locks;
+mod cancellation;
 mod diesel_queries;
+mod maintenance;
+mod migrations;
 
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{anyhow, Error};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::NaiveDateTime;
 use diesel::prelude::*;
+use diesel::{ConnectionError, ConnectionResult};
 use diesel_async::pooled_connection::deadpool::{Object, Pool};
 use diesel_async::pooled_connection::AsyncDieselConnectionManager;
 use diesel_async::scoped_futures::ScopedFutureExt;
 use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
 use diesel_async_migrations::{embed_migrations, EmbeddedMigrations};
-use graphix_common_types::{inputs, ApiKeyPermissionLevel, IndexerAddress, IpfsCid, PoiBytes};
-use graphix_indexer_client::{IndexerClient, IndexerId, WritablePoi};
+use graphix_common_types::{
+    inputs, ApiKeyPermissionLevel, BlockHash, DisputeStatus, DivergenceInvestigationJobStatus,
+    IndexerAddress, IndexerDataDeletion, IpfsCid, PoiBytes,
+};
+use graphix_indexer_client::{
+    ActiveAllocation, IndexerClient, IndexerId, IndexingStatus, RawResponse, WritablePoi,
+};
 use tracing::info;
 use uuid::Uuid;
 
+use crate::metrics::instrumented;
 use crate::models::{
-    ApiKey, ApiKeyDbRow, ApiKeyPublicMetadata, FailedQueryRow, Indexer as IndexerModel, IntId,
-    NewIndexerNetworkSubgraphMetadata, NewNetwork, NewlyCreatedApiKey, Poi, SgDeployment,
+    ApiKey, ApiKeyDbRow, ApiKeyLimits, ApiKeyPublicMetadata, ApiKeyUsage, BigIntId,
+    BisectionAttempt, BisectionEvidence, BisectionRun, DivergenceInvestigationJob, FailedQueryRow,
+    IntId, NewBisectionAttempt, NewBisectionEvidence, NewBisectionRun,
+    NewIndexerNetworkSubgraphMetadata, NewNetwork, NewRawIndexerResponse, NewlyCreatedApiKey, Poi,
+    RawIndexerResponse, SgDeployment,
 };
 use crate::{models, schema};
 
+pub use advisory_locks::LeaderLock;
+pub use maintenance::{IndexBloat, TableSize, HOT_TABLES};
+pub use migrations::MigrationStatus;
+
+/// How long to wait for a database connection before giving up, e.g. when
+/// the pool is exhausted because the database is slow.
+const CHECKOUT_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// An abstraction over all database operations. It uses [`Arc`] internally, so
 /// it's cheaply cloneable.
 #[derive(Clone)]
 pub struct Store {
     pool: Pool<AsyncPgConnection>,
+    checkout_timeouts: Arc<AtomicU64>,
+    /// Whether connections may be tagged by
+    /// [`Store::cancel_statements_on_drop`].
+    cancellation_scopes: Arc<AtomicBool>,
+}
+
+/// A snapshot of the database connection pool of a [`Store`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStatus {
+    pub max_size: usize,
+    /// Open connections, both idle and checked out.
+    pub size: usize,
+    pub idle: usize,
+    /// Callers waiting for a connection.
+    pub waiting: usize,
+    /// How many times waiting for a connection timed out so far.
+    pub checkout_timeouts: u64,
 }
 
 impl Debug for Store {
@@ -42,24 +88,91 @@ impl Store {
 
     /// Connects to the database and runs all pending migrations.
     pub async fn new(db_url: &str) -> anyhow::Result<Self> {
+        Self::new_with_statement_timeout(db_url, None).await
+    }
+
+    /// Connects to the database and runs all pending migrations. Statements
+    /// that run longer than `statement_timeout`, if given, are cancelled by
+    /// the database. Migrations aren't subject to it.
+    pub async fn new_with_statement_timeout(
+        db_url: &str,
+        statement_timeout: Option<Duration>,
+    ) -> anyhow::Result<Self> {
         info!("Initializing database connection pool");
 
-        let manager = AsyncDieselConnectionManager::new(db_url);
-        let pool = Pool::builder(manager).build()?;
-        let store = Self { pool };
+        let store = Self::connect(db_url, None)?;
+
+        // Replicas that start at the same time would otherwise race each
+        // other, e.g. creating several master API keys.
+        let mut lock_conn = store.conn().await?;
+        advisory_locks::lock_migrations(&mut lock_conn).await?;
+        let result = store.set_up().await;
+        advisory_locks::unlock_migrations(&mut lock_conn).await?;
+        result?;
+
+        match statement_timeout {
+            Some(_) => Self::connect(db_url, statement_timeout),
+            None => Ok(store),
+        }
+    }
 
-        store.run_migrations().await?;
+    async fn set_up(&self) -> anyhow::Result<()> {
+        self.run_migrations().await?;
 
-        if store.api_keys().await?.is_empty() {
+        if self.api_keys().await?.is_empty() {
             info!("No API keys found in database, creating master API key");
-            store.create_master_api_key().await?;
+            self.create_master_api_key().await?;
         }
 
-        Ok(store)
+        Ok(())
+    }
+
+    /// Connects to an existing database without running migrations or
+    /// writing anything, for serving the GraphQL API only. Statements that
+    /// run longer than `statement_timeout`, if given, are cancelled by the
+    /// database.
+    pub async fn new_read_only(
+        db_url: &str,
+        statement_timeout: Option<Duration>,
+    ) -> anyhow::Result<Self> {
+        info!("Initializing read-only database connection pool");
+
+        Self::connect(db_url, statement_timeout)
+    }
+
+    /// Connects to the database without running migrations, e.g. to inspect
+    /// or revert them.
+    pub async fn new_without_migrations(db_url: &str) -> anyhow::Result<Self> {
+        Self::new_without_migrations_with_statement_timeout(db_url, None).await
+    }
+
+    /// Connects to the database without running migrations, e.g. on replicas
+    /// that leave migrating to the leader. Statements that run longer than
+    /// `statement_timeout`, if given, are cancelled by the database.
+    pub async fn new_without_migrations_with_statement_timeout(
+        db_url: &str,
+        statement_timeout: Option<Duration>,
+    ) -> anyhow::Result<Self> {
+        info!("Initializing database connection pool without migrations");
+
+        Self::connect(db_url, statement_timeout)
+    }
+
+    fn connect(db_url: &str, statement_timeout: Option<Duration>) -> anyhow::Result<Self> {
+        let manager = AsyncDieselConnectionManager::new_with_setup(db_url, move |url| {
+            establish_connection(url, statement_timeout)
+        });
+        let pool = Pool::builder(manager).build()?;
+
+        Ok(Self {
+            pool,
+            checkout_timeouts: Default::default(),
+            cancellation_scopes: Default::default(),
+        })
     }
 
     async fn run_migrations(&self) -> anyhow::Result<()> {
-        let mut conn = self.pool.get().await?;
+        let mut conn = self.conn().await?;
 
         info!("Run database migrations");
 
@@ -71,13 +184,59 @@ impl Store {
         Ok(())
     }
 
+    /// Checks out a connection from the pool, waiting at most
+    /// [`CHECKOUT_TIMEOUT`] for one to become available.
     pub async fn conn(&self) -> anyhow::Result<Object<AsyncPgConnection>> {
-        Ok(self.pool.get().await?)
+        match tokio::time::timeout(CHECKOUT_TIMEOUT, self.pool.get()).await {
+            Ok(conn) => {
+                let mut conn = conn?;
+                self.tag_connection(&mut conn).await?;
+                Ok(conn)
+            }
+            Err(_) => {
+                self.checkout_timeouts.fetch_add(1, Ordering::Relaxed);
+                Err(anyhow!(
+                    "Timed out after {CHECKOUT_TIMEOUT:?} waiting for a database connection"
+                ))
+            }
+        }
     }
 
     pub async fn conn_err_string(&self) -> Result<Object<AsyncPgConnection>, String> {
-        self.pool.get().await.map_err(|e| e.to_string())
+        self.conn().await.map_err(|e| e.to_string())
     }
+
+    pub fn pool_status(&self) -> PoolStatus {
+        let status = self.pool.status();
+        // Negative availability is the number of waiting callers.
+        PoolStatus {
+            max_size: status.max_size,
+            size: status.size,
+            idle: status.available.max(0) as usize,
+            waiting: (-status.available).max(0) as usize,
+            checkout_timeouts: self.checkout_timeouts.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Opens a new connection for the pool, which applies `statement_timeout` to
+/// all statements run on it. It's set for the whole session rather than with
+/// `SET LOCAL`, which would only last for a single transaction: each category
+/// of statements, e.g. API reads, has a pool of its own.
+fn establish_connection(
+    url: &str,
+    statement_timeout: Option<Duration>,
+) -> Pin<Box<dyn Future<Output = ConnectionResult<AsyncPgConnection>> + Send + '_>> {
+    Box::pin(async move {
+        let mut conn = AsyncPgConnection::establish(url).await?;
+        if let Some(timeout) = statement_timeout {
+            diesel::sql_query(format!("SET statement_timeout = {}", timeout.as_millis()))
+                .execute(&mut conn)
+                .await
+                .map_err(ConnectionError::CouldntSetupConfiguration)?;
+        }
+        Ok(conn)
+    })
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -105,48 +264,101 @@ impl Store {
         &self,
         filter: inputs::SgDeploymentsQuery,
     ) -> anyhow::Result<Vec<SgDeployment>> {
-        use schema::sg_deployments as sgd;
+        let mut conn = self.conn().await?;
+        instrumented(
+            "sg_deployments",
+            diesel_queries::sg_deployments(&mut conn, filter),
+        )
+        .await
+    }
 
-        let mut query = sgd::table
-            .inner_join(schema::networks::table)
-            .left_join(schema::sg_names::table)
-            .select((
-                sgd::id,
-                sgd::ipfs_cid,
-                schema::sg_names::name.nullable(),
-                sgd::network,
-                sgd::created_at,
-            ))
-            .order_by(sgd::ipfs_cid.asc())
-            .into_boxed();
+    /// Fetches a Poi from the database.
+    pub async fn poi(&self, poi: &PoiBytes) -> anyhow::Result<Option<Poi>> {
+        use schema::{poi_values, pois};
 
-        if let Some(network_name) = filter.network_name {
-            query = query.filter(schema::networks::name.eq(network_name));
-        }
-        if let Some(name) = filter.name {
-            query = query.filter(schema::sg_names::name.eq(name));
-        }
-        if let Some(ipfs_cid) = filter.ipfs_cid {
-            query = query.filter(sgd::ipfs_cid.eq(ipfs_cid.to_string()));
-        }
-        if let Some(limit) = filter.limit {
-            query = query.limit(limit.into());
-        }
+        let query = pois::table
+            .inner_join(poi_values::table)
+            .select(Poi::as_select())
+            .filter(poi_values::poi.eq(poi));
 
-        Ok(query.load::<SgDeployment>(&mut self.conn().await?).await?)
+        Ok(query.get_result(&mut self.conn().await?).await.optional()?)
     }
 
-    /// Fetches a Poi from the database.
-    pub async fn poi(&self, poi: &PoiBytes) -> anyhow::Result<Option<Poi>> {
-        use schema::pois;
+    /// Fetches all PoIs with one of the given values, e.g. to find all
+    /// indexers that ever produced or agree with the given PoIs.
+    pub async fn pois_by_value(&self, pois: &[PoiBytes]) -> anyhow::Result<Vec<Poi>> {
+        use schema::{poi_values, pois};
+
+        let query = pois::table
+            .inner_join(poi_values::table)
+            .select(Poi::as_select())
+            .filter(poi_values::poi.eq_any(pois))
+            .order_by(pois::id);
+
+        Ok(query.load(&mut self.conn().await?).await?)
+    }
+
+    /// Fetches the most recent Poi that `indexer` produced for the given
+    /// deployment and block number, together with its block.
+    pub async fn indexer_poi_at_block(
+        &self,
+        indexer: &impl IndexerId,
+        deployment: &IpfsCid,
+        block_number: u64,
+    ) -> anyhow::Result<Option<(Poi, models::Block)>> {
+        use schema::{blocks, indexers, poi_values, pois, sg_deployments as sgd};
 
         let query = pois::table
-            .select(pois::all_columns)
-            .filter(pois::poi.eq(poi));
+            .inner_join(sgd::table)
+            .inner_join(indexers::table)
+            .inner_join(blocks::table)
+            .inner_join(poi_values::table)
+            .select((Poi::as_select(), blocks::all_columns))
+            .filter(indexers::name.is_not_distinct_from(indexer.name()))
+            .filter(indexers::address.eq(indexer.address()))
+            .filter(sgd::ipfs_cid.eq(deployment))
+            .filter(blocks::number.eq(i64::try_from(block_number)?))
+            .order_by(pois::created_at.desc())
+            .limit(1);
 
         Ok(query.get_result(&mut self.conn().await?).await.optional()?)
     }
 
+    /// Fetches the latest PoI of every indexer at every block of the given
+    /// deployment within `block_range`, together with its block, oldest block
+    /// first.
+    pub async fn deployment_poi_history(
+        &self,
+        deployment: &IpfsCid,
+        block_range: Option<inputs::BlockRange>,
+    ) -> anyhow::Result<Vec<(Poi, models::Block)>> {
+        use schema::{blocks, poi_values, pois, sg_deployments as sgd};
+
+        let start = block_range.as_ref().and_then(|range| range.start);
+        let end = block_range.as_ref().and_then(|range| range.end);
+        let mut query = pois::table
+            .inner_join(sgd::table)
+            .inner_join(blocks::table)
+            .inner_join(poi_values::table)
+            .select((Poi::as_select(), blocks::all_columns))
+            .filter(sgd::ipfs_cid.eq(deployment))
+            .filter(blocks::number.between(
+                start.map(i64::try_from).transpose()?.unwrap_or(0),
+                end.map(i64::try_from).transpose()?.unwrap_or(i64::MAX),
+            ))
+            .distinct_on((blocks::number, pois::indexer_id))
+            .order_by((blocks::number, pois::indexer_id, pois::created_at.desc()))
+            .into_boxed();
+        if let Some(start_time) = block_range.as_ref().and_then(|range| range.start_time) {
+            query = query.filter(blocks::timestamp.ge(start_time));
+        }
+        if let Some(end_time) = block_range.as_ref().and_then(|range| range.end_time) {
+            query = query.filter(blocks::timestamp.le(end_time));
+        }
+
+        Ok(query.load(&mut self.conn().await?).await?)
+    }
+
     pub async fn failed_query(
         &self,
         indexer: &impl IndexerId,
@@ -188,195 +400,2007 @@ impl Store {
             .await?)
     }
 
-    /// Returns all indexers stored in the database.
-    pub async fn indexers(
+    /// Returns the ID of the most recently written PoI, which changes with
+    /// every polling round that collects PoIs. Unlike
+    /// [`Store::api_data_version`], it's a single index lookup.
+    pub async fn latest_poi_id(&self) -> anyhow::Result<Option<IntId>> {
+        use schema::pois;
+
+        Ok(pois::table
+            .select(diesel::dsl::max(pois::id))
+            .get_result(&mut self.conn().await?)
+            .await?)
+    }
+
+    /// Returns an opaque version of the data behind the deployments, networks
+    /// and live PoIs, which changes whenever any of it changes, together with
+    /// the time of the latest PoI write. Unlike the data itself, it's cheap to
+    /// compute.
+    pub async fn api_data_version(&self) -> anyhow::Result<(String, Option<NaiveDateTime>)> {
+        use diesel::dsl::{count_star, max};
+        use schema::{
+            live_pois, networks, pois, sg_deployment_api_versions, sg_deployments, sg_names,
+        };
+
+        let conn = &mut self.conn().await?;
+
+        let latest_poi: Option<(IntId, NaiveDateTime)> = pois::table
+            .select((pois::id, pois::created_at))
+            .order_by(pois::id.desc())
+            .first(conn)
+            .await
+            .optional()?;
+        let live_pois: (i64, Option<IntId>) = live_pois::table
+            .select((count_star(), max(live_pois::id)))
+            .get_result(conn)
+            .await?;
+        let deployments: (i64, Option<IntId>) = sg_deployments::table
+            .select((count_star(), max(sg_deployments::id)))
+            .get_result(conn)
+            .await?;
+        let api_versions: Option<IntId> = sg_deployment_api_versions::table
+            .select(max(sg_deployment_api_versions::id))
+            .get_result(conn)
+            .await?;
+        // Names and networks are few, but can change in place.
+        let names: Vec<(IntId, String)> = sg_names::table
+            .select((sg_names::sg_deployment_id, sg_names::name))
+            .order_by(sg_names::id.asc())
+            .load(conn)
+            .await?;
+        let networks: Vec<models::Network> = networks::table
+            .select((networks::id, networks::name, networks::caip2))
+            .order_by(networks::id.asc())
+            .load(conn)
+            .await?;
+
+        let version = format!(
+            "{:?}/{:?}/{:?}/{:?}/{:?}/{:?}",
+            latest_poi.map(|(id, _)| id),
+            live_pois,
+            deployments,
+            api_versions,
+            names,
+            networks
+        );
+        Ok((version, latest_poi.map(|(_, created_at)| created_at)))
+    }
+
+    /// Returns all distinct `graph-node` versions that were observed for the
+    /// given indexer, oldest first.
+    pub async fn graph_node_version_history(
         &self,
-        filter: inputs::IndexersQuery,
-    ) -> anyhow::Result<Vec<models::Indexer>> {
-        use schema::indexers;
+        indexer_id: IntId,
+    ) -> anyhow::Result<Vec<models::IndexerGraphNodeVersion>> {
+        use schema::indexer_graph_node_versions as versions;
+
+        Ok(versions::table
+            .select(models::IndexerGraphNodeVersion::as_select())
+            .filter(versions::indexer_id.eq(indexer_id))
+            .order_by((versions::first_seen_at.asc(), versions::id.asc()))
+            .load(&mut self.conn().await?)
+            .await?)
+    }
+
+    /// Returns the names and endpoints that the given indexer had over time,
+    /// oldest first.
+    pub async fn indexer_identity_history(
+        &self,
+        indexer_id: IntId,
+    ) -> anyhow::Result<Vec<models::IndexerIdentity>> {
+        use schema::indexer_identities as identities;
+
+        Ok(identities::table
+            .select(models::IndexerIdentity::as_select())
+            .filter(identities::indexer_id.eq(indexer_id))
+            .order_by((identities::valid_from.asc(), identities::id.asc()))
+            .load(&mut self.conn().await?)
+            .await?)
+    }
 
-        let mut query = indexers::table.select(indexers::all_columns).into_boxed();
+    /// Returns the tags of the given indexer, from both the configuration and
+    /// the GraphQL API, sorted and without duplicates.
+    pub async fn indexer_tags(&self, indexer_id: IntId) -> anyhow::Result<Vec<String>> {
+        use schema::indexer_tags;
+
+        Ok(indexer_tags::table
+            .select(indexer_tags::tag)
+            .filter(indexer_tags::indexer_id.eq(indexer_id))
+            .distinct()
+            .order_by(indexer_tags::tag.asc())
+            .load(&mut self.conn().await?)
+            .await?)
+    }
 
-        if let Some(address) = filter.address {
-            query = query.filter(indexers::address.eq(address));
-        }
-        if let Some(limit) = filter.limit {
-            query = query.limit(limit.into());
+    /// Returns the tags of all tagged indexers, by address.
+    pub async fn indexer_tags_by_address(
+        &self,
+    ) -> anyhow::Result<HashMap<IndexerAddress, BTreeSet<String>>> {
+        use schema::{indexer_tags, indexers};
+
+        let rows: Vec<(IndexerAddress, String)> = indexer_tags::table
+            .inner_join(indexers::table)
+            .select((indexers::address, indexer_tags::tag))
+            .load(&mut self.conn().await?)
+            .await?;
+
+        let mut tags: HashMap<_, BTreeSet<_>> = HashMap::new();
+        for (address, tag) in rows {
+            tags.entry(address).or_default().insert(tag);
         }
+        Ok(tags)
+    }
+
+    /// Returns the uptime transitions of the given indexer that were observed
+    /// since `since`, oldest first, preceded by the last transition before
+    /// `since` (if any) so that the state at `since` is known.
+    pub async fn indexer_uptime_transitions(
+        &self,
+        indexer_id: IntId,
+        since: NaiveDateTime,
+    ) -> anyhow::Result<Vec<models::IndexerUptimeTransition>> {
+        use schema::indexer_uptime_transitions as transitions;
+
+        let conn = &mut self.conn().await?;
+        let previous: Option<models::IndexerUptimeTransition> = transitions::table
+            .select(models::IndexerUptimeTransition::as_select())
+            .filter(transitions::indexer_id.eq(indexer_id))
+            .filter(transitions::observed_at.lt(since))
+            .order_by((transitions::observed_at.desc(), transitions::id.desc()))
+            .first(conn)
+            .await
+            .optional()?;
+        let recent: Vec<models::IndexerUptimeTransition> = transitions::table
+            .select(models::IndexerUptimeTransition::as_select())
+            .filter(transitions::indexer_id.eq(indexer_id))
+            .filter(transitions::observed_at.ge(since))
+            .order_by((transitions::observed_at.asc(), transitions::id.asc()))
+            .load(conn)
+            .await?;
 
-        Ok(query.load::<IndexerModel>(&mut self.conn().await?).await?)
+        Ok(previous.into_iter().chain(recent).collect())
     }
 
-    /// Queries the database for proofs of indexing that refer to the specified
-    /// subgraph deployments and in the given [`inputs::BlockRange`], if given.
-    pub async fn pois(
+    /// Returns the fraction of the PoIs requested from the given indexer since
+    /// `since` that it returned, or [`None`] if none were requested.
+    pub async fn poi_request_success_rate(
         &self,
-        sg_deployments: &[IpfsCid],
-        block_range: Option<inputs::BlockRange>,
-        limit: Option<u16>,
-    ) -> anyhow::Result<Vec<Poi>> {
-        let mut conn = self.conn().await?;
-        diesel_queries::pois(
-            &mut conn,
-            None,
-            Some(sg_deployments),
-            block_range,
-            limit,
-            false,
-        )
-        .await
+        indexer_id: IntId,
+        since: NaiveDateTime,
+    ) -> anyhow::Result<Option<f64>> {
+        use diesel::dsl::sum;
+        use schema::poi_request_outcomes as outcomes;
+
+        let (requested, returned): (Option<i64>, Option<i64>) = outcomes::table
+            .filter(outcomes::indexer_id.eq(indexer_id))
+            .filter(outcomes::created_at.ge(since))
+            .select((sum(outcomes::requested), sum(outcomes::returned)))
+            .get_result(&mut self.conn().await?)
+            .await?;
+
+        Ok(match (requested, returned) {
+            (Some(requested), Some(returned)) if requested > 0 => {
+                Some(returned as f64 / requested as f64)
+            }
+            _ => None,
+        })
     }
 
-    /// Like `pois`, but only returns live pois.
-    pub async fn live_pois(
+    /// Returns all PoIs, of any indexer, for the same deployments and blocks
+    /// as the PoIs that the given indexer reported since `since`.
+    pub async fn pois_comparable_with_indexer(
         &self,
-        indexer_address: Option<&IndexerAddress>,
-        sg_deployments_cids: Option<&[IpfsCid]>,
-        block_range: Option<inputs::BlockRange>,
-        limit: Option<u16>,
+        indexer_id: IntId,
+        since: NaiveDateTime,
     ) -> anyhow::Result<Vec<Poi>> {
-        let mut conn = self.conn().await?;
-        diesel_queries::pois(
-            &mut conn,
-            indexer_address,
-            sg_deployments_cids,
-            block_range,
-            limit,
-            true,
-        )
-        .await
-    }
+        use schema::{poi_values, pois};
 
-    pub async fn api_keys(&self) -> anyhow::Result<Vec<ApiKeyPublicMetadata>> {
-        use schema::graphix_api_tokens;
+        let conn = &mut self.conn().await?;
+        let own_pois: Vec<(IntId, BigIntId)> = pois::table
+            .filter(pois::indexer_id.eq(indexer_id))
+            .filter(pois::created_at.ge(since))
+            .select((pois::sg_deployment_id, pois::block_id))
+            .distinct()
+            .load(conn)
+            .await?;
+        let own_pois: HashSet<(IntId, BigIntId)> = own_pois.into_iter().collect();
+        let block_ids: HashSet<BigIntId> = own_pois.iter().map(|(_, block_id)| *block_id).collect();
+
+        let pois: Vec<Poi> = pois::table
+            .inner_join(poi_values::table)
+            .filter(pois::block_id.eq_any(block_ids))
+            .select(Poi::as_select())
+            .load(conn)
+            .await?;
 
-        Ok(graphix_api_tokens::table
-            .load::<ApiKeyDbRow>(&mut self.conn().await?)
-            .await?
+        Ok(pois
             .into_iter()
-            .map(ApiKeyPublicMetadata::from)
+            .filter(|poi| own_pois.contains(&(poi.sg_deployment_id, poi.block_id)))
             .collect())
     }
 
-    pub async fn permission_level(
+    /// Returns the number of bisection runs involving the given indexer
+    /// since `since` that found the block at which PoIs diverged.
+    pub async fn confirmed_divergences_count(
         &self,
-        api_key: &ApiKey,
-    ) -> anyhow::Result<Option<ApiKeyPermissionLevel>> {
-        use schema::graphix_api_tokens;
-
-        Ok(graphix_api_tokens::table
-            .select(graphix_api_tokens::permission_level)
-            .filter(graphix_api_tokens::sha256_api_key_hash.eq(api_key.hash()))
+        indexer_id: IntId,
+        since: NaiveDateTime,
+    ) -> anyhow::Result<u64> {
+        use schema::bisection_runs;
+
+        let count: i64 = bisection_runs::table
+            .filter(
+                bisection_runs::indexer1_id
+                    .eq(indexer_id)
+                    .or(bisection_runs::indexer2_id.eq(indexer_id)),
+            )
+            .filter(bisection_runs::error.is_null())
+            .filter(bisection_runs::created_at.ge(since))
+            .count()
             .get_result(&mut self.conn().await?)
+            .await?;
+
+        Ok(count as u64)
+    }
+
+    /// Returns the most recently collected subgraph API versions of the given
+    /// deployment, if any were successfully collected.
+    pub async fn subgraph_api_versions(
+        &self,
+        sg_deployment_id: IntId,
+    ) -> anyhow::Result<Option<Vec<String>>> {
+        use schema::sg_deployment_api_versions as api_versions;
+
+        let versions: Option<Option<Vec<Option<String>>>> = api_versions::table
+            .select(api_versions::api_versions)
+            .filter(api_versions::sg_deployment_id.eq(sg_deployment_id))
+            .filter(api_versions::error.is_null())
+            .order_by(api_versions::id.desc())
+            .first(&mut self.conn().await?)
             .await
-            .optional()?)
+            .optional()?;
+
+        Ok(versions.map(|versions| versions.unwrap_or_default().into_iter().flatten().collect()))
+    }
+
+    /// Returns all subgraph deployments whose API versions were successfully
+    /// collected at least once.
+    pub async fn sg_deployments_with_api_versions(&self) -> anyhow::Result<HashSet<IpfsCid>> {
+        use schema::{sg_deployment_api_versions as api_versions, sg_deployments};
+
+        let cids: Vec<IpfsCid> = sg_deployments::table
+            .inner_join(api_versions::table)
+            .select(sg_deployments::ipfs_cid)
+            .filter(api_versions::error.is_null())
+            .distinct()
+            .load(&mut self.conn().await?)
+            .await?;
+
+        Ok(cids.into_iter().collect())
     }
 
-    pub async fn get_first_pending_divergence_investigation_request(
+    /// Returns the most recently collected entity count of every indexer for
+    /// the given deployment, ordered by indexer.
+    pub async fn latest_entity_counts(
         &self,
-    ) -> anyhow::Result<Option<(Uuid, serde_json::Value)>> {
-        use schema::pending_divergence_investigation_requests as requests;
+        deployment: &IpfsCid,
+    ) -> anyhow::Result<Vec<models::EntityCount>> {
+        use schema::{entity_counts, sg_deployments};
+
+        Ok(entity_counts::table
+            .inner_join(sg_deployments::table)
+            .filter(sg_deployments::ipfs_cid.eq(deployment))
+            .select(models::EntityCount::as_select())
+            .distinct_on(entity_counts::indexer_id)
+            .order_by((entity_counts::indexer_id, entity_counts::id.desc()))
+            .load(&mut self.conn().await?)
+            .await?)
+    }
 
-        Ok(requests::table
-            .select((requests::uuid, requests::request))
-            .first::<(Uuid, serde_json::Value)>(&mut self.conn().await?)
-            .await
-            .optional()?)
+    /// Returns the indexing statuses that were reported for the given
+    /// deployment since `since`, optionally restricted to a single indexer,
+    /// oldest first.
+    pub async fn indexing_status_history(
+        &self,
+        deployment: &IpfsCid,
+        indexer: Option<&IndexerAddress>,
+        since: NaiveDateTime,
+        limit: u32,
+    ) -> anyhow::Result<Vec<models::IndexingStatusSnapshot>> {
+        use schema::{indexers, indexing_status_history as history, sg_deployments};
+
+        let mut query = history::table
+            .inner_join(sg_deployments::table)
+            .inner_join(indexers::table)
+            .filter(sg_deployments::ipfs_cid.eq(deployment))
+            .filter(history::created_at.ge(since))
+            .select(models::IndexingStatusSnapshot::as_select())
+            .order_by((history::created_at.asc(), history::id.asc()))
+            .limit(limit.into())
+            .into_boxed();
+        if let Some(indexer) = indexer {
+            query = query.filter(indexers::address.eq(indexer));
+        }
+
+        Ok(query.load(&mut self.conn().await?).await?)
     }
 
-    /// Fetches the divergence investigation report with the given UUID, if it
-    /// exists.
-    pub async fn divergence_investigation_report(
+    /// Returns the subgraph manifest of the given deployment, if it was
+    /// fetched.
+    pub async fn subgraph_manifest(
         &self,
-        uuid: &Uuid,
-    ) -> anyhow::Result<Option<serde_json::Value>> {
-        use schema::divergence_investigation_reports as reports;
+        sg_deployment_id: IntId,
+    ) -> anyhow::Result<Option<models::SgDeploymentManifest>> {
+        use schema::sg_deployment_manifests as manifests;
 
-        Ok(reports::table
-            .select(reports::report)
-            .filter(reports::uuid.eq(uuid))
+        Ok(manifests::table
+            .select(models::SgDeploymentManifest::as_select())
+            .filter(manifests::sg_deployment_id.eq(sg_deployment_id))
             .first(&mut self.conn().await?)
             .await
             .optional()?)
     }
 
-    pub async fn divergence_investigation_request_exists(
+    /// Returns the display metadata and curation signal of the given
+    /// deployment, as last fetched from the network subgraph.
+    pub async fn sg_deployment_network_subgraph_metadata(
         &self,
-        uuid: &Uuid,
-    ) -> anyhow::Result<bool> {
-        use schema::pending_divergence_investigation_requests as requests;
+        sg_deployment_id: IntId,
+    ) -> anyhow::Result<Option<models::SgDeploymentNetworkSubgraphMetadata>> {
+        use schema::sg_deployment_network_subgraph_metadata as metadata;
 
-        let exists = requests::table
-            .filter(requests::uuid.eq(uuid))
-            .count()
-            .get_result::<i64>(&mut self.conn().await?)
-            .await?
-            > 0;
-        Ok(exists)
+        Ok(metadata::table
+            .select(models::SgDeploymentNetworkSubgraphMetadata::as_select())
+            .filter(metadata::sg_deployment_id.eq(sg_deployment_id))
+            .first(&mut self.conn().await?)
+            .await
+            .optional()?)
     }
-}
 
-/// Setters and write operations.
-impl Store {
-    pub async fn overwrite_config(&self, config: serde_json::Value) -> anyhow::Result<()> {
-        use schema::configs;
+    /// Returns the curation signal of all deployments with network subgraph
+    /// metadata, in GRT wei.
+    pub async fn sg_deployment_signal_amounts(&self) -> anyhow::Result<HashMap<IpfsCid, f64>> {
+        use schema::{sg_deployment_network_subgraph_metadata as metadata, sg_deployments};
 
-        diesel::update(configs::table)
-            .set(configs::config.eq(config))
-            .execute(&mut self.conn().await?)
+        let rows: Vec<(IpfsCid, BigDecimal)> = metadata::table
+            .inner_join(sg_deployments::table)
+            .select((sg_deployments::ipfs_cid, metadata::signal_amount))
+            .load(&mut self.conn().await?)
             .await?;
 
-        Ok(())
+        Ok(rows
+            .into_iter()
+            .filter_map(|(deployment, signal_amount)| Some((deployment, signal_amount.to_f64()?)))
+            .collect())
     }
 
-    async fn create_master_api_key(&self) -> anyhow::Result<()> {
-        let api_key = self
-            .create_api_key(None, ApiKeyPermissionLevel::Admin)
-            .await?;
-
-        let description = format!("Master API key created during database initialization. Use it to create a new private API key and then delete it for security reasons. `{}`", api_key.api_key);
-        self.modify_api_key(
-            &api_key.api_key,
-            Some(&description),
-            ApiKeyPermissionLevel::Admin,
-        )
-        .await?;
+    /// Returns the cross-check tier that the given deployment was last
+    /// assigned to, if any.
+    pub async fn sg_deployment_check_tier(
+        &self,
+        sg_deployment_id: IntId,
+    ) -> anyhow::Result<Option<i32>> {
+        use schema::sg_deployment_check_tiers as check_tiers;
 
-        info!(api_key = ?api_key.api_key, "Created master API key");
+        Ok(check_tiers::table
+            .select(check_tiers::tier)
+            .filter(check_tiers::sg_deployment_id.eq(sg_deployment_id))
+            .first(&mut self.conn().await?)
+            .await
+            .optional()?)
+    }
 
-        Ok(())
+    /// Returns the data sources and data source templates of the subgraph
+    /// manifest of the given deployment, in manifest order.
+    pub async fn subgraph_data_sources(
+        &self,
+        sg_deployment_id: IntId,
+    ) -> anyhow::Result<Vec<models::SgDeploymentDataSource>> {
+        use schema::sg_deployment_data_sources as data_sources;
+
+        Ok(data_sources::table
+            .select(models::SgDeploymentDataSource::as_select())
+            .filter(data_sources::sg_deployment_id.eq(sg_deployment_id))
+            .order_by(data_sources::id.asc())
+            .load(&mut self.conn().await?)
+            .await?)
     }
 
-    pub async fn create_networks_if_missing(&self, networks: &[NewNetwork]) -> anyhow::Result<()> {
-        use schema::networks;
+    /// Returns all subgraph deployments whose manifest wasn't fetched yet.
+    pub async fn sg_deployments_without_manifest(&self) -> anyhow::Result<Vec<IpfsCid>> {
+        use schema::{sg_deployment_manifests as manifests, sg_deployments};
 
-        let mut conn = self.conn().await?;
+        Ok(sg_deployments::table
+            .left_join(manifests::table)
+            .select(sg_deployments::ipfs_cid)
+            .filter(manifests::sg_deployment_id.nullable().is_null())
+            .order_by(sg_deployments::id.asc())
+            .load(&mut self.conn().await?)
+            .await?)
+    }
 
-        // batch insert
+    /// Returns the watchlists of subgraph deployments to cross-check of all
+    /// workspaces.
+    pub async fn tracked_deployments(&self) -> anyhow::Result<Vec<models::TrackedDeployment>> {
+        use schema::{networks, tracked_deployments};
+
+        Ok(tracked_deployments::table
+            .inner_join(networks::table)
+            .select((
+                tracked_deployments::ipfs_cid,
+                networks::name,
+                tracked_deployments::created_at,
+                tracked_deployments::workspace_id,
+            ))
+            .order_by(tracked_deployments::id.asc())
+            .load(&mut self.conn().await?)
+            .await?)
+    }
+
+    /// Aggregate statistics about the network with the given name, or
+    /// [`None`] if there's no such network. If a workspace is given, only
+    /// its tracked deployments are counted.
+    pub async fn network_stats(
+        &self,
+        network: &str,
+        workspace_id: Option<IntId>,
+    ) -> anyhow::Result<Option<models::NetworkStats>> {
+        let mut conn = self.conn().await?;
+        instrumented(
+            "network_stats",
+            diesel_queries::network_stats(&mut conn, network, workspace_id),
+        )
+        .await
+    }
+
+    /// How many indexers agree with each live PoI of the indexer(s) with the
+    /// given address, see [`models::PoiAgreementCounts`].
+    pub async fn poi_agreement_counts(
+        &self,
+        indexer: &IndexerAddress,
+    ) -> anyhow::Result<Vec<models::PoiAgreementCounts>> {
+        let mut conn = self.conn().await?;
+        instrumented(
+            "poi_agreement_counts",
+            diesel_queries::poi_agreement_counts(&mut conn, indexer),
+        )
+        .await
+    }
+
+    /// Recomputes the daily agreement rollups (see
+    /// [`Store::daily_deployment_agreement`] and
+    /// [`Store::daily_indexer_agreement`]) of all days since the last refresh,
+    /// including the day of the last refresh, which may have been incomplete.
+    /// The first refresh computes them for all PoIs.
+    pub async fn refresh_daily_agreement(&self) -> anyhow::Result<()> {
+        use schema::daily_deployment_agreement;
+
+        self.conn()
+            .await?
+            .transaction::<_, Error, _>(|conn| {
+                async move {
+                    maintenance::lift_statement_timeout(conn).await?;
+                    let since = daily_deployment_agreement::table
+                        .select(diesel::dsl::max(daily_deployment_agreement::day))
+                        .get_result(conn)
+                        .await?;
+                    instrumented(
+                        "refresh_daily_agreement",
+                        diesel_queries::refresh_daily_agreement(conn, since),
+                    )
+                    .await
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+
+    /// How well indexers agreed on the PoIs of the given subgraph deployment,
+    /// per day for the last `days` days, oldest first. It's only as recent as
+    /// the last [`Store::refresh_daily_agreement`].
+    pub async fn daily_deployment_agreement(
+        &self,
+        deployment: &IpfsCid,
+        days: u32,
+    ) -> anyhow::Result<Vec<models::DailyDeploymentAgreement>> {
+        use schema::{daily_deployment_agreement, sg_deployments};
+
+        let since = chrono::Utc::now().date_naive() - chrono::Days::new(days.into());
+        Ok(daily_deployment_agreement::table
+            .inner_join(sg_deployments::table)
+            .filter(sg_deployments::ipfs_cid.eq(deployment))
+            .filter(daily_deployment_agreement::day.gt(since))
+            .order_by(daily_deployment_agreement::day.asc())
+            .select(models::DailyDeploymentAgreement::as_select())
+            .load(&mut self.conn().await?)
+            .await?)
+    }
+
+    /// How well the PoIs of the indexer with the given address agreed with
+    /// those of other indexers, per day for the last `days` days, oldest
+    /// first. It's only as recent as the last
+    /// [`Store::refresh_daily_agreement`].
+    pub async fn daily_indexer_agreement(
+        &self,
+        indexer: &IndexerAddress,
+        days: u32,
+    ) -> anyhow::Result<Vec<models::DailyIndexerAgreement>> {
+        use schema::{daily_indexer_agreement, indexers};
+
+        let since = chrono::Utc::now().date_naive() - chrono::Days::new(days.into());
+        Ok(daily_indexer_agreement::table
+            .inner_join(indexers::table)
+            .filter(indexers::address.eq(indexer))
+            .filter(daily_indexer_agreement::day.gt(since))
+            .order_by(daily_indexer_agreement::day.asc())
+            .select(models::DailyIndexerAgreement::as_select())
+            .load(&mut self.conn().await?)
+            .await?)
+    }
+
+    /// Returns all indexers that were added at runtime, as opposed to those
+    /// listed in the configuration file, in any workspace.
+    pub async fn registered_indexers(&self) -> anyhow::Result<Vec<models::RegisteredIndexer>> {
+        use schema::registered_indexers;
+
+        Ok(registered_indexers::table
+            .select(models::RegisteredIndexer::as_select())
+            .order_by(registered_indexers::id.asc())
+            .load(&mut self.conn().await?)
+            .await?)
+    }
+
+    /// Returns all indexers stored in the database.
+    pub async fn indexers(
+        &self,
+        filter: inputs::IndexersQuery,
+    ) -> anyhow::Result<Vec<models::Indexer>> {
+        let mut conn = self.conn().await?;
+        instrumented("indexers", diesel_queries::indexers(&mut conn, filter)).await
+    }
+
+    /// Returns the subgraph deployments whose IPFS CID or name starts with
+    /// `term`, followed by those whose name is similar to it.
+    pub async fn search_sg_deployments(
+        &self,
+        term: &str,
+        limit: u16,
+    ) -> anyhow::Result<Vec<SgDeployment>> {
+        let mut conn = self.conn().await?;
+        instrumented(
+            "search_sg_deployments",
+            diesel_queries::search_sg_deployments(&mut conn, term, limit),
+        )
+        .await
+    }
+
+    /// Returns the indexers whose name or hex-encoded address starts with
+    /// `term`, followed by those whose name is similar to it.
+    pub async fn search_indexers(
+        &self,
+        term: &str,
+        limit: u16,
+    ) -> anyhow::Result<Vec<models::Indexer>> {
+        let mut conn = self.conn().await?;
+        instrumented(
+            "search_indexers",
+            diesel_queries::search_indexers(&mut conn, term, limit),
+        )
+        .await
+    }
+
+    /// Queries the database for proofs of indexing that refer to the specified
+    /// subgraph deployments and in the given [`inputs::BlockRange`], if given.
+    /// If any `indexer_tags` are given, only PoIs of indexers with one of them
+    /// are returned.
+    pub async fn pois(
+        &self,
+        sg_deployments: &[IpfsCid],
+        indexer_tags: &[String],
+        block_range: Option<inputs::BlockRange>,
+        limit: Option<u16>,
+        order: inputs::PoisOrder,
+    ) -> anyhow::Result<Vec<Poi>> {
+        let mut conn = self.conn().await?;
+        let query = diesel_queries::pois(
+            &mut conn,
+            None,
+            indexer_tags,
+            Some(sg_deployments),
+            block_range,
+            limit,
+            false,
+            order,
+        );
+        instrumented("pois", query).await
+    }
+
+    /// Like `pois`, but only returns live pois.
+    pub async fn live_pois(
+        &self,
+        indexer_address: Option<&IndexerAddress>,
+        indexer_tags: &[String],
+        sg_deployments_cids: Option<&[IpfsCid]>,
+        block_range: Option<inputs::BlockRange>,
+        limit: Option<u16>,
+    ) -> anyhow::Result<Vec<Poi>> {
+        let mut conn = self.conn().await?;
+        let query = diesel_queries::pois(
+            &mut conn,
+            indexer_address,
+            indexer_tags,
+            sg_deployments_cids,
+            block_range,
+            limit,
+            true,
+            inputs::PoisOrder::default(),
+        );
+        instrumented("live_pois", query).await
+    }
+
+    pub async fn api_keys(&self) -> anyhow::Result<Vec<ApiKeyPublicMetadata>> {
+        use schema::graphix_api_tokens;
+
+        Ok(graphix_api_tokens::table
+            .load::<ApiKeyDbRow>(&mut self.conn().await?)
+            .await?
+            .into_iter()
+            .map(ApiKeyPublicMetadata::from)
+            .collect())
+    }
+
+    pub async fn permission_level(
+        &self,
+        api_key: &ApiKey,
+    ) -> anyhow::Result<Option<ApiKeyPermissionLevel>> {
+        use schema::graphix_api_tokens;
+
+        Ok(graphix_api_tokens::table
+            .select(graphix_api_tokens::permission_level)
+            .filter(graphix_api_tokens::sha256_api_key_hash.eq(api_key.hash()))
+            .get_result(&mut self.conn().await?)
+            .await
+            .optional()?)
+    }
+
+    /// Returns the limits of the given API key, or [`None`] if it doesn't
+    /// exist.
+    pub async fn api_key_limits(&self, api_key: &ApiKey) -> anyhow::Result<Option<ApiKeyLimits>> {
+        use schema::{api_key_daily_usage, graphix_api_tokens};
+
+        let conn = &mut self.conn().await?;
+        let row: Option<(String, Option<NaiveDateTime>, Option<i32>)> = graphix_api_tokens::table
+            .select((
+                graphix_api_tokens::public_prefix,
+                graphix_api_tokens::expires_at,
+                graphix_api_tokens::daily_request_quota,
+            ))
+            .filter(graphix_api_tokens::sha256_api_key_hash.eq(api_key.hash()))
+            .get_result(conn)
+            .await
+            .optional()?;
+        let Some((public_prefix, expires_at, daily_request_quota)) = row else {
+            return Ok(None);
+        };
+
+        let requests_today: Option<i64> = api_key_daily_usage::table
+            .select(api_key_daily_usage::request_count)
+            .filter(api_key_daily_usage::public_prefix.eq(public_prefix))
+            .filter(api_key_daily_usage::day.eq(chrono::Utc::now().date_naive()))
+            .get_result(conn)
+            .await
+            .optional()?;
+
+        Ok(Some(ApiKeyLimits {
+            expires_at,
+            daily_request_quota,
+            requests_today: requests_today.unwrap_or(0),
+        }))
+    }
+
+    /// Returns the usage of all API keys that were used at least once, most
+    /// used first.
+    pub async fn api_key_usage(&self) -> anyhow::Result<Vec<ApiKeyUsage>> {
+        use schema::api_key_usage;
+
+        Ok(api_key_usage::table
+            .order_by(api_key_usage::query_count.desc())
+            .select(ApiKeyUsage::as_select())
+            .load(&mut self.conn().await?)
+            .await?)
+    }
+
+    /// Returns the workspace that the given API key is scoped to, if any.
+    pub async fn api_key_workspace_id(&self, api_key: &ApiKey) -> anyhow::Result<Option<IntId>> {
+        use schema::graphix_api_tokens;
+
+        Ok(graphix_api_tokens::table
+            .select(graphix_api_tokens::workspace_id)
+            .filter(graphix_api_tokens::sha256_api_key_hash.eq(api_key.hash()))
+            .get_result(&mut self.conn().await?)
+            .await
+            .optional()?
+            .flatten())
+    }
+
+    pub async fn workspaces(&self) -> anyhow::Result<Vec<models::Workspace>> {
+        use schema::workspaces;
+
+        Ok(workspaces::table
+            .select(models::Workspace::as_select())
+            .order_by(workspaces::id.asc())
+            .load(&mut self.conn().await?)
+            .await?)
+    }
+
+    /// Lists the divergence investigation jobs in the queue, oldest first.
+    pub async fn divergence_investigation_jobs(
+        &self,
+        status: Option<DivergenceInvestigationJobStatus>,
+    ) -> anyhow::Result<Vec<DivergenceInvestigationJob>> {
+        use schema::divergence_investigation_jobs as jobs;
+
+        let mut query = jobs::table
+            .select(DivergenceInvestigationJob::as_select())
+            .order_by(jobs::created_at.asc())
+            .into_boxed();
+        if let Some(status) = status {
+            query = query.filter(jobs::status.eq(status));
+        }
+
+        Ok(query.load(&mut self.conn().await?).await?)
+    }
+
+    /// Fetches the divergence investigation report with the given UUID, if it
+    /// exists.
+    pub async fn divergence_investigation_report(
+        &self,
+        uuid: &Uuid,
+    ) -> anyhow::Result<Option<serde_json::Value>> {
+        use schema::divergence_investigation_reports as reports;
+
+        Ok(reports::table
+            .select(reports::report)
+            .filter(reports::uuid.eq(uuid))
+            .first(&mut self.conn().await?)
+            .await
+            .optional()?)
+    }
+
+    /// Returns the reports of all divergence investigations that match the
+    /// filtering criteria, most recent first. Investigations are matched by
+    /// deployment and indexer through their bisection runs, so cohort
+    /// bisections only match filters without either.
+    pub async fn divergence_investigation_reports(
+        &self,
+        filter: inputs::DivergenceInvestigationsQuery,
+    ) -> anyhow::Result<Vec<serde_json::Value>> {
+        use schema::{
+            bisection_runs as runs, divergence_investigation_reports as reports, indexers,
+            sg_deployments as sgd,
+        };
+
+        let mut query = reports::table
+            .select(reports::report)
+            .order_by(reports::created_at.desc())
+            .into_boxed();
+
+        if let Some(deployment) = filter.deployment {
+            let investigation_uuids = runs::table
+                .inner_join(sgd::table)
+                .select(runs::investigation_uuid)
+                .filter(sgd::ipfs_cid.eq(deployment));
+            query = query.filter(reports::uuid.eq_any(investigation_uuids));
+        }
+        if let Some(address) = filter.indexer {
+            let indexer_ids = || {
+                indexers::table
+                    .select(indexers::id.nullable())
+                    .filter(indexers::address.eq(address))
+            };
+            let investigation_uuids = runs::table.select(runs::investigation_uuid).filter(
+                runs::indexer1_id
+                    .eq_any(indexer_ids())
+                    .or(runs::indexer2_id.eq_any(indexer_ids())),
+            );
+            query = query.filter(reports::uuid.eq_any(investigation_uuids));
+        }
+        if let Some(status) = filter.status {
+            query = query.filter(reports::report.contains(serde_json::json!({ "status": status })));
+        }
+        if let Some(created_after) = filter.created_after {
+            query = query.filter(reports::created_at.ge(created_after));
+        }
+        if let Some(created_before) = filter.created_before {
+            query = query.filter(reports::created_at.le(created_before));
+        }
+        if let Some(limit) = filter.limit {
+            query = query.limit(limit.into());
+        }
+
+        Ok(query.load(&mut self.conn().await?).await?)
+    }
+
+    /// Returns the bisection runs of all divergence investigations that match
+    /// the filtering criteria, most recent first.
+    pub async fn bisection_runs(
+        &self,
+        filter: inputs::BisectionRunsQuery,
+    ) -> anyhow::Result<Vec<BisectionRun>> {
+        use schema::{bisection_runs as runs, indexers, sg_deployments as sgd};
+
+        let mut query = runs::table
+            .select(BisectionRun::as_select())
+            .order_by(runs::created_at.desc())
+            .into_boxed();
+
+        if let Some(deployment) = filter.deployment {
+            let deployment_ids = sgd::table
+                .select(sgd::id.nullable())
+                .filter(sgd::ipfs_cid.eq(deployment));
+            query = query.filter(runs::sg_deployment_id.eq_any(deployment_ids));
+        }
+        if let Some(address) = filter.indexer {
+            let indexer_ids = || {
+                indexers::table
+                    .select(indexers::id.nullable())
+                    .filter(indexers::address.eq(address))
+            };
+            query = query.filter(
+                runs::indexer1_id
+                    .eq_any(indexer_ids())
+                    .or(runs::indexer2_id.eq_any(indexer_ids())),
+            );
+        }
+        if let Some(block_number) = filter.block_number {
+            let block_number = i64::try_from(block_number)?;
+            query = query.filter(
+                runs::lower_bound
+                    .le(block_number)
+                    .and(runs::upper_bound.ge(block_number)),
+            );
+        }
+        if let Some(limit) = filter.limit {
+            query = query.limit(limit.into());
+        }
+
+        Ok(query.load(&mut self.conn().await?).await?)
+    }
+
+    /// Returns the bisection run with the given UUID, if there is one.
+    pub async fn bisection_run(&self, uuid: &Uuid) -> anyhow::Result<Option<BisectionRun>> {
+        use schema::bisection_runs as runs;
+
+        Ok(runs::table
+            .select(BisectionRun::as_select())
+            .filter(runs::uuid.eq(uuid))
+            .get_result(&mut self.conn().await?)
+            .await
+            .optional()?)
+    }
+
+    /// Returns PoIs submitted on chain when closing allocations that match the
+    /// filtering criteria, most recently closed first.
+    pub async fn allocation_pois(
+        &self,
+        filter: inputs::AllocationPoisQuery,
+    ) -> anyhow::Result<Vec<models::AllocationPoi>> {
+        use schema::{allocation_pois, indexers, sg_deployments as sgd};
+
+        let mut query = allocation_pois::table
+            .select(models::AllocationPoi::as_select())
+            .order_by(allocation_pois::closed_at.desc())
+            .into_boxed();
+
+        if let Some(deployment) = filter.deployment {
+            let deployment_ids = sgd::table
+                .select(sgd::id)
+                .filter(sgd::ipfs_cid.eq(deployment));
+            query = query.filter(allocation_pois::sg_deployment_id.eq_any(deployment_ids));
+        }
+        if let Some(address) = filter.indexer {
+            let indexer_ids = indexers::table
+                .select(indexers::id)
+                .filter(indexers::address.eq(address));
+            query = query.filter(allocation_pois::indexer_id.eq_any(indexer_ids));
+        }
+        if filter.only_mismatches {
+            query = query.filter(allocation_pois::matches.eq(false));
+        }
+        if let Some(limit) = filter.limit {
+            query = query.limit(limit.into());
+        }
+
+        Ok(query.load(&mut self.conn().await?).await?)
+    }
+
+    /// Returns chain disagreements that match the filtering criteria, most
+    /// recently detected first.
+    pub async fn chain_disagreements(
+        &self,
+        filter: inputs::ChainDisagreementsQuery,
+    ) -> anyhow::Result<Vec<models::ChainDisagreement>> {
+        use diesel::dsl::min;
+        use schema::{chain_disagreements, sg_deployments as sgd};
+
+        let mut conn = self.conn().await?;
+        let mut query = chain_disagreements::table
+            .group_by((
+                chain_disagreements::sg_deployment_id,
+                chain_disagreements::block_number,
+            ))
+            .select((
+                chain_disagreements::sg_deployment_id,
+                chain_disagreements::block_number,
+            ))
+            .order_by(min(chain_disagreements::created_at).desc())
+            .into_boxed();
+        if let Some(deployment) = filter.deployment {
+            let deployment_ids = sgd::table
+                .select(sgd::id)
+                .filter(sgd::ipfs_cid.eq(deployment));
+            query = query.filter(chain_disagreements::sg_deployment_id.eq_any(deployment_ids));
+        }
+        if let Some(limit) = filter.limit {
+            query = query.limit(limit.into());
+        }
+        let blocks: Vec<(IntId, i64)> = query.load(&mut conn).await?;
+
+        let mut reports: HashMap<(IntId, i64), Vec<models::ChainDisagreementReport>> =
+            HashMap::new();
+        for report in chain_disagreements::table
+            .select(models::ChainDisagreementReport::as_select())
+            .filter(chain_disagreements::sg_deployment_id.eq_any(blocks.iter().map(|b| b.0)))
+            .filter(chain_disagreements::block_number.eq_any(blocks.iter().map(|b| b.1)))
+            .order_by(chain_disagreements::id)
+            .load(&mut conn)
+            .await?
+        {
+            reports
+                .entry((report.sg_deployment_id, report.block_number))
+                .or_default()
+                .push(report);
+        }
+
+        Ok(blocks
+            .into_iter()
+            .map(
+                |(sg_deployment_id, block_number)| models::ChainDisagreement {
+                    sg_deployment_id,
+                    block_number,
+                    reports: reports
+                        .remove(&(sg_deployment_id, block_number))
+                        .unwrap_or_default(),
+                },
+            )
+            .collect())
+    }
+
+    /// Returns the closing time of the most recently closed allocation whose
+    /// PoI is stored, if any.
+    pub async fn latest_allocation_poi_closed_at(&self) -> anyhow::Result<Option<NaiveDateTime>> {
+        use diesel::dsl::max;
+        use schema::allocation_pois;
+
+        Ok(allocation_pois::table
+            .select(max(allocation_pois::closed_at))
+            .get_result(&mut self.conn().await?)
+            .await?)
+    }
+
+    /// Returns on-chain disputes about PoIs that match the filtering criteria,
+    /// most recently opened first.
+    pub async fn poi_disputes(
+        &self,
+        filter: inputs::PoiDisputesQuery,
+    ) -> anyhow::Result<Vec<models::PoiDispute>> {
+        use schema::{indexers, poi_disputes, sg_deployments as sgd};
+
+        let mut query = poi_disputes::table
+            .select(models::PoiDispute::as_select())
+            .order_by(poi_disputes::opened_at.desc())
+            .into_boxed();
+
+        if let Some(deployment) = filter.deployment {
+            let deployment_ids = sgd::table
+                .select(sgd::id)
+                .filter(sgd::ipfs_cid.eq(deployment));
+            query = query.filter(poi_disputes::sg_deployment_id.eq_any(deployment_ids));
+        }
+        if let Some(address) = filter.indexer {
+            let indexer_ids = indexers::table
+                .select(indexers::id)
+                .filter(indexers::address.eq(address));
+            query = query.filter(poi_disputes::indexer_id.eq_any(indexer_ids));
+        }
+        if filter.only_undecided {
+            query = query.filter(poi_disputes::status.eq(DisputeStatus::Undecided));
+        }
+        if let Some(limit) = filter.limit {
+            query = query.limit(limit.into());
+        }
+
+        Ok(query.load(&mut self.conn().await?).await?)
+    }
+
+    /// Returns the opening time of the most recently opened dispute that is
+    /// stored, if any.
+    pub async fn latest_poi_dispute_opened_at(&self) -> anyhow::Result<Option<NaiveDateTime>> {
+        use diesel::dsl::max;
+        use schema::poi_disputes;
+
+        Ok(poi_disputes::table
+            .select(max(poi_disputes::opened_at))
+            .get_result(&mut self.conn().await?)
+            .await?)
+    }
+
+    /// Returns the on-chain IDs of all stored disputes that weren't resolved
+    /// yet.
+    pub async fn undecided_poi_dispute_ids(&self) -> anyhow::Result<Vec<String>> {
+        use schema::poi_disputes;
+
+        Ok(poi_disputes::table
+            .select(poi_disputes::dispute_id)
+            .filter(poi_disputes::status.eq(DisputeStatus::Undecided))
+            .load(&mut self.conn().await?)
+            .await?)
+    }
+
+    /// Returns active allocations that the allocating indexers report, but
+    /// either don't index the deployment of, or have no live PoIs for, most
+    /// recently created first.
+    pub async fn allocation_discrepancies(
+        &self,
+        filter: inputs::AllocationDiscrepanciesQuery,
+    ) -> anyhow::Result<Vec<models::AllocationDiscrepancy>> {
+        use diesel::dsl::{exists, not};
+        use schema::{
+            indexer_allocations as allocations, indexers, live_pois, sg_deployments as sgd,
+        };
+
+        let live_pois = || {
+            exists(
+                live_pois::table
+                    .inner_join(sgd::table)
+                    .filter(live_pois::indexer_id.eq(allocations::indexer_id))
+                    .filter(sgd::ipfs_cid.eq(allocations::deployment)),
+            )
+        };
+        let mut query = allocations::table
+            .select((models::IndexerAllocation::as_select(), live_pois()))
+            .filter(allocations::indexing.eq(false).or(not(live_pois())))
+            .order_by(allocations::created_at_epoch.desc())
+            .then_order_by(allocations::id.asc())
+            .into_boxed();
+
+        if let Some(address) = filter.indexer {
+            let indexer_ids = indexers::table
+                .select(indexers::id)
+                .filter(indexers::address.eq(address));
+            query = query.filter(allocations::indexer_id.eq_any(indexer_ids));
+        }
+        if let Some(limit) = filter.limit {
+            query = query.limit(limit.into());
+        }
+
+        let rows: Vec<(models::IndexerAllocation, bool)> =
+            query.load(&mut self.conn().await?).await?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(allocation, has_live_pois)| models::AllocationDiscrepancy {
+                    allocation,
+                    has_live_pois,
+                },
+            )
+            .collect())
+    }
+
+    /// Returns all blocks that were queried during the given bisection run,
+    /// in order.
+    pub async fn bisection_attempts(
+        &self,
+        bisection_run_uuid: &Uuid,
+    ) -> anyhow::Result<Vec<BisectionAttempt>> {
+        use schema::bisection_attempts as attempts;
+
+        Ok(attempts::table
+            .select(BisectionAttempt::as_select())
+            .filter(attempts::bisection_run_uuid.eq(bisection_run_uuid))
+            .order_by(attempts::id.asc())
+            .load(&mut self.conn().await?)
+            .await?)
+    }
+
+    /// Returns all evidence collected during the given bisection run.
+    pub async fn bisection_evidence(
+        &self,
+        bisection_run_uuid: &Uuid,
+    ) -> anyhow::Result<Vec<BisectionEvidence>> {
+        use schema::bisection_evidence as evidence;
+
+        Ok(evidence::table
+            .select(BisectionEvidence::as_select())
+            .filter(evidence::bisection_run_uuid.eq(bisection_run_uuid))
+            .order_by(evidence::id.asc())
+            .load(&mut self.conn().await?)
+            .await?)
+    }
+
+    /// Stores raw indexer responses captured during the given divergence
+    /// investigation. Responses of indexers that aren't in the database are
+    /// skipped.
+    pub async fn write_raw_indexer_responses(
+        &self,
+        investigation_uuid: &Uuid,
+        responses: Vec<RawResponse>,
+    ) -> anyhow::Result<()> {
+        use schema::raw_indexer_responses;
+
+        let conn = &mut self.conn().await?;
+        let mut rows = vec![];
+        for response in responses {
+            let name = response.indexer_name.map(Cow::Owned);
+            let Ok(indexer_id) =
+                diesel_queries::get_indexer_id(conn, name, &response.indexer_address).await
+            else {
+                continue;
+            };
+            rows.push(NewRawIndexerResponse {
+                investigation_uuid: *investigation_uuid,
+                indexer_id,
+                operation_name: response.operation_name,
+                body: response.body,
+            });
+        }
+
+        diesel::insert_into(raw_indexer_responses::table)
+            .values(&rows)
+            .execute(conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns all raw indexer responses captured during the given divergence
+    /// investigation, in the order they were received.
+    pub async fn raw_indexer_responses(
+        &self,
+        investigation_uuid: &Uuid,
+    ) -> anyhow::Result<Vec<RawIndexerResponse>> {
+        use schema::raw_indexer_responses as responses;
+
+        Ok(responses::table
+            .select(RawIndexerResponse::as_select())
+            .filter(responses::investigation_uuid.eq(investigation_uuid))
+            .order_by(responses::id.asc())
+            .load(&mut self.conn().await?)
+            .await?)
+    }
+
+    /// Whether the divergence investigation request with the given UUID is
+    /// still queued or being worked on, i.e. it's neither acknowledged nor
+    /// dead-lettered.
+    pub async fn divergence_investigation_request_exists(
+        &self,
+        uuid: &Uuid,
+    ) -> anyhow::Result<bool> {
+        use schema::divergence_investigation_jobs as jobs;
+
+        let exists = jobs::table
+            .filter(jobs::uuid.eq(uuid))
+            .filter(jobs::status.ne(DivergenceInvestigationJobStatus::DeadLettered))
+            .count()
+            .get_result::<i64>(&mut self.conn().await?)
+            .await?
+            > 0;
+        Ok(exists)
+    }
+
+    /// Marks the pending divergence investigation request with the given UUID
+    /// as cancelled. Returns false if there's no such request, e.g. because
+    /// the investigation is already over.
+    pub async fn cancel_divergence_investigation_request(
+        &self,
+        uuid: &Uuid,
+    ) -> anyhow::Result<bool> {
+        use schema::divergence_investigation_jobs as jobs;
+
+        let updated = diesel::update(
+            jobs::table
+                .filter(jobs::uuid.eq(uuid))
+                .filter(jobs::status.ne(DivergenceInvestigationJobStatus::DeadLettered)),
+        )
+        .set(jobs::cancelled.eq(true))
+        .execute(&mut self.conn().await?)
+        .await?;
+        Ok(updated > 0)
+    }
+
+    /// Whether the pending divergence investigation request with the given
+    /// UUID was cancelled.
+    pub async fn divergence_investigation_request_cancelled(
+        &self,
+        uuid: &Uuid,
+    ) -> anyhow::Result<bool> {
+        use schema::divergence_investigation_jobs as jobs;
+
+        let cancelled = jobs::table
+            .select(jobs::cancelled)
+            .filter(jobs::uuid.eq(uuid))
+            .first(&mut self.conn().await?)
+            .await
+            .optional()?;
+        Ok(cancelled.unwrap_or(false))
+    }
+
+    /// The number of divergence investigation requests that haven't been
+    /// handled yet. Dead-lettered ones are handled.
+    pub async fn pending_divergence_investigation_requests_count(&self) -> anyhow::Result<u64> {
+        use schema::divergence_investigation_jobs as jobs;
+
+        let count: i64 = jobs::table
+            .filter(jobs::status.ne(DivergenceInvestigationJobStatus::DeadLettered))
+            .count()
+            .get_result(&mut self.conn().await?)
+            .await?;
+        Ok(count as u64)
+    }
+}
+
+/// Setters and write operations.
+impl Store {
+    /// Stores a new version of the configuration, which becomes the
+    /// [`Store::current_config`]. Older versions are kept.
+    pub async fn overwrite_config(&self, config: serde_json::Value) -> anyhow::Result<()> {
+        use schema::configs;
+
+        diesel::insert_into(configs::table)
+            .values(configs::config.eq(config))
+            .execute(&mut self.conn().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn create_master_api_key(&self) -> anyhow::Result<()> {
+        let api_key = self
+            .create_api_key(None, ApiKeyPermissionLevel::Admin, None)
+            .await?;
+
+        let description = format!("Master API key created during database initialization. Use it to create a new private API key and then delete it for security reasons. `{}`", api_key.api_key);
+        self.modify_api_key(
+            &api_key.api_key,
+            Some(&description),
+            ApiKeyPermissionLevel::Admin,
+        )
+        .await?;
+
+        info!(api_key = ?api_key.api_key, "Created master API key");
+
+        Ok(())
+    }
+
+    pub async fn create_networks_if_missing(&self, networks: &[NewNetwork]) -> anyhow::Result<()> {
+        use schema::networks;
+
+        let mut conn = self.conn().await?;
+
+        // batch insert
         diesel::insert_into(networks::table)
             .values(networks)
             .on_conflict_do_nothing()
             .execute(&mut conn)
             .await?;
 
-        Ok(())
+        Ok(())
+    }
+
+    /// Stores the given subgraph deployments on their networks, which are
+    /// created if they don't exist yet. Deployments that are already stored
+    /// on another network are moved, e.g. if the name of their network was
+    /// normalized differently before.
+    pub async fn write_sg_deployment_networks(
+        &self,
+        deployment_networks: &HashMap<IpfsCid, String>,
+    ) -> anyhow::Result<()> {
+        use schema::{networks, sg_deployments};
+
+        let new_networks: Vec<NewNetwork> = deployment_networks
+            .values()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|name| NewNetwork {
+                name: name.clone(),
+                caip2: None,
+            })
+            .collect();
+        self.create_networks_if_missing(&new_networks).await?;
+
+        let conn = &mut self.conn().await?;
+        let network_ids: HashMap<String, IntId> = networks::table
+            .select((networks::name, networks::id))
+            .load::<(String, IntId)>(conn)
+            .await?
+            .into_iter()
+            .collect();
+        let cids: Vec<String> = deployment_networks.keys().map(|d| d.to_string()).collect();
+        let stored_networks: HashMap<String, IntId> = sg_deployments::table
+            .select((sg_deployments::ipfs_cid, sg_deployments::network))
+            .filter(sg_deployments::ipfs_cid.eq_any(&cids))
+            .load::<(String, IntId)>(conn)
+            .await?
+            .into_iter()
+            .collect();
+
+        for (deployment, network) in deployment_networks {
+            let network_id = *network_ids
+                .get(network)
+                .ok_or_else(|| anyhow!("network {network} not found"))?;
+            let cid = deployment.to_string();
+            if stored_networks.get(&cid) == Some(&network_id) {
+                continue;
+            }
+
+            diesel::insert_into(sg_deployments::table)
+                .values((
+                    sg_deployments::ipfs_cid.eq(&cid),
+                    sg_deployments::network.eq(network_id),
+                ))
+                .on_conflict(sg_deployments::ipfs_cid)
+                .do_update()
+                .set(sg_deployments::network.eq(network_id))
+                .execute(conn)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn create_sg_deployment(
+        &self,
+        network_name: &str,
+        ipfs_cid: &str,
+    ) -> anyhow::Result<()> {
+        use schema::sg_deployments as sgd;
+
+        diesel::insert_into(sgd::table)
+            .values((
+                sgd::ipfs_cid.eq(ipfs_cid),
+                sgd::network.eq(schema::networks::table
+                    .select(schema::networks::id)
+                    .filter(schema::networks::name.eq(network_name))
+                    .single_value()
+                    .assume_not_null()),
+            ))
+            .execute(&mut self.conn().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_deployment_name(
+        &self,
+        sg_deployment_id: &str,
+        name: &str,
+    ) -> anyhow::Result<()> {
+        use schema::{sg_deployments as sgd, sg_names};
+
+        diesel::insert_into(sg_names::table)
+            .values((
+                sg_names::sg_deployment_id.eq(sgd::table
+                    .select(sgd::id)
+                    .filter(sgd::ipfs_cid.eq(sg_deployment_id))
+                    .single_value()
+                    .assume_not_null()),
+                sg_names::name.eq(name),
+            ))
+            .on_conflict(sg_names::sg_deployment_id)
+            .do_update()
+            .set(sg_names::name.eq(name))
+            .execute(&mut self.conn().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Deletes the network with the given name from the database, together with
+    /// **all** of its related data (indexers, deployments, etc.).
+    pub async fn delete_network(&self, network_name: &str) -> anyhow::Result<()> {
+        use schema::networks;
+
+        diesel::delete(networks::table.filter(networks::name.eq(network_name)))
+            .execute(&mut self.conn().await?)
+            .await?;
+        // The `ON DELETE CASCADE`s should take care of the rest of the cleanup.
+
+        Ok(())
+    }
+
+    /// Adds a subgraph deployment to the watchlist of deployments to
+    /// cross-check of the given workspace (or to the unscoped watchlist), or
+    /// updates its network if it's already on it. The network must be known to
+    /// Graphix.
+    pub async fn track_deployment(
+        &self,
+        ipfs_cid: &IpfsCid,
+        network_name: &str,
+        workspace_id: Option<IntId>,
+    ) -> anyhow::Result<models::TrackedDeployment> {
+        use schema::{networks, tracked_deployments};
+
+        let mut conn = self.conn().await?;
+        let network_id: IntId = networks::table
+            .select(networks::id)
+            .filter(networks::name.eq(network_name))
+            .get_result(&mut conn)
+            .await
+            .optional()?
+            .ok_or_else(|| anyhow!("Unknown network: {}", network_name))?;
+
+        let insert = diesel::insert_into(tracked_deployments::table).values((
+            tracked_deployments::ipfs_cid.eq(ipfs_cid),
+            tracked_deployments::network_id.eq(network_id),
+            tracked_deployments::workspace_id.eq(workspace_id),
+        ));
+        let created_at = match workspace_id {
+            None => {
+                insert
+                    .on_conflict(tracked_deployments::ipfs_cid)
+                    .filter_target(tracked_deployments::workspace_id.is_null())
+                    .do_update()
+                    .set(tracked_deployments::network_id.eq(network_id))
+                    .returning(tracked_deployments::created_at)
+                    .get_result(&mut conn)
+                    .await?
+            }
+            Some(_) => {
+                insert
+                    .on_conflict((
+                        tracked_deployments::workspace_id,
+                        tracked_deployments::ipfs_cid,
+                    ))
+                    .do_update()
+                    .set(tracked_deployments::network_id.eq(network_id))
+                    .returning(tracked_deployments::created_at)
+                    .get_result(&mut conn)
+                    .await?
+            }
+        };
+
+        Ok(models::TrackedDeployment {
+            cid: ipfs_cid.clone(),
+            network: network_name.to_string(),
+            created_at,
+            workspace_id,
+        })
+    }
+
+    /// Removes a subgraph deployment from the watchlist of deployments to
+    /// cross-check of the given workspace (or from the unscoped watchlist).
+    /// Returns `false` if it wasn't on it.
+    pub async fn untrack_deployment(
+        &self,
+        ipfs_cid: &IpfsCid,
+        workspace_id: Option<IntId>,
+    ) -> anyhow::Result<bool> {
+        use schema::tracked_deployments;
+
+        let deleted = diesel::delete(
+            tracked_deployments::table
+                .filter(tracked_deployments::ipfs_cid.eq(ipfs_cid))
+                .filter(tracked_deployments::workspace_id.is_not_distinct_from(workspace_id)),
+        )
+        .execute(&mut self.conn().await?)
+        .await?;
+
+        Ok(deleted > 0)
+    }
+
+    /// Adds an indexer to the registry of indexers of its workspace (or to
+    /// the unscoped registry), or updates its name and endpoint if it's
+    /// already registered there.
+    pub async fn register_indexer(
+        &self,
+        indexer: &models::NewRegisteredIndexer,
+    ) -> anyhow::Result<models::RegisteredIndexer> {
+        use schema::registered_indexers;
+
+        let insert = diesel::insert_into(registered_indexers::table).values(indexer);
+        let conn = &mut self.conn().await?;
+        Ok(match indexer.workspace_id {
+            None => {
+                insert
+                    .on_conflict(registered_indexers::address)
+                    .filter_target(registered_indexers::workspace_id.is_null())
+                    .do_update()
+                    .set(indexer)
+                    .returning(models::RegisteredIndexer::as_returning())
+                    .get_result(conn)
+                    .await?
+            }
+            Some(_) => {
+                insert
+                    .on_conflict((
+                        registered_indexers::workspace_id,
+                        registered_indexers::address,
+                    ))
+                    .do_update()
+                    .set(indexer)
+                    .returning(models::RegisteredIndexer::as_returning())
+                    .get_result(conn)
+                    .await?
+            }
+        })
+    }
+
+    /// Removes an indexer from the registry of indexers of the given
+    /// workspace (or from the unscoped registry). Returns `false` if no such
+    /// indexer was registered. Data collected from the indexer is kept.
+    pub async fn unregister_indexer(
+        &self,
+        address: &IndexerAddress,
+        workspace_id: Option<IntId>,
+    ) -> anyhow::Result<bool> {
+        use schema::registered_indexers;
+
+        let deleted = diesel::delete(
+            registered_indexers::table
+                .filter(registered_indexers::address.eq(address))
+                .filter(registered_indexers::workspace_id.is_not_distinct_from(workspace_id)),
+        )
+        .execute(&mut self.conn().await?)
+        .await?;
+
+        Ok(deleted > 0)
+    }
+
+    /// Archives or unarchives the indexer with the given address. Archiving
+    /// an archived indexer keeps its original archival time. Returns `None`
+    /// if there's no such indexer.
+    pub async fn set_indexer_archived(
+        &self,
+        address: &IndexerAddress,
+        archived: bool,
+    ) -> anyhow::Result<Option<models::Indexer>> {
+        use schema::indexers;
+
+        let mut conn = self.conn().await?;
+        let indexer = || indexers::table.filter(indexers::address.eq(address));
+        if archived {
+            diesel::update(indexer().filter(indexers::archived_at.is_null()))
+                .set(indexers::archived_at.eq(chrono::Utc::now().naive_utc()))
+                .execute(&mut conn)
+                .await?;
+        } else {
+            diesel::update(indexer())
+                .set(indexers::archived_at.eq(None::<NaiveDateTime>))
+                .execute(&mut conn)
+                .await?;
+        }
+
+        Ok(indexer()
+            .select(indexers::all_columns)
+            .get_result::<models::Indexer>(&mut conn)
+            .await
+            .optional()?)
+    }
+
+    /// Replaces the tags of the indexer with the given address that were set
+    /// through the GraphQL API. Its tags from the configuration are kept.
+    /// Returns `None` if there's no such indexer.
+    pub async fn set_indexer_tags(
+        &self,
+        address: &IndexerAddress,
+        tags: &[String],
+    ) -> anyhow::Result<Option<models::Indexer>> {
+        use schema::{indexer_tags, indexers};
+
+        let tags: BTreeSet<&String> = tags.iter().collect();
+        self.conn()
+            .await?
+            .transaction::<_, Error, _>(|conn| {
+                async move {
+                    let Some(indexer) = indexers::table
+                        .select(indexers::all_columns)
+                        .filter(indexers::address.eq(address))
+                        .get_result::<models::Indexer>(conn)
+                        .await
+                        .optional()?
+                    else {
+                        return Ok(None);
+                    };
+
+                    diesel::delete(
+                        indexer_tags::table
+                            .filter(indexer_tags::indexer_id.eq(indexer.id))
+                            .filter(indexer_tags::from_config.eq(false)),
+                    )
+                    .execute(conn)
+                    .await?;
+                    let rows: Vec<_> = tags
+                        .into_iter()
+                        .map(|tag| {
+                            (
+                                indexer_tags::indexer_id.eq(indexer.id),
+                                indexer_tags::tag.eq(tag),
+                                indexer_tags::from_config.eq(false),
+                            )
+                        })
+                        .collect();
+                    if !rows.is_empty() {
+                        diesel::insert_into(indexer_tags::table)
+                            .values(&rows)
+                            .execute(conn)
+                            .await?;
+                    }
+
+                    Ok(Some(indexer))
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+
+    /// Replaces all tags that the configuration assigns to indexers, given as
+    /// pairs of indexer addresses and tags. Tags set through the GraphQL API
+    /// are kept, and indexers that aren't stored yet are skipped.
+    pub async fn write_configured_indexer_tags(
+        &self,
+        tags: &[(IndexerAddress, Vec<String>)],
+    ) -> anyhow::Result<()> {
+        use schema::{indexer_tags, indexers};
+
+        self.conn()
+            .await?
+            .transaction::<_, Error, _>(|conn| {
+                async move {
+                    let addresses: Vec<IndexerAddress> =
+                        tags.iter().map(|(address, _)| *address).collect();
+                    let indexer_ids: HashMap<IndexerAddress, IntId> = indexers::table
+                        .select((indexers::address, indexers::id))
+                        .filter(indexers::address.eq_any(addresses))
+                        .load::<(IndexerAddress, IntId)>(conn)
+                        .await?
+                        .into_iter()
+                        .collect();
+                    let rows: BTreeSet<(IntId, &String)> = tags
+                        .iter()
+                        .filter_map(|(address, tags)| Some((*indexer_ids.get(address)?, tags)))
+                        .flat_map(|(indexer_id, tags)| {
+                            tags.iter().map(move |tag| (indexer_id, tag))
+                        })
+                        .collect();
+
+                    diesel::delete(indexer_tags::table.filter(indexer_tags::from_config.eq(true)))
+                        .execute(conn)
+                        .await?;
+                    let rows: Vec<_> = rows
+                        .into_iter()
+                        .map(|(indexer_id, tag)| {
+                            (
+                                indexer_tags::indexer_id.eq(indexer_id),
+                                indexer_tags::tag.eq(tag),
+                                indexer_tags::from_config.eq(true),
+                            )
+                        })
+                        .collect();
+                    if !rows.is_empty() {
+                        diesel::insert_into(indexer_tags::table)
+                            .values(&rows)
+                            .execute(conn)
+                            .await?;
+                    }
+
+                    Ok(())
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+
+    /// Returns the addresses of archived indexers, which aren't
+    /// cross-checked.
+    pub async fn archived_indexer_addresses(&self) -> anyhow::Result<HashSet<IndexerAddress>> {
+        use schema::indexers;
+
+        let addresses = indexers::table
+            .select(indexers::address)
+            .filter(indexers::archived_at.is_not_null())
+            .load::<IndexerAddress>(&mut self.conn().await?)
+            .await?;
+
+        Ok(addresses.into_iter().collect())
+    }
+
+    /// Deletes all PoIs, failed queries, entity counts, indexing status
+    /// history and divergence investigation reports that belong to the indexer(s) with the given address. The indexer
+    /// itself is kept. Reports are only found through their stored bisection
+    /// runs. If `dry_run` is true, rows are counted but nothing is deleted.
+    pub async fn delete_indexer_data(
+        &self,
+        address: &IndexerAddress,
+        dry_run: bool,
+    ) -> anyhow::Result<IndexerDataDeletion> {
+        use schema::{
+            allocation_pois, bisection_evidence, bisection_runs, divergence_investigation_reports,
+            entity_counts, failed_queries, indexers, indexing_status_history, live_pois,
+            poi_values, pois,
+        };
+
+        self.conn()
+            .await?
+            .transaction::<_, Error, _>(|conn| {
+                async move {
+                    let indexer_ids: Vec<IntId> = indexers::table
+                        .select(indexers::id)
+                        .filter(indexers::address.eq(address))
+                        .load(conn)
+                        .await?;
+                    let report_uuids: Vec<Uuid> = bisection_runs::table
+                        .filter(
+                            bisection_runs::indexer1_id
+                                .eq_any(indexer_ids.clone())
+                                .or(bisection_runs::indexer2_id.eq_any(indexer_ids.clone())),
+                        )
+                        .select(bisection_runs::investigation_uuid)
+                        .distinct()
+                        .load(conn)
+                        .await?;
+
+                    let reports = divergence_investigation_reports::table
+                        .filter(divergence_investigation_reports::uuid.eq_any(&report_uuids));
+                    // Deleting reports cascades to all of their bisection runs,
+                    // not just the ones that involve this indexer.
+                    let all_runs = bisection_runs::table
+                        .filter(bisection_runs::investigation_uuid.eq_any(&report_uuids));
+                    let evidence = bisection_evidence::table.filter(
+                        bisection_evidence::indexer_id
+                            .eq_any(&indexer_ids)
+                            .or(bisection_evidence::bisection_run_uuid
+                                .eq_any(all_runs.clone().select(bisection_runs::uuid))),
+                    );
+                    let live_pois =
+                        live_pois::table.filter(live_pois::indexer_id.eq_any(&indexer_ids));
+                    let pois = pois::table.filter(pois::indexer_id.eq_any(&indexer_ids));
+                    let failed_queries = failed_queries::table
+                        .filter(failed_queries::indexer_id.eq_any(&indexer_ids));
+                    let allocation_pois = allocation_pois::table
+                        .filter(allocation_pois::indexer_id.eq_any(&indexer_ids));
+                    let entity_counts =
+                        entity_counts::table.filter(entity_counts::indexer_id.eq_any(&indexer_ids));
+                    let indexing_status_history = indexing_status_history::table
+                        .filter(indexing_status_history::indexer_id.eq_any(&indexer_ids));
+
+                    let count = |n: i64| n as u64;
+                    let deletion = IndexerDataDeletion {
+                        dry_run,
+                        pois: count(pois.clone().count().get_result(conn).await?),
+                        live_pois: count(live_pois.clone().count().get_result(conn).await?),
+                        failed_queries: count(
+                            failed_queries.clone().count().get_result(conn).await?,
+                        ),
+                        divergence_investigation_reports: report_uuids.len() as u64,
+                        bisection_runs: count(all_runs.count().get_result(conn).await?),
+                        bisection_evidence: count(evidence.clone().count().get_result(conn).await?),
+                        allocation_pois: count(
+                            allocation_pois.clone().count().get_result(conn).await?,
+                        ),
+                        entity_counts: count(entity_counts.clone().count().get_result(conn).await?),
+                        indexing_status_history: count(
+                            indexing_status_history
+                                .clone()
+                                .count()
+                                .get_result(conn)
+                                .await?,
+                        ),
+                    };
+
+                    if !dry_run {
+                        let poi_value_ids: Vec<IntId> = pois
+                            .clone()
+                            .select(pois::poi_value_id)
+                            .distinct()
+                            .load(conn)
+                            .await?;
+
+                        diesel::delete(evidence).execute(conn).await?;
+                        diesel::delete(reports).execute(conn).await?;
+                        diesel::delete(live_pois).execute(conn).await?;
+                        diesel::delete(pois).execute(conn).await?;
+                        // Values that only these indexers produced.
+                        diesel::delete(
+                            poi_values::table
+                                .filter(poi_values::id.eq_any(&poi_value_ids))
+                                .filter(diesel::dsl::not(diesel::dsl::exists(
+                                    pois::table.filter(pois::poi_value_id.eq(poi_values::id)),
+                                ))),
+                        )
+                        .execute(conn)
+                        .await?;
+                        diesel::delete(failed_queries).execute(conn).await?;
+                        diesel::delete(allocation_pois).execute(conn).await?;
+                        diesel::delete(entity_counts).execute(conn).await?;
+                        diesel::delete(indexing_status_history)
+                            .execute(conn)
+                            .await?;
+                    }
+
+                    Ok(deletion)
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+
+    pub async fn create_network(&self, network: &NewNetwork) -> anyhow::Result<IntId> {
+        use schema::networks;
+
+        let id = diesel::insert_into(networks::table)
+            .values(network)
+            .returning(networks::id)
+            .get_result(&mut self.conn().await?)
+            .await?;
+
+        Ok(id)
+    }
+
+    pub async fn write_pois<W>(&self, pois: Vec<W>, live: PoiLiveness) -> anyhow::Result<()>
+    where
+        W: WritablePoi + Send + Sync,
+        W::IndexerId: Send + Sync,
+    {
+        self.conn()
+            .await?
+            .transaction::<_, Error, _>(|conn| {
+                async move {
+                    instrumented("write_pois", diesel_queries::write_pois(conn, pois, live))
+                        .await?;
+                    Ok(())
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+
+    pub async fn write_indexers(
+        &self,
+        indexers: &[impl AsRef<dyn IndexerClient> + Sync],
+    ) -> anyhow::Result<()> {
+        self.conn()
+            .await?
+            .transaction::<_, Error, _>(|conn| {
+                async move {
+                    instrumented(
+                        "write_indexers",
+                        diesel_queries::write_indexers(conn, indexers),
+                    )
+                    .await?;
+                    Ok(())
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+
+    pub async fn delete_indexer_network_subgraph_metadata(
+        &self,
+        indexer_id: IntId,
+    ) -> anyhow::Result<()> {
+        use schema::indexers;
+
+        diesel::update(indexers::table.filter(indexers::id.eq(indexer_id)))
+            .set(indexers::network_subgraph_metadata.eq::<Option<IntId>>(None))
+            .execute(&mut self.conn().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn create_or_update_indexer_network_subgraph_metadata(
+        &self,
+        indexer_id: IntId,
+        metadata: NewIndexerNetworkSubgraphMetadata,
+    ) -> anyhow::Result<IntId> {
+        use schema::{indexer_network_subgraph_metadata, indexers};
+
+        self.conn()
+            .await?
+            .transaction::<_, Error, _>(|conn| {
+                // Fetch the metadata id from indexer_id, and update it if it exists
+                // create a new one and set the foreign key to the indexer_id if it doesn't exist
+                async move {
+                    let metadata_id = indexers::table
+                        .select(indexers::network_subgraph_metadata)
+                        .filter(indexers::id.eq(indexer_id))
+                        .get_result::<Option<IntId>>(conn)
+                        .await?;
+
+                    let metadata_id = match metadata_id {
+                        Some(id) => {
+                            diesel::update(
+                                indexer_network_subgraph_metadata::table
+                                    .filter(indexer_network_subgraph_metadata::id.eq(id)),
+                            )
+                            .set(metadata)
+                            .execute(conn)
+                            .await?;
+                            id
+                        }
+                        None => {
+                            let metadata_id =
+                                diesel::insert_into(indexer_network_subgraph_metadata::table)
+                                    .values(&metadata)
+                                    .returning(indexer_network_subgraph_metadata::id)
+                                    .get_result(conn)
+                                    .await?;
+
+                            diesel::update(indexers::table)
+                                .filter(indexers::id.eq(indexer_id))
+                                .set(indexers::network_subgraph_metadata.eq(metadata_id))
+                                .execute(conn)
+                                .await?;
+
+                            metadata_id
+                        }
+                    };
+
+                    Ok(metadata_id)
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+        Ok(indexer_id)
+    }
+
+    pub async fn create_api_key(
+        &self,
+        notes: Option<&str>,
+        permission_level: ApiKeyPermissionLevel,
+        workspace_id: Option<IntId>,
+    ) -> anyhow::Result<NewlyCreatedApiKey> {
+        use schema::graphix_api_tokens;
+
+        let api_key = ApiKey::generate();
+        let stored_api_key = ApiKeyDbRow {
+            public_prefix: api_key.public_part_as_string(),
+            sha256_api_key_hash: api_key.hash(),
+            notes: notes.map(|s| s.to_string()),
+            permission_level,
+            expires_at: None,
+            daily_request_quota: None,
+            workspace_id,
+        };
+
+        diesel::insert_into(graphix_api_tokens::table)
+            .values(&[stored_api_key])
+            .execute(&mut self.conn().await?)
+            .await?;
+
+        Ok(NewlyCreatedApiKey {
+            api_key: api_key.to_string(),
+            notes: notes.map(|s| s.to_string()),
+            permission_level,
+        })
     }
 
-    pub async fn create_sg_deployment(
+    pub async fn modify_api_key(
         &self,
-        network_name: &str,
-        ipfs_cid: &str,
+        api_key_s: &str,
+        notes: Option<&str>,
+        permission_level: ApiKeyPermissionLevel,
     ) -> anyhow::Result<()> {
-        use schema::sg_deployments as sgd;
+        use schema::graphix_api_tokens;
 
-        diesel::insert_into(sgd::table)
-            .values((
-                sgd::ipfs_cid.eq(ipfs_cid),
-                sgd::network.eq(schema::networks::table
-                    .select(schema::networks::id)
-                    .filter(schema::networks::name.eq(network_name))
-                    .single_value()
-                    .assume_not_null()),
+        let api_key = ApiKey::from_str(api_key_s).map_err(|e| anyhow!("invalid api key: {}", e))?;
+
+        diesel::update(graphix_api_tokens::table)
+            .filter(graphix_api_tokens::sha256_api_key_hash.eq(api_key.hash()))
+            .set((
+                graphix_api_tokens::notes.eq(notes),
+                graphix_api_tokens::permission_level.eq(permission_level),
             ))
             .execute(&mut self.conn().await?)
             .await?;
@@ -384,257 +2408,854 @@ impl Store {
         Ok(())
     }
 
-    pub async fn set_deployment_name(
+    /// Adds a GraphQL request that took `execution_time` to the usage of the
+    /// given API key. Unknown API keys are ignored.
+    pub async fn record_api_key_usage(
         &self,
-        sg_deployment_id: &str,
-        name: &str,
+        api_key: &ApiKey,
+        execution_time: Duration,
     ) -> anyhow::Result<()> {
-        use schema::{sg_deployments as sgd, sg_names};
+        use schema::{api_key_daily_usage, api_key_usage, graphix_api_tokens};
 
-        diesel::insert_into(sg_names::table)
+        let conn = &mut self.conn().await?;
+        let public_prefix: Option<String> = graphix_api_tokens::table
+            .select(graphix_api_tokens::public_prefix)
+            .filter(graphix_api_tokens::sha256_api_key_hash.eq(api_key.hash()))
+            .get_result(conn)
+            .await
+            .optional()?;
+        let Some(public_prefix) = public_prefix else {
+            return Ok(());
+        };
+
+        let execution_time_ms = execution_time.as_millis() as i64;
+        diesel::insert_into(api_key_usage::table)
             .values((
-                sg_names::sg_deployment_id.eq(sgd::table
-                    .select(sgd::id)
-                    .filter(sgd::ipfs_cid.eq(sg_deployment_id))
-                    .single_value()
-                    .assume_not_null()),
-                sg_names::name.eq(name),
+                api_key_usage::public_prefix.eq(&public_prefix),
+                api_key_usage::query_count.eq(1),
+                api_key_usage::total_execution_time_ms.eq(execution_time_ms),
             ))
-            .on_conflict(sg_names::sg_deployment_id)
+            .on_conflict(api_key_usage::public_prefix)
             .do_update()
-            .set(sg_names::name.eq(name))
-            .execute(&mut self.conn().await?)
+            .set((
+                api_key_usage::query_count.eq(api_key_usage::query_count + 1),
+                api_key_usage::total_execution_time_ms
+                    .eq(api_key_usage::total_execution_time_ms + execution_time_ms),
+                api_key_usage::last_used_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)
+            .await?;
+
+        diesel::insert_into(api_key_daily_usage::table)
+            .values((
+                api_key_daily_usage::public_prefix.eq(&public_prefix),
+                api_key_daily_usage::day.eq(chrono::Utc::now().date_naive()),
+                api_key_daily_usage::request_count.eq(1),
+            ))
+            .on_conflict((api_key_daily_usage::public_prefix, api_key_daily_usage::day))
+            .do_update()
+            .set(api_key_daily_usage::request_count.eq(api_key_daily_usage::request_count + 1))
+            .execute(conn)
             .await?;
 
         Ok(())
     }
 
-    /// Deletes the network with the given name from the database, together with
-    /// **all** of its related data (indexers, deployments, etc.).
-    pub async fn delete_network(&self, network_name: &str) -> anyhow::Result<()> {
-        use schema::networks;
+    /// Sets the expiry time and daily request quota of an API key. [`None`]
+    /// removes the respective limit.
+    pub async fn set_api_key_limits(
+        &self,
+        api_key_s: &str,
+        expires_at: Option<NaiveDateTime>,
+        daily_request_quota: Option<i32>,
+    ) -> anyhow::Result<()> {
+        use schema::graphix_api_tokens;
 
-        diesel::delete(networks::table.filter(networks::name.eq(network_name)))
+        let api_key = ApiKey::from_str(api_key_s).map_err(|e| anyhow!("invalid api key: {}", e))?;
+
+        let updated = diesel::update(graphix_api_tokens::table)
+            .filter(graphix_api_tokens::sha256_api_key_hash.eq(api_key.hash()))
+            .set((
+                graphix_api_tokens::expires_at.eq(expires_at),
+                graphix_api_tokens::daily_request_quota.eq(daily_request_quota),
+            ))
             .execute(&mut self.conn().await?)
             .await?;
-        // The `ON DELETE CASCADE`s should take care of the rest of the cleanup.
+        if updated == 0 {
+            return Err(anyhow!("api key not found"));
+        }
 
         Ok(())
     }
 
-    pub async fn create_network(&self, network: &NewNetwork) -> anyhow::Result<IntId> {
-        use schema::networks;
+    /// Scopes an API key to the workspace with the given name, or unscopes it
+    /// if `workspace` is [`None`].
+    pub async fn set_api_key_workspace(
+        &self,
+        api_key_s: &str,
+        workspace: Option<&str>,
+    ) -> anyhow::Result<()> {
+        use schema::{graphix_api_tokens, workspaces};
 
-        let id = diesel::insert_into(networks::table)
-            .values(network)
-            .returning(networks::id)
+        let api_key = ApiKey::from_str(api_key_s).map_err(|e| anyhow!("invalid api key: {}", e))?;
+
+        let conn = &mut self.conn().await?;
+        let workspace_id: Option<IntId> = match workspace {
+            Some(name) => Some(
+                workspaces::table
+                    .select(workspaces::id)
+                    .filter(workspaces::name.eq(name))
+                    .get_result(conn)
+                    .await
+                    .optional()?
+                    .ok_or_else(|| anyhow!("Unknown workspace: {}", name))?,
+            ),
+            None => None,
+        };
+
+        let updated = diesel::update(graphix_api_tokens::table)
+            .filter(graphix_api_tokens::sha256_api_key_hash.eq(api_key.hash()))
+            .set(graphix_api_tokens::workspace_id.eq(workspace_id))
+            .execute(conn)
+            .await?;
+        if updated == 0 {
+            return Err(anyhow!("api key not found"));
+        }
+
+        Ok(())
+    }
+
+    pub async fn create_workspace(&self, name: &str) -> anyhow::Result<models::Workspace> {
+        use schema::workspaces;
+
+        Ok(diesel::insert_into(workspaces::table)
+            .values(workspaces::name.eq(name))
+            .returning(models::Workspace::as_returning())
             .get_result(&mut self.conn().await?)
+            .await?)
+    }
+
+    /// Deletes a workspace together with its API keys, registered indexers
+    /// and tracked deployments. Returns `false` if there was no such
+    /// workspace.
+    pub async fn delete_workspace(&self, name: &str) -> anyhow::Result<bool> {
+        use schema::workspaces;
+
+        let deleted = diesel::delete(workspaces::table.filter(workspaces::name.eq(name)))
+            .execute(&mut self.conn().await?)
             .await?;
 
-        Ok(id)
+        Ok(deleted > 0)
     }
 
-    pub async fn write_pois<W>(&self, pois: Vec<W>, live: PoiLiveness) -> anyhow::Result<()>
-    where
-        W: WritablePoi + Send + Sync,
-        W::IndexerId: Send + Sync,
-    {
-        self.conn()
-            .await?
-            .transaction::<_, Error, _>(|conn| {
-                async move {
-                    diesel_queries::write_pois(conn, pois, live).await?;
-                    Ok(())
-                }
-                .scope_boxed()
-            })
+    pub async fn delete_api_key(&self, api_key_s: &str) -> anyhow::Result<()> {
+        use schema::graphix_api_tokens;
+
+        let api_key = ApiKey::from_str(api_key_s).map_err(|e| anyhow!("invalid api key: {}", e))?;
+
+        diesel::delete(graphix_api_tokens::table)
+            .filter(graphix_api_tokens::sha256_api_key_hash.eq(api_key.hash()))
+            .execute(&mut self.conn().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Stores the `graph-node` versions collected from the given indexers,
+    /// which must already exist in the database, and updates their version
+    /// history.
+    pub async fn write_graph_node_versions(
+        &self,
+        versions: HashMap<
+            Arc<dyn IndexerClient>,
+            anyhow::Result<graphix_common_types::GraphNodeCollectedVersion>,
+        >,
+    ) -> anyhow::Result<()> {
+        use schema::{graph_node_collected_versions, indexers};
+        for (indexer, version) in versions.iter() {
+            let conn = &mut self.conn().await?;
+
+            let new_version = match version {
+                Ok(v) => models::NewGraphNodeCollectedVersion {
+                    version_string: v.version.clone(),
+                    version_commit: v.commit.clone(),
+                    error_response: None,
+                },
+                Err(err) => models::NewGraphNodeCollectedVersion {
+                    version_string: None,
+                    version_commit: None,
+                    error_response: Some(err.to_string()),
+                },
+            };
+
+            let version_id: IntId = diesel::insert_into(graph_node_collected_versions::table)
+                .values(&new_version)
+                .returning(graph_node_collected_versions::id)
+                .get_result(conn)
+                .await?;
+
+            let indexer_id =
+                diesel_queries::get_indexer_id(conn, indexer.name(), &indexer.address()).await?;
+            diesel::update(indexers::table.filter(indexers::id.eq(indexer_id)))
+                .set(indexers::graph_node_version.eq(version_id))
+                .execute(conn)
+                .await?;
+
+            if let Ok(version) = version {
+                diesel_queries::record_graph_node_version(conn, indexer_id, version).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records whether the given indexer, which must already exist in the
+    /// database, is up. Only changes are stored; returns whether this was
+    /// one.
+    pub async fn record_indexer_uptime(
+        &self,
+        indexer: &dyn IndexerClient,
+        up: bool,
+    ) -> anyhow::Result<bool> {
+        use schema::indexer_uptime_transitions as transitions;
+
+        let conn = &mut self.conn().await?;
+        let indexer_id =
+            diesel_queries::get_indexer_id(conn, indexer.name(), &indexer.address()).await?;
+        let was_up: Option<bool> = transitions::table
+            .select(transitions::up)
+            .filter(transitions::indexer_id.eq(indexer_id))
+            .order_by((transitions::observed_at.desc(), transitions::id.desc()))
+            .first(conn)
             .await
+            .optional()?;
+        if was_up == Some(up) {
+            return Ok(false);
+        }
+
+        diesel::insert_into(transitions::table)
+            .values(models::NewIndexerUptimeTransition { indexer_id, up })
+            .execute(conn)
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Stores how many of the requested PoIs each indexer returned. The
+    /// indexers must already exist in the database.
+    pub async fn write_poi_request_outcomes(
+        &self,
+        outcomes: &[models::PoiRequestOutcome],
+    ) -> anyhow::Result<()> {
+        use schema::poi_request_outcomes;
+
+        let conn = &mut self.conn().await?;
+        let mut new_outcomes = vec![];
+        for outcome in outcomes {
+            let indexer_id = diesel_queries::get_indexer_id(
+                conn,
+                outcome.indexer.name(),
+                &outcome.indexer.address(),
+            )
+            .await?;
+            new_outcomes.push(models::NewPoiRequestOutcome {
+                indexer_id,
+                requested: outcome.requested.try_into()?,
+                returned: outcome.returned.try_into()?,
+            });
+        }
+
+        diesel::insert_into(poi_request_outcomes::table)
+            .values(&new_outcomes)
+            .execute(conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Stores the block hashes that indexers reported as part of chain
+    /// disagreements. Block hashes that were already stored for the same
+    /// indexer, deployment and block number are kept. The indexers must
+    /// already exist in the database, and deployments that don't are skipped.
+    pub async fn write_chain_disagreements(
+        &self,
+        disagreements: &[models::DetectedChainDisagreement],
+    ) -> anyhow::Result<()> {
+        use schema::{chain_disagreements, sg_deployments};
+
+        let conn = &mut self.conn().await?;
+        let cids: Vec<&IpfsCid> = disagreements.iter().map(|d| &d.deployment).collect();
+        let sg_deployment_ids: HashMap<IpfsCid, IntId> = sg_deployments::table
+            .select((sg_deployments::ipfs_cid, sg_deployments::id))
+            .filter(sg_deployments::ipfs_cid.eq_any(cids))
+            .load::<(IpfsCid, IntId)>(conn)
+            .await?
+            .into_iter()
+            .collect();
+
+        let mut new_reports = vec![];
+        for disagreement in disagreements {
+            let Some(sg_deployment_id) = sg_deployment_ids.get(&disagreement.deployment) else {
+                continue;
+            };
+            for (indexer, block_hash) in &disagreement.block_hashes {
+                let indexer_id =
+                    diesel_queries::get_indexer_id(conn, indexer.name(), &indexer.address())
+                        .await?;
+                new_reports.push(models::NewChainDisagreementReport {
+                    sg_deployment_id: *sg_deployment_id,
+                    block_number: disagreement.block_number.try_into()?,
+                    indexer_id,
+                    block_hash: block_hash.clone(),
+                });
+            }
+        }
+
+        diesel::insert_into(chain_disagreements::table)
+            .values(&new_reports)
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Marks the blocks with the given hashes as canonical or not. Unknown
+    /// blocks are ignored.
+    pub async fn set_blocks_canonical(
+        &self,
+        canonical_by_hash: &HashMap<BlockHash, bool>,
+    ) -> anyhow::Result<()> {
+        use schema::blocks;
+
+        let conn = &mut self.conn().await?;
+        for (hash, canonical) in canonical_by_hash {
+            diesel::update(blocks::table.filter(blocks::hash.eq(hash)))
+                .set(blocks::canonical.eq(canonical))
+                .execute(conn)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets the timestamps of the blocks with the given hashes. Unknown blocks
+    /// are ignored.
+    pub async fn set_block_timestamps(
+        &self,
+        timestamps: &HashMap<BlockHash, NaiveDateTime>,
+    ) -> anyhow::Result<()> {
+        use schema::blocks;
+
+        let conn = &mut self.conn().await?;
+        for (hash, timestamp) in timestamps {
+            diesel::update(blocks::table.filter(blocks::hash.eq(hash)))
+                .set(blocks::timestamp.eq(timestamp))
+                .execute(conn)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Stores the subgraph API versions collected for the given deployments.
+    /// Deployments that don't exist in the database are skipped.
+    pub async fn write_subgraph_api_versions(
+        &self,
+        versions: HashMap<IpfsCid, anyhow::Result<Vec<String>>>,
+    ) -> anyhow::Result<()> {
+        use schema::{sg_deployment_api_versions, sg_deployments};
+
+        let conn = &mut self.conn().await?;
+        for (deployment, result) in versions {
+            let sg_deployment_id: Option<IntId> = sg_deployments::table
+                .select(sg_deployments::id)
+                .filter(sg_deployments::ipfs_cid.eq(&deployment))
+                .first(conn)
+                .await
+                .optional()?;
+            let Some(sg_deployment_id) = sg_deployment_id else {
+                continue;
+            };
+
+            let new_versions = match result {
+                Ok(versions) => models::NewSgDeploymentApiVersions {
+                    sg_deployment_id,
+                    api_versions: Some(versions.into_iter().map(Some).collect()),
+                    error: None,
+                },
+                Err(err) => models::NewSgDeploymentApiVersions {
+                    sg_deployment_id,
+                    api_versions: None,
+                    error: Some(err.to_string()),
+                },
+            };
+
+            diesel::insert_into(sg_deployment_api_versions::table)
+                .values(&new_versions)
+                .execute(conn)
+                .await?;
+        }
+
+        Ok(())
     }
 
-    pub async fn write_indexers(
-        &self,
-        indexers: &[impl AsRef<dyn IndexerClient>],
-    ) -> anyhow::Result<()> {
-        let mut conn = self.conn().await?;
-        diesel_queries::write_indexers(&mut conn, indexers).await?;
+    /// Stores the entity counts reported in the given indexing statuses. The
+    /// indexers must already exist in the database, while statuses of
+    /// deployments that don't exist yet are skipped.
+    pub async fn write_entity_counts(
+        &self,
+        indexing_statuses: &[IndexingStatus],
+    ) -> anyhow::Result<()> {
+        use schema::entity_counts;
+
+        let conn = &mut self.conn().await?;
+        let mut new_entity_counts = vec![];
+        for status in indexing_statuses {
+            let Some((indexer_id, sg_deployment_id)) =
+                diesel_queries::get_indexing_status_ids(conn, status).await?
+            else {
+                continue;
+            };
+
+            new_entity_counts.push(models::NewEntityCount {
+                indexer_id,
+                sg_deployment_id,
+                block_number: status.latest_block.number as i64,
+                entity_count: status.entity_count as i64,
+            });
+        }
+
+        diesel::insert_into(entity_counts::table)
+            .values(&new_entity_counts)
+            .execute(conn)
+            .await?;
+
         Ok(())
     }
 
-    pub async fn delete_indexer_network_subgraph_metadata(
+    /// Appends the given indexing statuses to the indexing status history.
+    /// The indexers must already exist in the database, while statuses of
+    /// deployments that don't exist yet are skipped.
+    pub async fn write_indexing_status_history(
         &self,
-        indexer_id: IntId,
+        indexing_statuses: &[IndexingStatus],
     ) -> anyhow::Result<()> {
-        use schema::indexers;
+        use schema::indexing_status_history;
 
-        diesel::update(indexers::table.filter(indexers::id.eq(indexer_id)))
-            .set(indexers::network_subgraph_metadata.eq::<Option<IntId>>(None))
-            .execute(&mut self.conn().await?)
+        let conn = &mut self.conn().await?;
+        let mut snapshots = vec![];
+        for status in indexing_statuses {
+            let Some((indexer_id, sg_deployment_id)) =
+                diesel_queries::get_indexing_status_ids(conn, status).await?
+            else {
+                continue;
+            };
+
+            snapshots.push(models::NewIndexingStatusSnapshot {
+                indexer_id,
+                sg_deployment_id,
+                latest_block_number: status.latest_block.number as i64,
+                health: status.health,
+                synced: status.synced,
+            });
+        }
+
+        diesel::insert_into(indexing_status_history::table)
+            .values(&snapshots)
+            .execute(conn)
             .await?;
 
         Ok(())
     }
 
-    pub async fn create_or_update_indexer_network_subgraph_metadata(
+    /// Stores the network subgraph metadata of the given deployments,
+    /// replacing any previous metadata. Deployments that don't exist in the
+    /// database are skipped.
+    pub async fn write_sg_deployment_network_subgraph_metadata(
         &self,
-        indexer_id: IntId,
-        metadata: NewIndexerNetworkSubgraphMetadata,
-    ) -> anyhow::Result<IntId> {
-        use schema::{indexer_network_subgraph_metadata, indexers};
+        metadata: &[models::FetchedSgDeploymentMetadata],
+    ) -> anyhow::Result<()> {
+        use schema::{sg_deployment_network_subgraph_metadata as sgd_metadata, sg_deployments};
 
-        self.conn()
+        let mut conn = self.conn().await?;
+        let cids: Vec<&IpfsCid> = metadata.iter().map(|m| &m.deployment).collect();
+        let ids: HashMap<IpfsCid, IntId> = sg_deployments::table
+            .select((sg_deployments::ipfs_cid, sg_deployments::id))
+            .filter(sg_deployments::ipfs_cid.eq_any(cids))
+            .load::<(IpfsCid, IntId)>(&mut conn)
             .await?
-            .transaction::<_, Error, _>(|conn| {
-                // Fetch the metadata id from indexer_id, and update it if it exists
-                // create a new one and set the foreign key to the indexer_id if it doesn't exist
-                async move {
-                    let metadata_id = indexers::table
-                        .select(indexers::network_subgraph_metadata)
-                        .filter(indexers::id.eq(indexer_id))
-                        .get_result::<Option<IntId>>(conn)
+            .into_iter()
+            .collect();
+
+        let new_metadata: Vec<_> = metadata
+            .iter()
+            .filter_map(|m| {
+                Some(models::NewSgDeploymentNetworkSubgraphMetadata {
+                    sg_deployment_id: *ids.get(&m.deployment)?,
+                    display_name: m.display_name.clone(),
+                    image: m.image.clone(),
+                    signal_amount: m.signal_amount.clone(),
+                })
+            })
+            .collect();
+
+        conn.transaction::<_, Error, _>(|conn| {
+            async move {
+                for new_metadata in &new_metadata {
+                    diesel::insert_into(sgd_metadata::table)
+                        .values(new_metadata)
+                        .on_conflict(sgd_metadata::sg_deployment_id)
+                        .do_update()
+                        .set((
+                            new_metadata,
+                            sgd_metadata::last_updated_at.eq(diesel::dsl::now),
+                        ))
+                        .execute(conn)
                         .await?;
+                }
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await?;
 
-                    let metadata_id = match metadata_id {
-                        Some(id) => {
-                            diesel::update(
-                                indexer_network_subgraph_metadata::table
-                                    .filter(indexer_network_subgraph_metadata::id.eq(id)),
-                            )
-                            .set(metadata)
-                            .execute(conn)
-                            .await?;
-                            id
-                        }
-                        None => {
-                            let metadata_id =
-                                diesel::insert_into(indexer_network_subgraph_metadata::table)
-                                    .values(&metadata)
-                                    .returning(indexer_network_subgraph_metadata::id)
-                                    .get_result(conn)
-                                    .await?;
-
-                            diesel::update(indexers::table)
-                                .filter(indexers::id.eq(indexer_id))
-                                .set(indexers::network_subgraph_metadata.eq(metadata_id))
-                                .execute(conn)
-                                .await?;
+        Ok(())
+    }
 
-                            metadata_id
-                        }
-                    };
+    /// Replaces the cross-check tiers of all deployments with the given ones.
+    /// Deployments that don't exist in the database are skipped.
+    pub async fn write_sg_deployment_check_tiers(
+        &self,
+        tiers: &HashMap<IpfsCid, u32>,
+    ) -> anyhow::Result<()> {
+        use schema::{sg_deployment_check_tiers as check_tiers, sg_deployments};
 
-                    Ok(metadata_id)
-                }
-                .scope_boxed()
+        let mut conn = self.conn().await?;
+        let cids: Vec<&IpfsCid> = tiers.keys().collect();
+        let rows: Vec<_> = sg_deployments::table
+            .select((sg_deployments::ipfs_cid, sg_deployments::id))
+            .filter(sg_deployments::ipfs_cid.eq_any(cids))
+            .load::<(IpfsCid, IntId)>(&mut conn)
+            .await?
+            .into_iter()
+            .map(|(cid, id)| {
+                (
+                    check_tiers::sg_deployment_id.eq(id),
+                    check_tiers::tier.eq(tiers[&cid] as i32),
+                )
             })
-            .await?;
+            .collect();
+
+        conn.transaction::<_, Error, _>(|conn| {
+            async move {
+                diesel::delete(check_tiers::table).execute(conn).await?;
+                if !rows.is_empty() {
+                    diesel::insert_into(check_tiers::table)
+                        .values(&rows)
+                        .execute(conn)
+                        .await?;
+                }
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await?;
 
-        Ok(indexer_id)
+        Ok(())
     }
 
-    pub async fn create_api_key(
+    /// Stores the subgraph manifest of the given deployment, replacing any
+    /// previous one. Deployments that don't exist in the database are
+    /// skipped.
+    pub async fn write_subgraph_manifest(
         &self,
-        notes: Option<&str>,
-        permission_level: ApiKeyPermissionLevel,
-    ) -> anyhow::Result<NewlyCreatedApiKey> {
-        use schema::graphix_api_tokens;
-
-        let api_key = ApiKey::generate();
-        let stored_api_key = ApiKeyDbRow {
-            public_prefix: api_key.public_part_as_string(),
-            sha256_api_key_hash: api_key.hash(),
-            notes: notes.map(|s| s.to_string()),
-            permission_level,
+        deployment: &IpfsCid,
+        manifest: models::FetchedSubgraphManifest,
+    ) -> anyhow::Result<()> {
+        use schema::{
+            sg_deployment_data_sources as data_sources, sg_deployment_manifests as manifests,
+            sg_deployments,
         };
 
-        diesel::insert_into(graphix_api_tokens::table)
-            .values(&[stored_api_key])
-            .execute(&mut self.conn().await?)
-            .await?;
+        let mut conn = self.conn().await?;
+        let sg_deployment_id: Option<IntId> = sg_deployments::table
+            .select(sg_deployments::id)
+            .filter(sg_deployments::ipfs_cid.eq(deployment))
+            .first(&mut conn)
+            .await
+            .optional()?;
+        let Some(sg_deployment_id) = sg_deployment_id else {
+            return Ok(());
+        };
 
-        Ok(NewlyCreatedApiKey {
-            api_key: api_key.to_string(),
-            notes: notes.map(|s| s.to_string()),
-            permission_level,
+        let new_manifest = models::NewSgDeploymentManifest {
+            sg_deployment_id,
+            spec_version: manifest.spec_version,
+            features: manifest.features,
+            graft_base: manifest.graft_base,
+            graft_block: manifest.graft_block,
+            raw_manifest: manifest.raw_manifest,
+        };
+        let new_data_sources: Vec<_> = manifest
+            .data_sources
+            .into_iter()
+            .map(|data_source| models::NewSgDeploymentDataSource {
+                sg_deployment_id,
+                is_template: data_source.is_template,
+                kind: data_source.kind,
+                name: data_source.name,
+                network: data_source.network,
+                address: data_source.address,
+                start_block: data_source.start_block,
+            })
+            .collect();
+
+        conn.transaction::<_, Error, _>(|conn| {
+            async move {
+                diesel::insert_into(manifests::table)
+                    .values(&new_manifest)
+                    .on_conflict(manifests::sg_deployment_id)
+                    .do_update()
+                    .set((&new_manifest, manifests::fetched_at.eq(diesel::dsl::now)))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(
+                    data_sources::table.filter(data_sources::sg_deployment_id.eq(sg_deployment_id)),
+                )
+                .execute(conn)
+                .await?;
+                diesel::insert_into(data_sources::table)
+                    .values(&new_data_sources)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            .scope_boxed()
         })
+        .await
     }
 
-    pub async fn modify_api_key(
+    /// Compares PoIs submitted on chain when closing allocations against the
+    /// PoIs that Graphix collected from the same indexers for the same
+    /// deployments and blocks, and stores the results. PoIs of unknown
+    /// indexers or deployments, as well as already stored allocations, are
+    /// skipped. Returns the newly stored rows.
+    pub async fn write_allocation_pois(
         &self,
-        api_key_s: &str,
-        notes: Option<&str>,
-        permission_level: ApiKeyPermissionLevel,
-    ) -> anyhow::Result<()> {
-        use schema::graphix_api_tokens;
+        submitted_pois: &[models::SubmittedAllocationPoi],
+    ) -> anyhow::Result<Vec<models::AllocationPoi>> {
+        use schema::{allocation_pois, blocks, indexers, poi_values, pois, sg_deployments as sgd};
 
-        let api_key = ApiKey::from_str(api_key_s).map_err(|e| anyhow!("invalid api key: {}", e))?;
+        let conn = &mut self.conn().await?;
+        let mut written = vec![];
+        for submitted in submitted_pois {
+            let indexer_ids: Vec<IntId> = indexers::table
+                .select(indexers::id)
+                .filter(indexers::address.eq(submitted.indexer_address))
+                .order_by(indexers::id.asc())
+                .load(conn)
+                .await?;
+            let sg_deployment_id: Option<IntId> = sgd::table
+                .select(sgd::id)
+                .filter(sgd::ipfs_cid.eq(&submitted.deployment))
+                .first(conn)
+                .await
+                .optional()?;
+            let (Some(&first_indexer_id), Some(sg_deployment_id)) =
+                (<[IntId]>::first(&indexer_ids), sg_deployment_id)
+            else {
+                continue;
+            };
 
-        diesel::update(graphix_api_tokens::table)
-            .filter(graphix_api_tokens::sha256_api_key_hash.eq(api_key.hash()))
-            .set((
-                graphix_api_tokens::notes.eq(notes),
-                graphix_api_tokens::permission_level.eq(permission_level),
-            ))
-            .execute(&mut self.conn().await?)
-            .await?;
+            let collected_poi: Option<(IntId, IntId, PoiBytes)> = pois::table
+                .inner_join(blocks::table)
+                .inner_join(poi_values::table)
+                .select((pois::id, pois::indexer_id, poi_values::poi))
+                .filter(pois::indexer_id.eq_any(&indexer_ids))
+                .filter(pois::sg_deployment_id.eq(sg_deployment_id))
+                .filter(blocks::number.eq(submitted.block_number))
+                .order_by(pois::created_at.desc())
+                .first(conn)
+                .await
+                .optional()?;
+
+            let new_allocation_poi = models::NewAllocationPoi {
+                allocation_id: submitted.allocation_id.clone(),
+                indexer_id: collected_poi.map_or(first_indexer_id, |(_, id, _)| id),
+                sg_deployment_id,
+                closed_at_epoch: submitted.closed_at_epoch,
+                block_number: submitted.block_number,
+                submitted_poi: submitted.poi,
+                collected_poi_id: collected_poi.map(|(id, _, _)| id),
+                matches: collected_poi.map(|(_, _, poi)| poi == submitted.poi),
+                closed_at: submitted.closed_at,
+            };
+            let row: Option<models::AllocationPoi> = diesel::insert_into(allocation_pois::table)
+                .values(&new_allocation_poi)
+                .on_conflict(allocation_pois::allocation_id)
+                .do_nothing()
+                .returning(models::AllocationPoi::as_returning())
+                .get_result(conn)
+                .await
+                .optional()?;
+            written.extend(row);
+        }
 
-        Ok(())
+        Ok(written)
     }
 
-    pub async fn delete_api_key(&self, api_key_s: &str) -> anyhow::Result<()> {
-        use schema::graphix_api_tokens;
+    /// Stores on-chain disputes about PoIs, or updates them if they're
+    /// already stored, e.g. once they're resolved. Each dispute is correlated
+    /// with the most recent PoIs that Graphix collected from all indexers for
+    /// the same deployment and block. Disputes about unknown indexers or
+    /// deployments are skipped. Returns the stored rows.
+    pub async fn write_poi_disputes(
+        &self,
+        disputes: &[models::OnChainPoiDispute],
+    ) -> anyhow::Result<Vec<models::PoiDispute>> {
+        use schema::{blocks, indexers, poi_disputes, poi_values, pois, sg_deployments as sgd};
 
-        let api_key = ApiKey::from_str(api_key_s).map_err(|e| anyhow!("invalid api key: {}", e))?;
+        let conn = &mut self.conn().await?;
+        let mut written = vec![];
+        for dispute in disputes {
+            let indexer_ids: Vec<IntId> = indexers::table
+                .select(indexers::id)
+                .filter(indexers::address.eq(dispute.indexer_address))
+                .order_by(indexers::id.asc())
+                .load(conn)
+                .await?;
+            let sg_deployment_id: Option<IntId> = sgd::table
+                .select(sgd::id)
+                .filter(sgd::ipfs_cid.eq(&dispute.deployment))
+                .first(conn)
+                .await
+                .optional()?;
+            let (Some(&first_indexer_id), Some(sg_deployment_id)) =
+                (<[IntId]>::first(&indexer_ids), sg_deployment_id)
+            else {
+                continue;
+            };
 
-        diesel::delete(graphix_api_tokens::table)
-            .filter(graphix_api_tokens::sha256_api_key_hash.eq(api_key.hash()))
-            .execute(&mut self.conn().await?)
-            .await?;
+            let collected_pois: Vec<(IntId, IntId, PoiBytes)> = pois::table
+                .inner_join(blocks::table)
+                .inner_join(poi_values::table)
+                .select((pois::id, pois::indexer_id, poi_values::poi))
+                .filter(pois::sg_deployment_id.eq(sg_deployment_id))
+                .filter(blocks::number.eq(dispute.block_number))
+                .order_by(pois::created_at.desc())
+                .load(conn)
+                .await?;
+            // Only the most recent PoI of each indexer counts.
+            let mut seen_indexer_ids = HashSet::new();
+            let mut collected_poi = None;
+            let (mut agreeing_indexers, mut disagreeing_indexers) = (0, 0);
+            for (poi_id, indexer_id, poi) in collected_pois {
+                if !seen_indexer_ids.insert(indexer_id) {
+                    continue;
+                }
+                if indexer_ids.contains(&indexer_id) {
+                    collected_poi = collected_poi.or(Some((poi_id, indexer_id)));
+                } else if poi == dispute.poi {
+                    agreeing_indexers += 1;
+                } else {
+                    disagreeing_indexers += 1;
+                }
+            }
+
+            let new_dispute = models::NewPoiDispute {
+                dispute_id: dispute.dispute_id.clone(),
+                indexer_id: collected_poi.map_or(first_indexer_id, |(_, id)| id),
+                sg_deployment_id,
+                allocation_id: dispute.allocation_id.clone(),
+                block_number: dispute.block_number,
+                disputed_poi: dispute.poi,
+                status: dispute.status,
+                collected_poi_id: collected_poi.map(|(id, _)| id),
+                agreeing_indexers,
+                disagreeing_indexers,
+                opened_at: dispute.opened_at,
+                closed_at: dispute.closed_at,
+            };
+            let row: models::PoiDispute = diesel::insert_into(poi_disputes::table)
+                .values(&new_dispute)
+                .on_conflict(poi_disputes::dispute_id)
+                .do_update()
+                .set((&new_dispute, poi_disputes::updated_at.eq(diesel::dsl::now)))
+                .returning(models::PoiDispute::as_returning())
+                .get_result(conn)
+                .await?;
+            written.push(row);
+        }
 
-        Ok(())
+        Ok(written)
     }
 
-    pub async fn write_graph_node_versions(
+    /// Replaces the stored active allocations of the given indexer, which
+    /// must already exist in the database. `indexing_statuses` tell which
+    /// deployments the indexer is indexing; statuses of other indexers are
+    /// ignored.
+    pub async fn write_indexer_allocations(
         &self,
-        versions: HashMap<
-            Arc<dyn IndexerClient>,
-            anyhow::Result<graphix_common_types::GraphNodeCollectedVersion>,
-        >,
+        indexer: &dyn IndexerClient,
+        allocations: &[ActiveAllocation],
+        indexing_statuses: &[IndexingStatus],
     ) -> anyhow::Result<()> {
-        use schema::graph_node_collected_versions;
-        for version in versions.values() {
-            let conn = &mut self.conn().await?;
-
-            let new_version = match version {
-                Ok(v) => models::NewGraphNodeCollectedVersion {
-                    version_string: v.version.clone(),
-                    version_commit: v.commit.clone(),
-                    error_response: None,
-                },
-                Err(err) => models::NewGraphNodeCollectedVersion {
-                    version_string: None,
-                    version_commit: None,
-                    error_response: Some(err.to_string()),
-                },
-            };
+        use schema::indexer_allocations;
 
-            diesel::insert_into(graph_node_collected_versions::table)
-                .values(&new_version)
-                .execute(conn)
-                .await?;
-        }
+        let indexed_deployments: HashSet<&IpfsCid> = indexing_statuses
+            .iter()
+            .filter(|status| status.indexer.as_ref() == indexer)
+            .map(|status| &status.deployment)
+            .collect();
 
-        Ok(())
+        self.conn()
+            .await?
+            .transaction::<_, Error, _>(|conn| {
+                async move {
+                    let indexer_id =
+                        diesel_queries::get_indexer_id(conn, indexer.name(), &indexer.address())
+                            .await?;
+                    let new_allocations = allocations
+                        .iter()
+                        .map(|allocation| {
+                            Ok(models::NewIndexerAllocation {
+                                indexer_id,
+                                allocation_id: allocation.id.clone(),
+                                deployment: allocation.deployment.to_string(),
+                                created_at_epoch: allocation.created_at_epoch.try_into()?,
+                                indexing: indexed_deployments.contains(&allocation.deployment),
+                            })
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+
+                    diesel::delete(
+                        indexer_allocations::table
+                            .filter(indexer_allocations::indexer_id.eq(indexer_id)),
+                    )
+                    .execute(conn)
+                    .await?;
+                    diesel::insert_into(indexer_allocations::table)
+                        .values(&new_allocations)
+                        .on_conflict_do_nothing()
+                        .execute(conn)
+                        .await?;
+                    Ok(())
+                }
+                .scope_boxed()
+            })
+            .await
     }
 
     pub async fn create_divergence_investigation_request(
         &self,
         request: serde_json::Value,
     ) -> anyhow::Result<Uuid> {
-        use schema::pending_divergence_investigation_requests as requests;
+        use schema::divergence_investigation_jobs as jobs;
 
         let uuid = uuid::Uuid::new_v4();
-        diesel::insert_into(requests::table)
-            .values((requests::uuid.eq(&uuid), requests::request.eq(&request)))
+        diesel::insert_into(jobs::table)
+            .values((jobs::uuid.eq(&uuid), jobs::request.eq(&request)))
             .execute(&mut self.conn().await?)
             .await?;
 
@@ -659,13 +3280,225 @@ impl Store {
         Ok(())
     }
 
+    /// Stores a bisection run together with all its attempts and evidence. The
+    /// divergence investigation report that it belongs to must already exist.
+    pub async fn write_bisection_run(
+        &self,
+        run: NewBisectionRun,
+        attempts: Vec<NewBisectionAttempt>,
+        evidence: Vec<NewBisectionEvidence>,
+    ) -> anyhow::Result<()> {
+        use schema::{bisection_attempts, bisection_evidence, bisection_runs};
+
+        self.conn()
+            .await?
+            .transaction::<_, Error, _>(|conn| {
+                async move {
+                    diesel::insert_into(bisection_runs::table)
+                        .values(&run)
+                        .execute(conn)
+                        .await?;
+                    diesel::insert_into(bisection_attempts::table)
+                        .values(&attempts)
+                        .execute(conn)
+                        .await?;
+                    diesel::insert_into(bisection_evidence::table)
+                        .values(&evidence)
+                        .execute(conn)
+                        .await?;
+                    Ok(())
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+
     pub async fn delete_divergence_investigation_request(&self, uuid: &Uuid) -> anyhow::Result<()> {
-        use schema::pending_divergence_investigation_requests as requests;
+        use schema::divergence_investigation_jobs as jobs;
 
-        diesel::delete(requests::table.filter(requests::uuid.eq(uuid)))
+        diesel::delete(jobs::table.filter(jobs::uuid.eq(uuid)))
             .execute(&mut self.conn().await?)
             .await?;
 
         Ok(())
     }
+
+    /// Claims the oldest pending divergence investigation job that is due, if
+    /// any, on behalf of `worker`. Jobs that other workers are claiming at the
+    /// same time are skipped rather than waited for.
+    pub async fn claim_divergence_investigation_job(
+        &self,
+        worker: &str,
+    ) -> anyhow::Result<Option<DivergenceInvestigationJob>> {
+        use schema::divergence_investigation_jobs as jobs;
+
+        let worker = worker.to_string();
+        let now = chrono::Utc::now().naive_utc();
+        self.conn()
+            .await?
+            .transaction::<_, Error, _>(|conn| {
+                async move {
+                    let uuid = jobs::table
+                        .select(jobs::uuid)
+                        .filter(jobs::status.eq(DivergenceInvestigationJobStatus::Pending))
+                        .filter(jobs::run_after.le(now))
+                        .order_by(jobs::created_at.asc())
+                        .for_update()
+                        .skip_locked()
+                        .first::<Uuid>(conn)
+                        .await
+                        .optional()?;
+                    let Some(uuid) = uuid else {
+                        return Ok(None);
+                    };
+
+                    let job = diesel::update(jobs::table.filter(jobs::uuid.eq(uuid)))
+                        .set((
+                            jobs::status.eq(DivergenceInvestigationJobStatus::Running),
+                            jobs::attempts.eq(jobs::attempts + 1),
+                            jobs::locked_by.eq(worker),
+                            jobs::locked_at.eq(now),
+                        ))
+                        .returning(DivergenceInvestigationJob::as_returning())
+                        .get_result(conn)
+                        .await?;
+                    Ok(Some(job))
+                }
+                .scope_boxed()
+            })
+            .await
+    }
+
+    /// Returns running divergence investigation jobs that were claimed more
+    /// than `stale_after` ago to the queue, e.g. because their worker
+    /// crashed. Jobs that were already claimed `max_attempts` times are
+    /// dead-lettered instead, so that a job that crashes its workers isn't
+    /// retried forever. Returns the number of such jobs.
+    pub async fn requeue_stale_divergence_investigation_jobs(
+        &self,
+        stale_after: Duration,
+        max_attempts: i32,
+    ) -> anyhow::Result<u64> {
+        use schema::divergence_investigation_jobs as jobs;
+
+        let now = chrono::Utc::now().naive_utc();
+        let locked_before = now - chrono::Duration::from_std(stale_after)?;
+        let stale = self
+            .conn()
+            .await?
+            .transaction::<_, Error, _>(|conn| {
+                async move {
+                    let dead_lettered = diesel::update(
+                        jobs::table
+                            .filter(jobs::status.eq(DivergenceInvestigationJobStatus::Running))
+                            .filter(jobs::locked_at.lt(locked_before))
+                            .filter(jobs::attempts.ge(max_attempts)),
+                    )
+                    .set((
+                        jobs::status.eq(DivergenceInvestigationJobStatus::DeadLettered),
+                        jobs::dead_lettered_at.eq(now),
+                    ))
+                    .execute(conn)
+                    .await?;
+                    let requeued = diesel::update(
+                        jobs::table
+                            .filter(jobs::status.eq(DivergenceInvestigationJobStatus::Running))
+                            .filter(jobs::locked_at.lt(locked_before)),
+                    )
+                    .set(jobs::status.eq(DivergenceInvestigationJobStatus::Pending))
+                    .execute(conn)
+                    .await?;
+                    Ok(dead_lettered + requeued)
+                }
+                .scope_boxed()
+            })
+            .await?;
+
+        Ok(stale as u64)
+    }
+
+    /// Acknowledges that `worker` finished the divergence investigation job
+    /// with the given UUID, which removes it from the queue. Returns false if
+    /// the job isn't running on behalf of `worker` anymore, e.g. because it
+    /// was requeued as stale and claimed by another worker in the meantime.
+    pub async fn ack_divergence_investigation_job(
+        &self,
+        uuid: &Uuid,
+        worker: &str,
+    ) -> anyhow::Result<bool> {
+        use schema::divergence_investigation_jobs as jobs;
+
+        let deleted = diesel::delete(
+            jobs::table
+                .filter(jobs::uuid.eq(uuid))
+                .filter(jobs::status.eq(DivergenceInvestigationJobStatus::Running))
+                .filter(jobs::locked_by.eq(worker)),
+        )
+        .execute(&mut self.conn().await?)
+        .await?;
+
+        Ok(deleted > 0)
+    }
+
+    /// Records that `worker` failed at the divergence investigation job with
+    /// the given UUID. It's retried once `retry_at` has passed, if given, and
+    /// moved to the dead-letter queue otherwise. Returns false if the job
+    /// isn't running on behalf of `worker` anymore.
+    pub async fn nack_divergence_investigation_job(
+        &self,
+        uuid: &Uuid,
+        worker: &str,
+        error: &str,
+        retry_at: Option<NaiveDateTime>,
+    ) -> anyhow::Result<bool> {
+        use schema::divergence_investigation_jobs as jobs;
+
+        let now = chrono::Utc::now().naive_utc();
+        let (status, dead_lettered_at) = match retry_at {
+            Some(_) => (DivergenceInvestigationJobStatus::Pending, None),
+            None => (DivergenceInvestigationJobStatus::DeadLettered, Some(now)),
+        };
+        let updated = diesel::update(
+            jobs::table
+                .filter(jobs::uuid.eq(uuid))
+                .filter(jobs::status.eq(DivergenceInvestigationJobStatus::Running))
+                .filter(jobs::locked_by.eq(worker)),
+        )
+        .set((
+            jobs::status.eq(status),
+            jobs::run_after.eq(retry_at.unwrap_or(now)),
+            jobs::last_error.eq(error),
+            jobs::dead_lettered_at.eq(dead_lettered_at),
+        ))
+        .execute(&mut self.conn().await?)
+        .await?;
+
+        Ok(updated > 0)
+    }
+
+    /// Moves the dead-lettered divergence investigation job with the given
+    /// UUID back to the queue, with a fresh set of attempts. Returns false if
+    /// there's no such job.
+    pub async fn requeue_dead_lettered_divergence_investigation_job(
+        &self,
+        uuid: &Uuid,
+    ) -> anyhow::Result<bool> {
+        use schema::divergence_investigation_jobs as jobs;
+
+        let updated = diesel::update(
+            jobs::table
+                .filter(jobs::uuid.eq(uuid))
+                .filter(jobs::status.eq(DivergenceInvestigationJobStatus::DeadLettered)),
+        )
+        .set((
+            jobs::status.eq(DivergenceInvestigationJobStatus::Pending),
+            jobs::attempts.eq(0),
+            jobs::run_after.eq(chrono::Utc::now().naive_utc()),
+            jobs::dead_lettered_at.eq(None::<NaiveDateTime>),
+        ))
+        .execute(&mut self.conn().await?)
+        .await?;
+
+        Ok(updated > 0)
+    }
 }
@@ -0,0 +1,419 @@
+//! Backend-agnostic interfaces to the parts of the database that the
+//! cross-checker and divergence investigations need. [`Store`] is the
+//! Postgres implementation, and the default one.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use graphix_common_types::{
+    inputs, DivergenceInvestigationJobStatus, IndexerAddress, IpfsCid, PoiBytes,
+};
+use graphix_indexer_client::{IndexerClient, ProofOfIndexing, RawResponse};
+use uuid::Uuid;
+
+use crate::models::{
+    self, BisectionAttempt, BisectionEvidence, BisectionRun, DivergenceInvestigationJob,
+    NewBisectionAttempt, NewBisectionEvidence, NewBisectionRun, Poi, RawIndexerResponse,
+    SgDeployment,
+};
+use crate::{PoiLiveness, Store};
+
+/// Storage of indexers, subgraph deployments and the PoIs collected from
+/// indexers.
+#[async_trait]
+pub trait PoiStore: Send + Sync {
+    /// Stores the given indexers, unless they already exist.
+    async fn write_indexers(&self, indexers: &[Arc<dyn IndexerClient>]) -> anyhow::Result<()>;
+
+    /// Stores the given PoIs, along with their deployments and blocks. Live
+    /// PoIs replace the previous live PoI of the same indexer and deployment.
+    async fn write_pois(&self, pois: Vec<ProofOfIndexing>, live: PoiLiveness)
+        -> anyhow::Result<()>;
+
+    async fn indexers(&self, filter: inputs::IndexersQuery)
+        -> anyhow::Result<Vec<models::Indexer>>;
+
+    async fn sg_deployments(
+        &self,
+        filter: inputs::SgDeploymentsQuery,
+    ) -> anyhow::Result<Vec<SgDeployment>>;
+
+    async fn poi(&self, poi: &PoiBytes) -> anyhow::Result<Option<Poi>>;
+
+    async fn pois_by_value(&self, pois: &[PoiBytes]) -> anyhow::Result<Vec<Poi>>;
+
+    async fn pois(
+        &self,
+        sg_deployments: &[IpfsCid],
+        block_range: Option<inputs::BlockRange>,
+        limit: Option<u16>,
+        order: inputs::PoisOrder,
+    ) -> anyhow::Result<Vec<Poi>>;
+
+    async fn live_pois(
+        &self,
+        indexer_address: Option<&IndexerAddress>,
+        sg_deployments_cids: Option<&[IpfsCid]>,
+        block_range: Option<inputs::BlockRange>,
+        limit: Option<u16>,
+    ) -> anyhow::Result<Vec<Poi>>;
+
+    /// Returns the PoI that `indexer` reported for `deployment` at the given
+    /// block number, together with the block, if any.
+    async fn indexer_poi_at_block(
+        &self,
+        indexer: &Arc<dyn IndexerClient>,
+        deployment: &IpfsCid,
+        block_number: u64,
+    ) -> anyhow::Result<Option<(Poi, models::Block)>>;
+}
+
+/// Storage of divergence investigation requests and their results.
+#[async_trait]
+pub trait ReportStore: Send + Sync {
+    async fn create_divergence_investigation_request(
+        &self,
+        request: serde_json::Value,
+    ) -> anyhow::Result<Uuid>;
+
+    /// Claims the oldest pending job that is due, if any, on behalf of
+    /// `worker`. Concurrent claims never return the same job.
+    async fn claim_divergence_investigation_job(
+        &self,
+        worker: &str,
+    ) -> anyhow::Result<Option<DivergenceInvestigationJob>>;
+
+    /// Returns jobs that have been running for longer than `stale_after` to
+    /// the queue, or dead-letters them once they were claimed `max_attempts`
+    /// times.
+    async fn requeue_stale_divergence_investigation_jobs(
+        &self,
+        stale_after: Duration,
+        max_attempts: i32,
+    ) -> anyhow::Result<u64>;
+
+    /// Removes a finished job from the queue. Returns false if the job isn't
+    /// running on behalf of `worker`.
+    async fn ack_divergence_investigation_job(
+        &self,
+        uuid: &Uuid,
+        worker: &str,
+    ) -> anyhow::Result<bool>;
+
+    /// Retries a failed job once `retry_at` has passed, if given, and
+    /// dead-letters it otherwise. Returns false if the job isn't running on
+    /// behalf of `worker`.
+    async fn nack_divergence_investigation_job(
+        &self,
+        uuid: &Uuid,
+        worker: &str,
+        error: &str,
+        retry_at: Option<NaiveDateTime>,
+    ) -> anyhow::Result<bool>;
+
+    /// Returns a dead-lettered job to the queue. Returns false if there's no
+    /// such job.
+    async fn requeue_dead_lettered_divergence_investigation_job(
+        &self,
+        uuid: &Uuid,
+    ) -> anyhow::Result<bool>;
+
+    /// Oldest first.
+    async fn divergence_investigation_jobs(
+        &self,
+        status: Option<DivergenceInvestigationJobStatus>,
+    ) -> anyhow::Result<Vec<DivergenceInvestigationJob>>;
+
+    async fn divergence_investigation_request_exists(&self, uuid: &Uuid) -> anyhow::Result<bool>;
+
+    /// Marks the given request as cancelled. Returns false if there's no such
+    /// request.
+    async fn cancel_divergence_investigation_request(&self, uuid: &Uuid) -> anyhow::Result<bool>;
+
+    async fn divergence_investigation_request_cancelled(&self, uuid: &Uuid)
+        -> anyhow::Result<bool>;
+
+    async fn pending_divergence_investigation_requests_count(&self) -> anyhow::Result<u64>;
+
+    async fn delete_divergence_investigation_request(&self, uuid: &Uuid) -> anyhow::Result<()>;
+
+    async fn create_or_update_divergence_investigation_report(
+        &self,
+        uuid: &Uuid,
+        report: serde_json::Value,
+    ) -> anyhow::Result<()>;
+
+    async fn divergence_investigation_report(
+        &self,
+        uuid: &Uuid,
+    ) -> anyhow::Result<Option<serde_json::Value>>;
+
+    /// Most recent first.
+    async fn divergence_investigation_reports(
+        &self,
+        filter: inputs::DivergenceInvestigationsQuery,
+    ) -> anyhow::Result<Vec<serde_json::Value>>;
+
+    async fn write_bisection_run(
+        &self,
+        run: NewBisectionRun,
+        attempts: Vec<NewBisectionAttempt>,
+        evidence: Vec<NewBisectionEvidence>,
+    ) -> anyhow::Result<()>;
+
+    /// Most recent first.
+    async fn bisection_runs(
+        &self,
+        filter: inputs::BisectionRunsQuery,
+    ) -> anyhow::Result<Vec<BisectionRun>>;
+
+    async fn bisection_run(&self, uuid: &Uuid) -> anyhow::Result<Option<BisectionRun>>;
+
+    async fn bisection_attempts(
+        &self,
+        bisection_run_uuid: &Uuid,
+    ) -> anyhow::Result<Vec<BisectionAttempt>>;
+
+    async fn bisection_evidence(
+        &self,
+        bisection_run_uuid: &Uuid,
+    ) -> anyhow::Result<Vec<BisectionEvidence>>;
+
+    async fn write_raw_indexer_responses(
+        &self,
+        investigation_uuid: &Uuid,
+        responses: Vec<RawResponse>,
+    ) -> anyhow::Result<()>;
+
+    async fn raw_indexer_responses(
+        &self,
+        investigation_uuid: &Uuid,
+    ) -> anyhow::Result<Vec<RawIndexerResponse>>;
+}
+
+#[async_trait]
+impl PoiStore for Store {
+    async fn write_indexers(&self, indexers: &[Arc<dyn IndexerClient>]) -> anyhow::Result<()> {
+        Store::write_indexers(self, indexers).await
+    }
+
+    async fn write_pois(
+        &self,
+        pois: Vec<ProofOfIndexing>,
+        live: PoiLiveness,
+    ) -> anyhow::Result<()> {
+        Store::write_pois(self, pois, live).await
+    }
+
+    async fn indexers(
+        &self,
+        filter: inputs::IndexersQuery,
+    ) -> anyhow::Result<Vec<models::Indexer>> {
+        Store::indexers(self, filter).await
+    }
+
+    async fn sg_deployments(
+        &self,
+        filter: inputs::SgDeploymentsQuery,
+    ) -> anyhow::Result<Vec<SgDeployment>> {
+        Store::sg_deployments(self, filter).await
+    }
+
+    async fn poi(&self, poi: &PoiBytes) -> anyhow::Result<Option<Poi>> {
+        Store::poi(self, poi).await
+    }
+
+    async fn pois_by_value(&self, pois: &[PoiBytes]) -> anyhow::Result<Vec<Poi>> {
+        Store::pois_by_value(self, pois).await
+    }
+
+    async fn pois(
+        &self,
+        sg_deployments: &[IpfsCid],
+        block_range: Option<inputs::BlockRange>,
+        limit: Option<u16>,
+        order: inputs::PoisOrder,
+    ) -> anyhow::Result<Vec<Poi>> {
+        Store::pois(self, sg_deployments, &[], block_range, limit, order).await
+    }
+
+    async fn live_pois(
+        &self,
+        indexer_address: Option<&IndexerAddress>,
+        sg_deployments_cids: Option<&[IpfsCid]>,
+        block_range: Option<inputs::BlockRange>,
+        limit: Option<u16>,
+    ) -> anyhow::Result<Vec<Poi>> {
+        Store::live_pois(
+            self,
+            indexer_address,
+            &[],
+            sg_deployments_cids,
+            block_range,
+            limit,
+        )
+        .await
+    }
+
+    async fn indexer_poi_at_block(
+        &self,
+        indexer: &Arc<dyn IndexerClient>,
+        deployment: &IpfsCid,
+        block_number: u64,
+    ) -> anyhow::Result<Option<(Poi, models::Block)>> {
+        Store::indexer_poi_at_block(self, indexer, deployment, block_number).await
+    }
+}
+
+#[async_trait]
+impl ReportStore for Store {
+    async fn create_divergence_investigation_request(
+        &self,
+        request: serde_json::Value,
+    ) -> anyhow::Result<Uuid> {
+        Store::create_divergence_investigation_request(self, request).await
+    }
+
+    async fn claim_divergence_investigation_job(
+        &self,
+        worker: &str,
+    ) -> anyhow::Result<Option<DivergenceInvestigationJob>> {
+        Store::claim_divergence_investigation_job(self, worker).await
+    }
+
+    async fn requeue_stale_divergence_investigation_jobs(
+        &self,
+        stale_after: Duration,
+        max_attempts: i32,
+    ) -> anyhow::Result<u64> {
+        Store::requeue_stale_divergence_investigation_jobs(self, stale_after, max_attempts).await
+    }
+
+    async fn ack_divergence_investigation_job(
+        &self,
+        uuid: &Uuid,
+        worker: &str,
+    ) -> anyhow::Result<bool> {
+        Store::ack_divergence_investigation_job(self, uuid, worker).await
+    }
+
+    async fn nack_divergence_investigation_job(
+        &self,
+        uuid: &Uuid,
+        worker: &str,
+        error: &str,
+        retry_at: Option<NaiveDateTime>,
+    ) -> anyhow::Result<bool> {
+        Store::nack_divergence_investigation_job(self, uuid, worker, error, retry_at).await
+    }
+
+    async fn requeue_dead_lettered_divergence_investigation_job(
+        &self,
+        uuid: &Uuid,
+    ) -> anyhow::Result<bool> {
+        Store::requeue_dead_lettered_divergence_investigation_job(self, uuid).await
+    }
+
+    async fn divergence_investigation_jobs(
+        &self,
+        status: Option<DivergenceInvestigationJobStatus>,
+    ) -> anyhow::Result<Vec<DivergenceInvestigationJob>> {
+        Store::divergence_investigation_jobs(self, status).await
+    }
+
+    async fn divergence_investigation_request_exists(&self, uuid: &Uuid) -> anyhow::Result<bool> {
+        Store::divergence_investigation_request_exists(self, uuid).await
+    }
+
+    async fn cancel_divergence_investigation_request(&self, uuid: &Uuid) -> anyhow::Result<bool> {
+        Store::cancel_divergence_investigation_request(self, uuid).await
+    }
+
+    async fn divergence_investigation_request_cancelled(
+        &self,
+        uuid: &Uuid,
+    ) -> anyhow::Result<bool> {
+        Store::divergence_investigation_request_cancelled(self, uuid).await
+    }
+
+    async fn pending_divergence_investigation_requests_count(&self) -> anyhow::Result<u64> {
+        Store::pending_divergence_investigation_requests_count(self).await
+    }
+
+    async fn delete_divergence_investigation_request(&self, uuid: &Uuid) -> anyhow::Result<()> {
+        Store::delete_divergence_investigation_request(self, uuid).await
+    }
+
+    async fn create_or_update_divergence_investigation_report(
+        &self,
+        uuid: &Uuid,
+        report: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        Store::create_or_update_divergence_investigation_report(self, uuid, report).await
+    }
+
+    async fn divergence_investigation_report(
+        &self,
+        uuid: &Uuid,
+    ) -> anyhow::Result<Option<serde_json::Value>> {
+        Store::divergence_investigation_report(self, uuid).await
+    }
+
+    async fn divergence_investigation_reports(
+        &self,
+        filter: inputs::DivergenceInvestigationsQuery,
+    ) -> anyhow::Result<Vec<serde_json::Value>> {
+        Store::divergence_investigation_reports(self, filter).await
+    }
+
+    async fn write_bisection_run(
+        &self,
+        run: NewBisectionRun,
+        attempts: Vec<NewBisectionAttempt>,
+        evidence: Vec<NewBisectionEvidence>,
+    ) -> anyhow::Result<()> {
+        Store::write_bisection_run(self, run, attempts, evidence).await
+    }
+
+    async fn bisection_runs(
+        &self,
+        filter: inputs::BisectionRunsQuery,
+    ) -> anyhow::Result<Vec<BisectionRun>> {
+        Store::bisection_runs(self, filter).await
+    }
+
+    async fn bisection_run(&self, uuid: &Uuid) -> anyhow::Result<Option<BisectionRun>> {
+        Store::bisection_run(self, uuid).await
+    }
+
+    async fn bisection_attempts(
+        &self,
+        bisection_run_uuid: &Uuid,
+    ) -> anyhow::Result<Vec<BisectionAttempt>> {
+        Store::bisection_attempts(self, bisection_run_uuid).await
+    }
+
+    async fn bisection_evidence(
+        &self,
+        bisection_run_uuid: &Uuid,
+    ) -> anyhow::Result<Vec<BisectionEvidence>> {
+        Store::bisection_evidence(self, bisection_run_uuid).await
+    }
+
+    async fn write_raw_indexer_responses(
+        &self,
+        investigation_uuid: &Uuid,
+        responses: Vec<RawResponse>,
+    ) -> anyhow::Result<()> {
+        Store::write_raw_indexer_responses(self, investigation_uuid, responses).await
+    }
+
+    async fn raw_indexer_responses(
+        &self,
+        investigation_uuid: &Uuid,
+    ) -> anyhow::Result<Vec<RawIndexerResponse>> {
+        Store::raw_indexer_responses(self, investigation_uuid).await
+    }
+}
@@ -0,0 +1,429 @@
+//! Tests for [`InMemoryStore`], which unlike the Postgres store don't need a
+//! database.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use graphix_common_types::inputs::{
+    BisectionRunsQuery, DivergenceInvestigationsQuery, IndexersQuery, SgDeploymentsQuery,
+};
+use graphix_common_types::{DivergenceInvestigationJobStatus, DivergenceInvestigationStatus};
+use graphix_indexer_client::{BlockPointer, IndexerClient, ProofOfIndexing, RawResponse};
+use graphix_lib::test_utils::gen::{gen_blocks, gen_deployments};
+use graphix_lib::test_utils::mocks::MockIndexer;
+use graphix_store::models::NewBisectionRun;
+use graphix_store::{InMemoryStore, PoiLiveness, PoiStore, ReportStore};
+use uuid::Uuid;
+
+fn mock_indexers(names: &[&str]) -> Vec<Arc<dyn IndexerClient>> {
+    names
+        .iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn pois_of_unknown_indexers_are_rejected() {
+    let store = InMemoryStore::new();
+    let indexers = mock_indexers(&["foo"]);
+
+    let poi = ProofOfIndexing {
+        indexer: indexers[0].clone(),
+        deployment: gen_deployments()[0].clone(),
+        block: gen_blocks()[3].clone(),
+        proof_of_indexing: [1; 32].into(),
+    };
+    assert!(store
+        .write_pois(vec![poi], PoiLiveness::Live)
+        .await
+        .is_err());
+    assert!(store
+        .sg_deployments(SgDeploymentsQuery::default())
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn live_pois_written_in_chunks() {
+    let store = InMemoryStore::new();
+    let indexers = mock_indexers(&["foo", "bar"]);
+    store.write_indexers(&indexers).await.unwrap();
+
+    let deployment = gen_deployments()[0].clone();
+    let poi = |indexer: &Arc<dyn IndexerClient>, block: usize| ProofOfIndexing {
+        indexer: indexer.clone(),
+        deployment: deployment.clone(),
+        block: gen_blocks()[block].clone(),
+        proof_of_indexing: [1; 32].into(),
+    };
+    let deployments = [deployment.clone()];
+    let live_pois = || store.live_pois(None, Some(&deployments), None, None);
+
+    for indexer in &indexers {
+        store
+            .write_pois(vec![poi(indexer, 3)], PoiLiveness::Live)
+            .await
+            .unwrap();
+    }
+    let pois = live_pois().await.unwrap();
+    assert_eq!(pois.len(), 2);
+    let old_block_id = pois[0].block_id;
+
+    store
+        .write_pois(vec![poi(&indexers[0], 4)], PoiLiveness::Live)
+        .await
+        .unwrap();
+    let pois = live_pois().await.unwrap();
+    assert_eq!(pois.len(), 1);
+    assert_ne!(pois[0].block_id, old_block_id);
+    // Replaced PoIs are still around, just not live.
+    assert_eq!(
+        store
+            .pois(&deployments, None, None, Default::default())
+            .await
+            .unwrap()
+            .len(),
+        3
+    );
+}
+
+#[tokio::test]
+async fn pois_without_block_hashes() {
+    let store = InMemoryStore::new();
+    let indexers = mock_indexers(&["foo", "bar"]);
+    store.write_indexers(&indexers).await.unwrap();
+
+    let deployment = gen_deployments()[0].clone();
+    let block = BlockPointer {
+        number: 42,
+        hash: None,
+    };
+    for indexer in &indexers {
+        let poi = ProofOfIndexing {
+            indexer: indexer.clone(),
+            deployment: deployment.clone(),
+            block: block.clone(),
+            proof_of_indexing: [1; 32].into(),
+        };
+        store
+            .write_pois(vec![poi], PoiLiveness::NotLive)
+            .await
+            .unwrap();
+    }
+
+    let (poi1, block1) = store
+        .indexer_poi_at_block(&indexers[0], &deployment, 42)
+        .await
+        .unwrap()
+        .unwrap();
+    let (poi2, _) = store
+        .indexer_poi_at_block(&indexers[1], &deployment, 42)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(block1.hash, None);
+    assert_eq!(poi1.block_id, poi2.block_id);
+    assert!(store
+        .indexer_poi_at_block(&indexers[0], &deployment, 43)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn divergence_investigation_requests() {
+    let store = InMemoryStore::new();
+
+    let uuid = store
+        .create_divergence_investigation_request(serde_json::json!({}))
+        .await
+        .unwrap();
+    let job = store
+        .claim_divergence_investigation_job("worker")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(job.uuid, uuid);
+    assert_eq!(job.status, DivergenceInvestigationJobStatus::Running);
+    // Running jobs can't be claimed twice.
+    assert!(store
+        .claim_divergence_investigation_job("other worker")
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        store
+            .pending_divergence_investigation_requests_count()
+            .await
+            .unwrap(),
+        1
+    );
+
+    assert!(store
+        .cancel_divergence_investigation_request(&uuid)
+        .await
+        .unwrap());
+    assert!(store
+        .divergence_investigation_request_cancelled(&uuid)
+        .await
+        .unwrap());
+
+    store
+        .delete_divergence_investigation_request(&uuid)
+        .await
+        .unwrap();
+    assert!(!store
+        .divergence_investigation_request_exists(&uuid)
+        .await
+        .unwrap());
+    assert!(!store
+        .cancel_divergence_investigation_request(&uuid)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn dead_lettered_divergence_investigation_jobs() {
+    let store = InMemoryStore::new();
+    let uuid = store
+        .create_divergence_investigation_request(serde_json::json!({}))
+        .await
+        .unwrap();
+    let claim = || store.claim_divergence_investigation_job("worker");
+
+    claim().await.unwrap().unwrap();
+    let retry_at = chrono::Utc::now().naive_utc() + chrono::Duration::hours(1);
+    store
+        .nack_divergence_investigation_job(&uuid, "worker", "oops", Some(retry_at))
+        .await
+        .unwrap();
+    // Not due yet.
+    assert!(claim().await.unwrap().is_none());
+    assert_eq!(
+        store
+            .pending_divergence_investigation_requests_count()
+            .await
+            .unwrap(),
+        1
+    );
+
+    store
+        .nack_divergence_investigation_job(&uuid, "worker", "oops again", None)
+        .await
+        .unwrap();
+    let dead_lettered = store
+        .divergence_investigation_jobs(Some(DivergenceInvestigationJobStatus::DeadLettered))
+        .await
+        .unwrap();
+    assert_eq!(dead_lettered.len(), 1);
+    assert_eq!(dead_lettered[0].attempts, 1);
+    assert_eq!(dead_lettered[0].last_error.as_deref(), Some("oops again"));
+    assert!(dead_lettered[0].dead_lettered_at.is_some());
+    assert!(!store
+        .divergence_investigation_request_exists(&uuid)
+        .await
+        .unwrap());
+
+    assert!(store
+        .requeue_dead_lettered_divergence_investigation_job(&uuid)
+        .await
+        .unwrap());
+    let job = claim().await.unwrap().unwrap();
+    assert_eq!(job.attempts, 1);
+    assert!(store
+        .ack_divergence_investigation_job(&uuid, "worker")
+        .await
+        .unwrap());
+    assert!(!store
+        .divergence_investigation_request_exists(&uuid)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn stale_divergence_investigation_jobs_are_requeued() {
+    let store = InMemoryStore::new();
+    let uuid = store
+        .create_divergence_investigation_request(serde_json::json!({}))
+        .await
+        .unwrap();
+    store
+        .claim_divergence_investigation_job("crashed worker")
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        store
+            .requeue_stale_divergence_investigation_jobs(Duration::from_secs(3600), 3)
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        store
+            .requeue_stale_divergence_investigation_jobs(Duration::ZERO, 3)
+            .await
+            .unwrap(),
+        1
+    );
+    let job = store
+        .claim_divergence_investigation_job("worker")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(job.uuid, uuid);
+    assert_eq!(job.attempts, 2);
+    assert_eq!(job.locked_by.as_deref(), Some("worker"));
+
+    // The crashed worker can't settle the job anymore.
+    assert!(!store
+        .nack_divergence_investigation_job(&uuid, "crashed worker", "oops", None)
+        .await
+        .unwrap());
+    assert!(!store
+        .ack_divergence_investigation_job(&uuid, "crashed worker")
+        .await
+        .unwrap());
+    assert!(store
+        .divergence_investigation_request_exists(&uuid)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn divergence_investigation_reports() {
+    let store = InMemoryStore::new();
+    let indexers = mock_indexers(&["foo"]);
+    store.write_indexers(&indexers).await.unwrap();
+
+    let deployment = gen_deployments()[0].clone();
+    let poi = ProofOfIndexing {
+        indexer: indexers[0].clone(),
+        deployment: deployment.clone(),
+        block: gen_blocks()[3].clone(),
+        proof_of_indexing: [1; 32].into(),
+    };
+    store
+        .write_pois(vec![poi], PoiLiveness::NotLive)
+        .await
+        .unwrap();
+    let indexer_id = store.indexers(IndexersQuery::default()).await.unwrap()[0].id;
+    let deployment_id = store
+        .sg_deployments(SgDeploymentsQuery::default())
+        .await
+        .unwrap()[0]
+        .id;
+
+    let run = |investigation_uuid| NewBisectionRun {
+        uuid: Uuid::new_v4(),
+        investigation_uuid,
+        sg_deployment_id: Some(deployment_id),
+        indexer1_id: Some(indexer_id),
+        indexer2_id: None,
+        poi1: [1; 32].into(),
+        poi2: [2; 32].into(),
+        lower_bound: 10,
+        upper_bound: 20,
+        error: None,
+    };
+
+    // Bisection runs can only be written once their report exists.
+    let complete_uuid = Uuid::new_v4();
+    assert!(store
+        .write_bisection_run(run(complete_uuid), vec![], vec![])
+        .await
+        .is_err());
+    store
+        .create_or_update_divergence_investigation_report(
+            &complete_uuid,
+            serde_json::json!({ "uuid": complete_uuid, "status": "Complete" }),
+        )
+        .await
+        .unwrap();
+    store
+        .write_bisection_run(run(complete_uuid), vec![], vec![])
+        .await
+        .unwrap();
+    let in_progress_uuid = Uuid::new_v4();
+    store
+        .create_or_update_divergence_investigation_report(
+            &in_progress_uuid,
+            serde_json::json!({ "uuid": in_progress_uuid, "status": "InProgress" }),
+        )
+        .await
+        .unwrap();
+
+    let reports = |filter| {
+        let store = &store;
+        async move {
+            store
+                .divergence_investigation_reports(filter)
+                .await
+                .unwrap()
+        }
+    };
+    assert_eq!(
+        reports(DivergenceInvestigationsQuery::default())
+            .await
+            .len(),
+        2
+    );
+    let by_deployment_and_indexer = reports(DivergenceInvestigationsQuery {
+        deployment: Some(deployment.clone()),
+        indexer: Some(indexers[0].address()),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(by_deployment_and_indexer.len(), 1);
+    assert_eq!(
+        by_deployment_and_indexer[0]["uuid"],
+        complete_uuid.to_string()
+    );
+    let in_progress = reports(DivergenceInvestigationsQuery {
+        status: Some(DivergenceInvestigationStatus::InProgress),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(in_progress.len(), 1);
+    assert_eq!(in_progress[0]["uuid"], in_progress_uuid.to_string());
+
+    let runs = store
+        .bisection_runs(BisectionRunsQuery {
+            deployment: Some(deployment),
+            block_number: Some(15),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].investigation_uuid, complete_uuid);
+
+    let response = |indexer_address, indexer_name: Option<String>| RawResponse {
+        indexer_address,
+        indexer_name,
+        operation_name: "ProofsOfIndexing".to_string(),
+        body: "not json".to_string(),
+    };
+    store
+        .write_raw_indexer_responses(
+            &complete_uuid,
+            vec![
+                response(indexers[0].address(), indexers[0].name().map(Into::into)),
+                // Unknown indexers are skipped.
+                response([0; 20].into(), None),
+            ],
+        )
+        .await
+        .unwrap();
+    let responses = store.raw_indexer_responses(&complete_uuid).await.unwrap();
+    assert_eq!(responses.len(), 1);
+    assert_eq!(responses[0].indexer_id, indexer_id);
+}
@@ -1,7 +1,35 @@
 mod common;
 
-use graphix_common_types::inputs::SgDeploymentsQuery;
-use graphix_store::models::{Network, NewNetwork};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use diesel_async::RunQueryDsl;
+use graphix_common_types::inputs::{
+    AllocationPoisQuery, BisectionRunsQuery, BlockRange, ChainDisagreementsQuery,
+    DivergenceInvestigationsQuery, IndexersOrderBy, IndexersQuery, OrderDirection, PoisOrder,
+    PoisOrderBy, SgDeploymentsOrderBy, SgDeploymentsQuery,
+};
+use graphix_common_types::{
+    ApiKeyPermissionLevel, BisectionEvidenceKind, DivergenceInvestigationJobStatus,
+    DivergenceInvestigationStatus, GraphNodeCollectedVersion, IndexerDataDeletion, IntegrityReport,
+    IpfsCid, LivePoiReport, SubgraphHealth,
+};
+use graphix_indexer_client::{
+    BlockPointer, IndexerClient, IndexingStatus, ProofOfIndexing, RawResponse,
+};
+use graphix_lib::test_utils::gen::{gen_blocks, gen_deployments};
+use graphix_lib::test_utils::mocks::MockIndexer;
+use graphix_lib::test_utils::test_indexer_from_url;
+use graphix_store::models::{
+    ApiKey, ApiKeyLimits, DetectedChainDisagreement, FetchedDataSource,
+    FetchedSgDeploymentMetadata, FetchedSubgraphManifest, Network, NewBisectionAttempt,
+    NewBisectionEvidence, NewBisectionRun, NewNetwork, NewRegisteredIndexer, PoiRequestOutcome,
+    SubmittedAllocationPoi,
+};
+use graphix_store::{PoiLiveness, HOT_TABLES};
+
+use uuid::Uuid;
 
 use crate::common::EmptyStoreForTesting;
 
@@ -83,12 +111,2757 @@ async fn create_divergence_investigation_request() {
         .await
         .unwrap();
 
-    let req = store
-        .get_first_pending_divergence_investigation_request()
+    let job = store
+        .claim_divergence_investigation_job("worker")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(job.uuid, uuid);
+    assert_eq!(job.attempts, 1);
+    assert!(store
+        .claim_divergence_investigation_job("other worker")
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        store
+            .pending_divergence_investigation_requests_count()
+            .await
+            .unwrap(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn retry_divergence_investigation_job() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+
+    let uuid = store
+        .create_divergence_investigation_request(serde_json::json!({}))
+        .await
+        .unwrap();
+    store
+        .claim_divergence_investigation_job("worker")
+        .await
+        .unwrap()
+        .unwrap();
+
+    // Due right away.
+    let retry_at = chrono::Utc::now().naive_utc() - chrono::Duration::seconds(1);
+    store
+        .nack_divergence_investigation_job(&uuid, "worker", "oops", Some(retry_at))
+        .await
+        .unwrap();
+    let job = store
+        .claim_divergence_investigation_job("worker")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(job.attempts, 2);
+    assert_eq!(job.last_error.as_deref(), Some("oops"));
+
+    store
+        .nack_divergence_investigation_job(&uuid, "worker", "oops again", None)
+        .await
+        .unwrap();
+    assert!(store
+        .claim_divergence_investigation_job("worker")
+        .await
+        .unwrap()
+        .is_none());
+    assert!(!store
+        .divergence_investigation_request_exists(&uuid)
+        .await
+        .unwrap());
+    let dead_lettered = store
+        .divergence_investigation_jobs(Some(DivergenceInvestigationJobStatus::DeadLettered))
+        .await
+        .unwrap();
+    assert_eq!(dead_lettered.len(), 1);
+    assert_eq!(dead_lettered[0].uuid, uuid);
+
+    assert!(store
+        .requeue_dead_lettered_divergence_investigation_job(&uuid)
+        .await
+        .unwrap());
+    store
+        .claim_divergence_investigation_job("worker")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!store
+        .ack_divergence_investigation_job(&uuid, "other worker")
+        .await
+        .unwrap());
+    assert!(store
+        .ack_divergence_investigation_job(&uuid, "worker")
+        .await
+        .unwrap());
+    assert!(!store
+        .divergence_investigation_request_exists(&uuid)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn statements_of_dropped_futures_are_cancelled() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    let running_sleeps = || async {
+        diesel::select(diesel::dsl::sql::<diesel::sql_types::BigInt>(
+            "(SELECT count(*) FROM pg_stat_activity \
+             WHERE datname = current_database() AND query = 'SELECT pg_sleep(60)' \
+             AND state = 'active')",
+        ))
+        .get_result::<i64>(&mut store.conn().await.unwrap())
+        .await
+        .unwrap()
+    };
+
+    let sleep = store.cancel_statements_on_drop(async {
+        diesel::sql_query("SELECT pg_sleep(60)")
+            .execute(&mut store.conn().await.unwrap())
+            .await
+    });
+    assert!(tokio::time::timeout(Duration::from_secs(1), sleep)
+        .await
+        .is_err());
+
+    let started_at = std::time::Instant::now();
+    while running_sleeps().await > 0 {
+        assert!(started_at.elapsed() < Duration::from_secs(10));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+#[tokio::test]
+async fn reused_connections_of_dropped_futures_are_not_cancelled() {
+    async fn backend_pid(conn: &mut diesel_async::AsyncPgConnection) -> i32 {
+        diesel::select(diesel::dsl::sql::<diesel::sql_types::Integer>(
+            "pg_backend_pid()",
+        ))
+        .get_result(conn)
+        .await
+        .unwrap()
+    }
+
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    let tagged_pid = Arc::new(std::sync::atomic::AtomicI32::new(0));
+
+    let scoped_store = (*store).clone();
+    let scoped_pid = tagged_pid.clone();
+    let scope = tokio::spawn(async move {
+        scoped_store
+            .cancel_statements_on_drop(async {
+                let mut conn = scoped_store.conn().await.unwrap();
+                let pid = backend_pid(&mut conn).await;
+                drop(conn);
+                scoped_pid.store(pid, std::sync::atomic::Ordering::SeqCst);
+                std::future::pending::<()>().await
+            })
+            .await
+    });
+    let pid = loop {
+        match tagged_pid.load(std::sync::atomic::Ordering::SeqCst) {
+            0 => tokio::time::sleep(Duration::from_millis(10)).await,
+            pid => break pid,
+        }
+    };
+
+    // Like a batched load, which runs outside of the scope.
+    let reusing_store = (*store).clone();
+    let reuse = tokio::spawn(async move {
+        // Holds on to other connections until the pool hands out the one
+        // that was checked out within the scope.
+        let mut others = vec![];
+        let mut conn = loop {
+            let mut conn = reusing_store.conn().await.unwrap();
+            if backend_pid(&mut conn).await == pid {
+                break conn;
+            }
+            others.push(conn);
+        };
+        drop(others);
+        diesel::sql_query("SELECT pg_sleep(2)")
+            .execute(&mut conn)
+            .await
+    });
+    let started_at = std::time::Instant::now();
+    loop {
+        let running: i64 = diesel::select(diesel::dsl::sql::<diesel::sql_types::BigInt>(
+            "(SELECT count(*) FROM pg_stat_activity \
+             WHERE datname = current_database() AND query = 'SELECT pg_sleep(2)' \
+             AND state = 'active')",
+        ))
+        .get_result(&mut store.conn().await.unwrap())
+        .await
+        .unwrap();
+        if running > 0 {
+            break;
+        }
+        assert!(started_at.elapsed() < Duration::from_secs(10));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    scope.abort();
+    assert!(scope.await.unwrap_err().is_cancelled());
+    assert!(reuse.await.unwrap().is_ok());
+}
+
+#[tokio::test]
+async fn stale_divergence_investigation_jobs_are_dead_lettered_at_max_attempts() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+
+    let uuid = store
+        .create_divergence_investigation_request(serde_json::json!({}))
+        .await
+        .unwrap();
+    let claim = || store.claim_divergence_investigation_job("crashed worker");
+
+    claim().await.unwrap().unwrap();
+    assert_eq!(
+        store
+            .requeue_stale_divergence_investigation_jobs(Duration::ZERO, 2)
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(claim().await.unwrap().unwrap().attempts, 2);
+
+    // Out of attempts.
+    assert_eq!(
+        store
+            .requeue_stale_divergence_investigation_jobs(Duration::ZERO, 2)
+            .await
+            .unwrap(),
+        1
+    );
+    assert!(claim().await.unwrap().is_none());
+    let dead_lettered = store
+        .divergence_investigation_jobs(Some(DivergenceInvestigationJobStatus::DeadLettered))
+        .await
+        .unwrap();
+    assert_eq!(dead_lettered.len(), 1);
+    assert_eq!(dead_lettered[0].uuid, uuid);
+    assert!(dead_lettered[0].dead_lettered_at.is_some());
+}
+
+#[tokio::test]
+async fn cancel_divergence_investigation_request() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+
+    let uuid = store
+        .create_divergence_investigation_request(serde_json::json!({}))
+        .await
+        .unwrap();
+    assert!(!store
+        .divergence_investigation_request_cancelled(&uuid)
+        .await
+        .unwrap());
+
+    assert!(store
+        .cancel_divergence_investigation_request(&uuid)
+        .await
+        .unwrap());
+    assert!(store
+        .divergence_investigation_request_cancelled(&uuid)
+        .await
+        .unwrap());
+
+    // Investigations that are over can't be cancelled anymore.
+    store
+        .delete_divergence_investigation_request(&uuid)
+        .await
+        .unwrap();
+    assert!(!store
+        .cancel_divergence_investigation_request(&uuid)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn divergence_investigation_reports() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let indexer: Arc<dyn IndexerClient> = Arc::new(MockIndexer {
+        name: "foo".to_string(),
+        deployment_details: vec![],
+        fail_indexing_statuses: false,
+    });
+    store.write_indexers(&[indexer.clone()]).await.unwrap();
+
+    let deployment = gen_deployments()[0].clone();
+    let poi = ProofOfIndexing {
+        indexer: indexer.clone(),
+        deployment: deployment.clone(),
+        block: gen_blocks()[3].clone(),
+        proof_of_indexing: [1; 32].into(),
+    };
+    store
+        .write_pois(vec![poi], PoiLiveness::NotLive)
+        .await
+        .unwrap();
+    let indexer_id = store.indexers(IndexersQuery::default()).await.unwrap()[0].id;
+    let deployment_id = store
+        .sg_deployments(SgDeploymentsQuery::default())
+        .await
+        .unwrap()[0]
+        .id;
+
+    let complete_uuid = Uuid::new_v4();
+    store
+        .create_or_update_divergence_investigation_report(
+            &complete_uuid,
+            serde_json::json!({ "uuid": complete_uuid, "status": "Complete" }),
+        )
+        .await
+        .unwrap();
+    let run_uuid = Uuid::new_v4();
+    store
+        .write_bisection_run(
+            NewBisectionRun {
+                uuid: run_uuid,
+                investigation_uuid: complete_uuid,
+                sg_deployment_id: Some(deployment_id),
+                indexer1_id: Some(indexer_id),
+                indexer2_id: None,
+                poi1: [1; 32].into(),
+                poi2: [2; 32].into(),
+                lower_bound: 10,
+                upper_bound: 20,
+                error: None,
+            },
+            vec![],
+            vec![],
+        )
+        .await
+        .unwrap();
+    let in_progress_uuid = Uuid::new_v4();
+    store
+        .create_or_update_divergence_investigation_report(
+            &in_progress_uuid,
+            serde_json::json!({ "uuid": in_progress_uuid, "status": "InProgress" }),
+        )
+        .await
+        .unwrap();
+
+    let reports = |filter| {
+        let store = &store;
+        async move {
+            store
+                .divergence_investigation_reports(filter)
+                .await
+                .unwrap()
+        }
+    };
+    assert_eq!(
+        reports(DivergenceInvestigationsQuery::default())
+            .await
+            .len(),
+        2
+    );
+    let by_deployment_and_indexer = reports(DivergenceInvestigationsQuery {
+        deployment: Some(deployment),
+        indexer: Some(indexer.address()),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(by_deployment_and_indexer.len(), 1);
+    assert_eq!(
+        by_deployment_and_indexer[0]["uuid"],
+        complete_uuid.to_string()
+    );
+    let in_progress = reports(DivergenceInvestigationsQuery {
+        status: Some(DivergenceInvestigationStatus::InProgress),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(in_progress.len(), 1);
+    assert_eq!(in_progress[0]["uuid"], in_progress_uuid.to_string());
+    assert!(reports(DivergenceInvestigationsQuery {
+        created_after: Some(chrono::Utc::now().naive_utc() + chrono::Duration::hours(1)),
+        ..Default::default()
+    })
+    .await
+    .is_empty());
+    assert!(reports(DivergenceInvestigationsQuery {
+        indexer: Some([0; 20].into()),
+        ..Default::default()
+    })
+    .await
+    .is_empty());
+}
+
+#[tokio::test]
+async fn raw_indexer_responses() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+
+    let indexer: Arc<dyn IndexerClient> = Arc::new(MockIndexer {
+        name: "foo".to_string(),
+        deployment_details: vec![],
+        fail_indexing_statuses: false,
+    });
+    store.write_indexers(&[indexer.clone()]).await.unwrap();
+
+    let uuid = Uuid::new_v4();
+    store
+        .create_or_update_divergence_investigation_report(
+            &uuid,
+            serde_json::json!({ "uuid": uuid, "status": "Complete" }),
+        )
+        .await
+        .unwrap();
+
+    let response = |indexer_address, indexer_name: Option<String>| RawResponse {
+        indexer_address,
+        indexer_name,
+        operation_name: "ProofsOfIndexing".to_string(),
+        body: "not json".to_string(),
+    };
+    store
+        .write_raw_indexer_responses(
+            &uuid,
+            vec![
+                response(indexer.address(), indexer.name().map(Into::into)),
+                // Unknown indexers are skipped.
+                response([0; 20].into(), None),
+            ],
+        )
+        .await
+        .unwrap();
+
+    let responses = store.raw_indexer_responses(&uuid).await.unwrap();
+    assert_eq!(responses.len(), 1);
+    assert_eq!(responses[0].operation_name, "ProofsOfIndexing");
+    assert_eq!(responses[0].body, "not json");
+    assert!(store
+        .raw_indexer_responses(&Uuid::new_v4())
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn indexer_poi_at_block() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let indexer: Arc<dyn IndexerClient> = Arc::new(MockIndexer {
+        name: "foo".to_string(),
+        deployment_details: vec![],
+        fail_indexing_statuses: false,
+    });
+    store.write_indexers(&[indexer.clone()]).await.unwrap();
+
+    let deployment = gen_deployments()[0].clone();
+    let block: BlockPointer = gen_blocks()[3].clone();
+    let poi = ProofOfIndexing {
+        indexer: indexer.clone(),
+        deployment: deployment.clone(),
+        block: block.clone(),
+        proof_of_indexing: [1; 32].into(),
+    };
+    store
+        .write_pois(vec![poi], PoiLiveness::NotLive)
+        .await
+        .unwrap();
+
+    let (stored_poi, stored_block) = store
+        .indexer_poi_at_block(&indexer, &deployment, block.number)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored_poi.poi, [1; 32].into());
+    assert_eq!(stored_block.hash, block.hash);
+    assert_eq!(stored_block.canonical, None);
+
+    assert!(store
+        .indexer_poi_at_block(&indexer, &deployment, block.number + 1)
+        .await
+        .unwrap()
+        .is_none());
+
+    store
+        .set_blocks_canonical(&HashMap::from([(block.hash.clone().unwrap(), false)]))
+        .await
+        .unwrap();
+    let (_, stored_block) = store
+        .indexer_poi_at_block(&indexer, &deployment, block.number)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored_block.canonical, Some(false));
+}
+
+#[tokio::test]
+async fn pois_without_block_hashes() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["foo", "bar"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+
+    // Substreams-powered deployments, for example, may lack block hashes.
+    let deployment = gen_deployments()[0].clone();
+    let block = BlockPointer {
+        number: 42,
+        hash: None,
+    };
+    for indexer in &indexers {
+        let poi = ProofOfIndexing {
+            indexer: indexer.clone(),
+            deployment: deployment.clone(),
+            block: block.clone(),
+            proof_of_indexing: [1; 32].into(),
+        };
+        store
+            .write_pois(vec![poi], PoiLiveness::NotLive)
+            .await
+            .unwrap();
+    }
+
+    let (poi1, block1) = store
+        .indexer_poi_at_block(&indexers[0], &deployment, 42)
+        .await
+        .unwrap()
+        .unwrap();
+    let (poi2, block2) = store
+        .indexer_poi_at_block(&indexers[1], &deployment, 42)
         .await
         .unwrap()
         .unwrap();
-    assert_eq!(req.0, uuid);
+    assert_eq!(block1.hash, None);
+    assert_eq!(block1.id, block2.id);
+    assert_eq!(poi1.block_id, poi2.block_id);
+}
+
+#[tokio::test]
+async fn bisection_runs() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let indexer: Arc<dyn IndexerClient> = Arc::new(MockIndexer {
+        name: "foo".to_string(),
+        deployment_details: vec![],
+        fail_indexing_statuses: false,
+    });
+    store.write_indexers(&[indexer.clone()]).await.unwrap();
+
+    let deployment = gen_deployments()[0].clone();
+    let poi = ProofOfIndexing {
+        indexer: indexer.clone(),
+        deployment: deployment.clone(),
+        block: gen_blocks()[3].clone(),
+        proof_of_indexing: [1; 32].into(),
+    };
+    store
+        .write_pois(vec![poi], PoiLiveness::NotLive)
+        .await
+        .unwrap();
+    let indexer_id = store.indexers(IndexersQuery::default()).await.unwrap()[0].id;
+    let deployment_id = store
+        .sg_deployments(SgDeploymentsQuery::default())
+        .await
+        .unwrap()[0]
+        .id;
+
+    let investigation_uuid = Uuid::new_v4();
+    store
+        .create_or_update_divergence_investigation_report(
+            &investigation_uuid,
+            serde_json::json!({}),
+        )
+        .await
+        .unwrap();
+
+    let run_uuid = Uuid::new_v4();
+    store
+        .write_bisection_run(
+            NewBisectionRun {
+                uuid: run_uuid,
+                investigation_uuid,
+                sg_deployment_id: Some(deployment_id),
+                indexer1_id: Some(indexer_id),
+                indexer2_id: None,
+                poi1: [1; 32].into(),
+                poi2: [2; 32].into(),
+                lower_bound: 10,
+                upper_bound: 20,
+                error: None,
+            },
+            vec![NewBisectionAttempt {
+                bisection_run_uuid: run_uuid,
+                block_number: 15,
+                block_hash: None,
+                indexer1_response: "foo".to_string(),
+                indexer2_response: "bar".to_string(),
+            }],
+            vec![NewBisectionEvidence {
+                bisection_run_uuid: run_uuid,
+                indexer_id,
+                block_number: 20,
+                kind: BisectionEvidenceKind::BlockCache,
+                contents: serde_json::json!({}),
+            }],
+        )
+        .await
+        .unwrap();
+
+    let runs = |filter| {
+        let store = &store;
+        async move { store.bisection_runs(filter).await.unwrap().len() }
+    };
+    assert_eq!(runs(BisectionRunsQuery::default()).await, 1);
+    assert_eq!(
+        runs(BisectionRunsQuery {
+            deployment: Some(deployment),
+            indexer: Some(indexer.address()),
+            block_number: Some(15),
+            ..Default::default()
+        })
+        .await,
+        1
+    );
+    assert_eq!(
+        runs(BisectionRunsQuery {
+            block_number: Some(21),
+            ..Default::default()
+        })
+        .await,
+        0
+    );
+    assert_eq!(
+        runs(BisectionRunsQuery {
+            indexer: Some([0; 20].into()),
+            ..Default::default()
+        })
+        .await,
+        0
+    );
+
+    let attempts = store.bisection_attempts(&run_uuid).await.unwrap();
+    assert_eq!(attempts.len(), 1);
+    assert_eq!(attempts[0].block_number, 15);
+    let evidence = store.bisection_evidence(&run_uuid).await.unwrap();
+    assert_eq!(evidence.len(), 1);
+    assert_eq!(evidence[0].kind, BisectionEvidenceKind::BlockCache);
+}
+
+#[tokio::test]
+async fn delete_indexer_data() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["foo", "bar"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+
+    let deployment = gen_deployments()[0].clone();
+    let pois = indexers
+        .iter()
+        .map(|indexer| ProofOfIndexing {
+            indexer: indexer.clone(),
+            deployment: deployment.clone(),
+            block: gen_blocks()[3].clone(),
+            proof_of_indexing: [1; 32].into(),
+        })
+        .collect();
+    store.write_pois(pois, PoiLiveness::Live).await.unwrap();
+
+    let address = indexers[0].address();
+    let expected = IndexerDataDeletion {
+        dry_run: true,
+        pois: 1,
+        live_pois: 1,
+        ..Default::default()
+    };
+    assert_eq!(
+        store.delete_indexer_data(&address, true).await.unwrap(),
+        expected
+    );
+    assert_eq!(
+        store.delete_indexer_data(&address, false).await.unwrap(),
+        IndexerDataDeletion {
+            dry_run: false,
+            ..expected
+        }
+    );
+
+    let remaining_pois = store
+        .live_pois(None, &[], Some(&[deployment]), None, None)
+        .await
+        .unwrap();
+    assert_eq!(remaining_pois.len(), 1);
+    assert_eq!(
+        store.delete_indexer_data(&address, true).await.unwrap(),
+        IndexerDataDeletion {
+            dry_run: true,
+            ..Default::default()
+        }
+    );
+}
+
+#[tokio::test]
+async fn register_then_unregister_indexer() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    let address = [1; 20].into();
+
+    let mut indexer = NewRegisteredIndexer {
+        address,
+        name: None,
+        index_node_endpoint: "http://localhost:8030/graphql".to_string(),
+        workspace_id: None,
+    };
+    store.register_indexer(&indexer).await.unwrap();
+    indexer.name = Some("foo".to_string());
+    store.register_indexer(&indexer).await.unwrap();
+
+    let registered_indexers = store.registered_indexers().await.unwrap();
+    assert_eq!(registered_indexers.len(), 1);
+    assert_eq!(registered_indexers[0].name.as_deref(), Some("foo"));
+
+    assert!(store.unregister_indexer(&address, None).await.unwrap());
+    assert!(!store.unregister_indexer(&address, None).await.unwrap());
+    assert!(store.registered_indexers().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn track_then_untrack_deployment() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let deployment = gen_deployments()[0].clone();
+    assert!(store
+        .track_deployment(&deployment, "unknown", None)
+        .await
+        .is_err());
+    store
+        .track_deployment(&deployment, "mainnet", None)
+        .await
+        .unwrap();
+    store
+        .track_deployment(&deployment, "mainnet", None)
+        .await
+        .unwrap();
+
+    let tracked_deployments = store.tracked_deployments().await.unwrap();
+    assert_eq!(tracked_deployments.len(), 1);
+    assert_eq!(tracked_deployments[0].cid, deployment);
+    assert_eq!(tracked_deployments[0].network, "mainnet");
+
+    assert!(store.untrack_deployment(&deployment, None).await.unwrap());
+    assert!(!store.untrack_deployment(&deployment, None).await.unwrap());
+    assert!(store.tracked_deployments().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn leader_lock_is_exclusive_per_shard() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+
+    let mut lock = store.try_acquire_leader_lock(0).await.unwrap().unwrap();
+    assert!(lock.is_held().await);
+    assert!(store.try_acquire_leader_lock(0).await.unwrap().is_none());
+    // Other shards have leaders of their own.
+    assert!(store.try_acquire_leader_lock(1).await.unwrap().is_some());
+
+    // The lock is released once Postgres notices that its session ended.
+    drop(lock);
+    let mut reacquired = None;
+    for _ in 0..50 {
+        reacquired = store.try_acquire_leader_lock(0).await.unwrap();
+        if reacquired.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(reacquired.is_some());
+}
+
+#[tokio::test]
+async fn overwrite_config() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    assert!(store.current_config().await.unwrap().is_none());
+
+    for polling_period in [60, 120] {
+        let config = serde_json::json!({ "pollingPeriodInSeconds": polling_period });
+        store.overwrite_config(config.clone()).await.unwrap();
+        assert_eq!(store.current_config().await.unwrap(), Some(config));
+    }
+}
+
+#[tokio::test]
+async fn graph_node_version_history() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+
+    let indexer: Arc<dyn IndexerClient> = Arc::new(MockIndexer {
+        name: "foo".to_string(),
+        deployment_details: vec![],
+        fail_indexing_statuses: false,
+    });
+    store.write_indexers(&[indexer.clone()]).await.unwrap();
+
+    for version in ["0.34.0", "0.34.0", "0.35.0"] {
+        let version = GraphNodeCollectedVersion {
+            version: Some(version.to_string()),
+            commit: None,
+            error_response: None,
+            collected_at: chrono::Utc::now().naive_utc(),
+        };
+        let versions = HashMap::from([(indexer.clone(), Ok(version))]);
+        store.write_graph_node_versions(versions).await.unwrap();
+    }
+
+    let indexer_id = store.indexers(IndexersQuery::default()).await.unwrap()[0].id;
+    let history = store.graph_node_version_history(indexer_id).await.unwrap();
+    let versions: Vec<_> = history
+        .iter()
+        .map(|v| v.version_string.as_deref().unwrap())
+        .collect();
+    assert_eq!(versions, vec!["0.34.0", "0.35.0"]);
+}
+
+#[tokio::test]
+async fn subgraph_api_versions() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+
+    let ipfs_cid1 = "QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA";
+    let ipfs_cid2 = "QmYzsCjrVwwXtdsNm3PZVNziLGmb9o513GUzkq5wwhgXDT";
+
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+    store
+        .create_sg_deployment("mainnet", ipfs_cid1)
+        .await
+        .unwrap();
+    store
+        .create_sg_deployment("mainnet", ipfs_cid2)
+        .await
+        .unwrap();
+
+    let versions = HashMap::from([
+        (ipfs_cid1.parse().unwrap(), Ok(vec!["0.0.7".to_string()])),
+        (ipfs_cid2.parse().unwrap(), Err(anyhow::anyhow!("timeout"))),
+    ]);
+    store.write_subgraph_api_versions(versions).await.unwrap();
+
+    let known = store.sg_deployments_with_api_versions().await.unwrap();
+    assert_eq!(known, HashSet::from([ipfs_cid1.parse().unwrap()]));
+
+    let deployments = store
+        .sg_deployments(SgDeploymentsQuery::default())
+        .await
+        .unwrap();
+    let api_versions = |cid: &str| {
+        let id = deployments
+            .iter()
+            .find(|d| d.cid.to_string() == cid)
+            .unwrap()
+            .id;
+        store.subgraph_api_versions(id)
+    };
+    assert_eq!(
+        api_versions(ipfs_cid1).await.unwrap(),
+        Some(vec!["0.0.7".to_string()])
+    );
+    assert_eq!(api_versions(ipfs_cid2).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn subgraph_manifests() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+
+    let ipfs_cid1 = "QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA";
+    let ipfs_cid2 = "QmYzsCjrVwwXtdsNm3PZVNziLGmb9o513GUzkq5wwhgXDT";
+
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+    store
+        .create_sg_deployment("mainnet", ipfs_cid1)
+        .await
+        .unwrap();
+    store
+        .create_sg_deployment("mainnet", ipfs_cid2)
+        .await
+        .unwrap();
+    let id1 = store
+        .sg_deployments(SgDeploymentsQuery::default())
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|d| d.cid.to_string() == ipfs_cid1)
+        .unwrap()
+        .id;
+
+    let data_source = |name: &str, is_template: bool| FetchedDataSource {
+        is_template,
+        kind: "ethereum/contract".to_string(),
+        name: name.to_string(),
+        network: Some("mainnet".to_string()),
+        address: None,
+        start_block: Some(1),
+    };
+    let manifest = |data_sources| FetchedSubgraphManifest {
+        spec_version: "0.0.5".to_string(),
+        features: vec!["grafting".to_string()],
+        graft_base: Some(ipfs_cid2.to_string()),
+        graft_block: Some(10),
+        raw_manifest: "specVersion: 0.0.5".to_string(),
+        data_sources,
+    };
+
+    let cid1 = ipfs_cid1.parse().unwrap();
+    store
+        .write_subgraph_manifest(&cid1, manifest(vec![data_source("Foo", false)]))
+        .await
+        .unwrap();
+    // Manifests are replaced as a whole.
+    store
+        .write_subgraph_manifest(
+            &cid1,
+            manifest(vec![data_source("Bar", false), data_source("Baz", true)]),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store.sg_deployments_without_manifest().await.unwrap(),
+        vec![ipfs_cid2.parse().unwrap()]
+    );
+
+    let stored = store.subgraph_manifest(id1).await.unwrap().unwrap();
+    assert_eq!(stored.features, vec!["grafting".to_string()]);
+    assert_eq!(stored.graft_block, Some(10));
+    let names: Vec<_> = store
+        .subgraph_data_sources(id1)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|data_source| (data_source.name, data_source.is_template))
+        .collect();
+    assert_eq!(
+        names,
+        vec![("Bar".to_string(), false), ("Baz".to_string(), true)]
+    );
+}
+
+#[tokio::test]
+async fn api_data_version_changes_with_data() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+
+    let (version1, last_modified) = store.api_data_version().await.unwrap();
+    assert_eq!(last_modified, None);
+    assert_eq!(store.api_data_version().await.unwrap().0, version1);
+
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+    let (version2, _) = store.api_data_version().await.unwrap();
+    assert_ne!(version1, version2);
+
+    store
+        .create_sg_deployment("mainnet", "QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA")
+        .await
+        .unwrap();
+    let (version3, _) = store.api_data_version().await.unwrap();
+    assert_ne!(version2, version3);
+
+    store
+        .set_deployment_name("QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA", "foo")
+        .await
+        .unwrap();
+    assert_ne!(store.api_data_version().await.unwrap().0, version3);
+}
+
+#[tokio::test]
+async fn allocation_pois() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["foo", "bar", "baz"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+
+    let deployment = gen_deployments()[0].clone();
+    let pois = indexers[..2]
+        .iter()
+        .map(|indexer| ProofOfIndexing {
+            indexer: indexer.clone(),
+            deployment: deployment.clone(),
+            block: gen_blocks()[3].clone(),
+            proof_of_indexing: [1; 32].into(),
+        })
+        .collect();
+    store.write_pois(pois, PoiLiveness::Live).await.unwrap();
+
+    let closed_at = chrono::DateTime::from_timestamp(1_700_000_000, 0)
+        .unwrap()
+        .naive_utc();
+    let submitted = |id: &str, indexer: &Arc<dyn IndexerClient>, poi: u8| SubmittedAllocationPoi {
+        allocation_id: id.to_string(),
+        indexer_address: indexer.address(),
+        deployment: deployment.clone(),
+        closed_at_epoch: 100,
+        block_number: 3,
+        poi: [poi; 32].into(),
+        closed_at,
+    };
+    let submitted_pois = [
+        submitted("0x1", &indexers[0], 1),
+        submitted("0x2", &indexers[1], 2),
+        submitted("0x3", &indexers[2], 1),
+    ];
+
+    let written = store.write_allocation_pois(&submitted_pois).await.unwrap();
+    let matches: Vec<_> = written.iter().map(|poi| poi.matches).collect();
+    assert_eq!(matches, vec![Some(true), Some(false), None]);
+    assert!(written[2].collected_poi_id.is_none());
+
+    // Allocations are only compared once.
+    assert!(store
+        .write_allocation_pois(&submitted_pois)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let mismatches = store
+        .allocation_pois(AllocationPoisQuery {
+            only_mismatches: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].allocation_id, "0x2");
+
+    let by_indexer = store
+        .allocation_pois(AllocationPoisQuery {
+            indexer: Some(indexers[0].address()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(by_indexer.len(), 1);
+    assert_eq!(by_indexer[0].allocation_id, "0x1");
+
+    assert_eq!(
+        store.latest_allocation_poi_closed_at().await.unwrap(),
+        Some(closed_at)
+    );
+}
+
+#[tokio::test]
+async fn entity_counts() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["foo", "bar"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+
+    let deployments = gen_deployments();
+    let pois = indexers
+        .iter()
+        .map(|indexer| ProofOfIndexing {
+            indexer: indexer.clone(),
+            deployment: deployments[0].clone(),
+            block: gen_blocks()[3].clone(),
+            proof_of_indexing: [1; 32].into(),
+        })
+        .collect();
+    store.write_pois(pois, PoiLiveness::Live).await.unwrap();
+
+    let status =
+        |indexer: &Arc<dyn IndexerClient>, deployment: &IpfsCid, entity_count| IndexingStatus {
+            indexer: indexer.clone(),
+            deployment: deployment.clone(),
+            network: "mainnet".to_string(),
+            latest_block: gen_blocks()[3].clone(),
+            earliest_block_num: None,
+            synced: true,
+            health: SubgraphHealth::Healthy,
+            entity_count,
+        };
+    store
+        .write_entity_counts(&[
+            status(&indexers[0], &deployments[0], 10),
+            status(&indexers[1], &deployments[0], 12),
+            // Unknown deployments are skipped.
+            status(&indexers[0], &deployments[1], 5),
+        ])
+        .await
+        .unwrap();
+    store
+        .write_entity_counts(&[status(&indexers[0], &deployments[0], 11)])
+        .await
+        .unwrap();
+
+    let counts: Vec<_> = store
+        .latest_entity_counts(&deployments[0])
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|count| count.entity_count)
+        .collect();
+    assert_eq!(counts, vec![11, 12]);
+    assert!(store
+        .latest_entity_counts(&deployments[1])
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn indexing_status_history() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["foo", "bar"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+
+    let deployment = gen_deployments()[0].clone();
+    let blocks = gen_blocks();
+    let pois = indexers
+        .iter()
+        .map(|indexer| ProofOfIndexing {
+            indexer: indexer.clone(),
+            deployment: deployment.clone(),
+            block: blocks[0].clone(),
+            proof_of_indexing: [1; 32].into(),
+        })
+        .collect();
+    store.write_pois(pois, PoiLiveness::Live).await.unwrap();
+
+    let status = |indexer: &Arc<dyn IndexerClient>, block: &BlockPointer, health| IndexingStatus {
+        indexer: indexer.clone(),
+        deployment: deployment.clone(),
+        network: "mainnet".to_string(),
+        latest_block: block.clone(),
+        earliest_block_num: None,
+        synced: false,
+        health,
+        entity_count: 0,
+    };
+    store
+        .write_indexing_status_history(&[
+            status(&indexers[0], &blocks[1], SubgraphHealth::Healthy),
+            status(&indexers[1], &blocks[1], SubgraphHealth::Healthy),
+        ])
+        .await
+        .unwrap();
+    store
+        .write_indexing_status_history(&[
+            status(&indexers[0], &blocks[2], SubgraphHealth::Healthy),
+            status(&indexers[1], &blocks[1], SubgraphHealth::Failed),
+        ])
+        .await
+        .unwrap();
+
+    let since = chrono::Utc::now().naive_utc() - chrono::Duration::hours(1);
+    let history = store
+        .indexing_status_history(&deployment, None, since, 100)
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 4);
+
+    let history: Vec<_> = store
+        .indexing_status_history(&deployment, Some(&indexers[1].address()), since, 100)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|snapshot| (snapshot.latest_block_number, snapshot.health))
+        .collect();
+    let block_number = blocks[1].number as i64;
+    assert_eq!(
+        history,
+        vec![
+            (block_number, SubgraphHealth::Healthy),
+            (block_number, SubgraphHealth::Failed)
+        ]
+    );
+
+    let future = chrono::Utc::now().naive_utc() + chrono::Duration::hours(1);
+    assert!(store
+        .indexing_status_history(&deployment, None, future, 100)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn indexer_uptime_transitions() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    let indexer: Arc<dyn IndexerClient> = Arc::new(MockIndexer {
+        name: "foo".to_string(),
+        deployment_details: vec![],
+        fail_indexing_statuses: false,
+    });
+    store.write_indexers(&[indexer.clone()]).await.unwrap();
+    let indexer_id = store
+        .indexers(IndexersQuery::default())
+        .await
+        .unwrap()
+        .first()
+        .unwrap()
+        .id;
+
+    // Only changes are recorded.
+    assert!(store
+        .record_indexer_uptime(indexer.as_ref(), true)
+        .await
+        .unwrap());
+    assert!(!store
+        .record_indexer_uptime(indexer.as_ref(), true)
+        .await
+        .unwrap());
+    assert!(store
+        .record_indexer_uptime(indexer.as_ref(), false)
+        .await
+        .unwrap());
+
+    let long_ago = chrono::DateTime::from_timestamp(0, 0).unwrap().naive_utc();
+    let transitions: Vec<_> = store
+        .indexer_uptime_transitions(indexer_id, long_ago)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|transition| transition.up)
+        .collect();
+    assert_eq!(transitions, vec![true, false]);
+
+    // The last transition before the window is included.
+    let future = chrono::Utc::now().naive_utc() + chrono::Duration::hours(1);
+    let transitions: Vec<_> = store
+        .indexer_uptime_transitions(indexer_id, future)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|transition| transition.up)
+        .collect();
+    assert_eq!(transitions, vec![false]);
+}
+
+#[tokio::test]
+async fn indexer_sla_data() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["foo", "bar"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+    let indexer_id = |name: &str| {
+        let store = &store;
+        let name = name.to_string();
+        async move {
+            store
+                .indexers(IndexersQuery::default())
+                .await
+                .unwrap()
+                .into_iter()
+                .find(|indexer| indexer.name.as_deref() == Some(name.as_str()))
+                .unwrap()
+                .id
+        }
+    };
+    let foo_id = indexer_id("foo").await;
+    let bar_id = indexer_id("bar").await;
+
+    // Both indexers have a PoI for block 3, only `bar` for block 4.
+    let deployment = gen_deployments()[0].clone();
+    let blocks = gen_blocks();
+    for (indexer, block) in [(&indexers[0], 3), (&indexers[1], 3), (&indexers[1], 4)] {
+        let poi = ProofOfIndexing {
+            indexer: indexer.clone(),
+            deployment: deployment.clone(),
+            block: blocks[block].clone(),
+            proof_of_indexing: [1; 32].into(),
+        };
+        store
+            .write_pois(vec![poi], PoiLiveness::NotLive)
+            .await
+            .unwrap();
+    }
+
+    let long_ago = chrono::DateTime::from_timestamp(0, 0).unwrap().naive_utc();
+    let comparable_pois = store
+        .pois_comparable_with_indexer(foo_id, long_ago)
+        .await
+        .unwrap();
+    let indexer_ids: HashSet<_> = comparable_pois.iter().map(|poi| poi.indexer_id).collect();
+    assert_eq!(comparable_pois.len(), 2);
+    assert_eq!(indexer_ids, HashSet::from([foo_id, bar_id]));
+    assert_eq!(
+        store
+            .pois_comparable_with_indexer(bar_id, long_ago)
+            .await
+            .unwrap()
+            .len(),
+        3
+    );
+
+    assert_eq!(
+        store
+            .poi_request_success_rate(foo_id, long_ago)
+            .await
+            .unwrap(),
+        None
+    );
+    store
+        .write_poi_request_outcomes(&[
+            PoiRequestOutcome {
+                indexer: indexers[0].clone(),
+                requested: 4,
+                returned: 1,
+            },
+            PoiRequestOutcome {
+                indexer: indexers[0].clone(),
+                requested: 4,
+                returned: 3,
+            },
+        ])
+        .await
+        .unwrap();
+    assert_eq!(
+        store
+            .poi_request_success_rate(foo_id, long_ago)
+            .await
+            .unwrap(),
+        Some(0.5)
+    );
+
+    assert_eq!(
+        store
+            .confirmed_divergences_count(foo_id, long_ago)
+            .await
+            .unwrap(),
+        0
+    );
+}
+
+#[tokio::test]
+async fn api_key_usage() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    let api_key = store
+        .create_api_key(None, ApiKeyPermissionLevel::Admin, None)
+        .await
+        .unwrap();
+    let api_key: ApiKey = api_key.api_key.parse().unwrap();
+
+    for millis in [10, 30] {
+        store
+            .record_api_key_usage(&api_key, Duration::from_millis(millis))
+            .await
+            .unwrap();
+    }
+    // Unknown API keys aren't metered.
+    store
+        .record_api_key_usage(&ApiKey::generate(), Duration::from_millis(10))
+        .await
+        .unwrap();
+
+    let usage = store.api_key_usage().await.unwrap();
+    assert_eq!(usage.len(), 1);
+    assert_eq!(usage[0].public_prefix, api_key.public_part_as_string());
+    assert_eq!(usage[0].query_count, 2);
+    assert_eq!(usage[0].total_execution_time_ms, 40);
+
+    // Usage is deleted together with the API key.
+    store.delete_api_key(&api_key.to_string()).await.unwrap();
+    assert!(store.api_key_usage().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn api_key_limits() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    let api_key = store
+        .create_api_key(None, ApiKeyPermissionLevel::Admin, None)
+        .await
+        .unwrap();
+    let api_key_s = api_key.api_key;
+    let api_key: ApiKey = api_key_s.parse().unwrap();
+
+    let no_limits = ApiKeyLimits {
+        expires_at: None,
+        daily_request_quota: None,
+        requests_today: 0,
+    };
+    assert_eq!(
+        store.api_key_limits(&api_key).await.unwrap(),
+        Some(no_limits)
+    );
+    assert_eq!(
+        store.api_key_limits(&ApiKey::generate()).await.unwrap(),
+        None
+    );
+
+    let expires_at = chrono::DateTime::from_timestamp(2_000_000_000, 0)
+        .unwrap()
+        .naive_utc();
+    store
+        .set_api_key_limits(&api_key_s, Some(expires_at), Some(100))
+        .await
+        .unwrap();
+    for _ in 0..3 {
+        store
+            .record_api_key_usage(&api_key, Duration::from_millis(1))
+            .await
+            .unwrap();
+    }
+    assert_eq!(
+        store.api_key_limits(&api_key).await.unwrap(),
+        Some(ApiKeyLimits {
+            expires_at: Some(expires_at),
+            daily_request_quota: Some(100),
+            requests_today: 3,
+        })
+    );
+
+    let metadata = store.api_keys().await.unwrap();
+    let metadata = metadata
+        .iter()
+        .find(|key| key.public_prefix == api_key.public_part_as_string())
+        .unwrap();
+    assert_eq!(metadata.daily_request_quota, Some(100));
+
+    assert!(store
+        .set_api_key_limits(&ApiKey::generate().to_string(), None, None)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn workspaces() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+    let workspace = store.create_workspace("foo").await.unwrap();
+    assert!(store.create_workspace("foo").await.is_err());
+
+    // The same indexer and deployment can be added both with and without a
+    // workspace.
+    let address = [1; 20].into();
+    let deployment = gen_deployments()[0].clone();
+    for workspace_id in [None, Some(workspace.id)] {
+        store
+            .register_indexer(&NewRegisteredIndexer {
+                address,
+                name: None,
+                index_node_endpoint: "http://localhost:8030/graphql".to_string(),
+                workspace_id,
+            })
+            .await
+            .unwrap();
+        store
+            .track_deployment(&deployment, "mainnet", workspace_id)
+            .await
+            .unwrap();
+    }
+    assert_eq!(store.registered_indexers().await.unwrap().len(), 2);
+    assert_eq!(store.tracked_deployments().await.unwrap().len(), 2);
+
+    let api_key = store
+        .create_api_key(None, ApiKeyPermissionLevel::Admin, Some(workspace.id))
+        .await
+        .unwrap();
+    let api_key: ApiKey = api_key.api_key.parse().unwrap();
+    assert_eq!(
+        store.api_key_workspace_id(&api_key).await.unwrap(),
+        Some(workspace.id)
+    );
+    store
+        .set_api_key_workspace(&api_key.to_string(), None)
+        .await
+        .unwrap();
+    assert_eq!(store.api_key_workspace_id(&api_key).await.unwrap(), None);
+    assert!(store
+        .set_api_key_workspace(&api_key.to_string(), Some("bar"))
+        .await
+        .is_err());
+
+    assert!(store
+        .unregister_indexer(&address, Some(workspace.id))
+        .await
+        .unwrap());
+    assert_eq!(store.registered_indexers().await.unwrap().len(), 1);
+
+    // Deleting a workspace deletes everything that's scoped to it.
+    assert!(store.delete_workspace("foo").await.unwrap());
+    assert!(!store.delete_workspace("foo").await.unwrap());
+    let tracked_deployments = store.tracked_deployments().await.unwrap();
+    assert_eq!(tracked_deployments.len(), 1);
+    assert_eq!(tracked_deployments[0].workspace_id, None);
+}
+
+#[tokio::test]
+async fn live_pois_written_in_chunks() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["foo", "bar"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+
+    let deployment = gen_deployments()[0].clone();
+    let poi = |indexer: &Arc<dyn IndexerClient>, block: usize| ProofOfIndexing {
+        indexer: indexer.clone(),
+        deployment: deployment.clone(),
+        block: gen_blocks()[block].clone(),
+        proof_of_indexing: [1; 32].into(),
+    };
+    let deployments = [deployment.clone()];
+    let live_pois = || store.live_pois(None, &[], Some(&deployments), None, None);
+
+    // PoIs of different indexers for the same block are all live, even when
+    // written separately.
+    for indexer in &indexers {
+        store
+            .write_pois(vec![poi(indexer, 3)], PoiLiveness::Live)
+            .await
+            .unwrap();
+    }
+    let pois = live_pois().await.unwrap();
+    assert_eq!(pois.len(), 2);
+    let old_block_id = pois[0].block_id;
+
+    // A PoI for a newer block replaces all live PoIs at other blocks.
+    store
+        .write_pois(vec![poi(&indexers[0], 4)], PoiLiveness::Live)
+        .await
+        .unwrap();
+    let pois = live_pois().await.unwrap();
+    assert_eq!(pois.len(), 1);
+    assert_ne!(pois[0].block_id, old_block_id);
+}
+
+#[tokio::test]
+async fn network_stats() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    assert!(store
+        .network_stats("mainnet", None)
+        .await
+        .unwrap()
+        .is_none());
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let stats = store.network_stats("mainnet", None).await.unwrap().unwrap();
+    assert_eq!(stats.active_indexers, 0);
+    assert_eq!(stats.average_agreement_ratio, None);
+    assert_eq!(stats.chain_head_lag_max, None);
+
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["foo", "bar"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+
+    // The two indexers disagree, so each agrees with half of the indexers.
+    let pois = indexers
+        .iter()
+        .enumerate()
+        .map(|(i, indexer)| ProofOfIndexing {
+            indexer: indexer.clone(),
+            deployment: gen_deployments()[0].clone(),
+            block: gen_blocks()[3].clone(),
+            proof_of_indexing: [i as u8; 32].into(),
+        })
+        .collect();
+    store.write_pois(pois, PoiLiveness::Live).await.unwrap();
+
+    let stats = store.network_stats("mainnet", None).await.unwrap().unwrap();
+    assert_eq!(stats.active_indexers, 2);
+    assert_eq!(stats.average_agreement_ratio, Some(0.5));
+}
+
+#[tokio::test]
+async fn sg_deployments_are_stored_on_their_network() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    let indexer: Arc<dyn IndexerClient> = Arc::new(MockIndexer {
+        name: "foo".to_string(),
+        deployment_details: vec![],
+        fail_indexing_statuses: false,
+    });
+    store.write_indexers(&[indexer.clone()]).await.unwrap();
+
+    let deployment = gen_deployments()[0].clone();
+    let networks = |network: &str| HashMap::from([(deployment.clone(), network.to_string())]);
+    store
+        .write_sg_deployment_networks(&networks("gnosis"))
+        .await
+        .unwrap();
+    let poi = ProofOfIndexing {
+        indexer,
+        deployment: deployment.clone(),
+        block: gen_blocks()[3].clone(),
+        proof_of_indexing: [1; 32].into(),
+    };
+    store
+        .write_pois(vec![poi], PoiLiveness::Live)
+        .await
+        .unwrap();
+
+    let stats = store.network_stats("gnosis", None).await.unwrap().unwrap();
+    assert_eq!(stats.active_indexers, 1);
+    // So are their blocks.
+    let gnosis_blocks = diesel::select(diesel::dsl::sql::<diesel::sql_types::BigInt>(
+        "(SELECT count(*) FROM blocks JOIN networks ON networks.id = blocks.network_id \
+         WHERE networks.name = 'gnosis')",
+    ))
+    .get_result::<i64>(&mut store.conn().await.unwrap())
+    .await
+    .unwrap();
+    assert_eq!(gnosis_blocks, 1);
+
+    // E.g. after the network was renamed.
+    store
+        .write_sg_deployment_networks(&networks("xdai"))
+        .await
+        .unwrap();
+    let stats = store.network_stats("xdai", None).await.unwrap().unwrap();
+    assert_eq!(stats.active_indexers, 1);
+    let stats = store.network_stats("gnosis", None).await.unwrap().unwrap();
+    assert_eq!(stats.active_indexers, 0);
+}
+
+#[tokio::test]
+async fn daily_agreement() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["foo", "bar"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+
+    let deployment = gen_deployments()[0].clone();
+    let pois = indexers
+        .iter()
+        .enumerate()
+        .map(|(i, indexer)| ProofOfIndexing {
+            indexer: indexer.clone(),
+            deployment: deployment.clone(),
+            block: gen_blocks()[3].clone(),
+            proof_of_indexing: [i as u8; 32].into(),
+        })
+        .collect();
+    store.write_pois(pois, PoiLiveness::Live).await.unwrap();
+
+    // Nothing is rolled up before the first refresh, and refreshing again
+    // replaces the current day instead of adding to it.
+    let trend = store
+        .daily_deployment_agreement(&deployment, 7)
+        .await
+        .unwrap();
+    assert!(trend.is_empty());
+    store.refresh_daily_agreement().await.unwrap();
+    store.refresh_daily_agreement().await.unwrap();
+
+    let trend = store
+        .daily_deployment_agreement(&deployment, 7)
+        .await
+        .unwrap();
+    assert_eq!(trend.len(), 1);
+    assert_eq!(trend[0].poi_count, 2);
+    assert_eq!(trend[0].indexer_count, 2);
+    assert_eq!(trend[0].diverging_blocks, 1);
+    assert_eq!(trend[0].average_agreement_ratio, 0.5);
+
+    let trend = store
+        .daily_indexer_agreement(&indexers[0].address(), 7)
+        .await
+        .unwrap();
+    assert_eq!(trend.len(), 1);
+    assert_eq!(trend[0].poi_count, 1);
+    assert_eq!(trend[0].deployment_count, 1);
+}
+
+#[tokio::test]
+async fn poi_agreement_counts() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["foo", "bar", "baz"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+
+    // `foo` and `bar` agree, `baz` is the odd one out.
+    let pois = indexers
+        .iter()
+        .zip([1, 1, 2])
+        .map(|(indexer, poi)| ProofOfIndexing {
+            indexer: indexer.clone(),
+            deployment: gen_deployments()[0].clone(),
+            block: gen_blocks()[3].clone(),
+            proof_of_indexing: [poi; 32].into(),
+        })
+        .collect();
+    store.write_pois(pois, PoiLiveness::Live).await.unwrap();
+
+    let counts = store
+        .poi_agreement_counts(&indexers[0].address())
+        .await
+        .unwrap();
+    assert_eq!(counts.len(), 1);
+    assert_eq!(counts[0].total_indexers, 3);
+    assert_eq!(counts[0].agreeing_indexers, 2);
+    assert!(counts[0].has_consensus && counts[0].in_consensus);
+
+    let counts = store
+        .poi_agreement_counts(&indexers[2].address())
+        .await
+        .unwrap();
+    assert_eq!(counts[0].agreeing_indexers, 1);
+    assert!(counts[0].has_consensus && !counts[0].in_consensus);
+}
+
+#[tokio::test]
+async fn pois_by_value() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["foo", "bar", "baz"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+
+    let deployment = gen_deployments()[0].clone();
+    for (indexer, byte) in indexers.iter().zip([1, 1, 2]) {
+        let poi = ProofOfIndexing {
+            indexer: indexer.clone(),
+            deployment: deployment.clone(),
+            block: gen_blocks()[3].clone(),
+            proof_of_indexing: [byte; 32].into(),
+        };
+        store
+            .write_pois(vec![poi], PoiLiveness::NotLive)
+            .await
+            .unwrap();
+    }
+
+    let pois = store.pois_by_value(&[[1; 32].into()]).await.unwrap();
+    assert_eq!(pois.len(), 2);
+    assert_ne!(pois[0].indexer_id, pois[1].indexer_id);
+    assert!(pois.iter().all(|poi| poi.poi == [1; 32].into()));
+    let pois = store
+        .pois_by_value(&[[1; 32].into(), [2; 32].into(), [3; 32].into()])
+        .await
+        .unwrap();
+    assert_eq!(pois.len(), 3);
+}
+
+#[tokio::test]
+async fn deployment_poi_history() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["foo", "bar"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+
+    let deployment = gen_deployments()[0].clone();
+    let pois = [
+        (0, 3, 1),
+        (1, 3, 1),
+        // Replaces the previous PoI of the same indexer at the same block.
+        (1, 3, 2),
+        (0, 4, 3),
+    ];
+    for (indexer, block, byte) in pois {
+        let poi = ProofOfIndexing {
+            indexer: indexers[indexer].clone(),
+            deployment: deployment.clone(),
+            block: gen_blocks()[block].clone(),
+            proof_of_indexing: [byte; 32].into(),
+        };
+        store
+            .write_pois(vec![poi], PoiLiveness::NotLive)
+            .await
+            .unwrap();
+    }
+
+    let history = store
+        .deployment_poi_history(&deployment, None)
+        .await
+        .unwrap();
+    let history: Vec<_> = history
+        .iter()
+        .map(|(poi, block)| (block.number, poi.poi))
+        .collect();
+    assert_eq!(history.len(), 3);
+    assert!(history.contains(&(3, [1; 32].into())));
+    assert!(history.contains(&(3, [2; 32].into())));
+    assert_eq!(history[2], (4, [3; 32].into()));
+
+    let range = BlockRange {
+        start: Some(4),
+        end: None,
+        start_time: None,
+        end_time: None,
+    };
+    let history = store
+        .deployment_poi_history(&deployment, Some(range))
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 1);
+
+    // Time bounds only match blocks with a known timestamp.
+    let timestamp = chrono::DateTime::from_timestamp(1_700_000_000, 0)
+        .unwrap()
+        .naive_utc();
+    let hash = gen_blocks()[3].hash.clone().unwrap();
+    store
+        .set_block_timestamps(&HashMap::from([(hash, timestamp)]))
+        .await
+        .unwrap();
+    let range = BlockRange {
+        start: None,
+        end: None,
+        start_time: Some(timestamp - chrono::Duration::hours(1)),
+        end_time: Some(timestamp),
+    };
+    let history = store
+        .deployment_poi_history(&deployment, Some(range))
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 2);
+    assert!(history.iter().all(|(_, block)| block.number == 3));
+}
+
+#[tokio::test]
+async fn list_queries_ordering() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["b", "c", "a"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+
+    // Indexers "b" and "c" agree, "a" doesn't.
+    let deployment = gen_deployments()[0].clone();
+    let pois = indexers
+        .iter()
+        .zip([1, 1, 2])
+        .map(|(indexer, byte)| ProofOfIndexing {
+            indexer: indexer.clone(),
+            deployment: deployment.clone(),
+            block: gen_blocks()[3].clone(),
+            proof_of_indexing: [byte; 32].into(),
+        })
+        .collect();
+    store.write_pois(pois, PoiLiveness::Live).await.unwrap();
+
+    let indexer_names = |order_by, order_direction| {
+        let store = &store;
+        async move {
+            store
+                .indexers(IndexersQuery {
+                    order_by,
+                    order_direction,
+                    ..Default::default()
+                })
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|indexer| indexer.name.unwrap())
+                .collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(
+        indexer_names(IndexersOrderBy::Name, OrderDirection::Asc).await,
+        ["a", "b", "c"]
+    );
+    assert_eq!(
+        indexer_names(IndexersOrderBy::Name, OrderDirection::Desc).await,
+        ["c", "b", "a"]
+    );
+    assert_eq!(
+        indexer_names(IndexersOrderBy::AgreementRatio, OrderDirection::Asc).await,
+        ["a", "b", "c"]
+    );
+
+    let pois = store
+        .pois(
+            &[deployment],
+            &[],
+            None,
+            None,
+            PoisOrder {
+                by: PoisOrderBy::AgreementRatio,
+                direction: OrderDirection::Desc,
+            },
+        )
+        .await
+        .unwrap();
+    let poi_bytes: Vec<_> = pois.iter().map(|poi| poi.poi).collect();
+    assert_eq!(poi_bytes, [[1; 32].into(), [1; 32].into(), [2; 32].into()]);
+
+    let deployments = store
+        .sg_deployments(SgDeploymentsQuery {
+            order_by: SgDeploymentsOrderBy::CreatedAt,
+            order_direction: OrderDirection::Desc,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(deployments.len(), 1);
+}
+
+#[tokio::test]
+async fn search_deployments_and_indexers() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let ipfs_cid1 = "QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA";
+    let ipfs_cid2 = "QmYzsCjrVwwXtdsNm3PZVNziLGmb9o513GUzkq5wwhgXDT";
+    store
+        .create_sg_deployment("mainnet", ipfs_cid1)
+        .await
+        .unwrap();
+    store
+        .create_sg_deployment("mainnet", ipfs_cid2)
+        .await
+        .unwrap();
+    store
+        .set_deployment_name(ipfs_cid1, "uniswap-v3")
+        .await
+        .unwrap();
+    store
+        .set_deployment_name(ipfs_cid2, "ens-registry")
+        .await
+        .unwrap();
+
+    let cids = |term| {
+        let store = &store;
+        async move {
+            store
+                .search_sg_deployments(term, 10)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|deployment| deployment.cid.to_string())
+                .collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(cids("QmYz").await, [ipfs_cid2]);
+    assert_eq!(cids("UNISWAP").await, [ipfs_cid1]);
+    // Not a prefix, but similar enough.
+    assert_eq!(cids("uniswap-v2").await, [ipfs_cid1]);
+    assert!(cids("%").await.is_empty());
+
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["mainframe", "other"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+
+    let indexer_names = |term| {
+        let store = &store;
+        async move {
+            store
+                .search_indexers(term, 10)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|indexer| indexer.name.unwrap())
+                .collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(indexer_names("main").await, ["mainframe"]);
+    assert_eq!(indexer_names("othr").await, ["other"]);
+    // The mock addresses are the UTF-8 encoded names, "ot" is 0x6f74.
+    assert_eq!(indexer_names("0x6F74").await, ["other"]);
+    assert!(indexer_names("0x00").await.is_empty());
+}
+
+#[tokio::test]
+async fn sg_deployment_network_subgraph_metadata_roundtrip() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+    let ipfs_cid = "QmNY7gDNXHECV8SXoEY7hbfg4BX1aDMxTBDiFuG4huaSGA";
+    store
+        .create_sg_deployment("mainnet", ipfs_cid)
+        .await
+        .unwrap();
+    let deployment_id = store.sg_deployments(Default::default()).await.unwrap()[0].id;
+
+    let metadata = |display_name: &str, signal_amount: &str| FetchedSgDeploymentMetadata {
+        deployment: ipfs_cid.parse().unwrap(),
+        display_name: Some(display_name.to_string()),
+        image: None,
+        signal_amount: signal_amount.parse().unwrap(),
+    };
+    // Unknown deployments are skipped.
+    let unknown = FetchedSgDeploymentMetadata {
+        deployment: gen_deployments()[0].clone(),
+        ..metadata("unknown", "1")
+    };
+    store
+        .write_sg_deployment_network_subgraph_metadata(&[metadata("Uniswap", "1000"), unknown])
+        .await
+        .unwrap();
+    store
+        .write_sg_deployment_network_subgraph_metadata(&[metadata("Uniswap V3", "2500")])
+        .await
+        .unwrap();
+
+    let stored = store
+        .sg_deployment_network_subgraph_metadata(deployment_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.display_name.as_deref(), Some("Uniswap V3"));
+    assert_eq!(stored.signal_amount, "2500".parse().unwrap());
+    assert_eq!(
+        store.sg_deployment_signal_amounts().await.unwrap(),
+        HashMap::from([(ipfs_cid.parse().unwrap(), 2500.0)])
+    );
+}
+
+#[tokio::test]
+async fn sg_deployment_check_tiers_are_replaced() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+    let deployments = gen_deployments();
+    for deployment in &deployments[..2] {
+        store
+            .create_sg_deployment("mainnet", &deployment.to_string())
+            .await
+            .unwrap();
+    }
+    let ids: HashMap<IpfsCid, _> = store
+        .sg_deployments(Default::default())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|deployment| (deployment.cid, deployment.id))
+        .collect();
+    let (first, second) = (ids[&deployments[0]], ids[&deployments[1]]);
+
+    // Unknown deployments are skipped.
+    store
+        .write_sg_deployment_check_tiers(&HashMap::from([
+            (deployments[0].clone(), 1),
+            (deployments[1].clone(), 2),
+            (deployments[2].clone(), 3),
+        ]))
+        .await
+        .unwrap();
+    assert_eq!(
+        store.sg_deployment_check_tier(first).await.unwrap(),
+        Some(1)
+    );
+    assert_eq!(
+        store.sg_deployment_check_tier(second).await.unwrap(),
+        Some(2)
+    );
+
+    store
+        .write_sg_deployment_check_tiers(&HashMap::from([(deployments[1].clone(), 1)]))
+        .await
+        .unwrap();
+    assert_eq!(store.sg_deployment_check_tier(first).await.unwrap(), None);
+    assert_eq!(
+        store.sg_deployment_check_tier(second).await.unwrap(),
+        Some(1)
+    );
+}
+
+#[tokio::test]
+async fn chain_disagreements_roundtrip() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    store
+        .create_network(&NewNetwork {
+            name: "mainnet".to_string(),
+            caip2: None,
+        })
+        .await
+        .unwrap();
+
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["foo", "bar"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+    let deployment = gen_deployments()[0].clone();
+    let block = gen_blocks()[3].clone();
+    let poi = ProofOfIndexing {
+        indexer: indexers[0].clone(),
+        deployment: deployment.clone(),
+        block: block.clone(),
+        proof_of_indexing: [1; 32].into(),
+    };
+    store
+        .write_pois(vec![poi], PoiLiveness::NotLive)
+        .await
+        .unwrap();
+
+    let disagreement = DetectedChainDisagreement {
+        deployment: deployment.clone(),
+        block_number: block.number,
+        block_hashes: vec![
+            (indexers[0].clone(), vec![1; 32].into()),
+            (indexers[1].clone(), vec![2; 32].into()),
+        ],
+    };
+    // Disagreements are written again as long as they persist.
+    for _ in 0..2 {
+        store
+            .write_chain_disagreements(&[disagreement.clone()])
+            .await
+            .unwrap();
+    }
+
+    let disagreements = store
+        .chain_disagreements(ChainDisagreementsQuery {
+            deployment: Some(deployment),
+            limit: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(disagreements.len(), 1);
+    assert_eq!(disagreements[0].block_number, block.number as i64);
+    let hashes: Vec<_> = disagreements[0]
+        .reports
+        .iter()
+        .map(|report| report.block_hash.clone())
+        .collect();
+    assert_eq!(hashes, vec![vec![1; 32].into(), vec![2; 32].into()]);
+
+    let other_deployment = gen_deployments()[1].clone();
+    assert!(store
+        .chain_disagreements(ChainDisagreementsQuery {
+            deployment: Some(other_deployment),
+            limit: None,
+        })
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn database_maintenance() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+
+    store.vacuum_hot_tables(true).await.unwrap();
+    store.vacuum_hot_tables(false).await.unwrap();
+
+    let sizes = store.table_sizes().await.unwrap();
+    for table in HOT_TABLES {
+        assert!(sizes.iter().any(|size| size.table_name == *table));
+    }
+    assert!(sizes
+        .windows(2)
+        .all(|sizes| sizes[0].total_bytes >= sizes[1].total_bytes));
+
+    let bloat = store.index_bloat().await.unwrap();
+    assert!(bloat.iter().any(|index| index.table_name == "pois"));
+    assert!(bloat.iter().all(|index| index.estimated_bloat_bytes >= 0));
+}
+
+#[tokio::test]
+async fn check_integrity() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    let report = store.check_integrity(true).await.unwrap();
+    assert_eq!(
+        report,
+        IntegrityReport {
+            dry_run: true,
+            ..Default::default()
+        }
+    );
+
+    // Every collected version replaces the previous one.
+    let indexer: Arc<dyn IndexerClient> = Arc::new(MockIndexer {
+        name: "foo".to_string(),
+        deployment_details: vec![],
+        fail_indexing_statuses: false,
+    });
+    store.write_indexers(&[indexer.clone()]).await.unwrap();
+    for version in ["0.34.0", "0.35.0"] {
+        let version = GraphNodeCollectedVersion {
+            version: Some(version.to_string()),
+            commit: None,
+            error_response: None,
+            collected_at: chrono::Utc::now().naive_utc(),
+        };
+        let versions = HashMap::from([(indexer.clone(), Ok(version))]);
+        store.write_graph_node_versions(versions).await.unwrap();
+    }
+
+    let report = store.check_integrity(true).await.unwrap();
+    assert_eq!(report.orphaned_graph_node_versions, 1);
+    let report = store.check_integrity(false).await.unwrap();
+    assert!(!report.dry_run);
+    assert_eq!(report.orphaned_graph_node_versions, 1);
+    let report = store.check_integrity(true).await.unwrap();
+    assert_eq!(report.orphaned_graph_node_versions, 0);
+}
+
+#[tokio::test]
+async fn indexer_identity_history() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    // Both addresses are derived from the same URL prefix.
+    let old: Arc<dyn IndexerClient> = test_indexer_from_url("http://localhost:8000");
+    let new: Arc<dyn IndexerClient> = test_indexer_from_url("http://localhost:8001");
+    assert_eq!(old.address(), new.address());
+
+    store.write_indexers(&[old.clone()]).await.unwrap();
+    store.write_indexers(&[old]).await.unwrap();
+    store.write_indexers(&[new]).await.unwrap();
+
+    let indexer_id = store.indexers(IndexersQuery::default()).await.unwrap()[0].id;
+    let history = store.indexer_identity_history(indexer_id).await.unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(
+        history[0].endpoint.as_deref(),
+        Some("http://localhost:8000/status")
+    );
+    assert_eq!(history[0].valid_until, Some(history[1].valid_from));
+    assert_eq!(
+        history[1].endpoint.as_deref(),
+        Some("http://localhost:8001/status")
+    );
+    assert_eq!(history[1].valid_until, None);
+}
+
+#[tokio::test]
+async fn archived_indexers() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["foo", "bar"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+    let address = indexers[0].address();
+
+    let archived = store
+        .set_indexer_archived(&address, true)
+        .await
+        .unwrap()
+        .unwrap();
+    let archived_at = archived.archived_at.unwrap();
+    // Archiving again keeps the original time.
+    let archived = store
+        .set_indexer_archived(&address, true)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(archived.archived_at, Some(archived_at));
+    assert_eq!(
+        store.archived_indexer_addresses().await.unwrap(),
+        HashSet::from([address])
+    );
+
+    let listed = store.indexers(IndexersQuery::default()).await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_ne!(listed[0].address, address);
+    let listed = store
+        .indexers(IndexersQuery {
+            include_archived: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 2);
+    let listed = store
+        .indexers(IndexersQuery {
+            address: Some(address),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 1);
+
+    let unarchived = store
+        .set_indexer_archived(&address, false)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(unarchived.archived_at, None);
+    assert!(store.archived_indexer_addresses().await.unwrap().is_empty());
+    assert!(store
+        .set_indexer_archived(&[1; 20].into(), true)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn indexer_tags() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["foo", "bar"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+    let (foo, bar) = (indexers[0].address(), indexers[1].address());
+    let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+
+    store
+        .write_configured_indexer_tags(&[
+            (foo, tags(&["eu-west", "upgrade-canary"])),
+            // Unknown indexers are skipped.
+            ([1; 20].into(), tags(&["eu-west"])),
+        ])
+        .await
+        .unwrap();
+    let foo_id = store
+        .set_indexer_tags(&foo, &tags(&["untrusted", "eu-west"]))
+        .await
+        .unwrap()
+        .unwrap()
+        .id;
+    store
+        .set_indexer_tags(&bar, &tags(&["untrusted"]))
+        .await
+        .unwrap();
+    assert_eq!(
+        store.indexer_tags(foo_id).await.unwrap(),
+        tags(&["eu-west", "untrusted", "upgrade-canary"])
+    );
+
+    let tagged = |tags: Vec<String>| {
+        let store = &store;
+        async move {
+            store
+                .indexers(IndexersQuery {
+                    tags,
+                    ..Default::default()
+                })
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|indexer| indexer.address)
+                .collect::<HashSet<_>>()
+        }
+    };
+    assert_eq!(tagged(tags(&["eu-west"])).await, HashSet::from([foo]));
+    assert_eq!(
+        tagged(tags(&["untrusted"])).await,
+        HashSet::from([foo, bar])
+    );
+    assert_eq!(tagged(vec![]).await, HashSet::from([foo, bar]));
+
+    // Configuration and API tags replace only themselves.
+    store.write_configured_indexer_tags(&[]).await.unwrap();
+    store.set_indexer_tags(&bar, &[]).await.unwrap();
+    assert_eq!(
+        store.indexer_tags_by_address().await.unwrap(),
+        HashMap::from([(foo, BTreeSet::from_iter(tags(&["eu-west", "untrusted"])))])
+    );
+    assert!(store
+        .set_indexer_tags(&[1; 20].into(), &tags(&["eu-west"]))
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn pois_by_indexer_tags() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["foo", "bar"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+    let deployment = gen_deployments()[0].clone();
+    let pois = indexers
+        .iter()
+        .map(|indexer| ProofOfIndexing {
+            indexer: indexer.clone(),
+            deployment: deployment.clone(),
+            block: gen_blocks()[1].clone(),
+            proof_of_indexing: [1; 32].into(),
+        })
+        .collect();
+    store.write_pois(pois, PoiLiveness::Live).await.unwrap();
+    let canary = store
+        .set_indexer_tags(&indexers[0].address(), &["upgrade-canary".to_string()])
+        .await
+        .unwrap()
+        .unwrap();
+
+    let canary_tags = ["upgrade-canary".to_string()];
+    let pois = store
+        .pois(
+            &[deployment.clone()],
+            &canary_tags,
+            None,
+            None,
+            PoisOrder::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(pois.len(), 1);
+    assert_eq!(pois[0].indexer_id, canary.id);
+    let live_pois = store
+        .live_pois(None, &canary_tags, None, None, None)
+        .await
+        .unwrap();
+    assert_eq!(live_pois.len(), 1);
+    let live_pois = store.live_pois(None, &[], None, None, None).await.unwrap();
+    assert_eq!(live_pois.len(), 2);
+}
+
+#[tokio::test]
+async fn recompute_live_pois() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+    let indexers: Vec<Arc<dyn IndexerClient>> = ["foo", "bar"]
+        .into_iter()
+        .map(|name| {
+            Arc::new(MockIndexer {
+                name: name.to_string(),
+                deployment_details: vec![],
+                fail_indexing_statuses: false,
+            }) as Arc<dyn IndexerClient>
+        })
+        .collect();
+    store.write_indexers(&indexers).await.unwrap();
+
+    let deployment = gen_deployments()[0].clone();
+    let block = gen_blocks()[3].clone();
+    let poi = |indexer: &Arc<dyn IndexerClient>, value: u8| ProofOfIndexing {
+        indexer: indexer.clone(),
+        deployment: deployment.clone(),
+        block: block.clone(),
+        proof_of_indexing: [value; 32].into(),
+    };
+    store
+        .write_pois(
+            vec![poi(&indexers[0], 1), poi(&indexers[1], 1)],
+            PoiLiveness::Live,
+        )
+        .await
+        .unwrap();
+    let report = store.recompute_live_pois(true).await.unwrap();
+    assert_eq!(
+        report,
+        LivePoiReport {
+            dry_run: true,
+            ..Default::default()
+        }
+    );
+
+    // A more recent PoI of the same block, e.g. from a manual import, should
+    // have replaced the live one.
+    store
+        .write_pois(vec![poi(&indexers[0], 2)], PoiLiveness::NotLive)
+        .await
+        .unwrap();
+    let report = store.recompute_live_pois(true).await.unwrap();
+    assert_eq!(report.updated, 1);
+    let report = store.recompute_live_pois(false).await.unwrap();
+    assert_eq!(
+        report,
+        LivePoiReport {
+            dry_run: false,
+            inserted: 0,
+            updated: 1,
+            deleted: 0,
+        }
+    );
+    let report = store.recompute_live_pois(true).await.unwrap();
+    assert_eq!(report.updated, 0);
+}
+
+#[tokio::test]
+async fn statement_timeout() {
+    let store = EmptyStoreForTesting::new_with_statement_timeout(Some(Duration::from_millis(100)))
+        .await
+        .unwrap();
+    let result = diesel::sql_query("SELECT pg_sleep(1)")
+        .execute(&mut store.conn().await.unwrap())
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn revert_migration() {
+    let store = EmptyStoreForTesting::new().await.unwrap();
+
+    let status = store.migration_status().await.unwrap();
+    assert!(status.iter().all(|migration| migration.run_on.is_some()));
+    let latest = status.last().unwrap().name.clone();
+
+    // Only the latest migration can be reverted.
+    assert!(store.revert_migration(&status[0].name).await.is_err());
+    store.revert_migration(&latest).await.unwrap();
+
+    let status = store.migration_status().await.unwrap();
+    assert_eq!(status.last().unwrap().name, latest);
+    assert!(status.last().unwrap().run_on.is_none());
+    assert!(status[..status.len() - 1]
+        .iter()
+        .all(|migration| migration.run_on.is_some()));
 }
 
 //#[tokio::test]